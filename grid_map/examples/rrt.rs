@@ -1,4 +1,9 @@
-use rand::distributions::{Distribution, Uniform};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    SeedableRng,
+};
+use std::cell::RefCell;
 
 fn main() {
     use grid_map::*;
@@ -17,13 +22,15 @@ fn main() {
     }
     let x_range = Uniform::new(map.min_point().x, map.max_point().x);
     let y_range = Uniform::new(map.min_point().y, map.max_point().y);
+    // seeded for the reproducible result
+    let rng = RefCell::new(StdRng::seed_from_u64(0));
     let result = rrt::dual_rrt_connect(
         &[0.5, -0.8],
         &[2.5, 0.5],
         |p: &[f64]| map.value(&map.to_grid(p[0], p[1]).unwrap()).is_none(),
         || {
-            let mut rng = rand::thread_rng();
-            vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
+            let mut rng = rng.borrow_mut();
+            vec![x_range.sample(&mut *rng), y_range.sample(&mut *rng)]
         },
         0.05,
         1000,
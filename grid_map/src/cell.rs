@@ -1,4 +1,6 @@
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum Cell<T>
 where
     T: Clone,
@@ -38,6 +40,10 @@ where
     pub fn is_obstacle(&self) -> bool {
         matches!(self, Self::Obstacle)
     }
+    /// Default predicate of the traversable cell (not Obstacle nor Unknown)
+    pub fn is_traversable(&self) -> bool {
+        !matches!(self, Self::Obstacle | Self::Unknown)
+    }
 
     pub fn from_value(value: T) -> Self {
         Self::Value(value)
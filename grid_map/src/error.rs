@@ -1,4 +1,4 @@
-use crate::Grid;
+use crate::{Grid, LayerId};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,14 +6,23 @@ use thiserror::Error;
 pub enum Error {
     #[error("IO: {0}")]
     IoError(#[from] std::io::Error),
+    #[cfg(feature = "image")]
     #[error("image crate: {0}")]
     ImageError(#[from] image::ImageError),
     #[error("yaml parse error: {0}")]
     YamlParseError(#[from] serde_yaml::Error),
+    #[error("bincode: {0}")]
+    BincodeError(#[from] bincode::Error),
     #[error("out of range grid: {0:?}")]
     OutOfRangeGrid(Grid),
     #[error("out of range {0}, {1}")]
     OutOfRangePosition(f64, f64),
+    #[error("layer \"{layer}\" is incompatible with \"{reference}\": {reason}")]
+    IncompatibleLayer {
+        layer: LayerId,
+        reference: LayerId,
+        reason: String,
+    },
     #[error("{0}")]
     Other(String),
 }
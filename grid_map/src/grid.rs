@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 /// Grid coordinates for the map
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Grid {
     pub x: usize,
     pub y: usize,
@@ -41,12 +43,12 @@ mod grid_tests {
     #[test]
     fn pos1() {
         let result = Grid::new(1, 1) < Grid::new(2, 3);
-        assert_eq!(result, true);
+        assert!(result);
         let result = Grid::new(1, 3) < Grid::new(2, 2);
-        assert_eq!(result, false);
+        assert!(!result);
         let result = Grid::new(1, 3) < Grid::new(0, 2);
-        assert_eq!(result, false);
+        assert!(!result);
         let result = Grid::new(2, 3) > Grid::new(1, 2);
-        assert_eq!(result, true);
+        assert!(result);
     }
 }
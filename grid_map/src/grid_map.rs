@@ -1,9 +1,15 @@
 use crate::cell::Cell;
+use crate::error::{Error, Result};
 use crate::grid::Grid;
 use crate::position::Position;
+use crate::storage::{ChunkedStorage, Storage};
+
+use nalgebra::{Isometry2, Point2};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{fs::File, io::BufReader, io::BufWriter, marker::PhantomData, path::Path};
 
 /// Size of the map
-#[derive(Copy, Clone, Debug, Default)]
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Size {
     pub width: usize,
     pub height: usize,
@@ -21,7 +27,7 @@ impl Size {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 struct GridPositionConverter {
     resolution: f64,
     min_point: Position,
@@ -42,6 +48,19 @@ impl GridPositionConverter {
             size,
         }
     }
+    /// Create the converter with the exact size, not computed from the max point
+    fn with_size(min_point: Position, size: Size, resolution: f64) -> Self {
+        let max_point = Position::new(
+            min_point.x + size.width as f64 * resolution,
+            min_point.y + size.height as f64 * resolution,
+        );
+        Self {
+            resolution,
+            min_point,
+            max_point,
+            size,
+        }
+    }
     fn resolution(&self) -> f64 {
         self.resolution
     }
@@ -78,30 +97,30 @@ impl GridPositionConverter {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct GridMap<T>
+/// Map of the cells on the grids
+///
+/// The cells are kept in the storage `S`, which is the dense `Vec` by default.
+/// Use [`ChunkedGridMap`] for very large maps which allocate the cells lazily.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GridMap<T, S = Vec<Cell<T>>>
 where
     T: Clone,
+    S: Storage<T>,
 {
     grid_converter: GridPositionConverter,
-    cells: Vec<Cell<T>>,
+    cells: S,
+    /// Pose of the map frame in the world frame. `None` is the identity.
+    #[serde(default)]
+    origin: Option<Isometry2<f64>>,
+    #[serde(skip)]
+    phantom: PhantomData<T>,
 }
 
-impl<T> GridMap<T>
+impl<T, S> GridMap<T, S>
 where
     T: Clone,
+    S: Storage<T>,
 {
-    /// Create GridMap
-    pub fn new(min_point: Position, max_point: Position, resolution: f64) -> Self {
-        assert!(max_point > min_point);
-        let grid_converter = GridPositionConverter::new(min_point, max_point, resolution);
-        let cells = vec![Cell::Uninitialized; grid_converter.size().len()];
-        GridMap {
-            grid_converter,
-            cells,
-        }
-    }
-
     /// Convert the grid into the index of the cells
     fn to_index(&self, grid: &Grid) -> Option<usize> {
         self.grid_converter.to_index(grid)
@@ -114,14 +133,60 @@ where
         self.grid_converter.to_grid(&Position::new(x, y))
     }
 
-    /// Get cell by grid if it is inside of the map
-    pub fn cell(&self, grid: &Grid) -> Option<&Cell<T>> {
-        self.to_index(grid).map(|index| &self.cells[index])
+    /// Pose of the map frame in the world frame, e.g. a rotated origin of a SLAM map
+    ///
+    /// All positions of the map (like [`GridMap::min_point`] and [`GridMap::to_grid`])
+    /// are in the map frame. Use [`GridMap::world_to_map`] or
+    /// [`GridMap::cell_by_position`] for the positions in the world frame.
+    pub fn origin(&self) -> Option<&Isometry2<f64>> {
+        self.origin.as_ref()
     }
 
-    /// Access to the all cells
-    pub fn cells(&self) -> &Vec<Cell<T>> {
-        &self.cells
+    pub fn set_origin(&mut self, origin: Option<Isometry2<f64>>) {
+        self.origin = origin;
+    }
+
+    pub fn with_origin(mut self, origin: Isometry2<f64>) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    /// Convert the position in the world frame into the map frame
+    pub fn world_to_map(&self, position: &Position) -> Position {
+        match &self.origin {
+            Some(origin) => {
+                let p = origin.inverse_transform_point(&Point2::new(position.x, position.y));
+                Position::new(p.x, p.y)
+            }
+            None => *position,
+        }
+    }
+
+    /// Convert the position in the map frame into the world frame
+    pub fn map_to_world(&self, position: &Position) -> Position {
+        match &self.origin {
+            Some(origin) => {
+                let p = origin.transform_point(&Point2::new(position.x, position.y));
+                Position::new(p.x, p.y)
+            }
+            None => *position,
+        }
+    }
+
+    /// Grid of the position in the world frame
+    pub fn world_to_grid(&self, position: &Position) -> Option<Grid> {
+        self.grid_converter.to_grid(&self.world_to_map(position))
+    }
+
+    /// Get cell by the position in the world frame if it is inside of the map
+    pub fn cell_by_position(&self, position: &Position) -> Option<&Cell<T>> {
+        self.world_to_grid(position)
+            .and_then(|grid| self.cell(&grid))
+    }
+
+    /// Get cell by grid if it is inside of the map
+    pub fn cell(&self, grid: &Grid) -> Option<&Cell<T>> {
+        self.to_index(grid).map(|index| self.cells.get(index))
     }
 
     /// Return if it is empty
@@ -134,11 +199,6 @@ where
         self.cells.len()
     }
 
-    /// Get mutable all cells
-    pub fn cells_mut(&mut self) -> &mut Vec<Cell<T>> {
-        &mut self.cells
-    }
-
     /// Get x length of the map
     pub fn width(&self) -> usize {
         self.grid_converter.size().width
@@ -167,7 +227,7 @@ where
     /// Get mutable cell
     pub fn cell_mut(&mut self, grid: &Grid) -> Option<&mut Cell<T>> {
         match self.to_index(grid) {
-            Some(index) => Some(&mut self.cells[index]),
+            Some(index) => Some(self.cells.get_mut(index)),
             None => None,
         }
     }
@@ -194,6 +254,128 @@ where
         Some(())
     }
 
+    /// Position of the center of the grid
+    pub fn cell_center(&self, grid: &Grid) -> Position {
+        let resolution = self.resolution();
+        Position::new(
+            self.min_point().x + (grid.x as f64 + 0.5) * resolution,
+            self.min_point().y + (grid.y as f64 + 0.5) * resolution,
+        )
+    }
+
+    /// Iterate over all cells with their grids and the positions of the centers
+    pub fn enumerate_cells(&self) -> impl Iterator<Item = (Grid, Position, &Cell<T>)> + '_ {
+        let width = self.width();
+        (0..self.len()).map(move |index| {
+            let grid = Grid::new(index % width, index / width);
+            (grid, self.cell_center(&grid), self.cells.get(index))
+        })
+    }
+
+    /// Set all cells to the cell
+    pub fn fill(&mut self, cell: Cell<T>) {
+        self.cells.fill(cell);
+    }
+}
+
+impl<T> GridMap<T>
+where
+    T: Clone,
+{
+    /// Create GridMap
+    pub fn new(min_point: Position, max_point: Position, resolution: f64) -> Self {
+        assert!(max_point > min_point);
+        let grid_converter = GridPositionConverter::new(min_point, max_point, resolution);
+        let cells = vec![Cell::Uninitialized; grid_converter.size().len()];
+        GridMap {
+            grid_converter,
+            cells,
+            origin: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Create the map with the exact size filled with [`Cell::Uninitialized`]
+    ///
+    /// Unlike [`GridMap::new`], the size is not computed from the max point, so
+    /// no column or row is lost by the floating point error.
+    pub fn with_size(min_point: Position, size: Size, resolution: f64) -> Self {
+        GridMap {
+            grid_converter: GridPositionConverter::with_size(min_point, size, resolution),
+            cells: vec![Cell::Uninitialized; size.len()],
+            origin: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Access to the all cells
+    pub fn cells(&self) -> &Vec<Cell<T>> {
+        &self.cells
+    }
+
+    /// Get mutable all cells
+    pub fn cells_mut(&mut self) -> &mut Vec<Cell<T>> {
+        &mut self.cells
+    }
+
+    /// Iterate over all cells in the row-major order
+    pub fn iter(&self) -> std::slice::Iter<'_, Cell<T>> {
+        self.cells.iter()
+    }
+
+    /// Iterate over all mutable cells in the row-major order
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Cell<T>> {
+        self.cells.iter_mut()
+    }
+
+    /// Create the map of the same shape by converting the values
+    ///
+    /// Obstacle, Unknown and Uninitialized cells are kept as they are.
+    pub fn map_values<U, F>(&self, mut f: F) -> GridMap<U>
+    where
+        U: Clone,
+        F: FnMut(&T) -> U,
+    {
+        let cells = self
+            .cells
+            .iter()
+            .map(|cell| match cell {
+                Cell::Value(v) => Cell::Value(f(v)),
+                Cell::Obstacle => Cell::Obstacle,
+                Cell::Unknown => Cell::Unknown,
+                Cell::Uninitialized => Cell::Uninitialized,
+            })
+            .collect();
+        GridMap {
+            grid_converter: self.grid_converter.clone(),
+            cells,
+            origin: self.origin,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Iterate over the cells whose centers are within the radius from the position
+    pub fn cells_in_radius(
+        &self,
+        center: &Position,
+        radius: f64,
+    ) -> impl Iterator<Item = (Grid, &Cell<T>)> + '_ {
+        let resolution = self.resolution();
+        let range = |v: f64, min: f64, len: usize| {
+            let index = |v: f64| ((v - min) / resolution).floor().clamp(0.0, len as f64) as usize;
+            index(v - radius)..index(v + radius + resolution).min(len)
+        };
+        let xs = range(center.x, self.min_point().x, self.width());
+        let ys = range(center.y, self.min_point().y, self.height());
+        let center = *center;
+        ys.flat_map(move |y| xs.clone().map(move |x| Grid::new(x, y)))
+            .filter(move |grid| {
+                let p = self.cell_center(grid);
+                (p.x - center.x).powi(2) + (p.y - center.y).powi(2) <= radius * radius
+            })
+            .map(|grid| (grid, &self.cells[grid.y * self.width() + grid.x]))
+    }
+
     /// Copy the map, but un-initialize the Value cells with Uninitialized.
     pub fn copy_without_value(&self) -> Self {
         let cells: Vec<_> = self
@@ -211,6 +393,8 @@ where
         Self {
             grid_converter: self.grid_converter.clone(),
             cells,
+            origin: self.origin,
+            phantom: PhantomData,
         }
     }
 
@@ -238,6 +422,8 @@ where
         let mut new_map = GridMap {
             grid_converter: new_grid_converter,
             cells: new_cells,
+            origin: self.origin,
+            phantom: PhantomData,
         };
         let delta_x = ((self.min_point().x - new_map.min_point().x) / self.resolution()) as usize;
         let delta_y = ((self.min_point().y - new_map.min_point().y) / self.resolution()) as usize;
@@ -256,6 +442,210 @@ where
         }
         *self = new_map;
     }
+
+    /// Move the map keeping its size so that the center becomes close to the given position
+    ///
+    /// This is useful for a rolling-window local map around the robot. The map is
+    /// shifted by whole cells, so the cells keep their positions and the newly
+    /// exposed cells become [`Cell::Uninitialized`]. Returns the shift in cells.
+    pub fn recenter(&mut self, center: Position) -> (isize, isize) {
+        let resolution = self.resolution();
+        let current = Position::new(
+            (self.min_point().x + self.max_point().x) * 0.5,
+            (self.min_point().y + self.max_point().y) * 0.5,
+        );
+        let dx = ((center.x - current.x) / resolution).round() as isize;
+        let dy = ((center.y - current.y) / resolution).round() as isize;
+        if dx == 0 && dy == 0 {
+            return (0, 0);
+        }
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let mut cells = vec![Cell::Uninitialized; self.cells.len()];
+        for y in 0..height {
+            for x in 0..width {
+                let (old_x, old_y) = (x + dx, y + dy);
+                if (0..width).contains(&old_x) && (0..height).contains(&old_y) {
+                    cells[(y * width + x) as usize] =
+                        self.cells[(old_y * width + old_x) as usize].clone();
+                }
+            }
+        }
+        self.cells = cells;
+        let offset = Position::new(dx as f64 * resolution, dy as f64 * resolution);
+        let converter = &mut self.grid_converter;
+        converter.min_point = Position::new(
+            converter.min_point.x + offset.x,
+            converter.min_point.y + offset.y,
+        );
+        converter.max_point = Position::new(
+            converter.max_point.x + offset.x,
+            converter.max_point.y + offset.y,
+        );
+        (dx, dy)
+    }
+
+    /// Extract the rectangle region between the grids (inclusive) as a new map
+    ///
+    /// The world coordinates of the cells are preserved. Returns `None` if the
+    /// region is empty or out of the map.
+    pub fn sub_map(&self, min: &Grid, max: &Grid) -> Option<Self> {
+        if min.x > max.x || min.y > max.y || max.x >= self.width() || max.y >= self.height() {
+            return None;
+        }
+        let resolution = self.resolution();
+        let min_point = Position::new(
+            self.min_point().x + min.x as f64 * resolution,
+            self.min_point().y + min.y as f64 * resolution,
+        );
+        let size = Size::new(max.x - min.x + 1, max.y - min.y + 1);
+        let mut cells = Vec::with_capacity(size.len());
+        for y in min.y..=max.y {
+            let start = y * self.width();
+            cells.extend_from_slice(&self.cells[start + min.x..=start + max.x]);
+        }
+        Some(Self {
+            grid_converter: GridPositionConverter::with_size(min_point, size, resolution),
+            cells,
+            origin: self.origin,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Crop the map to the cells which overlap with the region between the positions
+    ///
+    /// The region is clipped by the map. Returns `None` if it doesn't overlap with the map.
+    pub fn crop(&self, min: Position, max: Position) -> Option<Self> {
+        let resolution = self.resolution();
+        let to_index = |v: f64, min: f64, len: usize| {
+            (((v - min) / resolution).floor() as i64).clamp(-1, len as i64)
+        };
+        let (min_x, max_x) = (
+            to_index(min.x, self.min_point().x, self.width()).max(0),
+            to_index(max.x, self.min_point().x, self.width()).min(self.width() as i64 - 1),
+        );
+        let (min_y, max_y) = (
+            to_index(min.y, self.min_point().y, self.height()).max(0),
+            to_index(max.y, self.min_point().y, self.height()).min(self.height() as i64 - 1),
+        );
+        if min_x > max_x || min_y > max_y {
+            return None;
+        }
+        self.sub_map(
+            &Grid::new(min_x as usize, min_y as usize),
+            &Grid::new(max_x as usize, max_y as usize),
+        )
+    }
+
+    /// Grow the map by whole cells until it includes the position
+    ///
+    /// The world coordinates of the existing cells are preserved and the new cells
+    /// are [`Cell::Uninitialized`].
+    pub fn expand_to_include(&mut self, position: &Position) {
+        let resolution = self.resolution();
+        let cells_to = |v: f64, min: f64| ((v - min) / resolution).floor() as i64;
+        let x = cells_to(position.x, self.min_point().x);
+        let y = cells_to(position.y, self.min_point().y);
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let (left, bottom) = ((-x).max(0), (-y).max(0));
+        let (right, top) = ((x - width + 1).max(0), (y - height + 1).max(0));
+        if left == 0 && bottom == 0 && right == 0 && top == 0 {
+            return;
+        }
+        let min_point = Position::new(
+            self.min_point().x - left as f64 * resolution,
+            self.min_point().y - bottom as f64 * resolution,
+        );
+        let size = Size::new(
+            (width + left + right) as usize,
+            (height + bottom + top) as usize,
+        );
+        let mut cells = vec![Cell::Uninitialized; size.len()];
+        for row in 0..self.height() {
+            let start = (row + bottom as usize) * size.width + left as usize;
+            cells[start..start + self.width()]
+                .clone_from_slice(&self.cells[row * self.width()..(row + 1) * self.width()]);
+        }
+        *self = Self {
+            grid_converter: GridPositionConverter::with_size(min_point, size, resolution),
+            cells,
+            origin: self.origin,
+            phantom: PhantomData,
+        };
+    }
+}
+
+/// [`GridMap`] which allocates the cells lazily by chunks
+pub type ChunkedGridMap<T> = GridMap<T, ChunkedStorage<T>>;
+
+impl<T> GridMap<T, ChunkedStorage<T>>
+where
+    T: Clone,
+{
+    /// Create the map whose cells are allocated by `chunk_size` x `chunk_size` chunks
+    pub fn new_chunked(
+        min_point: Position,
+        max_point: Position,
+        resolution: f64,
+        chunk_size: usize,
+    ) -> Self {
+        assert!(max_point > min_point);
+        let grid_converter = GridPositionConverter::new(min_point, max_point, resolution);
+        let cells = ChunkedStorage::new(*grid_converter.size(), chunk_size);
+        GridMap {
+            grid_converter,
+            cells,
+            origin: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Number of the allocated chunks
+    pub fn num_allocated_chunks(&self) -> usize {
+        self.cells.num_allocated_chunks()
+    }
+
+    /// Convert into the dense map
+    pub fn to_dense(&self) -> GridMap<T> {
+        GridMap {
+            grid_converter: self.grid_converter.clone(),
+            cells: (0..self.len()).map(|i| self.cells.get(i).clone()).collect(),
+            origin: self.origin,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<T, S> GridMap<T, S>
+where
+    T: Clone + Serialize + DeserializeOwned,
+    S: Storage<T> + Serialize + DeserializeOwned,
+{
+    /// Save the map to the file in binary format
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    /// Load the map saved by [`GridMap::save_to_file`]
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let map: Self = bincode::deserialize_from(reader)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    /// Check that the cells match the size, e.g. after deserializing with serde
+    pub fn validate(&self) -> Result<()> {
+        if self.cells.len() != self.grid_converter.size().len() {
+            return Err(Error::Other(format!(
+                "the number of the cells ({}) doesn't match the size {:?}",
+                self.cells.len(),
+                self.grid_converter.size()
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -271,6 +661,151 @@ mod tests {
         assert!(&map.to_grid(0.0, 0.4).is_none());
     }
 
+    #[test]
+    fn test_recenter() {
+        let mut map = GridMap::new(Position::new(-0.5, -0.5), Position::new(0.55, 0.55), 0.1);
+        assert_eq!(map.width(), 10);
+        map.set_value(&map.to_grid(0.15, 0.05).unwrap(), 1u8)
+            .unwrap();
+        map.set_obstacle(&map.to_grid(-0.45, -0.45).unwrap())
+            .unwrap();
+
+        assert_eq!(map.recenter(Position::new(0.32, -0.18)), (3, -2));
+        assert_eq!(map.width(), 10);
+        assert!((map.min_point().x - -0.2).abs() < 1e-9);
+        assert!((map.min_point().y - -0.7).abs() < 1e-9);
+        // cells keep their positions
+        assert_eq!(map.value(&map.to_grid(0.15, 0.05).unwrap()), Some(1));
+        // shifted out
+        assert!(map.to_grid(-0.45, -0.45).is_none());
+        assert_eq!(map.cells().iter().filter(|c| c.is_obstacle()).count(), 0);
+        // newly exposed
+        assert_eq!(
+            map.cell(&map.to_grid(0.75, -0.65).unwrap()),
+            Some(&Cell::Uninitialized)
+        );
+    }
+
+    #[test]
+    fn test_sub_map_crop() {
+        let mut map = GridMap::new(Position::new(-0.5, -0.5), Position::new(0.55, 0.55), 0.1);
+        map.set_value(&map.to_grid(0.15, 0.25).unwrap(), 3u8)
+            .unwrap();
+
+        let sub = map.sub_map(&Grid::new(5, 6), &Grid::new(7, 9)).unwrap();
+        assert_eq!((sub.width(), sub.height()), (3, 4));
+        assert_eq!(sub.value(&sub.to_grid(0.15, 0.25).unwrap()), Some(3));
+        assert!(map.sub_map(&Grid::new(5, 6), &Grid::new(10, 9)).is_none());
+
+        let cropped = map
+            .crop(Position::new(0.12, -2.0), Position::new(3.0, 0.28))
+            .unwrap();
+        assert_eq!((cropped.width(), cropped.height()), (4, 8));
+        assert!((cropped.min_point().x - 0.1).abs() < 1e-9);
+        assert!((cropped.max_point().x - 0.5).abs() < 1e-9);
+        assert_eq!(
+            cropped.value(&cropped.to_grid(0.15, 0.25).unwrap()),
+            Some(3)
+        );
+        assert!(map
+            .crop(Position::new(1.0, 1.0), Position::new(2.0, 2.0))
+            .is_none());
+    }
+
+    #[test]
+    fn test_expand_to_include() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.35, 0.25), 0.1);
+        map.set_obstacle(&Grid::new(1, 1)).unwrap();
+        map.expand_to_include(&Position::new(-0.25, 0.55));
+        assert_eq!((map.width(), map.height()), (6, 6));
+        assert!((map.min_point().x - -0.3).abs() < 1e-9);
+        assert!(map
+            .cell(&map.to_grid(0.15, 0.15).unwrap())
+            .unwrap()
+            .is_obstacle());
+        assert!(map.to_grid(-0.25, 0.55).is_some());
+        assert_eq!(map.cells().iter().filter(|c| c.is_obstacle()).count(), 1);
+        map.expand_to_include(&Position::new(0.0, 0.0));
+        assert_eq!((map.width(), map.height()), (6, 6));
+    }
+
+    #[test]
+    fn test_iterators() {
+        let mut map = GridMap::<u8>::new(Position::new(-0.5, 0.0), Position::new(0.55, 0.35), 0.1);
+        map.fill(Cell::Value(3));
+        assert!(map.iter().all(|c| *c == Cell::Value(3)));
+        for cell in map.iter_mut().take(2) {
+            *cell = Cell::Obstacle;
+        }
+        map.set_value(&Grid::new(4, 2), 7).unwrap();
+        let cells = map.enumerate_cells().collect::<Vec<_>>();
+        assert_eq!(cells.len(), map.len());
+        assert_eq!(cells[1].0, Grid::new(1, 0));
+        assert_eq!(cells[1].2, &Cell::Obstacle);
+        let (grid, position, cell) = cells[2 * map.width() + 4];
+        assert_eq!(grid, Grid::new(4, 2));
+        assert!((position.x + 0.05).abs() < 1e-9 && (position.y - 0.25).abs() < 1e-9);
+        assert_eq!(map.to_grid(position.x, position.y), Some(grid));
+        assert_eq!(cell, &Cell::Value(7));
+
+        let doubled = map.map_values(|v| *v as f64 * 2.0);
+        assert_eq!(doubled.cell(&Grid::new(4, 2)), Some(&Cell::Value(14.0)));
+        assert_eq!(doubled.cell(&Grid::new(0, 0)), Some(&Cell::Obstacle));
+        assert_eq!(doubled.min_point(), map.min_point());
+
+        let center = map.cell_center(&Grid::new(5, 1));
+        let mut in_radius = map
+            .cells_in_radius(&center, 0.11)
+            .map(|(grid, _)| (grid.x, grid.y))
+            .collect::<Vec<_>>();
+        in_radius.sort();
+        assert_eq!(in_radius, [(4, 1), (5, 0), (5, 1), (5, 2), (6, 1)]);
+        assert_eq!(
+            map.cells_in_radius(&Position::new(5.0, 5.0), 1.0).count(),
+            0
+        );
+        assert_eq!(
+            map.cells_in_radius(&Position::new(0.0, 0.15), 10.0).count(),
+            map.len()
+        );
+    }
+
+    #[test]
+    fn test_save_load() {
+        let mut map = GridMap::new(Position::new(0.1, 0.2), Position::new(0.5, 0.8), 0.1);
+        map.set_value(&Grid::new(1, 2), 3u8).unwrap();
+        map.set_obstacle(&Grid::new(2, 3)).unwrap();
+        let path = std::env::temp_dir().join("grid_map_test_save_load.bin");
+        map.save_to_file(&path).unwrap();
+        let loaded = GridMap::<u8>::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        crate::testkit::assert_maps_equal(&loaded, &map);
+    }
+
+    #[test]
+    fn test_rotated_origin() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 0.55), 0.1)
+            .with_origin(Isometry2::new(
+                nalgebra::Vector2::new(1.0, 2.0),
+                std::f64::consts::FRAC_PI_2,
+            ));
+        map.set_obstacle(&Grid::new(8, 2)).unwrap();
+        // x of the map is y of the world
+        let world = Position::new(1.0 - 0.25, 2.0 + 0.85);
+        let local = map.world_to_map(&world);
+        assert!((local.x - 0.85).abs() < 1e-9 && (local.y - 0.25).abs() < 1e-9);
+        let back = map.map_to_world(&local);
+        assert!((back.x - world.x).abs() < 1e-9 && (back.y - world.y).abs() < 1e-9);
+        assert_eq!(map.world_to_grid(&world), Some(Grid::new(8, 2)));
+        assert_eq!(map.cell_by_position(&world), Some(&Cell::Obstacle));
+        assert_eq!(map.cell_by_position(&Position::new(1.5, 2.5)), None);
+        // kept by the derived maps
+        assert!(map.copy_without_value().origin().is_some());
+
+        map.set_origin(None);
+        assert_eq!(map.cell_by_position(&world), None);
+    }
+
     #[test]
     fn test_value() {
         let mut map = GridMap::new(Position::new(0.1, 0.2), Position::new(0.5, 0.8), 0.1);
@@ -0,0 +1,170 @@
+use crate::cell::Cell;
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::grid_map::{GridMap, Size};
+use crate::position::Position;
+
+use image::{GrayImage, Luma};
+use std::path::Path;
+
+/// Pixel thresholds to convert a grayscale image into the occupancy grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageThresholds {
+    /// Pixels darker than or equal to this become [`Cell::Obstacle`]
+    pub occupied: u8,
+    /// Pixels brighter than or equal to this become `Cell::Value(0)`
+    pub free: u8,
+}
+
+impl Default for ImageThresholds {
+    fn default() -> Self {
+        // Same as occupied_thresh: 0.65 / free_thresh: 0.196 of ROS map_server
+        Self {
+            occupied: 89,
+            free: 205,
+        }
+    }
+}
+
+impl ImageThresholds {
+    fn cell_from_pixel(&self, pixel: u8) -> Cell<u8> {
+        if pixel <= self.occupied {
+            Cell::Obstacle
+        } else if pixel >= self.free {
+            Cell::Value(0)
+        } else {
+            Cell::Unknown
+        }
+    }
+}
+
+impl<T> GridMap<T>
+where
+    T: Clone,
+{
+    /// Create the map from a grayscale image converting each pixel by `f`
+    ///
+    /// The first row of the image is the top (max y) of the map and `origin` is
+    /// the position of the lower-left corner of the image.
+    pub fn from_image_with<F>(
+        image: &GrayImage,
+        resolution: f64,
+        origin: Position,
+        mut f: F,
+    ) -> Result<Self>
+    where
+        F: FnMut(u8) -> Cell<T>,
+    {
+        if resolution <= 0.0 {
+            return Err(Error::Other(format!("invalid resolution {resolution}")));
+        }
+        let (w, h) = (image.width() as usize, image.height() as usize);
+        let mut map = GridMap::with_size(origin, Size::new(w, h), resolution);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let grid = Grid::new(x as usize, h - 1 - y as usize);
+            *map.cell_mut(&grid).ok_or(Error::OutOfRangeGrid(grid))? = f(pixel.0[0]);
+        }
+        Ok(map)
+    }
+
+    /// Convert the map into a grayscale image converting each cell by `f`
+    ///
+    /// The first row of the image is the top (max y) of the map.
+    pub fn to_image_with<F>(&self, mut f: F) -> GrayImage
+    where
+        F: FnMut(&Cell<T>) -> u8,
+    {
+        let (w, h) = (self.width() as u32, self.height() as u32);
+        GrayImage::from_fn(w, h, |x, y| {
+            let grid = Grid::new(x as usize, (h - 1 - y) as usize);
+            Luma([self.cell(&grid).map(&mut f).unwrap_or(0)])
+        })
+    }
+}
+
+impl GridMap<u8> {
+    /// Create the occupancy map from a grayscale image like a floor plan bitmap
+    ///
+    /// Dark pixels become obstacles, bright pixels become free (`Cell::Value(0)`) and
+    /// the others become [`Cell::Unknown`].
+    pub fn from_image(
+        image: &GrayImage,
+        resolution: f64,
+        origin: Position,
+        thresholds: &ImageThresholds,
+    ) -> Result<Self> {
+        Self::from_image_with(image, resolution, origin, |p| thresholds.cell_from_pixel(p))
+    }
+
+    /// Load the occupancy map from an image file (PNG, PGM, ...)
+    pub fn from_image_file<P: AsRef<Path>>(
+        path: P,
+        resolution: f64,
+        origin: Position,
+        thresholds: &ImageThresholds,
+    ) -> Result<Self> {
+        let image = image::io::Reader::open(path)?.decode()?.to_luma8();
+        Self::from_image(&image, resolution, origin, thresholds)
+    }
+
+    /// Convert the cost layer into a grayscale image for debugging
+    ///
+    /// Values are used as they are, obstacles are white (255) and
+    /// Unknown/Uninitialized cells are black (0).
+    pub fn to_image(&self) -> GrayImage {
+        self.to_image_with(|cell| match cell {
+            Cell::Value(v) => *v,
+            Cell::Obstacle => u8::MAX,
+            Cell::Unknown | Cell::Uninitialized => 0,
+        })
+    }
+
+    /// Save [`GridMap::to_image`] to the file. The format is decided by the extension.
+    pub fn save_image<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.to_image().save(path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_image() {
+        let image = GrayImage::from_raw(3, 2, vec![0, 128, 255, 255, 255, 10]).unwrap();
+        let map = GridMap::from_image(
+            &image,
+            0.5,
+            Position::new(-1.0, 2.0),
+            &ImageThresholds::default(),
+        )
+        .unwrap();
+        assert_eq!(map.width(), 3);
+        assert_eq!(map.height(), 2);
+        // first row of the image is the top of the map
+        assert_eq!(map.cell(&Grid::new(0, 1)), Some(&Cell::Obstacle));
+        assert_eq!(map.cell(&Grid::new(1, 1)), Some(&Cell::Unknown));
+        assert_eq!(map.cell(&Grid::new(2, 1)), Some(&Cell::Value(0)));
+        assert_eq!(map.cell(&Grid::new(2, 0)), Some(&Cell::Obstacle));
+        assert_eq!(map.to_grid(-0.9, 2.9), Some(Grid::new(0, 1)));
+    }
+
+    #[test]
+    fn test_to_image() {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(0.305, 0.205), 0.1);
+        map.set_value(&Grid::new(0, 0), 42).unwrap();
+        map.set_obstacle(&Grid::new(2, 1)).unwrap();
+        let image = map.to_image();
+        assert_eq!(image.dimensions(), (3, 2));
+        assert_eq!(image.get_pixel(0, 1).0, [42]);
+        assert_eq!(image.get_pixel(2, 0).0, [255]);
+        assert_eq!(image.get_pixel(1, 1).0, [0]);
+
+        let path = std::env::temp_dir().join("grid_map_test_to_image.png");
+        map.save_image(&path).unwrap();
+        let loaded = image::open(&path).unwrap().to_luma8();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, image);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+use crate::position::Position;
+use crate::storage::Storage;
+
+/// Values of the four cell centers around a position and the fraction of the
+/// position between them
+struct Bilinear {
+    /// (x0, y0), (x1, y0), (x0, y1), (x1, y1)
+    values: [f64; 4],
+    fx: f64,
+    fy: f64,
+}
+
+impl<T, S> GridMap<T, S>
+where
+    T: Clone + Into<f64>,
+    S: Storage<T>,
+{
+    fn bilinear(&self, position: &Position) -> Option<Bilinear> {
+        self.to_grid(position.x, position.y)?;
+        let resolution = self.resolution();
+        // in the coordinates of the cell centers
+        let u = (position.x - self.min_point().x) / resolution - 0.5;
+        let v = (position.y - self.min_point().y) / resolution - 0.5;
+        let axis = |u: f64, len: usize| {
+            let i0 = (u.floor().max(0.0) as usize).min(len - 1);
+            let i1 = (i0 + 1).min(len - 1);
+            let f = if i0 == i1 {
+                0.0
+            } else {
+                (u - i0 as f64).clamp(0.0, 1.0)
+            };
+            (i0, i1, f)
+        };
+        let (x0, x1, fx) = axis(u, self.width());
+        let (y0, y1, fy) = axis(v, self.height());
+        let value = |x, y| Some(self.value(&Grid::new(x, y))?.into());
+        Some(Bilinear {
+            values: [
+                value(x0, y0)?,
+                value(x1, y0)?,
+                value(x0, y1)?,
+                value(x1, y1)?,
+            ],
+            fx,
+            fy,
+        })
+    }
+
+    /// Bilinear interpolation of the values of the cell centers around the position
+    ///
+    /// Returns `None` if the position is out of the map or any of the four cells has
+    /// no value (e.g. next to an obstacle). Near the border of the map, the value of
+    /// the edge cells is extended.
+    pub fn value_at_interpolated(&self, position: &Position) -> Option<f64> {
+        let Bilinear {
+            values: [v00, v10, v01, v11],
+            fx,
+            fy,
+        } = self.bilinear(position)?;
+        let bottom = v00 + (v10 - v00) * fx;
+        let top = v01 + (v11 - v01) * fx;
+        Some(bottom + (top - bottom) * fy)
+    }
+
+    /// Gradient `[d/dx, d/dy]` (per meter) of [`GridMap::value_at_interpolated`]
+    ///
+    /// The gradient is zero along the axis where the map has only one cell.
+    pub fn gradient_at(&self, position: &Position) -> Option<[f64; 2]> {
+        let Bilinear {
+            values: [v00, v10, v01, v11],
+            fx,
+            fy,
+        } = self.bilinear(position)?;
+        let resolution = self.resolution();
+        let dx = ((v10 - v00) * (1.0 - fy) + (v11 - v01) * fy) / resolution;
+        let dy = ((v01 - v00) * (1.0 - fx) + (v11 - v10) * fx) / resolution;
+        Some([dx, dy])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Cell;
+
+    #[test]
+    fn test_interpolation() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.45, 0.35), 0.1);
+        assert_eq!((map.width(), map.height()), (4, 3));
+        // v = 10 x + 2 y (in grids)
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                map.set_value(&Grid::new(x, y), (10 * x + 2 * y) as u8)
+                    .unwrap();
+            }
+        }
+        let at = |x, y| map.value_at_interpolated(&Position::new(x, y)).unwrap();
+        assert!((at(0.05, 0.05) - 0.0).abs() < 1e-9);
+        assert!((at(0.1, 0.05) - 5.0).abs() < 1e-9);
+        assert!((at(0.2, 0.2) - 18.0).abs() < 1e-9);
+        // extended at the border
+        assert!((at(0.01, 0.01) - 0.0).abs() < 1e-9);
+        assert!((at(0.39, 0.05) - 30.0).abs() < 1e-9);
+
+        let [dx, dy] = map.gradient_at(&Position::new(0.2, 0.2)).unwrap();
+        assert!((dx - 100.0).abs() < 1e-9);
+        assert!((dy - 20.0).abs() < 1e-9);
+
+        assert_eq!(map.value_at_interpolated(&Position::new(1.0, 0.0)), None);
+        map.set_obstacle(&Grid::new(2, 2)).unwrap();
+        assert_eq!(map.gradient_at(&Position::new(0.2, 0.2)), None);
+        assert!(map.gradient_at(&Position::new(0.1, 0.1)).is_some());
+
+        let mut single =
+            GridMap::<f32>::new(Position::new(0.0, 0.0), Position::new(0.15, 0.15), 0.1);
+        single.fill(Cell::Value(1.5));
+        assert_eq!(
+            single.gradient_at(&Position::new(0.05, 0.05)),
+            Some([0.0, 0.0])
+        );
+        assert_eq!(
+            single.value_at_interpolated(&Position::new(0.05, 0.05)),
+            Some(1.5)
+        );
+    }
+}
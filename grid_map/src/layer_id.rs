@@ -0,0 +1,180 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    sync::{OnceLock, RwLock},
+};
+
+/// Interned name of a layer (or a cost term)
+///
+/// Comparing and hashing the id is as cheap as an integer. The name is registered in
+/// the global registry once and kept for the lifetime of the program, so ids should
+/// be created from a bounded set of names. The built-in layers are available as
+/// constants to avoid typos.
+///
+/// The id is serialized as its name, so it is compatible with the string keys.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LayerId(u32);
+
+const BUILTIN_NAMES: [&str; 7] = [
+    "path",
+    "goal",
+    "obstacle",
+    "local_goal",
+    "rotation",
+    "path_direction",
+    "goal_direction",
+];
+
+#[derive(Debug)]
+struct Registry {
+    names: Vec<&'static str>,
+    ids: HashMap<&'static str, LayerId>,
+}
+
+fn registry() -> &'static RwLock<Registry> {
+    static REGISTRY: OnceLock<RwLock<Registry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let names = BUILTIN_NAMES.to_vec();
+        let ids = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (*name, LayerId(i as u32)))
+            .collect();
+        RwLock::new(Registry { names, ids })
+    })
+}
+
+impl LayerId {
+    /// Distance to the global path
+    pub const PATH: Self = Self(0);
+    /// Distance to the goal
+    pub const GOAL: Self = Self(1);
+    /// Distance to the obstacles
+    pub const OBSTACLE: Self = Self(2);
+    /// Distance to the local goal
+    pub const LOCAL_GOAL: Self = Self(3);
+    /// Angle cost of the rotation
+    pub const ROTATION: Self = Self(4);
+    /// Angle cost of the direction to the path
+    pub const PATH_DIRECTION: Self = Self(5);
+    /// Angle cost of the direction to the goal
+    pub const GOAL_DIRECTION: Self = Self(6);
+
+    /// Get the id of the name, registering it if it is new
+    pub fn new(name: &str) -> Self {
+        if let Some(id) = Self::get(name) {
+            return id;
+        }
+        let mut registry = registry().write().unwrap();
+        // registered by another thread in the meantime
+        if let Some(id) = registry.ids.get(name) {
+            return *id;
+        }
+        let name: &'static str = Box::leak(name.to_owned().into_boxed_str());
+        let id = LayerId(registry.names.len() as u32);
+        registry.names.push(name);
+        registry.ids.insert(name, id);
+        id
+    }
+
+    /// Get the id of the name if it is already registered
+    pub fn get(name: &str) -> Option<Self> {
+        registry().read().unwrap().ids.get(name).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        registry().read().unwrap().names[self.0 as usize]
+    }
+}
+
+impl std::fmt::Debug for LayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LayerId").field(&self.name()).finish()
+    }
+}
+
+impl std::fmt::Display for LayerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(self.name())
+    }
+}
+
+/// Ordered by the name
+impl PartialOrd for LayerId {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LayerId {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self == other {
+            return std::cmp::Ordering::Equal;
+        }
+        self.name().cmp(other.name())
+    }
+}
+
+impl From<&str> for LayerId {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<&String> for LayerId {
+    fn from(name: &String) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for LayerId {
+    fn from(name: String) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl Serialize for LayerId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// Only the registered names are accepted, so that the untrusted inputs (like the
+/// parameters from the clients) can't grow the registry. Register the custom layers
+/// by [`LayerId::new`] before deserializing them.
+impl<'de> Deserialize<'de> for LayerId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::get(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown layer \"{name}\"")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layer_id() {
+        assert_eq!(LayerId::new("obstacle"), LayerId::OBSTACLE);
+        assert_eq!(LayerId::GOAL_DIRECTION.name(), "goal_direction");
+        for (i, name) in BUILTIN_NAMES.iter().enumerate() {
+            assert_eq!(LayerId::new(name), LayerId(i as u32));
+        }
+        assert_eq!(LayerId::get("test_layer_id_not_registered"), None);
+        let id = LayerId::from("test_layer_id");
+        assert_eq!(LayerId::get("test_layer_id"), Some(id));
+        assert_eq!(id.to_string(), "test_layer_id");
+        assert_eq!(format!("{id:?}"), "LayerId(\"test_layer_id\")");
+        assert!(LayerId::GOAL < LayerId::PATH);
+
+        let yaml = serde_yaml::to_string(&LayerId::LOCAL_GOAL).unwrap();
+        assert_eq!(yaml.trim(), "local_goal");
+        let id: LayerId = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(id, LayerId::LOCAL_GOAL);
+        assert!(serde_yaml::from_str::<LayerId>("test_layer_id_deserialized").is_err());
+        assert_eq!(LayerId::get("test_layer_id_deserialized"), None);
+        let id: LayerId = serde_yaml::from_str("test_layer_id").unwrap();
+        assert_eq!(id.name(), "test_layer_id");
+    }
+}
@@ -1,12 +1,33 @@
+use crate::cell::Cell;
+use crate::error::{Error, Result};
 use crate::grid_map::GridMap;
-use std::collections::HashMap;
+use crate::layer_id::LayerId;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, fs::File, io::BufReader, io::BufWriter, path::Path};
 
-#[derive(Clone, Debug, Default)]
+/// How [`LayeredGridMap::combined`] combines the weighted values of the layers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Combination {
+    WeightedSum,
+    Max,
+}
+
+/// Priority of the cells which have no value in the combined map
+fn no_value_rank<T: Clone>(cell: &Cell<T>) -> u8 {
+    match cell {
+        Cell::Value(_) => 0,
+        Cell::Uninitialized => 1,
+        Cell::Unknown => 2,
+        Cell::Obstacle => 3,
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct LayeredGridMap<T>
 where
     T: Clone,
 {
-    maps: HashMap<String, GridMap<T>>,
+    maps: HashMap<LayerId, GridMap<T>>,
 }
 
 impl<T> LayeredGridMap<T>
@@ -14,19 +35,256 @@ where
     T: Clone,
 {
     /// Initialize with all maps
-    pub fn new(maps: HashMap<String, GridMap<T>>) -> Self {
+    pub fn new(maps: HashMap<LayerId, GridMap<T>>) -> Self {
         Self { maps }
     }
     /// Add a map as a layer
-    pub fn add_layer(&mut self, name: String, map: GridMap<T>) {
-        self.maps.insert(name, map);
+    pub fn add_layer(&mut self, id: LayerId, map: GridMap<T>) {
+        self.maps.insert(id, map);
+    }
+    /// Add a map as a layer, returning the replaced layer if any
+    pub fn insert_layer(&mut self, id: LayerId, map: GridMap<T>) -> Option<GridMap<T>> {
+        self.maps.insert(id, map)
+    }
+    /// Remove the layer, returning it if it existed
+    pub fn remove_layer(&mut self, id: LayerId) -> Option<GridMap<T>> {
+        self.maps.remove(&id)
+    }
+    /// Whether the layer exists
+    pub fn contains(&self, id: LayerId) -> bool {
+        self.maps.contains_key(&id)
+    }
+    /// Accessor for a map with id
+    pub fn layer(&self, id: LayerId) -> Option<&GridMap<T>> {
+        self.maps.get(&id)
+    }
+    /// Mutator for a map with id
+    pub fn layer_mut(&mut self, id: LayerId) -> Option<&mut GridMap<T>> {
+        self.maps.get_mut(&id)
+    }
+    /// Ids of all layers
+    pub fn layer_ids(&self) -> impl Iterator<Item = LayerId> + '_ {
+        self.maps.keys().copied()
     }
-    /// Accessor for a map with name
-    pub fn layer(&self, name: &str) -> Option<&GridMap<T>> {
-        self.maps.get(name)
+    /// Names of all layers
+    pub fn layer_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.maps.keys().map(LayerId::name)
     }
-    /// Mutator for a map with name
-    pub fn layer_mut(&mut self, name: &str) -> Option<&mut GridMap<T>> {
-        self.maps.get_mut(name)
+    /// Number of the layers
+    pub fn len(&self) -> usize {
+        self.maps.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.maps.is_empty()
+    }
+
+    /// Check that all layers have the same size, resolution and origin, so a grid
+    /// means the same place in every layer
+    ///
+    /// The first layer in the order of the names is the reference of the error.
+    pub fn validate_compatible(&self) -> Result<()> {
+        self.validate_layers_compatible(self.maps.keys().copied().collect())
+    }
+
+    fn validate_layers_compatible(&self, mut ids: Vec<LayerId>) -> Result<()> {
+        ids.sort();
+        let Some((&reference, others)) = ids.split_first() else {
+            return Ok(());
+        };
+        let base = &self.maps[&reference];
+        for &layer in others {
+            let map = &self.maps[&layer];
+            let reason = if (map.width(), map.height()) != (base.width(), base.height()) {
+                format!(
+                    "size {}x{} differs from {}x{}",
+                    map.width(),
+                    map.height(),
+                    base.width(),
+                    base.height()
+                )
+            } else if (map.resolution() - base.resolution()).abs() > f64::EPSILON {
+                format!(
+                    "resolution {} differs from {}",
+                    map.resolution(),
+                    base.resolution()
+                )
+            } else if (map.min_point().x - base.min_point().x).abs() > f64::EPSILON
+                || (map.min_point().y - base.min_point().y).abs() > f64::EPSILON
+            {
+                format!(
+                    "min_point {:?} differs from {:?}",
+                    map.min_point(),
+                    base.min_point()
+                )
+            } else if map.origin() != base.origin() {
+                format!("origin {:?} differs from {:?}", map.origin(), base.origin())
+            } else {
+                continue;
+            };
+            return Err(Error::IncompatibleLayer {
+                layer,
+                reference,
+                reason,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T> LayeredGridMap<T>
+where
+    T: Clone + Into<f64>,
+{
+    /// Combine the weighted layers into a single master cost map
+    ///
+    /// Only the layers which have the weights are used, and the weights of the
+    /// missing layers (e.g. the angle costs) are ignored. A cell is an obstacle if
+    /// it is an obstacle in any of the used layers, and otherwise unknown or
+    /// uninitialized in the same way, so the cells which have a value are the cells
+    /// where all used layers have a value.
+    pub fn combined(
+        &self,
+        weights: &HashMap<LayerId, f64>,
+        combination: Combination,
+    ) -> Result<GridMap<f64>> {
+        let mut layers = weights
+            .iter()
+            .filter_map(|(id, weight)| Some((*id, self.maps.get(id)?, *weight)))
+            .collect::<Vec<_>>();
+        layers.sort_by_key(|(id, _, _)| *id);
+        let Some((_, base, _)) = layers.first() else {
+            return Err(Error::Other("no weighted layer to combine".to_owned()));
+        };
+        self.validate_layers_compatible(layers.iter().map(|(id, _, _)| *id).collect())?;
+
+        let mut combined = base.map_values(|_| 0.0);
+        for (i, cell) in combined.cells_mut().iter_mut().enumerate() {
+            let mut value = None;
+            let mut rank = 0;
+            for (_, map, weight) in &layers {
+                match &map.cells()[i] {
+                    Cell::Value(v) => {
+                        let v = weight * v.clone().into();
+                        value = Some(match (combination, value) {
+                            (_, None) => v,
+                            (Combination::WeightedSum, Some(sum)) => sum + v,
+                            (Combination::Max, Some(max)) => v.max(max),
+                        });
+                    }
+                    other => rank = rank.max(no_value_rank(other)),
+                }
+            }
+            *cell = match rank {
+                0 => Cell::Value(value.unwrap_or_default()),
+                1 => Cell::Uninitialized,
+                2 => Cell::Unknown,
+                _ => Cell::Obstacle,
+            };
+        }
+        Ok(combined)
+    }
+}
+
+impl<T> LayeredGridMap<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Save all layers to the file in binary format
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    /// Load the layers saved by [`LayeredGridMap::save_to_file`]
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let layered: Self = bincode::deserialize_from(reader)?;
+        for map in layered.maps.values() {
+            map.validate()?;
+        }
+        Ok(layered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, Position};
+
+    #[test]
+    fn test_layer_management() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.1);
+        let mut layered = LayeredGridMap::default();
+        layered.validate_compatible().unwrap();
+        assert!(layered.insert_layer(LayerId::PATH, map.clone()).is_none());
+        assert!(layered.insert_layer(LayerId::PATH, map.clone()).is_some());
+        layered.add_layer(LayerId::GOAL, map.clone());
+        assert!(layered.contains(LayerId::GOAL));
+        let mut names = layered.layer_names().collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, ["goal", "path"]);
+        layered.validate_compatible().unwrap();
+
+        let coarse = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.2);
+        layered.add_layer(LayerId::OBSTACLE, coarse);
+        match layered.validate_compatible() {
+            Err(Error::IncompatibleLayer {
+                layer, reference, ..
+            }) => assert_eq!((layer, reference), (LayerId::OBSTACLE, LayerId::GOAL)),
+            other => panic!("unexpected {other:?}"),
+        }
+        layered.remove_layer(LayerId::OBSTACLE).unwrap();
+        assert!(!layered.contains(LayerId::OBSTACLE));
+
+        let shifted = GridMap::<u8>::new(Position::new(0.1, 0.0), Position::new(1.15, 1.05), 0.1);
+        layered.add_layer(LayerId::OBSTACLE, shifted);
+        assert!(layered.validate_compatible().is_err());
+        layered.remove_layer(LayerId::OBSTACLE);
+        layered.add_layer(
+            LayerId::OBSTACLE,
+            map.with_origin(nalgebra::Isometry2::new(
+                nalgebra::Vector2::new(0.0, 0.0),
+                0.5,
+            )),
+        );
+        assert!(layered.validate_compatible().is_err());
+        assert_eq!(layered.len(), 3);
+    }
+
+    #[test]
+    fn test_combined() {
+        let mut a = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.45, 0.15), 0.1);
+        a.fill(Cell::Value(10));
+        let mut b = a.clone();
+        b.set_value(&Grid::new(1, 0), 40).unwrap();
+        b.set_obstacle(&Grid::new(2, 0)).unwrap();
+        *a.cell_mut(&Grid::new(0, 0)).unwrap() = Cell::Unknown;
+        let mut layered = LayeredGridMap::default();
+        layered.add_layer(LayerId::PATH, a);
+        layered.add_layer(LayerId::OBSTACLE, b);
+        let mut weights = HashMap::new();
+        weights.insert(LayerId::PATH, 1.0);
+        weights.insert(LayerId::OBSTACLE, 0.5);
+        weights.insert(LayerId::ROTATION, 2.0);
+
+        let sum = layered
+            .combined(&weights, Combination::WeightedSum)
+            .unwrap();
+        assert_eq!(sum.cell(&Grid::new(0, 0)), Some(&Cell::Unknown));
+        assert_eq!(sum.value(&Grid::new(1, 0)), Some(30.0));
+        assert_eq!(sum.cell(&Grid::new(2, 0)), Some(&Cell::Obstacle));
+        assert_eq!(sum.value(&Grid::new(3, 0)), Some(15.0));
+        let max = layered.combined(&weights, Combination::Max).unwrap();
+        assert_eq!(max.value(&Grid::new(1, 0)), Some(20.0));
+        assert_eq!(max.value(&Grid::new(3, 0)), Some(10.0));
+
+        // the layers which are not weighted are not checked
+        let coarse = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.5);
+        layered.add_layer(LayerId::GOAL, coarse);
+        assert!(layered.combined(&weights, Combination::Max).is_ok());
+        weights.insert(LayerId::GOAL, 1.0);
+        assert!(layered.combined(&weights, Combination::Max).is_err());
+        assert!(layered.combined(&HashMap::new(), Combination::Max).is_err());
     }
 }
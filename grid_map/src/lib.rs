@@ -2,12 +2,42 @@ mod cell;
 mod error;
 mod grid;
 mod grid_map;
+#[cfg(feature = "image")]
+mod image_conversion;
+mod interpolation;
+mod layer_id;
 mod layered_grid_map;
+mod merge;
+mod morphology;
+mod neighbors;
+mod occupancy;
 mod position;
+mod pyramid;
+mod raycast;
+#[cfg(feature = "image")]
+mod ros_map;
+mod storage;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+mod tiled_grid_map;
+#[cfg(feature = "image")]
 pub mod utils;
+mod world;
 pub use crate::cell::*;
 pub use crate::error::*;
 pub use crate::grid::*;
 pub use crate::grid_map::*;
+#[cfg(feature = "image")]
+pub use crate::image_conversion::*;
+pub use crate::layer_id::*;
 pub use crate::layered_grid_map::*;
+pub use crate::merge::*;
+pub use crate::occupancy::*;
 pub use crate::position::*;
+pub use crate::pyramid::*;
+pub use crate::raycast::*;
+#[cfg(feature = "image")]
+pub use crate::ros_map::*;
+pub use crate::storage::*;
+pub use crate::tiled_grid_map::*;
+pub use crate::world::*;
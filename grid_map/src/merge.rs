@@ -0,0 +1,163 @@
+use crate::cell::Cell;
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+
+/// How to combine the cells in [`GridMap::merge`]
+///
+/// [`Cell::Obstacle`] is treated as higher than any value, and uninitialized or
+/// unknown cells are overwritten by the known cells of the other map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the higher cost
+    #[default]
+    Max,
+    /// Keep the lower cost
+    Min,
+    /// Replace with the cells of the other map if initialized
+    Overwrite,
+    /// Average of the costs. Obstacles are kept.
+    Average,
+}
+
+/// The cell with the higher cost (Uninitialized < Unknown < Value < Obstacle)
+pub(crate) fn max_cell(a: Cell<u8>, b: Cell<u8>) -> Cell<u8> {
+    let rank = |c: &Cell<u8>| match c {
+        Cell::Uninitialized => 0,
+        Cell::Unknown => 1,
+        Cell::Value(v) => 2 + *v as u16,
+        Cell::Obstacle => u16::MAX,
+    };
+    if rank(&b) > rank(&a) {
+        b
+    } else {
+        a
+    }
+}
+
+fn merge_cell(dst: Cell<u8>, src: Cell<u8>, policy: MergePolicy) -> Cell<u8> {
+    match (dst, src) {
+        (_, Cell::Uninitialized) => dst,
+        _ if policy == MergePolicy::Overwrite => src,
+        (Cell::Uninitialized | Cell::Unknown, _) => src,
+        (_, Cell::Unknown) => dst,
+        (Cell::Obstacle, other) | (other, Cell::Obstacle) => {
+            if policy == MergePolicy::Min {
+                other
+            } else {
+                Cell::Obstacle
+            }
+        }
+        (Cell::Value(a), Cell::Value(b)) => Cell::Value(match policy {
+            MergePolicy::Max => a.max(b),
+            MergePolicy::Min => a.min(b),
+            _ => ((a as u16 + b as u16) / 2) as u8,
+        }),
+    }
+}
+
+impl GridMap<u8> {
+    /// Resample the other map into the grids of this map
+    ///
+    /// The finer cells are aggregated by the max cost so that small obstacles are not
+    /// lost, and the coarser cells are sampled at the center of each grid.
+    fn resample(&self, other: &GridMap<u8>) -> Vec<Cell<u8>> {
+        let mut cells = vec![Cell::Uninitialized; self.len()];
+        let mut sampled = vec![false; self.len()];
+        for y in 0..other.height() {
+            for x in 0..other.width() {
+                let grid = Grid::new(x, y);
+                let center = other.cell_center(&grid);
+                if let Some(target) = self.to_grid(center.x, center.y) {
+                    let index = target.y * self.width() + target.x;
+                    cells[index] = max_cell(cells[index], *other.cell(&grid).unwrap());
+                    sampled[index] = true;
+                }
+            }
+        }
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let index = y * self.width() + x;
+                if sampled[index] {
+                    continue;
+                }
+                let center = self.cell_center(&Grid::new(x, y));
+                if let Some(source) = other.to_grid(center.x, center.y) {
+                    cells[index] = *other.cell(&source).unwrap();
+                }
+            }
+        }
+        cells
+    }
+
+    /// Merge the other map into this map by the policy
+    ///
+    /// The other map can have a different origin and resolution. It is resampled
+    /// into the grids of this map, and the area out of this map is ignored.
+    pub fn merge(&mut self, other: &GridMap<u8>, policy: MergePolicy) {
+        let resampled = self.resample(other);
+        for (cell, src) in self.cells_mut().iter_mut().zip(resampled) {
+            *cell = merge_cell(*cell, src, policy);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::position::Position;
+
+    fn new_map(min: f64, max: f64, resolution: f64) -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(min, min), Position::new(max, max), resolution);
+        map.fill(Cell::Value(10));
+        map
+    }
+
+    #[test]
+    fn test_merge_cell() {
+        use Cell::*;
+        use MergePolicy::*;
+        assert_eq!(merge_cell(Value(10), Value(30), Max), Value(30));
+        assert_eq!(merge_cell(Value(10), Value(30), Min), Value(10));
+        assert_eq!(merge_cell(Value(10), Value(30), Average), Value(20));
+        assert_eq!(merge_cell(Value(10), Value(30), Overwrite), Value(30));
+        assert_eq!(merge_cell(Value(10), Obstacle, Max), Obstacle);
+        assert_eq!(merge_cell(Value(10), Obstacle, Average), Obstacle);
+        assert_eq!(merge_cell(Obstacle, Value(10), Min), Value(10));
+        assert_eq!(merge_cell(Obstacle, Value(10), Overwrite), Value(10));
+        assert_eq!(merge_cell(Unknown, Value(10), Min), Value(10));
+        assert_eq!(merge_cell(Value(10), Unknown, Max), Value(10));
+        assert_eq!(merge_cell(Value(10), Unknown, Overwrite), Unknown);
+        assert_eq!(merge_cell(Obstacle, Uninitialized, Overwrite), Obstacle);
+    }
+
+    #[test]
+    fn test_merge_different_resolution() {
+        let mut map = new_map(0.0, 1.0, 0.1);
+        // finer and shifted map, partially out of the map
+        let mut fine = new_map(0.52, 1.52, 0.05);
+        fine.fill(Cell::Value(5));
+        let obstacle = fine.to_grid(0.66, 0.66).unwrap();
+        fine.set_obstacle(&obstacle).unwrap();
+        map.merge(&fine, MergePolicy::Max);
+        let cell =
+            |map: &GridMap<u8>, x: f64, y: f64| *map.cell(&map.to_grid(x, y).unwrap()).unwrap();
+        assert_eq!(cell(&map, 0.65, 0.65), Cell::Obstacle);
+        assert_eq!(cell(&map, 0.75, 0.75), Cell::Value(10));
+        assert_eq!(map.cells().iter().filter(|c| c.is_obstacle()).count(), 1);
+
+        // coarser map covers the cells by sampling
+        let mut coarse = new_map(-0.1, 0.55, 0.3);
+        coarse.set_value(&Grid::new(0, 0), 40).unwrap();
+        map.merge(&coarse, MergePolicy::Average);
+        assert_eq!(cell(&map, 0.05, 0.05), Cell::Value(25));
+        assert_eq!(cell(&map, 0.15, 0.15), Cell::Value(25));
+        assert_eq!(cell(&map, 0.35, 0.35), Cell::Value(10));
+        assert_eq!(cell(&map, 0.65, 0.65), Cell::Obstacle);
+        assert_eq!(cell(&map, 0.95, 0.95), Cell::Value(10));
+
+        map.merge(&coarse, MergePolicy::Overwrite);
+        assert_eq!(cell(&map, 0.05, 0.05), Cell::Value(40));
+        map.merge(&new_map(0.0, 0.3, 0.1), MergePolicy::Min);
+        assert_eq!(cell(&map, 0.05, 0.05), Cell::Value(10));
+    }
+}
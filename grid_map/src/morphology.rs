@@ -0,0 +1,159 @@
+use crate::cell::Cell;
+use crate::grid_map::GridMap;
+
+/// Offsets of the cells in the disk of the radius (m)
+fn disk_offsets(radius: f64, resolution: f64) -> Vec<(isize, isize)> {
+    let r = (radius.max(0.0) / resolution).floor() as isize;
+    let mut offsets = vec![];
+    for dy in -r..=r {
+        for dx in -r..=r {
+            if ((dx * dx + dy * dy) as f64).sqrt() * resolution <= radius + f64::EPSILON {
+                offsets.push((dx, dy));
+            }
+        }
+    }
+    if offsets.is_empty() {
+        offsets.push((0, 0));
+    }
+    offsets
+}
+
+impl GridMap<u8> {
+    /// Obstacle mask after the dilation (`any` obstacle in the disk) or the erosion
+    /// (`all` cells of the disk are obstacles). Only the disk inside of the map is used.
+    fn morphology(&self, radius: f64, dilation: bool) -> Vec<bool> {
+        let offsets = disk_offsets(radius, self.resolution());
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        let cells = self.cells();
+        let mut mask = Vec::with_capacity(cells.len());
+        for y in 0..height {
+            for x in 0..width {
+                let mut element = offsets.iter().filter_map(|(dx, dy)| {
+                    let (nx, ny) = (x + dx, y + dy);
+                    ((0..width).contains(&nx) && (0..height).contains(&ny))
+                        .then(|| cells[(ny * width + nx) as usize].is_obstacle())
+                });
+                mask.push(if dilation {
+                    element.any(|o| o)
+                } else {
+                    element.all(|o| o)
+                });
+            }
+        }
+        mask
+    }
+
+    fn apply_mask(&mut self, mask: &[bool]) {
+        for (cell, obstacle) in self.cells_mut().iter_mut().zip(mask) {
+            match (cell.is_obstacle(), obstacle) {
+                (false, true) => *cell = Cell::Obstacle,
+                (true, false) => *cell = Cell::Value(0),
+                _ => {}
+            }
+        }
+    }
+
+    /// Grow the obstacles by the disk of the radius (m)
+    ///
+    /// Any cell within the radius from an obstacle becomes [`Cell::Obstacle`].
+    pub fn dilate(&self, radius: f64) -> Self {
+        let mut map = self.clone();
+        map.apply_mask(&self.morphology(radius, true));
+        map
+    }
+
+    /// Shrink the obstacles by the disk of the radius (m)
+    ///
+    /// Obstacles which have a non-obstacle cell within the radius become
+    /// `Cell::Value(0)`. The area out of the map is ignored, so obstacles touching
+    /// the border of the map are shrunk only from the inside.
+    pub fn erode(&self, radius: f64) -> Self {
+        let mut map = self.clone();
+        map.apply_mask(&self.morphology(radius, false));
+        map
+    }
+
+    /// Erode and then dilate, which removes obstacles smaller than the disk like
+    /// the noise of the sensors
+    ///
+    /// The costs of the removed obstacles are lost, but the other values are kept.
+    pub fn open(&self, radius: f64) -> Self {
+        let opened = self.erode(radius).dilate(radius);
+        let mut map = self.clone();
+        for (cell, opened) in map.cells_mut().iter_mut().zip(opened.cells()) {
+            if cell.is_obstacle() && !opened.is_obstacle() {
+                *cell = Cell::Value(0);
+            }
+        }
+        map
+    }
+
+    /// Dilate and then erode, which fills gaps and holes smaller than the disk
+    ///
+    /// The values of the cells which are not filled are kept.
+    pub fn close(&self, radius: f64) -> Self {
+        let closed = self.dilate(radius).erode(radius);
+        let mut map = self.clone();
+        for (cell, closed) in map.cells_mut().iter_mut().zip(closed.cells()) {
+            if closed.is_obstacle() {
+                *cell = Cell::Obstacle;
+            }
+        }
+        map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Grid, Position};
+
+    fn obstacles(map: &GridMap<u8>) -> Vec<Grid> {
+        map.enumerate_cells()
+            .filter(|(_, _, cell)| cell.is_obstacle())
+            .map(|(grid, _, _)| grid)
+            .collect()
+    }
+
+    #[test]
+    fn test_morphology() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.1);
+        map.fill(Cell::Value(3));
+        // 3x3 block and a single noise
+        for y in 2..5 {
+            for x in 2..5 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        map.set_obstacle(&Grid::new(8, 8)).unwrap();
+
+        let dilated = map.dilate(0.1);
+        assert_eq!(obstacles(&dilated).len(), 21 + 5);
+        assert_eq!(dilated.cell(&Grid::new(3, 1)), Some(&Cell::Obstacle));
+        assert_eq!(dilated.cell(&Grid::new(1, 1)), Some(&Cell::Value(3)));
+        assert_eq!(obstacles(&map.dilate(0.0)), obstacles(&map));
+
+        let eroded = map.erode(0.1);
+        assert_eq!(obstacles(&eroded), [Grid::new(3, 3)]);
+        assert_eq!(eroded.cell(&Grid::new(2, 2)), Some(&Cell::Value(0)));
+
+        // the noise is removed and the corners of the block are cut by the cross element
+        let opened = map.open(0.1);
+        assert_eq!(obstacles(&opened).len(), 5);
+        assert_eq!(opened.cell(&Grid::new(8, 8)), Some(&Cell::Value(0)));
+        assert_eq!(opened.cell(&Grid::new(0, 0)), Some(&Cell::Value(3)));
+        // the square element keeps the block
+        assert_eq!(obstacles(&map.open(0.15)).len(), 9);
+
+        // the gap of one cell is filled
+        let mut walls = map.clone();
+        walls.fill(Cell::Value(3));
+        for y in 0..walls.height() {
+            walls.set_obstacle(&Grid::new(4, y)).unwrap();
+            walls.set_obstacle(&Grid::new(6, y)).unwrap();
+        }
+        let closed = walls.close(0.1);
+        assert_eq!(closed.cell(&Grid::new(5, 5)), Some(&Cell::Obstacle));
+        assert_eq!(closed.cell(&Grid::new(3, 5)), Some(&Cell::Value(3)));
+    }
+}
@@ -0,0 +1,136 @@
+use crate::cell::Cell;
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+use crate::storage::Storage;
+
+use std::collections::VecDeque;
+
+/// Offsets of the 4-connected neighbors followed by the diagonal ones
+const NEIGHBOR_OFFSETS: [(isize, isize); 8] = [
+    (1, 0),
+    (-1, 0),
+    (0, 1),
+    (0, -1),
+    (1, 1),
+    (1, -1),
+    (-1, 1),
+    (-1, -1),
+];
+
+impl<T, S> GridMap<T, S>
+where
+    T: Clone,
+    S: Storage<T>,
+{
+    fn neighbors_with_offsets(
+        &self,
+        grid: &Grid,
+        offsets: &'static [(isize, isize)],
+    ) -> impl Iterator<Item = Grid> {
+        let (x, y) = (grid.x as isize, grid.y as isize);
+        let (width, height) = (self.width() as isize, self.height() as isize);
+        offsets.iter().filter_map(move |(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+            ((0..width).contains(&nx) && (0..height).contains(&ny))
+                .then(|| Grid::new(nx as usize, ny as usize))
+        })
+    }
+
+    /// Up/Down/Left/Right neighbors inside of the map
+    pub fn neighbors4(&self, grid: &Grid) -> impl Iterator<Item = Grid> {
+        self.neighbors_with_offsets(grid, &NEIGHBOR_OFFSETS[..4])
+    }
+
+    /// 8-connected neighbors inside of the map. The diagonal ones come last.
+    pub fn neighbors8(&self, grid: &Grid) -> impl Iterator<Item = Grid> {
+        self.neighbors_with_offsets(grid, &NEIGHBOR_OFFSETS)
+    }
+
+    /// 8-connected neighbors whose cells satisfy the predicate
+    ///
+    /// [`Cell::is_traversable`] can be used as the default predicate.
+    pub fn traversable_neighbors8<'a, F>(
+        &'a self,
+        grid: &Grid,
+        is_traversable: F,
+    ) -> impl Iterator<Item = Grid> + 'a
+    where
+        F: Fn(&Cell<T>) -> bool + 'a,
+    {
+        self.neighbors8(grid)
+            .filter(move |n| self.cell(n).is_some_and(&is_traversable))
+    }
+
+    /// Grids 4-connected to the start through the cells which satisfy the predicate
+    ///
+    /// The start is included if it satisfies the predicate. The grids are returned in
+    /// the breadth-first order.
+    pub fn flood_fill<F>(&self, start: &Grid, is_traversable: F) -> Vec<Grid>
+    where
+        F: Fn(&Cell<T>) -> bool,
+    {
+        let mut visited = vec![false; self.len()];
+        let mut region = vec![];
+        let mut queue = VecDeque::new();
+        if self.cell(start).is_some_and(&is_traversable) {
+            visited[start.y * self.width() + start.x] = true;
+            queue.push_back(*start);
+        }
+        while let Some(grid) = queue.pop_front() {
+            region.push(grid);
+            for neighbor in self.neighbors4(&grid) {
+                let index = neighbor.y * self.width() + neighbor.x;
+                if !visited[index] && self.cell(&neighbor).is_some_and(&is_traversable) {
+                    visited[index] = true;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        region
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_neighbors() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.35), 0.1);
+        assert_eq!(map.neighbors4(&Grid::new(0, 0)).count(), 2);
+        assert_eq!(map.neighbors8(&Grid::new(0, 0)).count(), 3);
+        assert_eq!(map.neighbors8(&Grid::new(4, 2)).count(), 3);
+        assert_eq!(map.neighbors8(&Grid::new(2, 1)).count(), 8);
+        assert_eq!(
+            map.neighbors4(&Grid::new(4, 1)).collect::<Vec<_>>(),
+            [Grid::new(3, 1), Grid::new(4, 2), Grid::new(4, 0)]
+        );
+
+        map.set_obstacle(&Grid::new(1, 1)).unwrap();
+        *map.cell_mut(&Grid::new(0, 1)).unwrap() = Cell::Unknown;
+        let neighbors = map
+            .traversable_neighbors8(&Grid::new(0, 0), Cell::is_traversable)
+            .collect::<Vec<_>>();
+        assert_eq!(neighbors, [Grid::new(1, 0)]);
+    }
+
+    #[test]
+    fn test_flood_fill() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.35), 0.1);
+        map.fill(Cell::Value(0));
+        // wall at x = 2
+        for y in 0..3 {
+            map.set_obstacle(&Grid::new(2, y)).unwrap();
+        }
+        let left = map.flood_fill(&Grid::new(0, 0), Cell::is_traversable);
+        assert_eq!(left.len(), 6);
+        assert_eq!(left[0], Grid::new(0, 0));
+        assert!(left.iter().all(|g| g.x < 2));
+        let right = map.flood_fill(&Grid::new(4, 2), |c| c.has_value());
+        assert_eq!(right.len(), 6);
+        assert!(map
+            .flood_fill(&Grid::new(2, 0), Cell::is_traversable)
+            .is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use crate::cell::Cell;
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+
+/// Log-odds of the probability
+pub fn log_odds(probability: f32) -> f32 {
+    (probability / (1.0 - probability)).ln()
+}
+
+/// Probability of the log-odds
+pub fn probability(log_odds: f32) -> f32 {
+    1.0 - 1.0 / (1.0 + log_odds.exp())
+}
+
+/// Sensor model of the log-odds occupancy update
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogOddsParams {
+    /// Probability of occupied when the cell is observed as occupied
+    pub hit_probability: f32,
+    /// Probability of occupied when the cell is observed as free
+    pub miss_probability: f32,
+    /// The probability is clamped to [clamp_min, clamp_max] so that the cell can change quickly
+    pub clamp_min: f32,
+    pub clamp_max: f32,
+}
+
+impl Default for LogOddsParams {
+    fn default() -> Self {
+        // Same as OctoMap
+        Self {
+            hit_probability: 0.7,
+            miss_probability: 0.4,
+            clamp_min: 0.1192,
+            clamp_max: 0.971,
+        }
+    }
+}
+
+/// Probability thresholds to convert the occupancy map into the cost layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OccupancyThresholds {
+    /// Cells with the probability higher than or equal to this become [`Cell::Obstacle`]
+    pub occupied: f32,
+    /// Cells with the probability lower than or equal to this become `Cell::Value(0)`
+    pub free: f32,
+}
+
+impl Default for OccupancyThresholds {
+    fn default() -> Self {
+        Self {
+            occupied: 0.65,
+            free: 0.196,
+        }
+    }
+}
+
+impl GridMap<f32> {
+    /// Update the log-odds of the grid with the observation
+    ///
+    /// Uninitialized and Unknown cells start from the probability 0.5.
+    /// Obstacle cells are not changed.
+    fn update_log_odds(
+        &mut self,
+        grid: &Grid,
+        probability: f32,
+        params: &LogOddsParams,
+    ) -> Option<()> {
+        let cell = self.cell_mut(grid)?;
+        let current = match cell {
+            Cell::Value(v) => *v,
+            Cell::Uninitialized | Cell::Unknown => 0.0,
+            Cell::Obstacle => return Some(()),
+        };
+        let updated = (current + log_odds(probability))
+            .clamp(log_odds(params.clamp_min), log_odds(params.clamp_max));
+        *cell = Cell::Value(updated);
+        Some(())
+    }
+
+    /// Update the log-odds occupancy of the grid observed as occupied
+    pub fn update_occupied(&mut self, grid: &Grid, params: &LogOddsParams) -> Option<()> {
+        self.update_log_odds(grid, params.hit_probability, params)
+    }
+
+    /// Update the log-odds occupancy of the grid observed as free
+    pub fn update_free(&mut self, grid: &Grid, params: &LogOddsParams) -> Option<()> {
+        self.update_log_odds(grid, params.miss_probability, params)
+    }
+
+    /// Occupancy probability of the grid of the log-odds map. `None` if not observed.
+    pub fn occupancy_probability(&self, grid: &Grid) -> Option<f32> {
+        match self.cell(grid)? {
+            Cell::Value(v) => Some(probability(*v)),
+            Cell::Obstacle => Some(1.0),
+            _ => None,
+        }
+    }
+
+    /// Convert the log-odds map into the cost layer for the planner
+    ///
+    /// Occupied cells become [`Cell::Obstacle`], free cells become `Cell::Value(0)`
+    /// and the others (including the cells never observed) become [`Cell::Unknown`].
+    pub fn to_cost_layer(&self, thresholds: &OccupancyThresholds) -> GridMap<u8> {
+        let mut layer = self.map_values(|_| 0);
+        for (cost, cell) in layer.cells_mut().iter_mut().zip(self.cells()) {
+            *cost = match cell {
+                Cell::Value(v) => {
+                    let p = probability(*v);
+                    if p >= thresholds.occupied {
+                        Cell::Obstacle
+                    } else if p <= thresholds.free {
+                        Cell::Value(0)
+                    } else {
+                        Cell::Unknown
+                    }
+                }
+                Cell::Obstacle => Cell::Obstacle,
+                Cell::Uninitialized | Cell::Unknown => Cell::Unknown,
+            };
+        }
+        layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_log_odds_update() {
+        let mut map = GridMap::<f32>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.55), 0.1);
+        let params = LogOddsParams::default();
+        let (a, b) = (Grid::new(1, 1), Grid::new(2, 2));
+        map.update_occupied(&a, &params).unwrap();
+        assert!((map.occupancy_probability(&a).unwrap() - 0.7).abs() < 1e-5);
+        // a single false measurement doesn't flip the cell
+        for _ in 0..10 {
+            map.update_occupied(&a, &params).unwrap();
+        }
+        map.update_free(&a, &params).unwrap();
+        assert!(map.occupancy_probability(&a).unwrap() > 0.9);
+        // clamped
+        for _ in 0..100 {
+            map.update_occupied(&a, &params).unwrap();
+        }
+        assert!(map.occupancy_probability(&a).unwrap() <= params.clamp_max + 1e-5);
+        for _ in 0..5 {
+            map.update_free(&b, &params).unwrap();
+        }
+        assert_eq!(map.occupancy_probability(&Grid::new(3, 3)), None);
+
+        let layer = map.to_cost_layer(&OccupancyThresholds::default());
+        assert_eq!(layer.cell(&a), Some(&Cell::Obstacle));
+        assert_eq!(layer.cell(&b), Some(&Cell::Value(0)));
+        assert_eq!(layer.cell(&Grid::new(3, 3)), Some(&Cell::Unknown));
+
+        let map = GridMap::<f32>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.1);
+        let sub_map = map.sub_map(&Grid::new(2, 1), &Grid::new(20, 19)).unwrap();
+        let layer = sub_map.to_cost_layer(&OccupancyThresholds::default());
+        assert_eq!((layer.width(), layer.height()), (19, 19));
+    }
+}
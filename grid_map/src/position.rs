@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 /// Real position for the map
-#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
@@ -30,12 +32,12 @@ mod pos_tests {
     #[test]
     fn pos1() {
         let result = Position::new(-1.0, 1.0) < Position::new(0.0, 2.0);
-        assert_eq!(result, true);
+        assert!(result);
         let result = Position::new(-1.0, 3.0) < Position::new(0.0, 2.0);
-        assert_eq!(result, false);
+        assert!(!result);
         let result = Position::new(-1.0, 3.0) < Position::new(-2.0, 2.0);
-        assert_eq!(result, false);
+        assert!(!result);
         let result = Position::new(-1.0, 3.0) > Position::new(-2.0, 2.0);
-        assert_eq!(result, true);
+        assert!(result);
     }
 }
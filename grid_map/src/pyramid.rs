@@ -0,0 +1,189 @@
+use crate::cell::Cell;
+use crate::grid::Grid;
+use crate::grid_map::{GridMap, Size};
+use crate::merge::max_cell;
+
+/// How to aggregate the cells in [`GridMap::downsample`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    /// The highest cost in the block. Obstacles are always kept.
+    #[default]
+    Max,
+    /// Mean of the values in the block. The block becomes an obstacle if it has any
+    /// obstacle, and unknown if it has no value.
+    Mean,
+}
+
+fn aggregate(cells: &[Cell<u8>], aggregation: Aggregation) -> Cell<u8> {
+    match aggregation {
+        Aggregation::Max => cells
+            .iter()
+            .fold(Cell::Uninitialized, |acc, c| max_cell(acc, *c)),
+        Aggregation::Mean => {
+            let (mut sum, mut count) = (0_u32, 0_u32);
+            let mut unknown = false;
+            for cell in cells {
+                match cell {
+                    Cell::Obstacle => return Cell::Obstacle,
+                    Cell::Value(v) => {
+                        sum += *v as u32;
+                        count += 1;
+                    }
+                    Cell::Unknown => unknown = true,
+                    Cell::Uninitialized => {}
+                }
+            }
+            if let Some(mean) = sum.checked_div(count) {
+                Cell::Value(mean as u8)
+            } else if unknown {
+                Cell::Unknown
+            } else {
+                Cell::Uninitialized
+            }
+        }
+    }
+}
+
+impl GridMap<u8> {
+    /// Create the coarser map whose cell covers `factor` x `factor` cells
+    ///
+    /// The min point and the origin are kept, and the map is extended to cover the
+    /// whole original map.
+    pub fn downsample(&self, factor: usize, aggregation: Aggregation) -> Self {
+        assert!(factor > 0, "factor must be positive");
+        let size = Size::new(
+            self.width().div_ceil(factor),
+            self.height().div_ceil(factor),
+        );
+        let mut map = Self::with_size(*self.min_point(), size, self.resolution() * factor as f64);
+        map.set_origin(self.origin().copied());
+        let mut block = Vec::with_capacity(factor * factor);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                block.clear();
+                for fine_y in y * factor..((y + 1) * factor).min(self.height()) {
+                    for fine_x in x * factor..((x + 1) * factor).min(self.width()) {
+                        block.push(*self.cell(&Grid::new(fine_x, fine_y)).unwrap());
+                    }
+                }
+                *map.cell_mut(&Grid::new(x, y)).unwrap() = aggregate(&block, aggregation);
+            }
+        }
+        map
+    }
+}
+
+/// Maps of multiple resolutions built by downsampling the finest map
+///
+/// Level 0 is the original map and the resolution of each level is twice as coarse
+/// as the previous level.
+#[derive(Debug, Clone)]
+pub struct MapPyramid {
+    levels: Vec<GridMap<u8>>,
+}
+
+impl MapPyramid {
+    /// Build the pyramid with `num_levels` levels (including the original map)
+    pub fn new(map: GridMap<u8>, num_levels: usize, aggregation: Aggregation) -> Self {
+        let mut levels = vec![map];
+        while levels.len() < num_levels {
+            let coarser = levels.last().unwrap().downsample(2, aggregation);
+            levels.push(coarser);
+        }
+        Self { levels }
+    }
+
+    pub fn level(&self, level: usize) -> Option<&GridMap<u8>> {
+        self.levels.get(level)
+    }
+
+    pub fn levels(&self) -> &[GridMap<u8>] {
+        &self.levels
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// The original map
+    pub fn finest(&self) -> &GridMap<u8> {
+        &self.levels[0]
+    }
+
+    pub fn coarsest(&self) -> &GridMap<u8> {
+        self.levels.last().unwrap()
+    }
+
+    /// The coarsest level whose resolution is finer than or equal to the resolution
+    pub fn level_for_resolution(&self, resolution: f64) -> &GridMap<u8> {
+        self.levels
+            .iter()
+            .rev()
+            .find(|map| map.resolution() <= resolution)
+            .unwrap_or_else(|| self.finest())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_downsample() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.35), 0.1);
+        assert_eq!((map.width(), map.height()), (5, 3));
+        for (i, cell) in map.cells_mut().iter_mut().enumerate() {
+            *cell = Cell::Value(i as u8);
+        }
+        map.set_obstacle(&Grid::new(4, 2)).unwrap();
+        *map.cell_mut(&Grid::new(0, 2)).unwrap() = Cell::Unknown;
+        *map.cell_mut(&Grid::new(1, 2)).unwrap() = Cell::Unknown;
+
+        let max = map.downsample(2, Aggregation::Max);
+        assert_eq!((max.width(), max.height()), (3, 2));
+        assert!((max.resolution() - 0.2).abs() < 1e-9);
+        assert_eq!(max.min_point(), map.min_point());
+        assert_eq!(max.cell(&Grid::new(0, 0)), Some(&Cell::Value(6)));
+        assert_eq!(max.cell(&Grid::new(2, 0)), Some(&Cell::Value(9)));
+        assert_eq!(max.cell(&Grid::new(0, 1)), Some(&Cell::Unknown));
+        assert_eq!(max.cell(&Grid::new(2, 1)), Some(&Cell::Obstacle));
+
+        let mean = map.downsample(2, Aggregation::Mean);
+        assert_eq!(mean.cell(&Grid::new(0, 0)), Some(&Cell::Value(3)));
+        assert_eq!(mean.cell(&Grid::new(1, 1)), Some(&Cell::Value(12)));
+        assert_eq!(mean.cell(&Grid::new(0, 1)), Some(&Cell::Unknown));
+        assert_eq!(mean.cell(&Grid::new(2, 1)), Some(&Cell::Obstacle));
+
+        let origin = nalgebra::Isometry2::new(nalgebra::Vector2::new(1.0, 2.0), 0.5);
+        let rotated = map
+            .clone()
+            .with_origin(origin)
+            .downsample(2, Aggregation::Max);
+        assert_eq!(rotated.origin(), Some(&origin));
+
+        // the same position is in the same region
+        let grid = max.to_grid(0.45, 0.25).unwrap();
+        assert_eq!(grid, Grid::new(2, 1));
+    }
+
+    #[test]
+    fn test_map_pyramid() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.1);
+        map.set_obstacle(&Grid::new(7, 3)).unwrap();
+        let pyramid = MapPyramid::new(map, 3, Aggregation::Max);
+        assert_eq!(pyramid.len(), 3);
+        assert_eq!(pyramid.coarsest().width(), 3);
+        assert_eq!(
+            pyramid.coarsest().cell(&Grid::new(1, 0)),
+            Some(&Cell::Obstacle)
+        );
+        assert!((pyramid.level_for_resolution(0.3).resolution() - 0.2).abs() < 1e-9);
+        assert!((pyramid.level_for_resolution(0.01).resolution() - 0.1).abs() < 1e-9);
+        assert!((pyramid.level_for_resolution(1.0).resolution() - 0.4).abs() < 1e-9);
+    }
+}
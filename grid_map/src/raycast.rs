@@ -0,0 +1,212 @@
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+use crate::position::Position;
+
+/// Iterator over the grids traversed by a line segment (Amanatides-Woo DDA)
+///
+/// Every grid which the segment passes through is returned in order, so it can be
+/// used for clearing free space along a beam. Grids out of the map are skipped.
+#[derive(Debug, Clone)]
+pub struct GridTraversal {
+    current: (i64, i64),
+    step: (i64, i64),
+    t_max: (f64, f64),
+    t_delta: (f64, f64),
+    length: f64,
+    /// Distance to the entry point of `current`
+    entry: f64,
+    /// Distance to the entry point of the last returned grid
+    distance: f64,
+    size: (i64, i64),
+    done: bool,
+}
+
+impl GridTraversal {
+    fn new<T: Clone>(map: &GridMap<T>, from: &Position, to: &Position) -> Self {
+        let resolution = map.resolution();
+        let min = map.min_point();
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        let length = (dx * dx + dy * dy).sqrt();
+        let local = |v: f64, min: f64| ((v - min) / resolution).floor() as i64;
+        let current = (local(from.x, min.x), local(from.y, min.y));
+        let axis = |d: f64, from: f64, min: f64, cell: i64| {
+            if length == 0.0 || d == 0.0 {
+                return (0, f64::INFINITY, f64::INFINITY);
+            }
+            let dir = d / length;
+            let (step, boundary) = if dir > 0.0 {
+                (1, min + (cell + 1) as f64 * resolution)
+            } else {
+                (-1, min + cell as f64 * resolution)
+            };
+            (step, (boundary - from) / dir, resolution / dir.abs())
+        };
+        let (step_x, t_max_x, t_delta_x) = axis(dx, from.x, min.x, current.0);
+        let (step_y, t_max_y, t_delta_y) = axis(dy, from.y, min.y, current.1);
+        Self {
+            current,
+            step: (step_x, step_y),
+            t_max: (t_max_x, t_max_y),
+            t_delta: (t_delta_x, t_delta_y),
+            length,
+            entry: 0.0,
+            distance: 0.0,
+            size: (map.width() as i64, map.height() as i64),
+            done: false,
+        }
+    }
+
+    /// Distance from the start of the segment to the entry point of the last returned grid
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    fn advance(&mut self) {
+        let t = if self.t_max.0 < self.t_max.1 {
+            self.current.0 += self.step.0;
+            let t = self.t_max.0;
+            self.t_max.0 += self.t_delta.0;
+            t
+        } else {
+            self.current.1 += self.step.1;
+            let t = self.t_max.1;
+            self.t_max.1 += self.t_delta.1;
+            t
+        };
+        if t > self.length {
+            self.done = true;
+        } else {
+            self.entry = t;
+        }
+    }
+
+    fn is_inside(&self) -> bool {
+        (0..self.size.0).contains(&self.current.0) && (0..self.size.1).contains(&self.current.1)
+    }
+}
+
+impl Iterator for GridTraversal {
+    type Item = Grid;
+
+    fn next(&mut self) -> Option<Grid> {
+        while !self.done {
+            let inside = self.is_inside();
+            let grid = Grid::new(self.current.0 as usize, self.current.1 as usize);
+            let entry = self.entry;
+            self.advance();
+            if inside {
+                self.distance = entry;
+                return Some(grid);
+            }
+        }
+        None
+    }
+}
+
+/// Result of [`GridMap::raycast`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaycastHit {
+    /// Reached the end of the ray without hitting an obstacle
+    Clear,
+    /// Hit the obstacle grid at the distance from the start
+    Obstacle { grid: Grid, distance: f64 },
+    /// The end of the ray is out of the map
+    OutOfMap,
+}
+
+impl RaycastHit {
+    pub fn is_clear(&self) -> bool {
+        matches!(self, RaycastHit::Clear)
+    }
+}
+
+impl<T> GridMap<T>
+where
+    T: Clone,
+{
+    /// Iterate over the grids traversed by the line segment from `from` to `to`
+    pub fn traverse(&self, from: &Position, to: &Position) -> GridTraversal {
+        GridTraversal::new(self, from, to)
+    }
+
+    /// Cast a ray from `from` to `to` and return the first obstacle on it
+    ///
+    /// Unknown and Uninitialized cells don't block the ray.
+    pub fn raycast(&self, from: &Position, to: &Position) -> RaycastHit {
+        let mut traversal = self.traverse(from, to);
+        while let Some(grid) = traversal.next() {
+            if self.cell(&grid).is_some_and(|c| c.is_obstacle()) {
+                return RaycastHit::Obstacle {
+                    grid,
+                    distance: traversal.distance(),
+                };
+            }
+        }
+        if self.to_grid(to.x, to.y).is_some() {
+            RaycastHit::Clear
+        } else {
+            RaycastHit::OutOfMap
+        }
+    }
+
+    /// Return true if there is no obstacle between the positions
+    pub fn has_line_of_sight(&self, from: &Position, to: &Position) -> bool {
+        self.raycast(from, to).is_clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_map() -> GridMap<u8> {
+        GridMap::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.1)
+    }
+
+    #[test]
+    fn test_traverse() {
+        let map = new_map();
+        let grids = map
+            .traverse(&Position::new(0.05, 0.05), &Position::new(0.35, 0.05))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            grids,
+            [(0, 0), (1, 0), (2, 0), (3, 0)].map(|(x, y)| Grid::new(x, y))
+        );
+
+        // every step moves to a 4-connected neighbor and ends at the goal grid
+        let (from, to) = (Position::new(0.93, 0.12), Position::new(0.07, 0.88));
+        let grids = map.traverse(&from, &to).collect::<Vec<_>>();
+        assert_eq!(grids.first(), map.to_grid(from.x, from.y).as_ref());
+        assert_eq!(grids.last(), map.to_grid(to.x, to.y).as_ref());
+        for (a, b) in grids.iter().zip(grids.iter().skip(1)) {
+            assert_eq!(a.x.abs_diff(b.x) + a.y.abs_diff(b.y), 1);
+        }
+
+        // out of map grids are skipped
+        let grids = map
+            .traverse(&Position::new(-0.25, 0.05), &Position::new(0.15, 0.05))
+            .collect::<Vec<_>>();
+        assert_eq!(grids, [Grid::new(0, 0), Grid::new(1, 0)]);
+    }
+
+    #[test]
+    fn test_raycast() {
+        let mut map = new_map();
+        map.set_obstacle(&Grid::new(5, 2)).unwrap();
+        let from = Position::new(0.05, 0.25);
+        match map.raycast(&from, &Position::new(0.95, 0.25)) {
+            RaycastHit::Obstacle { grid, distance } => {
+                assert_eq!(grid, Grid::new(5, 2));
+                assert!((distance - 0.45).abs() < 1e-9);
+            }
+            hit => panic!("{hit:?}"),
+        }
+        assert!(map.has_line_of_sight(&from, &Position::new(0.45, 0.25)));
+        assert!(map.has_line_of_sight(&from, &Position::new(0.95, 0.95)));
+        assert_eq!(
+            map.raycast(&from, &Position::new(0.05, 2.0)),
+            RaycastHit::OutOfMap
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use crate::cell::Cell;
+use crate::error::{Error, Result};
+use crate::grid_map::GridMap;
+use crate::position::Position;
+
+use image::{io::Reader, GrayImage};
+use nalgebra::{Isometry2, Vector2};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How the pixel values of the ROS map image are interpreted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RosMapMode {
+    /// Occupied, free or unknown
+    #[default]
+    Trinary,
+    /// Like trinary, but the cells between the thresholds keep the occupancy (1..=99)
+    Scale,
+    /// The pixel values are used as they are
+    Raw,
+}
+
+/// Metadata of the ROS map_server yaml file
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RosMapMetadata {
+    pub image: String,
+    pub resolution: f64,
+    /// [x, y, yaw] of the lower-left pixel
+    pub origin: [f64; 3],
+    #[serde(default)]
+    pub negate: u8,
+    #[serde(default = "default_occupied_thresh")]
+    pub occupied_thresh: f64,
+    #[serde(default = "default_free_thresh")]
+    pub free_thresh: f64,
+    #[serde(default)]
+    pub mode: RosMapMode,
+}
+
+fn default_occupied_thresh() -> f64 {
+    0.65
+}
+
+fn default_free_thresh() -> f64 {
+    0.196
+}
+
+const PGM_OCCUPIED: u8 = 0;
+const PGM_UNKNOWN: u8 = 205;
+const PGM_FREE: u8 = 254;
+
+impl RosMapMetadata {
+    fn cell_from_pixel(&self, pixel: u8) -> Cell<u8> {
+        if self.mode == RosMapMode::Raw {
+            return Cell::Value(pixel);
+        }
+        let pixel = if self.negate != 0 { pixel } else { 255 - pixel };
+        let occupancy = pixel as f64 / 255.0;
+        if occupancy > self.occupied_thresh {
+            Cell::Obstacle
+        } else if occupancy < self.free_thresh {
+            Cell::Value(0)
+        } else if self.mode == RosMapMode::Scale {
+            let ratio = (occupancy - self.free_thresh) / (self.occupied_thresh - self.free_thresh);
+            Cell::Value((1.0 + ratio * 98.0).round() as u8)
+        } else {
+            Cell::Unknown
+        }
+    }
+}
+
+fn resolve_image_path(yaml_path: &Path, image: &str) -> PathBuf {
+    let image = Path::new(image);
+    if image.is_absolute() {
+        return image.to_owned();
+    }
+    let relative_to_yaml = yaml_path.parent().unwrap_or(Path::new("")).join(image);
+    if relative_to_yaml.exists() {
+        relative_to_yaml
+    } else {
+        image.to_owned()
+    }
+}
+
+impl GridMap<u8> {
+    /// Load the map from the ROS map_server format (yaml + image)
+    ///
+    /// In the trinary mode, occupied cells become [`Cell::Obstacle`], free cells
+    /// become `Cell::Value(0)` and the others become [`Cell::Unknown`].
+    /// If the origin is rotated, the map frame starts at the origin and the pose is
+    /// kept as [`GridMap::origin`].
+    pub fn from_ros_map_yaml<P: AsRef<Path>>(yaml_path: P) -> Result<Self> {
+        let yaml_path = yaml_path.as_ref();
+        let metadata: RosMapMetadata = serde_yaml::from_str(&std::fs::read_to_string(yaml_path)?)?;
+        let img = Reader::open(resolve_image_path(yaml_path, &metadata.image))?.decode()?;
+        Self::from_ros_map_image(&img.to_luma8(), &metadata)
+    }
+
+    /// Convert the image of the ROS map into the map
+    pub fn from_ros_map_image(image: &GrayImage, metadata: &RosMapMetadata) -> Result<Self> {
+        let [x, y, yaw] = metadata.origin;
+        if yaw == 0.0 {
+            return Self::from_image_with(image, metadata.resolution, Position::new(x, y), |p| {
+                metadata.cell_from_pixel(p)
+            });
+        }
+        let map =
+            Self::from_image_with(image, metadata.resolution, Position::new(0.0, 0.0), |p| {
+                metadata.cell_from_pixel(p)
+            })?;
+        Ok(map.with_origin(Isometry2::new(Vector2::new(x, y), yaw)))
+    }
+
+    /// Convert the map into the trinary image of the ROS map
+    pub fn to_ros_map_image(&self) -> GrayImage {
+        self.to_image_with(|cell| match cell {
+            Cell::Obstacle => PGM_OCCUPIED,
+            Cell::Value(_) => PGM_FREE,
+            _ => PGM_UNKNOWN,
+        })
+    }
+
+    /// Pose of the min point in the world frame as (x, y, yaw)
+    fn ros_origin(&self) -> [f64; 3] {
+        let min_point = self.map_to_world(self.min_point());
+        let yaw = self.origin().map_or(0.0, |o| o.rotation.angle());
+        [min_point.x, min_point.y, yaw]
+    }
+
+    /// Save the map in the ROS map_server format
+    ///
+    /// The image is written next to the yaml file with the same file stem and `.pgm` extension.
+    pub fn to_ros_map_yaml<P: AsRef<Path>>(&self, yaml_path: P) -> Result<()> {
+        let yaml_path = yaml_path.as_ref();
+        let image_path = yaml_path.with_extension("pgm");
+        self.to_ros_map_image().save(&image_path)?;
+        let metadata = RosMapMetadata {
+            image: image_path
+                .file_name()
+                .ok_or_else(|| Error::Other(format!("invalid path {yaml_path:?}")))?
+                .to_string_lossy()
+                .into_owned(),
+            resolution: self.resolution(),
+            origin: self.ros_origin(),
+            negate: 0,
+            occupied_thresh: default_occupied_thresh(),
+            free_thresh: default_free_thresh(),
+            mode: RosMapMode::Trinary,
+        };
+        std::fs::write(yaml_path, serde_yaml::to_string(&metadata)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grid;
+
+    #[test]
+    fn test_from_ros_map_yaml() {
+        let map = GridMap::from_ros_map_yaml("test/map.yaml").unwrap();
+        assert_eq!(map.width(), 194);
+        assert_eq!(map.height(), 170);
+        let count = |f: fn(&Cell<u8>) -> bool| map.cells().iter().filter(|c| f(c)).count();
+        assert_eq!(count(|c| c.is_obstacle()), 1335);
+        assert_eq!(count(|c| matches!(c, Cell::Unknown)), 16273);
+        assert_eq!(count(|c| c.has_value()), 15372);
+    }
+
+    #[test]
+    fn test_ros_map_round_trip() {
+        let mut map = GridMap::new(Position::new(-1.0, -0.5), Position::new(1.0, 0.5), 0.1);
+        for cell in map.cells_mut() {
+            *cell = Cell::Value(0);
+        }
+        map.set_obstacle(&Grid::new(1, 9)).unwrap();
+        *map.cell_mut(&Grid::new(3, 0)).unwrap() = Cell::Unknown;
+
+        let dir = std::env::temp_dir().join("grid_map_test_ros_map_round_trip");
+        std::fs::create_dir_all(&dir).unwrap();
+        map.to_ros_map_yaml(dir.join("map.yaml")).unwrap();
+        let loaded = GridMap::from_ros_map_yaml(dir.join("map.yaml")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded.cells(), map.cells());
+        assert_eq!(loaded.min_point(), map.min_point());
+        assert!(loaded.origin().is_none());
+
+        let rotated = map.with_origin(Isometry2::new(Vector2::new(2.0, 1.0), 0.5));
+        let yaml_path = dir.join("rotated.yaml");
+        std::fs::create_dir_all(&dir).unwrap();
+        rotated.to_ros_map_yaml(&yaml_path).unwrap();
+        let loaded = GridMap::from_ros_map_yaml(&yaml_path).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded.cells(), rotated.cells());
+        let p = rotated.map_to_world(&Position::new(-0.85, 0.45));
+        assert_eq!(
+            loaded.world_to_grid(&p),
+            Some(Grid::new(1, 9)),
+            "{:?}",
+            loaded.origin()
+        );
+        assert_eq!(loaded.cell_by_position(&p), Some(&Cell::Obstacle));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::cell::Cell;
+use crate::grid_map::Size;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Backend which keeps the cells of [`GridMap`](crate::GridMap)
+///
+/// The cells are addressed by the row-major index, which is always inside of the
+/// map. `Vec<Cell<T>>` is the dense default and [`ChunkedStorage`] allocates the
+/// cells lazily by square chunks.
+pub trait Storage<T>
+where
+    T: Clone,
+{
+    /// Number of the cells (including the cells which are not allocated)
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn get(&self, index: usize) -> &Cell<T>;
+
+    /// Get the mutable cell, allocating it if needed
+    fn get_mut(&mut self, index: usize) -> &mut Cell<T>;
+
+    /// Set all cells to the cell
+    fn fill(&mut self, cell: Cell<T>);
+}
+
+impl<T> Storage<T> for Vec<Cell<T>>
+where
+    T: Clone,
+{
+    fn len(&self) -> usize {
+        <[Cell<T>]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> &Cell<T> {
+        &self[index]
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut Cell<T> {
+        &mut self[index]
+    }
+
+    fn fill(&mut self, cell: Cell<T>) {
+        <[Cell<T>]>::fill(self, cell);
+    }
+}
+
+/// Storage which allocates `chunk_size` x `chunk_size` cells when any cell of the
+/// chunk is modified
+///
+/// The cells of the chunks which are not allocated are `default` (initially
+/// [`Cell::Uninitialized`]), so very large maps which are mostly unexplored don't
+/// need the memory of the whole map.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChunkedStorage<T>
+where
+    T: Clone,
+{
+    size: Size,
+    chunk_size: usize,
+    chunks: HashMap<(usize, usize), Vec<Cell<T>>>,
+    default: Cell<T>,
+}
+
+impl<T> ChunkedStorage<T>
+where
+    T: Clone,
+{
+    pub fn new(size: Size, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        Self {
+            size,
+            chunk_size,
+            chunks: HashMap::new(),
+            default: Cell::Uninitialized,
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Number of the allocated chunks
+    pub fn num_allocated_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Key of the chunk and the index in the chunk
+    fn locate(&self, index: usize) -> ((usize, usize), usize) {
+        let (x, y) = (index % self.size.width, index / self.size.width);
+        let n = self.chunk_size;
+        ((x / n, y / n), (y % n) * n + x % n)
+    }
+}
+
+impl<T> Storage<T> for ChunkedStorage<T>
+where
+    T: Clone,
+{
+    fn len(&self) -> usize {
+        self.size.len()
+    }
+
+    fn get(&self, index: usize) -> &Cell<T> {
+        let (key, i) = self.locate(index);
+        self.chunks
+            .get(&key)
+            .map_or(&self.default, |chunk| &chunk[i])
+    }
+
+    fn get_mut(&mut self, index: usize) -> &mut Cell<T> {
+        let (key, i) = self.locate(index);
+        let len = self.chunk_size * self.chunk_size;
+        let default = &self.default;
+        &mut self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| vec![default.clone(); len])[i]
+    }
+
+    /// All chunks are released because they have the same cell
+    fn fill(&mut self, cell: Cell<T>) {
+        self.chunks.clear();
+        self.default = cell;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cell, ChunkedGridMap, Grid, GridMap, Position};
+
+    #[test]
+    fn test_chunked_grid_map() {
+        let (min, max) = (Position::new(-50.0, -50.0), Position::new(50.0, 50.0));
+        let mut map = ChunkedGridMap::<u8>::new_chunked(min, max, 0.025, 64);
+        assert_eq!((map.width(), map.height()), (4000, 4000));
+        assert_eq!(map.num_allocated_chunks(), 0);
+        assert_eq!(map.cell(&Grid::new(3999, 3999)), Some(&Cell::Uninitialized));
+
+        map.set_obstacle(&Grid::new(100, 200)).unwrap();
+        map.set_value(&Grid::new(101, 200), 3).unwrap();
+        map.set_value(&Grid::new(3999, 0), 5).unwrap();
+        assert_eq!(map.num_allocated_chunks(), 2);
+        assert_eq!(map.set_value(&Grid::new(4000, 0), 5), None);
+        assert_eq!(map.value(&Grid::new(101, 200)), Some(3));
+        assert_eq!(map.cell(&Grid::new(99, 200)), Some(&Cell::Uninitialized));
+        let grid = map.to_grid(-50.0 + 100.5 * 0.025, -50.0 + 200.5 * 0.025);
+        assert_eq!(grid, Some(Grid::new(100, 200)));
+        assert_eq!(
+            map.neighbors4(&Grid::new(100, 200))
+                .filter(|g| map.value(g).is_some())
+                .count(),
+            1
+        );
+
+        // the same cells as the dense map
+        let mut dense = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.35), 0.1);
+        let mut chunked = ChunkedGridMap::<u8>::new_chunked(
+            Position::new(0.0, 0.0),
+            Position::new(0.55, 0.35),
+            0.1,
+            2,
+        );
+        for (i, grid) in [Grid::new(0, 0), Grid::new(4, 2), Grid::new(3, 1)]
+            .iter()
+            .enumerate()
+        {
+            dense.set_value(grid, i as u8).unwrap();
+            chunked.set_value(grid, i as u8).unwrap();
+        }
+        crate::testkit::assert_maps_equal(&chunked.to_dense(), &dense);
+        assert_eq!(chunked.enumerate_cells().count(), dense.len());
+
+        chunked.fill(Cell::Value(1));
+        assert_eq!(chunked.num_allocated_chunks(), 0);
+        assert_eq!(chunked.value(&Grid::new(2, 2)), Some(1));
+    }
+}
@@ -0,0 +1,228 @@
+//! Comparison and assertion helpers for the tests of map-producing functions
+//!
+//! ```
+//! use grid_map::{testkit::assert_maps_equal, GridMap, Position};
+//!
+//! let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.1);
+//! assert_maps_equal(&map.clone(), &map);
+//! ```
+
+use crate::{Cell, Grid, GridMap};
+use std::fmt::{self, Debug};
+
+/// Number of the differing cells shown in [`MapDiff`]
+const MAX_SHOWN_DIFFERENCES: usize = 10;
+
+/// Numbers of the cells of each kind and the range of the values
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CellHistogram {
+    pub uninitialized: usize,
+    pub unknown: usize,
+    pub obstacle: usize,
+    pub value: usize,
+    /// `(min, max)` of the values
+    pub value_range: Option<(f64, f64)>,
+}
+
+impl CellHistogram {
+    pub fn new<T>(map: &GridMap<T>) -> Self
+    where
+        T: Clone + Into<f64>,
+    {
+        let mut histogram = Self::default();
+        for cell in map.cells() {
+            match cell {
+                Cell::Uninitialized => histogram.uninitialized += 1,
+                Cell::Unknown => histogram.unknown += 1,
+                Cell::Obstacle => histogram.obstacle += 1,
+                Cell::Value(v) => {
+                    let v = v.clone().into();
+                    histogram.value += 1;
+                    histogram.value_range = Some(match histogram.value_range {
+                        Some((min, max)) => (min.min(v), max.max(v)),
+                        None => (v, v),
+                    });
+                }
+            }
+        }
+        histogram
+    }
+}
+
+impl fmt::Display for CellHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "uninitialized: {}, unknown: {}, obstacle: {}, value: {}",
+            self.uninitialized, self.unknown, self.obstacle, self.value
+        )?;
+        if let Some((min, max)) = self.value_range {
+            write!(f, " ({min} ..= {max})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Differences between two maps
+#[derive(Debug, Clone)]
+pub struct MapDiff<T>
+where
+    T: Clone,
+{
+    /// Description of the differing geometry (size, resolution or min_point)
+    pub geometry: Option<String>,
+    /// The first differing cells as `(grid, actual, expected)`
+    pub differences: Vec<(Grid, Cell<T>, Cell<T>)>,
+    pub num_differences: usize,
+    pub actual: CellHistogram,
+    pub expected: CellHistogram,
+}
+
+impl<T> MapDiff<T>
+where
+    T: Clone + Into<f64>,
+{
+    /// Compare the maps cell by cell with `same`
+    pub fn new<F>(actual: &GridMap<T>, expected: &GridMap<T>, same: F) -> Self
+    where
+        F: Fn(&Cell<T>, &Cell<T>) -> bool,
+    {
+        let geometry = if (actual.width(), actual.height()) != (expected.width(), expected.height())
+        {
+            Some(format!(
+                "size {}x{} != {}x{}",
+                actual.width(),
+                actual.height(),
+                expected.width(),
+                expected.height()
+            ))
+        } else if (actual.resolution() - expected.resolution()).abs() > f64::EPSILON {
+            Some(format!(
+                "resolution {} != {}",
+                actual.resolution(),
+                expected.resolution()
+            ))
+        } else if (actual.min_point().x - expected.min_point().x).abs() > f64::EPSILON
+            || (actual.min_point().y - expected.min_point().y).abs() > f64::EPSILON
+        {
+            Some(format!(
+                "min_point {:?} != {:?}",
+                actual.min_point(),
+                expected.min_point()
+            ))
+        } else {
+            None
+        };
+        let mut differences = vec![];
+        let mut num_differences = 0;
+        if geometry.is_none() {
+            for (grid, _, a) in actual.enumerate_cells() {
+                let e = expected.cell(&grid).unwrap();
+                if !same(a, e) {
+                    num_differences += 1;
+                    if differences.len() < MAX_SHOWN_DIFFERENCES {
+                        differences.push((grid, a.clone(), e.clone()));
+                    }
+                }
+            }
+        }
+        Self {
+            geometry,
+            differences,
+            num_differences,
+            actual: CellHistogram::new(actual),
+            expected: CellHistogram::new(expected),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.geometry.is_none() && self.num_differences == 0
+    }
+}
+
+impl<T> fmt::Display for MapDiff<T>
+where
+    T: Clone + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(geometry) = &self.geometry {
+            writeln!(f, "maps have different geometry: {geometry}")?;
+        } else {
+            writeln!(f, "{} cells differ:", self.num_differences)?;
+            for (grid, actual, expected) in &self.differences {
+                writeln!(f, "  ({}, {}): {actual:?} != {expected:?}", grid.x, grid.y)?;
+            }
+            if self.num_differences > self.differences.len() {
+                writeln!(
+                    f,
+                    "  ... and {} more",
+                    self.num_differences - self.differences.len()
+                )?;
+            }
+        }
+        writeln!(f, "actual:   {}", self.actual)?;
+        write!(f, "expected: {}", self.expected)
+    }
+}
+
+/// Assert that the maps have the same geometry and cells
+#[track_caller]
+pub fn assert_maps_equal<T>(actual: &GridMap<T>, expected: &GridMap<T>)
+where
+    T: Clone + Debug + PartialEq + Into<f64>,
+{
+    let diff = MapDiff::new(actual, expected, |a, e| a == e);
+    assert!(diff.is_empty(), "{diff}");
+}
+
+/// Assert that the maps have the same geometry and kinds of the cells, and the
+/// values differ at most by the tolerance
+#[track_caller]
+pub fn assert_maps_close<T>(actual: &GridMap<T>, expected: &GridMap<T>, tolerance: f64)
+where
+    T: Clone + Debug + Into<f64>,
+{
+    let diff = MapDiff::new(actual, expected, |a, e| match (a, e) {
+        (Cell::Value(a), Cell::Value(e)) => {
+            let (a, e) = (a.clone().into(), e.clone().into());
+            // infinite values are close only if they are equal
+            a == e || (a - e).abs() <= tolerance
+        }
+        _ => std::mem::discriminant(a) == std::mem::discriminant(e),
+    });
+    assert!(diff.is_empty(), "{diff}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_map_diff() {
+        let mut expected =
+            GridMap::<f32>::new(Position::new(0.0, 0.0), Position::new(0.45, 0.25), 0.1);
+        expected.fill(Cell::Value(1.0));
+        let mut actual = expected.clone();
+        assert_maps_equal(&actual, &expected);
+
+        actual.set_value(&Grid::new(1, 1), 1.05).unwrap();
+        assert_maps_close(&actual, &expected, 0.1);
+        actual.set_obstacle(&Grid::new(3, 0)).unwrap();
+        let diff = MapDiff::new(&actual, &expected, |a, e| a == e);
+        assert_eq!(diff.num_differences, 2);
+        assert_eq!(diff.differences[0].0, Grid::new(3, 0));
+        assert_eq!(diff.actual.obstacle, 1);
+        assert_eq!(diff.expected.value_range, Some((1.0, 1.0)));
+        let message = diff.to_string();
+        assert!(
+            message.contains("(1, 1): Value(1.05) != Value(1.0)"),
+            "{message}"
+        );
+
+        let other = GridMap::<f32>::new(Position::new(0.0, 0.0), Position::new(0.55, 0.25), 0.1);
+        let diff = MapDiff::new(&other, &expected, |a, e| a == e);
+        assert_eq!(diff.geometry.as_deref(), Some("size 5x2 != 4x2"));
+        assert!(std::panic::catch_unwind(|| assert_maps_close(&actual, &expected, 0.1)).is_err());
+    }
+}
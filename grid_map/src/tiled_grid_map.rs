@@ -0,0 +1,366 @@
+use crate::cell::Cell;
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::grid_map::GridMap;
+use crate::position::Position;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+const METADATA_FILE_NAME: &str = "tiles.yaml";
+
+/// Index of a tile of [`TiledGridMap`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct TileIndex {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl TileIndex {
+    pub fn new(x: i64, y: i64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Geometry of the tiles, saved as `tiles.yaml` in the directory
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TileMetadata {
+    /// Position of the lower-left corner of the tile (0, 0)
+    pub origin: Position,
+    pub resolution: f64,
+    /// Number of cells of each side of a tile
+    pub tile_size: usize,
+}
+
+fn tile_path(directory: &Path, index: &TileIndex) -> PathBuf {
+    directory.join(format!("tile_{}_{}.bin", index.x, index.y))
+}
+
+#[derive(Debug)]
+struct LoadedTile<T>
+where
+    T: Clone,
+{
+    map: GridMap<T>,
+    last_access: u64,
+    dirty: bool,
+}
+
+/// Map split into square tiles on disk, which loads only the tiles in use
+///
+/// Each tile is a [`GridMap`] saved in the binary format. Tiles which have never
+/// been written are [`Cell::Uninitialized`]. When more than `max_loaded_tiles`
+/// tiles are loaded, the least recently used tile is saved (if modified) and evicted,
+/// so very large maps don't need to fit in memory. Call [`TiledGridMap::flush`]
+/// to save the modified tiles which are still loaded.
+#[derive(Debug)]
+pub struct TiledGridMap<T>
+where
+    T: Clone,
+{
+    directory: PathBuf,
+    metadata: TileMetadata,
+    max_loaded_tiles: usize,
+    tiles: HashMap<TileIndex, LoadedTile<T>>,
+    tick: u64,
+}
+
+impl<T> TiledGridMap<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Create a new tiled map in the directory
+    pub fn create<P: AsRef<Path>>(
+        directory: P,
+        metadata: TileMetadata,
+        max_loaded_tiles: usize,
+    ) -> Result<Self> {
+        if metadata.resolution <= 0.0 || metadata.tile_size == 0 {
+            return Err(Error::Other(format!("invalid tile metadata {metadata:?}")));
+        }
+        let directory = directory.as_ref().to_owned();
+        std::fs::create_dir_all(&directory)?;
+        std::fs::write(
+            directory.join(METADATA_FILE_NAME),
+            serde_yaml::to_string(&metadata)?,
+        )?;
+        Ok(Self::with_metadata(directory, metadata, max_loaded_tiles))
+    }
+
+    /// Open the tiled map created by [`TiledGridMap::create`]
+    pub fn open<P: AsRef<Path>>(directory: P, max_loaded_tiles: usize) -> Result<Self> {
+        let directory = directory.as_ref().to_owned();
+        let metadata: TileMetadata = serde_yaml::from_str(&std::fs::read_to_string(
+            directory.join(METADATA_FILE_NAME),
+        )?)?;
+        Ok(Self::with_metadata(directory, metadata, max_loaded_tiles))
+    }
+
+    /// Split the map into tiles and save them in the directory
+    pub fn from_grid_map<P: AsRef<Path>>(
+        map: &GridMap<T>,
+        directory: P,
+        tile_size: usize,
+        max_loaded_tiles: usize,
+    ) -> Result<Self> {
+        let metadata = TileMetadata {
+            origin: *map.min_point(),
+            resolution: map.resolution(),
+            tile_size,
+        };
+        let mut tiled = Self::create(directory, metadata, max_loaded_tiles)?;
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let cell = map.cell(&Grid::new(x, y)).unwrap();
+                if !cell.is_uninitialized() {
+                    tiled.set_cell_by_global(x as i64, y as i64, cell.clone())?;
+                }
+            }
+        }
+        tiled.flush()?;
+        Ok(tiled)
+    }
+
+    fn with_metadata(directory: PathBuf, metadata: TileMetadata, max_loaded_tiles: usize) -> Self {
+        Self {
+            directory,
+            metadata,
+            max_loaded_tiles: max_loaded_tiles.max(1),
+            tiles: HashMap::new(),
+            tick: 0,
+        }
+    }
+
+    pub fn metadata(&self) -> &TileMetadata {
+        &self.metadata
+    }
+
+    pub fn resolution(&self) -> f64 {
+        self.metadata.resolution
+    }
+
+    /// Number of the tiles in memory
+    pub fn num_loaded_tiles(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_loaded(&self, index: &TileIndex) -> bool {
+        self.tiles.contains_key(index)
+    }
+
+    fn tile_path(&self, index: &TileIndex) -> PathBuf {
+        tile_path(&self.directory, index)
+    }
+
+    fn global_cell(&self, position: &Position) -> (i64, i64) {
+        (
+            ((position.x - self.metadata.origin.x) / self.metadata.resolution).floor() as i64,
+            ((position.y - self.metadata.origin.y) / self.metadata.resolution).floor() as i64,
+        )
+    }
+
+    fn split_global(&self, x: i64, y: i64) -> (TileIndex, Grid) {
+        let size = self.metadata.tile_size as i64;
+        (
+            TileIndex::new(x.div_euclid(size), y.div_euclid(size)),
+            Grid::new(x.rem_euclid(size) as usize, y.rem_euclid(size) as usize),
+        )
+    }
+
+    /// Index of the tile which contains the position
+    pub fn tile_index(&self, position: &Position) -> TileIndex {
+        let (x, y) = self.global_cell(position);
+        self.split_global(x, y).0
+    }
+
+    fn new_tile(&self, index: &TileIndex) -> GridMap<T> {
+        let length = self.metadata.tile_size as f64 * self.metadata.resolution;
+        let min_point = Position::new(
+            self.metadata.origin.x + index.x as f64 * length,
+            self.metadata.origin.y + index.y as f64 * length,
+        );
+        // Half cell margin not to lose the last column/row by the floating point error
+        let margin = self.metadata.resolution * 0.5;
+        let max_point = Position::new(min_point.x + length + margin, min_point.y + length + margin);
+        GridMap::new(min_point, max_point, self.metadata.resolution)
+    }
+
+    /// Load the tile into memory if it's not loaded yet
+    pub fn load_tile(&mut self, index: &TileIndex) -> Result<&mut GridMap<T>> {
+        self.tick += 1;
+        if !self.tiles.contains_key(index) {
+            while self.tiles.len() >= self.max_loaded_tiles {
+                self.evict_least_recently_used()?;
+            }
+            let path = self.tile_path(index);
+            let map = if path.exists() {
+                GridMap::load_from_file(&path)?
+            } else {
+                self.new_tile(index)
+            };
+            self.tiles.insert(
+                *index,
+                LoadedTile {
+                    map,
+                    last_access: 0,
+                    dirty: false,
+                },
+            );
+        }
+        let tile = self.tiles.get_mut(index).unwrap();
+        tile.last_access = self.tick;
+        Ok(&mut tile.map)
+    }
+
+    fn evict_least_recently_used(&mut self) -> Result<()> {
+        let Some(index) = self
+            .tiles
+            .iter()
+            .min_by_key(|(_, tile)| tile.last_access)
+            .map(|(index, _)| *index)
+        else {
+            return Ok(());
+        };
+        self.unload_tile(&index)
+    }
+
+    /// Save the tile if it's modified and remove it from memory
+    pub fn unload_tile(&mut self, index: &TileIndex) -> Result<()> {
+        if let Some(tile) = self.tiles.remove(index) {
+            if tile.dirty {
+                tile.map.save_to_file(self.tile_path(index))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the tiles within the radius from the position and evict the others
+    /// if the number of the loaded tiles exceeds the limit
+    pub fn load_around(&mut self, position: &Position, radius: f64) -> Result<()> {
+        let min = self.tile_index(&Position::new(position.x - radius, position.y - radius));
+        let max = self.tile_index(&Position::new(position.x + radius, position.y + radius));
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                self.load_tile(&TileIndex::new(x, y))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the cell at the position, loading the tile if required
+    pub fn cell(&mut self, position: &Position) -> Result<Cell<T>> {
+        let (x, y) = self.global_cell(position);
+        let (index, grid) = self.split_global(x, y);
+        let tile = self.load_tile(&index)?;
+        tile.cell(&grid).cloned().ok_or(Error::OutOfRangeGrid(grid))
+    }
+
+    /// Set the cell at the position, loading the tile if required
+    pub fn set_cell(&mut self, position: &Position, cell: Cell<T>) -> Result<()> {
+        let (x, y) = self.global_cell(position);
+        self.set_cell_by_global(x, y, cell)
+    }
+
+    fn set_cell_by_global(&mut self, x: i64, y: i64, cell: Cell<T>) -> Result<()> {
+        let (index, grid) = self.split_global(x, y);
+        *self
+            .load_tile(&index)?
+            .cell_mut(&grid)
+            .ok_or(Error::OutOfRangeGrid(grid))? = cell;
+        self.tiles.get_mut(&index).unwrap().dirty = true;
+        Ok(())
+    }
+
+    /// Save all modified tiles
+    pub fn flush(&mut self) -> Result<()> {
+        for (index, tile) in &mut self.tiles {
+            if tile.dirty {
+                tile.map.save_to_file(tile_path(&self.directory, index))?;
+                tile.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a [`GridMap`] of the square region around the center from the tiles,
+    /// e.g. the local map around the robot
+    pub fn local_map(&mut self, center: &Position, half_size: f64) -> Result<GridMap<T>> {
+        let resolution = self.metadata.resolution;
+        let (min_x, min_y) =
+            self.global_cell(&Position::new(center.x - half_size, center.y - half_size));
+        let (max_x, max_y) =
+            self.global_cell(&Position::new(center.x + half_size, center.y + half_size));
+        let min_point = Position::new(
+            self.metadata.origin.x + min_x as f64 * resolution,
+            self.metadata.origin.y + min_y as f64 * resolution,
+        );
+        let max_point = Position::new(
+            self.metadata.origin.x + (max_x as f64 + 1.5) * resolution,
+            self.metadata.origin.y + (max_y as f64 + 1.5) * resolution,
+        );
+        let mut map = GridMap::new(min_point, max_point, resolution);
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let (index, grid) = self.split_global(min_x + x as i64, min_y + y as i64);
+                if let Some(cell) = self.load_tile(&index)?.cell(&grid).cloned() {
+                    *map.cell_mut(&Grid::new(x, y)).unwrap() = cell;
+                }
+            }
+        }
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tiled_grid_map() {
+        let dir = std::env::temp_dir().join("grid_map_test_tiled_grid_map");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut map = GridMap::new(Position::new(-1.0, -1.0), Position::new(1.05, 1.05), 0.1);
+        map.set_value(&Grid::new(3, 4), 7u8).unwrap();
+        map.set_obstacle(&Grid::new(15, 12)).unwrap();
+
+        let mut tiled = TiledGridMap::from_grid_map(&map, &dir, 8, 2).unwrap();
+        assert!(tiled.num_loaded_tiles() <= 2);
+        assert_eq!(
+            tiled.cell(&Position::new(-0.65, -0.55)).unwrap(),
+            Cell::Value(7)
+        );
+        tiled
+            .set_cell(&Position::new(5.05, 5.05), Cell::Value(1))
+            .unwrap();
+        assert_eq!(
+            tiled.tile_index(&Position::new(5.05, 5.05)),
+            TileIndex::new(7, 7)
+        );
+        tiled.flush().unwrap();
+        drop(tiled);
+
+        let mut tiled = TiledGridMap::<u8>::open(&dir, 1).unwrap();
+        assert!(tiled
+            .cell(&Position::new(0.55, 0.25))
+            .unwrap()
+            .is_obstacle());
+        assert_eq!(
+            tiled.cell(&Position::new(5.05, 5.05)).unwrap(),
+            Cell::Value(1)
+        );
+        assert_eq!(tiled.num_loaded_tiles(), 1);
+        assert!(tiled
+            .cell(&Position::new(-5.0, -5.0))
+            .unwrap()
+            .is_uninitialized());
+
+        let local = tiled.local_map(&Position::new(0.0, 0.0), 0.95).unwrap();
+        assert_eq!(local.width(), 20);
+        let grid = local.to_grid(-0.65, -0.55).unwrap();
+        assert_eq!(local.value(&grid), Some(7));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
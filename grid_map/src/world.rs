@@ -0,0 +1,255 @@
+use crate::cell::Cell;
+use crate::error::{Error, Result};
+use crate::grid::Grid;
+use crate::grid_map::{GridMap, Size};
+use crate::position::Position;
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Obstacle shape in the world coordinates
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Shape {
+    /// Closed polygon. The last point is connected to the first point.
+    Polygon {
+        points: Vec<[f64; 2]>,
+    },
+    Circle {
+        center: [f64; 2],
+        radius: f64,
+    },
+}
+
+impl Shape {
+    fn is_valid(&self) -> bool {
+        match self {
+            Shape::Polygon { points } => points.len() >= 3,
+            Shape::Circle { radius, .. } => *radius > 0.0,
+        }
+    }
+}
+
+/// Resolution independent description of the world
+///
+/// ```yaml
+/// min_point: [-2.0, -2.0]
+/// max_point: [2.0, 2.0]
+/// obstacles:
+///   - type: polygon
+///     points: [[0.0, 0.0], [1.0, 0.0], [1.0, 0.5]]
+///   - type: circle
+///     center: [-1.0, 1.0]
+///     radius: 0.3
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct World {
+    pub min_point: [f64; 2],
+    pub max_point: [f64; 2],
+    #[serde(default)]
+    pub obstacles: Vec<Shape>,
+}
+
+/// Even-odd rule
+fn is_inside_polygon(points: &[[f64; 2]], p: &Position) -> bool {
+    let mut inside = false;
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let ([xi, yi], [xj, yj]) = (points[i], points[j]);
+        if (yi > p.y) != (yj > p.y) && p.x < (xj - xi) * (p.y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+impl World {
+    pub fn from_yaml_str(yaml: &str) -> Result<Self> {
+        let world: Self = serde_yaml::from_str(yaml)?;
+        world.validate()?;
+        Ok(world)
+    }
+
+    /// Check the bounds and the shapes, which is done by [`World::from_yaml_str`]
+    /// for the world embedded in other configs
+    pub fn validate(&self) -> Result<()> {
+        if self.max_point[0] <= self.min_point[0] || self.max_point[1] <= self.min_point[1] {
+            return Err(Error::Other(format!(
+                "max_point {:?} must be larger than min_point {:?}",
+                self.max_point, self.min_point
+            )));
+        }
+        if let Some(shape) = self.obstacles.iter().find(|s| !s.is_valid()) {
+            return Err(Error::Other(format!("invalid shape: {shape:?}")));
+        }
+        Ok(())
+    }
+
+    pub fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_yaml_str(&std::fs::read_to_string(path)?)
+    }
+
+    /// Rasterize the world into the cost map of the resolution
+    ///
+    /// Cells which overlap with any obstacle become [`Cell::Obstacle`] and the others
+    /// become `Cell::Value(0)`. The map covers the whole world, so it can be a little
+    /// larger than `max_point`.
+    pub fn rasterize(&self, resolution: f64) -> GridMap<u8> {
+        let min_point = Position::from(self.min_point);
+        let cells = |len: f64| {
+            let n = len / resolution;
+            // avoid an extra cell by the rounding error
+            let n = if (n - n.round()).abs() < 1e-6 {
+                n.round()
+            } else {
+                n.ceil()
+            };
+            n.max(1.0) as usize
+        };
+        let size = Size::new(
+            cells(self.max_point[0] - self.min_point[0]),
+            cells(self.max_point[1] - self.min_point[1]),
+        );
+        let mut map = GridMap::with_size(min_point, size, resolution);
+        map.fill(Cell::Value(0));
+        for shape in &self.obstacles {
+            rasterize_shape(&mut map, shape);
+        }
+        map
+    }
+}
+
+impl From<[f64; 2]> for Position {
+    fn from(value: [f64; 2]) -> Self {
+        Self::new(value[0], value[1])
+    }
+}
+
+/// Range of the grids which overlap with the bounding box, clipped by the map
+fn grid_range(map: &GridMap<u8>, min: [f64; 2], max: [f64; 2]) -> Option<(Grid, Grid)> {
+    let resolution = map.resolution();
+    let index = |v: f64, origin: f64, len: usize| {
+        (((v - origin) / resolution).floor() as i64).clamp(-1, len as i64)
+    };
+    let min_x = index(min[0], map.min_point().x, map.width()).max(0);
+    let min_y = index(min[1], map.min_point().y, map.height()).max(0);
+    let max_x = index(max[0], map.min_point().x, map.width()).min(map.width() as i64 - 1);
+    let max_y = index(max[1], map.min_point().y, map.height()).min(map.height() as i64 - 1);
+    if min_x > max_x || min_y > max_y {
+        return None;
+    }
+    Some((
+        Grid::new(min_x as usize, min_y as usize),
+        Grid::new(max_x as usize, max_y as usize),
+    ))
+}
+
+fn rasterize_shape(map: &mut GridMap<u8>, shape: &Shape) {
+    let resolution = map.resolution();
+    let origin = *map.min_point();
+    let cell_min = |grid: &Grid| {
+        Position::new(
+            origin.x + grid.x as f64 * resolution,
+            origin.y + grid.y as f64 * resolution,
+        )
+    };
+    match shape {
+        Shape::Polygon { points } => {
+            let (mut min, mut max) = ([f64::MAX; 2], [f64::MIN; 2]);
+            for p in points {
+                for i in 0..2 {
+                    min[i] = min[i].min(p[i]);
+                    max[i] = max[i].max(p[i]);
+                }
+            }
+            let Some((from, to)) = grid_range(map, min, max) else {
+                return;
+            };
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let grid = Grid::new(x, y);
+                    if is_inside_polygon(points, &map.cell_center(&grid)) {
+                        map.set_obstacle(&grid);
+                    }
+                }
+            }
+            // thin polygons which don't include any cell center
+            for (i, a) in points.iter().enumerate() {
+                let b = points[(i + 1) % points.len()];
+                let edge = map
+                    .traverse(&Position::from(*a), &Position::from(b))
+                    .collect::<Vec<_>>();
+                for grid in edge {
+                    map.set_obstacle(&grid);
+                }
+            }
+        }
+        Shape::Circle { center, radius } => {
+            let min = [center[0] - radius, center[1] - radius];
+            let max = [center[0] + radius, center[1] + radius];
+            let Some((from, to)) = grid_range(map, min, max) else {
+                return;
+            };
+            for y in from.y..=to.y {
+                for x in from.x..=to.x {
+                    let grid = Grid::new(x, y);
+                    let corner = cell_min(&grid);
+                    // the nearest point of the cell to the center
+                    let dx = center[0] - center[0].clamp(corner.x, corner.x + resolution);
+                    let dy = center[1] - center[1].clamp(corner.y, corner.y + resolution);
+                    if dx * dx + dy * dy <= radius * radius {
+                        map.set_obstacle(&grid);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORLD: &str = r#"
+min_point: [-1.0, -1.0]
+max_point: [1.0, 1.0]
+obstacles:
+  - type: polygon
+    points: [[0.0, 0.0], [0.6, 0.0], [0.6, 0.4], [0.0, 0.4]]
+  - type: circle
+    center: [-0.5, -0.5]
+    radius: 0.25
+  - type: polygon
+    points: [[-0.9, 0.8], [0.9, 0.8], [0.9, 0.81]]
+"#;
+
+    #[test]
+    fn test_rasterize_world() {
+        let world = World::from_yaml_str(WORLD).unwrap();
+        assert_eq!(world.obstacles.len(), 3);
+        let cell =
+            |map: &GridMap<u8>, x: f64, y: f64| *map.cell(&map.to_grid(x, y).unwrap()).unwrap();
+        for resolution in [0.1, 0.05, 0.3] {
+            let map = world.rasterize(resolution);
+            assert!(map.max_point().x >= 1.0 - 1e-9);
+            assert_eq!(cell(&map, 0.3, 0.2), Cell::Obstacle);
+            assert_eq!(cell(&map, -0.5, -0.5), Cell::Obstacle);
+            assert_eq!(cell(&map, 0.0, 0.805), Cell::Obstacle);
+            assert_eq!(cell(&map, 0.9, -0.9), Cell::Value(0));
+            assert_eq!(cell(&map, -0.95, 0.3), Cell::Value(0));
+        }
+        let map = world.rasterize(0.1);
+        assert_eq!((map.width(), map.height()), (20, 20));
+        assert_eq!(cell(&map, 0.75, 0.2), Cell::Value(0));
+        assert_eq!(cell(&map, -0.5, -0.15), Cell::Value(0));
+        assert_eq!(cell(&map, -0.5, -0.3), Cell::Obstacle);
+
+        assert!(World::from_yaml_str("min_point: [1, 1]\nmax_point: [0, 0]").is_err());
+        assert!(World::from_yaml_str(
+            "min_point: [0, 0]\nmax_point: [1, 1]\nobstacles:\n  - type: circle\n    center: [0, 0]\n    radius: -1"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * C API of openrr-nav
+ *
+ * Link with libopenrr_nav_capi (cdylib or staticlib). All functions of a planner
+ * must be called from one thread at a time.
+ *
+ * The panics in the library are not propagated to the caller. They are returned as
+ * OPENRR_NAV_ERROR (NULL, -1 or 0 by the functions not returning the codes) with
+ * the message of openrr_nav_last_error.
+ */
+#ifndef OPENRR_NAV_H
+#define OPENRR_NAV_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define OPENRR_NAV_API_VERSION 1
+
+/* Return codes */
+#define OPENRR_NAV_OK 0
+#define OPENRR_NAV_INVALID_ARGUMENT 1
+#define OPENRR_NAV_ERROR 2
+
+/* States of the planner */
+#define OPENRR_NAV_STATE_IDLE 0
+#define OPENRR_NAV_STATE_COMPUTING_PATH 1
+#define OPENRR_NAV_STATE_FOLLOWING_PATH 2
+#define OPENRR_NAV_STATE_RECOVERY 3
+#define OPENRR_NAV_STATE_GOAL_REACHED 4
+#define OPENRR_NAV_STATE_FAILED 5
+
+typedef struct OpenrrNavPlanner OpenrrNavPlanner;
+
+/* Pose in the map frame [m, m, rad] */
+typedef struct OpenrrNavPose {
+  double x;
+  double y;
+  double theta;
+} OpenrrNavPose;
+
+/* Velocity of the differential drive base [m/s, rad/s] */
+typedef struct OpenrrNavVelocity {
+  double x;
+  double theta;
+} OpenrrNavVelocity;
+
+/* Create the planner from the config file of the local planner. Returns NULL on
+ * error. */
+OpenrrNavPlanner *openrr_nav_planner_new(const char *config_path);
+
+void openrr_nav_planner_free(OpenrrNavPlanner *planner);
+
+/* Set the map in the cells of nav_msgs/OccupancyGrid: -1 is unknown, 100 is an
+ * obstacle and 0-99 are the costs, in row-major order from the origin. */
+int32_t openrr_nav_planner_set_map(OpenrrNavPlanner *planner, const int8_t *data,
+                                   size_t width, size_t height, double resolution,
+                                   double origin_x, double origin_y);
+
+int32_t openrr_nav_planner_set_goal(OpenrrNavPlanner *planner, OpenrrNavPose goal);
+
+int32_t openrr_nav_planner_cancel(OpenrrNavPlanner *planner);
+
+/* Run a control cycle and write the velocity command to `command`. */
+int32_t openrr_nav_planner_tick(OpenrrNavPlanner *planner, OpenrrNavPose pose,
+                                OpenrrNavVelocity velocity,
+                                OpenrrNavVelocity *command);
+
+/* One of OPENRR_NAV_STATE_*, or -1 if `planner` is NULL. */
+int32_t openrr_nav_planner_state(const OpenrrNavPlanner *planner);
+
+/* Copy the global path to `poses` up to `capacity`, and return the length of the
+ * path. Call with `capacity` 0 to get the length. */
+size_t openrr_nav_planner_global_path(const OpenrrNavPlanner *planner,
+                                      OpenrrNavPose *poses, size_t capacity);
+
+/* Copy the path of the last local plan like openrr_nav_planner_global_path. */
+size_t openrr_nav_planner_local_path(const OpenrrNavPlanner *planner,
+                                     OpenrrNavPose *poses, size_t capacity);
+
+/* Message of the last error on the calling thread, or NULL. The string is valid
+ * until the next call of the API on the thread. */
+const char *openrr_nav_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* OPENRR_NAV_H */
@@ -0,0 +1,471 @@
+//! C API of openrr-nav for embedding the planner in the non-Rust robot stacks
+//!
+//! The API is declared in `include/openrr_nav.h`. A planner is the [`Navigator`]
+//! with [`AStarPlanner`] and the local planner of the config file. All functions
+//! of a planner must be called from one thread at a time.
+//!
+//! The panics are caught at the entry points and returned as the errors with the
+//! message of [`openrr_nav_last_error`], instead of unwinding into the caller.
+
+use grid_map::{Cell, GridMap, Position, Size};
+use nalgebra as na;
+use openrr_nav_core::{
+    AStarPlanner, LocalPlannerConfig, Navigator, NavigatorState, Pose, Velocity,
+};
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr, slice,
+};
+
+pub const OPENRR_NAV_OK: i32 = 0;
+pub const OPENRR_NAV_INVALID_ARGUMENT: i32 = 1;
+pub const OPENRR_NAV_ERROR: i32 = 2;
+
+pub const OPENRR_NAV_STATE_IDLE: i32 = 0;
+pub const OPENRR_NAV_STATE_COMPUTING_PATH: i32 = 1;
+pub const OPENRR_NAV_STATE_FOLLOWING_PATH: i32 = 2;
+pub const OPENRR_NAV_STATE_RECOVERY: i32 = 3;
+pub const OPENRR_NAV_STATE_GOAL_REACHED: i32 = 4;
+pub const OPENRR_NAV_STATE_FAILED: i32 = 5;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpenrrNavPose {
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+}
+
+impl From<OpenrrNavPose> for Pose {
+    fn from(pose: OpenrrNavPose) -> Self {
+        Pose::new(na::Vector2::new(pose.x, pose.y), pose.theta)
+    }
+}
+
+impl From<&Pose> for OpenrrNavPose {
+    fn from(pose: &Pose) -> Self {
+        Self {
+            x: pose.translation.x,
+            y: pose.translation.y,
+            theta: pose.rotation.angle(),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpenrrNavVelocity {
+    pub x: f64,
+    pub theta: f64,
+}
+
+/// Opaque planner handle
+#[derive(Debug)]
+pub struct OpenrrNavPlanner {
+    navigator: Navigator,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let message = CString::new(message.into().replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+/// Run the body of the entry point, and return `on_panic` if it panics
+fn catch_panic<T>(on_panic: T, f: impl FnOnce() -> T) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(value) => value,
+        Err(payload) => {
+            set_last_error(format!("panicked: {}", panic_message(&*payload)));
+            on_panic
+        }
+    }
+}
+
+fn state_to_c(state: NavigatorState) -> i32 {
+    match state {
+        NavigatorState::Idle => OPENRR_NAV_STATE_IDLE,
+        NavigatorState::ComputingPath => OPENRR_NAV_STATE_COMPUTING_PATH,
+        NavigatorState::FollowingPath => OPENRR_NAV_STATE_FOLLOWING_PATH,
+        NavigatorState::Recovery => OPENRR_NAV_STATE_RECOVERY,
+        NavigatorState::GoalReached => OPENRR_NAV_STATE_GOAL_REACHED,
+        NavigatorState::Failed => OPENRR_NAV_STATE_FAILED,
+    }
+}
+
+/// Map from the cells of nav_msgs/OccupancyGrid
+fn occupancy_to_map(
+    data: &[i8],
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin: Position,
+) -> GridMap<u8> {
+    let mut map = GridMap::with_size(origin, Size::new(width, height), resolution);
+    for (cell, value) in map.cells_mut().iter_mut().zip(data) {
+        *cell = match *value {
+            v if v >= 100 => Cell::Obstacle,
+            v if v < 0 => Cell::Unknown,
+            v => Cell::Value(v as u8),
+        };
+    }
+    map
+}
+
+/// Copy the poses to the buffer and return the number of all poses
+///
+/// # Safety
+///
+/// `poses` must be valid for `capacity` writes unless `capacity` is 0.
+unsafe fn copy_poses(
+    source: impl ExactSizeIterator<Item = OpenrrNavPose>,
+    poses: *mut OpenrrNavPose,
+    capacity: usize,
+) -> usize {
+    let len = source.len();
+    if !poses.is_null() && capacity > 0 {
+        let buffer = slice::from_raw_parts_mut(poses, capacity);
+        for (dst, pose) in buffer.iter_mut().zip(source) {
+            *dst = pose;
+        }
+    }
+    len
+}
+
+/// Create the planner from the config file of the local planner
+///
+/// Returns null on error.
+///
+/// # Safety
+///
+/// `config_path` must be a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_new(
+    config_path: *const c_char,
+) -> *mut OpenrrNavPlanner {
+    catch_panic(ptr::null_mut(), || {
+        if config_path.is_null() {
+            set_last_error("config_path is null");
+            return ptr::null_mut();
+        }
+        let path = match CStr::from_ptr(config_path).to_str() {
+            Ok(path) => path,
+            Err(e) => {
+                set_last_error(format!("invalid config_path: {e}"));
+                return ptr::null_mut();
+            }
+        };
+        let config = match LocalPlannerConfig::new_from_config(path) {
+            Ok(config) => config,
+            Err(e) => {
+                set_last_error(e.to_string());
+                return ptr::null_mut();
+            }
+        };
+        let map = GridMap::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.05);
+        let navigator = Navigator::new(Box::new(AStarPlanner::default()), config.to_planner(), map);
+        Box::into_raw(Box::new(OpenrrNavPlanner { navigator }))
+    })
+}
+
+/// # Safety
+///
+/// `planner` must be null or created by [`openrr_nav_planner_new`], and must not be
+/// used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_free(planner: *mut OpenrrNavPlanner) {
+    if !planner.is_null() {
+        catch_panic((), || drop(Box::from_raw(planner)));
+    }
+}
+
+/// Set the map in the cells of nav_msgs/OccupancyGrid: -1 is unknown, 100 is an
+/// obstacle and 0-99 are the costs, in row-major order from the origin
+///
+/// # Safety
+///
+/// `planner` must be valid and `data` must be valid for `width * height` reads.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_set_map(
+    planner: *mut OpenrrNavPlanner,
+    data: *const i8,
+    width: usize,
+    height: usize,
+    resolution: f64,
+    origin_x: f64,
+    origin_y: f64,
+) -> i32 {
+    catch_panic(OPENRR_NAV_ERROR, || {
+        let Some(planner) = planner.as_mut() else {
+            set_last_error("planner is null");
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        };
+        if data.is_null() || width == 0 || height == 0 || resolution <= 0.0 {
+            set_last_error(format!(
+                "invalid map of {width}x{height} with resolution {resolution}"
+            ));
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        }
+        let Some(len) = width.checked_mul(height) else {
+            set_last_error(format!("too large map of {width}x{height}"));
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        };
+        let data = slice::from_raw_parts(data, len);
+        let map = occupancy_to_map(
+            data,
+            width,
+            height,
+            resolution,
+            Position::new(origin_x, origin_y),
+        );
+        match planner.navigator.set_map(map) {
+            Ok(()) => OPENRR_NAV_OK,
+            Err(e) => {
+                set_last_error(e.to_string());
+                OPENRR_NAV_ERROR
+            }
+        }
+    })
+}
+
+/// # Safety
+///
+/// `planner` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_set_goal(
+    planner: *mut OpenrrNavPlanner,
+    goal: OpenrrNavPose,
+) -> i32 {
+    catch_panic(OPENRR_NAV_ERROR, || {
+        let Some(planner) = planner.as_mut() else {
+            set_last_error("planner is null");
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        };
+        planner.navigator.set_goal(Pose::from(goal));
+        OPENRR_NAV_OK
+    })
+}
+
+/// # Safety
+///
+/// `planner` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_cancel(planner: *mut OpenrrNavPlanner) -> i32 {
+    catch_panic(OPENRR_NAV_ERROR, || {
+        let Some(planner) = planner.as_mut() else {
+            set_last_error("planner is null");
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        };
+        planner.navigator.cancel();
+        OPENRR_NAV_OK
+    })
+}
+
+/// Run a control cycle with the pose in the map frame and the current velocity,
+/// and write the velocity command to `command`
+///
+/// # Safety
+///
+/// `planner` and `command` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_tick(
+    planner: *mut OpenrrNavPlanner,
+    pose: OpenrrNavPose,
+    velocity: OpenrrNavVelocity,
+    command: *mut OpenrrNavVelocity,
+) -> i32 {
+    catch_panic(OPENRR_NAV_ERROR, || {
+        let (Some(planner), Some(command)) = (planner.as_mut(), command.as_mut()) else {
+            set_last_error("planner or command is null");
+            return OPENRR_NAV_INVALID_ARGUMENT;
+        };
+        let velocity = Velocity {
+            x: velocity.x,
+            theta: velocity.theta,
+        };
+        let output = planner.navigator.tick(&pose.into(), &velocity).velocity();
+        *command = OpenrrNavVelocity {
+            x: output.x,
+            theta: output.theta,
+        };
+        OPENRR_NAV_OK
+    })
+}
+
+/// One of `OPENRR_NAV_STATE_*`, or -1 if `planner` is null
+///
+/// # Safety
+///
+/// `planner` must be valid.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_state(planner: *const OpenrrNavPlanner) -> i32 {
+    catch_panic(-1, || match planner.as_ref() {
+        Some(planner) => state_to_c(planner.navigator.state()),
+        None => -1,
+    })
+}
+
+/// Copy the global path to `poses` up to `capacity`, and return the length of the
+/// path
+///
+/// # Safety
+///
+/// `planner` must be valid and `poses` must be valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_global_path(
+    planner: *const OpenrrNavPlanner,
+    poses: *mut OpenrrNavPose,
+    capacity: usize,
+) -> usize {
+    catch_panic(0, || {
+        let Some(planner) = planner.as_ref() else {
+            return 0;
+        };
+        let path = planner
+            .navigator
+            .global_path()
+            .iter()
+            .map(|p| OpenrrNavPose {
+                x: p[0],
+                y: p[1],
+                theta: p[2],
+            });
+        copy_poses(path, poses, capacity)
+    })
+}
+
+/// Copy the path of the last local plan like [`openrr_nav_planner_global_path`]
+///
+/// # Safety
+///
+/// `planner` must be valid and `poses` must be valid for `capacity` writes.
+#[no_mangle]
+pub unsafe extern "C" fn openrr_nav_planner_local_path(
+    planner: *const OpenrrNavPlanner,
+    poses: *mut OpenrrNavPose,
+    capacity: usize,
+) -> usize {
+    catch_panic(0, || {
+        let Some(plan) = planner.as_ref().and_then(|p| p.navigator.last_plan()) else {
+            return 0;
+        };
+        copy_poses(plan.path.iter().map(OpenrrNavPose::from), poses, capacity)
+    })
+}
+
+/// Message of the last error on the calling thread, or null
+///
+/// The string is valid until the next call of the API on the thread.
+#[no_mangle]
+pub extern "C" fn openrr_nav_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planner() {
+        unsafe {
+            assert!(openrr_nav_planner_new(c"/nonexistent.yaml".as_ptr()).is_null());
+            assert!(!openrr_nav_last_error().is_null());
+
+            let path = CString::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../openrr-nav-core/config/turtlebot_dwa_config.yaml"
+            ))
+            .unwrap();
+            let planner = openrr_nav_planner_new(path.as_ptr());
+            assert!(!planner.is_null());
+            let (width, height) = (60, 40);
+            let data = vec![0i8; width * height];
+            assert_eq!(
+                openrr_nav_planner_set_map(planner, data.as_ptr(), width, height, 0.05, 0.0, 0.0),
+                OPENRR_NAV_OK
+            );
+            let goal = OpenrrNavPose {
+                x: 2.0,
+                y: 1.5,
+                theta: 0.0,
+            };
+            assert_eq!(openrr_nav_planner_set_goal(planner, goal), OPENRR_NAV_OK);
+
+            let dt = 0.1;
+            let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+            let mut velocity = OpenrrNavVelocity::default();
+            let mut poses = vec![];
+            for _ in 0..1000 {
+                let mut command = OpenrrNavVelocity::default();
+                assert_eq!(
+                    openrr_nav_planner_tick(planner, (&pose).into(), velocity, &mut command),
+                    OPENRR_NAV_OK
+                );
+                if poses.is_empty() {
+                    let len = openrr_nav_planner_global_path(planner, ptr::null_mut(), 0);
+                    poses.resize(len, OpenrrNavPose::default());
+                    assert_eq!(
+                        openrr_nav_planner_global_path(planner, poses.as_mut_ptr(), len),
+                        len
+                    );
+                }
+                if openrr_nav_planner_state(planner) == OPENRR_NAV_STATE_GOAL_REACHED {
+                    break;
+                }
+                velocity = command;
+                pose *= Pose::new(na::Vector2::new(velocity.x * dt, 0.0), velocity.theta * dt);
+            }
+            assert_eq!(
+                openrr_nav_planner_state(planner),
+                OPENRR_NAV_STATE_GOAL_REACHED
+            );
+            let last = poses.last().unwrap();
+            assert!((last.x - goal.x).abs() < 0.1 && (last.y - goal.y).abs() < 0.1);
+            openrr_nav_planner_free(planner);
+        }
+    }
+
+    #[test]
+    fn test_invalid_arguments() {
+        unsafe {
+            let path = CString::new(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../openrr-nav-core/config/turtlebot_dwa_config.yaml"
+            ))
+            .unwrap();
+            let planner = openrr_nav_planner_new(path.as_ptr());
+            let data = [0i8; 4];
+            // not read, since the size overflows
+            assert_eq!(
+                openrr_nav_planner_set_map(planner, data.as_ptr(), usize::MAX, 2, 0.05, 0.0, 0.0),
+                OPENRR_NAV_INVALID_ARGUMENT
+            );
+            assert!(CStr::from_ptr(openrr_nav_last_error())
+                .to_str()
+                .unwrap()
+                .contains("too large"));
+            openrr_nav_planner_free(planner);
+        }
+
+        assert_eq!(
+            catch_panic(OPENRR_NAV_ERROR, || panic!("broken {}", "map")),
+            OPENRR_NAV_ERROR
+        );
+        let message = unsafe { CStr::from_ptr(openrr_nav_last_error()) };
+        assert_eq!(message.to_str().unwrap(), "panicked: broken map");
+    }
+}
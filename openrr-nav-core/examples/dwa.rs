@@ -1,7 +1,6 @@
 use grid_map::*;
-use openrr_nav::utils::show_ascii_map;
-use openrr_nav::*;
-use rand::distributions::{Distribution, Uniform};
+use openrr_nav_core::utils::show_ascii_map;
+use openrr_nav_core::*;
 use std::collections::HashMap;
 
 fn new_sample_map() -> GridMap<u8> {
@@ -19,8 +18,7 @@ fn new_sample_map() -> GridMap<u8> {
 
 fn main() {
     let mut map = new_sample_map();
-    let x_range = Uniform::new(map.min_point().x, map.max_point().x);
-    let y_range = Uniform::new(map.min_point().y, map.max_point().y);
+    let sampler = PositionSampler::from_seed(&map, 0);
     let start = [-0.8, -0.9];
     let goal = [2.5, 0.5];
     let result = rrt::dual_rrt_connect(
@@ -32,14 +30,12 @@ fn main() {
                 Cell::Obstacle
             )
         },
-        || {
-            let mut rng = rand::thread_rng();
-            vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
-        },
+        || sampler.sample(),
         0.05,
         1000,
     )
     .unwrap();
+    let result = PathSmoother::default().smooth(&map, &result).unwrap();
 
     let path_grid = result
         .iter()
@@ -60,18 +56,15 @@ fn main() {
     let obstacle_distance_map = obstacle_distance_map(&map).unwrap();
     show_ascii_map(&obstacle_distance_map, 0.1);
     let mut maps = HashMap::new();
-    const PATH_DISTANCE_MAP_NAME: &str = "path";
-    const GOAL_DISTANCE_MAP_NAME: &str = "goal";
-    const OBSTACLE_DISTANCE_MAP_NAME: &str = "obstacle";
-    maps.insert(PATH_DISTANCE_MAP_NAME.to_owned(), path_distance_map);
-    maps.insert(GOAL_DISTANCE_MAP_NAME.to_owned(), goal_distance_map);
-    maps.insert(OBSTACLE_DISTANCE_MAP_NAME.to_owned(), obstacle_distance_map);
+    maps.insert(LayerId::PATH, path_distance_map);
+    maps.insert(LayerId::GOAL, goal_distance_map);
+    maps.insert(LayerId::OBSTACLE, obstacle_distance_map);
     let layered = LayeredGridMap::new(maps);
     let angles = HashMap::new();
     let mut weights = HashMap::new();
-    weights.insert(PATH_DISTANCE_MAP_NAME.to_owned(), 0.8);
-    weights.insert(GOAL_DISTANCE_MAP_NAME.to_owned(), 0.9);
-    weights.insert(OBSTACLE_DISTANCE_MAP_NAME.to_owned(), 0.3);
+    weights.insert(LayerId::PATH, 0.8);
+    weights.insert(LayerId::GOAL, 0.9);
+    weights.insert(LayerId::OBSTACLE, 0.3);
 
     let planner = DwaPlanner::new(
         Limits {
@@ -93,19 +86,25 @@ fn main() {
     );
 
     let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), 0.0);
+    let mut odometry = DeadReckoning::from_pose(current_pose);
     let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), 0.0);
     let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
     let mut plan_map = map.clone();
     for i in 0..100 {
         let plan = planner.plan_local_path(&current_pose, &current_velocity, &layered, &angles);
         println!("vel = {:?} cost = {}", current_velocity, plan.cost);
+        if let Some(issue) = plan.sampling_issue {
+            println!("sampling issue: {issue}");
+        }
         println!(
             "pose = {:?}, {}",
             current_pose.translation,
             current_pose.rotation.angle()
         );
         current_velocity = plan.velocity;
-        current_pose = plan.path[0];
+        current_pose = odometry
+            .integrate(&current_velocity, planner.controller_dt())
+            .pose;
         if let Some(grid) = plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
         {
             let _ = plan_map.set_value(&grid, 9);
@@ -0,0 +1,20 @@
+use grid_map::*;
+use openrr_nav_core::*;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+fn main() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    for size in [500, 1000, 2000, 4000] {
+        let length = size as f64 * 0.05 + 0.025;
+        let mut map =
+            GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(length, length), 0.05);
+        for _ in 0..size * 10 {
+            let grid = Grid::new(rng.gen_range(0..size), rng.gen_range(0..size));
+            map.set_obstacle(&grid).unwrap();
+        }
+        let now = Instant::now();
+        let _ = obstacle_distance_map_edt(&map).unwrap();
+        println!("{size}x{size}: {:?}", now.elapsed());
+    }
+}
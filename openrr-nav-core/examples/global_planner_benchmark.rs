@@ -0,0 +1,39 @@
+use grid_map::*;
+use openrr_nav_core::*;
+use std::time::Instant;
+
+fn new_open_map(size: usize) -> GridMap<u8> {
+    let length = size as f64 * 0.05 + 0.025;
+    let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(length, length), 0.05);
+    for cell in map.cells_mut() {
+        *cell = Cell::Value(0);
+    }
+    // A long wall which forces a detour, so that A* has to expand a large open area
+    for i in 0..size * 9 / 10 {
+        map.set_obstacle(&Grid::new(size / 2, i)).unwrap();
+    }
+    map
+}
+
+fn main() {
+    let size = 1000;
+    let map = new_open_map(size);
+    let start = Grid::new(1, 1);
+    let goal = Grid::new(size - 2, size - 2);
+    for algorithm in [
+        GridSearchAlgorithm::AStar,
+        GridSearchAlgorithm::JumpPointSearch,
+        GridSearchAlgorithm::ThetaStar,
+    ] {
+        let planner = AStarPlanner::default().with_algorithm(algorithm);
+        let now = Instant::now();
+        let result = planner.search(&map, &start, &goal).unwrap();
+        println!(
+            "{algorithm:?}: {:?}, cost = {:.2}, expanded = {}, path length = {}",
+            now.elapsed(),
+            result.cost,
+            result.num_expanded,
+            result.path.len()
+        );
+    }
+}
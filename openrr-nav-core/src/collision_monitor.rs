@@ -0,0 +1,220 @@
+use grid_map::GridMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{dwa_planner::velocity_to_pose, is_path_valid, Footprint, Pose, Velocity};
+
+/// Step (s) of the poses swept along the commanded velocity
+const SWEEP_STEP: f64 = 0.1;
+
+/// Area around the robot (in the robot frame) monitored by [`CollisionMonitor`]
+///
+/// ```yaml
+/// type: stop
+/// shape: {type: circle, radius: 0.3}
+/// ---
+/// type: slowdown
+/// shape: {type: circle, radius: 0.6}
+/// ratio: 0.3
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum CollisionZone {
+    /// Stop the robot if an obstacle is in the shape
+    Stop { shape: Footprint },
+    /// Scale the velocity by the ratio if an obstacle is in the shape
+    Slowdown { shape: Footprint, ratio: f64 },
+}
+
+impl CollisionZone {
+    pub fn shape(&self) -> &Footprint {
+        match self {
+            CollisionZone::Stop { shape } | CollisionZone::Slowdown { shape, .. } => shape,
+        }
+    }
+}
+
+/// Override of the command by [`CollisionMonitor::monitor`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MonitorAction {
+    /// The command is sent as it is
+    Pass,
+    /// `zone` is the index of [`CollisionMonitor::zones`] which has the obstacle
+    Slowdown {
+        zone: usize,
+        ratio: f64,
+    },
+    Stop {
+        zone: usize,
+    },
+}
+
+/// Safety net which overrides the velocity command independently of the planners
+///
+/// The obstacle cells of the map (e.g. the latest sensor costmap) in the zones
+/// around the robot stop or slow down the robot. If `time_horizon` is set, the
+/// zones are swept along the commanded velocity for the duration, so the robot
+/// is stopped before it gets close to the obstacle ahead. Use it just before
+/// sending the command to the base.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CollisionMonitor {
+    pub zones: Vec<CollisionZone>,
+    /// (s)
+    #[serde(default)]
+    pub time_horizon: f64,
+}
+
+impl CollisionMonitor {
+    pub fn new(zones: Vec<CollisionZone>, time_horizon: f64) -> Self {
+        Self {
+            zones,
+            time_horizon,
+        }
+    }
+
+    /// Poses of the robot within `time_horizon` with the velocity
+    fn swept_poses(&self, pose: &Pose, velocity: &Velocity) -> Vec<Pose> {
+        let steps = (self.time_horizon.max(0.0) / SWEEP_STEP).ceil() as usize;
+        let mut poses = vec![*pose];
+        if steps > 0 {
+            let diff = velocity_to_pose(velocity, self.time_horizon / steps as f64);
+            for _ in 0..steps {
+                poses.push(poses.last().unwrap() * diff);
+            }
+        }
+        poses
+    }
+
+    /// Check the command at the pose, and return the velocity to be sent
+    ///
+    /// Stop has priority over the slowdown, and the smallest ratio is used if the
+    /// obstacles are in multiple slowdown zones.
+    pub fn monitor(
+        &self,
+        map: &GridMap<u8>,
+        pose: &Pose,
+        velocity: &Velocity,
+    ) -> (Velocity, MonitorAction) {
+        let poses = self.swept_poses(pose, velocity);
+        let mut action = MonitorAction::Pass;
+        for (zone, collision_zone) in self.zones.iter().enumerate() {
+            if is_path_valid(&poses, map, collision_zone.shape()).is_ok() {
+                continue;
+            }
+            match (collision_zone, action) {
+                (CollisionZone::Stop { .. }, _) => {
+                    action = MonitorAction::Stop { zone };
+                    break;
+                }
+                (
+                    CollisionZone::Slowdown { ratio, .. },
+                    MonitorAction::Slowdown { ratio: r, .. },
+                ) if *ratio >= r => {}
+                (CollisionZone::Slowdown { ratio, .. }, _) => {
+                    action = MonitorAction::Slowdown {
+                        zone,
+                        ratio: ratio.clamp(0.0, 1.0),
+                    };
+                }
+            }
+        }
+        let velocity = match action {
+            MonitorAction::Pass => *velocity,
+            MonitorAction::Slowdown { ratio, .. } => Velocity {
+                x: velocity.x * ratio,
+                theta: velocity.theta * ratio,
+            },
+            MonitorAction::Stop { .. } => Velocity::default(),
+        };
+        (velocity, action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::{Grid, Position};
+    use nalgebra as na;
+
+    #[test]
+    fn test_collision_monitor() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 1.05), 0.05);
+        // wall at x = 2.0
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(40, y)).unwrap();
+        }
+        let monitor: CollisionMonitor = serde_yaml::from_str(
+            r#"
+zones:
+  - type: stop
+    shape: {type: circle, radius: 0.2}
+  - type: slowdown
+    shape: {type: circle, radius: 0.6}
+    ratio: 0.5
+  - type: slowdown
+    shape: {type: circle, radius: 0.4}
+    ratio: 0.2
+"#,
+        )
+        .unwrap();
+        let velocity = Velocity { x: 0.4, theta: 0.2 };
+        let at = |x: f64| Pose::new(na::Vector2::new(x, 0.5), 0.0);
+        assert_eq!(
+            monitor.monitor(&map, &at(1.0), &velocity),
+            (velocity, MonitorAction::Pass)
+        );
+        let (slow, action) = monitor.monitor(&map, &at(1.5), &velocity);
+        assert_eq!(
+            action,
+            MonitorAction::Slowdown {
+                zone: 1,
+                ratio: 0.5
+            }
+        );
+        assert!((slow.x - 0.2).abs() < 1e-9);
+        assert_eq!(
+            monitor.monitor(&map, &at(1.7), &velocity).1,
+            MonitorAction::Slowdown {
+                zone: 2,
+                ratio: 0.2
+            }
+        );
+        assert_eq!(
+            monitor.monitor(&map, &at(1.85), &velocity),
+            (Velocity::default(), MonitorAction::Stop { zone: 0 })
+        );
+
+        // the obstacle ahead within the time horizon
+        let monitor = CollisionMonitor::new(
+            vec![CollisionZone::Stop {
+                shape: Footprint::Circle { radius: 0.2 },
+            }],
+            1.0,
+        );
+        assert_eq!(
+            monitor
+                .monitor(&map, &at(1.4), &Velocity { x: 0.5, theta: 0.0 })
+                .1,
+            MonitorAction::Stop { zone: 0 }
+        );
+        assert_eq!(
+            monitor
+                .monitor(&map, &at(1.4), &Velocity { x: 0.2, theta: 0.0 })
+                .1,
+            MonitorAction::Pass
+        );
+        assert_eq!(
+            monitor
+                .monitor(
+                    &map,
+                    &at(1.4),
+                    &Velocity {
+                        x: -0.5,
+                        theta: 0.0
+                    }
+                )
+                .1,
+            MonitorAction::Pass
+        );
+    }
+}
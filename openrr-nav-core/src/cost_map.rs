@@ -0,0 +1,481 @@
+use grid_map::{Cell, Error, Grid, GridMap, Position, Result};
+
+use crate::utils::nearest_path_point;
+
+/// Create path distance map
+pub fn path_distance_map(map: &GridMap<u8>, path: &[Grid]) -> Result<GridMap<u8>> {
+    let mut path_distance_map = map.copy_without_value();
+    for ind in path {
+        path_distance_map
+            .set_value(ind, 0)
+            .ok_or(Error::OutOfRangeGrid(*ind))?;
+    }
+    expand_distance_map_internal(&mut path_distance_map, path, 0, |v| {
+        if v == u8::MAX {
+            u8::MAX
+        } else {
+            v + 1
+        }
+    });
+    Ok(path_distance_map)
+}
+
+/// Create goal distance map
+pub fn goal_distance_map(map: &GridMap<u8>, goal: &Grid) -> Result<GridMap<u8>> {
+    let mut goal_distance_map = map.copy_without_value();
+    goal_distance_map
+        .set_value(goal, 0)
+        .ok_or(Error::OutOfRangeGrid(*goal))?;
+    expand_distance_map_internal(&mut goal_distance_map, &[goal.to_owned()], 0, |v| {
+        if v == u8::MAX {
+            u8::MAX
+        } else {
+            v + 1
+        }
+    });
+    Ok(goal_distance_map)
+}
+
+/// Create obstacle distance map
+pub fn obstacle_distance_map(map: &GridMap<u8>) -> Result<GridMap<u8>> {
+    let mut distance_map = map.copy_without_value();
+    let mut obstacle_grid = vec![];
+    for y in 0..distance_map.height() {
+        for x in 0..distance_map.width() {
+            let grid = Grid { x, y };
+            if distance_map
+                .cell(&grid)
+                .ok_or(Error::OutOfRangeGrid(grid))?
+                .is_obstacle()
+            {
+                obstacle_grid.push(grid);
+            }
+        }
+    }
+    const REDUCE: u8 = 10;
+    expand_distance_map_internal(&mut distance_map, &obstacle_grid, 50, |v| {
+        v.saturating_sub(REDUCE)
+    });
+    Ok(distance_map)
+}
+
+/// Squared distance transform of a 1D sampled function (Felzenszwalb and Huttenlocher)
+///
+/// `f` is replaced by `min_q((p - q)^2 + f(q))`. `v` and `z` are working buffers.
+fn distance_transform_1d(f: &mut [f64], v: &mut Vec<usize>, z: &mut Vec<f64>, d: &mut Vec<f64>) {
+    let n = f.len();
+    v.clear();
+    z.clear();
+    d.clear();
+    for q in 0..n {
+        if f[q].is_infinite() {
+            continue;
+        }
+        // z[0] is -inf, so the first parabola is never removed
+        while let (Some(&r), Some(&last_z)) = (v.last(), z.last()) {
+            let s = ((f[q] + (q * q) as f64) - (f[r] + (r * r) as f64)) / (2 * (q - r)) as f64;
+            if s > last_z {
+                z.push(s);
+                break;
+            }
+            v.pop();
+            z.pop();
+        }
+        if v.is_empty() {
+            z.push(f64::NEG_INFINITY);
+        }
+        v.push(q);
+    }
+    if v.is_empty() {
+        return;
+    }
+    let mut k = 0;
+    for q in 0..n {
+        while k + 1 < z.len() && z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let diff = q as f64 - v[k] as f64;
+        d.push(diff * diff + f[v[k]]);
+    }
+    f.copy_from_slice(d);
+}
+
+/// Exact Euclidean distance (in meters) from the center of each cell to the center
+/// of the nearest obstacle cell
+///
+/// This is the linear time algorithm by Felzenszwalb and Huttenlocher, so it scales
+/// to large maps. Cells are `f64::INFINITY` if there is no obstacle in the map.
+pub fn euclidean_distance_transform(map: &GridMap<u8>) -> GridMap<f64> {
+    let (width, height) = (map.width(), map.height());
+    let mut squared = map
+        .cells()
+        .iter()
+        .map(|c| if c.is_obstacle() { 0.0 } else { f64::INFINITY })
+        .collect::<Vec<_>>();
+    let (mut v, mut z, mut d) = (vec![], vec![], vec![]);
+    let mut column = vec![0.0; height];
+    for x in 0..width {
+        for y in 0..height {
+            column[y] = squared[y * width + x];
+        }
+        distance_transform_1d(&mut column, &mut v, &mut z, &mut d);
+        for y in 0..height {
+            squared[y * width + x] = column[y];
+        }
+    }
+    for row in squared.chunks_mut(width.max(1)) {
+        distance_transform_1d(row, &mut v, &mut z, &mut d);
+    }
+    let mut distance_map = map.map_values(|_| 0.0);
+    for (cell, squared) in distance_map.cells_mut().iter_mut().zip(squared) {
+        *cell = Cell::Value(squared.sqrt() * map.resolution());
+    }
+    distance_map
+}
+
+/// Create obstacle distance map using the exact Euclidean distance transform
+///
+/// The cost is the same as [`obstacle_distance_map`] (50 at the obstacle, decreasing
+/// by 10 per cell), but the distance is Euclidean instead of 4-connected propagation.
+pub fn obstacle_distance_map_edt(map: &GridMap<u8>) -> Result<GridMap<u8>> {
+    obstacle_distance_cost(map, &euclidean_distance_transform(map))
+}
+
+/// Cost of [`obstacle_distance_map_edt`] from the distances (m) to the obstacles
+pub(crate) fn obstacle_distance_cost(
+    map: &GridMap<u8>,
+    distances: &GridMap<f64>,
+) -> Result<GridMap<u8>> {
+    const MAX_COST: f64 = 50.0;
+    const REDUCE: f64 = 10.0;
+    let mut distance_map = map.copy_without_value();
+    for (cell, distance) in distance_map.cells_mut().iter_mut().zip(distances.cells()) {
+        if !cell.is_uninitialized() {
+            continue;
+        }
+        let distance = distance
+            .value()
+            .ok_or_else(|| Error::Other("invalid distance".to_owned()))?
+            / map.resolution();
+        *cell = Cell::Value((MAX_COST - REDUCE * distance).max(0.0).round() as u8);
+    }
+    Ok(distance_map)
+}
+
+/// Cost of the cells inside of the inscribed radius of the robot
+pub const INSCRIBED_INFLATED_COST: u8 = 253;
+
+/// Create inflation layer like `costmap_2d` of ROS
+///
+/// Obstacles are kept as they are, cells within `inscribed_radius` become
+/// [`INSCRIBED_INFLATED_COST`] and the cost decays exponentially by `cost_scaling`
+/// until `inflation_radius`. Cells farther than `inflation_radius` are 0.
+pub fn inflate_obstacles(
+    map: &GridMap<u8>,
+    inscribed_radius: f64,
+    inflation_radius: f64,
+    cost_scaling: f64,
+) -> Result<GridMap<u8>> {
+    if inscribed_radius < 0.0 || inflation_radius < inscribed_radius || cost_scaling < 0.0 {
+        return Err(Error::Other(format!(
+            "invalid inflation parameters: inscribed_radius = {inscribed_radius}, \
+             inflation_radius = {inflation_radius}, cost_scaling = {cost_scaling}"
+        )));
+    }
+    let distances = euclidean_distance_transform(map);
+    let mut inflation_map = map.copy_without_value();
+    for (cell, distance) in inflation_map.cells_mut().iter_mut().zip(distances.cells()) {
+        if !cell.is_uninitialized() {
+            continue;
+        }
+        let distance = *distance
+            .value()
+            .ok_or_else(|| Error::Other("invalid distance".to_owned()))?;
+        let cost = if distance <= inscribed_radius {
+            INSCRIBED_INFLATED_COST
+        } else if distance <= inflation_radius {
+            let factor = (-cost_scaling * (distance - inscribed_radius)).exp();
+            ((INSCRIBED_INFLATED_COST - 1) as f64 * factor).round() as u8
+        } else {
+            0
+        };
+        *cell = Cell::Value(cost);
+    }
+    Ok(inflation_map)
+}
+
+/// Create cost layer which repels the robot from the border of the map and the
+/// boundary of the unexplored (Unknown) region
+///
+/// The cost is `max_cost` next to the border or the unknown cells and decreases
+/// linearly until `falloff_distance` (m), so the robot doesn't hug the edge of the
+/// known space. Obstacle and Unknown cells are kept as they are.
+pub fn edge_cost_map(
+    map: &GridMap<u8>,
+    max_cost: u8,
+    falloff_distance: f64,
+) -> Result<GridMap<u8>> {
+    if falloff_distance.is_nan() || falloff_distance <= 0.0 {
+        return Err(Error::Other(format!(
+            "falloff_distance ({falloff_distance}) must be positive"
+        )));
+    }
+    let mut unknown = map.map_values(|_| 0);
+    for cell in unknown.cells_mut() {
+        *cell = if matches!(cell, Cell::Unknown) {
+            Cell::Obstacle
+        } else {
+            Cell::Value(0)
+        };
+    }
+    let distances = euclidean_distance_transform(&unknown);
+    let (width, height) = (map.width(), map.height());
+    let resolution = map.resolution();
+    let mut edge_map = map.copy_without_value();
+    for (i, (cell, distance)) in edge_map
+        .cells_mut()
+        .iter_mut()
+        .zip(distances.cells())
+        .enumerate()
+    {
+        if !cell.is_uninitialized() {
+            continue;
+        }
+        let (x, y) = (i % width, i / width);
+        // from the center of the cell to the border
+        let border =
+            x.min(width - 1 - x).min(y).min(height - 1 - y) as f64 * resolution + resolution * 0.5;
+        let distance = distance
+            .value()
+            .ok_or_else(|| Error::Other("invalid distance".to_owned()))?
+            .min(border);
+        let cost = max_cost as f64 * (1.0 - distance / falloff_distance).max(0.0);
+        *cell = Cell::Value(cost.round() as u8);
+    }
+    Ok(edge_map)
+}
+
+/// Create local goal distance map
+pub fn local_goal_distance_map(
+    map: &GridMap<u8>,
+    global_path: &[Vec<f64>],
+    current_pose: [f64; 2],
+) -> Result<GridMap<u8>> {
+    let len = global_path.len();
+    let nearest = nearest_path_point(global_path, current_pose)
+        .ok_or_else(|| Error::Other("empty global path".to_owned()))?;
+
+    const LOCAL_GOAL_FORWARD_OFFSET: usize = 20;
+    let local_goal = global_path[(nearest.0 + LOCAL_GOAL_FORWARD_OFFSET).min(len - 1)].clone();
+
+    let local_width = (2. * (local_goal[0] - current_pose[0]).abs()).max(1.);
+    let local_height = (2. * (local_goal[1] - current_pose[1]).abs()).max(1.);
+
+    // margin of 1.5 cells, since the size of the map is floored
+    let margin = map.resolution() * 1.5;
+
+    let min_point = Position::new(
+        current_pose[0] - local_width * 0.5 - margin,
+        current_pose[1] - local_height * 0.5 - margin,
+    );
+    let max_point = Position::new(
+        current_pose[0] + local_width * 0.5 + margin,
+        current_pose[1] + local_height * 0.5 + margin,
+    );
+
+    let local_map = GridMap::<u8>::new(min_point, max_point, map.resolution());
+    let grid = local_map
+        .to_grid(local_goal[0], local_goal[1])
+        .ok_or(Error::OutOfRangePosition(local_goal[0], local_goal[1]))?;
+
+    goal_distance_map(&local_map, &grid)
+}
+
+pub fn expand_distance_map_internal<F>(
+    map: &mut GridMap<u8>,
+    previous_grids: &[Grid],
+    previous_value: u8,
+    increment_func: F,
+) -> bool
+where
+    F: Fn(u8) -> u8,
+{
+    if !map.cells().iter().any(|c| c.is_uninitialized()) || previous_grids.is_empty() {
+        return true;
+    }
+    let current_value = increment_func(previous_value);
+    let mut current_grid = vec![];
+    for ind in previous_grids {
+        // search +/-
+        if map.cell(ind).is_none() {
+            continue;
+        }
+        for neighbor in ind.neighbors4() {
+            if let Some(cell) = map.cell_mut(&neighbor) {
+                if !cell.is_uninitialized() {
+                    continue;
+                }
+                *cell = Cell::Value(current_value);
+                current_grid.push(neighbor);
+            }
+        }
+    }
+    expand_distance_map_internal(map, &current_grid, current_value, increment_func)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::show_ascii_map;
+    use crate::*;
+    use grid_map::*;
+
+    #[test]
+    fn euclidean_distance_transform_test() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.1);
+        let mut obstacles = vec![];
+        for _ in 0..15 {
+            let grid = Grid::new(
+                rng.gen_range(0..map.width()),
+                rng.gen_range(0..map.height()),
+            );
+            map.set_obstacle(&grid).unwrap();
+            obstacles.push(grid);
+        }
+        let distances = euclidean_distance_transform(&map);
+        let mut expected = distances.clone();
+        for y in 0..map.height() {
+            for x in 0..map.width() {
+                let distance = obstacles
+                    .iter()
+                    .map(|o| {
+                        ((o.x as f64 - x as f64).powi(2) + (o.y as f64 - y as f64).powi(2)).sqrt()
+                    })
+                    .fold(f64::INFINITY, f64::min)
+                    * 0.1;
+                expected.set_value(&Grid::new(x, y), distance).unwrap();
+            }
+        }
+        grid_map::testkit::assert_maps_close(&distances, &expected, 1e-9);
+
+        let cost_map = obstacle_distance_map_edt(&map).unwrap();
+        let o = obstacles[0];
+        assert!(cost_map.cell(&o).unwrap().is_obstacle());
+        if o.x + 1 < map.width() && !map.cell(&Grid::new(o.x + 1, o.y)).unwrap().is_obstacle() {
+            assert_eq!(cost_map.value(&Grid::new(o.x + 1, o.y)), Some(40));
+        }
+        assert!(euclidean_distance_transform(&map.copy_without_value())
+            .cells()
+            .iter()
+            .all(|c| c.value().is_some()));
+
+        let sub_map = map.sub_map(&Grid::new(2, 1), &Grid::new(20, 19)).unwrap();
+        let distances = euclidean_distance_transform(&sub_map);
+        assert_eq!((distances.width(), distances.height()), (19, 19));
+        assert_eq!(distances.min_point(), sub_map.min_point());
+        assert_eq!(distances.cells().len(), sub_map.cells().len());
+    }
+    #[test]
+    fn inflate_obstacles_test() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        map.fill(Cell::Value(0));
+        map.set_obstacle(&Grid::new(10, 5)).unwrap();
+        let inflated = inflate_obstacles(&map, 0.2, 0.5, 3.0).unwrap();
+        assert!(inflated.cell(&Grid::new(10, 5)).unwrap().is_obstacle());
+        assert_eq!(
+            inflated.value(&Grid::new(12, 5)),
+            Some(INSCRIBED_INFLATED_COST)
+        );
+        let cost_3 = inflated.value(&Grid::new(13, 5)).unwrap();
+        let cost_4 = inflated.value(&Grid::new(14, 5)).unwrap();
+        assert_eq!(cost_3, (252.0 * (-0.3_f64).exp()).round() as u8);
+        assert!(cost_3 > cost_4 && cost_4 > 0);
+        assert_eq!(inflated.value(&Grid::new(16, 5)), Some(0));
+        assert!(inflate_obstacles(&map, 0.5, 0.2, 3.0).is_err());
+    }
+
+    #[test]
+    fn edge_cost_map_test() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        map.fill(Cell::Value(0));
+        for y in 0..map.height() {
+            *map.cell_mut(&Grid::new(15, y)).unwrap() = Cell::Unknown;
+        }
+        map.set_obstacle(&Grid::new(5, 5)).unwrap();
+        let edge = edge_cost_map(&map, 120, 0.3).unwrap();
+        // half a cell from the border
+        assert_eq!(edge.value(&Grid::new(0, 5)), Some(100));
+        assert_eq!(edge.value(&Grid::new(2, 5)), Some(20));
+        assert_eq!(edge.value(&Grid::new(7, 5)), Some(0));
+        // the next to unknown cells
+        assert_eq!(edge.value(&Grid::new(14, 5)), Some(80));
+        assert_eq!(edge.value(&Grid::new(16, 5)), Some(80));
+        assert_eq!(edge.cell(&Grid::new(15, 5)), Some(&Cell::Unknown));
+        assert_eq!(edge.cell(&Grid::new(5, 5)), Some(&Cell::Obstacle));
+        assert!(edge_cost_map(&map, 100, 0.0).is_err());
+    }
+
+    #[test]
+    fn local_goal_distance_map_test() {
+        let map = GridMap::<u8>::new(Position::new(-2.05, -2.05), Position::new(2.05, 2.05), 0.1);
+        // the local goal on the border of the local map, which is out of the floored
+        // map without the margin
+        for goal in [[0.55, 0.0], [-0.55, 0.0], [0.0, 0.55], [0.55, -0.55]] {
+            let path = (0..=10)
+                .map(|i| vec![goal[0] * i as f64 / 10.0, goal[1] * i as f64 / 10.0, 0.0])
+                .collect::<Vec<_>>();
+            let local_map = local_goal_distance_map(&map, &path, [0.0, 0.0]).unwrap();
+            let grid = local_map.to_grid(goal[0], goal[1]).unwrap();
+            assert_eq!(local_map.value(&grid), Some(0));
+        }
+        assert!(local_goal_distance_map(&map, &[], [0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn path_distance_map_test() {
+        use rrt;
+        let mut map = grid_map::GridMap::<u8>::new(
+            Position::new(-1.05, -1.05),
+            Position::new(3.05, 1.05),
+            0.1,
+        );
+        for i in 0..20 {
+            map.set_obstacle(&map.to_grid(0.2 + 0.1 * i as f64, -0.5).unwrap());
+            for j in 0..10 {
+                map.set_obstacle(&map.to_grid(0.1 * i as f64, -0.2 + 0.1 * j as f64).unwrap());
+            }
+        }
+        let sampler = PositionSampler::from_seed(&map, 0);
+        let goal = [2.5, 0.5];
+        let result = rrt::dual_rrt_connect(
+            &[0.5, -0.8],
+            &goal,
+            |p: &[f64]| {
+                !matches!(
+                    map.cell(&map.to_grid(p[0], p[1]).unwrap()).unwrap(),
+                    Cell::Obstacle
+                )
+            },
+            || sampler.sample(),
+            0.05,
+            1000,
+        )
+        .unwrap();
+
+        let path_grid = result
+            .iter()
+            .map(|p| map.to_grid(p[0], p[1]).unwrap())
+            .collect::<Vec<_>>();
+        for p in result {
+            map.set_value(&map.to_grid(p[0], p[1]).unwrap(), 0).unwrap();
+        }
+
+        show_ascii_map(&path_distance_map(&map, &path_grid).unwrap(), 1.0);
+        println!("=======================");
+        let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
+        show_ascii_map(&goal_distance_map(&map, &goal_grid).unwrap(), 1.0);
+        println!("=======================");
+        show_ascii_map(&obstacle_distance_map(&map).unwrap(), 0.1);
+    }
+}
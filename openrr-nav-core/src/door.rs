@@ -0,0 +1,206 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::{polygon_contains, AStarPlanner, Result};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DoorState {
+    Open,
+    /// Traversable only after the door is opened
+    #[default]
+    Closed,
+}
+
+/// Openable obstacle in the map coordinates
+///
+/// ```yaml
+/// - name: lab
+///   state: closed
+///   polygon: [{x: 1.0, y: 0.0}, {x: 1.2, y: 0.0}, {x: 1.2, y: 1.0}, {x: 1.0, y: 1.0}]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Door {
+    pub name: String,
+    pub polygon: Vec<Position>,
+    #[serde(default)]
+    pub state: DoorState,
+}
+
+/// Request to open the door on the way
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoorAction {
+    pub door: String,
+    /// Index of the waypoint in front of the door, where the robot waits for it to open
+    pub waypoint: usize,
+}
+
+/// Path which may pass through the closed doors
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DoorPlan {
+    pub path: Vec<Grid>,
+    /// Doors to open, in the order along the path
+    pub actions: Vec<DoorAction>,
+}
+
+/// Doors of the map, whose cells are obstacles in the static map
+///
+/// Open doors are free. Closed doors are avoided if there is another path, otherwise
+/// the path goes through them with [`DoorAction`]s.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DoorLayer {
+    doors: Vec<Door>,
+}
+
+impl DoorLayer {
+    pub fn new(doors: Vec<Door>) -> Self {
+        Self { doors }
+    }
+
+    pub fn doors(&self) -> &[Door] {
+        &self.doors
+    }
+
+    pub fn add_door(&mut self, name: impl Into<String>, polygon: Vec<Position>, state: DoorState) {
+        self.doors.push(Door {
+            name: name.into(),
+            polygon,
+            state,
+        });
+    }
+
+    /// Update the state of the door, returns false if there is no such door
+    pub fn set_state(&mut self, name: &str, state: DoorState) -> bool {
+        match self.doors.iter_mut().find(|d| d.name == name) {
+            Some(door) => {
+                door.state = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Door whose center of the cell is inside
+    fn door_at(&self, position: &Position) -> Option<&Door> {
+        self.doors
+            .iter()
+            .find(|d| polygon_contains(&d.polygon, position))
+    }
+
+    /// Layer of the same geometry as the map with the index of the door of each cell
+    fn door_indices<T: Clone>(&self, map: &GridMap<T>) -> Vec<Option<usize>> {
+        map.enumerate_cells()
+            .map(|(_, center, _)| {
+                self.doors
+                    .iter()
+                    .position(|d| polygon_contains(&d.polygon, &center))
+            })
+            .collect()
+    }
+
+    /// Map where the cells of the doors are free (`Cell::Value(0)`) if they are open
+    /// or `closed` is true, and obstacles otherwise
+    pub fn apply(&self, map: &GridMap<u8>, closed: bool) -> GridMap<u8> {
+        let mut map = map.clone();
+        let indices = self.door_indices(&map);
+        for (cell, index) in map.cells_mut().iter_mut().zip(indices) {
+            if let Some(index) = index {
+                *cell = if closed || self.doors[index].state == DoorState::Open {
+                    Cell::Value(0)
+                } else {
+                    Cell::Obstacle
+                };
+            }
+        }
+        map
+    }
+
+    /// Whether the position is in a closed door
+    pub fn is_closed_at(&self, position: &Position) -> bool {
+        self.door_at(position)
+            .is_some_and(|d| d.state == DoorState::Closed)
+    }
+
+    /// Plan the path avoiding the closed doors, or through them if there is no other path
+    pub fn plan(
+        &self,
+        planner: &AStarPlanner,
+        map: &GridMap<u8>,
+        start: &Grid,
+        goal: &Grid,
+    ) -> Result<DoorPlan> {
+        if let Ok(path) = planner.plan(&self.apply(map, false), start, goal) {
+            return Ok(DoorPlan {
+                path,
+                actions: vec![],
+            });
+        }
+        let path = planner.plan(&self.apply(map, true), start, goal)?;
+        let width = map.width();
+        let indices = self.door_indices(map);
+        let mut actions: Vec<DoorAction> = vec![];
+        let mut previous = None;
+        for (i, grid) in path.iter().enumerate() {
+            let door = indices[grid.y * width + grid.x];
+            if let Some(index) = door.filter(|&d| self.doors[d].state == DoorState::Closed) {
+                if previous != Some(index) {
+                    actions.push(DoorAction {
+                        door: self.doors[index].name.clone(),
+                        waypoint: i.saturating_sub(1),
+                    });
+                }
+            }
+            previous = door;
+        }
+        Ok(DoorPlan { path, actions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_door_plan() {
+        // wall at x = 1.0 with the doors at y = 0.1 and y = 0.9
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(10, y)).unwrap();
+        }
+        let door = |y: f64| {
+            vec![
+                Position::new(1.0, y - 0.05),
+                Position::new(1.1, y - 0.05),
+                Position::new(1.1, y + 0.05),
+                Position::new(1.0, y + 0.05),
+            ]
+        };
+        let mut doors = DoorLayer::default();
+        doors.add_door("lower", door(0.15), DoorState::Closed);
+        doors.add_door("upper", door(0.85), DoorState::Closed);
+        assert!(doors.is_closed_at(&Position::new(1.05, 0.15)));
+        let planner = AStarPlanner::default();
+        let (start, goal) = (Grid::new(2, 1), Grid::new(18, 1));
+        assert!(planner.plan(&map, &start, &goal).is_err());
+
+        // through the nearest closed door
+        let plan = doors.plan(&planner, &map, &start, &goal).unwrap();
+        assert_eq!(
+            plan.actions,
+            vec![DoorAction {
+                door: "lower".to_owned(),
+                waypoint: 7
+            }]
+        );
+        assert_eq!(plan.path[8], Grid::new(10, 1));
+
+        // the open door is preferred even if it is longer
+        assert!(doors.set_state("upper", DoorState::Open));
+        assert!(!doors.set_state("kitchen", DoorState::Open));
+        let plan = doors.plan(&planner, &map, &start, &goal).unwrap();
+        assert!(plan.actions.is_empty());
+        assert!(plan.path.contains(&Grid::new(10, 8)));
+    }
+}
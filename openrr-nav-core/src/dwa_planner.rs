@@ -0,0 +1,1190 @@
+use grid_map::{Cell, GridMap, LayerId, LayeredGridMap, Position};
+pub use na::Vector2;
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{Error, PredictedOccupancy, ZoneLayer};
+
+pub(crate) mod serde_cost_name_weight;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, from = "[f64; 2]", into = "[f64; 2]")]
+pub struct Velocity {
+    pub x: f64,
+    pub theta: f64,
+}
+
+impl From<[f64; 2]> for Velocity {
+    fn from(value: [f64; 2]) -> Self {
+        Self {
+            x: value[0],
+            theta: value[1],
+        }
+    }
+}
+
+impl From<Velocity> for [f64; 2] {
+    fn from(value: Velocity) -> Self {
+        [value.x, value.theta]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields, from = "[f64; 2]", into = "[f64; 2]")]
+pub struct Acceleration {
+    pub x: f64,
+    pub theta: f64,
+}
+
+impl From<[f64; 2]> for Acceleration {
+    fn from(value: [f64; 2]) -> Self {
+        Self {
+            x: value[0],
+            theta: value[1],
+        }
+    }
+}
+
+impl From<Acceleration> for [f64; 2] {
+    fn from(value: Acceleration) -> Self {
+        [value.x, value.theta]
+    }
+}
+
+pub type Pose = na::Isometry2<f64>;
+
+pub(crate) fn velocity_to_pose(velocity: &Velocity, dt: f64) -> Pose {
+    Pose::new(na::Vector2::new(velocity.x * dt, 0.0), velocity.theta * dt)
+}
+
+/// Quantization step for the trajectory cache keys
+const TRAJECTORY_CACHE_QUANTUM: f64 = 1e-4;
+/// The cache is cleared when it holds more trajectories than this
+const TRAJECTORY_CACHE_CAPACITY: usize = 4096;
+
+type TrajectoryCacheKey = [i64; 4];
+
+/// Cache of forward simulated trajectories relative to the identity pose.
+///
+/// The velocity window moves slowly between control cycles, so most candidates are
+/// simulated with the same velocity again and again. The cached relative poses are
+/// transformed by the current pose instead of being simulated again.
+#[derive(Debug, Default)]
+struct TrajectoryCache(Mutex<HashMap<TrajectoryCacheKey, Arc<Vec<Pose>>>>);
+
+impl Clone for TrajectoryCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl TrajectoryCache {
+    fn key(velocity: &Velocity, dt: f64, duration: f64) -> TrajectoryCacheKey {
+        let quantize = |v: f64| (v / TRAJECTORY_CACHE_QUANTUM).round() as i64;
+        [
+            quantize(velocity.x),
+            quantize(velocity.theta),
+            quantize(dt),
+            quantize(duration),
+        ]
+    }
+
+    fn get_or_insert_with<F>(&self, key: TrajectoryCacheKey, f: F) -> Arc<Vec<Pose>>
+    where
+        F: FnOnce() -> Vec<Pose>,
+    {
+        let mut cache = self.0.lock().unwrap();
+        if let Some(poses) = cache.get(&key) {
+            return poses.clone();
+        }
+        if cache.len() >= TRAJECTORY_CACHE_CAPACITY {
+            cache.clear();
+        }
+        let poses = Arc::new(f());
+        cache.insert(key, poses.clone());
+        poses
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+}
+
+fn simulate_relative_poses(velocity: &Velocity, dt: f64, duration: f64) -> Vec<Pose> {
+    let mut last_pose = Pose::identity();
+    let diff = velocity_to_pose(velocity, dt);
+    let mut poses = vec![];
+    for _ in 0..(duration / dt) as usize {
+        let next_pose = last_pose * diff;
+        poses.push(next_pose);
+        last_pose = next_pose;
+    }
+    poses
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    pub velocity: Velocity,
+    pub cost: f64,
+    pub path: Vec<Pose>,
+    /// Set if the dynamic window was degenerate when the plan was sampled
+    pub sampling_issue: Option<SamplingIssue>,
+}
+
+/// Degenerate situation of the dynamic window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SamplingIssue {
+    /// The current velocity is out of the velocity limits, e.g. the robot was pushed.
+    /// The window is computed from the velocity clamped into the limits.
+    VelocityOutOfLimits { velocity: Velocity },
+    /// The window has no width on both axes, so all candidates have the same velocity.
+    /// This happens when the acceleration limits are zero or inverted.
+    CollapsedWindow,
+}
+
+impl std::fmt::Display for SamplingIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::VelocityOutOfLimits { velocity } => write!(
+                f,
+                "current velocity ({}, {}) is out of the limits",
+                velocity.x, velocity.theta
+            ),
+            Self::CollapsedWindow => write!(f, "dynamic window collapsed"),
+        }
+    }
+}
+
+/// Range of the velocities reachable in the next control cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicWindow {
+    pub min: Velocity,
+    pub max: Velocity,
+    pub issue: Option<SamplingIssue>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// Velocity and acceleration limitations of the robot
+pub struct Limits {
+    /// plus limit of the velocity
+    pub max_velocity: Velocity,
+    #[serde(rename = "max_acceleration")]
+    /// plus limit of the acceleration
+    pub max_accel: Acceleration,
+    /// minus limit of the velocity (like -0.5)
+    pub min_velocity: Velocity,
+    #[serde(rename = "min_acceleration")]
+    /// minus limit of the acceleration (like -1.0)
+    pub min_accel: Acceleration,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+/// DWA Planner
+pub struct DwaPlanner {
+    limits: Limits,
+    #[serde(with = "serde_cost_name_weight")]
+    cost_name_weight: HashMap<LayerId, f64>,
+    controller_dt: f64,
+    simulation_duration: f64,
+    num_vel_sample: i32,
+    /// Keep-out and speed limit zones. Only the speed limits are used by the planner.
+    #[serde(default, skip_serializing_if = "ZoneLayer::is_empty")]
+    zones: ZoneLayer,
+    #[serde(skip)]
+    trajectory_cache: TrajectoryCache,
+}
+
+/// Parameters of [`DwaPlanner`] other than the weights and the zones, which can be
+/// changed at runtime by [`DwaPlanner::set_parameters`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DwaParameters {
+    pub limits: Limits,
+    /// (s)
+    pub controller_dt: f64,
+    /// Horizon of the forward simulation (s)
+    pub simulation_duration: f64,
+    /// Number of the samples of each velocity
+    pub num_vel_sample: i32,
+}
+
+impl DwaParameters {
+    /// Check that the limits are ordered, the accelerations have the signs, the
+    /// horizon is at least a cycle and there are samples
+    pub fn validate(&self) -> Result<(), Error> {
+        let Limits {
+            max_velocity,
+            max_accel,
+            min_velocity,
+            min_accel,
+        } = &self.limits;
+        let mut errors = vec![];
+        if min_velocity.x > max_velocity.x || min_velocity.theta > max_velocity.theta {
+            errors.push("min_velocity must not be greater than max_velocity".to_owned());
+        }
+        if max_accel.x <= 0.0 || max_accel.theta <= 0.0 {
+            errors.push("max_acceleration must be positive".to_owned());
+        }
+        if min_accel.x >= 0.0 || min_accel.theta >= 0.0 {
+            errors.push("min_acceleration must be negative".to_owned());
+        }
+        if self.controller_dt <= 0.0 {
+            errors.push("controller_dt must be positive".to_owned());
+        }
+        if self.simulation_duration < self.controller_dt {
+            errors.push("simulation_duration must not be less than controller_dt".to_owned());
+        }
+        if self.num_vel_sample < 1 {
+            errors.push("num_vel_sample must be at least 1".to_owned());
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::Other(errors.join(", ")))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DwaPlannerConfig {
+    #[serde(rename = "DwaPlanner")]
+    dwa_planner: DwaPlanner,
+}
+
+/// Cost of the Obstacle and Unknown cells
+pub const LETHAL_COST: f64 = 255.0;
+
+/// Cost of the cell used by the planner. `None` for Uninitialized cells.
+pub(crate) fn cell_cost(cell: &Cell<u8>) -> Option<f64> {
+    match cell {
+        Cell::Value(v) => Some(*v as f64),
+        Cell::Uninitialized => None,
+        // TODO: Support allow Unknown
+        Cell::Obstacle | Cell::Unknown => Some(LETHAL_COST),
+    }
+}
+
+fn accumulate_values_by_positions(map: &GridMap<u8>, positions: &[Position]) -> f64 {
+    if positions.is_empty() {
+        return f64::MAX;
+    }
+    let mut cost: f64 = 0.0;
+    for p in positions {
+        if let Some(grid) = map.world_to_grid(p) {
+            if let Some(cell) = map.cell(&grid) {
+                match cell_cost(cell) {
+                    Some(c) => cost += c,
+                    // not reachable, e.g. behind the wall in the goal layer
+                    None => return f64::MAX,
+                }
+            } else {
+                // out of grid (should not happen)
+                return f64::MAX;
+            }
+        } else {
+            // out of grid
+            return f64::MAX;
+        }
+    }
+    cost
+}
+
+/// Contribution of a layer to the cost of a position
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerCost {
+    pub name: LayerId,
+    /// Cell of the layer at the position. `None` if the position is out of the layer.
+    pub cell: Option<Cell<u8>>,
+    /// Raw cost of the cell. `None` if it is out of the layer or Uninitialized.
+    pub cost: Option<f64>,
+    pub weight: f64,
+}
+
+impl LayerCost {
+    /// Weighted cost which is added to the total cost
+    pub fn weighted_cost(&self) -> Option<f64> {
+        self.cost.map(|c| c * self.weight)
+    }
+
+    pub fn is_lethal(&self) -> bool {
+        self.cost.is_some_and(|c| c >= LETHAL_COST)
+    }
+}
+
+/// Breakdown of the cost of a position by layer
+#[derive(Debug, Clone, PartialEq)]
+pub struct CostReport {
+    pub position: Position,
+    pub layers: Vec<LayerCost>,
+}
+
+impl CostReport {
+    /// Sum of the weighted costs, `None` if any layer can't be evaluated
+    pub fn total(&self) -> Option<f64> {
+        self.layers.iter().map(|l| l.weighted_cost()).sum()
+    }
+
+    /// Layers which make the position lethal
+    pub fn lethal_layers(&self) -> impl Iterator<Item = &LayerCost> {
+        self.layers.iter().filter(|l| l.is_lethal())
+    }
+}
+
+impl std::fmt::Display for CostReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "cost at ({}, {})", self.position.x, self.position.y)?;
+        for layer in &self.layers {
+            let cell = match &layer.cell {
+                Some(cell) => format!("{cell:?}"),
+                None => "out of map".to_owned(),
+            };
+            let weighted = match layer.weighted_cost() {
+                Some(c) => format!("{c:.3}"),
+                None => "-".to_owned(),
+            };
+            writeln!(
+                f,
+                "  {:<16} {:<16} x {:<6} = {:>10}{}",
+                layer.name,
+                cell,
+                layer.weight,
+                weighted,
+                if layer.is_lethal() { "  LETHAL" } else { "" }
+            )?;
+        }
+        match self.total() {
+            Some(total) => write!(f, "  total = {total:.3}"),
+            None => write!(f, "  total = not available"),
+        }
+    }
+}
+
+impl DwaPlanner {
+    pub fn new(
+        limits: Limits,
+        cost_name_weight: HashMap<LayerId, f64>,
+        controller_dt: f64,
+        simulation_duration: f64,
+        num_vel_sample: i32,
+    ) -> Self {
+        Self {
+            limits,
+            cost_name_weight,
+            controller_dt,
+            simulation_duration,
+            num_vel_sample,
+            zones: ZoneLayer::default(),
+            trajectory_cache: TrajectoryCache::default(),
+        }
+    }
+
+    pub fn new_from_config(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let source = fs::read_to_string(path).unwrap();
+        Self::new_from_config_text(&source)
+    }
+
+    pub fn new_from_config_text(source: &str) -> Result<Self, Error> {
+        use serde_yaml::from_str;
+        let config: DwaPlannerConfig = from_str(source).map_err(grid_map::Error::from)?;
+        Ok(config.dwa_planner)
+    }
+
+    /// Dynamic window of the current velocity
+    ///
+    /// If the current velocity is out of the limits, it is clamped into the limits
+    /// before applying the accelerations so that the window always has the full width
+    /// inside of the limits and the planner can recover, instead of collapsing to a
+    /// single velocity at the limit.
+    pub fn dynamic_window(&self, current_velocity: &Velocity) -> DynamicWindow {
+        self.dynamic_window_with_limits(current_velocity, &self.limits)
+    }
+
+    /// Dynamic window at the pose, where `max_velocity.x` is capped by the speed
+    /// limit zone containing the pose
+    ///
+    /// If the robot is faster than the zone allows, the velocity is clamped to the
+    /// limit of the zone in the same way as [`DwaPlanner::dynamic_window`].
+    pub fn dynamic_window_at(&self, pose: &Pose, current_velocity: &Velocity) -> DynamicWindow {
+        match self.zones.speed_limit_at_pose(pose) {
+            Some(limit) => {
+                let mut limits = self.limits.clone();
+                limits.max_velocity.x = limits.max_velocity.x.min(limit);
+                limits.min_velocity.x = limits.min_velocity.x.max(-limit);
+                self.dynamic_window_with_limits(current_velocity, &limits)
+            }
+            None => self.dynamic_window(current_velocity),
+        }
+    }
+
+    fn dynamic_window_with_limits(
+        &self,
+        current_velocity: &Velocity,
+        limits: &Limits,
+    ) -> DynamicWindow {
+        const EPSILON: f64 = 1e-9;
+        let clamped = Velocity {
+            x: current_velocity
+                .x
+                .clamp(limits.min_velocity.x, limits.max_velocity.x),
+            theta: current_velocity
+                .theta
+                .clamp(limits.min_velocity.theta, limits.max_velocity.theta),
+        };
+        let window = |v: f64, min_accel: f64, max_accel: f64, min: f64, max: f64| {
+            let low = (v + min_accel * self.controller_dt).clamp(min, max);
+            let high = (v + max_accel * self.controller_dt).clamp(min, max);
+            (low.min(high), high.max(low))
+        };
+        let (min_x, max_x) = window(
+            clamped.x,
+            limits.min_accel.x,
+            limits.max_accel.x,
+            limits.min_velocity.x,
+            limits.max_velocity.x,
+        );
+        let (min_theta, max_theta) = window(
+            clamped.theta,
+            limits.min_accel.theta,
+            limits.max_accel.theta,
+            limits.min_velocity.theta,
+            limits.max_velocity.theta,
+        );
+        let issue = if (clamped.x - current_velocity.x).abs() > EPSILON
+            || (clamped.theta - current_velocity.theta).abs() > EPSILON
+        {
+            Some(SamplingIssue::VelocityOutOfLimits {
+                velocity: *current_velocity,
+            })
+        } else if max_x - min_x <= EPSILON && max_theta - min_theta <= EPSILON {
+            Some(SamplingIssue::CollapsedWindow)
+        } else {
+            None
+        };
+        DynamicWindow {
+            min: Velocity {
+                x: min_x,
+                theta: min_theta,
+            },
+            max: Velocity {
+                x: max_x,
+                theta: max_theta,
+            },
+            issue,
+        }
+    }
+
+    /// Get candidate velocities from current velocity
+    #[cfg(test)]
+    pub(crate) fn sample_velocity(&self, current_velocity: &Velocity) -> Vec<Velocity> {
+        self.sample_window(&self.dynamic_window(current_velocity))
+    }
+
+    /// Get candidate velocities in the dynamic window
+    fn sample_window(&self, window: &DynamicWindow) -> Vec<Velocity> {
+        let (min_x_limit, max_x_limit) = (window.min.x, window.max.x);
+        let (min_theta_limit, max_theta_limit) = (window.min.theta, window.max.theta);
+        // avoid NaN velocities if no sample is configured
+        let num_vel_sample = self.num_vel_sample.max(1) as f64;
+        let d_vel_x = (max_x_limit - min_x_limit) / num_vel_sample;
+        let d_vel_theta = (max_theta_limit - min_theta_limit) / num_vel_sample;
+        let mut velocities = vec![];
+        for i in 0..(self.num_vel_sample + 1) {
+            for j in 0..(self.num_vel_sample + 1) {
+                velocities.push(Velocity {
+                    x: min_x_limit + d_vel_x * j as f64,
+                    theta: min_theta_limit + d_vel_theta * i as f64,
+                });
+            }
+            velocities.push(Velocity {
+                x: 0.0,
+                theta: min_theta_limit + d_vel_theta * i as f64,
+            });
+        }
+        velocities
+    }
+
+    fn forward_simulation(&self, current_pose: &Pose, target_velocity: &Velocity) -> Vec<Pose> {
+        let key = TrajectoryCache::key(
+            target_velocity,
+            self.controller_dt,
+            self.simulation_duration,
+        );
+        let relative_poses = self.trajectory_cache.get_or_insert_with(key, || {
+            simulate_relative_poses(
+                target_velocity,
+                self.controller_dt,
+                self.simulation_duration,
+            )
+        });
+        relative_poses.iter().map(|p| current_pose * p).collect()
+    }
+
+    /// Get predicted plan candidates
+    pub fn predicted_plan_candidates(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+    ) -> Vec<Plan> {
+        self.generate_candidates(current_pose, current_velocity)
+    }
+
+    /// Simulate the rollouts of the sampled velocities. The costs are not computed yet.
+    pub fn generate_candidates(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+    ) -> Vec<Plan> {
+        let window = self.dynamic_window_at(current_pose, current_velocity);
+        let sampling_issue = window.issue;
+        self.sample_window(&window)
+            .into_iter()
+            .map(|v| Plan {
+                velocity: v.to_owned(),
+                cost: 0.0,
+                path: self.forward_simulation(current_pose, &v),
+                sampling_issue,
+            })
+            .collect::<Vec<_>>()
+    }
+
+    /// Cost of the candidate with the given weights
+    pub fn score_plan(
+        plan: &Plan,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        weights: &HashMap<LayerId, f64>,
+    ) -> f64 {
+        let positions = plan
+            .path
+            .iter()
+            .map(|p| Position::new(p.translation.x, p.translation.y))
+            .collect::<Vec<_>>();
+        let mut all_layer_cost = 0.0;
+        for (cost_name, v) in weights {
+            let dist_cost = match maps.layer(*cost_name) {
+                Some(map) => v * accumulate_values_by_positions(map, &positions),
+                None => 0.,
+            };
+            all_layer_cost += dist_cost;
+
+            let angle_cost = match angles.get(cost_name) {
+                Some(angle) => v * (angle - plan.path.last().unwrap().rotation.angle()).abs(),
+                None => 0.,
+            };
+            all_layer_cost += angle_cost;
+        }
+        all_layer_cost
+    }
+
+    /// Select the best candidate with the given weights
+    ///
+    /// The same candidates can be scored again with different weights without
+    /// re-simulating, e.g. for interactive weight tuning and parameter sweeps.
+    pub fn score_candidates(
+        candidates: &[Plan],
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        weights: &HashMap<LayerId, f64>,
+    ) -> Plan {
+        let mut min_cost = f64::MAX;
+        let mut selected_plan = Plan::default();
+        for plan in candidates {
+            let cost = Self::score_plan(plan, maps, angles, weights);
+            if cost < min_cost {
+                min_cost = cost;
+                selected_plan = plan.clone();
+            }
+        }
+        selected_plan.cost = min_cost;
+        selected_plan
+    }
+
+    /// Plan the path using forward simulation
+    pub fn plan_local_path(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+    ) -> Plan {
+        let candidates = self.generate_candidates(current_pose, current_velocity);
+        Self::score_candidates(&candidates, maps, angles, &self.cost_name_weight)
+    }
+
+    /// [`DwaPlanner::plan_local_path`] which also scores the candidates with the
+    /// occupancy of the moving obstacles predicted at the time of each pose
+    ///
+    /// The cost of a candidate is the cost of the layers plus
+    /// `prediction_weight * predictions.path_cost(path, controller_dt)`.
+    pub fn plan_local_path_with_predictions(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        predictions: &PredictedOccupancy,
+        prediction_weight: f64,
+    ) -> Plan {
+        let mut min_cost = f64::MAX;
+        let mut selected_plan = Plan::default();
+        for plan in self.generate_candidates(current_pose, current_velocity) {
+            let cost = Self::score_plan(&plan, maps, angles, &self.cost_name_weight)
+                + prediction_weight * predictions.path_cost(&plan.path, self.controller_dt);
+            if cost < min_cost {
+                min_cost = cost;
+                selected_plan = plan;
+            }
+        }
+        selected_plan.cost = min_cost;
+        selected_plan
+    }
+
+    /// [`DwaPlanner::plan_local_path`] which checks the layers first
+    ///
+    /// Returns an error if the layers don't share the geometry (see
+    /// [`LayeredGridMap::validate_compatible`]) or a weighted layer (other than the
+    /// angles) is missing, instead of planning with the costs of the wrong places.
+    pub fn try_plan_local_path(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+    ) -> Result<Plan, Error> {
+        maps.validate_compatible()?;
+        self.check_weighted_layers(maps, angles)?;
+        Ok(self.plan_local_path(current_pose, current_velocity, maps, angles))
+    }
+
+    /// Return an error if a weighted layer (other than the angles) is missing
+    pub(crate) fn check_weighted_layers(
+        &self,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+    ) -> Result<(), Error> {
+        let mut names = self.cost_name_weight.keys().collect::<Vec<_>>();
+        names.sort();
+        match names
+            .into_iter()
+            .find(|name| !maps.contains(**name) && !angles.contains_key(name))
+        {
+            Some(name) => Err(Error::Other(format!(
+                "no layer or angle for the weight \"{name}\""
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Explain which layer contributes how much to the cost of the position
+    pub fn explain_cost_at(&self, maps: &LayeredGridMap<u8>, position: &Position) -> CostReport {
+        let mut names = self.cost_name_weight.keys().collect::<Vec<_>>();
+        names.sort();
+        let layers = names
+            .into_iter()
+            .filter_map(|name| {
+                let map = maps.layer(*name)?;
+                let cell = map.cell_by_position(position).cloned();
+                Some(LayerCost {
+                    name: *name,
+                    cost: cell.as_ref().and_then(cell_cost),
+                    cell,
+                    weight: self.cost_name_weight[name],
+                })
+            })
+            .collect();
+        CostReport {
+            position: *position,
+            layers,
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn map_name_weight(&self) -> &HashMap<LayerId, f64> {
+        &self.cost_name_weight
+    }
+
+    pub fn map_name_weight_mut(&mut self) -> &mut HashMap<LayerId, f64> {
+        &mut self.cost_name_weight
+    }
+
+    pub fn map_names(&self) -> impl Iterator<Item = LayerId> + '_ {
+        self.cost_name_weight.keys().copied()
+    }
+
+    pub fn controller_dt(&self) -> f64 {
+        self.controller_dt
+    }
+
+    pub fn zones(&self) -> &ZoneLayer {
+        &self.zones
+    }
+
+    pub fn set_zones(&mut self, zones: ZoneLayer) {
+        self.zones = zones;
+    }
+
+    pub fn simulation_duration(&self) -> f64 {
+        self.simulation_duration
+    }
+
+    pub fn num_vel_sample(&self) -> i32 {
+        self.num_vel_sample
+    }
+
+    pub fn parameters(&self) -> DwaParameters {
+        DwaParameters {
+            limits: self.limits.clone(),
+            controller_dt: self.controller_dt,
+            simulation_duration: self.simulation_duration,
+            num_vel_sample: self.num_vel_sample,
+        }
+    }
+
+    /// Replace the parameters if they are valid, which takes effect in the next
+    /// planning cycle
+    pub fn set_parameters(&mut self, parameters: DwaParameters) -> Result<(), Error> {
+        parameters.validate()?;
+        let DwaParameters {
+            limits,
+            controller_dt,
+            simulation_duration,
+            num_vel_sample,
+        } = parameters;
+        self.limits = limits;
+        self.controller_dt = controller_dt;
+        self.simulation_duration = simulation_duration;
+        self.num_vel_sample = num_vel_sample;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid_map::*;
+    use na::Vector2;
+    use std::collections::HashMap;
+
+    use crate::dwa_planner::*;
+    use crate::utils::show_ascii_map;
+    use crate::*;
+
+    fn new_sample_map() -> GridMap<u8> {
+        let mut map = grid_map::GridMap::<u8>::new(
+            Position::new(-1.05, -1.05),
+            Position::new(3.05, 1.05),
+            0.05,
+        );
+        for i in 10..50 {
+            map.set_obstacle(&Grid::new(i + 10, 5)).unwrap();
+            map.set_obstacle(&Grid::new(i + 10, 6)).unwrap();
+            for j in 20..30 {
+                map.set_obstacle(&Grid::new(i, j)).unwrap();
+            }
+        }
+        map
+    }
+
+    #[test]
+    fn score_candidates_test() {
+        let planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        let map = new_sample_map();
+        let pose = Pose::new(Vector2::new(0.0, -0.5), 0.0);
+        let velocity = Velocity { x: 0.3, theta: 0.0 };
+        let candidates = planner.generate_candidates(&pose, &velocity);
+        let mut maps = HashMap::new();
+        maps.insert(LayerId::OBSTACLE, obstacle_distance_map(&map).unwrap());
+        let layered = LayeredGridMap::new(maps);
+        let mut angles = HashMap::new();
+        angles.insert(LayerId::GOAL_DIRECTION, 1.0);
+
+        let plan = planner.plan_local_path(&pose, &velocity, &layered, &angles);
+        let rescored =
+            DwaPlanner::score_candidates(&candidates, &layered, &angles, planner.map_name_weight());
+        assert_eq!(plan.velocity, rescored.velocity);
+        assert_eq!(plan.cost, rescored.cost);
+
+        // turn to the goal direction only if that is the only cost
+        let mut weights = HashMap::new();
+        weights.insert(LayerId::GOAL_DIRECTION, 1.0);
+        let turning = DwaPlanner::score_candidates(&candidates, &layered, &angles, &weights);
+        assert!(turning.velocity.theta > 0.0);
+    }
+
+    #[test]
+    fn new_from_config_test() {
+        let _ = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+    }
+
+    #[test]
+    fn dwa_planner_test() {
+        use rrt;
+        let mut map = new_sample_map();
+        let sampler = PositionSampler::from_seed(&map, 0);
+        let start = [-0.8, -0.9];
+        let goal = [2.5, 0.5];
+        let result = rrt::dual_rrt_connect(
+            &start,
+            &goal,
+            |p: &[f64]| {
+                !matches!(
+                    map.cell(&map.to_grid(p[0], p[1]).unwrap()).unwrap(),
+                    Cell::Obstacle
+                )
+            },
+            || sampler.sample(),
+            0.05,
+            1000,
+        )
+        .unwrap();
+
+        let path_grid = result
+            .iter()
+            .map(|p| map.to_grid(p[0], p[1]).unwrap())
+            .collect::<Vec<_>>();
+
+        for p in result {
+            map.set_value(&map.to_grid(p[0], p[1]).unwrap(), 0).unwrap();
+        }
+        show_ascii_map(&map, 1.0);
+        let path_distance_map = path_distance_map(&map, &path_grid).unwrap();
+        show_ascii_map(&path_distance_map, 1.0);
+        println!("=======================");
+        let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
+        let goal_distance_map = goal_distance_map(&map, &goal_grid).unwrap();
+        show_ascii_map(&goal_distance_map, 0.1);
+        println!("=======================");
+        let obstacle_distance_map = obstacle_distance_map(&map).unwrap();
+        show_ascii_map(&obstacle_distance_map, 0.1);
+        let mut maps = HashMap::new();
+        maps.insert(LayerId::PATH, path_distance_map);
+        maps.insert(LayerId::GOAL, goal_distance_map);
+        maps.insert(LayerId::OBSTACLE, obstacle_distance_map);
+        let layered = LayeredGridMap::new(maps);
+        let angles = HashMap::new();
+        let mut weights = HashMap::new();
+        weights.insert(LayerId::PATH, 0.8);
+        weights.insert(LayerId::GOAL, 0.9);
+        weights.insert(LayerId::OBSTACLE, 0.3);
+
+        let planner = DwaPlanner::new(
+            Limits {
+                max_velocity: Velocity { x: 0.5, theta: 2.0 },
+                max_accel: Acceleration { x: 2.0, theta: 5.0 },
+                min_velocity: Velocity {
+                    x: 0.0,
+                    theta: -2.0,
+                },
+                min_accel: Acceleration {
+                    x: -2.0,
+                    theta: -5.0,
+                },
+            },
+            weights,
+            0.1,
+            1.0,
+            5,
+        );
+
+        let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), 0.0);
+        let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), 0.0);
+        let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
+        let mut plan_map = map.clone();
+        let mut reached = false;
+        for i in 0..100 {
+            let plan = planner.plan_local_path(&current_pose, &current_velocity, &layered, &angles);
+            println!("vel = {:?} cost = {}", current_velocity, plan.cost);
+            println!(
+                "pose = {:?}, {}",
+                current_pose.translation,
+                current_pose.rotation.angle()
+            );
+            current_velocity = plan.velocity;
+            current_pose = plan.path[0];
+            if let Some(grid) =
+                plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
+            {
+                let _ = plan_map.set_value(&grid, 9);
+            } else {
+                println!("OUT OF MAP!");
+                return;
+            }
+            const GOAL_THRESHOLD: f64 = 0.1;
+            if (goal_pose.translation.vector - current_pose.translation.vector).norm()
+                < GOAL_THRESHOLD
+            {
+                println!("GOAL! count = {i}");
+                reached = true;
+                break;
+            }
+            show_ascii_map(&plan_map, 1.0);
+        }
+        assert!(reached);
+    }
+
+    #[test]
+    fn test_forward_simulation_cache() {
+        let planner = DwaPlanner::new(Limits::default(), HashMap::new(), 0.1, 1.0, 5);
+        let velocity = Velocity { x: 0.3, theta: 0.5 };
+        let pose0 = Pose::new(Vector2::new(1.0, -0.5), 0.3);
+        let pose1 = Pose::new(Vector2::new(-2.0, 0.5), -1.2);
+
+        let poses0 = planner.forward_simulation(&pose0, &velocity);
+        assert_eq!(planner.trajectory_cache.len(), 1);
+        let poses1 = planner.forward_simulation(&pose1, &velocity);
+        assert_eq!(planner.trajectory_cache.len(), 1);
+
+        assert_eq!(poses0.len(), 10);
+        let mut last_pose = pose1;
+        for cached in poses1 {
+            let expected = last_pose * velocity_to_pose(&velocity, 0.1);
+            assert!((expected.translation.vector - cached.translation.vector).norm() < 1e-9);
+            assert!((expected.rotation.angle() - cached.rotation.angle()).abs() < 1e-9);
+            last_pose = expected;
+        }
+
+        planner.forward_simulation(&pose0, &Velocity { x: 0.3, theta: 0.6 });
+        assert_eq!(planner.trajectory_cache.len(), 2);
+    }
+
+    #[test]
+    fn test_explain_cost_at() {
+        let map = new_sample_map();
+        let obstacle_distance_map = obstacle_distance_map(&map).unwrap();
+        let mut weights = HashMap::new();
+        weights.insert(LayerId::OBSTACLE, 0.5);
+        weights.insert(LayerId::new("missing"), 1.0);
+        let planner = DwaPlanner::new(Limits::default(), weights, 0.1, 1.0, 5);
+        let mut layered = LayeredGridMap::default();
+        layered.add_layer(LayerId::OBSTACLE, obstacle_distance_map);
+
+        // Grid(10, 20) is an obstacle
+        let report = planner.explain_cost_at(&layered, &Position::new(-0.52, -0.02));
+        assert_eq!(report.layers.len(), 1);
+        assert_eq!(report.layers[0].cell, Some(Cell::Obstacle));
+        assert_eq!(report.total(), Some(LETHAL_COST * 0.5));
+        assert_eq!(report.lethal_layers().count(), 1);
+        println!("{report}");
+
+        let report = planner.explain_cost_at(&layered, &Position::new(10.0, 10.0));
+        assert_eq!(report.layers[0].cell, None);
+        assert_eq!(report.total(), None);
+    }
+
+    #[test]
+    fn test_try_plan_local_path() {
+        let map = new_sample_map();
+        let mut weights = HashMap::new();
+        weights.insert(LayerId::OBSTACLE, 0.5);
+        weights.insert(LayerId::GOAL_DIRECTION, 0.1);
+        let planner = DwaPlanner::new(Limits::default(), weights, 0.1, 1.0, 5);
+        let mut layered = LayeredGridMap::default();
+        layered.add_layer(LayerId::OBSTACLE, obstacle_distance_map(&map).unwrap());
+        let pose = Pose::new(Vector2::new(0.0, -0.5), 0.0);
+        let velocity = Velocity::default();
+
+        let mut angles = HashMap::new();
+        assert!(planner
+            .try_plan_local_path(&pose, &velocity, &layered, &angles)
+            .is_err());
+        angles.insert(LayerId::GOAL_DIRECTION, 1.0);
+        let plan = planner
+            .try_plan_local_path(&pose, &velocity, &layered, &angles)
+            .unwrap();
+        let expected = planner.plan_local_path(&pose, &velocity, &layered, &angles);
+        assert_eq!(
+            (plan.velocity, plan.cost),
+            (expected.velocity, expected.cost)
+        );
+
+        let coarse =
+            GridMap::<u8>::new(Position::new(-1.05, -1.05), Position::new(3.05, 1.05), 0.1);
+        layered.add_layer(LayerId::PATH, coarse);
+        assert!(matches!(
+            planner.try_plan_local_path(&pose, &velocity, &layered, &angles),
+            Err(crate::Error::GridError(
+                grid_map::Error::IncompatibleLayer { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_sample_velocities() {
+        let planner = DwaPlanner::new(
+            Limits {
+                max_velocity: Velocity { x: 0.1, theta: 0.5 },
+                max_accel: Acceleration { x: 0.5, theta: 1.0 },
+                min_velocity: Velocity {
+                    x: 0.0,
+                    theta: -0.5,
+                },
+                min_accel: Acceleration {
+                    x: -0.5,
+                    theta: -1.0,
+                },
+            },
+            HashMap::new(),
+            0.1,
+            3.0,
+            5,
+        );
+        let velocities = planner.sample_velocity(&Velocity { x: 0.0, theta: 0.0 });
+        for velocity in velocities {
+            println!("{velocity:?}");
+        }
+        let poses = planner.forward_simulation(
+            &Pose::identity(),
+            &Velocity {
+                x: 0.01,
+                theta: 0.1,
+            },
+        );
+        for pose in poses {
+            println!("pose = {:?}, {}", pose.translation, pose.rotation.angle());
+        }
+    }
+
+    #[test]
+    fn test_dynamic_window() {
+        let limits = Limits {
+            max_velocity: Velocity { x: 0.5, theta: 0.5 },
+            max_accel: Acceleration { x: 1.0, theta: 1.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -0.5,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -1.0,
+            },
+        };
+        let planner = DwaPlanner::new(limits.clone(), HashMap::new(), 0.1, 1.0, 4);
+        let window = planner.dynamic_window(&Velocity { x: 0.2, theta: 0.0 });
+        assert_eq!(window.issue, None);
+        assert!((window.min.x - 0.1).abs() < 1e-9);
+        assert!((window.max.x - 0.3).abs() < 1e-9);
+
+        // pushed faster than the limit
+        let pushed = Velocity { x: 1.0, theta: 0.0 };
+        let window = planner.dynamic_window(&pushed);
+        assert_eq!(
+            window.issue,
+            Some(SamplingIssue::VelocityOutOfLimits { velocity: pushed })
+        );
+        assert!((window.min.x - 0.4).abs() < 1e-9);
+        assert!((window.max.x - 0.5).abs() < 1e-9);
+        let candidates = planner.generate_candidates(&Pose::identity(), &pushed);
+        assert!(candidates
+            .iter()
+            .any(|c| c.velocity.x < 0.5 && c.velocity.x > 0.0));
+        assert!(candidates.iter().all(|c| c.sampling_issue.is_some()));
+
+        let mut no_accel = limits;
+        no_accel.max_accel = Acceleration { x: 0.0, theta: 0.0 };
+        no_accel.min_accel = Acceleration { x: 0.0, theta: 0.0 };
+        let planner = DwaPlanner::new(no_accel, HashMap::new(), 0.1, 1.0, 0);
+        let window = planner.dynamic_window(&Velocity { x: 0.2, theta: 0.0 });
+        assert_eq!(window.issue, Some(SamplingIssue::CollapsedWindow));
+        let velocities = planner.sample_velocity(&Velocity { x: 0.2, theta: 0.0 });
+        assert!(velocities
+            .iter()
+            .all(|v| v.x.is_finite() && v.theta.is_finite()));
+    }
+
+    #[test]
+    fn test_speed_limit_zone() {
+        let limits = Limits {
+            max_velocity: Velocity { x: 0.5, theta: 0.5 },
+            max_accel: Acceleration { x: 1.0, theta: 1.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -0.5,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -1.0,
+            },
+        };
+        let mut planner = DwaPlanner::new(limits, HashMap::new(), 0.1, 1.0, 4);
+        let mut zones = ZoneLayer::default();
+        zones.add_speed_limit(
+            vec![
+                Position::new(1.0, -1.0),
+                Position::new(2.0, -1.0),
+                Position::new(2.0, 1.0),
+                Position::new(1.0, 1.0),
+            ],
+            0.2,
+        );
+        planner.set_zones(zones);
+        let velocity = Velocity { x: 0.2, theta: 0.0 };
+        let outside = Pose::identity();
+        let inside = Pose::new(Vector2::new(1.5, 0.0), 0.0);
+        assert!((planner.dynamic_window_at(&outside, &velocity).max.x - 0.3).abs() < 1e-9);
+        let window = planner.dynamic_window_at(&inside, &velocity);
+        assert!((window.max.x - 0.2).abs() < 1e-9);
+        assert_eq!(window.issue, None);
+        assert!(planner
+            .generate_candidates(&inside, &velocity)
+            .iter()
+            .all(|c| c.velocity.x <= 0.2 + 1e-9));
+
+        // entering the zone faster than the limit
+        let fast = Velocity { x: 0.5, theta: 0.0 };
+        let window = planner.dynamic_window_at(&inside, &fast);
+        assert!(window.max.x <= 0.2 + 1e-9);
+        assert!(window.issue.is_some());
+
+        let planner = DwaPlanner::new_from_config_text(
+            r#"
+DwaPlanner:
+  limits:
+    max_velocity: [0.5, 2.0]
+    max_acceleration: [2.0, 5.0]
+    min_velocity: [0.0, -2.0]
+    min_acceleration: [-2.0, -5.0]
+  cost_name_weight: []
+  controller_dt: 0.1
+  simulation_duration: 1.0
+  num_vel_sample: 5
+  zones:
+    - type: speed_limit
+      max_velocity: 0.1
+      polygon: [{x: 0.0, y: 0.0}, {x: 1.0, y: 0.0}, {x: 1.0, y: 1.0}]
+"#,
+        )
+        .unwrap();
+        assert_eq!(planner.zones().zones().len(), 1);
+    }
+
+    #[test]
+    fn test_set_parameters() {
+        let mut planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        let mut parameters = planner.parameters();
+        parameters.simulation_duration *= 2.0;
+        parameters.num_vel_sample += 1;
+        planner.set_parameters(parameters.clone()).unwrap();
+        assert_eq!(planner.parameters(), parameters);
+
+        let mut invalid = parameters.clone();
+        invalid.limits.min_velocity.x = invalid.limits.max_velocity.x + 1.0;
+        invalid.controller_dt = 0.0;
+        let e = planner.set_parameters(invalid).unwrap_err().to_string();
+        assert!(e.contains("min_velocity"), "{e}");
+        assert!(e.contains("controller_dt"), "{e}");
+        // not changed
+        assert_eq!(planner.parameters(), parameters);
+    }
+}
@@ -1,22 +1,23 @@
+use grid_map::LayerId;
 use std::collections::HashMap;
 
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct CostNameWeightPair {
-    name: String,
+    name: LayerId,
     value: f64,
 }
 
 pub(crate) fn serialize<S: Serializer>(
-    data: &HashMap<String, f64>,
+    data: &HashMap<LayerId, f64>,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
     let pairs = data
         .iter()
         .map(|(name, value)| CostNameWeightPair {
-            name: name.clone(),
+            name: *name,
             value: *value,
         })
         .collect::<Vec<_>>();
@@ -25,7 +26,7 @@ pub(crate) fn serialize<S: Serializer>(
 
 pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
     deserializer: D,
-) -> Result<HashMap<String, f64>, D::Error> {
+) -> Result<HashMap<LayerId, f64>, D::Error> {
     let pairs = Vec::<CostNameWeightPair>::deserialize(deserializer)?;
     let map = pairs.into_iter().map(|c| (c.name, c.value)).collect();
     Ok(map)
@@ -0,0 +1,321 @@
+use grid_map::{Cell, Grid, GridMap};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::{cost_map::obstacle_distance_cost, Error, Result};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct QueueEntry {
+    /// Distance (grids) when the entry is pushed
+    distance: f64,
+    index: usize,
+}
+
+impl Eq for QueueEntry {}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for the min-heap
+        other
+            .distance
+            .total_cmp(&self.distance)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Distance map to the obstacles which is updated incrementally
+///
+/// This is the dynamic brushfire algorithm (Lau, Sprunk and Burgard, "Efficient
+/// grid-based spatial representations for robot navigation in dynamic
+/// environments"). Each cell keeps its nearest obstacle. When obstacles are added,
+/// the distances are lowered around them, and when obstacles are removed, the cells
+/// which referred to them are raised (cleared) and then lowered from the remaining
+/// obstacles, so only the affected region is visited.
+///
+/// The distances are the same as [`euclidean_distance_transform`](crate::euclidean_distance_transform)
+/// up to the small error of the propagation of the nearest obstacle.
+#[derive(Debug, Clone)]
+pub struct DynamicDistanceMap {
+    /// Obstacle and Unknown cells of the map, the others are Uninitialized
+    map: GridMap<u8>,
+    /// Distance (grids) to the nearest obstacle
+    distances: Vec<f64>,
+    nearest: Vec<Option<usize>>,
+    raise: Vec<bool>,
+    queue: BinaryHeap<QueueEntry>,
+}
+
+impl DynamicDistanceMap {
+    pub fn new(map: &GridMap<u8>) -> Self {
+        let len = map.len();
+        let mut distance_map = Self {
+            map: map.copy_without_value(),
+            distances: vec![f64::INFINITY; len],
+            nearest: vec![None; len],
+            raise: vec![false; len],
+            queue: BinaryHeap::new(),
+        };
+        for index in 0..len {
+            if distance_map.is_obstacle(index) {
+                distance_map.set_source(index);
+            }
+        }
+        distance_map.update(&mut vec![]);
+        distance_map
+    }
+
+    fn index(&self, grid: &Grid) -> Result<usize> {
+        if grid.x >= self.map.width() || grid.y >= self.map.height() {
+            return Err(Error::GridError(grid_map::Error::OutOfRangeGrid(*grid)));
+        }
+        Ok(grid.y * self.map.width() + grid.x)
+    }
+
+    fn grid(&self, index: usize) -> Grid {
+        Grid::new(index % self.map.width(), index / self.map.width())
+    }
+
+    fn is_obstacle(&self, index: usize) -> bool {
+        self.map.cells()[index].is_obstacle()
+    }
+
+    /// Distance (grids) between the centers of the cells
+    fn grid_distance(&self, a: usize, b: usize) -> f64 {
+        let (a, b) = (self.grid(a), self.grid(b));
+        (a.x.abs_diff(b.x) as f64).hypot(a.y.abs_diff(b.y) as f64)
+    }
+
+    fn set_source(&mut self, index: usize) {
+        self.distances[index] = 0.0;
+        self.nearest[index] = Some(index);
+        self.queue.push(QueueEntry {
+            distance: 0.0,
+            index,
+        });
+    }
+
+    /// Record the distance before the first modification in this update
+    fn touch(&self, index: usize, touched: &mut Vec<(usize, f64)>) {
+        touched.push((index, self.distances[index]));
+    }
+
+    /// Update the map with the obstacle cells which appeared and disappeared, and
+    /// return the cells whose distance is changed
+    ///
+    /// Cells which are both added and removed are obstacles after the update.
+    pub fn cells_changed(&mut self, added: &[Grid], removed: &[Grid]) -> Result<Vec<Grid>> {
+        let removed = removed
+            .iter()
+            .map(|g| self.index(g))
+            .collect::<Result<Vec<_>>>()?;
+        let added = added
+            .iter()
+            .map(|g| self.index(g))
+            .collect::<Result<Vec<_>>>()?;
+        let mut touched = vec![];
+        for index in removed {
+            if !self.is_obstacle(index) {
+                continue;
+            }
+            self.map.cells_mut()[index] = Cell::Uninitialized;
+            self.touch(index, &mut touched);
+            self.distances[index] = f64::INFINITY;
+            self.nearest[index] = None;
+            self.raise[index] = true;
+            self.queue.push(QueueEntry {
+                distance: 0.0,
+                index,
+            });
+        }
+        for index in added {
+            if self.is_obstacle(index) {
+                continue;
+            }
+            self.map.cells_mut()[index] = Cell::Obstacle;
+            self.touch(index, &mut touched);
+            self.raise[index] = false;
+            self.set_source(index);
+        }
+        self.update(&mut touched);
+
+        // a cell can be touched more than once, and the first record is the original
+        let mut original = vec![None; self.distances.len()];
+        for (index, distance) in touched {
+            original[index].get_or_insert(distance);
+        }
+        Ok(original
+            .iter()
+            .enumerate()
+            .filter(|(index, distance)| distance.is_some_and(|d| d != self.distances[*index]))
+            .map(|(index, _)| self.grid(index))
+            .collect())
+    }
+
+    fn update(&mut self, touched: &mut Vec<(usize, f64)>) {
+        while let Some(QueueEntry { distance, index }) = self.queue.pop() {
+            if self.raise[index] {
+                self.raise_cell(index, touched);
+            } else if self.nearest[index].is_some_and(|o| self.is_obstacle(o))
+                && distance <= self.distances[index]
+            {
+                self.lower_cell(index, touched);
+            }
+        }
+    }
+
+    /// Clear the neighbors whose nearest obstacle is removed, and let the other
+    /// neighbors propagate their distances into the cleared region
+    fn raise_cell(&mut self, index: usize, touched: &mut Vec<(usize, f64)>) {
+        let grid = self.grid(index);
+        let neighbors = self.map.neighbors8(&grid).collect::<Vec<_>>();
+        for neighbor in neighbors {
+            let n = neighbor.y * self.map.width() + neighbor.x;
+            let Some(nearest) = self.nearest[n] else {
+                continue;
+            };
+            if self.raise[n] {
+                continue;
+            }
+            let distance = self.distances[n];
+            if !self.is_obstacle(nearest) {
+                self.touch(n, touched);
+                self.distances[n] = f64::INFINITY;
+                self.nearest[n] = None;
+                self.raise[n] = true;
+            }
+            self.queue.push(QueueEntry { distance, index: n });
+        }
+        self.raise[index] = false;
+    }
+
+    fn lower_cell(&mut self, index: usize, touched: &mut Vec<(usize, f64)>) {
+        let Some(nearest) = self.nearest[index] else {
+            return;
+        };
+        let grid = self.grid(index);
+        let neighbors = self.map.neighbors8(&grid).collect::<Vec<_>>();
+        for neighbor in neighbors {
+            let n = neighbor.y * self.map.width() + neighbor.x;
+            if self.raise[n] {
+                continue;
+            }
+            let distance = self.grid_distance(nearest, n);
+            if distance < self.distances[n] {
+                self.touch(n, touched);
+                self.distances[n] = distance;
+                self.nearest[n] = Some(nearest);
+                self.queue.push(QueueEntry { distance, index: n });
+            }
+        }
+    }
+
+    /// Distance (m) from the center of the cell to the center of the nearest obstacle
+    pub fn distance(&self, grid: &Grid) -> Option<f64> {
+        let index = self.index(grid).ok()?;
+        Some(self.distances[index] * self.map.resolution())
+    }
+
+    /// Nearest obstacle of the cell
+    pub fn nearest_obstacle(&self, grid: &Grid) -> Option<Grid> {
+        let index = self.index(grid).ok()?;
+        self.nearest[index].map(|o| self.grid(o))
+    }
+
+    /// Obstacle and Unknown cells of the current map
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    /// Distances (m) in the same format as [`euclidean_distance_transform`](crate::euclidean_distance_transform)
+    pub fn distance_map(&self) -> GridMap<f64> {
+        let resolution = self.map.resolution();
+        let mut distance_map = self.map.map_values(|_| 0.0);
+        for (cell, distance) in distance_map.cells_mut().iter_mut().zip(&self.distances) {
+            *cell = Cell::Value(distance * resolution);
+        }
+        distance_map
+    }
+
+    /// Cost map in the same format as [`obstacle_distance_map_edt`](crate::obstacle_distance_map_edt)
+    pub fn obstacle_distance_map(&self) -> Result<GridMap<u8>> {
+        Ok(obstacle_distance_cost(&self.map, &self.distance_map())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{euclidean_distance_transform, obstacle_distance_map_edt};
+    use grid_map::{testkit::assert_maps_close, Position};
+    use rand::{Rng, SeedableRng};
+
+    #[test]
+    fn test_dynamic_distance_map() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.1);
+        for _ in 0..10 {
+            let grid = Grid::new(
+                rng.gen_range(0..map.width()),
+                rng.gen_range(0..map.height()),
+            );
+            map.set_obstacle(&grid).unwrap();
+        }
+        let mut distance_map = DynamicDistanceMap::new(&map);
+        assert_maps_close(
+            &distance_map.distance_map(),
+            &euclidean_distance_transform(&map),
+            0.01,
+        );
+
+        for _ in 0..20 {
+            let mut added = vec![];
+            let mut removed = vec![];
+            for _ in 0..3 {
+                let grid = Grid::new(
+                    rng.gen_range(0..map.width()),
+                    rng.gen_range(0..map.height()),
+                );
+                if map.cell(&grid).unwrap().is_obstacle() {
+                    *map.cell_mut(&grid).unwrap() = Cell::Uninitialized;
+                    removed.push(grid);
+                } else {
+                    map.set_obstacle(&grid).unwrap();
+                    added.push(grid);
+                }
+            }
+            let before = distance_map.distance_map();
+            let changed = distance_map.cells_changed(&added, &removed).unwrap();
+            let after = distance_map.distance_map();
+            assert_maps_close(&after, &euclidean_distance_transform(&map), 0.01);
+            for (grid, _, cell) in after.enumerate_cells() {
+                assert_eq!(
+                    before.cell(&grid) != Some(cell),
+                    changed.contains(&grid),
+                    "{grid:?}"
+                );
+            }
+        }
+        assert_eq!(
+            distance_map.obstacle_distance_map().unwrap().cells(),
+            obstacle_distance_map_edt(&map).unwrap().cells()
+        );
+
+        // no change
+        assert!(distance_map.cells_changed(&[], &[]).unwrap().is_empty());
+        assert!(distance_map
+            .cells_changed(&[Grid::new(100, 0)], &[])
+            .is_err());
+
+        let sub_map = map.sub_map(&Grid::new(2, 1), &Grid::new(20, 19)).unwrap();
+        assert_maps_close(
+            &DynamicDistanceMap::new(&sub_map).distance_map(),
+            &euclidean_distance_transform(&sub_map),
+            0.01,
+        );
+    }
+}
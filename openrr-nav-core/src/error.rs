@@ -0,0 +1,25 @@
+use thiserror::Error;
+
+use crate::{LifecycleState, LifecycleTransition};
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("IO: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("bincode: {0}")]
+    BincodeError(#[from] bincode::Error),
+    #[error("grid_map: {0:?}")]
+    GridError(#[from] grid_map::Error),
+    #[error("transition {transition:?} is not allowed in the {state:?} state")]
+    InvalidTransition {
+        state: LifecycleState,
+        transition: LifecycleTransition,
+    },
+    #[error("not active (the current state is {0:?})")]
+    NotActive(LifecycleState),
+    #[error("{0}")]
+    Other(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,130 @@
+use grid_map::{Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::{polygon_contains, Pose};
+
+/// Shape of the robot in the robot frame
+///
+/// ```yaml
+/// type: circle
+/// radius: 0.2
+/// ---
+/// type: polygon
+/// points: [{x: 0.3, y: 0.2}, {x: -0.2, y: 0.2}, {x: -0.2, y: -0.2}, {x: 0.3, y: -0.2}]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Footprint {
+    Circle { radius: f64 },
+    Polygon { points: Vec<Position> },
+}
+
+impl Footprint {
+    /// Vertices of the polygon at the pose in the world frame
+    fn transformed_points(&self, pose: &Pose) -> Vec<Position> {
+        match self {
+            Footprint::Circle { .. } => vec![],
+            Footprint::Polygon { points } => points
+                .iter()
+                .map(|p| {
+                    let p = pose.transform_point(&[p.x, p.y].into());
+                    Position::new(p.x, p.y)
+                })
+                .collect(),
+        }
+    }
+
+    /// Grids of the map covered by the footprint at the pose in the world frame
+    ///
+    /// The grids whose centers are inside, the grids on the outline and the grid of
+    /// the center of the robot are covered. Grids out of the map are skipped.
+    pub fn grids<T: Clone>(&self, map: &GridMap<T>, pose: &Pose) -> Vec<Grid> {
+        let center = map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+        let polygon = self
+            .transformed_points(pose)
+            .iter()
+            .map(|p| map.world_to_map(p))
+            .collect::<Vec<_>>();
+        let (min, max) = match self {
+            Footprint::Circle { radius } => (
+                Position::new(center.x - radius, center.y - radius),
+                Position::new(center.x + radius, center.y + radius),
+            ),
+            Footprint::Polygon { .. } => polygon.iter().fold((center, center), |(min, max), p| {
+                (
+                    Position::new(min.x.min(p.x), min.y.min(p.y)),
+                    Position::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            }),
+        };
+        let resolution = map.resolution();
+        let to_index = |value: f64, min: f64, size: usize| {
+            (((value - min) / resolution).floor().max(0.0) as usize).min(size.saturating_sub(1))
+        };
+        let (x0, x1) = (
+            to_index(min.x, map.min_point().x, map.width()),
+            to_index(max.x, map.min_point().x, map.width()),
+        );
+        let (y0, y1) = (
+            to_index(min.y, map.min_point().y, map.height()),
+            to_index(max.y, map.min_point().y, map.height()),
+        );
+        let mut grids = vec![];
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let grid = Grid::new(x, y);
+                let c = map.cell_center(&grid);
+                let inside = match self {
+                    Footprint::Circle { radius } => {
+                        (c.x - center.x).hypot(c.y - center.y) <= *radius
+                    }
+                    Footprint::Polygon { .. } => polygon_contains(&polygon, &c),
+                };
+                if inside {
+                    grids.push(grid);
+                }
+            }
+        }
+        for (a, b) in polygon.iter().zip(polygon.iter().cycle().skip(1)) {
+            grids.extend(map.traverse(a, b));
+        }
+        grids.extend(map.to_grid(center.x, center.y));
+        grids.sort_by_key(|g| (g.y, g.x));
+        grids.dedup();
+        grids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+
+    #[test]
+    fn test_footprint_grids() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.05, 1.05), 0.1);
+        let pose = Pose::new(na::Vector2::new(0.55, 0.55), 0.0);
+        let circle = Footprint::Circle { radius: 0.12 };
+        assert_eq!(
+            circle.grids(&map, &pose),
+            [(5, 4), (4, 5), (5, 5), (6, 5), (5, 6)].map(|(x, y)| Grid::new(x, y))
+        );
+        // 0.36 x 0.16 rectangle rotated by 90 degrees
+        let polygon: Footprint = serde_yaml::from_str(
+            "{type: polygon, points: [{x: 0.18, y: 0.08}, {x: -0.18, y: 0.08}, {x: -0.18, y: -0.08}, {x: 0.18, y: -0.08}]}",
+        )
+        .unwrap();
+        let grids = polygon.grids(
+            &map,
+            &Pose::new(na::Vector2::new(0.5, 0.5), std::f64::consts::FRAC_PI_2),
+        );
+        assert_eq!(grids.len(), 8);
+        assert!(grids
+            .iter()
+            .all(|g| (4..=5).contains(&g.x) && (3..=6).contains(&g.y)));
+        // out of the map
+        assert!(circle
+            .grids(&map, &Pose::new(na::Vector2::new(-1.0, 0.5), 0.0))
+            .is_empty());
+    }
+}
@@ -0,0 +1,1018 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use crate::{Error, Pose, Result};
+
+const SQRT_2: f64 = std::f64::consts::SQRT_2;
+
+/// Octile distance between grids, which is the exact cost on the 8-connected empty grid
+fn octile_distance(a: &Grid, b: &Grid) -> f64 {
+    let dx = a.x.abs_diff(b.x) as f64;
+    let dy = a.y.abs_diff(b.y) as f64;
+    dx.max(dy) + (SQRT_2 - 1.0) * dx.min(dy)
+}
+
+/// Euclidean distance between grids, which is the exact cost of the any-angle path
+/// on the empty grid
+fn euclidean_distance(a: &Grid, b: &Grid) -> f64 {
+    (a.x.abs_diff(b.x) as f64).hypot(a.y.abs_diff(b.y) as f64)
+}
+
+pub(crate) fn is_free_cell(cell: Option<&Cell<u8>>, allow_unknown: bool) -> bool {
+    match cell {
+        None | Some(Cell::Obstacle) => false,
+        Some(Cell::Unknown) => allow_unknown,
+        _ => true,
+    }
+}
+
+fn validate_cost_layer(map: &GridMap<u8>, cost_layer: Option<&GridMap<u8>>) -> Result<()> {
+    match cost_layer {
+        Some(layer) if (layer.width(), layer.height()) != (map.width(), map.height()) => {
+            Err(Error::Other(format!(
+                "size of the cost layer ({}x{}) is different from the map ({}x{})",
+                layer.width(),
+                layer.height(),
+                map.width(),
+                map.height()
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn is_traversable(
+    map: &GridMap<u8>,
+    cost_layer: Option<&GridMap<u8>>,
+    allow_unknown: bool,
+    grid: &Grid,
+) -> bool {
+    is_free_cell(map.cell(grid), allow_unknown)
+        && cost_layer.is_none_or(|layer| !layer.cell(grid).is_some_and(Cell::is_obstacle))
+}
+
+/// Multiplier of the cost to move into the grid
+fn cost_scale(cost_layer: Option<&GridMap<u8>>, cost_factor: f64, grid: &Grid) -> f64 {
+    cost_layer
+        .and_then(|layer| layer.value(grid))
+        .map_or(1.0, |cost| 1.0 + cost_factor * cost as f64 / u8::MAX as f64)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct OpenNode {
+    /// Primary priority (smaller is better)
+    pub(crate) f: f64,
+    /// Tie breaker (smaller is better)
+    pub(crate) tie: f64,
+    pub(crate) index: usize,
+}
+
+impl Eq for OpenNode {}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // reversed for the min-heap
+        other
+            .f
+            .total_cmp(&self.f)
+            .then_with(|| other.tie.total_cmp(&self.tie))
+    }
+}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Result of the grid search
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchResult {
+    /// Grids from the start to the goal
+    pub path: Vec<Grid>,
+    /// Cost of the path in grids (1 for a straight move, sqrt(2) for a diagonal move)
+    pub cost: f64,
+    /// Number of the expanded nodes
+    pub num_expanded: usize,
+}
+
+/// A* planner on the 8-connected grid
+///
+/// Nodes are prioritized by `g + epsilon * h`, where `h` is the octile distance (the
+/// Euclidean distance for Theta*).
+///
+/// - `epsilon == 1.0`: A*, the path is optimal.
+/// - `epsilon > 1.0`: weighted A*, the cost of the path is at most `epsilon` times the optimal cost.
+/// - `epsilon == f64::INFINITY`: greedy best-first search, no bound on the cost but usually
+///   the fastest.
+///
+/// Obstacle cells and cells out of the map are not traversable. Diagonal moves
+/// cutting the corner of the non-traversable cells are not allowed.
+///
+/// With a traversal-cost layer (e.g. [`inflate_obstacles`](crate::inflate_obstacles)),
+/// the cost of a move is multiplied by `1 + cost_factor * cost / 255`, where `cost` is
+/// the value of the destination cell, so the path keeps away from the high-cost cells.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AStarPlanner {
+    #[serde(default = "default_epsilon")]
+    epsilon: f64,
+    #[serde(default)]
+    allow_unknown: bool,
+    #[serde(default)]
+    algorithm: GridSearchAlgorithm,
+    #[serde(default = "default_cost_factor")]
+    cost_factor: f64,
+}
+
+fn default_epsilon() -> f64 {
+    1.0
+}
+
+fn default_cost_factor() -> f64 {
+    1.0
+}
+
+/// Search algorithm of [`AStarPlanner`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GridSearchAlgorithm {
+    /// Expand all 8 neighbors
+    #[default]
+    AStar,
+    /// Jump Point Search, which expands far fewer nodes on uniform-cost maps
+    /// with large open areas. The cost of the path is the same as A*.
+    JumpPointSearch,
+    /// Theta*, which connects the node to the parent of its parent if there is the
+    /// line of sight between them. The path is not restricted to the 8 directions,
+    /// so it is shorter and straighter than A*, but not always the shortest.
+    ThetaStar,
+}
+
+impl Default for AStarPlanner {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+impl AStarPlanner {
+    /// Create planner with the heuristic weight. `epsilon` is clamped to be >= 1.0.
+    pub fn new(epsilon: f64) -> Self {
+        Self {
+            epsilon: if epsilon.is_nan() {
+                1.0
+            } else {
+                epsilon.max(1.0)
+            },
+            allow_unknown: false,
+            algorithm: GridSearchAlgorithm::AStar,
+            cost_factor: default_cost_factor(),
+        }
+    }
+
+    /// Greedy best-first search
+    pub fn greedy() -> Self {
+        Self::new(f64::INFINITY)
+    }
+
+    /// Allow to pass through Unknown cells
+    pub fn with_allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    /// Select the search algorithm
+    pub fn with_algorithm(mut self, algorithm: GridSearchAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Weight of the traversal-cost layer. Negative values are clamped to 0.
+    pub fn with_cost_factor(mut self, cost_factor: f64) -> Self {
+        self.cost_factor = cost_factor.max(0.0);
+        self
+    }
+
+    pub fn cost_factor(&self) -> f64 {
+        self.cost_factor
+    }
+
+    pub fn algorithm(&self) -> GridSearchAlgorithm {
+        self.algorithm
+    }
+
+    pub fn epsilon(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Upper bound of `(cost of the found path) / (optimal cost)`
+    pub fn suboptimality_bound(&self) -> f64 {
+        self.epsilon
+    }
+
+    /// Plan the path from start to goal
+    pub fn plan(&self, map: &GridMap<u8>, start: &Grid, goal: &Grid) -> Result<Vec<Grid>> {
+        Ok(self.search(map, start, goal)?.path)
+    }
+
+    /// Plan the path from start to goal with the search statistics
+    pub fn search(&self, map: &GridMap<u8>, start: &Grid, goal: &Grid) -> Result<SearchResult> {
+        self.search_with_cost(map, None, start, goal)
+    }
+
+    /// Plan the path of the poses in the world frame from start to goal
+    ///
+    /// The poses are at the centers of the cells and head to the next cell. The last
+    /// pose has the orientation of the goal.
+    pub fn plan_poses(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        let to_grid = |pose: &Pose| {
+            let position = Position::new(pose.translation.x, pose.translation.y);
+            map.world_to_grid(&position)
+                .ok_or_else(|| Error::Other(format!("{position:?} is out of the map")))
+        };
+        let path = self
+            .search_with_cost(map, cost_layer, &to_grid(start)?, &to_grid(goal)?)?
+            .path;
+        Ok(grids_to_poses(map, &path, goal))
+    }
+
+    /// [`AStarPlanner::search`] with the optional traversal-cost layer
+    ///
+    /// The layer must have the same size as the map. Obstacle cells of the layer are
+    /// not traversable and the cells without values have no extra cost. Jump Point
+    /// Search assumes the uniform cost, so A* is used when the layer is given. Theta*
+    /// scales the length of a line by the average cost of the cells on it.
+    pub fn search_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Grid,
+        goal: &Grid,
+    ) -> Result<SearchResult> {
+        let (width, height) = (map.width(), map.height());
+        validate_cost_layer(map, cost_layer)?;
+        let algorithm =
+            if cost_layer.is_some() && self.algorithm == GridSearchAlgorithm::JumpPointSearch {
+                GridSearchAlgorithm::AStar
+            } else {
+                self.algorithm
+            };
+        let is_free = |grid: &Grid| is_traversable(map, cost_layer, self.allow_unknown, grid);
+        let cost_scale = |grid: &Grid| cost_scale(cost_layer, self.cost_factor, grid);
+        if !is_free(start) {
+            return Err(Error::Other(format!("start {start:?} is not traversable")));
+        }
+        if !is_free(goal) {
+            return Err(Error::Other(format!("goal {goal:?} is not traversable")));
+        }
+        let free = |x: isize, y: isize| {
+            x >= 0
+                && y >= 0
+                && (x as usize) < width
+                && (y as usize) < height
+                && is_free(&Grid::new(x as usize, y as usize))
+        };
+        let to_index = |grid: &Grid| grid.y * width + grid.x;
+        let to_grid = |index: usize| Grid::new(index % width, index / width);
+        let heuristic = |grid: &Grid| {
+            if algorithm == GridSearchAlgorithm::ThetaStar {
+                euclidean_distance(grid, goal)
+            } else {
+                octile_distance(grid, goal)
+            }
+        };
+        // cost of the straight line between the grids if there is the line of sight
+        let line_cost = |from: &Grid, to: &Grid| {
+            let (mut num_grids, mut scale) = (0, 0.0);
+            for grid in map.traverse(&map.cell_center(from), &map.cell_center(to)) {
+                if !is_free(&grid) {
+                    return None;
+                }
+                num_grids += 1;
+                scale += cost_scale(&grid);
+            }
+            Some(euclidean_distance(from, to) * scale / num_grids.max(1) as f64)
+        };
+        let priority = |g: f64, h: f64| {
+            if self.epsilon.is_infinite() {
+                (h, g)
+            } else {
+                (g + self.epsilon * h, h)
+            }
+        };
+
+        let mut g_costs = vec![f64::INFINITY; width * height];
+        let mut parents = vec![usize::MAX; width * height];
+        let mut closed = vec![false; width * height];
+        let mut open = BinaryHeap::new();
+        let start_index = to_index(start);
+        let goal_index = to_index(goal);
+        g_costs[start_index] = 0.0;
+        let (f, tie) = priority(0.0, heuristic(start));
+        open.push(OpenNode {
+            f,
+            tie,
+            index: start_index,
+        });
+        let mut num_expanded = 0;
+        let mut successors = vec![];
+
+        while let Some(OpenNode { index, .. }) = open.pop() {
+            if closed[index] {
+                continue;
+            }
+            closed[index] = true;
+            num_expanded += 1;
+            if index == goal_index {
+                let mut path = vec![to_grid(index)];
+                let mut current = index;
+                while parents[current] != usize::MAX {
+                    current = parents[current];
+                    path.push(to_grid(current));
+                }
+                path.reverse();
+                match algorithm {
+                    GridSearchAlgorithm::AStar => {}
+                    GridSearchAlgorithm::JumpPointSearch => path = interpolate_jump_points(&path),
+                    GridSearchAlgorithm::ThetaStar => path = interpolate_lines(&path),
+                }
+                return Ok(SearchResult {
+                    path,
+                    cost: g_costs[goal_index],
+                    num_expanded,
+                });
+            }
+            let grid = to_grid(index);
+            successors.clear();
+            match algorithm {
+                GridSearchAlgorithm::AStar | GridSearchAlgorithm::ThetaStar => {
+                    for (neighbor, distance) in free_neighbors8(map, &grid, &is_free) {
+                        successors.push((neighbor, distance * cost_scale(&neighbor)));
+                    }
+                }
+                GridSearchAlgorithm::JumpPointSearch => {
+                    let parent = (parents[index] != usize::MAX).then(|| to_grid(parents[index]));
+                    for (dx, dy) in jps_directions(&grid, parent.as_ref(), &free) {
+                        if let Some(jump_point) =
+                            jump(grid.x as isize, grid.y as isize, dx, dy, goal, &free)
+                        {
+                            successors.push((jump_point, octile_distance(&grid, &jump_point)));
+                        }
+                    }
+                }
+            }
+            let grandparent = (algorithm == GridSearchAlgorithm::ThetaStar
+                && parents[index] != usize::MAX)
+                .then_some(parents[index]);
+            for &(neighbor, move_cost) in &successors {
+                let neighbor_index = to_index(&neighbor);
+                if closed[neighbor_index] {
+                    continue;
+                }
+                let direct = (index, g_costs[index] + move_cost);
+                // the line of sight is not always shorter with the cost layer
+                let (parent, g) = match grandparent.and_then(|p| {
+                    line_cost(&to_grid(p), &neighbor).map(|cost| (p, g_costs[p] + cost))
+                }) {
+                    Some(shortcut) if shortcut.1 <= direct.1 => shortcut,
+                    _ => direct,
+                };
+                if g < g_costs[neighbor_index] {
+                    g_costs[neighbor_index] = g;
+                    parents[neighbor_index] = parent;
+                    let (f, tie) = priority(g, heuristic(&neighbor));
+                    open.push(OpenNode {
+                        f,
+                        tie,
+                        index: neighbor_index,
+                    });
+                }
+            }
+        }
+        Err(Error::Other(format!(
+            "failed to find the path from {start:?} to {goal:?}"
+        )))
+    }
+}
+
+/// Poses in the world frame at the centers of the cells
+pub(crate) fn grids_to_poses(map: &GridMap<u8>, path: &[Grid], goal: &Pose) -> Vec<Pose> {
+    let positions = path
+        .iter()
+        .map(|grid| map.map_to_world(&map.cell_center(grid)))
+        .collect::<Vec<_>>();
+    positions_to_poses(&positions, goal)
+}
+
+/// Poses heading to the next position, and the last one has the orientation of the goal
+pub(crate) fn positions_to_poses(positions: &[Position], goal: &Pose) -> Vec<Pose> {
+    let mut poses = positions
+        .iter()
+        .zip(positions.iter().skip(1))
+        .map(|(p, next)| {
+            Pose::new(
+                na::Vector2::new(p.x, p.y),
+                (next.y - p.y).atan2(next.x - p.x),
+            )
+        })
+        .collect::<Vec<_>>();
+    if let Some(last) = positions.last() {
+        poses.push(Pose::new(
+            na::Vector2::new(last.x, last.y),
+            goal.rotation.angle(),
+        ));
+    }
+    poses
+}
+
+/// Free 8-connected neighbors with the distances (grids), without cutting the corners
+fn free_neighbors8<'a, T, F>(
+    map: &'a GridMap<T>,
+    grid: &'a Grid,
+    is_free: &'a F,
+) -> impl Iterator<Item = (Grid, f64)> + 'a
+where
+    T: Clone,
+    F: Fn(&Grid) -> bool,
+{
+    map.neighbors8(grid).filter_map(move |neighbor| {
+        if !is_free(&neighbor) {
+            return None;
+        }
+        let diagonal = neighbor.x != grid.x && neighbor.y != grid.y;
+        if diagonal
+            && (!is_free(&Grid::new(neighbor.x, grid.y))
+                || !is_free(&Grid::new(grid.x, neighbor.y)))
+        {
+            return None;
+        }
+        Some((neighbor, if diagonal { SQRT_2 } else { 1.0 }))
+    })
+}
+
+/// Directions to search from the grid, pruned by the direction from the parent.
+///
+/// Diagonal moves are allowed only when both of the adjacent orthogonal cells are free.
+fn jps_directions<F>(grid: &Grid, parent: Option<&Grid>, free: &F) -> Vec<(isize, isize)>
+where
+    F: Fn(isize, isize) -> bool,
+{
+    let (x, y) = (grid.x as isize, grid.y as isize);
+    let Some(parent) = parent else {
+        let mut directions = vec![];
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            if free(x + dx, y + dy) {
+                directions.push((dx, dy));
+            }
+        }
+        for (dx, dy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+            if free(x + dx, y) && free(x, y + dy) && free(x + dx, y + dy) {
+                directions.push((dx, dy));
+            }
+        }
+        return directions;
+    };
+    let dx = (x - parent.x as isize).signum();
+    let dy = (y - parent.y as isize).signum();
+    let mut directions = vec![];
+    if dx != 0 && dy != 0 {
+        if free(x, y + dy) {
+            directions.push((0, dy));
+        }
+        if free(x + dx, y) {
+            directions.push((dx, 0));
+        }
+        if free(x, y + dy) && free(x + dx, y) {
+            directions.push((dx, dy));
+        }
+    } else if dx != 0 {
+        let (up, down) = (free(x, y + 1), free(x, y - 1));
+        if free(x + dx, y) {
+            directions.push((dx, 0));
+            if up {
+                directions.push((dx, 1));
+            }
+            if down {
+                directions.push((dx, -1));
+            }
+        }
+        if up {
+            directions.push((0, 1));
+        }
+        if down {
+            directions.push((0, -1));
+        }
+    } else {
+        let (right, left) = (free(x + 1, y), free(x - 1, y));
+        if free(x, y + dy) {
+            directions.push((0, dy));
+            if right {
+                directions.push((1, dy));
+            }
+            if left {
+                directions.push((-1, dy));
+            }
+        }
+        if right {
+            directions.push((1, 0));
+        }
+        if left {
+            directions.push((-1, 0));
+        }
+    }
+    directions
+}
+
+/// Jump from (x, y) to the direction and return the next jump point
+fn jump<F>(x: isize, y: isize, dx: isize, dy: isize, goal: &Grid, free: &F) -> Option<Grid>
+where
+    F: Fn(isize, isize) -> bool,
+{
+    let is_goal = |x: isize, y: isize| x == goal.x as isize && y == goal.y as isize;
+    let (mut x, mut y) = (x + dx, y + dy);
+    loop {
+        if !free(x, y) {
+            return None;
+        }
+        if is_goal(x, y) {
+            return Some(Grid::new(x as usize, y as usize));
+        }
+        if dx != 0 && dy != 0 {
+            if jump(x, y, dx, 0, goal, free).is_some() || jump(x, y, 0, dy, goal, free).is_some() {
+                return Some(Grid::new(x as usize, y as usize));
+            }
+            if !(free(x + dx, y) && free(x, y + dy)) {
+                return None;
+            }
+        } else if dx != 0 {
+            if (free(x, y - 1) && !free(x - dx, y - 1)) || (free(x, y + 1) && !free(x - dx, y + 1))
+            {
+                return Some(Grid::new(x as usize, y as usize));
+            }
+        } else if (free(x - 1, y) && !free(x - 1, y - dy))
+            || (free(x + 1, y) && !free(x + 1, y - dy))
+        {
+            return Some(Grid::new(x as usize, y as usize));
+        }
+        x += dx;
+        y += dy;
+    }
+}
+
+/// Fill the grids between the jump points, which are connected by straight or diagonal lines
+fn interpolate_jump_points(jump_points: &[Grid]) -> Vec<Grid> {
+    let mut path = vec![];
+    for (from, to) in jump_points.iter().zip(jump_points.iter().skip(1)) {
+        let (mut x, mut y) = (from.x as isize, from.y as isize);
+        let (tx, ty) = (to.x as isize, to.y as isize);
+        while (x, y) != (tx, ty) {
+            path.push(Grid::new(x as usize, y as usize));
+            x += (tx - x).signum();
+            y += (ty - y).signum();
+        }
+    }
+    if let Some(last) = jump_points.last() {
+        path.push(*last);
+    }
+    path
+}
+
+/// Fill the grids between the vertices of the any-angle path with the 8-connected lines
+fn interpolate_lines(vertices: &[Grid]) -> Vec<Grid> {
+    let mut path = vec![];
+    for (from, to) in vertices.iter().zip(vertices.iter().skip(1)) {
+        let (x0, y0) = (from.x as isize, from.y as isize);
+        let (dx, dy) = (to.x as isize - x0, to.y as isize - y0);
+        let num_steps = dx.abs().max(dy.abs());
+        // the grids nearest to the line at each step of the major axis
+        for i in 0..num_steps {
+            let t = i as f64 / num_steps as f64;
+            path.push(Grid::new(
+                (x0 as f64 + t * dx as f64).round() as usize,
+                (y0 as f64 + t * dy as f64).round() as usize,
+            ));
+        }
+    }
+    if let Some(last) = vertices.last() {
+        path.push(*last);
+    }
+    path
+}
+
+/// Cost to the goal of all cells reachable from the goal
+///
+/// This is a navigation function: following the cells toward the goal from any cell
+/// gives the optimal path, so the path can be extracted for many starts from one
+/// computation.
+#[derive(Debug, Clone)]
+pub struct NavigationFunction {
+    /// Cost (m) to the goal. Unreachable cells are Uninitialized, and Obstacle and
+    /// Unknown cells are kept as they are in the map.
+    costs: GridMap<f64>,
+    /// Index of the next cell toward the goal
+    next: Vec<Option<usize>>,
+    goal: Grid,
+}
+
+impl NavigationFunction {
+    /// Cost layer, which respects the obstacles unlike [`goal_distance_map`](crate::goal_distance_map)
+    pub fn costs(&self) -> &GridMap<f64> {
+        &self.costs
+    }
+
+    pub fn into_costs(self) -> GridMap<f64> {
+        self.costs
+    }
+
+    pub fn goal(&self) -> Grid {
+        self.goal
+    }
+
+    /// Layer in the format of [`goal_distance_map`](crate::goal_distance_map) (the
+    /// cost in grids saturated at 255), which can replace the goal layer of
+    /// [`DwaPlanner`](crate::DwaPlanner)
+    pub fn goal_distance_layer(&self) -> GridMap<u8> {
+        let resolution = self.costs.resolution();
+        self.costs
+            .map_values(|cost| (cost / resolution).round().min(u8::MAX as f64) as u8)
+    }
+
+    /// Cost (m) from the grid to the goal, `None` if it is not reachable
+    pub fn cost(&self, grid: &Grid) -> Option<f64> {
+        self.costs.value(grid)
+    }
+
+    /// Optimal path from the start to the goal
+    pub fn path_from(&self, start: &Grid) -> Result<Vec<Grid>> {
+        if self.cost(start).is_none() {
+            return Err(Error::Other(format!(
+                "goal {:?} is not reachable from {start:?}",
+                self.goal
+            )));
+        }
+        let width = self.costs.width();
+        let mut path = vec![*start];
+        let mut index = start.y * width + start.x;
+        while let Some(next) = self.next[index] {
+            path.push(Grid::new(next % width, next / width));
+            index = next;
+        }
+        Ok(path)
+    }
+}
+
+/// Dijkstra planner which computes the cost to the goal over the whole map
+///
+/// The moves and the costs are the same as [`AStarPlanner`], so the cost of the path
+/// is the same as A* with `epsilon == 1.0`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DijkstraPlanner {
+    #[serde(default)]
+    allow_unknown: bool,
+    #[serde(default = "default_cost_factor")]
+    cost_factor: f64,
+}
+
+impl Default for DijkstraPlanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DijkstraPlanner {
+    pub fn new() -> Self {
+        Self {
+            allow_unknown: false,
+            cost_factor: default_cost_factor(),
+        }
+    }
+
+    /// Allow to pass through Unknown cells
+    pub fn with_allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    /// Weight of the traversal-cost layer. Negative values are clamped to 0.
+    pub fn with_cost_factor(mut self, cost_factor: f64) -> Self {
+        self.cost_factor = cost_factor.max(0.0);
+        self
+    }
+
+    pub fn cost_factor(&self) -> f64 {
+        self.cost_factor
+    }
+
+    /// Compute the navigation function to the goal with the optional traversal-cost
+    /// layer of the same size as the map
+    pub fn navigation_function(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        goal: &Grid,
+    ) -> Result<NavigationFunction> {
+        validate_cost_layer(map, cost_layer)?;
+        let is_free = |grid: &Grid| is_traversable(map, cost_layer, self.allow_unknown, grid);
+        if !is_free(goal) {
+            return Err(Error::Other(format!("goal {goal:?} is not traversable")));
+        }
+        let width = map.width();
+        let to_index = |grid: &Grid| grid.y * width + grid.x;
+        let mut g_costs = vec![f64::INFINITY; map.len()];
+        let mut next = vec![None; map.len()];
+        let mut closed = vec![false; map.len()];
+        let mut open = BinaryHeap::new();
+        g_costs[to_index(goal)] = 0.0;
+        open.push(OpenNode {
+            f: 0.0,
+            tie: 0.0,
+            index: to_index(goal),
+        });
+        while let Some(OpenNode { index, .. }) = open.pop() {
+            if closed[index] {
+                continue;
+            }
+            closed[index] = true;
+            let grid = Grid::new(index % width, index / width);
+            // the cost of the move from the neighbor into this grid
+            let move_scale = cost_scale(cost_layer, self.cost_factor, &grid);
+            for (neighbor, distance) in free_neighbors8(map, &grid, &is_free) {
+                let neighbor_index = to_index(&neighbor);
+                let g = g_costs[index] + distance * move_scale;
+                if !closed[neighbor_index] && g < g_costs[neighbor_index] {
+                    g_costs[neighbor_index] = g;
+                    next[neighbor_index] = Some(index);
+                    open.push(OpenNode {
+                        f: g,
+                        tie: 0.0,
+                        index: neighbor_index,
+                    });
+                }
+            }
+        }
+
+        let mut costs = map.copy_without_value().map_values(|_| 0.0);
+        for (cell, g) in costs.cells_mut().iter_mut().zip(g_costs) {
+            if g.is_finite() {
+                *cell = Cell::Value(g * map.resolution());
+            }
+        }
+        Ok(NavigationFunction {
+            costs,
+            next,
+            goal: *goal,
+        })
+    }
+
+    /// Plan the path from start to goal
+    pub fn plan(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Grid,
+        goal: &Grid,
+    ) -> Result<Vec<Grid>> {
+        if !is_traversable(map, cost_layer, self.allow_unknown, start) {
+            return Err(Error::Other(format!("start {start:?} is not traversable")));
+        }
+        self.navigation_function(map, cost_layer, goal)?
+            .path_from(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Position;
+
+    fn new_wall_map() -> GridMap<u8> {
+        let mut map = GridMap::new(Position::new(0.0, 0.0), Position::new(5.0, 5.0), 0.1);
+        for y in 0..40 {
+            map.set_obstacle(&Grid::new(25, y)).unwrap();
+        }
+        for x in 10..25 {
+            map.set_obstacle(&Grid::new(x, 39)).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn test_astar_open_map() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.0, 2.0), 0.1);
+        let result = AStarPlanner::default()
+            .search(&map, &Grid::new(0, 0), &Grid::new(19, 10))
+            .unwrap();
+        assert_eq!(result.path.first(), Some(&Grid::new(0, 0)));
+        assert_eq!(result.path.last(), Some(&Grid::new(19, 10)));
+        assert!((result.cost - octile_distance(&Grid::new(0, 0), &Grid::new(19, 10))).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_weighted_astar_bound() {
+        let map = new_wall_map();
+        let (start, goal) = (Grid::new(20, 20), Grid::new(30, 20));
+        let optimal = AStarPlanner::default().search(&map, &start, &goal).unwrap();
+        for epsilon in [1.5, 3.0] {
+            let planner = AStarPlanner::new(epsilon);
+            let result = planner.search(&map, &start, &goal).unwrap();
+            assert!(result.cost <= optimal.cost * planner.suboptimality_bound() + 1e-9);
+            assert!(result.num_expanded <= optimal.num_expanded);
+        }
+        let greedy = AStarPlanner::greedy().search(&map, &start, &goal).unwrap();
+        assert_eq!(greedy.path.last(), Some(&goal));
+        assert!(greedy.num_expanded < optimal.num_expanded);
+        for grid in greedy.path {
+            assert!(!map.cell(&grid).unwrap().is_obstacle());
+        }
+    }
+
+    #[test]
+    fn test_jump_point_search() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+        let jps = AStarPlanner::default().with_algorithm(GridSearchAlgorithm::JumpPointSearch);
+        for _ in 0..20 {
+            let mut map = new_wall_map();
+            for _ in 0..300 {
+                let grid = Grid::new(rng.gen_range(0..50), rng.gen_range(0..50));
+                map.set_obstacle(&grid).unwrap();
+            }
+            let start = Grid::new(rng.gen_range(0..50), rng.gen_range(0..50));
+            let goal = Grid::new(rng.gen_range(0..50), rng.gen_range(0..50));
+            match (
+                AStarPlanner::default().search(&map, &start, &goal),
+                jps.search(&map, &start, &goal),
+            ) {
+                (Ok(astar), Ok(jps)) => {
+                    assert!((astar.cost - jps.cost).abs() < 1e-6);
+                    assert_eq!(jps.path.first(), Some(&start));
+                    assert_eq!(jps.path.last(), Some(&goal));
+                    for (a, b) in jps.path.iter().zip(jps.path.iter().skip(1)) {
+                        assert!(a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1);
+                        assert!(!map.cell(b).unwrap().is_obstacle());
+                    }
+                }
+                (Err(_), Err(_)) => {}
+                (astar, jps) => panic!("mismatch {astar:?} {jps:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_theta_star() {
+        let map = new_wall_map();
+        let theta = AStarPlanner::default().with_algorithm(GridSearchAlgorithm::ThetaStar);
+        let (start, goal) = (Grid::new(20, 20), Grid::new(30, 20));
+        let astar = AStarPlanner::default().search(&map, &start, &goal).unwrap();
+        let result = theta.search(&map, &start, &goal).unwrap();
+        assert!(result.cost < astar.cost);
+        assert_eq!(result.path.first(), Some(&start));
+        assert_eq!(result.path.last(), Some(&goal));
+        for (a, b) in result.path.iter().zip(result.path.iter().skip(1)) {
+            assert!(a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1);
+            assert!(!map.cell(b).unwrap().is_obstacle());
+        }
+        // the cost is the length of the path, which is at least the straight line
+        let length = result
+            .path
+            .iter()
+            .zip(result.path.iter().skip(1))
+            .map(|(a, b)| euclidean_distance(a, b))
+            .sum::<f64>();
+        assert!(result.cost <= length + 1e-9);
+        assert!(result.cost >= euclidean_distance(&start, &goal));
+
+        // a straight line on the empty map
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        let result = theta
+            .search(&map, &Grid::new(0, 0), &Grid::new(19, 7))
+            .unwrap();
+        assert!((result.cost - 19f64.hypot(7.0)).abs() < 1e-9);
+        assert_eq!(result.path.len(), 20);
+    }
+
+    #[test]
+    fn test_astar_cost_layer() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        let mut cost_layer = map.map_values(|_| 0);
+        cost_layer.fill(Cell::Value(0));
+        // expensive band in the middle except for the top row
+        for x in 5..15 {
+            for y in 0..9 {
+                cost_layer.set_value(&Grid::new(x, y), 255).unwrap();
+            }
+        }
+        let (start, goal) = (Grid::new(0, 2), Grid::new(19, 2));
+        let planner = AStarPlanner::default().with_cost_factor(10.0);
+        let straight = planner.search(&map, &start, &goal).unwrap();
+        assert!(straight.path.iter().all(|g| g.y == 2));
+        let detour = planner
+            .search_with_cost(&map, Some(&cost_layer), &start, &goal)
+            .unwrap();
+        assert!(detour.path.iter().any(|g| g.y == 9));
+        assert!(detour.cost > straight.cost);
+        let jps = planner
+            .clone()
+            .with_algorithm(GridSearchAlgorithm::JumpPointSearch);
+        let result = jps
+            .search_with_cost(&map, Some(&cost_layer), &start, &goal)
+            .unwrap();
+        assert!((result.cost - detour.cost).abs() < 1e-9);
+        let theta = planner
+            .clone()
+            .with_algorithm(GridSearchAlgorithm::ThetaStar);
+        let result = theta
+            .search_with_cost(&map, Some(&cost_layer), &start, &goal)
+            .unwrap();
+        assert!(result.path.iter().any(|g| g.y == 9));
+        assert!(result.cost < detour.cost);
+
+        let poses = planner
+            .plan_poses(
+                &map,
+                Some(&cost_layer),
+                &Pose::new(na::Vector2::new(0.05, 0.25), 0.0),
+                &Pose::new(na::Vector2::new(1.95, 0.25), 1.0),
+            )
+            .unwrap();
+        assert_eq!(poses.len(), detour.path.len());
+        assert!((poses[0].translation.x - 0.05).abs() < 1e-9);
+        assert!((poses.last().unwrap().rotation.angle() - 1.0).abs() < 1e-9);
+        assert!(AStarPlanner::default()
+            .search_with_cost(
+                &map,
+                Some(&GridMap::new(
+                    Position::new(0.0, 0.0),
+                    Position::new(1.0, 1.0),
+                    0.1
+                )),
+                &start,
+                &goal
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_dijkstra_navigation_function() {
+        let mut map = new_wall_map();
+        *map.cell_mut(&Grid::new(40, 40)).unwrap() = Cell::Unknown;
+        let goal = Grid::new(30, 20);
+        let mut cost_layer = map.map_values(|_| 0);
+        cost_layer.set_value(&Grid::new(27, 30), 200).unwrap();
+        let dijkstra = DijkstraPlanner::default();
+        let field = dijkstra
+            .navigation_function(&map, Some(&cost_layer), &goal)
+            .unwrap();
+        assert_eq!(field.cost(&goal), Some(0.0));
+        assert_eq!(field.costs().cell(&Grid::new(25, 0)), Some(&Cell::Obstacle));
+        assert_eq!(field.costs().cell(&Grid::new(40, 40)), Some(&Cell::Unknown));
+        assert_eq!(
+            field.goal_distance_layer().value(&Grid::new(31, 21)),
+            Some(1)
+        );
+        let astar = AStarPlanner::default();
+        for start in [Grid::new(20, 20), Grid::new(0, 0), Grid::new(49, 49)] {
+            let path = field.path_from(&start).unwrap();
+            let result = astar
+                .search_with_cost(&map, Some(&cost_layer), &start, &goal)
+                .unwrap();
+            assert_eq!(path.first(), Some(&start));
+            assert_eq!(path.last(), Some(&goal));
+            // the same cost as A*, in meters
+            assert!((field.cost(&start).unwrap() - result.cost * 0.1).abs() < 1e-9);
+        }
+
+        // enclosed cells are not reachable
+        for grid in [Grid::new(1, 0), Grid::new(0, 1), Grid::new(1, 1)] {
+            map.set_obstacle(&grid).unwrap();
+        }
+        let field = dijkstra.navigation_function(&map, None, &goal).unwrap();
+        assert_eq!(
+            field.costs().cell(&Grid::new(0, 0)),
+            Some(&Cell::Uninitialized)
+        );
+        assert!(dijkstra.plan(&map, None, &Grid::new(0, 0), &goal).is_err());
+    }
+
+    #[test]
+    fn test_astar_no_path() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.1);
+        for y in 0..10 {
+            map.set_obstacle(&Grid::new(5, y)).unwrap();
+        }
+        assert!(AStarPlanner::default()
+            .plan(&map, &Grid::new(0, 0), &Grid::new(9, 9))
+            .is_err());
+        assert!(AStarPlanner::default()
+            .plan(&map, &Grid::new(5, 0), &Grid::new(9, 9))
+            .is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use grid_map::{Cell, Error, Grid, GridMap, Position, Result};
+
+use crate::{expand_distance_map_internal, Pose};
+
+/// Goal of the navigation
+///
+/// In addition to a single pose, a set of poses or a polygon region can be used.
+/// A polygon goal is reached anywhere inside of it with any heading, which is
+/// useful for parking areas.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Goal {
+    /// Reach this pose
+    Pose(Pose),
+    /// Reach any of these poses
+    PoseSet(Vec<Pose>),
+    /// Reach anywhere inside this polygon (vertices in order) with any heading
+    Polygon(Vec<Position>),
+}
+
+impl From<Pose> for Goal {
+    fn from(pose: Pose) -> Self {
+        Self::Pose(pose)
+    }
+}
+
+impl Goal {
+    /// Return true if the pose is inside of the goal (position only)
+    pub fn contains(&self, position: &Position, xy_tolerance: f64) -> bool {
+        match self {
+            Goal::Pose(goal) => distance(goal, position) < xy_tolerance,
+            Goal::PoseSet(goals) => goals.iter().any(|g| distance(g, position) < xy_tolerance),
+            Goal::Polygon(vertices) => polygon_contains(vertices, position),
+        }
+    }
+
+    /// Return true if the goal is reached by the pose
+    ///
+    /// The yaw tolerance is ignored for the polygon goal.
+    pub fn is_reached(&self, pose: &Pose, xy_tolerance: f64, yaw_tolerance: f64) -> bool {
+        let position = Position::new(pose.translation.x, pose.translation.y);
+        let reached = |goal: &Pose| {
+            distance(goal, &position) < xy_tolerance
+                && goal.rotation.angle_to(&pose.rotation).abs() < yaw_tolerance
+        };
+        match self {
+            Goal::Pose(goal) => reached(goal),
+            Goal::PoseSet(goals) => goals.iter().any(reached),
+            Goal::Polygon(vertices) => polygon_contains(vertices, &position),
+        }
+    }
+
+    /// All grids of the map which belong to the goal in the world frame and are not
+    /// obstacles
+    pub fn grids(&self, map: &GridMap<u8>) -> Vec<Grid> {
+        let mut grids = match self {
+            Goal::Pose(goal) => map.world_to_grid(&position_of(goal)).into_iter().collect(),
+            Goal::PoseSet(goals) => goals
+                .iter()
+                .filter_map(|g| map.world_to_grid(&position_of(g)))
+                .collect(),
+            Goal::Polygon(vertices) => {
+                let mut grids = vec![];
+                for y in 0..map.height() {
+                    for x in 0..map.width() {
+                        let center = map.map_to_world(&map.cell_center(&Grid::new(x, y)));
+                        if polygon_contains(vertices, &center) {
+                            grids.push(Grid::new(x, y));
+                        }
+                    }
+                }
+                grids
+            }
+        };
+        grids.retain(|g| !matches!(map.cell(g), Some(Cell::Obstacle) | None));
+        grids.dedup();
+        grids
+    }
+
+    /// The goal grid whose center is nearest to the given position, used as the
+    /// target of the global planner
+    pub fn nearest_grid(&self, map: &GridMap<u8>, from: &Position) -> Option<Grid> {
+        let distance = |grid: &Grid| {
+            let center = map.map_to_world(&map.cell_center(grid));
+            (center.x - from.x).powi(2) + (center.y - from.y).powi(2)
+        };
+        self.grids(map)
+            .into_iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+    }
+}
+
+fn position_of(pose: &Pose) -> Position {
+    Position::new(pose.translation.x, pose.translation.y)
+}
+
+fn distance(pose: &Pose, position: &Position) -> f64 {
+    ((pose.translation.x - position.x).powi(2) + (pose.translation.y - position.y).powi(2)).sqrt()
+}
+
+/// Even-odd rule point in polygon test
+pub fn polygon_contains(vertices: &[Position], point: &Position) -> bool {
+    if vertices.len() < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = vertices.len() - 1;
+    for i in 0..vertices.len() {
+        let (vi, vj) = (&vertices[i], &vertices[j]);
+        if (vi.y > point.y) != (vj.y > point.y)
+            && point.x < (vj.x - vi.x) * (point.y - vi.y) / (vj.y - vi.y) + vi.x
+        {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Create goal distance map from every grid of the goal region
+pub fn goal_region_distance_map(map: &GridMap<u8>, goal: &Goal) -> Result<GridMap<u8>> {
+    let goal_grids = goal.grids(map);
+    if goal_grids.is_empty() {
+        return Err(Error::Other("goal region has no free grid".to_owned()));
+    }
+    let mut goal_distance_map = map.copy_without_value();
+    for grid in &goal_grids {
+        goal_distance_map
+            .set_value(grid, 0)
+            .ok_or(Error::OutOfRangeGrid(*grid))?;
+    }
+    expand_distance_map_internal(&mut goal_distance_map, &goal_grids, 0, |v| {
+        v.saturating_add(1)
+    });
+    Ok(goal_distance_map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    fn square() -> Goal {
+        Goal::Polygon(vec![
+            Position::new(0.0, 0.0),
+            Position::new(1.0, 0.0),
+            Position::new(1.0, 1.0),
+            Position::new(0.0, 1.0),
+        ])
+    }
+
+    #[test]
+    fn test_polygon_goal() {
+        let goal = square();
+        assert!(goal.is_reached(&Pose::new(Vector2::new(0.5, 0.5), 3.0), 0.1, 0.1));
+        assert!(!goal.is_reached(&Pose::new(Vector2::new(1.5, 0.5), 0.0), 0.1, 0.1));
+
+        let map = GridMap::<u8>::new(Position::new(-1.0, -1.0), Position::new(2.0, 2.0), 0.1);
+        let nearest = goal.nearest_grid(&map, &Position::new(-1.0, -1.0)).unwrap();
+        assert_eq!(nearest, Grid::new(10, 10));
+        // the grid containing the position, not the one with the nearest corner
+        let nearest = goal.nearest_grid(&map, &Position::new(0.06, 0.06)).unwrap();
+        assert_eq!(nearest, Grid::new(10, 10));
+
+        let distance_map = goal_region_distance_map(&map, &goal).unwrap();
+        assert_eq!(distance_map.value(&Grid::new(15, 15)), Some(0));
+        assert_eq!(distance_map.value(&Grid::new(9, 15)), Some(1));
+    }
+
+    #[test]
+    fn test_pose_set_goal() {
+        let goal = Goal::PoseSet(vec![
+            Pose::new(Vector2::new(0.0, 0.0), 0.0),
+            Pose::new(Vector2::new(1.0, 0.0), 1.0),
+        ]);
+        assert!(goal.is_reached(&Pose::new(Vector2::new(1.05, 0.0), 1.05), 0.1, 0.1));
+        assert!(!goal.is_reached(&Pose::new(Vector2::new(1.05, 0.0), 0.0), 0.1, 0.1));
+        assert!(goal.contains(&Position::new(0.05, 0.0), 0.1));
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{Goal, Pose, Velocity};
+
+/// Check if the robot has reached the goal
+pub trait GoalChecker: fmt::Debug + Send + Sync {
+    fn is_reached(&self, goal: &Goal, pose: &Pose, velocity: &Velocity) -> bool;
+}
+
+/// Goal checker with the tolerances of the position and the yaw
+///
+/// The yaw tolerance is ignored for the polygon goal (see [`Goal::is_reached`]).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SimpleGoalChecker {
+    /// (m)
+    #[serde(default = "default_xy_tolerance")]
+    pub xy_tolerance: f64,
+    /// (rad)
+    #[serde(default = "default_yaw_tolerance")]
+    pub yaw_tolerance: f64,
+}
+
+fn default_xy_tolerance() -> f64 {
+    0.1
+}
+
+fn default_yaw_tolerance() -> f64 {
+    0.4
+}
+
+fn default_stopped_velocity() -> f64 {
+    0.05
+}
+
+impl Default for SimpleGoalChecker {
+    fn default() -> Self {
+        Self::new(default_xy_tolerance(), default_yaw_tolerance())
+    }
+}
+
+impl SimpleGoalChecker {
+    pub fn new(xy_tolerance: f64, yaw_tolerance: f64) -> Self {
+        Self {
+            xy_tolerance,
+            yaw_tolerance,
+        }
+    }
+}
+
+impl GoalChecker for SimpleGoalChecker {
+    fn is_reached(&self, goal: &Goal, pose: &Pose, _velocity: &Velocity) -> bool {
+        goal.is_reached(pose, self.xy_tolerance, self.yaw_tolerance)
+    }
+}
+
+/// [`SimpleGoalChecker`] which also requires the robot to be stopped
+///
+/// The robot is stopped when the absolute values of the velocity are below the
+/// thresholds, so the goal is not reported while the robot is still passing it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StoppedGoalChecker {
+    /// (m)
+    #[serde(default = "default_xy_tolerance")]
+    pub xy_tolerance: f64,
+    /// (rad)
+    #[serde(default = "default_yaw_tolerance")]
+    pub yaw_tolerance: f64,
+    /// (m/s)
+    #[serde(default = "default_stopped_velocity")]
+    pub translational_velocity: f64,
+    /// (rad/s)
+    #[serde(default = "default_stopped_velocity")]
+    pub rotational_velocity: f64,
+}
+
+impl Default for StoppedGoalChecker {
+    fn default() -> Self {
+        Self {
+            xy_tolerance: default_xy_tolerance(),
+            yaw_tolerance: default_yaw_tolerance(),
+            translational_velocity: default_stopped_velocity(),
+            rotational_velocity: default_stopped_velocity(),
+        }
+    }
+}
+
+impl StoppedGoalChecker {
+    pub fn new(
+        goal_checker: SimpleGoalChecker,
+        translational_velocity: f64,
+        rotational_velocity: f64,
+    ) -> Self {
+        Self {
+            xy_tolerance: goal_checker.xy_tolerance,
+            yaw_tolerance: goal_checker.yaw_tolerance,
+            translational_velocity,
+            rotational_velocity,
+        }
+    }
+
+    pub fn is_stopped(&self, velocity: &Velocity) -> bool {
+        velocity.x.abs() < self.translational_velocity
+            && velocity.theta.abs() < self.rotational_velocity
+    }
+}
+
+impl GoalChecker for StoppedGoalChecker {
+    fn is_reached(&self, goal: &Goal, pose: &Pose, velocity: &Velocity) -> bool {
+        self.is_stopped(velocity) && goal.is_reached(pose, self.xy_tolerance, self.yaw_tolerance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+
+    #[test]
+    fn test_goal_checkers() {
+        let goal = Goal::Pose(Pose::new(na::Vector2::new(1.0, 1.0), 0.0));
+        let stopped = Velocity {
+            x: 0.01,
+            theta: 0.0,
+        };
+        let moving = Velocity { x: 0.2, theta: 0.0 };
+        let simple = SimpleGoalChecker::default();
+        let pose = Pose::new(na::Vector2::new(1.05, 1.0), 0.2);
+        assert!(simple.is_reached(&goal, &pose, &moving));
+        assert!(!simple.is_reached(&goal, &Pose::new(na::Vector2::new(1.2, 1.0), 0.0), &stopped));
+        assert!(!simple.is_reached(&goal, &Pose::new(na::Vector2::new(1.0, 1.0), 0.5), &stopped));
+
+        let checker: StoppedGoalChecker = serde_yaml::from_str("xy_tolerance: 0.1").unwrap();
+        assert_eq!(checker, StoppedGoalChecker::new(simple.clone(), 0.05, 0.05));
+        let checkers: [Box<dyn GoalChecker>; 2] = [Box::new(simple), Box::new(checker)];
+        assert!(checkers[1].is_reached(&goal, &pose, &stopped));
+        assert!(!checkers[1].is_reached(&goal, &pose, &moving));
+        assert!(!checkers[1].is_reached(
+            &goal,
+            &pose,
+            &Velocity {
+                x: 0.0,
+                theta: -0.1
+            }
+        ));
+        assert!(checkers[0].is_reached(&goal, &pose, &moving));
+    }
+}
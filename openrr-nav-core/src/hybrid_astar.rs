@@ -0,0 +1,296 @@
+use grid_map::{Grid, GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    f64::consts::TAU,
+};
+
+use crate::{
+    global_planner::{is_free_cell, OpenNode},
+    DijkstraPlanner, Error, Pose, Result,
+};
+
+/// Hybrid A* planner for car-like robots
+///
+/// The search expands the arcs of the minimum turning radius, straight segments and
+/// the arcs bending the other way, forward and, if allowed, backward. The states are
+/// continuous, and pruned by the cell and the discretized heading, so the path is
+/// drivable with the curvature of at most `1 / turning_radius`. The heuristic is the
+/// cost to the goal of [`DijkstraPlanner`], which accounts for the obstacles.
+///
+/// The search ends when a pose within the tolerances of the goal is reached, and the
+/// last pose of the path is that pose (not exactly the goal).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HybridAStarPlanner {
+    /// Minimum turning radius (m)
+    pub turning_radius: f64,
+    /// Length of the motion primitives (m), longer than the diagonal of the cell
+    pub step_length: f64,
+    #[serde(default = "default_num_headings")]
+    pub num_headings: usize,
+    /// Multiplier of the cost of the backward motion. Backward motion is not allowed
+    /// if `None`.
+    #[serde(default = "default_reverse_penalty")]
+    pub reverse_penalty: Option<f64>,
+    /// Additional cost (m) of switching between forward and backward
+    #[serde(default)]
+    pub switch_penalty: f64,
+    /// (m)
+    #[serde(default = "default_goal_tolerance")]
+    pub goal_position_tolerance: f64,
+    /// (rad)
+    #[serde(default = "default_goal_tolerance")]
+    pub goal_yaw_tolerance: f64,
+    #[serde(default = "default_max_expansions")]
+    pub max_expansions: usize,
+    #[serde(default)]
+    pub allow_unknown: bool,
+}
+
+fn default_num_headings() -> usize {
+    72
+}
+
+fn default_reverse_penalty() -> Option<f64> {
+    Some(2.0)
+}
+
+fn default_goal_tolerance() -> f64 {
+    0.1
+}
+
+fn default_max_expansions() -> usize {
+    100_000
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    pose: [f64; 3],
+    g: f64,
+    reverse: bool,
+    parent: Option<usize>,
+}
+
+impl HybridAStarPlanner {
+    pub fn new(turning_radius: f64, step_length: f64) -> Self {
+        Self {
+            turning_radius,
+            step_length,
+            num_headings: default_num_headings(),
+            reverse_penalty: default_reverse_penalty(),
+            switch_penalty: 0.0,
+            goal_position_tolerance: default_goal_tolerance(),
+            goal_yaw_tolerance: default_goal_tolerance(),
+            max_expansions: default_max_expansions(),
+            allow_unknown: false,
+        }
+    }
+
+    /// Move along the arc of the curvature by the signed length
+    fn drive(pose: [f64; 3], curvature: f64, length: f64) -> [f64; 3] {
+        let [x, y, yaw] = pose;
+        if curvature == 0.0 {
+            return [x + length * yaw.cos(), y + length * yaw.sin(), yaw];
+        }
+        let next_yaw = yaw + curvature * length;
+        [
+            x + (next_yaw.sin() - yaw.sin()) / curvature,
+            y + (yaw.cos() - next_yaw.cos()) / curvature,
+            next_yaw.rem_euclid(TAU),
+        ]
+    }
+
+    fn to_grid(map: &GridMap<u8>, pose: &[f64; 3]) -> Option<Grid> {
+        map.world_to_grid(&Position::new(pose[0], pose[1]))
+    }
+
+    /// Plan the poses in the world frame from start to goal
+    pub fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        if self.turning_radius <= 0.0 || self.step_length <= 0.0 || self.num_headings == 0 {
+            return Err(Error::Other(format!(
+                "turning_radius ({}), step_length ({}) and num_headings ({}) must be positive",
+                self.turning_radius, self.step_length, self.num_headings
+            )));
+        }
+        let to_pose = |p: &Pose| {
+            [
+                p.translation.x,
+                p.translation.y,
+                p.rotation.angle().rem_euclid(TAU),
+            ]
+        };
+        let (start, goal) = (to_pose(start), to_pose(goal));
+        let is_free = |pose: &[f64; 3]| {
+            Self::to_grid(map, pose).is_some_and(|g| is_free_cell(map.cell(&g), self.allow_unknown))
+        };
+        if !is_free(&start) {
+            return Err(Error::Other(format!("start {start:?} is not traversable")));
+        }
+        let goal_grid = Self::to_grid(map, &goal)
+            .filter(|_| is_free(&goal))
+            .ok_or_else(|| Error::Other(format!("goal {goal:?} is not traversable")))?;
+        let heuristic = DijkstraPlanner::new()
+            .with_allow_unknown(self.allow_unknown)
+            .navigation_function(map, None, &goal_grid)?;
+        let h = |pose: &[f64; 3]| Self::to_grid(map, pose).and_then(|g| heuristic.cost(&g));
+
+        let heading_bin = |yaw: f64| {
+            ((yaw.rem_euclid(TAU) / TAU * self.num_headings as f64).round() as usize)
+                % self.num_headings
+        };
+        let key =
+            |pose: &[f64; 3]| Self::to_grid(map, pose).map(|g| (g.x, g.y, heading_bin(pose[2])));
+        let is_goal = |pose: &[f64; 3]| {
+            let yaw_diff = na::UnitComplex::new(pose[2])
+                .angle_to(&na::UnitComplex::new(goal[2]))
+                .abs();
+            (pose[0] - goal[0]).hypot(pose[1] - goal[1]) <= self.goal_position_tolerance
+                && yaw_diff <= self.goal_yaw_tolerance
+        };
+        // collision check between the poses of the primitive
+        let num_checks = (self.step_length / (map.resolution() * 0.5))
+            .ceil()
+            .max(1.0) as usize;
+        let curvature = 1.0 / self.turning_radius;
+        let mut directions = vec![(false, 1.0)];
+        if let Some(penalty) = self.reverse_penalty {
+            directions.push((true, penalty.max(0.0)));
+        }
+
+        let mut nodes = vec![Node {
+            pose: start,
+            g: 0.0,
+            reverse: false,
+            parent: None,
+        }];
+        let mut best = HashMap::new();
+        let mut closed = HashSet::new();
+        let mut open = BinaryHeap::new();
+        let start_h =
+            h(&start).ok_or_else(|| Error::Other(format!("goal {goal:?} is not reachable")))?;
+        open.push(OpenNode {
+            f: start_h,
+            tie: start_h,
+            index: 0,
+        });
+        let mut num_expanded = 0;
+        while let Some(OpenNode { index, .. }) = open.pop() {
+            let node = nodes[index];
+            let node_key = key(&node.pose).unwrap();
+            if !closed.insert(node_key) {
+                continue;
+            }
+            if is_goal(&node.pose) {
+                let mut poses = vec![];
+                let mut current = Some(index);
+                while let Some(i) = current {
+                    let [x, y, yaw] = nodes[i].pose;
+                    poses.push(Pose::new(na::Vector2::new(x, y), yaw));
+                    current = nodes[i].parent;
+                }
+                poses.reverse();
+                return Ok(poses);
+            }
+            num_expanded += 1;
+            if num_expanded > self.max_expansions {
+                break;
+            }
+            for &(reverse, scale) in &directions {
+                let length = if reverse {
+                    -self.step_length
+                } else {
+                    self.step_length
+                };
+                for k in [-curvature, 0.0, curvature] {
+                    let collides = (1..=num_checks).any(|i| {
+                        let l = length * i as f64 / num_checks as f64;
+                        !is_free(&Self::drive(node.pose, k, l))
+                    });
+                    if collides {
+                        continue;
+                    }
+                    let pose = Self::drive(node.pose, k, length);
+                    let (Some(next_key), Some(next_h)) = (key(&pose), h(&pose)) else {
+                        continue;
+                    };
+                    if closed.contains(&next_key) {
+                        continue;
+                    }
+                    let mut g = node.g + self.step_length * scale;
+                    if node.parent.is_some() && reverse != node.reverse {
+                        g += self.switch_penalty;
+                    }
+                    if best.get(&next_key).is_some_and(|&b| b <= g) {
+                        continue;
+                    }
+                    best.insert(next_key, g);
+                    nodes.push(Node {
+                        pose,
+                        g,
+                        reverse,
+                        parent: Some(index),
+                    });
+                    open.push(OpenNode {
+                        f: g + next_h,
+                        tie: next_h,
+                        index: nodes.len() - 1,
+                    });
+                }
+            }
+        }
+        Err(Error::Other(format!(
+            "failed to find the path from {start:?} to {goal:?}"
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    #[test]
+    fn test_hybrid_astar() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(4.05, 3.05), 0.05);
+        // wall in the middle with a gap at the top
+        for y in 0..45 {
+            map.set_obstacle(&Grid::new(40, y)).unwrap();
+        }
+        let mut planner = HybridAStarPlanner::new(0.5, 0.1);
+        let start = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let goal = Pose::new(na::Vector2::new(3.5, 0.5), -FRAC_PI_2);
+        for reverse_penalty in [Some(2.0), None] {
+            planner.reverse_penalty = reverse_penalty;
+            let poses = planner.plan(&map, &start, &goal).unwrap();
+            assert_eq!(poses[0], start);
+            let last = poses.last().unwrap();
+            assert!((last.translation.vector - goal.translation.vector).norm() <= 0.1);
+            assert!(last.rotation.angle_to(&goal.rotation).abs() <= 0.1);
+            for (a, b) in poses.iter().zip(poses.iter().skip(1)) {
+                let step = b.translation.vector - a.translation.vector;
+                // the heading is along the motion and the curvature is bounded
+                // the chord of the arc is slightly shorter than the step
+                assert!((0.099..=0.1 + 1e-9).contains(&step.norm()));
+                let heading = step.y.atan2(step.x);
+                let mid = a.rotation.angle() + a.rotation.angle_to(&b.rotation) / 2.0;
+                let along = na::UnitComplex::new(heading).angle_to(&na::UnitComplex::new(mid));
+                assert!(along.abs() < 0.05 || (along.abs() - std::f64::consts::PI).abs() < 0.05);
+                if reverse_penalty.is_none() {
+                    assert!(along.abs() < 0.05);
+                }
+                assert!(a.rotation.angle_to(&b.rotation).abs() <= 0.1 / 0.5 + 1e-9);
+                let grid = map
+                    .world_to_grid(&Position::new(b.translation.x, b.translation.y))
+                    .unwrap();
+                assert!(!map.cell(&grid).unwrap().is_obstacle());
+            }
+            // over the wall
+            assert!(poses.iter().any(|p| p.translation.y > 2.25));
+        }
+
+        planner.turning_radius = 0.0;
+        assert!(planner.plan(&map, &start, &goal).is_err());
+    }
+}
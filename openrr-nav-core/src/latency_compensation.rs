@@ -0,0 +1,130 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, SystemTime},
+};
+
+use crate::{dwa_planner::velocity_to_pose, Pose, Velocity};
+
+/// Maximum step (s) of the integration of the commands
+const INTEGRATION_STEP: f64 = 0.01;
+
+/// Extrapolate the pose by the actuation latency of the drive controller
+///
+/// The velocities commanded within the last `latency` are not executed yet when the
+/// pose is measured, so the robot will still move by them before the next command
+/// takes effect. Planning from the extrapolated pose reduces the overshoot of the
+/// robots with slow drive controllers. The latency of zero disables the
+/// compensation.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyCompensator {
+    latency: Duration,
+    /// Commanded velocities and their time, in the order of the time
+    history: VecDeque<(SystemTime, Velocity)>,
+}
+
+impl LatencyCompensator {
+    pub fn new(latency: Duration) -> Self {
+        Self {
+            latency,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn latency(&self) -> Duration {
+        self.latency
+    }
+
+    /// The last commanded velocity, which the robot will be running at
+    pub fn last_command(&self) -> Option<Velocity> {
+        self.history.back().map(|(_, v)| *v)
+    }
+
+    /// Record the velocity commanded at the time
+    ///
+    /// Commands older than the latency are dropped, except the last one of them,
+    /// which is still being executed at the beginning of the window.
+    pub fn push(&mut self, time: SystemTime, velocity: Velocity) {
+        if self.history.back().is_some_and(|(t, _)| *t > time) {
+            // the clock went back
+            self.history.clear();
+        }
+        self.history.push_back((time, velocity));
+        let start = time.checked_sub(self.latency).unwrap_or(time);
+        while self.history.len() > 1 && self.history[1].0 <= start {
+            self.history.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Pose after the commands of the last `latency` before `now` are executed
+    /// from the measured pose
+    pub fn compensate(&self, pose: &Pose, now: SystemTime) -> Pose {
+        let Some(start) = now.checked_sub(self.latency) else {
+            return *pose;
+        };
+        let mut compensated = *pose;
+        for (i, (time, velocity)) in self.history.iter().enumerate() {
+            let begin = (*time).max(start);
+            let end = self
+                .history
+                .get(i + 1)
+                .map_or(now, |(next, _)| *next)
+                .min(now);
+            let Ok(duration) = end.duration_since(begin) else {
+                continue;
+            };
+            let duration = duration.as_secs_f64();
+            let steps = (duration / INTEGRATION_STEP).ceil() as usize;
+            if steps == 0 {
+                continue;
+            }
+            let step = velocity_to_pose(velocity, duration / steps as f64);
+            for _ in 0..steps {
+                compensated *= step;
+            }
+        }
+        compensated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector2;
+
+    #[test]
+    fn test_latency_compensation() {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        let ms = |n| t0 + Duration::from_millis(n);
+        let pose = Pose::new(Vector2::new(1.0, 2.0), 0.0);
+
+        let mut compensator = LatencyCompensator::new(Duration::ZERO);
+        compensator.push(t0, Velocity { x: 1.0, theta: 0.0 });
+        assert_eq!(compensator.compensate(&pose, ms(100)), pose);
+
+        let mut compensator = LatencyCompensator::new(Duration::from_millis(200));
+        assert_eq!(compensator.compensate(&pose, t0), pose);
+        compensator.push(t0, Velocity { x: 0.5, theta: 0.0 });
+        compensator.push(ms(300), Velocity { x: 1.0, theta: 0.0 });
+        compensator.push(ms(400), Velocity { x: 0.0, theta: 0.0 });
+        // 0.5 m/s from 200 ms to 300 ms and 1.0 m/s from 300 ms to 400 ms
+        let compensated = compensator.compensate(&pose, ms(400));
+        assert!((compensated.translation.x - 1.15).abs() < 1e-9);
+        assert!((compensated.translation.y - 2.0).abs() < 1e-9);
+        // the first command is still executed at the beginning of the window
+        assert_eq!(compensator.history.len(), 3);
+        compensator.push(ms(600), Velocity { x: 0.0, theta: 0.0 });
+        assert_eq!(compensator.history.len(), 2);
+        assert_eq!(compensator.last_command(), Some(Velocity::default()));
+
+        // turning in place
+        let mut compensator = LatencyCompensator::new(Duration::from_millis(500));
+        compensator.push(t0, Velocity { x: 0.0, theta: 1.0 });
+        let compensated = compensator.compensate(&pose, ms(250));
+        assert!((compensated.rotation.angle() - 0.25).abs() < 1e-9);
+        assert!((compensated.translation.vector - pose.translation.vector).norm() < 1e-9);
+    }
+}
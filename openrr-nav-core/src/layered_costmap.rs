@@ -0,0 +1,503 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use std::{any::Any, fmt};
+
+use crate::{inflate_obstacles, Pose, RangeReading, Result, ScanIntegrator};
+
+/// Region of the costmap updated in a cycle
+///
+/// The positions are in the frame of the map, like the positions of
+/// [`GridMap::to_grid`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Position,
+    pub max: Position,
+}
+
+impl Default for Bounds {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Bounds {
+    /// Bounds which contain nothing
+    pub fn empty() -> Self {
+        Self {
+            min: Position::new(f64::INFINITY, f64::INFINITY),
+            max: Position::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// Bounds of the whole map
+    pub fn of_map<T: Clone>(map: &GridMap<T>) -> Self {
+        Self {
+            min: *map.min_point(),
+            max: *map.max_point(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y
+    }
+
+    /// Grow the bounds to include the position
+    pub fn include(&mut self, position: &Position) {
+        self.min = Position::new(self.min.x.min(position.x), self.min.y.min(position.y));
+        self.max = Position::new(self.max.x.max(position.x), self.max.y.max(position.y));
+    }
+
+    /// Grow the bounds to include the other bounds
+    pub fn merge(&mut self, other: &Bounds) {
+        if !other.is_empty() {
+            self.include(&other.min);
+            self.include(&other.max);
+        }
+    }
+
+    /// Grow the bounds by the margin (m) on each side
+    pub fn expand(&mut self, margin: f64) {
+        if !self.is_empty() {
+            self.min = Position::new(self.min.x - margin, self.min.y - margin);
+            self.max = Position::new(self.max.x + margin, self.max.y + margin);
+        }
+    }
+
+    /// Grids of the map inside of the bounds
+    pub fn grids<T: Clone>(&self, map: &GridMap<T>) -> impl Iterator<Item = Grid> {
+        let range = |min: f64, max: f64, origin: f64, len: usize| {
+            if self.is_empty() || len == 0 {
+                return 0..0;
+            }
+            let to_index = |v: f64| {
+                ((v - origin) / map.resolution())
+                    .floor()
+                    .clamp(0.0, len as f64 - 1.0)
+            };
+            let (start, end) = (to_index(min), to_index(max));
+            if max < origin || min >= origin + len as f64 * map.resolution() {
+                0..0
+            } else {
+                start as usize..end as usize + 1
+            }
+        };
+        let xs = range(self.min.x, self.max.x, map.min_point().x, map.width());
+        let ys = range(self.min.y, self.max.y, map.min_point().y, map.height());
+        ys.flat_map(move |y| xs.clone().map(move |x| Grid::new(x, y)))
+    }
+}
+
+/// Plugin of [`LayeredCostmap`] like the layers of `costmap_2d` of ROS
+///
+/// In every [`LayeredCostmap::update`], all layers expand the bounds in
+/// [`Layer::update_bounds`] first, the master costmap is reset to free in the bounds,
+/// and then the layers write their costs into the master in
+/// [`Layer::update_costs`] in the order of the layers. The costs are
+/// [`Cell::Obstacle`] for the lethal cells and [`Cell::Value`] otherwise, as the
+/// other maps of this crate.
+pub trait Layer: Any + fmt::Debug + Send + Sync {
+    /// Expand the bounds to include the region changed by the layer
+    fn update_bounds(&mut self, robot_pose: &Pose, bounds: &mut Bounds);
+
+    /// Write the costs into the master costmap within the bounds
+    fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()>;
+
+    /// Forget the state of the layer
+    fn reset(&mut self) {}
+}
+
+/// Layer which copies the static map, e.g. the map loaded from the file
+///
+/// The map must have the same geometry as the master costmap.
+#[derive(Debug, Clone)]
+pub struct StaticLayer {
+    map: GridMap<u8>,
+    updated: bool,
+}
+
+impl StaticLayer {
+    pub fn new(map: GridMap<u8>) -> Self {
+        Self { map, updated: true }
+    }
+
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    /// Replace the map. The whole map is updated in the next cycle.
+    pub fn set_map(&mut self, map: GridMap<u8>) {
+        self.map = map;
+        self.updated = true;
+    }
+}
+
+impl Layer for StaticLayer {
+    fn update_bounds(&mut self, _robot_pose: &Pose, bounds: &mut Bounds) {
+        if self.updated {
+            bounds.merge(&Bounds::of_map(&self.map));
+            self.updated = false;
+        }
+    }
+
+    fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()> {
+        for grid in bounds.grids(master) {
+            if let (Some(cell), Some(master_cell)) = (self.map.cell(&grid), master.cell_mut(&grid))
+            {
+                *master_cell = *cell;
+            }
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.updated = true;
+    }
+}
+
+/// Layer which marks and clears the obstacles by the range scans with
+/// [`ScanIntegrator`]
+///
+/// The observed obstacles are kept until they are cleared by the later scans, and
+/// the known free cells replace the Unknown cells of the lower layers.
+#[derive(Debug, Clone)]
+pub struct ObstacleLayer {
+    pub integrator: ScanIntegrator,
+    observations: Option<GridMap<u8>>,
+    scans: Vec<(Pose, Vec<RangeReading>)>,
+}
+
+impl ObstacleLayer {
+    pub fn new(integrator: ScanIntegrator) -> Self {
+        Self {
+            integrator,
+            observations: None,
+            scans: vec![],
+        }
+    }
+
+    /// Add the scan taken at the robot pose, which is integrated in the next update
+    pub fn add_scan(&mut self, robot_pose: Pose, readings: Vec<RangeReading>) {
+        self.scans.push((robot_pose, readings));
+    }
+
+    /// Obstacles and free cells observed so far
+    pub fn observations(&self) -> Option<&GridMap<u8>> {
+        self.observations.as_ref()
+    }
+}
+
+impl Layer for ObstacleLayer {
+    fn update_bounds(&mut self, _robot_pose: &Pose, bounds: &mut Bounds) {
+        let range = self.integrator.max_range;
+        for (pose, _) in &self.scans {
+            let sensor = pose * self.integrator.sensor_pose;
+            let mut scan_bounds = Bounds::empty();
+            scan_bounds.include(&Position::new(sensor.translation.x, sensor.translation.y));
+            scan_bounds.expand(range);
+            bounds.merge(&scan_bounds);
+        }
+    }
+
+    fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()> {
+        let observations = self
+            .observations
+            .get_or_insert_with(|| master.copy_without_value());
+        for (pose, readings) in self.scans.drain(..) {
+            self.integrator.integrate(observations, &pose, &readings);
+        }
+        for grid in bounds.grids(master) {
+            let (Some(observed), Some(cell)) = (observations.cell(&grid), master.cell_mut(&grid))
+            else {
+                continue;
+            };
+            match observed {
+                Cell::Obstacle => *cell = Cell::Obstacle,
+                Cell::Value(_) if matches!(cell, Cell::Unknown | Cell::Uninitialized) => {
+                    *cell = Cell::Value(0);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.observations = None;
+        self.scans.clear();
+    }
+}
+
+/// Layer which inflates the obstacles of the lower layers by [`inflate_obstacles`]
+///
+/// The cost is the maximum of the inflation and the cost of the lower layers. Put
+/// it after the layers which add the obstacles.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InflationLayer {
+    pub inscribed_radius: f64,
+    pub inflation_radius: f64,
+    pub cost_scaling: f64,
+}
+
+impl InflationLayer {
+    pub fn new(inscribed_radius: f64, inflation_radius: f64, cost_scaling: f64) -> Self {
+        Self {
+            inscribed_radius,
+            inflation_radius,
+            cost_scaling,
+        }
+    }
+}
+
+impl Layer for InflationLayer {
+    fn update_bounds(&mut self, _robot_pose: &Pose, bounds: &mut Bounds) {
+        // the obstacles in the bounds change the costs around them
+        bounds.expand(self.inflation_radius);
+    }
+
+    fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()> {
+        if bounds.is_empty() {
+            return Ok(());
+        }
+        let inflation = inflate_obstacles(
+            master,
+            self.inscribed_radius,
+            self.inflation_radius,
+            self.cost_scaling,
+        )?;
+        for grid in bounds.grids(master) {
+            if let (Some(Cell::Value(cost)), Some(Cell::Value(v))) =
+                (inflation.cell(&grid), master.cell_mut(&grid))
+            {
+                *v = (*v).max(*cost);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Costmap composed of the [`Layer`]s updated in order
+///
+/// ```
+/// # use grid_map::{GridMap, Position};
+/// # use openrr_nav_core::*;
+/// let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.0, 2.0), 0.05);
+/// let mut costmap = LayeredCostmap::new(&map);
+/// costmap.add_layer("static", StaticLayer::new(map));
+/// costmap.add_layer("obstacles", ObstacleLayer::new(ScanIntegrator::new(3.0)));
+/// costmap.add_layer("inflation", InflationLayer::new(0.1, 0.4, 5.0));
+/// costmap
+///     .layer_mut::<ObstacleLayer>("obstacles")
+///     .unwrap()
+///     .add_scan(Pose::identity(), vec![RangeReading::new(0.0, 1.0)]);
+/// costmap.update(&Pose::identity()).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct LayeredCostmap {
+    master: GridMap<u8>,
+    layers: Vec<(String, Box<dyn Layer>)>,
+}
+
+impl LayeredCostmap {
+    /// Create the master costmap of the same geometry as the map, free everywhere
+    pub fn new(map: &GridMap<u8>) -> Self {
+        let mut master = map.copy_without_value();
+        for cell in master.cells_mut() {
+            *cell = Cell::Value(0);
+        }
+        Self {
+            master,
+            layers: vec![],
+        }
+    }
+
+    /// Add the layer on top of the layers, replacing the one of the same name
+    pub fn add_layer(&mut self, name: impl Into<String>, layer: impl Layer) {
+        let name = name.into();
+        let layer: Box<dyn Layer> = Box::new(layer);
+        match self.layers.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, old)) => *old = layer,
+            None => self.layers.push((name, layer)),
+        }
+    }
+
+    pub fn remove_layer(&mut self, name: &str) -> Option<Box<dyn Layer>> {
+        let index = self.layers.iter().position(|(n, _)| n == name)?;
+        Some(self.layers.remove(index).1)
+    }
+
+    /// Names of the layers in the order of the update
+    pub fn layer_names(&self) -> impl Iterator<Item = &str> {
+        self.layers.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Layer of the name if it is `L`
+    pub fn layer<L: Layer>(&self, name: &str) -> Option<&L> {
+        let (_, layer) = self.layers.iter().find(|(n, _)| n == name)?;
+        (&**layer as &dyn Any).downcast_ref()
+    }
+
+    /// Layer of the name if it is `L`, e.g. to add the observations
+    pub fn layer_mut<L: Layer>(&mut self, name: &str) -> Option<&mut L> {
+        let (_, layer) = self.layers.iter_mut().find(|(n, _)| n == name)?;
+        (&mut **layer as &mut dyn Any).downcast_mut()
+    }
+
+    /// The master costmap
+    pub fn costmap(&self) -> &GridMap<u8> {
+        &self.master
+    }
+
+    /// Run the update pipeline of the layers, and return the updated bounds
+    pub fn update(&mut self, robot_pose: &Pose) -> Result<Bounds> {
+        let mut bounds = Bounds::empty();
+        for (_, layer) in &mut self.layers {
+            layer.update_bounds(robot_pose, &mut bounds);
+        }
+        for grid in bounds.grids(&self.master) {
+            if let Some(cell) = self.master.cell_mut(&grid) {
+                *cell = Cell::Value(0);
+            }
+        }
+        for (_, layer) in &mut self.layers {
+            layer.update_costs(&mut self.master, &bounds)?;
+        }
+        Ok(bounds)
+    }
+
+    /// Reset the layers, and update the whole map in the next update
+    pub fn reset(&mut self) {
+        for (_, layer) in &mut self.layers {
+            layer.reset();
+        }
+        for cell in self.master.cells_mut() {
+            *cell = Cell::Value(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::INSCRIBED_INFLATED_COST;
+    use nalgebra as na;
+
+    /// Custom layer which adds the cost to the cells within the radius of a point
+    #[derive(Debug)]
+    struct KeepAwayLayer {
+        center: Position,
+        radius: f64,
+    }
+
+    impl Layer for KeepAwayLayer {
+        fn update_bounds(&mut self, _robot_pose: &Pose, bounds: &mut Bounds) {
+            let mut own = Bounds::empty();
+            own.include(&self.center);
+            own.expand(self.radius);
+            bounds.merge(&own);
+        }
+
+        fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()> {
+            for grid in bounds.grids(master).collect::<Vec<_>>() {
+                let p = master.cell_center(&grid);
+                if (p.x - self.center.x).hypot(p.y - self.center.y) <= self.radius {
+                    if let Some(Cell::Value(v)) = master.cell_mut(&grid) {
+                        *v = (*v).max(100);
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(1.0, 0.5), 0.1);
+        let mut bounds = Bounds::empty();
+        assert!(bounds.is_empty());
+        assert_eq!(bounds.grids(&map).count(), 0);
+        bounds.include(&Position::new(0.25, 0.15));
+        assert_eq!(bounds.grids(&map).collect::<Vec<_>>(), [Grid::new(2, 1)]);
+        bounds.expand(0.1);
+        assert_eq!(bounds.grids(&map).count(), 9);
+        bounds.merge(&Bounds::of_map(&map));
+        assert_eq!(bounds.grids(&map).count(), map.len());
+        let mut outside = Bounds::empty();
+        outside.include(&Position::new(-1.0, -1.0));
+        assert_eq!(outside.grids(&map).count(), 0);
+    }
+
+    #[test]
+    fn test_layered_costmap() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for grid in map.enumerate_cells().map(|(g, ..)| g).collect::<Vec<_>>() {
+            map.set_value(&grid, 0).unwrap();
+        }
+        // wall in the static map
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(50, y)).unwrap();
+        }
+        let mut costmap = LayeredCostmap::new(&map);
+        costmap.add_layer("static", StaticLayer::new(map.clone()));
+        costmap.add_layer("obstacles", ObstacleLayer::new(ScanIntegrator::new(1.0)));
+        costmap.add_layer(
+            "social",
+            KeepAwayLayer {
+                center: Position::new(0.5, 1.5),
+                radius: 0.2,
+            },
+        );
+        costmap.add_layer("inflation", InflationLayer::new(0.1, 0.4, 5.0));
+        assert_eq!(
+            costmap.layer_names().collect::<Vec<_>>(),
+            ["static", "obstacles", "social", "inflation"]
+        );
+        assert!(costmap.layer::<StaticLayer>("obstacles").is_none());
+
+        let robot = Pose::new(na::Vector2::new(1.0, 1.0), 0.0);
+        let bounds = costmap.update(&robot).unwrap();
+        assert_eq!(bounds.grids(costmap.costmap()).count(), map.len());
+        let at = |costmap: &LayeredCostmap, x: f64, y: f64| {
+            let grid = costmap.costmap().to_grid(x, y).unwrap();
+            *costmap.costmap().cell(&grid).unwrap()
+        };
+        assert_eq!(at(&costmap, 2.525, 1.0), Cell::Obstacle);
+        assert_eq!(
+            at(&costmap, 2.425, 1.0),
+            Cell::Value(INSCRIBED_INFLATED_COST)
+        );
+        assert_eq!(at(&costmap, 0.5, 1.5), Cell::Value(100));
+        assert_eq!(at(&costmap, 1.0, 0.5), Cell::Value(0));
+
+        // an obstacle observed by the scan is inflated
+        costmap
+            .layer_mut::<ObstacleLayer>("obstacles")
+            .unwrap()
+            .add_scan(
+                robot,
+                vec![RangeReading::new(-std::f64::consts::FRAC_PI_2, 0.5)],
+            );
+        let bounds = costmap.update(&robot).unwrap();
+        assert!(bounds.grids(costmap.costmap()).count() < map.len());
+        assert_eq!(at(&costmap, 1.0, 0.5), Cell::Obstacle);
+        assert!(matches!(at(&costmap, 1.0, 0.6), Cell::Value(c) if c > 0));
+        // the static obstacles out of the bounds are kept
+        assert_eq!(at(&costmap, 2.525, 1.0), Cell::Obstacle);
+
+        // cleared by the next scan
+        costmap
+            .layer_mut::<ObstacleLayer>("obstacles")
+            .unwrap()
+            .add_scan(
+                robot,
+                vec![RangeReading::new(-std::f64::consts::FRAC_PI_2, 0.9)],
+            );
+        costmap.update(&robot).unwrap();
+        assert!(matches!(at(&costmap, 1.0, 0.5), Cell::Value(_)));
+        assert_eq!(at(&costmap, 2.525, 1.0), Cell::Obstacle);
+
+        assert!(costmap.remove_layer("social").is_some());
+        costmap.reset();
+        costmap.update(&robot).unwrap();
+        assert_eq!(at(&costmap, 0.5, 1.5), Cell::Value(0));
+        assert_eq!(at(&costmap, 2.525, 1.0), Cell::Obstacle);
+    }
+}
@@ -0,0 +1,91 @@
+// mod angle_table;
+mod collision_monitor;
+mod cost_map;
+mod door;
+mod dwa_planner;
+mod dynamic_distance_map;
+mod error;
+mod footprint;
+mod global_planner;
+mod goal;
+mod goal_checker;
+mod hybrid_astar;
+mod latency_compensation;
+mod layered_costmap;
+mod lifecycle;
+mod local_planner;
+mod mission;
+mod mppi;
+mod navigator;
+mod obstacle_memory;
+mod obstacle_tracker;
+mod odometry;
+mod particle_filter;
+pub mod path;
+mod path_smoother;
+mod path_validity;
+mod planner_registry;
+mod pose_estimate;
+mod potential_field;
+mod pure_pursuit;
+mod recovery;
+mod resolution_advisor;
+mod robot_path;
+mod rrt_star;
+mod sampling;
+mod scan_integrator;
+mod scan_matcher;
+mod self_test;
+mod social_layer;
+mod teb;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+pub mod utils;
+mod velocity_smoother;
+mod waypoint_follower;
+mod zone;
+mod zone_schedule;
+
+// pub use crate::angle_table::*;
+pub use crate::collision_monitor::*;
+pub use crate::cost_map::*;
+pub use crate::door::*;
+pub use crate::dwa_planner::*;
+pub use crate::dynamic_distance_map::*;
+pub use crate::error::*;
+pub use crate::footprint::*;
+pub use crate::global_planner::*;
+pub use crate::goal::*;
+pub use crate::goal_checker::*;
+pub use crate::hybrid_astar::*;
+pub use crate::latency_compensation::*;
+pub use crate::layered_costmap::*;
+pub use crate::lifecycle::*;
+pub use crate::local_planner::*;
+pub use crate::mission::*;
+pub use crate::mppi::*;
+pub use crate::navigator::*;
+pub use crate::obstacle_memory::*;
+pub use crate::obstacle_tracker::*;
+pub use crate::odometry::*;
+pub use crate::particle_filter::*;
+pub use crate::path_smoother::*;
+pub use crate::path_validity::*;
+pub use crate::planner_registry::*;
+pub use crate::pose_estimate::*;
+pub use crate::potential_field::*;
+pub use crate::pure_pursuit::*;
+pub use crate::recovery::*;
+pub use crate::resolution_advisor::*;
+pub use crate::robot_path::*;
+pub use crate::rrt_star::*;
+pub use crate::sampling::*;
+pub use crate::scan_integrator::*;
+pub use crate::scan_matcher::*;
+pub use crate::self_test::*;
+pub use crate::social_layer::*;
+pub use crate::teb::*;
+pub use crate::velocity_smoother::*;
+pub use crate::waypoint_follower::*;
+pub use crate::zone::*;
+pub use crate::zone_schedule::*;
@@ -0,0 +1,231 @@
+use crate::{Error, Result};
+
+/// State of the managed component, following the lifecycle of nav2 (ROS 2 managed nodes)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum LifecycleState {
+    /// Created, but the parameters are not loaded yet
+    #[default]
+    Unconfigured,
+    /// Configured, but doesn't accept commands
+    Inactive,
+    /// Accepts commands
+    Active,
+    /// Shut down. No transition is possible anymore.
+    Finalized,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LifecycleTransition {
+    /// Unconfigured -> Inactive
+    Configure,
+    /// Inactive -> Active
+    Activate,
+    /// Active -> Inactive
+    Deactivate,
+    /// Inactive -> Unconfigured
+    Cleanup,
+    /// Any state except Finalized -> Finalized
+    Shutdown,
+}
+
+impl LifecycleTransition {
+    /// State after the transition from the state, `None` if it is not allowed
+    pub fn target(self, from: LifecycleState) -> Option<LifecycleState> {
+        use LifecycleState::*;
+        use LifecycleTransition::*;
+        match (self, from) {
+            (Configure, Unconfigured) => Some(Inactive),
+            (Activate, Inactive) => Some(Active),
+            (Deactivate, Active) => Some(Inactive),
+            (Cleanup, Inactive) => Some(Unconfigured),
+            (Shutdown, Unconfigured | Inactive | Active) => Some(Finalized),
+            _ => None,
+        }
+    }
+}
+
+/// Callbacks of the transitions. All of them do nothing by default.
+///
+/// If a callback fails, the state is not changed.
+pub trait LifecycleNode {
+    /// Load the parameters and allocate the resources
+    fn on_configure(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Start processing the commands
+    fn on_activate(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Stop the motion and the processing of the commands
+    fn on_deactivate(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Release the resources allocated by [`LifecycleNode::on_configure`]
+    fn on_cleanup(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called from any state. An active node should stop the motion here as well.
+    fn on_shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Node with the lifecycle state, which gives mutable access only while it is active
+///
+/// Supervisors drive the transitions explicitly in the bring-up and the shutdown,
+/// so the node never receives commands before it is configured and activated.
+#[derive(Debug)]
+pub struct Managed<N> {
+    node: N,
+    state: LifecycleState,
+}
+
+impl<N> Managed<N>
+where
+    N: LifecycleNode,
+{
+    pub fn new(node: N) -> Self {
+        Self {
+            node,
+            state: LifecycleState::Unconfigured,
+        }
+    }
+
+    pub fn state(&self) -> LifecycleState {
+        self.state
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.state == LifecycleState::Active
+    }
+
+    /// Read the node in any state
+    pub fn node(&self) -> &N {
+        &self.node
+    }
+
+    /// Mutable access to the node to send commands, which fails unless active
+    pub fn node_mut(&mut self) -> Result<&mut N> {
+        if !self.is_active() {
+            return Err(Error::NotActive(self.state));
+        }
+        Ok(&mut self.node)
+    }
+
+    /// Run the callback of the transition and move to the next state
+    ///
+    /// Returns the new state. The transition which is not allowed from the current
+    /// state fails without calling the callback.
+    pub fn trigger(&mut self, transition: LifecycleTransition) -> Result<LifecycleState> {
+        let target = transition
+            .target(self.state)
+            .ok_or(Error::InvalidTransition {
+                state: self.state,
+                transition,
+            })?;
+        match transition {
+            LifecycleTransition::Configure => self.node.on_configure(),
+            LifecycleTransition::Activate => self.node.on_activate(),
+            LifecycleTransition::Deactivate => self.node.on_deactivate(),
+            LifecycleTransition::Cleanup => self.node.on_cleanup(),
+            LifecycleTransition::Shutdown => self.node.on_shutdown(),
+        }?;
+        self.state = target;
+        Ok(target)
+    }
+
+    pub fn configure(&mut self) -> Result<LifecycleState> {
+        self.trigger(LifecycleTransition::Configure)
+    }
+
+    pub fn activate(&mut self) -> Result<LifecycleState> {
+        self.trigger(LifecycleTransition::Activate)
+    }
+
+    pub fn deactivate(&mut self) -> Result<LifecycleState> {
+        self.trigger(LifecycleTransition::Deactivate)
+    }
+
+    pub fn cleanup(&mut self) -> Result<LifecycleState> {
+        self.trigger(LifecycleTransition::Cleanup)
+    }
+
+    pub fn shutdown(&mut self) -> Result<LifecycleState> {
+        self.trigger(LifecycleTransition::Shutdown)
+    }
+
+    /// Take the node back, e.g. after the shutdown
+    pub fn into_inner(self) -> N {
+        self.node
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default)]
+    struct Counter {
+        configured: bool,
+        fail_activate: bool,
+        commands: usize,
+    }
+
+    impl LifecycleNode for Counter {
+        fn on_configure(&mut self) -> Result<()> {
+            self.configured = true;
+            Ok(())
+        }
+        fn on_activate(&mut self) -> Result<()> {
+            if self.fail_activate {
+                return Err(Error::Other("activation failed".to_owned()));
+            }
+            Ok(())
+        }
+        fn on_cleanup(&mut self) -> Result<()> {
+            self.configured = false;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        use LifecycleState::*;
+        let mut managed = Managed::new(Counter {
+            fail_activate: true,
+            ..Default::default()
+        });
+        assert!(matches!(
+            managed.node_mut(),
+            Err(Error::NotActive(Unconfigured))
+        ));
+        assert!(matches!(
+            managed.activate(),
+            Err(Error::InvalidTransition {
+                state: Unconfigured,
+                transition: LifecycleTransition::Activate
+            })
+        ));
+
+        assert_eq!(managed.configure().unwrap(), Inactive);
+        assert!(managed.node().configured);
+        // the state is kept if the callback fails
+        assert!(managed.activate().is_err());
+        assert_eq!(managed.state(), Inactive);
+        assert!(managed.node_mut().is_err());
+
+        assert_eq!(managed.cleanup().unwrap(), Unconfigured);
+        assert!(!managed.node().configured);
+        managed.configure().unwrap();
+        managed.node.fail_activate = false;
+        assert_eq!(managed.activate().unwrap(), Active);
+        managed.node_mut().unwrap().commands += 1;
+        assert_eq!(managed.deactivate().unwrap(), Inactive);
+        assert!(managed.node_mut().is_err());
+
+        assert_eq!(managed.shutdown().unwrap(), Finalized);
+        assert!(managed.configure().is_err());
+        assert!(managed.shutdown().is_err());
+        assert_eq!(managed.into_inner().commands, 1);
+    }
+}
@@ -0,0 +1,286 @@
+use grid_map::{GridMap, LayerId, LayeredGridMap};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt, fs, path::Path};
+
+use crate::{
+    DiagnosticLevel, DwaPlanner, Error, MppiController, Plan, Pose, PurePursuitController, Result,
+    SelfTestReport, TebPlanner, Velocity, ZoneLayer,
+};
+
+/// Controller which computes the velocity command in every control cycle
+///
+/// `maps` and `angles` are the cost layers of the [`DwaPlanner`] (see
+/// [`Navigator`](crate::Navigator) for the layers built for the global path), and
+/// `global_path` is the remaining waypoints `[x, y, theta]`. Each planner uses the
+/// part of them it needs. The planners which need the obstacles use the `obstacle`
+/// layer.
+///
+/// An error is returned if the inputs are missing. A plan without a path or with
+/// the infinite cost means that there is no feasible velocity.
+pub trait LocalPlanner: fmt::Debug + Send + Sync {
+    fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        global_path: &[Vec<f64>],
+    ) -> Result<Plan>;
+
+    /// (s) Period of the control cycle assumed by the planner
+    fn controller_dt(&self) -> f64;
+
+    /// Forget the state kept between the cycles, e.g. when the goal is changed
+    fn reset(&self) {}
+
+    /// Replace the keep-out and speed limit zones. The zones are ignored by default.
+    fn set_zones(&mut self, _zones: ZoneLayer) {}
+
+    /// Validate the configuration with the layers, see [`DwaPlanner::self_test`]
+    ///
+    /// The report has only a warning by default.
+    fn self_test(
+        &self,
+        _maps: &LayeredGridMap<u8>,
+        _angles: &HashMap<LayerId, f64>,
+        _robot_radius: f64,
+    ) -> SelfTestReport {
+        let mut report = SelfTestReport::default();
+        report.push(
+            DiagnosticLevel::Warn,
+            "self_test",
+            format!("not supported by {self:?}"),
+        );
+        report
+    }
+}
+
+/// Layer used as the occupancy by the planners which check the obstacles
+fn obstacle_layer(maps: &LayeredGridMap<u8>) -> Result<&GridMap<u8>> {
+    maps.layer(LayerId::OBSTACLE)
+        .ok_or_else(|| Error::Other(format!("no \"{}\" layer", LayerId::OBSTACLE)))
+}
+
+impl LocalPlanner for DwaPlanner {
+    fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        _global_path: &[Vec<f64>],
+    ) -> Result<Plan> {
+        // the layers may have the different geometries, e.g. `local_goal` of the Navigator
+        self.check_weighted_layers(maps, angles)?;
+        Ok(self.plan_local_path(pose, velocity, maps, angles))
+    }
+
+    fn controller_dt(&self) -> f64 {
+        DwaPlanner::controller_dt(self)
+    }
+
+    fn set_zones(&mut self, zones: ZoneLayer) {
+        DwaPlanner::set_zones(self, zones);
+    }
+
+    fn self_test(
+        &self,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        robot_radius: f64,
+    ) -> SelfTestReport {
+        DwaPlanner::self_test(self, maps, angles, robot_radius)
+    }
+}
+
+impl LocalPlanner for MppiController {
+    fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        _global_path: &[Vec<f64>],
+    ) -> Result<Plan> {
+        Ok(self.plan_local_path(pose, velocity, maps, angles))
+    }
+
+    fn controller_dt(&self) -> f64 {
+        MppiController::controller_dt(self)
+    }
+
+    fn reset(&self) {
+        MppiController::reset(self);
+    }
+}
+
+impl LocalPlanner for PurePursuitController {
+    fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        _angles: &HashMap<LayerId, f64>,
+        global_path: &[Vec<f64>],
+    ) -> Result<Plan> {
+        Ok(PurePursuitController::plan(
+            self,
+            pose,
+            velocity,
+            global_path,
+            obstacle_layer(maps)?,
+        ))
+    }
+
+    fn controller_dt(&self) -> f64 {
+        self.controller_dt
+    }
+}
+
+impl LocalPlanner for TebPlanner {
+    fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        _angles: &HashMap<LayerId, f64>,
+        global_path: &[Vec<f64>],
+    ) -> Result<Plan> {
+        Ok(TebPlanner::plan(
+            self,
+            pose,
+            velocity,
+            global_path,
+            obstacle_layer(maps)?,
+        ))
+    }
+
+    fn controller_dt(&self) -> f64 {
+        self.controller_dt
+    }
+}
+
+/// Local planner selected by the configuration
+///
+/// ```yaml
+/// type: pure_pursuit
+/// limits:
+///   max_velocity: [0.5, 2.0]
+///   max_acceleration: [1.0, 4.0]
+///   min_velocity: [0.0, -2.0]
+///   min_acceleration: [-1.0, -4.0]
+/// controller_dt: 0.1
+/// ```
+///
+/// The config of [`DwaPlanner::new_from_config_text`] (with the top-level
+/// `DwaPlanner` key) is also accepted by
+/// [`LocalPlannerConfig::new_from_config_text`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LocalPlannerConfig {
+    Dwa(DwaPlanner),
+    PurePursuit(PurePursuitController),
+    Mppi(Box<MppiController>),
+    Teb(TebPlanner),
+}
+
+impl LocalPlannerConfig {
+    pub fn new_from_config(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)
+            .map_err(|e| Error::Other(format!("failed to read {}: {e}", path.display())))?;
+        Self::new_from_config_text(&source)
+    }
+
+    pub fn new_from_config_text(source: &str) -> Result<Self> {
+        let value: serde_yaml::Value =
+            serde_yaml::from_str(source).map_err(grid_map::Error::from)?;
+        if value.get("DwaPlanner").is_some() {
+            return Ok(Self::Dwa(DwaPlanner::new_from_config_text(source)?));
+        }
+        Ok(serde_yaml::from_value(value).map_err(grid_map::Error::from)?)
+    }
+
+    pub fn to_planner(&self) -> Box<dyn LocalPlanner> {
+        match self {
+            Self::Dwa(planner) => Box::new(planner.clone()),
+            Self::PurePursuit(planner) => Box::new(planner.clone()),
+            Self::Mppi(planner) => planner.clone(),
+            Self::Teb(planner) => Box::new(planner.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{obstacle_distance_map_edt, Acceleration, Limits};
+    use grid_map::{Grid, Position};
+    use nalgebra as na;
+
+    #[test]
+    fn test_local_planner_config() {
+        let dwa = LocalPlannerConfig::new_from_config_text(include_str!(
+            "../config/dwa_parameter_config.yaml"
+        ))
+        .unwrap();
+        assert!(matches!(dwa, LocalPlannerConfig::Dwa(_)));
+        let yaml = r#"
+type: teb
+limits:
+  max_velocity: [0.4, 1.5]
+  max_acceleration: [1.0, 3.0]
+  min_velocity: [0.0, -1.5]
+  min_acceleration: [-1.0, -3.0]
+controller_dt: 0.1
+horizon: 1.0
+"#;
+        let config = LocalPlannerConfig::new_from_config_text(yaml).unwrap();
+        let LocalPlannerConfig::Teb(teb) = &config else {
+            panic!("{config:?}");
+        };
+        assert_eq!(teb.horizon, 1.0);
+        assert!(LocalPlannerConfig::new_from_config_text("type: unknown").is_err());
+
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.05);
+        map.set_obstacle(&Grid::new(30, 5)).unwrap();
+        let mut maps = LayeredGridMap::default();
+        let pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let velocity = Velocity::default();
+        let path = vec![vec![0.5, 0.5, 0.0], vec![1.5, 0.5, 0.0]];
+        let limits = Limits {
+            max_velocity: Velocity { x: 0.4, theta: 1.5 },
+            max_accel: Acceleration { x: 1.0, theta: 3.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -1.5,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -3.0,
+            },
+        };
+        let planners: Vec<Box<dyn LocalPlanner>> = vec![
+            config.to_planner(),
+            Box::new(PurePursuitController::new(limits.clone(), 0.1)),
+        ];
+        for planner in &planners {
+            assert_eq!(planner.controller_dt(), 0.1);
+            assert!(planner
+                .plan(&pose, &velocity, &maps, &HashMap::new(), &path)
+                .is_err());
+        }
+        maps.add_layer(LayerId::OBSTACLE, obstacle_distance_map_edt(&map).unwrap());
+        for planner in &planners {
+            let plan = planner
+                .plan(&pose, &velocity, &maps, &HashMap::new(), &path)
+                .unwrap();
+            assert!(plan.cost.is_finite());
+            assert!(plan.velocity.x > 0.0);
+            assert_eq!(
+                planner.self_test(&maps, &HashMap::new(), 0.1).level(),
+                DiagnosticLevel::Warn
+            );
+        }
+    }
+}
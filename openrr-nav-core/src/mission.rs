@@ -0,0 +1,245 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::{Error, Pose, Result, Vector2};
+
+/// Waypoint of a mission
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    /// (m)
+    pub x: f64,
+    /// (m)
+    pub y: f64,
+    /// (rad)
+    pub yaw: f64,
+    /// Time to stay at the waypoint after reaching it
+    #[serde(default)]
+    pub pause: Duration,
+    /// (rad) Yaw tolerance used instead of the one of the goal checker
+    #[serde(default)]
+    pub yaw_tolerance: Option<f64>,
+}
+
+impl Waypoint {
+    pub fn with_pause(mut self, pause: Duration) -> Self {
+        self.pause = pause;
+        self
+    }
+
+    pub fn with_yaw_tolerance(mut self, yaw_tolerance: f64) -> Self {
+        self.yaw_tolerance = Some(yaw_tolerance);
+        self
+    }
+}
+
+impl From<&Pose> for Waypoint {
+    fn from(pose: &Pose) -> Self {
+        Self {
+            x: pose.translation.x,
+            y: pose.translation.y,
+            yaw: pose.rotation.angle(),
+            pause: Duration::ZERO,
+            yaw_tolerance: None,
+        }
+    }
+}
+
+impl From<&Waypoint> for Pose {
+    fn from(waypoint: &Waypoint) -> Self {
+        Pose::new(Vector2::new(waypoint.x, waypoint.y), waypoint.yaw)
+    }
+}
+
+/// State of the active mission which is persisted by [`MissionStore`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MissionState {
+    pub waypoints: Vec<Waypoint>,
+    /// Index of the waypoint which the robot is heading to
+    pub current_index: usize,
+    pub paused: bool,
+}
+
+impl MissionState {
+    pub fn new(waypoints: &[Pose]) -> Self {
+        Self::from_waypoints(waypoints.iter().map(Waypoint::from).collect())
+    }
+
+    pub fn from_waypoints(waypoints: Vec<Waypoint>) -> Self {
+        Self {
+            waypoints,
+            current_index: 0,
+            paused: false,
+        }
+    }
+
+    /// The waypoint which the robot is heading to
+    pub fn current_waypoint(&self) -> Option<Pose> {
+        self.waypoints.get(self.current_index).map(Pose::from)
+    }
+
+    /// Waypoints which are not reached yet, including the current one
+    pub fn remaining_waypoints(&self) -> &[Waypoint] {
+        self.waypoints.get(self.current_index..).unwrap_or_default()
+    }
+
+    /// Move to the next waypoint
+    pub fn advance(&mut self) {
+        self.current_index = (self.current_index + 1).min(self.waypoints.len());
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.current_index >= self.waypoints.len()
+    }
+}
+
+/// Periodically saves the mission state to a file so that the mission can be
+/// resumed after a crash or a reboot
+///
+/// The file is written to a temporary file and renamed, so the saved state is not
+/// broken even if the process dies while saving.
+#[derive(Debug, Clone)]
+pub struct MissionStore {
+    path: PathBuf,
+    save_interval: Duration,
+    last_save: Option<SystemTime>,
+}
+
+impl MissionStore {
+    pub fn new<P: AsRef<Path>>(path: P, save_interval: Duration) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            save_interval,
+            last_save: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn save_interval(&self) -> Duration {
+        self.save_interval
+    }
+
+    /// Save the state in binary format
+    pub fn save(&mut self, state: &MissionState) -> Result<()> {
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut writer = BufWriter::new(File::create(&temp)?);
+        bincode::serialize_into(&mut writer, state)?;
+        writer.flush()?;
+        drop(writer);
+        std::fs::rename(&temp, &self.path)?;
+        self.last_save = Some(SystemTime::now());
+        Ok(())
+    }
+
+    /// Save the state if `save_interval` has passed since the last save
+    ///
+    /// Returns true if saved. This is meant to be called every control cycle. It is
+    /// also due if `now` is before the last save, e.g. the clock is stepped back.
+    pub fn save_if_due(&mut self, state: &MissionState, now: SystemTime) -> Result<bool> {
+        let due = self.last_save.is_none_or(|last| {
+            now.duration_since(last)
+                .map_or(true, |elapsed| elapsed >= self.save_interval)
+        });
+        if !due {
+            return Ok(false);
+        }
+        self.save(state)?;
+        self.last_save = Some(now);
+        Ok(true)
+    }
+
+    /// The unfinished mission saved before the restart, if any
+    ///
+    /// The navigator should ask the user (or the supervisor) before resuming it.
+    pub fn resume_last_mission(&self) -> Result<Option<MissionState>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let state: MissionState = bincode::deserialize_from(BufReader::new(file))?;
+        if state.current_index > state.waypoints.len() {
+            return Err(Error::Other("broken mission state".to_owned()));
+        }
+        Ok((!state.is_finished()).then_some(state))
+    }
+
+    /// Remove the saved state, e.g. when the mission is completed or canceled
+    pub fn clear(&mut self) -> Result<()> {
+        self.last_save = None;
+        match std::fs::remove_file(&self.path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mission_store() {
+        let poses = [
+            Pose::new(Vector2::new(1.0, 0.0), 0.0),
+            Pose::new(Vector2::new(1.0, 2.0), 1.5),
+            Pose::new(Vector2::new(-1.0, 2.0), -3.0),
+        ];
+        let mut state = MissionState::new(&poses);
+        let path = std::env::temp_dir().join("openrr_nav_test_mission_store.bin");
+        let mut store = MissionStore::new(&path, Duration::from_secs(5));
+        store.clear().unwrap();
+        assert_eq!(store.resume_last_mission().unwrap(), None);
+
+        let t0 = SystemTime::now();
+        assert!(store.save_if_due(&state, t0).unwrap());
+        state.advance();
+        state.paused = true;
+        assert!(!store
+            .save_if_due(&state, t0 + Duration::from_secs(1))
+            .unwrap());
+        assert_eq!(
+            store.resume_last_mission().unwrap().unwrap().current_index,
+            0
+        );
+        assert!(store
+            .save_if_due(&state, t0 + Duration::from_secs(5))
+            .unwrap());
+        // the clock is stepped back
+        assert!(store
+            .save_if_due(&state, t0 - Duration::from_secs(60))
+            .unwrap());
+        assert!(!store
+            .save_if_due(&state, t0 - Duration::from_secs(59))
+            .unwrap());
+
+        // restarted
+        let resumed = MissionStore::new(&path, Duration::from_secs(5))
+            .resume_last_mission()
+            .unwrap()
+            .unwrap();
+        assert_eq!(resumed, state);
+        assert_eq!(resumed.remaining_waypoints().len(), 2);
+        let current = resumed.current_waypoint().unwrap();
+        assert!((current.translation.vector - poses[1].translation.vector).norm() < 1e-12);
+        assert!((current.rotation.angle() - 1.5).abs() < 1e-12);
+
+        // finished mission is not resumed
+        state.advance();
+        state.advance();
+        assert!(state.is_finished());
+        assert!(state.remaining_waypoints().is_empty());
+        store.save(&state).unwrap();
+        assert_eq!(store.resume_last_mission().unwrap(), None);
+        store.clear().unwrap();
+        assert!(!path.exists());
+    }
+}
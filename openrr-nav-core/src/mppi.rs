@@ -0,0 +1,352 @@
+use grid_map::{LayerId, LayeredGridMap};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+use crate::{
+    dwa_planner::{serde_cost_name_weight, velocity_to_pose},
+    DwaPlanner, Limits, Plan, Pose, Velocity,
+};
+
+fn default_num_samples() -> usize {
+    200
+}
+
+fn default_noise_std() -> Velocity {
+    Velocity { x: 0.1, theta: 0.5 }
+}
+
+fn default_temperature() -> f64 {
+    0.1
+}
+
+/// Random generator and the control sequence of the last cycle
+#[derive(Debug, Default)]
+struct MppiState {
+    /// Created from the seed at the first cycle
+    rng: Option<StdRng>,
+    sequence: Vec<Velocity>,
+}
+
+/// Sample of the standard normal distribution by the Box-Muller transform
+pub(crate) fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (std::f64::consts::TAU * u2).cos()
+}
+
+/// Model Predictive Path Integral controller
+///
+/// The control sequence of the last cycle is perturbed by the gaussian noise
+/// `num_samples` times, and the rollouts of the sequences are scored with the cost
+/// layers and the weights in the same way as [`DwaPlanner::score_plan`]. The new
+/// sequence is the average of the samples weighted by `exp(-cost / temperature)`,
+/// where the costs are normalized into `[0, 1]`. Unlike the DWA, the velocity can
+/// change along the rollout, so the robot finds the way through the tight spaces.
+///
+/// The sequence is kept between the cycles as the warm start. Call
+/// [`MppiController::reset`] when the goal is changed.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MppiController {
+    limits: Limits,
+    #[serde(with = "serde_cost_name_weight")]
+    cost_name_weight: HashMap<LayerId, f64>,
+    controller_dt: f64,
+    simulation_duration: f64,
+    #[serde(default = "default_num_samples")]
+    num_samples: usize,
+    /// Standard deviation of the noise (m/s, rad/s)
+    #[serde(default = "default_noise_std")]
+    noise_std: Velocity,
+    #[serde(default = "default_temperature")]
+    temperature: f64,
+    #[serde(default)]
+    seed: u64,
+    #[serde(skip)]
+    state: Mutex<MppiState>,
+}
+
+impl Clone for MppiController {
+    fn clone(&self) -> Self {
+        Self {
+            limits: self.limits.clone(),
+            cost_name_weight: self.cost_name_weight.clone(),
+            controller_dt: self.controller_dt,
+            simulation_duration: self.simulation_duration,
+            num_samples: self.num_samples,
+            noise_std: self.noise_std,
+            temperature: self.temperature,
+            seed: self.seed,
+            state: Mutex::default(),
+        }
+    }
+}
+
+impl MppiController {
+    pub fn new(
+        limits: Limits,
+        cost_name_weight: HashMap<LayerId, f64>,
+        controller_dt: f64,
+        simulation_duration: f64,
+    ) -> Self {
+        Self {
+            limits,
+            cost_name_weight,
+            controller_dt,
+            simulation_duration,
+            num_samples: default_num_samples(),
+            noise_std: default_noise_std(),
+            temperature: default_temperature(),
+            seed: 0,
+            state: Mutex::default(),
+        }
+    }
+
+    pub fn with_num_samples(mut self, num_samples: usize) -> Self {
+        self.num_samples = num_samples;
+        self
+    }
+
+    pub fn with_noise_std(mut self, noise_std: Velocity) -> Self {
+        self.noise_std = noise_std;
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Reseed the generator so that the samples are reproducible
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self.state = Mutex::default();
+        self
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn map_name_weight(&self) -> &HashMap<LayerId, f64> {
+        &self.cost_name_weight
+    }
+
+    pub fn map_name_weight_mut(&mut self) -> &mut HashMap<LayerId, f64> {
+        &mut self.cost_name_weight
+    }
+
+    pub fn controller_dt(&self) -> f64 {
+        self.controller_dt
+    }
+
+    /// Forget the control sequence of the last cycle
+    pub fn reset(&self) {
+        self.state.lock().unwrap().sequence.clear();
+    }
+
+    fn num_steps(&self) -> usize {
+        ((self.simulation_duration / self.controller_dt) as usize).max(1)
+    }
+
+    /// Clamp the sequence into the velocity and the acceleration limits from the
+    /// current velocity
+    fn clamp_sequence(&self, current: &Velocity, sequence: &mut [Velocity]) {
+        let limits = &self.limits;
+        let dt = self.controller_dt;
+        let mut last = *current;
+        for v in sequence {
+            v.x =
+                v.x.clamp(
+                    last.x + limits.min_accel.x * dt,
+                    last.x + limits.max_accel.x * dt,
+                )
+                .clamp(limits.min_velocity.x, limits.max_velocity.x);
+            v.theta = v
+                .theta
+                .clamp(
+                    last.theta + limits.min_accel.theta * dt,
+                    last.theta + limits.max_accel.theta * dt,
+                )
+                .clamp(limits.min_velocity.theta, limits.max_velocity.theta);
+            last = *v;
+        }
+    }
+
+    fn rollout(&self, pose: &Pose, sequence: &[Velocity]) -> Vec<Pose> {
+        let mut last = *pose;
+        sequence
+            .iter()
+            .map(|v| {
+                last *= velocity_to_pose(v, self.controller_dt);
+                last
+            })
+            .collect()
+    }
+
+    /// Plan the velocity with the layers and the angles of the DWA
+    ///
+    /// The cost of the plan is the cost of the rollout of the averaged sequence.
+    pub fn plan_local_path(
+        &self,
+        current_pose: &Pose,
+        current_velocity: &Velocity,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+    ) -> Plan {
+        let num_steps = self.num_steps();
+        let mut state = self.state.lock().unwrap();
+        let MppiState { rng, sequence } = &mut *state;
+        let rng = rng.get_or_insert_with(|| StdRng::seed_from_u64(self.seed));
+        // warm start with the last sequence shifted by one step
+        if !sequence.is_empty() {
+            sequence.remove(0);
+        }
+        let last = sequence.last().copied().unwrap_or(*current_velocity);
+        sequence.resize(num_steps, last);
+        self.clamp_sequence(current_velocity, sequence);
+
+        let score = |sequence: &[Velocity]| {
+            let plan = Plan {
+                path: self.rollout(current_pose, sequence),
+                ..Default::default()
+            };
+            DwaPlanner::score_plan(&plan, maps, angles, &self.cost_name_weight)
+        };
+        // the first sample is the sequence without the noise
+        let mut samples = Vec::with_capacity(self.num_samples + 1);
+        samples.push(sequence.clone());
+        for _ in 0..self.num_samples {
+            let mut sample = sequence
+                .iter()
+                .map(|v| Velocity {
+                    x: v.x + self.noise_std.x * standard_normal(rng),
+                    theta: v.theta + self.noise_std.theta * standard_normal(rng),
+                })
+                .collect::<Vec<_>>();
+            self.clamp_sequence(current_velocity, &mut sample);
+            samples.push(sample);
+        }
+        let costs = samples.iter().map(|s| score(s)).collect::<Vec<_>>();
+        let finite = costs.iter().copied().filter(|c| c.is_finite());
+        let min = finite.clone().fold(f64::INFINITY, f64::min);
+        let max = finite.fold(f64::NEG_INFINITY, f64::max);
+        let range = (max - min).max(f64::EPSILON);
+        let weights = costs
+            .iter()
+            .map(|c| {
+                if c.is_finite() {
+                    (-(c - min) / range / self.temperature.max(f64::EPSILON)).exp()
+                } else {
+                    0.0
+                }
+            })
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if total > 0.0 {
+            for (t, v) in sequence.iter_mut().enumerate() {
+                *v = samples
+                    .iter()
+                    .zip(&weights)
+                    .fold(Velocity::default(), |sum, (sample, w)| Velocity {
+                        x: sum.x + sample[t].x * w / total,
+                        theta: sum.theta + sample[t].theta * w / total,
+                    });
+            }
+            self.clamp_sequence(current_velocity, sequence);
+        }
+        Plan {
+            velocity: sequence[0],
+            cost: score(sequence),
+            path: self.rollout(current_pose, sequence),
+            sampling_issue: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{obstacle_distance_map, path_distance_map, utils, Acceleration};
+    use grid_map::{Grid, GridMap, Position};
+    use nalgebra as na;
+
+    #[test]
+    fn test_mppi_controller() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 1.55), 0.05);
+        // narrow gap of 0.3 m at x = 1.5
+        for y in 0..map.height() {
+            if !(12..18).contains(&y) {
+                map.set_obstacle(&Grid::new(30, y)).unwrap();
+            }
+        }
+        let goal = [2.5, 0.75];
+        let path = (0..=40)
+            .map(|i| vec![0.5 + i as f64 * 0.05, 0.75])
+            .collect::<Vec<_>>();
+        let mut layers = LayeredGridMap::default();
+        layers.add_layer(
+            LayerId::PATH,
+            path_distance_map(&map, &utils::path_to_grids(&map, &path)).unwrap(),
+        );
+        layers.add_layer(
+            LayerId::GOAL,
+            crate::goal_distance_map(&map, &map.to_grid(goal[0], goal[1]).unwrap()).unwrap(),
+        );
+        layers.add_layer(LayerId::OBSTACLE, obstacle_distance_map(&map).unwrap());
+        let weights = [
+            (LayerId::PATH, 0.5),
+            (LayerId::GOAL, 1.0),
+            (LayerId::OBSTACLE, 0.3),
+        ]
+        .into_iter()
+        .collect();
+        let limits = Limits {
+            max_velocity: Velocity { x: 0.3, theta: 1.5 },
+            max_accel: Acceleration { x: 1.0, theta: 3.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -1.5,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -3.0,
+            },
+        };
+        let controller = MppiController::new(limits, weights, 0.1, 1.5)
+            .with_num_samples(100)
+            .with_seed(1);
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.75), 0.0);
+        let mut velocity = Velocity::default();
+        let mut reached = false;
+        for _ in 0..200 {
+            let plan = controller.plan_local_path(&pose, &velocity, &layers, &HashMap::new());
+            assert_eq!(plan.path.len(), 15);
+            assert!(plan.cost.is_finite());
+            velocity = plan.velocity;
+            pose = plan.path[0];
+            let grid = map.to_grid(pose.translation.x, pose.translation.y).unwrap();
+            assert!(!map.cell(&grid).unwrap().is_obstacle());
+            if (pose.translation.x - goal[0]).abs() < 0.1 {
+                reached = true;
+                break;
+            }
+        }
+        assert!(reached);
+
+        // reproducible from the seed
+        controller.reset();
+        let a = controller
+            .clone()
+            .plan_local_path(&pose, &velocity, &layers, &HashMap::new());
+        let b = controller
+            .clone()
+            .plan_local_path(&pose, &velocity, &layers, &HashMap::new());
+        assert_eq!(a.velocity, b.velocity);
+        let yaml = serde_yaml::to_string(&controller).unwrap();
+        let loaded: MppiController = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(loaded.num_samples, 100);
+        assert_eq!(loaded.seed, 1);
+    }
+}
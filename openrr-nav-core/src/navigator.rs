@@ -0,0 +1,852 @@
+use grid_map::{GridMap, LayerId, LayeredGridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::SystemTime};
+
+use crate::{
+    goal_region_distance_map, inflate_obstacles, is_path_valid, local_goal_distance_map,
+    obstacle_distance_map_edt, path, path_distance_map, utils, DijkstraPlanner, Error, Footprint,
+    GlobalPlanner, Goal, GoalChecker, LifecycleNode, LocalPlanner, MissionState, MissionStore,
+    PathInvalidAt, Plan, Pose, RecoveryBehavior, RecoveryConfig, RecoveryContext, RecoveryStatus,
+    Result, SelfTestReport, SimpleGoalChecker, Velocity, ZoneSchedule,
+};
+
+/// State of the [`Navigator`]
+///
+/// ```text
+/// Idle -> ComputingPath -> FollowingPath -> GoalReached
+///              ^    |            |
+///              |    v            v
+///              +- Recovery <-----+
+///                   |
+///                   v
+///                 Failed
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NavigatorState {
+    /// No goal is set
+    #[default]
+    Idle,
+    /// The global path is planned in the next tick
+    ComputingPath,
+    /// Following the global path with the local planner
+    FollowingPath,
+    /// The global or the local planning failed. The next recovery behavior is
+    /// executed, and then the global path is planned again.
+    Recovery,
+    GoalReached,
+    /// The planning failed more than `max_recoveries` times
+    Failed,
+}
+
+impl NavigatorState {
+    /// Return true if the navigation to the goal is over
+    pub fn is_finished(self) -> bool {
+        matches!(self, NavigatorState::GoalReached | NavigatorState::Failed)
+    }
+}
+
+/// Output of [`Navigator::tick`] to be sent to the base
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Velocity(Velocity),
+    Stop,
+}
+
+impl Command {
+    /// Velocity to be sent, zero for [`Command::Stop`]
+    pub fn velocity(&self) -> Velocity {
+        match self {
+            Command::Velocity(velocity) => *velocity,
+            Command::Stop => Velocity::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NavigatorConfig {
+    /// Number of the waypoints ahead of the robot to take the path direction from
+    #[serde(default = "default_look_ahead")]
+    pub look_ahead: usize,
+    /// Number of the replans after the failures before giving up
+    #[serde(default = "default_max_recoveries")]
+    pub max_recoveries: usize,
+    /// Parameters of the inflation layer used as the traversal cost of the global
+    /// planner, see [`inflate_obstacles`]
+    #[serde(default = "default_inscribed_radius")]
+    pub inscribed_radius: f64,
+    #[serde(default = "default_inflation_radius")]
+    pub inflation_radius: f64,
+    #[serde(default = "default_cost_scaling")]
+    pub cost_scaling: f64,
+    /// If set, the global path is planned again when the footprint along it hits
+    /// an obstacle of the map
+    #[serde(default)]
+    pub footprint: Option<Footprint>,
+    /// Recovery behaviors executed in turn before each replan after the failures.
+    /// The path is just planned again if empty.
+    #[serde(default)]
+    pub recoveries: Vec<RecoveryConfig>,
+}
+
+fn default_inscribed_radius() -> f64 {
+    0.1
+}
+
+fn default_inflation_radius() -> f64 {
+    0.4
+}
+
+fn default_cost_scaling() -> f64 {
+    5.0
+}
+
+fn default_look_ahead() -> usize {
+    10
+}
+
+fn default_max_recoveries() -> usize {
+    3
+}
+
+impl Default for NavigatorConfig {
+    fn default() -> Self {
+        Self {
+            look_ahead: default_look_ahead(),
+            max_recoveries: default_max_recoveries(),
+            inscribed_radius: default_inscribed_radius(),
+            inflation_radius: default_inflation_radius(),
+            cost_scaling: default_cost_scaling(),
+            footprint: None,
+            recoveries: vec![],
+        }
+    }
+}
+
+/// Navigation to a goal with the global planner and the [`LocalPlanner`]
+///
+/// Call [`Navigator::tick`] with the current pose and velocity in every control
+/// cycle and send the returned command to the base. The global path is planned at
+/// the first tick after [`Navigator::set_goal`], and the cost layers of the local
+/// planner (`path`, `goal`, `obstacle` and `local_goal`) are built from it. The
+/// local planner, e.g. [`DwaPlanner`](crate::DwaPlanner), can be created from the
+/// config by [`LocalPlannerConfig`](crate::LocalPlannerConfig).
+///
+/// The poses, the goals and the paths are in the world frame. They are converted
+/// into the grids by [`GridMap::world_to_grid`], so the map can have a rotated
+/// [`GridMap::origin`].
+#[derive(Debug)]
+pub struct Navigator {
+    global_planner: Box<dyn GlobalPlanner>,
+    local_planner: Box<dyn LocalPlanner>,
+    goal_checker: Box<dyn GoalChecker>,
+    config: NavigatorConfig,
+    map: GridMap<u8>,
+    static_map: GridMap<u8>,
+    layers: LayeredGridMap<u8>,
+    angles: HashMap<LayerId, f64>,
+    zone_schedule: Option<ZoneSchedule>,
+    goal: Option<Goal>,
+    /// Pose in the goal which the global path heads to
+    target: Option<Pose>,
+    path: Vec<Vec<f64>>,
+    state: NavigatorState,
+    num_recoveries: usize,
+    recoveries: Vec<Box<dyn RecoveryBehavior>>,
+    /// Index of the running recovery behavior
+    active_recovery: Option<usize>,
+    last_error: Option<String>,
+    last_plan: Option<Plan>,
+}
+
+impl Navigator {
+    /// The map is also kept as the static map, which is restored by
+    /// [`ClearCostmapRecovery`](crate::ClearCostmapRecovery).
+    pub fn new(
+        global_planner: Box<dyn GlobalPlanner>,
+        local_planner: Box<dyn LocalPlanner>,
+        map: GridMap<u8>,
+    ) -> Self {
+        Self {
+            global_planner,
+            local_planner,
+            goal_checker: Box::new(SimpleGoalChecker::default()),
+            config: NavigatorConfig::default(),
+            static_map: map.clone(),
+            map,
+            layers: LayeredGridMap::default(),
+            angles: HashMap::new(),
+            zone_schedule: None,
+            goal: None,
+            target: None,
+            path: vec![],
+            state: NavigatorState::Idle,
+            num_recoveries: 0,
+            recoveries: vec![],
+            active_recovery: None,
+            last_error: None,
+            last_plan: None,
+        }
+    }
+
+    /// Set the config, and create the recovery behaviors of the config
+    pub fn with_config(mut self, config: NavigatorConfig) -> Self {
+        self.set_config(config);
+        self
+    }
+
+    /// Replace the config and the recovery behaviors, e.g. with the parameters
+    /// changed at runtime. Takes effect from the next tick.
+    pub fn set_config(&mut self, config: NavigatorConfig) {
+        self.recoveries = config
+            .recoveries
+            .iter()
+            .map(RecoveryConfig::to_behavior)
+            .collect();
+        self.active_recovery = None;
+        self.config = config;
+    }
+
+    /// Replace the recovery behaviors, e.g. with the custom ones
+    pub fn with_recoveries(mut self, recoveries: Vec<Box<dyn RecoveryBehavior>>) -> Self {
+        self.recoveries = recoveries;
+        self
+    }
+
+    /// Replace the [`SimpleGoalChecker`] with the default tolerances
+    pub fn with_goal_checker(mut self, goal_checker: Box<dyn GoalChecker>) -> Self {
+        self.goal_checker = goal_checker;
+        self
+    }
+
+    /// Replace the goal checker, and return the old one
+    pub fn replace_goal_checker(
+        &mut self,
+        goal_checker: Box<dyn GoalChecker>,
+    ) -> Box<dyn GoalChecker> {
+        std::mem::replace(&mut self.goal_checker, goal_checker)
+    }
+
+    /// Update the zones of the local planner by the schedule in every tick
+    pub fn with_zone_schedule(mut self, zone_schedule: ZoneSchedule) -> Self {
+        self.zone_schedule = Some(zone_schedule);
+        self
+    }
+
+    pub fn config(&self) -> &NavigatorConfig {
+        &self.config
+    }
+
+    pub fn state(&self) -> NavigatorState {
+        self.state
+    }
+
+    pub fn goal(&self) -> Option<&Goal> {
+        self.goal.as_ref()
+    }
+
+    /// Pose in the goal which the global path heads to
+    ///
+    /// For the pose set and polygon goals, this is set when the path is planned.
+    pub fn target(&self) -> Option<&Pose> {
+        self.target.as_ref()
+    }
+
+    /// Remaining waypoints `[x, y, theta]` of the global path
+    pub fn global_path(&self) -> &[Vec<f64>] {
+        &self.path
+    }
+
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    /// Cost layers used by the local planner in the last tick
+    pub fn layers(&self) -> &LayeredGridMap<u8> {
+        &self.layers
+    }
+
+    pub fn angles(&self) -> &HashMap<LayerId, f64> {
+        &self.angles
+    }
+
+    /// Switch the global planner, e.g. with the planner selected in the
+    /// [`GlobalPlannerRegistry`](crate::GlobalPlannerRegistry), and return the old one
+    ///
+    /// The new planner is used from the next replanning.
+    pub fn replace_global_planner(
+        &mut self,
+        global_planner: Box<dyn GlobalPlanner>,
+    ) -> Box<dyn GlobalPlanner> {
+        std::mem::replace(&mut self.global_planner, global_planner)
+    }
+
+    pub fn local_planner(&self) -> &dyn LocalPlanner {
+        &*self.local_planner
+    }
+
+    pub fn local_planner_mut(&mut self) -> &mut dyn LocalPlanner {
+        &mut *self.local_planner
+    }
+
+    /// Switch the local planner, and return the old one
+    ///
+    /// The zones of the schedule are set to the new planner in the next tick.
+    pub fn replace_local_planner(
+        &mut self,
+        local_planner: Box<dyn LocalPlanner>,
+    ) -> Box<dyn LocalPlanner> {
+        if let Some(schedule) = &mut self.zone_schedule {
+            schedule.invalidate();
+        }
+        std::mem::replace(&mut self.local_planner, local_planner)
+    }
+
+    /// Error of the last failed planning
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Plan of the local planner in the last tick following the path
+    pub fn last_plan(&self) -> Option<&Plan> {
+        self.last_plan.as_ref()
+    }
+
+    /// Replace the costmap, e.g. with the obstacles observed by the sensors
+    ///
+    /// The cost layers are rebuilt for the current path, and the path is planned
+    /// again if it is blocked (see [`NavigatorConfig::footprint`]).
+    pub fn set_map(&mut self, map: GridMap<u8>) -> Result<()> {
+        self.map = map;
+        if self.state == NavigatorState::FollowingPath {
+            self.update_layers()?;
+        }
+        Ok(())
+    }
+
+    /// Start the navigation to the goal, canceling the current one
+    ///
+    /// For the pose set and polygon goals, the global path is planned to the goal
+    /// grid nearest to the robot.
+    pub fn set_goal(&mut self, goal: impl Into<Goal>) {
+        let goal = goal.into();
+        self.target = match &goal {
+            Goal::Pose(pose) => Some(*pose),
+            _ => None,
+        };
+        self.goal = Some(goal);
+        self.path.clear();
+        self.local_planner.reset();
+        self.last_plan = None;
+        self.num_recoveries = 0;
+        self.active_recovery = None;
+        self.last_error = None;
+        self.state = NavigatorState::ComputingPath;
+    }
+
+    /// Stop the navigation
+    pub fn cancel(&mut self) {
+        self.goal = None;
+        self.target = None;
+        self.path.clear();
+        self.last_plan = None;
+        self.active_recovery = None;
+        self.state = NavigatorState::Idle;
+    }
+
+    /// Update the zones of the local planner by the schedule
+    ///
+    /// Returns true if the zones are replaced. This is called in [`Navigator::tick`].
+    pub fn update_zones(&mut self, now: SystemTime) -> bool {
+        match &mut self.zone_schedule {
+            Some(schedule) => schedule.update(&mut *self.local_planner, now),
+            None => false,
+        }
+    }
+
+    /// Run one cycle of the state machine and return the command to the base
+    ///
+    /// The clock is read only with the zone schedule, so this runs on the targets
+    /// without the clock like wasm32-unknown-unknown. Call
+    /// [`Navigator::update_zones`] with the time of the host there instead.
+    pub fn tick(&mut self, pose: &Pose, velocity: &Velocity) -> Command {
+        if self.zone_schedule.is_some() {
+            self.update_zones(SystemTime::now());
+        }
+        let Some(goal) = &self.goal else {
+            return Command::Stop;
+        };
+        if !self.state.is_finished() && self.goal_checker.is_reached(goal, pose, velocity) {
+            self.path.clear();
+            self.state = NavigatorState::GoalReached;
+        }
+        match self.state {
+            NavigatorState::Idle | NavigatorState::GoalReached | NavigatorState::Failed => {
+                Command::Stop
+            }
+            NavigatorState::ComputingPath => match self.compute_path(pose) {
+                Ok(()) => {
+                    self.state = NavigatorState::FollowingPath;
+                    self.follow_path(pose, velocity)
+                }
+                Err(e) => self.fail(e),
+            },
+            NavigatorState::FollowingPath => self.follow_path(pose, velocity),
+            NavigatorState::Recovery => self.recover(pose),
+        }
+    }
+
+    fn recover(&mut self, pose: &Pose) -> Command {
+        if self.num_recoveries >= self.config.max_recoveries {
+            self.active_recovery = None;
+            self.state = NavigatorState::Failed;
+            return Command::Stop;
+        }
+        if !self.recoveries.is_empty() {
+            let index = match self.active_recovery {
+                Some(index) => index,
+                None => {
+                    let index = self.num_recoveries % self.recoveries.len();
+                    self.recoveries[index].start(pose);
+                    self.active_recovery = Some(index);
+                    index
+                }
+            };
+            let mut context = RecoveryContext {
+                map: &mut self.map,
+                static_map: &self.static_map,
+                footprint: self.config.footprint.as_ref(),
+            };
+            match self.recoveries[index].tick(&mut context, pose) {
+                RecoveryStatus::Running(velocity) => return Command::Velocity(velocity),
+                RecoveryStatus::Succeeded => {}
+                RecoveryStatus::Failed(e) => {
+                    self.last_error = Some(format!("recovery {index} failed: {e}"));
+                }
+            }
+            self.active_recovery = None;
+        }
+        self.num_recoveries += 1;
+        self.state = NavigatorState::ComputingPath;
+        Command::Stop
+    }
+
+    fn fail(&mut self, error: Error) -> Command {
+        self.last_error = Some(error.to_string());
+        self.state = NavigatorState::Recovery;
+        Command::Stop
+    }
+
+    fn compute_path(&mut self, pose: &Pose) -> Result<()> {
+        let goal = self.target_of(pose)?;
+        self.target = Some(goal);
+        let inflation = inflate_obstacles(
+            &self.map,
+            self.config.inscribed_radius,
+            self.config.inflation_radius,
+            self.config.cost_scaling,
+        )?;
+        let poses = self
+            .global_planner
+            .plan_with_cost(&self.map, Some(&inflation), pose, &goal)?;
+        if poses.is_empty() {
+            return Err(Error::Other("the global path is empty".to_owned()));
+        }
+        let path = poses
+            .iter()
+            .map(|p| vec![p.translation.x, p.translation.y, p.rotation.angle()])
+            .collect::<Vec<_>>();
+        // merge the collinear grid steps, and then interpolate at the even spacing
+        // smaller than the grid to get the connected path grids
+        let path = utils::simplify_path(&path, self.map.resolution() * 0.1);
+        self.path = path::densify_path(&path, self.map.resolution() * 0.5);
+        self.validate_path()
+            .map_err(|e| Error::Other(format!("the new global path is blocked: {e}")))?;
+        self.update_layers()
+    }
+
+    /// Pose which the global path from the pose heads to
+    ///
+    /// The nearest pose of the set, or the center of the nearest grid of the polygon
+    /// faced from the pose.
+    fn target_of(&self, pose: &Pose) -> Result<Pose> {
+        let goal = self
+            .goal
+            .as_ref()
+            .ok_or_else(|| Error::Other("no goal is set".to_owned()))?;
+        if let Goal::Pose(goal) = goal {
+            return Ok(*goal);
+        }
+        let position = Position::new(pose.translation.x, pose.translation.y);
+        let grid = goal
+            .nearest_grid(&self.map, &position)
+            .ok_or_else(|| Error::Other("the goal region has no free grid".to_owned()))?;
+        match goal {
+            Goal::PoseSet(poses) => Ok(*poses
+                .iter()
+                .find(|p| {
+                    self.map
+                        .world_to_grid(&Position::new(p.translation.x, p.translation.y))
+                        == Some(grid)
+                })
+                .unwrap()),
+            _ => {
+                let center = self.map.map_to_world(&self.map.cell_center(&grid));
+                Ok(Pose::new(
+                    na::Vector2::new(center.x, center.y),
+                    (center.y - position.y).atan2(center.x - position.x),
+                ))
+            }
+        }
+    }
+
+    /// Build the layers which don't depend on the current pose
+    fn update_layers(&mut self) -> Result<()> {
+        let (Some(goal), Some(target)) = (&self.goal, &self.target) else {
+            return Ok(());
+        };
+        let path_grid = utils::path_to_grids(&self.map, &self.path);
+        let goal_layer = match goal {
+            Goal::Pose(_) => {
+                let goal_grid = self
+                    .map
+                    .world_to_grid(&Position::new(target.translation.x, target.translation.y))
+                    .ok_or_else(|| {
+                        Error::Other(format!("goal {:?} is out of the map", target.translation))
+                    })?;
+                DijkstraPlanner::new()
+                    .navigation_function(&self.map, None, &goal_grid)?
+                    .goal_distance_layer()
+            }
+            // distance to the nearest grid of the region, not only to the target
+            _ => goal_region_distance_map(&self.map, goal)?,
+        };
+        self.layers
+            .add_layer(LayerId::PATH, path_distance_map(&self.map, &path_grid)?);
+        self.layers.add_layer(LayerId::GOAL, goal_layer);
+        self.layers
+            .add_layer(LayerId::OBSTACLE, obstacle_distance_map_edt(&self.map)?);
+        self.angles
+            .insert(LayerId::GOAL_DIRECTION, target.rotation.angle());
+        Ok(())
+    }
+
+    fn validate_path(&self) -> std::result::Result<(), PathInvalidAt> {
+        let Some(footprint) = &self.config.footprint else {
+            return Ok(());
+        };
+        let poses = self
+            .path
+            .iter()
+            .map(|p| Pose::new(na::Vector2::new(p[0], p[1]), p[2]))
+            .collect::<Vec<_>>();
+        is_path_valid(&poses, &self.map, footprint)
+    }
+
+    fn follow_path(&mut self, pose: &Pose, velocity: &Velocity) -> Command {
+        let position = [pose.translation.x, pose.translation.y];
+        // follow the waypoints ahead of the robot
+        self.path = path::prune_passed(&self.path, position);
+        if let Err(e) = self.validate_path() {
+            // blocked by a new obstacle
+            self.last_error = Some(e.to_string());
+            self.state = NavigatorState::ComputingPath;
+            return Command::Stop;
+        }
+        match local_goal_distance_map(&self.map, &self.path, position) {
+            Ok(map) => self.layers.add_layer(LayerId::LOCAL_GOAL, map),
+            Err(e) => return self.fail(e.into()),
+        }
+        self.angles.insert(LayerId::ROTATION, pose.rotation.angle());
+        let look_ahead = self.config.look_ahead.min(self.path.len() - 1);
+        self.angles
+            .insert(LayerId::PATH_DIRECTION, self.path[look_ahead][2]);
+
+        let plan =
+            match self
+                .local_planner
+                .plan(pose, velocity, &self.layers, &self.angles, &self.path)
+            {
+                Ok(plan) => plan,
+                Err(e) => return self.fail(e),
+            };
+        if plan.path.is_empty() || !plan.cost.is_finite() {
+            return self.fail(Error::Other(format!(
+                "no feasible velocity at {:?}",
+                pose.translation
+            )));
+        }
+        let velocity = plan.velocity;
+        self.last_plan = Some(plan);
+        Command::Velocity(velocity)
+    }
+
+    /// [`LocalPlanner::self_test`] with the current layers
+    ///
+    /// The `local_goal` layer is excluded since it covers only around the robot.
+    pub fn self_test(&self, robot_radius: f64) -> SelfTestReport {
+        let mut layers = self.layers.clone();
+        layers.remove_layer(LayerId::LOCAL_GOAL);
+        self.local_planner
+            .self_test(&layers, &self.angles, robot_radius)
+    }
+
+    /// Load the mission saved by the store, and head to its current waypoint
+    pub fn resume_last_mission(&mut self, store: &MissionStore) -> Result<Option<MissionState>> {
+        let mission = store.resume_last_mission()?;
+        if let Some(waypoint) = mission.as_ref().and_then(|m| m.current_waypoint()) {
+            self.set_goal(waypoint);
+        }
+        Ok(mission)
+    }
+}
+
+impl LifecycleNode for Navigator {
+    fn on_deactivate(&mut self) -> Result<()> {
+        self.cancel();
+        Ok(())
+    }
+
+    fn on_cleanup(&mut self) -> Result<()> {
+        self.layers = LayeredGridMap::default();
+        self.angles.clear();
+        Ok(())
+    }
+
+    fn on_shutdown(&mut self) -> Result<()> {
+        self.cancel();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        polygon_contains,
+        testkit::{self, move_pose, DT},
+        PurePursuitController,
+    };
+    use grid_map::Grid;
+
+    #[test]
+    fn test_navigator() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for y in 0..15 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let mut navigator = testkit::navigator(map).with_config(NavigatorConfig {
+            footprint: Some(Footprint::Circle { radius: 0.04 }),
+            ..Default::default()
+        });
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity::default();
+        assert_eq!(navigator.tick(&pose, &velocity), Command::Stop);
+        assert_eq!(navigator.state(), NavigatorState::Idle);
+
+        navigator.set_goal(Pose::new(na::Vector2::new(2.5, 0.5), 0.0));
+        assert_eq!(navigator.state(), NavigatorState::ComputingPath);
+        let mut num_replans = 0;
+        for i in 0..1000 {
+            let command = navigator.tick(&pose, &velocity);
+            if navigator.state().is_finished() {
+                break;
+            }
+            if navigator.state() == NavigatorState::ComputingPath {
+                assert_eq!(command, Command::Stop);
+                num_replans += 1;
+            } else {
+                assert_eq!(navigator.state(), NavigatorState::FollowingPath);
+            }
+            if i == 20 {
+                // a new obstacle on the path ahead
+                let mut map = navigator.map().clone();
+                let p = navigator
+                    .global_path()
+                    .iter()
+                    .find(|p| p[0] >= 1.375)
+                    .unwrap();
+                map.set_obstacle(&map.to_grid(p[0], p[1]).unwrap()).unwrap();
+                navigator.set_map(map).unwrap();
+            }
+            velocity = command.velocity();
+            pose = move_pose(&pose, &velocity, DT);
+            let grid = navigator
+                .map()
+                .to_grid(pose.translation.x, pose.translation.y)
+                .unwrap();
+            assert!(!navigator.map().cell(&grid).unwrap().is_obstacle());
+        }
+        assert_eq!(num_replans, 1);
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+        assert_eq!(navigator.tick(&pose, &velocity), Command::Stop);
+
+        // the goal in the obstacle
+        navigator.set_goal(Pose::new(na::Vector2::new(1.525, 0.5), 0.0));
+        for _ in 0..10 {
+            assert_eq!(navigator.tick(&pose, &velocity), Command::Stop);
+        }
+        assert_eq!(navigator.state(), NavigatorState::Failed);
+        assert!(navigator.last_error().is_some());
+
+        navigator.set_goal(Pose::new(na::Vector2::new(0.5, 0.5), 0.0));
+        navigator.cancel();
+        assert_eq!(navigator.tick(&pose, &velocity), Command::Stop);
+        assert_eq!(navigator.state(), NavigatorState::Idle);
+    }
+
+    #[test]
+    fn test_navigator_rotated_origin() {
+        // the same wall as test_navigator in the map frame rotated by 90 degrees
+        let origin = na::Isometry2::new(na::Vector2::new(1.0, 2.0), std::f64::consts::FRAC_PI_2);
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05)
+            .with_origin(origin);
+        for y in 0..15 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let mut navigator = testkit::navigator(map).with_config(NavigatorConfig {
+            footprint: Some(Footprint::Circle { radius: 0.04 }),
+            ..Default::default()
+        });
+        let goal = origin * Pose::new(na::Vector2::new(2.5, 0.5), 0.0);
+        navigator.set_goal(goal);
+        let mut pose = origin * Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity::default();
+        for _ in 0..1000 {
+            let command = navigator.tick(&pose, &velocity);
+            if navigator.state().is_finished() {
+                break;
+            }
+            assert_eq!(navigator.state(), NavigatorState::FollowingPath);
+            velocity = command.velocity();
+            pose = move_pose(&pose, &velocity, DT);
+            let position = Position::new(pose.translation.x, pose.translation.y);
+            assert!(!navigator
+                .map()
+                .cell_by_position(&position)
+                .unwrap()
+                .is_obstacle());
+        }
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+    }
+
+    #[test]
+    fn test_navigator_unreachable_region() {
+        let mut map = testkit::free_map();
+        // the cells above the wall are not reachable from the goal and the path
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 20)).unwrap();
+        }
+        let mut navigator = testkit::navigator(map).with_config(NavigatorConfig {
+            footprint: Some(Footprint::Circle { radius: 0.04 }),
+            ..Default::default()
+        });
+        let goal = Pose::new(na::Vector2::new(2.5, 0.5), 0.0);
+        navigator.set_goal(goal);
+        let mut pose = Pose::new(na::Vector2::new(0.3, 0.95), 0.6);
+        let mut velocity = Velocity::default();
+        for _ in 0..1000 {
+            let command = navigator.tick(&pose, &velocity);
+            if navigator.state().is_finished() {
+                break;
+            }
+            velocity = command.velocity();
+            pose = move_pose(&pose, &velocity, DT);
+        }
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+    }
+
+    #[test]
+    fn test_navigator_recovery() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        map.set_obstacle(&Grid::new(40, 10)).unwrap();
+        let config: NavigatorConfig = serde_yaml::from_str(
+            "{max_recoveries: 2, recoveries: [{type: rotate, angle: 0.5}, {type: back_up, distance: 0.1}]}",
+        )
+        .unwrap();
+        let mut navigator = testkit::navigator(map).with_config(config);
+        let start = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut pose = start;
+        // the goal in the obstacle
+        navigator.set_goal(Pose::new(na::Vector2::new(2.025, 0.525), 0.0));
+        let mut commands = vec![];
+        for _ in 0..1000 {
+            let velocity = navigator.tick(&pose, &Velocity::default()).velocity();
+            if navigator.state().is_finished() {
+                break;
+            }
+            commands.push(velocity);
+            pose = move_pose(&pose, &velocity, DT);
+        }
+        assert_eq!(navigator.state(), NavigatorState::Failed);
+        // rotated, and then backed up
+        let rotating = commands.iter().position(|v| v.theta > 0.0).unwrap();
+        let backing = commands.iter().position(|v| v.x < 0.0).unwrap();
+        assert!(rotating < backing);
+        assert!((pose.rotation.angle() - 0.5).abs() < 0.1);
+        assert!(((pose.translation.vector - start.translation.vector).norm() - 0.1).abs() < 0.02);
+    }
+
+    #[test]
+    fn test_navigator_polygon_goal() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for y in 0..15 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let mut navigator = testkit::navigator(map);
+        let region = vec![
+            Position::new(2.2, 0.2),
+            Position::new(2.8, 0.2),
+            Position::new(2.8, 0.8),
+            Position::new(2.2, 0.8),
+        ];
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity::default();
+        navigator.set_goal(Goal::Polygon(region.clone()));
+        assert!(navigator.target().is_none());
+        for _ in 0..2000 {
+            velocity = navigator.tick(&pose, &velocity).velocity();
+            if navigator.state().is_finished() {
+                break;
+            }
+            assert_ne!(navigator.state(), NavigatorState::Recovery);
+            pose = move_pose(&pose, &velocity, DT);
+        }
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+        let position = Position::new(pose.translation.x, pose.translation.y);
+        assert!(polygon_contains(&region, &position));
+        // planned to the grid of the region nearest to the start
+        let target = navigator.target().unwrap();
+        assert!((target.translation.x - 2.225).abs() < 1e-6);
+        assert!((target.translation.y - 0.475).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_navigator_local_planner() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        let dwa = testkit::turtlebot_dwa();
+        let pure_pursuit = PurePursuitController::new(dwa.limits().clone(), dwa.controller_dt());
+        let mut navigator = testkit::navigator(map);
+        let old = navigator.replace_local_planner(Box::new(pure_pursuit));
+        assert!(format!("{old:?}").starts_with("DwaPlanner"));
+        let dt = navigator.local_planner().controller_dt();
+        let goal = Pose::new(na::Vector2::new(2.0, 1.5), 0.0);
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity::default();
+        navigator.set_goal(goal);
+        for _ in 0..1000 {
+            velocity = navigator.tick(&pose, &velocity).velocity();
+            if navigator.state().is_finished() {
+                break;
+            }
+            assert_ne!(navigator.state(), NavigatorState::Recovery);
+            pose = move_pose(&pose, &velocity, dt);
+        }
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+        assert!(navigator
+            .self_test(0.1)
+            .warnings()
+            .any(|d| d.message.contains("PurePursuitController")));
+    }
+}
@@ -0,0 +1,208 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, Result};
+
+/// Long-term memory of the observed obstacles
+///
+/// Each cell keeps a score which is increased every time an obstacle is observed
+/// there and decays exponentially with `half_life`. Cells whose score is higher
+/// than `threshold` are remembered as obstacles, so semi-static objects like
+/// furniture which are not in the static map are still anticipated after restart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObstacleMemory {
+    scores: GridMap<f32>,
+    half_life: Duration,
+    threshold: f32,
+    last_update: SystemTime,
+}
+
+impl ObstacleMemory {
+    /// Create an empty memory with the same geometry as the map
+    pub fn new<T: Clone>(map: &GridMap<T>, half_life: Duration, threshold: f32) -> Self {
+        let mut scores = map.map_values(|_| 0.0);
+        for cell in scores.cells_mut() {
+            *cell = Cell::Value(0.0);
+        }
+        Self {
+            scores,
+            half_life,
+            threshold,
+            last_update: UNIX_EPOCH,
+        }
+    }
+
+    pub fn half_life(&self) -> Duration {
+        self.half_life
+    }
+
+    pub fn threshold(&self) -> f32 {
+        self.threshold
+    }
+
+    /// Time of the last observation
+    pub fn last_update(&self) -> SystemTime {
+        self.last_update
+    }
+
+    /// Decay the scores until the time
+    pub fn decay(&mut self, now: SystemTime) {
+        let Ok(elapsed) = now.duration_since(self.last_update) else {
+            return;
+        };
+        if self.last_update != UNIX_EPOCH && !self.half_life.is_zero() {
+            let factor = 0.5_f64.powf(elapsed.as_secs_f64() / self.half_life.as_secs_f64()) as f32;
+            for cell in self.scores.cells_mut() {
+                if let Cell::Value(score) = cell {
+                    *score *= factor;
+                }
+            }
+        }
+        self.last_update = now;
+    }
+
+    /// Accumulate the obstacles of the observed map (e.g. a local costmap made
+    /// from the sensor data) observed at `stamp`
+    ///
+    /// The observed map may have a different geometry from the memory.
+    pub fn observe(&mut self, observed: &GridMap<u8>, stamp: SystemTime) {
+        self.decay(stamp);
+        let resolution = observed.resolution();
+        for y in 0..observed.height() {
+            for x in 0..observed.width() {
+                if !matches!(observed.cell(&Grid::new(x, y)), Some(Cell::Obstacle)) {
+                    continue;
+                }
+                let position = Position::new(
+                    observed.min_point().x + (x as f64 + 0.5) * resolution,
+                    observed.min_point().y + (y as f64 + 0.5) * resolution,
+                );
+                self.observe_obstacle(&position);
+            }
+        }
+    }
+
+    /// Accumulate an obstacle at the position without decaying
+    pub fn observe_obstacle(&mut self, position: &Position) {
+        if let Some(Cell::Value(score)) = self
+            .scores
+            .to_grid(position.x, position.y)
+            .and_then(|grid| self.scores.cell_mut(&grid))
+        {
+            *score += 1.0;
+        }
+    }
+
+    /// Score of the grid of the memory
+    pub fn score(&self, grid: &Grid) -> Option<f32> {
+        self.scores.value(grid)
+    }
+
+    /// Return true if the obstacle at the position is remembered
+    pub fn is_remembered(&self, position: &Position) -> bool {
+        self.scores
+            .to_grid(position.x, position.y)
+            .and_then(|grid| self.score(&grid))
+            .is_some_and(|score| score >= self.threshold)
+    }
+
+    /// Positions (center of the cells) of the remembered obstacles
+    pub fn remembered_positions(&self) -> Vec<Position> {
+        let resolution = self.scores.resolution();
+        let mut positions = vec![];
+        for y in 0..self.scores.height() {
+            for x in 0..self.scores.width() {
+                if self
+                    .score(&Grid::new(x, y))
+                    .is_some_and(|score| score >= self.threshold)
+                {
+                    positions.push(Position::new(
+                        self.scores.min_point().x + (x as f64 + 0.5) * resolution,
+                        self.scores.min_point().y + (y as f64 + 0.5) * resolution,
+                    ));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Mark the remembered obstacles on the map, typically the static map
+    /// before creating the obstacle distance map
+    pub fn apply_to(&self, map: &mut GridMap<u8>) {
+        for position in self.remembered_positions() {
+            if let Some(grid) = map.to_grid(position.x, position.y) {
+                map.set_obstacle(&grid);
+            }
+        }
+    }
+
+    /// Save the memory to the file in binary format
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    /// Load the memory saved by [`ObstacleMemory::save_to_file`]
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let memory: Self = bincode::deserialize_from(reader)?;
+        if memory.scores.cells().len() != memory.scores.width() * memory.scores.height() {
+            return Err(Error::Other("broken obstacle memory".to_owned()));
+        }
+        Ok(memory)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    #[test]
+    fn test_obstacle_memory() {
+        let map = GridMap::<u8>::new(Position::new(-1.05, -1.05), Position::new(1.05, 1.05), 0.1);
+        let mut memory = ObstacleMemory::new(&map, DAY, 2.5);
+        let mut observed =
+            GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(0.5, 0.5), 0.1);
+        observed.set_obstacle(&Grid::new(2, 2)).unwrap();
+        let chair = Position::new(0.25, 0.25);
+
+        let t0 = UNIX_EPOCH + DAY * 20000;
+        for i in 0..3 {
+            memory.observe(&observed, t0 + Duration::from_secs(i));
+        }
+        assert!(memory.is_remembered(&chair));
+        assert!(!memory.is_remembered(&Position::new(-0.5, 0.25)));
+
+        let path = std::env::temp_dir().join("openrr_nav_test_obstacle_memory.bin");
+        memory.save_to_file(&path).unwrap();
+        let mut loaded = ObstacleMemory::load_from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.is_remembered(&chair));
+
+        let mut static_map = map.clone();
+        loaded.apply_to(&mut static_map);
+        let grid = static_map.to_grid(chair.x, chair.y).unwrap();
+        assert!(static_map.cell(&grid).unwrap().is_obstacle());
+
+        // forgotten after a few days without observation
+        loaded.decay(t0 + DAY * 2);
+        assert!(!loaded.is_remembered(&chair));
+
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.1);
+        let sub_map = map.sub_map(&Grid::new(2, 1), &Grid::new(20, 19)).unwrap();
+        let memory = ObstacleMemory::new(&sub_map, DAY, 2.5);
+        assert_eq!(
+            (memory.scores.width(), memory.scores.height()),
+            (sub_map.width(), sub_map.height())
+        );
+    }
+}
@@ -0,0 +1,488 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{dwa_planner::cell_cost, Pose};
+
+fn default_association_distance() -> f64 {
+    0.5
+}
+
+fn default_acceleration_noise() -> f64 {
+    1.0
+}
+
+fn default_measurement_noise() -> f64 {
+    0.05
+}
+
+fn default_initial_velocity_std() -> f64 {
+    1.0
+}
+
+fn default_max_misses() -> usize {
+    5
+}
+
+fn default_min_cluster_cells() -> usize {
+    1
+}
+
+/// Parameters of [`ObstacleTracker`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ObstacleTrackerConfig {
+    /// (m) Detections farther than this from the predicted track are not associated
+    #[serde(default = "default_association_distance")]
+    pub association_distance: f64,
+    /// (m/s^2) Standard deviation of the acceleration of the constant velocity model
+    #[serde(default = "default_acceleration_noise")]
+    pub acceleration_noise: f64,
+    /// (m) Standard deviation of the detected position
+    #[serde(default = "default_measurement_noise")]
+    pub measurement_noise: f64,
+    /// (m/s) Standard deviation of the velocity of a new track
+    #[serde(default = "default_initial_velocity_std")]
+    pub initial_velocity_std: f64,
+    /// Tracks which are not detected more than this times in a row are removed
+    #[serde(default = "default_max_misses")]
+    pub max_misses: usize,
+    /// Clusters smaller than this number of cells are ignored as the noise
+    #[serde(default = "default_min_cluster_cells")]
+    pub min_cluster_cells: usize,
+}
+
+impl Default for ObstacleTrackerConfig {
+    fn default() -> Self {
+        Self {
+            association_distance: default_association_distance(),
+            acceleration_noise: default_acceleration_noise(),
+            measurement_noise: default_measurement_noise(),
+            initial_velocity_std: default_initial_velocity_std(),
+            max_misses: default_max_misses(),
+            min_cluster_cells: default_min_cluster_cells(),
+        }
+    }
+}
+
+/// Obstacle detected in a cycle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    /// Position in the frame of the map
+    pub position: Position,
+    /// (m)
+    pub radius: f64,
+}
+
+impl Detection {
+    pub fn new(position: Position, radius: f64) -> Self {
+        Self { position, radius }
+    }
+}
+
+/// Clusters of the 8-connected obstacle cells of the map
+///
+/// The obstacles of `static_map` (the map of the same geometry without the moving
+/// obstacles) are skipped. The detection is the centroid of the cluster, and the
+/// radius covers all the cells.
+pub fn cluster_obstacles(
+    map: &GridMap<u8>,
+    static_map: Option<&GridMap<u8>>,
+    min_cells: usize,
+) -> Vec<Detection> {
+    let is_dynamic = |grid: &Grid| {
+        matches!(map.cell(grid), Some(Cell::Obstacle))
+            && !matches!(static_map.and_then(|m| m.cell(grid)), Some(Cell::Obstacle))
+    };
+    let mut visited = vec![false; map.len()];
+    let mut detections = vec![];
+    for y in 0..map.height() {
+        for x in 0..map.width() {
+            let start = Grid::new(x, y);
+            if visited[y * map.width() + x] || !is_dynamic(&start) {
+                continue;
+            }
+            visited[y * map.width() + x] = true;
+            let mut cluster = vec![];
+            let mut stack = vec![start];
+            while let Some(grid) = stack.pop() {
+                cluster.push(grid);
+                for (dx, dy) in [
+                    (-1, -1),
+                    (0, -1),
+                    (1, -1),
+                    (-1, 0),
+                    (1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                ] {
+                    let (nx, ny) = (grid.x as i64 + dx, grid.y as i64 + dy);
+                    if nx < 0 || ny < 0 || nx >= map.width() as i64 || ny >= map.height() as i64 {
+                        continue;
+                    }
+                    let neighbor = Grid::new(nx as usize, ny as usize);
+                    let index = neighbor.y * map.width() + neighbor.x;
+                    if !visited[index] && is_dynamic(&neighbor) {
+                        visited[index] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+            if cluster.len() < min_cells {
+                continue;
+            }
+            let centers = cluster
+                .iter()
+                .map(|g| map.cell_center(g))
+                .collect::<Vec<_>>();
+            let n = centers.len() as f64;
+            let centroid = Position::new(
+                centers.iter().map(|p| p.x).sum::<f64>() / n,
+                centers.iter().map(|p| p.y).sum::<f64>() / n,
+            );
+            let radius = centers
+                .iter()
+                .map(|p| (p.x - centroid.x).hypot(p.y - centroid.y))
+                .fold(0.0, f64::max)
+                + map.resolution() / 2.0;
+            detections.push(Detection::new(centroid, radius));
+        }
+    }
+    detections
+}
+
+/// Obstacle tracked by the constant velocity Kalman filter
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedObstacle {
+    pub id: u64,
+    /// State (x, y, vx, vy) in the frame of the map
+    pub state: na::Vector4<f64>,
+    pub covariance: na::Matrix4<f64>,
+    /// (m)
+    pub radius: f64,
+    /// Number of the associated detections
+    pub hits: usize,
+    /// Number of the cycles without the detection in a row
+    pub misses: usize,
+}
+
+impl TrackedObstacle {
+    pub fn position(&self) -> Position {
+        Position::new(self.state[0], self.state[1])
+    }
+
+    /// (m/s)
+    pub fn velocity(&self) -> na::Vector2<f64> {
+        na::Vector2::new(self.state[2], self.state[3])
+    }
+
+    /// Position after `time` (s) with the constant velocity
+    pub fn predict_position(&self, time: f64) -> Position {
+        let v = self.velocity();
+        Position::new(self.state[0] + v.x * time, self.state[1] + v.y * time)
+    }
+}
+
+/// Occupancy of the moving obstacles predicted at the future times
+///
+/// `layers[k]` is the occupancy after `(k + 1) * dt` (s), which is the time of
+/// `k`-th pose of [`Plan::path`](crate::Plan::path) of the planners simulated with
+/// the same `dt`.
+#[derive(Debug, Clone, Default)]
+pub struct PredictedOccupancy {
+    /// (s)
+    pub dt: f64,
+    pub layers: Vec<GridMap<u8>>,
+}
+
+impl PredictedOccupancy {
+    /// Layer of the nearest predicted time, `None` after the last layer
+    pub fn layer_at(&self, time: f64) -> Option<&GridMap<u8>> {
+        if self.dt <= 0.0 || time < 0.0 {
+            return None;
+        }
+        let index = ((time / self.dt).round() as usize).saturating_sub(1);
+        self.layers.get(index)
+    }
+
+    /// Sum of the costs of the poses, where `i`-th pose is at `(i + 1) * dt` (s)
+    ///
+    /// The poses out of the maps or after the last prediction have no cost.
+    pub fn path_cost(&self, path: &[Pose], dt: f64) -> f64 {
+        path.iter()
+            .enumerate()
+            .filter_map(|(i, pose)| {
+                let map = self.layer_at((i + 1) as f64 * dt)?;
+                map.cell_by_position(&Position::new(pose.translation.x, pose.translation.y))
+                    .and_then(cell_cost)
+            })
+            .sum()
+    }
+}
+
+/// Tracker of the moving obstacles, e.g. the people and the other robots
+///
+/// In every [`ObstacleTracker::update`], the tracks are predicted by the constant
+/// velocity model, the detections are associated with the nearest tracks within
+/// `association_distance`, and the associated tracks are corrected by the Kalman
+/// filter. The unassociated detections start new tracks. Use
+/// [`ObstacleTracker::predicted_occupancy`] to score the local plans with the
+/// future positions of the obstacles.
+#[derive(Debug, Clone, Default)]
+pub struct ObstacleTracker {
+    pub config: ObstacleTrackerConfig,
+    tracks: Vec<TrackedObstacle>,
+    next_id: u64,
+}
+
+impl ObstacleTracker {
+    pub fn new(config: ObstacleTrackerConfig) -> Self {
+        Self {
+            config,
+            tracks: vec![],
+            next_id: 0,
+        }
+    }
+
+    pub fn tracks(&self) -> &[TrackedObstacle] {
+        &self.tracks
+    }
+
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// Update the tracks with the detections after `dt` (s) from the last update
+    pub fn update(&mut self, detections: &[Detection], dt: f64) {
+        self.predict(dt);
+        // greedy nearest neighbor association
+        let mut pairs = vec![];
+        for (t, track) in self.tracks.iter().enumerate() {
+            let p = track.position();
+            for (d, detection) in detections.iter().enumerate() {
+                let distance = (detection.position.x - p.x).hypot(detection.position.y - p.y);
+                if distance <= self.config.association_distance {
+                    pairs.push((distance, t, d));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut track_associated = vec![false; self.tracks.len()];
+        let mut detection_associated = vec![false; detections.len()];
+        for (_, t, d) in pairs {
+            if track_associated[t] || detection_associated[d] {
+                continue;
+            }
+            track_associated[t] = true;
+            detection_associated[d] = true;
+            self.correct(t, &detections[d]);
+        }
+        for (track, associated) in self.tracks.iter_mut().zip(track_associated) {
+            if !associated {
+                track.misses += 1;
+            }
+        }
+        let max_misses = self.config.max_misses;
+        self.tracks.retain(|track| track.misses <= max_misses);
+        for (detection, _) in detections
+            .iter()
+            .zip(detection_associated)
+            .filter(|(_, associated)| !associated)
+        {
+            self.start_track(detection);
+        }
+    }
+
+    /// [`ObstacleTracker::update`] with the clusters of the obstacles of the map
+    /// (see [`cluster_obstacles`])
+    pub fn update_with_map(
+        &mut self,
+        map: &GridMap<u8>,
+        static_map: Option<&GridMap<u8>>,
+        dt: f64,
+    ) {
+        let detections = cluster_obstacles(map, static_map, self.config.min_cluster_cells);
+        self.update(&detections, dt);
+    }
+
+    fn predict(&mut self, dt: f64) {
+        if dt <= 0.0 {
+            return;
+        }
+        let mut f = na::Matrix4::identity();
+        f[(0, 2)] = dt;
+        f[(1, 3)] = dt;
+        // white noise acceleration
+        let q = self.config.acceleration_noise.powi(2);
+        let (q11, q12, q22) = (dt.powi(4) / 4.0 * q, dt.powi(3) / 2.0 * q, dt.powi(2) * q);
+        #[rustfmt::skip]
+        let noise = na::Matrix4::new(
+            q11, 0.0, q12, 0.0,
+            0.0, q11, 0.0, q12,
+            q12, 0.0, q22, 0.0,
+            0.0, q12, 0.0, q22,
+        );
+        for track in &mut self.tracks {
+            track.state = f * track.state;
+            track.covariance = f * track.covariance * f.transpose() + noise;
+        }
+    }
+
+    fn correct(&mut self, index: usize, detection: &Detection) {
+        let r = na::Matrix2::identity() * self.config.measurement_noise.powi(2);
+        let track = &mut self.tracks[index];
+        let h = na::Matrix2x4::new(1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0);
+        let innovation =
+            na::Vector2::new(detection.position.x, detection.position.y) - h * track.state;
+        let s = h * track.covariance * h.transpose() + r;
+        let Some(s_inverse) = s.try_inverse() else {
+            return;
+        };
+        let gain = track.covariance * h.transpose() * s_inverse;
+        track.state += gain * innovation;
+        track.covariance = (na::Matrix4::identity() - gain * h) * track.covariance;
+        track.radius = detection.radius;
+        track.hits += 1;
+        track.misses = 0;
+    }
+
+    fn start_track(&mut self, detection: &Detection) {
+        let position = self.config.measurement_noise.powi(2);
+        let velocity = self.config.initial_velocity_std.powi(2);
+        self.tracks.push(TrackedObstacle {
+            id: self.next_id,
+            state: na::Vector4::new(detection.position.x, detection.position.y, 0.0, 0.0),
+            covariance: na::Matrix4::from_diagonal(&na::Vector4::new(
+                position, position, velocity, velocity,
+            )),
+            radius: detection.radius,
+            hits: 1,
+            misses: 0,
+        });
+        self.next_id += 1;
+    }
+
+    /// Occupancy of the tracks in `num_steps` layers of the geometry of the template
+    ///
+    /// The cells within `radius + margin` (m) of the predicted positions are
+    /// obstacles, and the others are free. Tracks detected only once are ignored
+    /// because their velocities are unknown.
+    pub fn predicted_occupancy(
+        &self,
+        template: &GridMap<u8>,
+        dt: f64,
+        num_steps: usize,
+        margin: f64,
+    ) -> PredictedOccupancy {
+        let mut free = template.copy_without_value();
+        for cell in free.cells_mut() {
+            *cell = Cell::Value(0);
+        }
+        let layers = (1..=num_steps)
+            .map(|step| {
+                let mut layer = free.clone();
+                for track in self.tracks.iter().filter(|t| t.hits > 1) {
+                    let center = layer.world_to_map(&track.predict_position(step as f64 * dt));
+                    let radius = track.radius + margin;
+                    let cells = (radius / layer.resolution()).ceil() as i64;
+                    let Some(grid) = layer.to_grid(center.x, center.y) else {
+                        continue;
+                    };
+                    for dy in -cells..=cells {
+                        for dx in -cells..=cells {
+                            let (x, y) = (grid.x as i64 + dx, grid.y as i64 + dy);
+                            if x < 0 || y < 0 {
+                                continue;
+                            }
+                            let neighbor = Grid::new(x as usize, y as usize);
+                            let p = layer.cell_center(&neighbor);
+                            if (p.x - center.x).hypot(p.y - center.y) <= radius {
+                                // ignore the cells out of the map
+                                let _ = layer.set_obstacle(&neighbor);
+                            }
+                        }
+                    }
+                }
+                layer
+            })
+            .collect();
+        PredictedOccupancy { dt, layers }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DwaPlanner, Velocity};
+    use grid_map::{LayerId, LayeredGridMap};
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_obstacle_tracker() {
+        let empty = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(4.05, 2.05), 0.05);
+        let mut static_map = empty.clone();
+        for x in 0..static_map.width() {
+            static_map.set_obstacle(&Grid::new(x, 0)).unwrap();
+        }
+        let mut tracker = ObstacleTracker::default();
+        let dt = 0.1;
+        // a box moving at (-0.5, 0.0) m/s and a standing one
+        for step in 0..20 {
+            let mut map = static_map.clone();
+            let x = 3.0 - 0.5 * dt * step as f64;
+            for (cx, cy) in [(x, 1.0), (1.0, 1.5)] {
+                for (dx, dy) in [(0.0, 0.0), (0.05, 0.0), (0.0, 0.05), (0.05, 0.05)] {
+                    let grid = map.to_grid(cx + dx, cy + dy).unwrap();
+                    map.set_obstacle(&grid).unwrap();
+                }
+            }
+            tracker.update_with_map(&map, Some(&static_map), dt);
+        }
+        assert_eq!(tracker.tracks().len(), 2);
+        let moving = tracker
+            .tracks()
+            .iter()
+            .max_by(|a, b| a.velocity().norm().total_cmp(&b.velocity().norm()))
+            .unwrap();
+        assert!((moving.velocity() - na::Vector2::new(-0.5, 0.0)).norm() < 0.05);
+        assert_eq!(moving.hits, 20);
+        let standing = tracker.tracks().iter().find(|t| t.id != moving.id).unwrap();
+        assert!(standing.velocity().norm() < 0.05);
+
+        let predictions = tracker.predicted_occupancy(&empty, dt, 10, 0.1);
+        assert_eq!(predictions.layers.len(), 10);
+        let future = moving.predict_position(1.0);
+        let layer = predictions.layer_at(1.0).unwrap();
+        let grid = layer.to_grid(future.x, future.y).unwrap();
+        assert_eq!(layer.cell(&grid), Some(&Cell::Obstacle));
+        assert!(predictions.layer_at(1.5).is_none());
+
+        // the robot crossing in front of the moving obstacle
+        let planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        let pose = Pose::new(na::Vector2::new(future.x, 0.5), std::f64::consts::FRAC_PI_2);
+        let velocity = Velocity { x: 0.5, theta: 0.0 };
+        let mut maps = LayeredGridMap::default();
+        let mut free = empty.clone();
+        for cell in free.cells_mut() {
+            *cell = Cell::Value(0);
+        }
+        maps.add_layer(LayerId::OBSTACLE, free);
+        let candidates = planner.generate_candidates(&pose, &velocity);
+        let straight = candidates
+            .iter()
+            .filter(|p| p.velocity.theta.abs() < 0.15)
+            .max_by(|a, b| a.velocity.x.total_cmp(&b.velocity.x))
+            .unwrap();
+        assert!(predictions.path_cost(&straight.path, planner.controller_dt()) > 0.0);
+        let plan = planner.plan_local_path_with_predictions(
+            &pose,
+            &velocity,
+            &maps,
+            &HashMap::new(),
+            &predictions,
+            1.0,
+        );
+        assert!(predictions.path_cost(&plan.path, planner.controller_dt()) == 0.0);
+    }
+}
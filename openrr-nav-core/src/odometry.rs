@@ -0,0 +1,182 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{dwa_planner::velocity_to_pose, Pose, PoseWithCovariance, Velocity};
+
+/// Noise of the odometry of a differential drive base
+///
+/// The parameters are the same as `odom_alpha1` to `odom_alpha4` of AMCL. The
+/// variance of the translation is `translation_by_translation * d^2 +
+/// translation_by_rotation * r^2` and the variance of the rotation is
+/// `rotation_by_rotation * r^2 + rotation_by_translation * d^2`, where `d` (m) and
+/// `r` (rad) are the motion of the step.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OdometryNoise {
+    pub rotation_by_rotation: f64,
+    pub rotation_by_translation: f64,
+    pub translation_by_translation: f64,
+    pub translation_by_rotation: f64,
+}
+
+impl Default for OdometryNoise {
+    fn default() -> Self {
+        // Same as the defaults of AMCL
+        Self {
+            rotation_by_rotation: 0.2,
+            rotation_by_translation: 0.2,
+            translation_by_translation: 0.2,
+            translation_by_rotation: 0.2,
+        }
+    }
+}
+
+impl OdometryNoise {
+    /// Variances of (translation, rotation) of the motion in the robot frame
+    pub fn variances(&self, delta: &Pose) -> (f64, f64) {
+        let d2 = delta.translation.vector.norm_squared();
+        let r2 = delta.rotation.angle().powi(2);
+        (
+            self.translation_by_translation * d2 + self.translation_by_rotation * r2,
+            self.rotation_by_rotation * r2 + self.rotation_by_translation * d2,
+        )
+    }
+}
+
+/// Pose estimation by integrating the velocities or the odometry deltas
+///
+/// The covariance grows with the motion by [`OdometryNoise`], so the estimate can
+/// be used as the prediction of the localization, e.g. the particle filter. This
+/// also simulates the motion of the robot in the examples.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeadReckoning {
+    estimate: PoseWithCovariance,
+    velocity: Velocity,
+    noise: OdometryNoise,
+}
+
+impl DeadReckoning {
+    pub fn new(initial: PoseWithCovariance) -> Self {
+        Self {
+            estimate: initial,
+            velocity: Velocity::default(),
+            noise: OdometryNoise::default(),
+        }
+    }
+
+    /// Start from the pose without the uncertainty
+    pub fn from_pose(pose: Pose) -> Self {
+        Self::new(PoseWithCovariance::new(pose, na::Matrix3::zeros()))
+    }
+
+    pub fn with_noise(mut self, noise: OdometryNoise) -> Self {
+        self.noise = noise;
+        self
+    }
+
+    pub fn pose(&self) -> Pose {
+        self.estimate.pose
+    }
+
+    pub fn estimate(&self) -> &PoseWithCovariance {
+        &self.estimate
+    }
+
+    /// The last integrated velocity
+    pub fn velocity(&self) -> Velocity {
+        self.velocity
+    }
+
+    pub fn noise(&self) -> &OdometryNoise {
+        &self.noise
+    }
+
+    /// Restart from the estimate, e.g. the result of the localization
+    pub fn reset(&mut self, estimate: PoseWithCovariance) {
+        self.estimate = estimate;
+    }
+
+    /// Move by the velocity for `dt` (s) in the same way as the forward simulation of
+    /// the planners, so the robot follows [`Plan::path`](crate::Plan::path)
+    pub fn integrate(&mut self, velocity: &Velocity, dt: f64) -> &PoseWithCovariance {
+        self.velocity = *velocity;
+        if dt > 0.0 {
+            self.integrate_delta(&velocity_to_pose(velocity, dt));
+        }
+        &self.estimate
+    }
+
+    /// Move by the motion in the robot frame, e.g. the difference of the odometry
+    /// poses of the wheels
+    pub fn integrate_delta(&mut self, delta: &Pose) -> &PoseWithCovariance {
+        let yaw = self.estimate.pose.rotation.angle();
+        let (sin, cos) = yaw.sin_cos();
+        let (dx, dy) = (delta.translation.x, delta.translation.y);
+        // jacobian of the motion by the pose
+        #[rustfmt::skip]
+        let f = na::Matrix3::new(
+            1.0, 0.0, -sin * dx - cos * dy,
+            0.0, 1.0, cos * dx - sin * dy,
+            0.0, 0.0, 1.0,
+        );
+        // noise along the direction of the motion
+        let (translation, rotation) = self.noise.variances(delta);
+        let heading = yaw + dy.atan2(dx);
+        let (sin, cos) = if dx == 0.0 && dy == 0.0 {
+            (sin, cos)
+        } else {
+            heading.sin_cos()
+        };
+        #[rustfmt::skip]
+        let g = na::Matrix3::new(
+            cos, 0.0, 0.0,
+            sin, 0.0, 0.0,
+            0.0, 0.0, 1.0,
+        );
+        let q = na::Matrix3::from_diagonal(&na::Vector3::new(translation, 0.0, rotation));
+        self.estimate = PoseWithCovariance::new(
+            self.estimate.pose * delta,
+            f * self.estimate.covariance * f.transpose() + g * q * g.transpose(),
+        );
+        &self.estimate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    #[test]
+    fn test_dead_reckoning() {
+        let start = Pose::new(Vector2::new(1.0, 0.0), std::f64::consts::FRAC_PI_2);
+        let mut odometry = DeadReckoning::from_pose(start);
+        assert_eq!(odometry.estimate().covariance, na::Matrix3::zeros());
+        // almost half circle of the radius 1.0 around the origin
+        let velocity = Velocity { x: 0.5, theta: 0.5 };
+        for _ in 0..31 {
+            odometry.integrate(&velocity, 0.2);
+        }
+        let distance = 3.1;
+        let angle = std::f64::consts::FRAC_PI_2 + distance;
+        let pose = odometry.pose();
+        // the first order integration drifts slightly from the circle
+        assert!((pose.translation.x - distance.cos()).abs() < 0.1);
+        assert!((pose.translation.y - distance.sin()).abs() < 0.1);
+        assert!((pose.rotation.angle() - na::UnitComplex::new(angle).angle()).abs() < 1e-9);
+        assert_eq!(odometry.velocity(), velocity);
+
+        // the uncertainty grows with the motion
+        let std_dev = odometry.estimate().std_dev();
+        assert!(std_dev.x > 0.0 && std_dev.y > 0.0 && std_dev.yaw > 0.0);
+        let before = odometry.estimate().covariance.trace();
+        odometry.integrate(&Velocity::default(), 0.1);
+        assert_eq!(odometry.estimate().covariance.trace(), before);
+        odometry.integrate_delta(&Pose::new(Vector2::new(1.0, 0.0), 0.0));
+        assert!(odometry.estimate().covariance.trace() > before);
+
+        odometry.reset(PoseWithCovariance::new(start, na::Matrix3::identity()));
+        assert_eq!(odometry.pose(), start);
+        assert_eq!(odometry.estimate().covariance, na::Matrix3::identity());
+    }
+}
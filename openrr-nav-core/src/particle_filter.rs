@@ -0,0 +1,455 @@
+use grid_map::{Cell, GridMap, Position, RaycastHit};
+use nalgebra as na;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    euclidean_distance_transform, mppi::standard_normal, OdometryNoise, Pose, PoseWithCovariance,
+    RangeReading,
+};
+
+fn default_num_particles() -> usize {
+    500
+}
+
+fn default_max_range() -> f64 {
+    10.0
+}
+
+fn default_beam_step() -> usize {
+    1
+}
+
+fn default_sigma_hit() -> f64 {
+    0.2
+}
+
+fn default_z_hit() -> f64 {
+    0.95
+}
+
+fn default_z_rand() -> f64 {
+    0.05
+}
+
+fn default_resample_threshold() -> f64 {
+    0.5
+}
+
+/// Parameters of [`ParticleFilter`]
+///
+/// The names follow the parameters of AMCL with the likelihood field model.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParticleFilterConfig {
+    #[serde(default = "default_num_particles")]
+    pub num_particles: usize,
+    /// (m) Readings shorter than this are ignored
+    #[serde(default)]
+    pub min_range: f64,
+    /// (m) Readings longer than or equal to this are ignored
+    #[serde(default = "default_max_range")]
+    pub max_range: f64,
+    /// Use every `beam_step`-th reading of the scan
+    #[serde(default = "default_beam_step")]
+    pub beam_step: usize,
+    /// (m) Standard deviation of the distance from the endpoint to the obstacle
+    #[serde(default = "default_sigma_hit")]
+    pub sigma_hit: f64,
+    #[serde(default = "default_z_hit")]
+    pub z_hit: f64,
+    /// Weight of the random measurements, which keeps the particles alive against
+    /// the unmodeled obstacles
+    #[serde(default = "default_z_rand")]
+    pub z_rand: f64,
+    /// Resample if the effective number of the particles is less than
+    /// `resample_threshold * num_particles`
+    #[serde(default = "default_resample_threshold")]
+    pub resample_threshold: f64,
+    #[serde(default)]
+    pub odometry_noise: OdometryNoise,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+impl Default for ParticleFilterConfig {
+    fn default() -> Self {
+        Self {
+            num_particles: default_num_particles(),
+            min_range: 0.0,
+            max_range: default_max_range(),
+            beam_step: default_beam_step(),
+            sigma_hit: default_sigma_hit(),
+            z_hit: default_z_hit(),
+            z_rand: default_z_rand(),
+            resample_threshold: default_resample_threshold(),
+            odometry_noise: OdometryNoise::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// Hypothesis of the pose of the robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub pose: Pose,
+    /// Normalized so that the sum of the particles is 1
+    pub weight: f64,
+}
+
+/// Monte Carlo localization on the static map
+///
+/// The particles are moved by the odometry deltas with the noise of
+/// [`OdometryNoise`] in [`ParticleFilter::predict`], and weighted by the range
+/// readings with the likelihood field of the map in [`ParticleFilter::update`].
+/// Each reading contributes `z_hit * exp(-d^2 / (2 sigma_hit^2)) + z_rand / max_range`,
+/// where `d` is the distance from its endpoint to the nearest obstacle of the map.
+/// The particles are resampled by the low variance sampling.
+///
+/// Unlike AMCL, the number of the particles is fixed.
+#[derive(Debug, Clone)]
+pub struct ParticleFilter {
+    config: ParticleFilterConfig,
+    /// Pose of the sensor in the robot frame
+    pub sensor_pose: Pose,
+    map: GridMap<u8>,
+    /// (m) Distance to the nearest obstacle of the map
+    distance_map: GridMap<f64>,
+    particles: Vec<Particle>,
+    rng: StdRng,
+}
+
+impl ParticleFilter {
+    /// Create the filter without particles. Initialize it with
+    /// [`ParticleFilter::initialize`] or [`ParticleFilter::initialize_global`].
+    pub fn new(map: GridMap<u8>, config: ParticleFilterConfig) -> Self {
+        let mut distance_map = euclidean_distance_transform(&map);
+        distance_map.set_origin(map.origin().copied());
+        Self {
+            rng: StdRng::seed_from_u64(config.seed),
+            config,
+            sensor_pose: Pose::identity(),
+            map,
+            distance_map,
+            particles: vec![],
+        }
+    }
+
+    pub fn with_sensor_pose(mut self, sensor_pose: Pose) -> Self {
+        self.sensor_pose = sensor_pose;
+        self
+    }
+
+    pub fn config(&self) -> &ParticleFilterConfig {
+        &self.config
+    }
+
+    pub fn map(&self) -> &GridMap<u8> {
+        &self.map
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    fn uniform_weight(&self) -> f64 {
+        1.0 / self.config.num_particles.max(1) as f64
+    }
+
+    /// Sample the particles around the initial pose, e.g. the pose set in the viewer
+    pub fn initialize(&mut self, initial: &PoseWithCovariance) {
+        // the covariance may be singular, e.g. zero for yaw
+        let l = na::Cholesky::new(initial.covariance + na::Matrix3::identity() * 1e-12)
+            .map_or_else(|| initial.covariance.map(|v| v.max(0.0).sqrt()), |c| c.l());
+        let weight = self.uniform_weight();
+        self.particles = (0..self.config.num_particles)
+            .map(|_| {
+                let noise = l * na::Vector3::from_fn(|_, _| standard_normal(&mut self.rng));
+                let p = initial.pose;
+                Particle {
+                    pose: Pose::new(
+                        p.translation.vector + noise.xy(),
+                        p.rotation.angle() + noise.z,
+                    ),
+                    weight,
+                }
+            })
+            .collect();
+    }
+
+    /// Sample the particles uniformly from the free cells of the map, when the pose
+    /// is not known at all
+    pub fn initialize_global(&mut self) {
+        let free = self
+            .map
+            .enumerate_cells()
+            .filter(|(_, _, cell)| matches!(cell, Cell::Value(_)))
+            .map(|(_, position, _)| position)
+            .collect::<Vec<_>>();
+        if free.is_empty() {
+            self.particles.clear();
+            return;
+        }
+        let half = self.map.resolution() / 2.0;
+        let weight = self.uniform_weight();
+        self.particles = (0..self.config.num_particles)
+            .map(|_| {
+                let center = free[self.rng.gen_range(0..free.len())];
+                let position = self.map.map_to_world(&Position::new(
+                    center.x + self.rng.gen_range(-half..half),
+                    center.y + self.rng.gen_range(-half..half),
+                ));
+                let yaw = self
+                    .rng
+                    .gen_range(-std::f64::consts::PI..std::f64::consts::PI);
+                Particle {
+                    pose: Pose::new(na::Vector2::new(position.x, position.y), yaw),
+                    weight,
+                }
+            })
+            .collect();
+    }
+
+    /// Move the particles by the odometry delta in the robot frame
+    pub fn predict(&mut self, delta: &Pose) {
+        let (translation_variance, rotation_variance) = self.config.odometry_noise.variances(delta);
+        let distance = delta.translation.vector.norm();
+        let direction = if distance > 0.0 {
+            delta.translation.vector / distance
+        } else {
+            na::Vector2::zeros()
+        };
+        for particle in &mut self.particles {
+            let d = distance + translation_variance.sqrt() * standard_normal(&mut self.rng);
+            let r =
+                delta.rotation.angle() + rotation_variance.sqrt() * standard_normal(&mut self.rng);
+            particle.pose *= Pose::new(direction * d, r);
+        }
+    }
+
+    /// Log likelihood of the readings at the pose
+    fn log_likelihood(&self, pose: &Pose, readings: &[RangeReading]) -> f64 {
+        let config = &self.config;
+        let sensor = pose * self.sensor_pose;
+        let random = config.z_rand / config.max_range;
+        let denominator = 2.0 * config.sigma_hit.powi(2);
+        readings
+            .iter()
+            .step_by(config.beam_step.max(1))
+            .filter(|r| r.range >= config.min_range && r.range < config.max_range)
+            .map(|r| {
+                let angle = sensor.rotation.angle() + r.bearing;
+                let end = Position::new(
+                    sensor.translation.x + r.range * angle.cos(),
+                    sensor.translation.y + r.range * angle.sin(),
+                );
+                let hit = match self.distance_map.cell_by_position(&end) {
+                    Some(Cell::Value(d)) => config.z_hit * (-d * d / denominator).exp(),
+                    _ => 0.0,
+                };
+                (hit + random).ln()
+            })
+            .sum()
+    }
+
+    /// Weight the particles by the range readings taken at the current pose
+    ///
+    /// Readings which are NaN, shorter than `min_range` or not shorter than
+    /// `max_range` are ignored.
+    pub fn update(&mut self, readings: &[RangeReading]) {
+        if self.particles.is_empty() {
+            return;
+        }
+        let log_weights = self
+            .particles
+            .iter()
+            .map(|p| p.weight.ln() + self.log_likelihood(&p.pose, readings))
+            .collect::<Vec<_>>();
+        let max = log_weights
+            .iter()
+            .copied()
+            .fold(f64::NEG_INFINITY, f64::max);
+        let weights = log_weights
+            .iter()
+            .map(|w| (w - max).exp())
+            .collect::<Vec<_>>();
+        let total = weights.iter().sum::<f64>();
+        if !(total > 0.0 && total.is_finite()) {
+            return;
+        }
+        for (particle, weight) in self.particles.iter_mut().zip(weights) {
+            particle.weight = weight / total;
+        }
+        if self.effective_num_particles()
+            < self.config.resample_threshold * self.particles.len() as f64
+        {
+            self.resample();
+        }
+    }
+
+    /// `1 / sum(w^2)`, which is the number of the particles if the weights are uniform
+    pub fn effective_num_particles(&self) -> f64 {
+        1.0 / self.particles.iter().map(|p| p.weight.powi(2)).sum::<f64>()
+    }
+
+    /// Low variance resampling
+    pub fn resample(&mut self) {
+        let n = self.config.num_particles;
+        if self.particles.is_empty() || n == 0 {
+            return;
+        }
+        let step = 1.0 / n as f64;
+        let mut target = self.rng.gen_range(0.0..step);
+        let mut cumulative = self.particles[0].weight;
+        let mut i = 0;
+        let mut resampled = Vec::with_capacity(n);
+        for _ in 0..n {
+            while target > cumulative && i + 1 < self.particles.len() {
+                i += 1;
+                cumulative += self.particles[i].weight;
+            }
+            resampled.push(Particle {
+                pose: self.particles[i].pose,
+                weight: step,
+            });
+            target += step;
+        }
+        self.particles = resampled;
+    }
+
+    /// Weighted mean and covariance of the particles
+    ///
+    /// The yaw is the circular mean. `None` if there is no particle.
+    pub fn estimate(&self) -> Option<PoseWithCovariance> {
+        if self.particles.is_empty() {
+            return None;
+        }
+        let total = self.particles.iter().map(|p| p.weight).sum::<f64>();
+        let (mut position, mut sin, mut cos) = (na::Vector2::zeros(), 0.0, 0.0);
+        for p in &self.particles {
+            let w = p.weight / total;
+            position += p.pose.translation.vector * w;
+            sin += p.pose.rotation.angle().sin() * w;
+            cos += p.pose.rotation.angle().cos() * w;
+        }
+        let mean = Pose::new(position, sin.atan2(cos));
+        let mut covariance = na::Matrix3::zeros();
+        for p in &self.particles {
+            let d = p.pose.translation.vector - position;
+            let yaw = (mean.rotation.inverse() * p.pose.rotation).angle();
+            let v = na::Vector3::new(d.x, d.y, yaw);
+            covariance += v * v.transpose() * (p.weight / total);
+        }
+        Some(PoseWithCovariance::new(mean, covariance))
+    }
+}
+
+/// Range readings at the pose simulated by raycasting on the map
+///
+/// The readings without obstacle within `max_range` are infinite.
+pub fn simulate_scan(
+    map: &GridMap<u8>,
+    sensor_pose: &Pose,
+    bearings: &[f64],
+    max_range: f64,
+) -> Vec<RangeReading> {
+    let origin = Position::new(sensor_pose.translation.x, sensor_pose.translation.y);
+    bearings
+        .iter()
+        .map(|bearing| {
+            let angle = sensor_pose.rotation.angle() + bearing;
+            let end = Position::new(
+                origin.x + max_range * angle.cos(),
+                origin.y + max_range * angle.sin(),
+            );
+            let range = match map.raycast(&map.world_to_map(&origin), &map.world_to_map(&end)) {
+                RaycastHit::Obstacle { distance, .. } => distance,
+                RaycastHit::Clear | RaycastHit::OutOfMap => f64::INFINITY,
+            };
+            RangeReading::new(*bearing, range)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Grid;
+
+    fn room() -> GridMap<u8> {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(4.05, 3.05), 0.05);
+        for grid in map.enumerate_cells().map(|(g, ..)| g).collect::<Vec<_>>() {
+            map.set_value(&grid, 0).unwrap();
+        }
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 0)).unwrap();
+            map.set_obstacle(&Grid::new(x, map.height() - 1)).unwrap();
+        }
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(0, y)).unwrap();
+            map.set_obstacle(&Grid::new(map.width() - 1, y)).unwrap();
+        }
+        // asymmetric obstacles
+        for x in 20..30 {
+            for y in 40..45 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        for y in 10..30 {
+            map.set_obstacle(&Grid::new(60, y)).unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn test_particle_filter() {
+        let map = room();
+        let bearings = (0..36)
+            .map(|i| i as f64 * std::f64::consts::TAU / 36.0)
+            .collect::<Vec<_>>();
+        let config = ParticleFilterConfig {
+            num_particles: 300,
+            max_range: 5.0,
+            seed: 1,
+            ..Default::default()
+        };
+        let mut filter = ParticleFilter::new(map.clone(), config);
+        assert!(filter.estimate().is_none());
+        let mut pose = Pose::new(na::Vector2::new(1.0, 1.0), 0.3);
+        // the initial guess is off by 0.3 m and 0.2 rad
+        let guess = Pose::new(na::Vector2::new(1.2, 0.8), 0.5);
+        filter.initialize(&PoseWithCovariance::from_std_dev(
+            guess,
+            &crate::PoseStdDev {
+                x: 0.3,
+                y: 0.3,
+                yaw: 0.3,
+            },
+        ));
+        assert_eq!(filter.particles().len(), 300);
+        let delta = Pose::new(na::Vector2::new(0.05, 0.0), 0.02);
+        for _ in 0..30 {
+            pose *= delta;
+            filter.predict(&delta);
+            filter.update(&simulate_scan(&map, &pose, &bearings, 5.0));
+        }
+        let estimate = filter.estimate().unwrap();
+        let error = (estimate.pose.translation.vector - pose.translation.vector).norm();
+        assert!(error < 0.1, "{error}");
+        let yaw_error = (estimate.pose.rotation.inverse() * pose.rotation).angle();
+        assert!(yaw_error.abs() < 0.1, "{yaw_error}");
+        let std_dev = estimate.std_dev();
+        assert!(std_dev.x < 0.1 && std_dev.y < 0.1);
+        let total = filter.particles().iter().map(|p| p.weight).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+
+        // the particles are in the free cells
+        filter.initialize_global();
+        assert!(filter.particles().iter().all(|p| {
+            matches!(
+                map.cell_by_position(&Position::new(p.pose.translation.x, p.pose.translation.y)),
+                Some(Cell::Value(_))
+            )
+        }));
+    }
+}
@@ -0,0 +1,83 @@
+//! Waypoints of the global path
+//!
+//! The paths are the waypoints `[x, y, ...]` in the map frame, like the output of
+//! `rrt::dual_rrt_connect`. Only x and y (the first two elements) are used.
+
+use crate::utils::distance_to_segment;
+
+/// Remove the waypoints which the robot at the position has passed
+///
+/// The waypoints before the segment nearest to the position are removed, and so is
+/// the start of the segment if the robot has moved along it. The last waypoint is
+/// always kept. This is different from
+/// [`utils::simplify_path`](crate::utils::simplify_path), which removes the redundant
+/// waypoints.
+pub fn prune_passed(path: &[Vec<f64>], position: [f64; 2]) -> Vec<Vec<f64>> {
+    if path.len() <= 1 {
+        return path.to_vec();
+    }
+    let mut nearest = (0, f64::INFINITY);
+    for (i, (a, b)) in path.iter().zip(path.iter().skip(1)).enumerate() {
+        let distance = distance_to_segment(&position, a, b);
+        if distance < nearest.1 {
+            nearest = (i, distance);
+        }
+    }
+    let (a, b) = (&path[nearest.0], &path[nearest.0 + 1]);
+    // passed the start of the segment if the projection is ahead of it
+    let along = (position[0] - a[0]) * (b[0] - a[0]) + (position[1] - a[1]) * (b[1] - a[1]);
+    let first = if along > 0.0 {
+        nearest.0 + 1
+    } else {
+        nearest.0
+    };
+    path[first..].to_vec()
+}
+
+/// Insert waypoints so that the distance between waypoints is at most `max_spacing`
+///
+/// x and y are interpolated linearly and the other elements (like the angle)
+/// are copied from the start of each segment.
+pub fn densify_path(path: &[Vec<f64>], max_spacing: f64) -> Vec<Vec<f64>> {
+    let mut densified = vec![];
+    for (a, b) in path.iter().zip(path.iter().skip(1)) {
+        let length = ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt();
+        let num = if max_spacing > 0.0 {
+            (length / max_spacing).ceil().max(1.0) as usize
+        } else {
+            1
+        };
+        for i in 0..num {
+            let t = i as f64 / num as f64;
+            let mut p = a.clone();
+            p[0] = a[0] + (b[0] - a[0]) * t;
+            p[1] = a[1] + (b[1] - a[1]) * t;
+            densified.push(p);
+        }
+    }
+    if let Some(last) = path.last() {
+        densified.push(last.clone());
+    }
+    densified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prune_passed_waypoints() {
+        let path = densify_path(&[vec![0.0, 0.0], vec![1.0, 0.0], vec![1.0, 1.0]], 0.1);
+        assert_eq!(path.len(), 21);
+        assert_eq!(prune_passed(&path, [-0.1, 0.05]), path);
+        // on the 4th segment
+        let pruned = prune_passed(&path, [0.33, 0.02]);
+        assert_eq!(pruned[0], vec![0.4, 0.0]);
+        assert_eq!(pruned.len(), 17);
+        // the nearest segment is on the second line
+        let pruned = prune_passed(&path, [0.8, 0.55]);
+        assert_eq!(pruned[0], vec![1.0, 0.6]);
+        assert_eq!(prune_passed(&path, [2.0, 2.0]), vec![vec![1.0, 1.0]]);
+        assert!(prune_passed(&[], [0.0, 0.0]).is_empty());
+    }
+}
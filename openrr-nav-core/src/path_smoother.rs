@@ -0,0 +1,357 @@
+use grid_map::{GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::{global_planner::is_free_cell, path, Error, Result};
+
+/// Curve fitted to the shortcut path by [`PathSmoother`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmoothingMethod {
+    /// Centripetal Catmull-Rom spline through the waypoints
+    CubicSpline,
+    /// Quadratic Bezier curves rounding the corners, sized for the curvature limit
+    #[default]
+    Bezier,
+}
+
+/// Post-processor of the raw global path (e.g. the output of RRT)
+///
+/// The path is shortcut to the farthest waypoints in the line of sight, and the
+/// corners are smoothed with the [`SmoothingMethod`]:
+///
+/// - `Bezier`: each corner is rounded so that the curvature is at most
+///   `max_curvature` if the neighboring segments are long enough. The curve is
+///   shrunk if it collides.
+/// - `CubicSpline`: the spline passes through the waypoints of the shortcut path.
+///   The spans of the spline which collide or exceed `max_curvature` are replaced
+///   by the straight lines.
+///
+/// The paths are `[x, y]` in the map frame and the output is sampled at `spacing`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathSmoother {
+    #[serde(default)]
+    method: SmoothingMethod,
+    /// (1/m)
+    #[serde(default = "default_max_curvature")]
+    max_curvature: f64,
+    /// (m)
+    #[serde(default = "default_spacing")]
+    spacing: f64,
+    #[serde(default)]
+    allow_unknown: bool,
+}
+
+fn default_max_curvature() -> f64 {
+    2.0
+}
+
+fn default_spacing() -> f64 {
+    0.05
+}
+
+impl Default for PathSmoother {
+    fn default() -> Self {
+        Self::new(SmoothingMethod::default())
+    }
+}
+
+type Point = [f64; 2];
+
+fn distance(a: &Point, b: &Point) -> f64 {
+    (b[0] - a[0]).hypot(b[1] - a[1])
+}
+
+fn lerp(a: &Point, b: &Point, t: f64) -> Point {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+}
+
+/// Curvature of the circle through the points
+fn menger_curvature(a: &Point, b: &Point, c: &Point) -> f64 {
+    let cross = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+    let lengths = distance(a, b) * distance(b, c) * distance(c, a);
+    if lengths == 0.0 {
+        0.0
+    } else {
+        2.0 * cross.abs() / lengths
+    }
+}
+
+/// Points of the segment excluding the end point
+fn sample_line(a: &Point, b: &Point, spacing: f64) -> Vec<Point> {
+    let num = (distance(a, b) / spacing).ceil().max(1.0) as usize;
+    (0..num)
+        .map(|i| lerp(a, b, i as f64 / num as f64))
+        .collect()
+}
+
+impl PathSmoother {
+    pub fn new(method: SmoothingMethod) -> Self {
+        Self {
+            method,
+            max_curvature: default_max_curvature(),
+            spacing: default_spacing(),
+            allow_unknown: false,
+        }
+    }
+
+    /// Maximum curvature (1/m) of the smoothed corners
+    pub fn with_max_curvature(mut self, max_curvature: f64) -> Self {
+        self.max_curvature = max_curvature;
+        self
+    }
+
+    /// Distance (m) between the points of the output
+    pub fn with_spacing(mut self, spacing: f64) -> Self {
+        self.spacing = spacing;
+        self
+    }
+
+    /// Allow the path to pass through Unknown cells
+    pub fn with_allow_unknown(mut self, allow_unknown: bool) -> Self {
+        self.allow_unknown = allow_unknown;
+        self
+    }
+
+    pub fn method(&self) -> SmoothingMethod {
+        self.method
+    }
+
+    pub fn max_curvature(&self) -> f64 {
+        self.max_curvature
+    }
+
+    pub fn spacing(&self) -> f64 {
+        self.spacing
+    }
+
+    fn is_free(&self, map: &GridMap<u8>, p: &Point) -> bool {
+        map.world_to_grid(&Position::new(p[0], p[1]))
+            .is_some_and(|grid| is_free_cell(map.cell(&grid), self.allow_unknown))
+    }
+
+    fn is_line_free(&self, map: &GridMap<u8>, a: &Point, b: &Point) -> bool {
+        self.is_free(map, a)
+            && self.is_free(map, b)
+            && map
+                .traverse(&Position::new(a[0], a[1]), &Position::new(b[0], b[1]))
+                .all(|grid| is_free_cell(map.cell(&grid), self.allow_unknown))
+    }
+
+    fn is_curve_free(&self, map: &GridMap<u8>, curve: &[Point]) -> bool {
+        curve
+            .iter()
+            .zip(curve.iter().skip(1))
+            .all(|(a, b)| self.is_line_free(map, a, b))
+    }
+
+    /// Waypoints connected to the farthest waypoint in the line of sight
+    pub fn shortcut(&self, map: &GridMap<u8>, path: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        if path.len() <= 2 {
+            return path.to_vec();
+        }
+        let point = |i: usize| [path[i][0], path[i][1]];
+        let mut shortcut = vec![path[0].clone()];
+        let mut i = 0;
+        while i < path.len() - 1 {
+            let mut j = path.len() - 1;
+            while j > i + 1 && !self.is_line_free(map, &point(i), &point(j)) {
+                j -= 1;
+            }
+            shortcut.push(path[j].clone());
+            i = j;
+        }
+        shortcut
+    }
+
+    /// Shortcut and smooth the path
+    ///
+    /// The start and the end of the path are kept. Returns error if the parameters
+    /// are not positive.
+    pub fn smooth(&self, map: &GridMap<u8>, path: &[Vec<f64>]) -> Result<Vec<Vec<f64>>> {
+        if !(self.spacing > 0.0 && self.max_curvature > 0.0) {
+            return Err(Error::Other(format!(
+                "spacing ({}) and max_curvature ({}) must be positive",
+                self.spacing, self.max_curvature
+            )));
+        }
+        let waypoints = self
+            .shortcut(map, path)
+            .iter()
+            .map(|p| [p[0], p[1]])
+            .collect::<Vec<_>>();
+        let Some(last) = waypoints.last().copied() else {
+            return Ok(vec![]);
+        };
+        let mut points = match self.method {
+            SmoothingMethod::Bezier => self.round_corners(map, &waypoints),
+            SmoothingMethod::CubicSpline => self.fit_spline(map, &waypoints),
+        };
+        points.push(last);
+        // the parameters of the curves are not proportional to the length
+        let points = points.iter().map(|p| p.to_vec()).collect::<Vec<_>>();
+        Ok(path::densify_path(&points, self.spacing))
+    }
+
+    fn round_corners(&self, map: &GridMap<u8>, waypoints: &[Point]) -> Vec<Point> {
+        let mut points = vec![];
+        let mut current = waypoints[0];
+        for corner in waypoints.windows(3) {
+            let [a, b, c] = [corner[0], corner[1], corner[2]];
+            let (ab, bc) = (distance(&a, &b), distance(&b, &c));
+            let u1 = [(b[0] - a[0]) / ab, (b[1] - a[1]) / ab];
+            let u2 = [(c[0] - b[0]) / bc, (c[1] - b[1]) / bc];
+            let angle = (u1[0] * u2[0] + u1[1] * u2[1]).clamp(-1.0, 1.0).acos();
+            // the curvature of the quadratic Bezier with the legs of the length d is
+            // the largest at the middle, sin(angle / 2) / (d cos^2(angle / 2))
+            let half = angle / 2.0;
+            let required = half.sin() / (self.max_curvature * half.cos().powi(2));
+            let mut d = required.min(ab / 2.0).min(bc / 2.0);
+            let mut curve = vec![];
+            while d > map.resolution() * 0.5 {
+                let (p0, p2) = (lerp(&b, &a, d / ab), lerp(&b, &c, d / bc));
+                let num = (2.0 * d / self.spacing).ceil().max(2.0) as usize;
+                curve = (0..=num)
+                    .map(|i| {
+                        let t = i as f64 / num as f64;
+                        lerp(&lerp(&p0, &b, t), &lerp(&b, &p2, t), t)
+                    })
+                    .collect();
+                if self.is_curve_free(map, &curve) {
+                    break;
+                }
+                curve.clear();
+                d *= 0.5;
+            }
+            match (curve.first().copied(), curve.pop()) {
+                (Some(start), Some(end)) if angle > 1e-6 => {
+                    points.extend(sample_line(&current, &start, self.spacing));
+                    points.extend(curve);
+                    current = end;
+                }
+                _ => {
+                    points.extend(sample_line(&current, &b, self.spacing));
+                    current = b;
+                }
+            }
+        }
+        points.extend(sample_line(
+            &current,
+            waypoints.last().unwrap(),
+            self.spacing,
+        ));
+        points
+    }
+
+    fn fit_spline(&self, map: &GridMap<u8>, waypoints: &[Point]) -> Vec<Point> {
+        let n = waypoints.len();
+        // extrapolated control points at both ends
+        let control = |i: isize| -> Point {
+            if i < 0 {
+                lerp(&waypoints[1.min(n - 1)], &waypoints[0], 2.0)
+            } else if i as usize >= n {
+                lerp(&waypoints[n.saturating_sub(2)], &waypoints[n - 1], 2.0)
+            } else {
+                waypoints[i as usize]
+            }
+        };
+        let mut points: Vec<Point> = vec![];
+        for i in 0..n as isize - 1 {
+            let [p0, p1, p2, p3] = [control(i - 1), control(i), control(i + 1), control(i + 2)];
+            let num = (distance(&p1, &p2) / self.spacing).ceil().max(1.0) as usize;
+            let mut span = (0..=num)
+                .map(|k| catmull_rom(&p0, &p1, &p2, &p3, k as f64 / num as f64))
+                .collect::<Vec<_>>();
+            // exactly through the waypoints
+            span[0] = p1;
+            span[num] = p2;
+            let previous = points.last().copied();
+            let exceeds = previous
+                .iter()
+                .chain(&span)
+                .collect::<Vec<_>>()
+                .windows(3)
+                .any(|w| menger_curvature(w[0], w[1], w[2]) > self.max_curvature);
+            if exceeds || !self.is_curve_free(map, &span) {
+                span = sample_line(&p1, &p2, self.spacing);
+            } else {
+                span.pop();
+            }
+            points.extend(span);
+        }
+        points
+    }
+}
+
+/// Centripetal Catmull-Rom spline between p1 and p2
+fn catmull_rom(p0: &Point, p1: &Point, p2: &Point, p3: &Point, t: f64) -> Point {
+    let knot = |a: &Point, b: &Point| distance(a, b).sqrt().max(1e-9);
+    let t1 = knot(p0, p1);
+    let t2 = t1 + knot(p1, p2);
+    let t3 = t2 + knot(p2, p3);
+    let t = t1 + (t2 - t1) * t;
+    let mix = |a: &Point, b: &Point, ta: f64, tb: f64| lerp(a, b, (t - ta) / (tb - ta));
+    let a1 = mix(p0, p1, 0.0, t1);
+    let a2 = mix(p1, p2, t1, t2);
+    let a3 = mix(p2, p3, t2, t3);
+    let b1 = mix(&a1, &a2, 0.0, t2);
+    let b2 = mix(&a2, &a3, t1, t3);
+    mix(&b1, &b2, t1, t2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Grid;
+
+    #[test]
+    fn test_path_smoother() {
+        // wall from the bottom, the path goes around its top
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for y in 0..25 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        // jagged path like RRT
+        let raw = [
+            [0.2, 0.2],
+            [0.5, 0.6],
+            [0.4, 1.0],
+            [0.9, 1.3],
+            [1.3, 1.5],
+            [1.8, 1.4],
+            [2.1, 1.0],
+            [2.3, 0.8],
+            [2.8, 0.2],
+        ]
+        .map(|p| p.to_vec());
+        let length = |path: &[Vec<f64>]| {
+            path.iter()
+                .zip(path.iter().skip(1))
+                .map(|(a, b)| (b[0] - a[0]).hypot(b[1] - a[1]))
+                .sum::<f64>()
+        };
+        for method in [SmoothingMethod::Bezier, SmoothingMethod::CubicSpline] {
+            let smoother = PathSmoother::new(method).with_max_curvature(3.0);
+            let shortcut = smoother.shortcut(&map, &raw);
+            assert!(shortcut.len() < raw.len());
+            let path = smoother.smooth(&map, &raw).unwrap();
+            assert_eq!(path.first(), raw.first());
+            assert_eq!(path.last(), raw.last());
+            assert!(length(&path) < length(&raw));
+            for (a, b) in path.iter().zip(path.iter().skip(1)) {
+                assert!((b[0] - a[0]).hypot(b[1] - a[1]) <= 0.05 + 1e-9);
+                assert!(smoother.is_line_free(&map, &[a[0], a[1]], &[b[0], b[1]]));
+            }
+            if method == SmoothingMethod::Bezier {
+                for w in path.windows(3) {
+                    let [a, b, c] = [0, 1, 2].map(|i| [w[i][0], w[i][1]]);
+                    assert!(menger_curvature(&a, &b, &c) <= 3.0 * 1.05);
+                }
+            }
+        }
+        assert!(PathSmoother::default()
+            .with_spacing(0.0)
+            .smooth(&map, &raw)
+            .is_err());
+    }
+}
@@ -0,0 +1,84 @@
+use grid_map::{Cell, Grid, GridMap};
+use thiserror::Error;
+
+use crate::{Footprint, Pose};
+
+/// First blocked part of the path found by [`is_path_valid`]
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("segment {segment} of the path is blocked by the obstacle at {grid:?}")]
+pub struct PathInvalidAt {
+    /// Index of the segment from `path[segment]` to `path[segment + 1]`, or 0 if the
+    /// path has only one pose
+    pub segment: usize,
+    /// Pose on the segment where the footprint hits the obstacle
+    pub pose: Pose,
+    pub grid: Grid,
+}
+
+/// Check that the footprint along the path doesn't hit the obstacles of the map
+///
+/// The poses are interpolated between the waypoints of the path by half of the
+/// resolution, so a new obstacle in the costmap (e.g. [`ObstacleMemory`](crate::ObstacleMemory))
+/// is detected even between the sparse waypoints. Only `Cell::Obstacle` blocks the
+/// path, and the parts of the path out of the map are not checked. Use the blocked
+/// segment to decide when to replan the global path.
+pub fn is_path_valid(
+    path: &[Pose],
+    map: &GridMap<u8>,
+    footprint: &Footprint,
+) -> Result<(), PathInvalidAt> {
+    let check = |segment: usize, pose: &Pose| match footprint
+        .grids(map, pose)
+        .into_iter()
+        .find(|grid| matches!(map.cell(grid), Some(Cell::Obstacle)))
+    {
+        Some(grid) => Err(PathInvalidAt {
+            segment,
+            pose: *pose,
+            grid,
+        }),
+        None => Ok(()),
+    };
+    if let [pose] = path {
+        return check(0, pose);
+    }
+    let step = map.resolution() * 0.5;
+    for (segment, (a, b)) in path.iter().zip(path.iter().skip(1)).enumerate() {
+        let length = (b.translation.vector - a.translation.vector).norm();
+        let num = (length / step).ceil().max(1.0) as usize;
+        for i in 0..num {
+            check(segment, &a.lerp_slerp(b, i as f64 / num as f64))?;
+        }
+        if segment + 2 == path.len() {
+            check(segment, b)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Position;
+    use nalgebra as na;
+
+    #[test]
+    fn test_is_path_valid() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 1.05), 0.05);
+        let path = [[0.2, 0.5], [1.0, 0.5], [2.0, 0.5], [2.5, 0.8]]
+            .map(|[x, y]| Pose::new(na::Vector2::new(x, y), 0.0));
+        let footprint = Footprint::Circle { radius: 0.15 };
+        assert_eq!(is_path_valid(&path, &map, &footprint), Ok(()));
+
+        // off the center line, but in the footprint
+        map.set_obstacle(&Grid::new(30, 12)).unwrap();
+        let invalid = is_path_valid(&path, &map, &footprint).unwrap_err();
+        assert_eq!(invalid.segment, 1);
+        assert_eq!(invalid.grid, Grid::new(30, 12));
+        assert!(invalid.pose.translation.x < 1.5 && invalid.pose.translation.x > 1.3);
+        assert!(is_path_valid(&path, &map, &Footprint::Circle { radius: 0.05 }).is_ok());
+        assert_eq!(is_path_valid(&path[..2], &map, &footprint), Ok(()));
+        assert!(is_path_valid(&path[1..2], &map, &footprint).is_ok());
+        assert!(is_path_valid(&[], &map, &footprint).is_ok());
+    }
+}
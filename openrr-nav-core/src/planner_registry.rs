@@ -0,0 +1,374 @@
+use grid_map::{GridMap, Position};
+use std::{collections::BTreeMap, fmt};
+
+use crate::{
+    global_planner::grids_to_poses, AStarPlanner, DijkstraPlanner, Error, GridSearchAlgorithm,
+    HybridAStarPlanner, Pose, Result, RrtStarPlanner,
+};
+#[cfg(feature = "rrt")]
+use crate::{global_planner::positions_to_poses, path, PositionSampler};
+#[cfg(feature = "rrt")]
+use grid_map::Cell;
+#[cfg(feature = "rrt")]
+use serde::{Deserialize, Serialize};
+
+/// Planner of the global path in the world frame
+///
+/// The path starts at the start and ends at (or near, depending on the planner) the
+/// goal. The poses head along the path and the last one has the orientation of the
+/// goal.
+pub trait GlobalPlanner: fmt::Debug + Send + Sync {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>>;
+
+    /// Plan with the traversal-cost layer of the same size as the map, e.g. the
+    /// inflation layer made by [`inflate_obstacles`](crate::inflate_obstacles)
+    ///
+    /// The layer is ignored by default.
+    fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        _cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        self.plan(map, start, goal)
+    }
+}
+
+impl GlobalPlanner for AStarPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        self.plan_poses(map, None, start, goal)
+    }
+
+    fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        self.plan_poses(map, cost_layer, start, goal)
+    }
+}
+
+impl GlobalPlanner for DijkstraPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        GlobalPlanner::plan_with_cost(self, map, None, start, goal)
+    }
+
+    fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        let to_grid = |pose: &Pose| {
+            let position = Position::new(pose.translation.x, pose.translation.y);
+            map.world_to_grid(&position)
+                .ok_or_else(|| Error::Other(format!("{position:?} is out of the map")))
+        };
+        let path = DijkstraPlanner::plan(self, map, cost_layer, &to_grid(start)?, &to_grid(goal)?)?;
+        Ok(grids_to_poses(map, &path, goal))
+    }
+}
+
+impl GlobalPlanner for HybridAStarPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        HybridAStarPlanner::plan(self, map, start, goal)
+    }
+}
+
+/// RRT-Connect planner with the shortcut smoothing
+///
+/// The samples are drawn from the generator seeded with `seed`, so the same query
+/// gives the same path. The path is densified to `extend_length`.
+#[cfg(feature = "rrt")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RrtPlanner {
+    /// (m)
+    pub extend_length: f64,
+    pub num_max_try: usize,
+    /// Number of the shortcut trials
+    pub num_smoothing: usize,
+    #[serde(default)]
+    pub seed: u64,
+}
+
+#[cfg(feature = "rrt")]
+impl Default for RrtPlanner {
+    fn default() -> Self {
+        Self {
+            extend_length: 0.05,
+            num_max_try: 4000,
+            num_smoothing: 1000,
+            seed: 0,
+        }
+    }
+}
+
+#[cfg(feature = "rrt")]
+impl GlobalPlanner for RrtPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        let to_map = |pose: &Pose| {
+            let p = map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+            [p.x, p.y]
+        };
+        let is_free = |p: &[f64]| {
+            map.to_grid(p[0], p[1])
+                .and_then(|grid| map.cell(&grid))
+                .is_some_and(|cell| !matches!(cell, Cell::Obstacle))
+        };
+        let (start, goal_position) = (to_map(start), to_map(goal));
+        for p in [&start, &goal_position] {
+            if !is_free(p) {
+                return Err(Error::Other(format!("{p:?} is not traversable")));
+            }
+        }
+        let sampler = PositionSampler::from_seed(map, self.seed);
+        let mut path = rrt::dual_rrt_connect(
+            &start,
+            &goal_position,
+            is_free,
+            || sampler.sample(),
+            self.extend_length,
+            self.num_max_try,
+        )
+        .map_err(Error::Other)?;
+        crate::smooth_path(
+            &mut path,
+            is_free,
+            self.extend_length,
+            self.num_smoothing,
+            &mut *sampler.rng(),
+        );
+        let positions = path::densify_path(&path, self.extend_length)
+            .iter()
+            .map(|p| map.map_to_world(&Position::new(p[0], p[1])))
+            .collect::<Vec<_>>();
+        Ok(positions_to_poses(&positions, goal))
+    }
+}
+
+/// Global planners selectable by the name at runtime
+///
+/// [`GlobalPlannerRegistry::default`] has the planners of this crate:
+///
+/// | name           | planner                                                  |
+/// |----------------|----------------------------------------------------------|
+/// | `astar`        | [`AStarPlanner`] (active)                                |
+/// | `jps`          | [`AStarPlanner`] with [`GridSearchAlgorithm::JumpPointSearch`] |
+/// | `theta_star`   | [`AStarPlanner`] with [`GridSearchAlgorithm::ThetaStar`] |
+/// | `dijkstra`     | [`DijkstraPlanner`]                                      |
+/// | `hybrid_astar` | [`HybridAStarPlanner`] with the radius of 0.5 m          |
+/// | `rrt`          | [`RrtPlanner`] (the `rrt` feature)                       |
+/// | `rrt_star`     | [`RrtStarPlanner`]                                       |
+/// | `informed_rrt_star` | [`RrtStarPlanner::informed`]                        |
+#[derive(Debug)]
+pub struct GlobalPlannerRegistry {
+    planners: BTreeMap<String, Box<dyn GlobalPlanner>>,
+    active: Option<String>,
+}
+
+impl Default for GlobalPlannerRegistry {
+    fn default() -> Self {
+        let mut registry = Self::new();
+        registry.register("astar", AStarPlanner::default());
+        registry.register(
+            "jps",
+            AStarPlanner::default().with_algorithm(GridSearchAlgorithm::JumpPointSearch),
+        );
+        registry.register(
+            "theta_star",
+            AStarPlanner::default().with_algorithm(GridSearchAlgorithm::ThetaStar),
+        );
+        registry.register("dijkstra", DijkstraPlanner::default());
+        registry.register("hybrid_astar", HybridAStarPlanner::new(0.5, 0.1));
+        #[cfg(feature = "rrt")]
+        registry.register("rrt", RrtPlanner::default());
+        registry.register("rrt_star", RrtStarPlanner::default());
+        registry.register("informed_rrt_star", RrtStarPlanner::informed());
+        registry.active = Some("astar".to_owned());
+        registry
+    }
+}
+
+impl GlobalPlannerRegistry {
+    /// Registry without planners
+    pub fn new() -> Self {
+        Self {
+            planners: BTreeMap::new(),
+            active: None,
+        }
+    }
+
+    /// Add the planner, replacing the one of the same name
+    ///
+    /// The first planner becomes active.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        planner: impl GlobalPlanner + 'static,
+    ) -> Option<Box<dyn GlobalPlanner>> {
+        let name = name.into();
+        if self.active.is_none() {
+            self.active = Some(name.clone());
+        }
+        self.planners.insert(name, Box::new(planner))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn GlobalPlanner> {
+        self.planners.get(name).map(|p| &**p)
+    }
+
+    /// Sorted names of the planners
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.planners.keys().map(String::as_str)
+    }
+
+    pub fn active(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+
+    /// Switch the planner used by [`GlobalPlannerRegistry::plan`]
+    pub fn set_active(&mut self, name: &str) -> Result<()> {
+        if !self.planners.contains_key(name) {
+            return Err(self.unknown(name));
+        }
+        self.active = Some(name.to_owned());
+        Ok(())
+    }
+
+    fn unknown(&self, name: &str) -> Error {
+        Error::Other(format!(
+            "unknown global planner {name:?} (available: {})",
+            self.names().collect::<Vec<_>>().join(", ")
+        ))
+    }
+
+    /// Plan with the active planner
+    pub fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        let name = self
+            .active
+            .as_deref()
+            .ok_or_else(|| Error::Other("no global planner is registered".to_owned()))?;
+        self.plan_with(name, map, start, goal)
+    }
+
+    /// Plan with the active planner and the traversal-cost layer (see
+    /// [`GlobalPlanner::plan_with_cost`])
+    pub fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        let name = self
+            .active
+            .as_deref()
+            .ok_or_else(|| Error::Other("no global planner is registered".to_owned()))?;
+        self.get(name)
+            .ok_or_else(|| self.unknown(name))?
+            .plan_with_cost(map, cost_layer, start, goal)
+    }
+
+    /// Plan with the planner of the name
+    pub fn plan_with(
+        &self,
+        name: &str,
+        map: &GridMap<u8>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        self.get(name)
+            .ok_or_else(|| self.unknown(name))?
+            .plan(map, start, goal)
+    }
+}
+
+/// The active planner is used, so the registry can be the planner of the
+/// [`Navigator`](crate::Navigator)
+impl GlobalPlanner for GlobalPlannerRegistry {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        GlobalPlannerRegistry::plan(self, map, start, goal)
+    }
+
+    fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        GlobalPlannerRegistry::plan_with_cost(self, map, cost_layer, start, goal)
+    }
+}
+
+#[cfg(all(test, feature = "rrt"))]
+mod tests {
+    use super::*;
+    use grid_map::Grid;
+    use nalgebra as na;
+
+    #[test]
+    fn test_global_planner_registry() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for y in 0..30 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let start = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let goal = Pose::new(na::Vector2::new(2.5, 0.5), 1.0);
+        let mut registry = GlobalPlannerRegistry::default();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [
+                "astar",
+                "dijkstra",
+                "hybrid_astar",
+                "informed_rrt_star",
+                "jps",
+                "rrt",
+                "rrt_star",
+                "theta_star"
+            ]
+        );
+        assert_eq!(registry.active(), Some("astar"));
+        for name in registry.names() {
+            let path = registry.plan_with(name, &map, &start, &goal).unwrap();
+            assert!((path[0].translation.vector - start.translation.vector).norm() < 0.05);
+            let last = path.last().unwrap();
+            assert!((last.translation.vector - goal.translation.vector).norm() <= 0.1);
+            // around the wall
+            assert!(path.iter().any(|p| p.translation.y > 1.5), "{name}");
+        }
+
+        registry.set_active("theta_star").unwrap();
+        let theta = registry.plan(&map, &start, &goal).unwrap();
+        assert!(registry.set_active("prm").is_err());
+        assert_eq!(registry.active(), Some("theta_star"));
+        let astar = registry.plan_with("astar", &map, &start, &goal).unwrap();
+        let length = |path: &[Pose]| {
+            path.iter()
+                .zip(path.iter().skip(1))
+                .map(|(a, b)| (b.translation.vector - a.translation.vector).norm())
+                .sum::<f64>()
+        };
+        assert!(length(&theta) < length(&astar));
+        let cost_layer = crate::inflate_obstacles(&map, 0.1, 0.5, 3.0).unwrap();
+        assert_eq!(
+            registry
+                .plan_with_cost(&map, Some(&cost_layer), &start, &goal)
+                .unwrap(),
+            AStarPlanner::default()
+                .with_algorithm(GridSearchAlgorithm::ThetaStar)
+                .plan_poses(&map, Some(&cost_layer), &start, &goal)
+                .unwrap()
+        );
+        assert!(registry.register("astar", AStarPlanner::greedy()).is_some());
+        assert!(GlobalPlannerRegistry::new()
+            .plan(&map, &start, &goal)
+            .is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::Pose;
+
+/// Standard deviation of (x, y, yaw) used to build a diagonal covariance
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PoseStdDev {
+    /// (m)
+    pub x: f64,
+    /// (m)
+    pub y: f64,
+    /// (rad)
+    pub yaw: f64,
+}
+
+impl Default for PoseStdDev {
+    fn default() -> Self {
+        // Same as the initial covariance of AMCL
+        Self {
+            x: 0.5,
+            y: 0.5,
+            yaw: std::f64::consts::PI / 12.0,
+        }
+    }
+}
+
+/// Pose with the covariance of (x, y, yaw)
+///
+/// This is used to initialize the pose estimators, e.g. a particle filter samples
+/// the particles around the pose.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PoseWithCovariance {
+    pub pose: Pose,
+    pub covariance: na::Matrix3<f64>,
+}
+
+impl PoseWithCovariance {
+    pub fn new(pose: Pose, covariance: na::Matrix3<f64>) -> Self {
+        Self { pose, covariance }
+    }
+
+    /// Create with the diagonal covariance
+    pub fn from_std_dev(pose: Pose, std_dev: &PoseStdDev) -> Self {
+        let covariance = na::Matrix3::from_diagonal(&na::Vector3::new(
+            std_dev.x.powi(2),
+            std_dev.y.powi(2),
+            std_dev.yaw.powi(2),
+        ));
+        Self::new(pose, covariance)
+    }
+
+    /// Standard deviation of each axis (the correlations are ignored)
+    pub fn std_dev(&self) -> PoseStdDev {
+        PoseStdDev {
+            x: self.covariance[(0, 0)].sqrt(),
+            y: self.covariance[(1, 1)].sqrt(),
+            yaw: self.covariance[(2, 2)].sqrt(),
+        }
+    }
+
+    /// Points on the ellipse of `scale` sigma of the position uncertainty
+    pub fn position_ellipse(&self, scale: f64, num_points: usize) -> Vec<[f64; 2]> {
+        let position = self.covariance.fixed_view::<2, 2>(0, 0).into_owned();
+        let eigen = position.symmetric_eigen();
+        let axes = eigen.eigenvectors;
+        let radii = eigen.eigenvalues.map(|v| scale * v.max(0.0).sqrt());
+        let center = self.pose.translation.vector;
+        (0..=num_points)
+            .map(|i| {
+                let t = i as f64 / num_points as f64 * std::f64::consts::TAU;
+                let p = center
+                    + axes.column(0) * radii[0] * t.cos()
+                    + axes.column(1) * radii[1] * t.sin();
+                [p.x, p.y]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+
+    #[test]
+    fn test_pose_with_covariance() {
+        let pose = Pose::new(Vector2::new(1.0, 2.0), 0.5);
+        let std_dev = PoseStdDev {
+            x: 0.2,
+            y: 0.1,
+            yaw: 0.3,
+        };
+        let estimate = PoseWithCovariance::from_std_dev(pose, &std_dev);
+        assert!((estimate.covariance[(0, 0)] - 0.04).abs() < 1e-12);
+        assert_eq!(estimate.covariance[(0, 1)], 0.0);
+        let restored = estimate.std_dev();
+        assert!((restored.x - 0.2).abs() < 1e-12);
+        assert!((restored.yaw - 0.3).abs() < 1e-12);
+
+        let ellipse = estimate.position_ellipse(2.0, 16);
+        assert_eq!(ellipse.len(), 17);
+        let max_dx = ellipse
+            .iter()
+            .map(|p| (p[0] - 1.0).abs())
+            .fold(0.0, f64::max);
+        let max_dy = ellipse
+            .iter()
+            .map(|p| (p[1] - 2.0).abs())
+            .fold(0.0, f64::max);
+        assert!((max_dx - 0.4).abs() < 1e-9);
+        assert!((max_dy - 0.2).abs() < 1e-2);
+    }
+}
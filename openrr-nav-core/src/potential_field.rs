@@ -0,0 +1,235 @@
+use grid_map::{GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    euclidean_distance_transform, global_planner::positions_to_poses, Error, GlobalPlanner, Pose,
+    Result,
+};
+
+/// Artificial potential field planner
+///
+/// The path descends the sum of the attractive potential of the goal and the
+/// repulsive potential of the obstacles closer than `influence_distance`, which is
+/// computed from the Euclidean distance transform of the map. It is cheap and gives
+/// smooth paths in open spaces, but may be trapped in the local minima (e.g. in
+/// front of a concave obstacle), so it is meant as a fallback when the global
+/// planning fails or is unnecessary. The planning fails if the path does not get
+/// closer to the goal in `max_stalled_steps`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PotentialFieldPlanner {
+    #[serde(default = "default_attractive_gain")]
+    pub attractive_gain: f64,
+    #[serde(default = "default_repulsive_gain")]
+    pub repulsive_gain: f64,
+    /// (m)
+    #[serde(default = "default_influence_distance")]
+    pub influence_distance: f64,
+    /// (m)
+    #[serde(default = "default_step_length")]
+    pub step_length: f64,
+    /// (m)
+    #[serde(default = "default_goal_tolerance")]
+    pub goal_tolerance: f64,
+    #[serde(default = "default_max_steps")]
+    pub max_steps: usize,
+    #[serde(default = "default_max_stalled_steps")]
+    pub max_stalled_steps: usize,
+}
+
+fn default_attractive_gain() -> f64 {
+    1.0
+}
+
+fn default_repulsive_gain() -> f64 {
+    0.05
+}
+
+fn default_influence_distance() -> f64 {
+    0.5
+}
+
+fn default_step_length() -> f64 {
+    0.05
+}
+
+fn default_goal_tolerance() -> f64 {
+    0.05
+}
+
+fn default_max_steps() -> usize {
+    2000
+}
+
+fn default_max_stalled_steps() -> usize {
+    50
+}
+
+impl Default for PotentialFieldPlanner {
+    fn default() -> Self {
+        Self {
+            attractive_gain: default_attractive_gain(),
+            repulsive_gain: default_repulsive_gain(),
+            influence_distance: default_influence_distance(),
+            step_length: default_step_length(),
+            goal_tolerance: default_goal_tolerance(),
+            max_steps: default_max_steps(),
+            max_stalled_steps: default_max_stalled_steps(),
+        }
+    }
+}
+
+/// Distance (m) to the nearest obstacle interpolated between the centers of the cells
+fn interpolated_distance(distances: &GridMap<f64>, p: &na::Vector2<f64>) -> Option<f64> {
+    let resolution = distances.resolution();
+    let min = distances.min_point();
+    let fx = ((p.x - min.x) / resolution - 0.5).clamp(0.0, (distances.width() - 1) as f64);
+    let fy = ((p.y - min.y) / resolution - 0.5).clamp(0.0, (distances.height() - 1) as f64);
+    let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+    let (x1, y1) = (
+        (x0 + 1).min(distances.width() - 1),
+        (y0 + 1).min(distances.height() - 1),
+    );
+    let (tx, ty) = (fx - x0 as f64, fy - y0 as f64);
+    let d = |x: usize, y: usize| distances.value(&grid_map::Grid::new(x, y));
+    let (d00, d10, d01, d11) = (d(x0, y0)?, d(x1, y0)?, d(x0, y1)?, d(x1, y1)?);
+    Some((d00 * (1.0 - tx) + d10 * tx) * (1.0 - ty) + (d01 * (1.0 - tx) + d11 * tx) * ty)
+}
+
+impl PotentialFieldPlanner {
+    /// Negative gradient of the potential at the position
+    fn force(
+        &self,
+        distances: &GridMap<f64>,
+        p: &na::Vector2<f64>,
+        goal: &na::Vector2<f64>,
+    ) -> na::Vector2<f64> {
+        let mut force = (goal - p) * self.attractive_gain;
+        let h = distances.resolution();
+        let d = |offset: na::Vector2<f64>| interpolated_distance(distances, &(p + offset));
+        if let (Some(d), Some(dx0), Some(dx1), Some(dy0), Some(dy1)) = (
+            d(na::Vector2::zeros()),
+            d(na::Vector2::new(-h, 0.0)),
+            d(na::Vector2::new(h, 0.0)),
+            d(na::Vector2::new(0.0, -h)),
+            d(na::Vector2::new(0.0, h)),
+        ) {
+            if d.is_finite() && d < self.influence_distance {
+                let gradient = na::Vector2::new(dx1 - dx0, dy1 - dy0) / (2.0 * h);
+                let d = d.max(h * 0.5);
+                force += gradient
+                    * (self.repulsive_gain * (1.0 / d - 1.0 / self.influence_distance) / d.powi(2));
+            }
+        }
+        force
+    }
+
+    /// Plan with the distance transform of the map, e.g. to reuse it for many queries
+    pub fn plan_with_distances(
+        &self,
+        map: &GridMap<u8>,
+        distances: &GridMap<f64>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        if !(self.step_length > 0.0 && self.goal_tolerance > 0.0) {
+            return Err(Error::Other(format!(
+                "step_length ({}) and goal_tolerance ({}) must be positive",
+                self.step_length, self.goal_tolerance
+            )));
+        }
+        let to_map = |pose: &Pose| {
+            let p = map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+            na::Vector2::new(p.x, p.y)
+        };
+        let is_free = |p: &na::Vector2<f64>| {
+            map.to_grid(p.x, p.y)
+                .is_some_and(|grid| !map.cell(&grid).unwrap().is_obstacle())
+        };
+        let (mut p, goal_position) = (to_map(start), to_map(goal));
+        if !is_free(&p) {
+            return Err(Error::Other(format!("start {p:?} is not traversable")));
+        }
+        let mut points = vec![p];
+        let mut best = (p - goal_position).norm();
+        let mut num_stalled = 0;
+        for _ in 0..self.max_steps {
+            let to_goal = (goal_position - p).norm();
+            if to_goal <= self.goal_tolerance {
+                points.push(goal_position);
+                let positions = points
+                    .iter()
+                    .map(|p| map.map_to_world(&Position::new(p.x, p.y)))
+                    .collect::<Vec<_>>();
+                return Ok(positions_to_poses(&positions, goal));
+            }
+            let force = self.force(distances, &p, &goal_position);
+            if force.norm() < 1e-12 {
+                break;
+            }
+            p += force.normalize() * self.step_length.min(to_goal);
+            if !is_free(&p) {
+                return Err(Error::Other(format!("collided at {p:?}")));
+            }
+            points.push(p);
+            if to_goal < best - self.step_length * 0.1 {
+                best = to_goal;
+                num_stalled = 0;
+            } else {
+                num_stalled += 1;
+                if num_stalled > self.max_stalled_steps {
+                    break;
+                }
+            }
+        }
+        Err(Error::Other(format!(
+            "trapped in a local minimum at {p:?} on the way to {goal_position:?}"
+        )))
+    }
+}
+
+impl GlobalPlanner for PotentialFieldPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        self.plan_with_distances(map, &euclidean_distance_transform(map), start, goal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use grid_map::Grid;
+
+    #[test]
+    fn test_potential_field() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        // small obstacle on the straight line
+        for x in 28..32 {
+            for y in 18..22 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        let planner = PotentialFieldPlanner::default();
+        let start = Pose::new(na::Vector2::new(0.3, 0.8), 0.0);
+        let goal = Pose::new(na::Vector2::new(2.7, 1.3), 0.5);
+        let path = planner.plan(&map, &start, &goal).unwrap();
+        let last = path.last().unwrap();
+        assert_eq!(last.translation.vector, goal.translation.vector);
+        assert_eq!(last.rotation.angle(), 0.5);
+        let distances = euclidean_distance_transform(&map);
+        for pose in &path {
+            let p = pose.translation.vector;
+            assert!(interpolated_distance(&distances, &p).unwrap() > 0.1);
+        }
+
+        // trapped in the U-shaped obstacle which opens toward the start
+        for i in 10..30 {
+            map.set_obstacle(&Grid::new(40, i)).unwrap();
+            map.set_obstacle(&Grid::new(i + 20, 10)).unwrap();
+            map.set_obstacle(&Grid::new(i + 20, 30)).unwrap();
+        }
+        let goal = Pose::new(na::Vector2::new(2.7, 1.0), 0.0);
+        let start = Pose::new(na::Vector2::new(1.75, 1.2), 0.0);
+        assert!(planner.plan(&map, &start, &goal).is_err());
+    }
+}
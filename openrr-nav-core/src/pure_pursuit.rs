@@ -0,0 +1,336 @@
+use grid_map::{GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dwa_planner::velocity_to_pose, is_path_valid, path, Footprint, Limits, Plan, Pose, Velocity,
+};
+
+fn default_desired_velocity() -> f64 {
+    0.3
+}
+
+fn default_min_lookahead_distance() -> f64 {
+    0.3
+}
+
+fn default_max_lookahead_distance() -> f64 {
+    0.9
+}
+
+fn default_lookahead_time() -> f64 {
+    1.5
+}
+
+fn default_regulated_min_radius() -> f64 {
+    0.9
+}
+
+fn default_min_regulated_velocity() -> f64 {
+    0.05
+}
+
+fn default_rotate_to_heading_angle() -> f64 {
+    0.785
+}
+
+fn default_rotate_velocity() -> f64 {
+    1.0
+}
+
+fn default_collision_time() -> f64 {
+    1.0
+}
+
+/// Regulated pure pursuit path follower
+///
+/// The robot drives along the arc to the point of the global path at the lookahead
+/// distance, which grows with the velocity. The linear velocity is reduced on the
+/// sharp curves, near the obstacles and when approaching the end of the path, and
+/// the robot rotates in place first if the path is behind it. This is lighter than
+/// [`DwaPlanner`](crate::DwaPlanner) and follows well-structured global paths
+/// closely, but doesn't avoid the obstacles which are not avoided by the path.
+///
+/// The output is the same [`Plan`] as the DWA. The cost is infinite if the
+/// footprint along the arc for `collision_time` hits an obstacle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PurePursuitController {
+    pub limits: Limits,
+    /// (s)
+    pub controller_dt: f64,
+    /// (m/s)
+    #[serde(default = "default_desired_velocity")]
+    pub desired_velocity: f64,
+    /// (m)
+    #[serde(default = "default_min_lookahead_distance")]
+    pub min_lookahead_distance: f64,
+    /// (m)
+    #[serde(default = "default_max_lookahead_distance")]
+    pub max_lookahead_distance: f64,
+    /// (s) The lookahead distance is `velocity * lookahead_time` between the min and the max
+    #[serde(default = "default_lookahead_time")]
+    pub lookahead_time: f64,
+    /// (m) Slow down on the curves with the smaller radius. Zero disables this.
+    #[serde(default = "default_regulated_min_radius")]
+    pub regulated_min_radius: f64,
+    /// (m) Slow down closer to the obstacles than the distance. Zero disables this.
+    #[serde(default)]
+    pub obstacle_slowdown_distance: f64,
+    /// (m) Slow down closer to the end of the path than the distance. Zero disables this.
+    #[serde(default)]
+    pub approach_distance: f64,
+    /// (m/s) Lower bound of the regulations
+    #[serde(default = "default_min_regulated_velocity")]
+    pub min_regulated_velocity: f64,
+    /// (rad) Rotate in place if the lookahead point is out of the angle
+    #[serde(default = "default_rotate_to_heading_angle")]
+    pub rotate_to_heading_angle: f64,
+    /// (rad/s)
+    #[serde(default = "default_rotate_velocity")]
+    pub rotate_velocity: f64,
+    /// (s)
+    #[serde(default = "default_collision_time")]
+    pub collision_time: f64,
+    /// The collision is not checked if `None`
+    #[serde(default)]
+    pub footprint: Option<Footprint>,
+}
+
+impl PurePursuitController {
+    pub fn new(limits: Limits, controller_dt: f64) -> Self {
+        Self {
+            limits,
+            controller_dt,
+            desired_velocity: default_desired_velocity(),
+            min_lookahead_distance: default_min_lookahead_distance(),
+            max_lookahead_distance: default_max_lookahead_distance(),
+            lookahead_time: default_lookahead_time(),
+            regulated_min_radius: default_regulated_min_radius(),
+            obstacle_slowdown_distance: 0.0,
+            approach_distance: 0.0,
+            min_regulated_velocity: default_min_regulated_velocity(),
+            rotate_to_heading_angle: default_rotate_to_heading_angle(),
+            rotate_velocity: default_rotate_velocity(),
+            collision_time: default_collision_time(),
+            footprint: None,
+        }
+    }
+
+    pub fn lookahead_distance(&self, velocity: &Velocity) -> f64 {
+        (velocity.x.abs() * self.lookahead_time).clamp(
+            self.min_lookahead_distance,
+            self.max_lookahead_distance.max(self.min_lookahead_distance),
+        )
+    }
+
+    /// Distance (m) to the nearest obstacle within `obstacle_slowdown_distance`
+    fn obstacle_distance(&self, map: &GridMap<u8>, pose: &Pose) -> Option<f64> {
+        let center = map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+        map.cells_in_radius(&center, self.obstacle_slowdown_distance)
+            .filter(|(_, cell)| cell.is_obstacle())
+            .map(|(grid, _)| {
+                let p = map.cell_center(&grid);
+                (p.x - center.x).hypot(p.y - center.y)
+            })
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Velocity to follow the path before the acceleration limits
+    fn target_velocity(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        path: &[Vec<f64>],
+        map: &GridMap<u8>,
+    ) -> Velocity {
+        let position = [pose.translation.x, pose.translation.y];
+        let distance = |p: &[f64]| (p[0] - position[0]).hypot(p[1] - position[1]);
+        let lookahead = self.lookahead_distance(velocity);
+        let target = path
+            .iter()
+            .find(|p| distance(p) >= lookahead)
+            .unwrap_or_else(|| path.last().unwrap());
+        let local = pose.inverse_transform_point(&na::Point2::new(target[0], target[1]));
+        let angle = local.y.atan2(local.x);
+        if angle.abs() > self.rotate_to_heading_angle {
+            return Velocity {
+                x: 0.0,
+                theta: self.rotate_velocity.abs().copysign(angle),
+            };
+        }
+        let squared = local.coords.norm_squared();
+        let curvature = if squared > 0.0 {
+            2.0 * local.y / squared
+        } else {
+            0.0
+        };
+
+        let mut v = self.desired_velocity;
+        if self.regulated_min_radius > 0.0 && curvature.abs() > 1e-9 {
+            v *= (1.0 / curvature.abs() / self.regulated_min_radius).min(1.0);
+        }
+        if self.obstacle_slowdown_distance > 0.0 {
+            if let Some(d) = self.obstacle_distance(map, pose) {
+                v *= d / self.obstacle_slowdown_distance;
+            }
+        }
+        if self.approach_distance > 0.0 {
+            let remaining = distance(&path[0])
+                + path
+                    .iter()
+                    .zip(path.iter().skip(1))
+                    .map(|(a, b)| (b[0] - a[0]).hypot(b[1] - a[1]))
+                    .sum::<f64>();
+            v *= (remaining / self.approach_distance).min(1.0);
+        }
+        let v = v.max(self.min_regulated_velocity.min(self.desired_velocity));
+        Velocity {
+            x: v,
+            theta: v * curvature,
+        }
+    }
+
+    /// Command to follow the global path `[x, y, ...]` from the pose
+    ///
+    /// The passed waypoints are ignored. The plan has no path if the global path
+    /// is empty.
+    pub fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        path: &[Vec<f64>],
+        map: &GridMap<u8>,
+    ) -> Plan {
+        if path.is_empty() {
+            return Plan {
+                cost: f64::INFINITY,
+                ..Default::default()
+            };
+        }
+        let path = path::prune_passed(path, [pose.translation.x, pose.translation.y]);
+        let target = self.target_velocity(pose, velocity, &path, map);
+        let limits = &self.limits;
+        let mut x = target.x.clamp(limits.min_velocity.x, limits.max_velocity.x);
+        let mut theta = target.theta;
+        // keep the curvature if the angular velocity is saturated
+        let max_theta = if theta > 0.0 {
+            limits.max_velocity.theta
+        } else {
+            -limits.min_velocity.theta
+        };
+        if theta.abs() > max_theta {
+            if target.x != 0.0 {
+                x *= max_theta / theta.abs();
+            }
+            theta = max_theta.copysign(theta);
+        }
+        let dt = self.controller_dt;
+        let next = Velocity {
+            x: x.clamp(
+                velocity.x + limits.min_accel.x * dt,
+                velocity.x + limits.max_accel.x * dt,
+            ),
+            theta: theta.clamp(
+                velocity.theta + limits.min_accel.theta * dt,
+                velocity.theta + limits.max_accel.theta * dt,
+            ),
+        };
+
+        let diff = velocity_to_pose(&next, dt);
+        let steps = ((self.collision_time / dt).ceil() as usize).max(1);
+        let mut poses = Vec::with_capacity(steps);
+        let mut last = *pose;
+        for _ in 0..steps {
+            last *= diff;
+            poses.push(last);
+        }
+        let cost = match &self.footprint {
+            Some(footprint) if is_path_valid(&poses, map, footprint).is_err() => f64::INFINITY,
+            _ => 0.0,
+        };
+        Plan {
+            velocity: next,
+            cost,
+            path: poses,
+            sampling_issue: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Acceleration;
+    use grid_map::Grid;
+
+    fn limits() -> Limits {
+        Limits {
+            max_velocity: Velocity { x: 0.5, theta: 2.0 },
+            max_accel: Acceleration { x: 1.0, theta: 4.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -2.0,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -4.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_pure_pursuit() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 3.05), 0.05);
+        let mut controller = PurePursuitController::new(limits(), 0.1);
+        controller.approach_distance = 0.5;
+        // straight, and then turning left by 90 degrees
+        let path = path::densify_path(
+            &[
+                vec![0.5, 0.5, 0.0],
+                vec![2.0, 0.5, 0.0],
+                vec![2.0, 2.0, 0.0],
+            ],
+            0.05,
+        );
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity::default();
+        let mut max_deviation = 0.0_f64;
+        let goal = na::Vector2::new(2.0, 2.0);
+        for _ in 0..500 {
+            let plan = controller.plan(&pose, &velocity, &path, &map);
+            assert_eq!(plan.cost, 0.0);
+            velocity = plan.velocity;
+            pose = plan.path[0];
+            let (x, y) = (pose.translation.x, pose.translation.y);
+            max_deviation = max_deviation.max((y - 0.5).abs().min((x - 2.0).abs()));
+            if (pose.translation.vector - goal).norm() < 0.05 {
+                break;
+            }
+        }
+        assert!((pose.translation.vector - goal).norm() < 0.05);
+        assert!(max_deviation < 0.3, "{max_deviation}");
+        assert!(velocity.x < 0.1);
+
+        // rotate in place to the path behind
+        let behind = Pose::new(na::Vector2::new(0.5, 0.5), std::f64::consts::PI);
+        let plan = controller.plan(&behind, &Velocity::default(), &path, &map);
+        assert_eq!(plan.velocity.x, 0.0);
+        assert!(plan.velocity.theta.abs() > 0.0);
+
+        // slow down near the obstacle
+        let fast = Velocity { x: 0.3, theta: 0.0 };
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let at = Pose::new(na::Vector2::new(1.2, 0.5), 0.0);
+        let normal = controller.plan(&at, &fast, &path, &map);
+        controller.obstacle_slowdown_distance = 0.5;
+        let slow = controller.plan(&at, &fast, &path, &map);
+        assert!(slow.velocity.x < normal.velocity.x);
+        assert_eq!(slow.cost, 0.0);
+        controller.footprint = Some(Footprint::Circle { radius: 0.2 });
+        assert!(controller.plan(&at, &fast, &path, &map).cost.is_infinite());
+        assert!(controller.plan(&at, &fast, &[], &map).path.is_empty());
+    }
+}
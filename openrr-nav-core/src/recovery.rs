@@ -0,0 +1,304 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::{is_path_valid, Footprint, Pose, Velocity};
+
+/// Resources of the [`Navigator`](crate::Navigator) which the recovery behaviors can use
+#[derive(Debug)]
+pub struct RecoveryContext<'a> {
+    /// Current costmap with the observed obstacles
+    pub map: &'a mut GridMap<u8>,
+    /// Map without the observed obstacles
+    pub static_map: &'a GridMap<u8>,
+    /// Footprint for the collision checking. Only the center of the robot is checked
+    /// if `None`.
+    pub footprint: Option<&'a Footprint>,
+}
+
+/// Output of [`RecoveryBehavior::tick`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecoveryStatus {
+    /// Send the velocity and call [`RecoveryBehavior::tick`] again in the next cycle
+    Running(Velocity),
+    Succeeded,
+    Failed(String),
+}
+
+/// Action to get out of the situation where no feasible plan is found
+pub trait RecoveryBehavior: fmt::Debug + Send + Sync {
+    /// Called at the first tick of the behavior. The state of the last run
+    /// should be reset here.
+    fn start(&mut self, _pose: &Pose) {}
+    /// Run one cycle of the behavior
+    fn tick(&mut self, context: &mut RecoveryContext<'_>, pose: &Pose) -> RecoveryStatus;
+}
+
+fn default_angular_velocity() -> f64 {
+    0.5
+}
+
+fn default_back_up_speed() -> f64 {
+    0.1
+}
+
+/// Rotate in place by the angle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RotateRecovery {
+    /// (rad), negative to rotate clockwise
+    pub angle: f64,
+    /// (rad/s)
+    #[serde(default = "default_angular_velocity")]
+    pub angular_velocity: f64,
+    #[serde(skip)]
+    last_pose: Option<Pose>,
+    #[serde(skip)]
+    rotated: f64,
+}
+
+impl RotateRecovery {
+    pub fn new(angle: f64, angular_velocity: f64) -> Self {
+        Self {
+            angle,
+            angular_velocity,
+            last_pose: None,
+            rotated: 0.0,
+        }
+    }
+}
+
+impl RecoveryBehavior for RotateRecovery {
+    fn start(&mut self, _pose: &Pose) {
+        self.last_pose = None;
+        self.rotated = 0.0;
+    }
+
+    fn tick(&mut self, _context: &mut RecoveryContext<'_>, pose: &Pose) -> RecoveryStatus {
+        if let Some(last) = &self.last_pose {
+            self.rotated += (last.rotation.inverse() * pose.rotation).angle().abs();
+        }
+        self.last_pose = Some(*pose);
+        if self.rotated >= self.angle.abs() {
+            return RecoveryStatus::Succeeded;
+        }
+        RecoveryStatus::Running(Velocity {
+            x: 0.0,
+            theta: self.angular_velocity.abs().copysign(self.angle),
+        })
+    }
+}
+
+/// Move backward by the distance
+///
+/// Fails without moving if the footprint along the straight line to the
+/// destination hits an obstacle of the current costmap.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BackUpRecovery {
+    /// (m)
+    pub distance: f64,
+    /// (m/s)
+    #[serde(default = "default_back_up_speed")]
+    pub speed: f64,
+    #[serde(skip)]
+    start: Option<Pose>,
+}
+
+impl BackUpRecovery {
+    pub fn new(distance: f64, speed: f64) -> Self {
+        Self {
+            distance,
+            speed,
+            start: None,
+        }
+    }
+}
+
+impl RecoveryBehavior for BackUpRecovery {
+    fn start(&mut self, _pose: &Pose) {
+        self.start = None;
+    }
+
+    fn tick(&mut self, context: &mut RecoveryContext<'_>, pose: &Pose) -> RecoveryStatus {
+        let start = *self.start.get_or_insert(*pose);
+        let travelled = (pose.translation.vector - start.translation.vector).norm();
+        let remaining = self.distance.abs() - travelled;
+        if remaining <= 0.0 {
+            return RecoveryStatus::Succeeded;
+        }
+        let center = Footprint::Circle { radius: 0.0 };
+        let footprint = context.footprint.unwrap_or(&center);
+        let destination = pose * Pose::translation(-remaining, 0.0);
+        if let Err(e) = is_path_valid(&[*pose, destination], context.map, footprint) {
+            return RecoveryStatus::Failed(format!("can't back up: {e}"));
+        }
+        RecoveryStatus::Running(Velocity {
+            x: -self.speed.abs(),
+            theta: 0.0,
+        })
+    }
+}
+
+/// Remove the observed obstacles from the costmap by restoring the cells of the
+/// static map
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClearCostmapRecovery {
+    /// (m) Only the cells within the radius from the robot are cleared if set
+    #[serde(default)]
+    pub radius: Option<f64>,
+}
+
+impl ClearCostmapRecovery {
+    pub fn new(radius: Option<f64>) -> Self {
+        Self { radius }
+    }
+}
+
+impl RecoveryBehavior for ClearCostmapRecovery {
+    fn tick(&mut self, context: &mut RecoveryContext<'_>, pose: &Pose) -> RecoveryStatus {
+        let map = &mut *context.map;
+        let grids = match self.radius {
+            Some(radius) => {
+                let center =
+                    map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+                map.cells_in_radius(&center, radius)
+                    .map(|(grid, _)| grid)
+                    .collect::<Vec<_>>()
+            }
+            None => (0..map.height())
+                .flat_map(|y| (0..map.width()).map(move |x| Grid::new(x, y)))
+                .collect(),
+        };
+        for grid in grids {
+            let position = map.map_to_world(&map.cell_center(&grid));
+            let cell = context
+                .static_map
+                .cell_by_position(&position)
+                .cloned()
+                .unwrap_or(Cell::Unknown);
+            if let Some(c) = map.cell_mut(&grid) {
+                *c = cell;
+            }
+        }
+        RecoveryStatus::Succeeded
+    }
+}
+
+/// One of the built-in recovery behaviors
+///
+/// ```yaml
+/// - type: clear_costmap
+///   radius: 1.0
+/// - type: rotate
+///   angle: 3.14
+/// - type: back_up
+///   distance: 0.3
+///   speed: 0.1
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecoveryConfig {
+    Rotate(RotateRecovery),
+    BackUp(BackUpRecovery),
+    ClearCostmap(ClearCostmapRecovery),
+}
+
+impl RecoveryConfig {
+    pub fn to_behavior(&self) -> Box<dyn RecoveryBehavior> {
+        match self {
+            RecoveryConfig::Rotate(r) => Box::new(r.clone()),
+            RecoveryConfig::BackUp(r) => Box::new(r.clone()),
+            RecoveryConfig::ClearCostmap(r) => Box::new(r.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra as na;
+
+    fn run(
+        behavior: &mut dyn RecoveryBehavior,
+        map: &mut GridMap<u8>,
+        static_map: &GridMap<u8>,
+        mut pose: Pose,
+    ) -> (RecoveryStatus, Pose) {
+        let dt = 0.1;
+        behavior.start(&pose);
+        for _ in 0..1000 {
+            let mut context = RecoveryContext {
+                map,
+                static_map,
+                footprint: Some(&Footprint::Circle { radius: 0.1 }),
+            };
+            match behavior.tick(&mut context, &pose) {
+                RecoveryStatus::Running(v) => {
+                    pose *= Pose::new(na::Vector2::new(v.x * dt, 0.0), v.theta * dt);
+                }
+                status => return (status, pose),
+            }
+        }
+        panic!("not finished");
+    }
+
+    #[test]
+    fn test_recovery_behaviors() {
+        let static_map =
+            GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.05);
+        let mut map = static_map.clone();
+        let pose = Pose::new(na::Vector2::new(1.0, 0.5), 0.0);
+
+        let configs: Vec<RecoveryConfig> = serde_yaml::from_str(
+            "[{type: rotate, angle: -3.0}, {type: back_up, distance: 0.3}, {type: clear_costmap, radius: 0.3}]",
+        )
+        .unwrap();
+        assert_eq!(
+            configs,
+            [
+                RecoveryConfig::Rotate(RotateRecovery::new(-3.0, 0.5)),
+                RecoveryConfig::BackUp(BackUpRecovery::new(0.3, 0.1)),
+                RecoveryConfig::ClearCostmap(ClearCostmapRecovery::new(Some(0.3))),
+            ]
+        );
+        let mut behaviors = configs.iter().map(|c| c.to_behavior()).collect::<Vec<_>>();
+
+        // more than a half turn
+        let (status, rotated) = run(behaviors[0].as_mut(), &mut map, &static_map, pose);
+        assert_eq!(status, RecoveryStatus::Succeeded);
+        assert!((rotated.rotation.angle() + 3.0).abs() < 0.06);
+
+        let (status, backed) = run(behaviors[1].as_mut(), &mut map, &static_map, pose);
+        assert_eq!(status, RecoveryStatus::Succeeded);
+        assert!((backed.translation.x - 0.7).abs() < 0.02);
+
+        // blocked behind
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(15, y)).unwrap();
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let (status, blocked) = run(behaviors[1].as_mut(), &mut map, &static_map, pose);
+        assert!(matches!(status, RecoveryStatus::Failed(_)));
+        assert_eq!(blocked, pose);
+
+        // only the obstacles near the robot are cleared
+        let (status, _) = run(behaviors[2].as_mut(), &mut map, &static_map, pose);
+        assert_eq!(status, RecoveryStatus::Succeeded);
+        assert!(!map.cell(&Grid::new(15, 10)).unwrap().is_obstacle());
+        assert!(map.cell(&Grid::new(30, 10)).unwrap().is_obstacle());
+        let (status, _) = run(behaviors[1].as_mut(), &mut map, &static_map, pose);
+        assert_eq!(status, RecoveryStatus::Succeeded);
+
+        ClearCostmapRecovery::default().tick(
+            &mut RecoveryContext {
+                map: &mut map,
+                static_map: &static_map,
+                footprint: None,
+            },
+            &pose,
+        );
+        assert!(map.iter().all(|c| !c.is_obstacle()));
+    }
+}
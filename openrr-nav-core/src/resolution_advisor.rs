@@ -0,0 +1,192 @@
+use grid_map::{Grid, GridMap};
+
+use crate::{DiagnosticLevel, DwaPlanner, SelfTestReport};
+
+/// Length (m) of the runs of traversable cells containing each cell along one axis
+///
+/// Runs which reach the border of the map are not bounded by walls, so they are
+/// `f64::INFINITY`.
+fn run_lengths(map: &GridMap<u8>, horizontal: bool) -> Vec<f64> {
+    let (width, height) = (map.width(), map.height());
+    let (outer, inner) = if horizontal {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let grid = |o: usize, i: usize| {
+        if horizontal {
+            Grid::new(i, o)
+        } else {
+            Grid::new(o, i)
+        }
+    };
+    let mut lengths = vec![0.0; map.len()];
+    for o in 0..outer {
+        let mut i = 0;
+        while i < inner {
+            if !map.cell(&grid(o, i)).unwrap().is_traversable() {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < inner && map.cell(&grid(o, i)).unwrap().is_traversable() {
+                i += 1;
+            }
+            let length = if start == 0 || i == inner {
+                f64::INFINITY
+            } else {
+                (i - start) as f64 * map.resolution()
+            };
+            for j in start..i {
+                let g = grid(o, j);
+                lengths[g.y * width + g.x] = length;
+            }
+        }
+    }
+    lengths
+}
+
+/// Width (m) of the narrowest passage which is at least `min_width` wide
+///
+/// The width of the passage at each traversable cell is approximated by the shorter
+/// of the horizontal and vertical free runs through the cell, so diagonal
+/// passages are overestimated up to √2 times. Passages narrower than `min_width`
+/// (e.g. the footprint) are ignored because the robot can't go through them anyway.
+pub fn narrowest_passage(map: &GridMap<u8>, min_width: f64) -> Option<f64> {
+    let horizontal = run_lengths(map, true);
+    let vertical = run_lengths(map, false);
+    map.cells()
+        .iter()
+        .zip(horizontal.iter().zip(vertical))
+        .filter(|(cell, _)| cell.is_traversable())
+        .map(|(_, (h, v))| h.min(v))
+        .filter(|w| w.is_finite() && *w >= min_width)
+        .min_by(f64::total_cmp)
+}
+
+impl DwaPlanner {
+    /// Check whether the resolution of the map and `controller_dt` are fine enough
+    /// for the footprint, the passages of the map and the rollout step length
+    ///
+    /// The report uses the same diagnostics as [`DwaPlanner::self_test`].
+    pub fn advise_resolution(&self, map: &GridMap<u8>, robot_radius: f64) -> SelfTestReport {
+        use DiagnosticLevel::*;
+        let mut report = SelfTestReport::default();
+        let resolution = map.resolution();
+        let diameter = 2.0 * robot_radius;
+
+        let name = "footprint_resolution";
+        let cells = diameter / resolution;
+        if cells < 2.0 {
+            report.push(
+                Error,
+                name,
+                format!(
+                    "footprint spans only {cells:.1} cells, resolution {resolution} is too coarse"
+                ),
+            );
+        } else if cells < 4.0 {
+            report.push(Warn, name, format!("footprint spans only {cells:.1} cells"));
+        } else {
+            report.push(Ok, name, format!("footprint spans {cells:.1} cells"));
+        }
+
+        let name = "passage_clearance";
+        match narrowest_passage(map, diameter) {
+            Some(width) if (width - diameter) / 2.0 < resolution => report.push(
+                Warn,
+                name,
+                format!(
+                    "the narrowest passage ({width:.3} m) leaves less than one cell \
+                     ({resolution} m) on each side of the robot, it may be closed by the discretization"
+                ),
+            ),
+            Some(width) => report.push(
+                Ok,
+                name,
+                format!("the narrowest passage is {width:.3} m wide"),
+            ),
+            None => report.push(Ok, name, "no passage which the robot can go through"),
+        }
+
+        let name = "rollout_step";
+        let limits = self.limits();
+        let speed = limits.max_velocity.x.abs().max(limits.min_velocity.x.abs());
+        let step = speed * self.controller_dt();
+        if step > diameter {
+            report.push(
+                Error,
+                name,
+                format!(
+                    "rollout step ({step:.3} m) is longer than the footprint, \
+                     obstacles can be jumped over. controller_dt is too long"
+                ),
+            );
+        } else if step > resolution {
+            report.push(
+                Warn,
+                name,
+                format!(
+                    "rollout step ({step:.3} m) is longer than the resolution ({resolution} m), \
+                     thin obstacles can be skipped"
+                ),
+            );
+        } else {
+            report.push(Ok, name, format!("rollout step is {step:.3} m"));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Acceleration, Limits, Velocity};
+    use grid_map::{Cell, Position};
+    use std::collections::HashMap;
+
+    fn planner(max_velocity: f64, dt: f64) -> DwaPlanner {
+        let limits = Limits {
+            max_velocity: Velocity {
+                x: max_velocity,
+                theta: 1.0,
+            },
+            max_accel: Acceleration { x: 1.0, theta: 1.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -1.0,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -1.0,
+            },
+        };
+        DwaPlanner::new(limits, HashMap::new(), dt, 1.0, 5)
+    }
+
+    #[test]
+    fn test_advise_resolution() {
+        // corridor of 0.7 m between the walls along x
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.15), 0.1);
+        map.fill(Cell::Value(0));
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 2)).unwrap();
+            map.set_obstacle(&Grid::new(x, 10)).unwrap();
+        }
+        // small alcove which the robot can't enter
+        map.set_obstacle(&Grid::new(5, 1)).unwrap();
+        map.set_obstacle(&Grid::new(7, 1)).unwrap();
+        assert!((narrowest_passage(&map, 0.5).unwrap() - 0.7).abs() < 1e-9);
+        assert!((narrowest_passage(&map, 0.0).unwrap() - 0.1).abs() < 1e-9);
+
+        let report = planner(0.5, 0.1).advise_resolution(&map, 0.2);
+        assert_eq!(report.level(), DiagnosticLevel::Ok, "{report}");
+
+        let report = planner(0.5, 0.1).advise_resolution(&map, 0.26);
+        assert_eq!(report.level(), DiagnosticLevel::Warn, "{report}");
+        assert_eq!(report.warnings().next().unwrap().name, "passage_clearance");
+
+        let report = planner(1.0, 0.5).advise_resolution(&map, 0.04);
+        assert_eq!(report.errors().count(), 2, "{report}");
+    }
+}
@@ -0,0 +1,408 @@
+use grid_map::{GridMap, Position};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+use crate::{
+    global_planner::{is_free_cell, positions_to_poses},
+    obstacle_distance_map_edt, path, Error, GlobalPlanner, Pose, Result,
+};
+
+/// RRT* planner, optionally informed, with the samples biased away from the high-cost cells
+///
+/// Unlike RRT-Connect, the tree keeps growing after the goal is reached and the
+/// nodes are rewired to the cheaper parents, so the path approaches the shortest one
+/// as `max_iterations` increases. With `informed`, the samples are drawn from the
+/// ellipse of the positions which can improve the current path.
+///
+/// With `cost_bias > 0`, a sample in a cell of the cost `c` is rejected with the
+/// probability `cost_bias * c / (max cost of the layer)`, so the tree grows in the
+/// cells away from the obstacles. The layer is [`obstacle_distance_map_edt`] unless
+/// it is given with [`RrtStarPlanner::plan_with_cost`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RrtStarPlanner {
+    /// Maximum length (m) of the edges
+    pub extend_length: f64,
+    /// Radius (m) to choose the parent and rewire the neighbors
+    pub rewire_radius: f64,
+    pub max_iterations: usize,
+    /// Probability to sample the goal
+    #[serde(default = "default_goal_bias")]
+    pub goal_bias: f64,
+    #[serde(default)]
+    pub informed: bool,
+    /// 0.0 (uniform) to 1.0
+    #[serde(default)]
+    pub cost_bias: f64,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub allow_unknown: bool,
+}
+
+fn default_goal_bias() -> f64 {
+    0.05
+}
+
+impl Default for RrtStarPlanner {
+    fn default() -> Self {
+        Self {
+            extend_length: 0.1,
+            rewire_radius: 0.3,
+            max_iterations: 3000,
+            goal_bias: default_goal_bias(),
+            informed: false,
+            cost_bias: 0.0,
+            seed: 0,
+            allow_unknown: false,
+        }
+    }
+}
+
+type Point = [f64; 2];
+
+fn distance(a: &Point, b: &Point) -> f64 {
+    (b[0] - a[0]).hypot(b[1] - a[1])
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    point: Point,
+    parent: Option<usize>,
+    /// Length of the path from the start
+    cost: f64,
+    children: Vec<usize>,
+}
+
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+    /// Nodes connected to the goal
+    goal_parents: Vec<usize>,
+    goal: Point,
+}
+
+impl Tree {
+    fn best_goal_parent(&self) -> Option<(usize, f64)> {
+        self.goal_parents
+            .iter()
+            .map(|&i| {
+                (
+                    i,
+                    self.nodes[i].cost + distance(&self.nodes[i].point, &self.goal),
+                )
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Change the parent and update the costs of the subtree
+    fn rewire(&mut self, index: usize, parent: usize) {
+        if let Some(old) = self.nodes[index].parent {
+            self.nodes[old].children.retain(|&c| c != index);
+        }
+        self.nodes[index].parent = Some(parent);
+        self.nodes[parent].children.push(index);
+        let mut stack = vec![index];
+        while let Some(i) = stack.pop() {
+            let p = self.nodes[i].parent.unwrap();
+            self.nodes[i].cost =
+                self.nodes[p].cost + distance(&self.nodes[p].point, &self.nodes[i].point);
+            stack.extend_from_slice(&self.nodes[i].children);
+        }
+    }
+}
+
+impl RrtStarPlanner {
+    pub fn informed() -> Self {
+        Self {
+            informed: true,
+            ..Default::default()
+        }
+    }
+
+    fn is_free(&self, map: &GridMap<u8>, p: &Point) -> bool {
+        map.to_grid(p[0], p[1])
+            .is_some_and(|grid| is_free_cell(map.cell(&grid), self.allow_unknown))
+    }
+
+    fn is_edge_free(&self, map: &GridMap<u8>, a: &Point, b: &Point) -> bool {
+        self.is_free(map, b)
+            && map
+                .traverse(&Position::new(a[0], a[1]), &Position::new(b[0], b[1]))
+                .all(|grid| is_free_cell(map.cell(&grid), self.allow_unknown))
+    }
+
+    /// Uniform sample in the map, or in the ellipse whose foci are the start and the goal
+    fn sample(&self, map: &GridMap<u8>, rng: &mut StdRng, tree: &Tree) -> Point {
+        let start = tree.nodes[0].point;
+        let best = self.informed.then(|| tree.best_goal_parent()).flatten();
+        loop {
+            let point = match best {
+                Some((_, c_best)) => {
+                    let c_min = distance(&start, &tree.goal);
+                    let (a, b) = (
+                        c_best / 2.0,
+                        (c_best.powi(2) - c_min.powi(2)).max(0.0).sqrt() / 2.0,
+                    );
+                    let (r, theta) = (rng.gen::<f64>().sqrt(), rng.gen::<f64>() * TAU);
+                    let (x, y) = (a * r * theta.cos(), b * r * theta.sin());
+                    let yaw = (tree.goal[1] - start[1]).atan2(tree.goal[0] - start[0]);
+                    [
+                        (start[0] + tree.goal[0]) / 2.0 + x * yaw.cos() - y * yaw.sin(),
+                        (start[1] + tree.goal[1]) / 2.0 + x * yaw.sin() + y * yaw.cos(),
+                    ]
+                }
+                None => {
+                    let (min, max) = (map.min_point(), map.max_point());
+                    [rng.gen_range(min.x..max.x), rng.gen_range(min.y..max.y)]
+                }
+            };
+            if map.to_grid(point[0], point[1]).is_some() {
+                return point;
+            }
+        }
+    }
+
+    fn grow(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: Point,
+        goal: Point,
+    ) -> Tree {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let max_cost = cost_layer
+            .and_then(|layer| {
+                layer
+                    .cells()
+                    .iter()
+                    .filter_map(|c| c.value())
+                    .max()
+                    .copied()
+            })
+            .filter(|&c| c > 0);
+        let mut tree = Tree {
+            nodes: vec![Node {
+                point: start,
+                parent: None,
+                cost: 0.0,
+                children: vec![],
+            }],
+            goal_parents: vec![],
+            goal,
+        };
+        for _ in 0..self.max_iterations {
+            let sample = if rng.gen::<f64>() < self.goal_bias {
+                goal
+            } else {
+                let sample = self.sample(map, &mut rng, &tree);
+                if let (Some(layer), Some(max_cost)) = (cost_layer, max_cost) {
+                    let cost = map
+                        .to_grid(sample[0], sample[1])
+                        .and_then(|grid| layer.value(&grid))
+                        .unwrap_or(0);
+                    if rng.gen::<f64>() < self.cost_bias * cost as f64 / max_cost as f64 {
+                        continue;
+                    }
+                }
+                sample
+            };
+            let (nearest, nearest_distance) = tree
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(i, n)| (i, distance(&n.point, &sample)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .unwrap();
+            if nearest_distance < 1e-9 {
+                continue;
+            }
+            let from = tree.nodes[nearest].point;
+            let t = (self.extend_length / nearest_distance).min(1.0);
+            let point = [
+                from[0] + (sample[0] - from[0]) * t,
+                from[1] + (sample[1] - from[1]) * t,
+            ];
+            if !self.is_edge_free(map, &from, &point) {
+                continue;
+            }
+            let near = tree
+                .nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, n)| distance(&n.point, &point) <= self.rewire_radius)
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>();
+            let mut parent = (nearest, tree.nodes[nearest].cost + distance(&from, &point));
+            for &i in &near {
+                let cost = tree.nodes[i].cost + distance(&tree.nodes[i].point, &point);
+                if cost < parent.1 && self.is_edge_free(map, &tree.nodes[i].point, &point) {
+                    parent = (i, cost);
+                }
+            }
+            let index = tree.nodes.len();
+            tree.nodes.push(Node {
+                point,
+                parent: Some(parent.0),
+                cost: parent.1,
+                children: vec![],
+            });
+            tree.nodes[parent.0].children.push(index);
+            for &i in &near {
+                let cost = parent.1 + distance(&point, &tree.nodes[i].point);
+                if cost < tree.nodes[i].cost && self.is_edge_free(map, &point, &tree.nodes[i].point)
+                {
+                    tree.rewire(i, index);
+                }
+            }
+            if distance(&point, &goal) <= self.extend_length
+                && self.is_edge_free(map, &point, &goal)
+            {
+                tree.goal_parents.push(index);
+            }
+        }
+        tree
+    }
+
+    /// Plan the poses in the world frame with the cost layer for the sampling bias
+    ///
+    /// The layer must have the same geometry as the map.
+    pub fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        if !(self.extend_length > 0.0 && self.rewire_radius >= 0.0) {
+            return Err(Error::Other(format!(
+                "extend_length ({}) must be positive and rewire_radius ({}) must not be negative",
+                self.extend_length, self.rewire_radius
+            )));
+        }
+        let to_map = |pose: &Pose| {
+            let p = map.world_to_map(&Position::new(pose.translation.x, pose.translation.y));
+            [p.x, p.y]
+        };
+        let (start_point, goal_point) = (to_map(start), to_map(goal));
+        for p in [&start_point, &goal_point] {
+            if !self.is_free(map, p) {
+                return Err(Error::Other(format!("{p:?} is not traversable")));
+            }
+        }
+        let tree = self.grow(map, cost_layer, start_point, goal_point);
+        let (mut current, _) = tree.best_goal_parent().ok_or_else(|| {
+            Error::Other(format!(
+                "failed to find the path from {start_point:?} to {goal_point:?}"
+            ))
+        })?;
+        let mut path = vec![goal_point.to_vec()];
+        loop {
+            path.push(tree.nodes[current].point.to_vec());
+            match tree.nodes[current].parent {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+        path.reverse();
+        let positions = path::densify_path(&path, self.extend_length)
+            .iter()
+            .map(|p| map.map_to_world(&Position::new(p[0], p[1])))
+            .collect::<Vec<_>>();
+        Ok(positions_to_poses(&positions, goal))
+    }
+}
+
+impl GlobalPlanner for RrtStarPlanner {
+    fn plan(&self, map: &GridMap<u8>, start: &Pose, goal: &Pose) -> Result<Vec<Pose>> {
+        let cost_layer = if self.cost_bias > 0.0 {
+            Some(obstacle_distance_map_edt(map)?)
+        } else {
+            None
+        };
+        RrtStarPlanner::plan_with_cost(self, map, cost_layer.as_ref(), start, goal)
+    }
+
+    fn plan_with_cost(
+        &self,
+        map: &GridMap<u8>,
+        cost_layer: Option<&GridMap<u8>>,
+        start: &Pose,
+        goal: &Pose,
+    ) -> Result<Vec<Pose>> {
+        match cost_layer {
+            Some(_) => RrtStarPlanner::plan_with_cost(self, map, cost_layer, start, goal),
+            None => GlobalPlanner::plan(self, map, start, goal),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rrt"))]
+mod tests {
+    use super::*;
+    use crate::RrtPlanner;
+    use grid_map::Grid;
+    use nalgebra as na;
+
+    fn length(path: &[Pose]) -> f64 {
+        path.iter()
+            .zip(path.iter().skip(1))
+            .map(|(a, b)| (b.translation.vector - a.translation.vector).norm())
+            .sum()
+    }
+
+    #[test]
+    fn test_rrt_star() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+        for y in 0..30 {
+            map.set_obstacle(&Grid::new(30, y)).unwrap();
+        }
+        let start = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let goal = Pose::new(na::Vector2::new(2.5, 0.5), 0.0);
+        // over the top of the wall at (1.5, 1.5)
+        let shortest = 2.0 * 1.0f64.hypot(1.0);
+        let rrt = RrtPlanner {
+            num_smoothing: 0,
+            ..Default::default()
+        };
+        let rrt_length = length(&rrt.plan(&map, &start, &goal).unwrap());
+        for planner in [RrtStarPlanner::default(), RrtStarPlanner::informed()] {
+            let path = planner.plan(&map, &start, &goal).unwrap();
+            let path_length = length(&path);
+            assert!(path_length < rrt_length);
+            assert!(path_length < shortest * 1.2, "{path_length}");
+            assert!((path[0].translation.vector - start.translation.vector).norm() < 1e-9);
+            assert!(
+                (path.last().unwrap().translation.vector - goal.translation.vector).norm() < 1e-9
+            );
+        }
+        assert!(
+            length(
+                &RrtStarPlanner::informed()
+                    .plan(&map, &start, &goal)
+                    .unwrap()
+            ) <= length(&RrtStarPlanner::default().plan(&map, &start, &goal).unwrap()) + 0.05
+        );
+
+        // fewer nodes near the obstacles
+        let layer = obstacle_distance_map_edt(&map).unwrap();
+        let near_obstacles = |planner: &RrtStarPlanner| {
+            let tree = planner.grow(&map, Some(&layer), [0.5, 0.5], [2.5, 0.5]);
+            tree.nodes
+                .iter()
+                .filter(|n| {
+                    let grid = map.to_grid(n.point[0], n.point[1]).unwrap();
+                    layer.value(&grid).unwrap() > 20
+                })
+                .count() as f64
+                / tree.nodes.len() as f64
+        };
+        let biased = RrtStarPlanner {
+            cost_bias: 1.0,
+            ..Default::default()
+        };
+        assert!(near_obstacles(&biased) < near_obstacles(&RrtStarPlanner::default()));
+        assert!(biased.plan(&map, &start, &goal).is_ok());
+    }
+}
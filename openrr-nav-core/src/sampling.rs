@@ -0,0 +1,135 @@
+use grid_map::{GridMap, Position};
+use rand::{
+    distributions::{Distribution, Uniform},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+use std::cell::{RefCell, RefMut};
+
+/// Uniform sampler of the positions in the bounds of the map
+///
+/// The random number generator is owned by the sampler, so the samples are
+/// reproducible from the seed. [`PositionSampler::sample`] takes `&self`, so it can
+/// be passed to `rrt::dual_rrt_connect` as `|| sampler.sample()`.
+#[derive(Debug)]
+pub struct PositionSampler<R> {
+    rng: RefCell<R>,
+    x_range: Uniform<f64>,
+    y_range: Uniform<f64>,
+}
+
+impl PositionSampler<StdRng> {
+    pub fn from_seed<T: Clone>(map: &GridMap<T>, seed: u64) -> Self {
+        Self::new(map, StdRng::seed_from_u64(seed))
+    }
+}
+
+impl<R> PositionSampler<R>
+where
+    R: Rng,
+{
+    pub fn new<T: Clone>(map: &GridMap<T>, rng: R) -> Self {
+        Self {
+            rng: RefCell::new(rng),
+            x_range: Uniform::new(map.min_point().x, map.max_point().x),
+            y_range: Uniform::new(map.min_point().y, map.max_point().y),
+        }
+    }
+
+    /// `[x, y]` in the map frame
+    pub fn sample(&self) -> Vec<f64> {
+        let position = self.sample_position();
+        vec![position.x, position.y]
+    }
+
+    pub fn sample_position(&self) -> Position {
+        let mut rng = self.rng.borrow_mut();
+        let x = self.x_range.sample(&mut *rng);
+        Position::new(x, self.y_range.sample(&mut *rng))
+    }
+
+    /// The generator, e.g. to share it with [`smooth_path`]
+    pub fn rng(&self) -> RefMut<'_, R> {
+        self.rng.borrow_mut()
+    }
+
+    pub fn into_rng(self) -> R {
+        self.rng.into_inner()
+    }
+}
+
+/// Shortcut the path by connecting two random points with a straight line
+///
+/// This is the same as `rrt::smooth_path`, but the random points are selected with
+/// the given generator instead of the thread-local one.
+pub fn smooth_path<F, R>(
+    path: &mut Vec<Vec<f64>>,
+    mut is_free: F,
+    extend_length: f64,
+    num_max_try: usize,
+    rng: &mut R,
+) where
+    F: FnMut(&[f64]) -> bool,
+    R: Rng + ?Sized,
+{
+    for _ in 0..num_max_try {
+        if path.len() < 3 {
+            return;
+        }
+        let index1 = rng.gen_range(0..path.len() - 2);
+        let index2 = rng.gen_range(index1 + 2..path.len());
+        let mut base_point = path[index1].clone();
+        let target = &path[index2];
+        loop {
+            let distance = base_point
+                .iter()
+                .zip(target)
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt();
+            if distance < extend_length {
+                path.drain(index1 + 1..index2);
+                break;
+            }
+            let check_point = base_point
+                .iter()
+                .zip(target)
+                .map(|(near, target)| near + (target - near) * extend_length / distance)
+                .collect::<Vec<_>>();
+            if !is_free(&check_point) {
+                break;
+            }
+            base_point = check_point;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_sampling() {
+        let map = GridMap::<u8>::new(Position::new(-1.0, 0.0), Position::new(1.0, 2.0), 0.1);
+        let a = PositionSampler::from_seed(&map, 3);
+        let b = PositionSampler::from_seed(&map, 3);
+        let samples = (0..10).map(|_| a.sample()).collect::<Vec<_>>();
+        assert_eq!(samples, (0..10).map(|_| b.sample()).collect::<Vec<_>>());
+        for s in &samples {
+            assert!((-1.0..1.0).contains(&s[0]) && (0.0..2.0).contains(&s[1]));
+        }
+
+        let path = (0..=10)
+            .map(|i| vec![i as f64 * 0.1, 0.0])
+            .collect::<Vec<_>>();
+        let mut smoothed = path.clone();
+        smooth_path(&mut smoothed, |_| true, 0.05, 100, &mut *a.rng());
+        assert_eq!(smoothed.len(), 2);
+        assert_eq!((smoothed[0][0], smoothed[1][0]), (0.0, 1.0));
+        // blocked in the middle
+        let mut blocked = path.clone();
+        let is_free = |p: &[f64]| (p[0] - 0.5).abs() > 0.01;
+        smooth_path(&mut blocked, is_free, 0.02, 100, &mut *a.rng());
+        assert!(blocked.iter().any(|p| (p[0] - 0.5).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,135 @@
+use grid_map::{Cell, GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::Pose;
+
+/// A reading of the range sensor in the sensor frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct RangeReading {
+    /// Angle of the beam (rad)
+    pub bearing: f64,
+    /// Measured range (m). NaN means no measurement.
+    pub range: f64,
+}
+
+impl RangeReading {
+    pub fn new(bearing: f64, range: f64) -> Self {
+        Self { bearing, range }
+    }
+}
+
+/// Build the local costmap from laser scans (mark and clear)
+///
+/// The endpoint of each beam is marked as an obstacle and the cells along the beam
+/// are cleared as free (`Cell::Value(0)`) using raycasting.
+#[derive(Debug, Clone)]
+pub struct ScanIntegrator {
+    /// Pose of the sensor in the robot frame
+    pub sensor_pose: Pose,
+    /// Readings shorter than this are ignored
+    pub min_range: f64,
+    /// Readings longer than or equal to this have no obstacle
+    pub max_range: f64,
+    /// Clear the cells up to `max_range` for the readings without obstacle
+    /// (out of range or infinite). If false, such beams are ignored.
+    pub clear_on_max_range: bool,
+}
+
+impl Default for ScanIntegrator {
+    fn default() -> Self {
+        Self {
+            sensor_pose: Pose::identity(),
+            min_range: 0.0,
+            max_range: 10.0,
+            clear_on_max_range: true,
+        }
+    }
+}
+
+impl ScanIntegrator {
+    pub fn new(max_range: f64) -> Self {
+        Self {
+            max_range,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_sensor_pose(mut self, sensor_pose: Pose) -> Self {
+        self.sensor_pose = sensor_pose;
+        self
+    }
+
+    /// Integrate the readings taken at the robot pose into the map
+    ///
+    /// All beams are cleared first and then marked, so that a beam doesn't clear
+    /// the endpoint of another beam. Readings which are NaN or shorter than
+    /// `min_range` are unknown and don't change the map.
+    pub fn integrate(&self, map: &mut GridMap<u8>, robot_pose: &Pose, readings: &[RangeReading]) {
+        let sensor = robot_pose * self.sensor_pose;
+        let origin = Position::new(sensor.translation.x, sensor.translation.y);
+        let mut hits = vec![];
+        for reading in readings {
+            if reading.range.is_nan() || reading.range < self.min_range {
+                continue;
+            }
+            let hit = reading.range < self.max_range;
+            if !hit && !self.clear_on_max_range {
+                continue;
+            }
+            let range = reading.range.min(self.max_range);
+            let angle = sensor.rotation.angle() + reading.bearing;
+            let end = Position::new(
+                origin.x + range * angle.cos(),
+                origin.y + range * angle.sin(),
+            );
+            let end_grid = map.to_grid(end.x, end.y);
+            for grid in map.traverse(&origin, &end) {
+                if hit && Some(grid) == end_grid {
+                    break;
+                }
+                map.set_value(&grid, 0);
+            }
+            if hit {
+                hits.push(end_grid);
+            }
+        }
+        for grid in hits.into_iter().flatten() {
+            *map.cell_mut(&grid).unwrap() = Cell::Obstacle;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Vector2;
+    use grid_map::Grid;
+
+    #[test]
+    fn test_scan_integrator() {
+        let mut map =
+            GridMap::<u8>::new(Position::new(-1.05, -1.05), Position::new(1.05, 1.05), 0.1);
+        map.set_obstacle(&map.to_grid(-0.5, 0.0).unwrap()).unwrap();
+        let integrator = ScanIntegrator::new(0.8);
+        let pose = Pose::new(Vector2::new(0.0, 0.0), std::f64::consts::PI);
+        integrator.integrate(
+            &mut map,
+            &pose,
+            &[
+                // behind the robot (+x), hits at 0.5 m
+                RangeReading::new(std::f64::consts::PI, 0.5),
+                // front (-x), clears the old obstacle
+                RangeReading::new(0.0, f64::INFINITY),
+                RangeReading::new(std::f64::consts::FRAC_PI_2, f64::NAN),
+            ],
+        );
+        let cell = |x: f64, y: f64| *map.cell(&map.to_grid(x, y).unwrap()).unwrap();
+        assert_eq!(cell(0.5, 0.0), Cell::Obstacle);
+        assert_eq!(cell(0.3, 0.0), Cell::Value(0));
+        assert_eq!(cell(-0.5, 0.0), Cell::Value(0));
+        assert_eq!(cell(-0.9, 0.0), Cell::Uninitialized);
+        assert_eq!(cell(0.0, 0.4), Cell::Uninitialized);
+        assert_eq!(map.cells().iter().filter(|c| c.is_obstacle()).count(), 1);
+        assert_eq!(map.cell(&Grid::new(10, 10)), Some(&Cell::Value(0)));
+    }
+}
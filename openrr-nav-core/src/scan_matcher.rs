@@ -0,0 +1,269 @@
+use grid_map::{Cell, GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{euclidean_distance_transform, Pose, RangeReading};
+
+fn default_linear_window() -> f64 {
+    0.3
+}
+
+fn default_angular_window() -> f64 {
+    0.3
+}
+
+fn default_linear_step() -> f64 {
+    0.05
+}
+
+fn default_angular_step() -> f64 {
+    0.05
+}
+
+fn default_num_refinements() -> usize {
+    3
+}
+
+fn default_sigma() -> f64 {
+    0.1
+}
+
+fn default_max_range() -> f64 {
+    10.0
+}
+
+/// Parameters of [`ScanMatcher`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScanMatcherConfig {
+    /// (m) The pose is searched within `±linear_window` of the initial pose
+    #[serde(default = "default_linear_window")]
+    pub linear_window: f64,
+    /// (rad)
+    #[serde(default = "default_angular_window")]
+    pub angular_window: f64,
+    /// (m) Step of the first search. Each refinement halves the steps.
+    #[serde(default = "default_linear_step")]
+    pub linear_step: f64,
+    /// (rad)
+    #[serde(default = "default_angular_step")]
+    pub angular_step: f64,
+    #[serde(default = "default_num_refinements")]
+    pub num_refinements: usize,
+    /// (m) Standard deviation of the distance from the endpoint to the obstacle
+    #[serde(default = "default_sigma")]
+    pub sigma: f64,
+    /// (m) Readings shorter than this are ignored
+    #[serde(default)]
+    pub min_range: f64,
+    /// (m) Readings longer than or equal to this are ignored
+    #[serde(default = "default_max_range")]
+    pub max_range: f64,
+    /// The result is rejected if the score is lower than this
+    #[serde(default)]
+    pub min_score: f64,
+}
+
+impl Default for ScanMatcherConfig {
+    fn default() -> Self {
+        Self {
+            linear_window: default_linear_window(),
+            angular_window: default_angular_window(),
+            linear_step: default_linear_step(),
+            angular_step: default_angular_step(),
+            num_refinements: default_num_refinements(),
+            sigma: default_sigma(),
+            min_range: 0.0,
+            max_range: default_max_range(),
+            min_score: 0.0,
+        }
+    }
+}
+
+/// Result of [`ScanMatcher::match_scan`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScanMatch {
+    pub pose: Pose,
+    /// Mean of `exp(-d^2 / (2 sigma^2))` of the endpoints in `[0, 1]`, where `d`
+    /// is the distance to the nearest obstacle of the map
+    pub score: f64,
+    /// Number of the readings used for the matching
+    pub num_points: usize,
+}
+
+/// Scan to map matcher which corrects the drift of the odometry
+///
+/// The correlative search evaluates all the poses on the grid of the window around
+/// the initial pose, and then the search is repeated around the best pose with the
+/// half steps `num_refinements` times. The score is the likelihood field of the
+/// distance map, so the matching works with the partial or noisy scans. Use it to
+/// correct [`DeadReckoning`](crate::DeadReckoning), or as the measurement of the
+/// localization.
+#[derive(Debug, Clone)]
+pub struct ScanMatcher {
+    config: ScanMatcherConfig,
+    /// Pose of the sensor in the robot frame
+    pub sensor_pose: Pose,
+    /// (m) Distance to the nearest obstacle of the map
+    distance_map: GridMap<f64>,
+}
+
+impl ScanMatcher {
+    pub fn new(map: &GridMap<u8>, config: ScanMatcherConfig) -> Self {
+        let mut distance_map = euclidean_distance_transform(map);
+        distance_map.set_origin(map.origin().copied());
+        Self {
+            config,
+            sensor_pose: Pose::identity(),
+            distance_map,
+        }
+    }
+
+    pub fn with_sensor_pose(mut self, sensor_pose: Pose) -> Self {
+        self.sensor_pose = sensor_pose;
+        self
+    }
+
+    pub fn config(&self) -> &ScanMatcherConfig {
+        &self.config
+    }
+
+    /// Score of the endpoints in the sensor frame at the pose
+    fn score(&self, pose: &Pose, points: &[na::Point2<f64>]) -> f64 {
+        let sensor = pose * self.sensor_pose;
+        let denominator = 2.0 * self.config.sigma.powi(2);
+        points
+            .iter()
+            .map(|p| {
+                let p = sensor * p;
+                match self.distance_map.cell_by_position(&Position::new(p.x, p.y)) {
+                    Some(Cell::Value(d)) => (-d * d / denominator).exp(),
+                    _ => 0.0,
+                }
+            })
+            .sum::<f64>()
+            / points.len() as f64
+    }
+
+    /// Best pose on the grid of the window around the center
+    fn search(
+        &self,
+        center: &Pose,
+        points: &[na::Point2<f64>],
+        (linear_window, angular_window): (f64, f64),
+        (linear_step, angular_step): (f64, f64),
+    ) -> (Pose, f64) {
+        let num_linear = (linear_window / linear_step).floor() as i64;
+        let num_angular = (angular_window / angular_step).floor() as i64;
+        let mut best = (*center, self.score(center, points));
+        for a in -num_angular..=num_angular {
+            let yaw = center.rotation.angle() + a as f64 * angular_step;
+            for ix in -num_linear..=num_linear {
+                for iy in -num_linear..=num_linear {
+                    let pose = Pose::new(
+                        center.translation.vector
+                            + na::Vector2::new(ix as f64, iy as f64) * linear_step,
+                        yaw,
+                    );
+                    let score = self.score(&pose, points);
+                    if score > best.1 {
+                        best = (pose, score);
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    /// Refine the initial pose (e.g. the pose by the odometry) with the readings
+    ///
+    /// Returns `None` if no reading is used or the score is lower than `min_score`.
+    pub fn match_scan(&self, initial: &Pose, readings: &[RangeReading]) -> Option<ScanMatch> {
+        let config = &self.config;
+        let points = readings
+            .iter()
+            .filter(|r| r.range >= config.min_range && r.range < config.max_range)
+            .map(|r| na::Point2::new(r.range * r.bearing.cos(), r.range * r.bearing.sin()))
+            .collect::<Vec<_>>();
+        if points.is_empty() {
+            return None;
+        }
+        let mut steps = (config.linear_step.max(1e-3), config.angular_step.max(1e-3));
+        let (mut pose, mut score) = self.search(
+            initial,
+            &points,
+            (config.linear_window, config.angular_window),
+            steps,
+        );
+        for _ in 0..config.num_refinements {
+            // search the neighbors of the last best pose with the half steps
+            let window = steps;
+            steps = (steps.0 / 2.0, steps.1 / 2.0);
+            (pose, score) = self.search(&pose, &points, window, steps);
+        }
+        (score >= config.min_score).then_some(ScanMatch {
+            pose,
+            score,
+            num_points: points.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate_scan;
+    use grid_map::Grid;
+
+    #[test]
+    fn test_scan_matcher() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(4.05, 3.05), 0.05);
+        for grid in map.enumerate_cells().map(|(g, ..)| g).collect::<Vec<_>>() {
+            map.set_value(&grid, 0).unwrap();
+        }
+        for x in 0..map.width() {
+            map.set_obstacle(&Grid::new(x, 0)).unwrap();
+            map.set_obstacle(&Grid::new(x, map.height() - 1)).unwrap();
+        }
+        for y in 0..map.height() {
+            map.set_obstacle(&Grid::new(0, y)).unwrap();
+            map.set_obstacle(&Grid::new(map.width() - 1, y)).unwrap();
+        }
+        for x in 20..30 {
+            map.set_obstacle(&Grid::new(x, 40)).unwrap();
+        }
+        let bearings = (0..72)
+            .map(|i| i as f64 * std::f64::consts::TAU / 72.0)
+            .collect::<Vec<_>>();
+        let truth = Pose::new(na::Vector2::new(1.5, 1.2), 0.4);
+        let scan = simulate_scan(&map, &truth, &bearings, 5.0);
+        let matcher = ScanMatcher::new(
+            &map,
+            ScanMatcherConfig {
+                max_range: 5.0,
+                min_score: 0.5,
+                ..Default::default()
+            },
+        );
+        // the drifted odometry
+        let odometry = Pose::new(na::Vector2::new(1.65, 1.1), 0.25);
+        let result = matcher.match_scan(&odometry, &scan).unwrap();
+        assert_eq!(result.num_points, 72);
+        assert!(
+            (result.pose.translation.vector - truth.translation.vector).norm() < 0.03,
+            "{result:?}"
+        );
+        assert!(
+            (result.pose.rotation.inverse() * truth.rotation)
+                .angle()
+                .abs()
+                < 0.02
+        );
+        assert!(result.score > 0.9);
+
+        // too far to be corrected
+        let lost = Pose::new(na::Vector2::new(3.0, 2.0), -1.0);
+        assert!(matcher.match_scan(&lost, &scan).is_none());
+        assert!(matcher.match_scan(&truth, &[]).is_none());
+    }
+}
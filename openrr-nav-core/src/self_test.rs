@@ -0,0 +1,311 @@
+use grid_map::{Cell, GridMap, LayerId, LayeredGridMap, Position};
+use std::collections::HashMap;
+
+use crate::{DwaPlanner, Pose, Vector2, Velocity};
+
+/// Severity of a [`Diagnostic`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticLevel {
+    Ok,
+    Warn,
+    Error,
+}
+
+/// Result of one check of the self test
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub level: DiagnosticLevel,
+    pub name: String,
+    pub message: String,
+}
+
+/// Structured diagnostics returned by the self test
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelfTestReport {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl SelfTestReport {
+    pub(crate) fn push(&mut self, level: DiagnosticLevel, name: &str, message: impl Into<String>) {
+        self.diagnostics.push(Diagnostic {
+            level,
+            name: name.to_owned(),
+            message: message.into(),
+        });
+    }
+
+    /// The worst level of the diagnostics
+    pub fn level(&self) -> DiagnosticLevel {
+        self.diagnostics
+            .iter()
+            .map(|d| d.level)
+            .max()
+            .unwrap_or(DiagnosticLevel::Ok)
+    }
+
+    /// Return true if there is no error (warnings are allowed)
+    pub fn is_ok(&self) -> bool {
+        self.level() != DiagnosticLevel::Error
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.level == DiagnosticLevel::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|d| d.level == DiagnosticLevel::Warn)
+    }
+}
+
+impl std::fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for d in &self.diagnostics {
+            writeln!(f, "[{:?}] {}: {}", d.level, d.name, d.message)?;
+        }
+        write!(f, "result: {:?}", self.level())
+    }
+}
+
+impl DwaPlanner {
+    /// Validate the configuration and run one synthetic planning cycle
+    ///
+    /// `maps` and `angles` are the cost layers used by [`DwaPlanner::plan_local_path`]
+    /// and `robot_radius` is the radius of the footprint. This is intended to be
+    /// run at the boot of the robot.
+    pub fn self_test(
+        &self,
+        maps: &LayeredGridMap<u8>,
+        angles: &HashMap<LayerId, f64>,
+        robot_radius: f64,
+    ) -> SelfTestReport {
+        use DiagnosticLevel::*;
+        let mut report = SelfTestReport::default();
+        let limits = self.limits();
+
+        let name = "limits";
+        let mut ok = true;
+        let velocity_ok = limits.min_velocity.x <= limits.max_velocity.x
+            && limits.min_velocity.theta <= limits.max_velocity.theta;
+        if !velocity_ok {
+            report.push(Error, name, "min_velocity is larger than max_velocity");
+            ok = false;
+        }
+        if limits.max_accel.x <= 0.0
+            || limits.max_accel.theta <= 0.0
+            || limits.min_accel.x >= 0.0
+            || limits.min_accel.theta >= 0.0
+        {
+            report.push(
+                Error,
+                name,
+                "max_acceleration must be positive and min_acceleration must be negative",
+            );
+            ok = false;
+        }
+        if ok {
+            report.push(Ok, name, "velocity and acceleration limits are consistent");
+        }
+
+        let name = "timing";
+        let dt = self.controller_dt();
+        if dt.is_nan() || dt <= 0.0 {
+            report.push(
+                Error,
+                name,
+                format!("controller_dt ({dt}) must be positive"),
+            );
+        } else if self.simulation_duration() < dt {
+            report.push(
+                Error,
+                name,
+                format!(
+                    "simulation_duration ({}) is shorter than controller_dt ({dt})",
+                    self.simulation_duration()
+                ),
+            );
+        } else if self.num_vel_sample() <= 0 {
+            report.push(Error, name, "num_vel_sample must be positive");
+        } else if velocity_ok
+            && (limits.max_accel.x * dt > limits.max_velocity.x - limits.min_velocity.x
+                || limits.max_accel.theta * dt
+                    > limits.max_velocity.theta - limits.min_velocity.theta)
+        {
+            report.push(
+                Warn,
+                name,
+                "the velocity can change over the whole range in one controller_dt, dt may be too long",
+            );
+        } else {
+            report.push(
+                Ok,
+                name,
+                "controller_dt and simulation_duration are consistent",
+            );
+        }
+
+        let name = "footprint";
+        let resolutions = maps
+            .layer_ids()
+            .filter_map(|n| maps.layer(n))
+            .map(|m| m.resolution())
+            .collect::<Vec<_>>();
+        let resolution = resolutions.iter().copied().fold(0.0, f64::max);
+        if robot_radius.is_nan() || robot_radius <= 0.0 {
+            report.push(
+                Error,
+                name,
+                format!("robot radius ({robot_radius}) must be positive"),
+            );
+        } else if robot_radius < resolution {
+            report.push(
+                Warn,
+                name,
+                format!("robot radius ({robot_radius}) is smaller than the map resolution ({resolution})"),
+            );
+        } else {
+            report.push(Ok, name, "footprint is larger than the map resolution");
+        }
+        if let Err(e) = maps.validate_compatible() {
+            report.push(Error, "layers", e.to_string());
+        }
+
+        let name = "weights";
+        let mut ok = true;
+        let mut layer_ids = maps.layer_ids().collect::<Vec<_>>();
+        layer_ids.sort();
+        for layer in layer_ids {
+            if !self.map_name_weight().contains_key(&layer) {
+                report.push(Error, name, format!("no weight for the layer \"{layer}\""));
+                ok = false;
+            }
+        }
+        let mut weight_names = self.map_names().collect::<Vec<_>>();
+        weight_names.sort();
+        for weight_name in weight_names {
+            let weight = self.map_name_weight()[&weight_name];
+            if !weight.is_finite() || weight < 0.0 {
+                report.push(
+                    Error,
+                    name,
+                    format!("invalid weight {weight} for \"{weight_name}\""),
+                );
+                ok = false;
+            }
+            if maps.layer(weight_name).is_none() && !angles.contains_key(&weight_name) {
+                report.push(
+                    Warn,
+                    name,
+                    format!("no layer or angle for the weight \"{weight_name}\""),
+                );
+                ok = false;
+            }
+        }
+        if ok {
+            report.push(Ok, name, "weights are present for all layers");
+        }
+
+        if report.is_ok() {
+            self.synthetic_cycle(&mut report, robot_radius, resolution);
+        }
+        report
+    }
+
+    fn synthetic_cycle(&self, report: &mut SelfTestReport, robot_radius: f64, resolution: f64) {
+        let name = "planning_cycle";
+        let resolution = if resolution > 0.0 { resolution } else { 0.05 };
+        let half = self.limits().max_velocity.x.abs().max(robot_radius)
+            * self.simulation_duration()
+            + robot_radius
+            + resolution;
+        let mut map = GridMap::new(
+            Position::new(-half, -half),
+            Position::new(half + resolution * 0.5, half + resolution * 0.5),
+            resolution,
+        );
+        map.fill(Cell::Value(0));
+        let mut maps = HashMap::new();
+        for layer in self.map_names() {
+            maps.insert(layer, map.clone());
+        }
+        let plan = self.plan_local_path(
+            &Pose::new(Vector2::new(0.0, 0.0), 0.0),
+            &Velocity { x: 0.0, theta: 0.0 },
+            &LayeredGridMap::new(maps),
+            &HashMap::new(),
+        );
+        let limits = self.limits();
+        let within_limits = (limits.min_velocity.x..=limits.max_velocity.x)
+            .contains(&plan.velocity.x)
+            && (limits.min_velocity.theta..=limits.max_velocity.theta)
+                .contains(&plan.velocity.theta);
+        if plan.path.is_empty() || !plan.cost.is_finite() {
+            report.push(
+                DiagnosticLevel::Error,
+                name,
+                "failed to plan on an empty map",
+            );
+        } else if !within_limits {
+            report.push(
+                DiagnosticLevel::Error,
+                name,
+                format!("planned velocity {:?} is out of the limits", plan.velocity),
+            );
+        } else {
+            report.push(
+                DiagnosticLevel::Ok,
+                name,
+                format!("planned {} poses on an empty map", plan.path.len()),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() {
+        let planner = DwaPlanner::new_from_config("config/dwa_parameter_config.yaml").unwrap();
+        let map = GridMap::<u8>::new(Position::new(-1.0, -1.0), Position::new(1.0, 1.0), 0.05);
+        let mut maps = HashMap::new();
+        for id in [
+            LayerId::PATH,
+            LayerId::GOAL,
+            LayerId::OBSTACLE,
+            LayerId::LOCAL_GOAL,
+        ] {
+            maps.insert(id, map.clone());
+        }
+        let mut angles = HashMap::new();
+        for id in [
+            LayerId::ROTATION,
+            LayerId::PATH_DIRECTION,
+            LayerId::GOAL_DIRECTION,
+        ] {
+            angles.insert(id, 0.0);
+        }
+        let layered = LayeredGridMap::new(maps.clone());
+        let report = planner.self_test(&layered, &angles, 0.3);
+        assert_eq!(report.level(), DiagnosticLevel::Ok, "{report}");
+        assert!(report
+            .diagnostics
+            .iter()
+            .any(|d| d.name == "planning_cycle"));
+
+        maps.insert(LayerId::new("unknown_layer"), map);
+        let report = planner.self_test(&LayeredGridMap::new(maps), &angles, 0.01);
+        assert!(!report.is_ok());
+        assert_eq!(report.errors().count(), 1);
+        assert_eq!(report.warnings().count(), 1);
+        // the synthetic cycle is skipped on errors
+        assert!(!report
+            .diagnostics
+            .iter()
+            .any(|d| d.name == "planning_cycle"));
+    }
+}
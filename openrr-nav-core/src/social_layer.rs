@@ -0,0 +1,201 @@
+use grid_map::{Cell, GridMap, Position};
+use nalgebra as na;
+
+use crate::{Bounds, Layer, Pose, Result};
+
+/// Person detected around the robot
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Person {
+    /// Position in the frame of the map
+    pub position: Position,
+    /// (m/s) Velocity in the frame of the map
+    pub velocity: na::Vector2<f64>,
+}
+
+impl Person {
+    pub fn new(position: Position, velocity: na::Vector2<f64>) -> Self {
+        Self { position, velocity }
+    }
+
+    /// Person standing still
+    pub fn standing(position: Position) -> Self {
+        Self::new(position, na::Vector2::zeros())
+    }
+}
+
+/// [`Layer`] of the proxemic costs around the people
+///
+/// The cost is the Gaussian `max_cost * exp(-(a^2 / 2 sa^2 + l^2 / 2 sigma^2))`,
+/// where `a` and `l` are the distances along and across the walking direction.
+/// `sa` is `sigma * (1 + velocity_factor * speed)` in front of the person and
+/// `sigma` behind, so the robot keeps more distance from the way the person goes.
+/// Costs lower than `min_cost` are not written. Put it before the
+/// [`InflationLayer`](crate::InflationLayer) to keep the larger of the costs, and use
+/// the costmap as the layer of the [`DwaPlanner`](crate::DwaPlanner).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocialLayer {
+    /// (m)
+    pub sigma: f64,
+    /// (s/m)
+    pub velocity_factor: f64,
+    /// Cost at the position of the person, lower than the obstacle
+    pub max_cost: u8,
+    pub min_cost: u8,
+    people: Vec<Person>,
+    /// Region of the last costs, which is cleared in the next update
+    last_bounds: Bounds,
+}
+
+impl Default for SocialLayer {
+    fn default() -> Self {
+        Self::new(0.5, 1.0)
+    }
+}
+
+impl SocialLayer {
+    pub fn new(sigma: f64, velocity_factor: f64) -> Self {
+        Self {
+            sigma,
+            velocity_factor,
+            max_cost: 200,
+            min_cost: 10,
+            people: vec![],
+            last_bounds: Bounds::empty(),
+        }
+    }
+
+    pub fn people(&self) -> &[Person] {
+        &self.people
+    }
+
+    /// Replace the detected people, which are written in the next update
+    pub fn set_people(&mut self, people: Vec<Person>) {
+        self.people = people;
+    }
+
+    /// Sigma along the walking direction in front of the person
+    fn front_sigma(&self, person: &Person) -> f64 {
+        self.sigma * (1.0 + self.velocity_factor * person.velocity.norm())
+    }
+
+    /// Proxemic cost of the person at the position
+    pub fn cost(&self, person: &Person, position: &Position) -> f64 {
+        let d = na::Vector2::new(
+            position.x - person.position.x,
+            position.y - person.position.y,
+        );
+        let speed = person.velocity.norm();
+        let direction = if speed > f64::EPSILON {
+            person.velocity / speed
+        } else {
+            na::Vector2::x()
+        };
+        let along = d.dot(&direction);
+        let across = direction.x * d.y - direction.y * d.x;
+        let sigma_along = if along > 0.0 {
+            self.front_sigma(person)
+        } else {
+            self.sigma
+        };
+        let exponent = along.powi(2) / (2.0 * sigma_along.powi(2))
+            + across.powi(2) / (2.0 * self.sigma.powi(2));
+        self.max_cost as f64 * (-exponent).exp()
+    }
+
+    /// (m) Distance from the person where the cost falls below `min_cost`
+    fn radius(&self, person: &Person) -> f64 {
+        let ratio = self.max_cost as f64 / self.min_cost.max(1) as f64;
+        self.front_sigma(person) * (2.0 * ratio.max(1.0).ln()).sqrt()
+    }
+}
+
+impl Layer for SocialLayer {
+    fn update_bounds(&mut self, _robot_pose: &Pose, bounds: &mut Bounds) {
+        let mut current = Bounds::empty();
+        for person in &self.people {
+            let mut region = Bounds::empty();
+            region.include(&person.position);
+            region.expand(self.radius(person));
+            current.merge(&region);
+        }
+        // the costs of the last update are cleared by the master
+        bounds.merge(&self.last_bounds);
+        bounds.merge(&current);
+        self.last_bounds = current;
+    }
+
+    fn update_costs(&mut self, master: &mut GridMap<u8>, bounds: &Bounds) -> Result<()> {
+        if self.people.is_empty() {
+            return Ok(());
+        }
+        for grid in bounds.grids(master).collect::<Vec<_>>() {
+            let position = master.cell_center(&grid);
+            let cost = self
+                .people
+                .iter()
+                .map(|person| self.cost(person, &position))
+                .fold(0.0, f64::max);
+            if cost < self.min_cost as f64 {
+                continue;
+            }
+            if let Some(Cell::Value(v)) = master.cell_mut(&grid) {
+                *v = (*v).max(cost.round() as u8);
+            }
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.people.clear();
+        self.last_bounds = Bounds::empty();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LayeredCostmap;
+
+    #[test]
+    fn test_social_layer() {
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(6.05, 4.05), 0.05);
+        let mut costmap = LayeredCostmap::new(&map);
+        costmap.add_layer("social", SocialLayer::new(0.4, 1.0));
+        let walking = Person::new(Position::new(3.0, 2.0), na::Vector2::new(1.0, 0.0));
+        costmap
+            .layer_mut::<SocialLayer>("social")
+            .unwrap()
+            .set_people(vec![walking]);
+        costmap.update(&Pose::identity()).unwrap();
+        let at = |costmap: &LayeredCostmap, x: f64, y: f64| {
+            let grid = costmap.costmap().to_grid(x, y).unwrap();
+            match costmap.costmap().cell(&grid).unwrap() {
+                Cell::Value(v) => *v,
+                cell => panic!("{cell:?}"),
+            }
+        };
+        assert!(at(&costmap, 3.0, 2.0) > 190);
+        // elongated along the walking direction
+        let front = at(&costmap, 3.5, 2.0);
+        let behind = at(&costmap, 2.5, 2.0);
+        let side = at(&costmap, 3.0, 2.5);
+        assert!(front > behind, "{front} {behind}");
+        assert!(front > side, "{front} {side}");
+        assert_eq!(at(&costmap, 0.5, 0.5), 0);
+
+        // the costs move with the person
+        costmap
+            .layer_mut::<SocialLayer>("social")
+            .unwrap()
+            .set_people(vec![Person::standing(Position::new(1.0, 1.0))]);
+        costmap.update(&Pose::identity()).unwrap();
+        assert_eq!(at(&costmap, 3.0, 2.0), 0);
+        assert!(at(&costmap, 1.0, 1.0) > 190);
+        let layer = costmap.layer::<SocialLayer>("social").unwrap();
+        let person = layer.people()[0];
+        let distance = 0.3;
+        let east = layer.cost(&person, &Position::new(1.0 + distance, 1.0));
+        let west = layer.cost(&person, &Position::new(1.0 - distance, 1.0));
+        assert!((east - west).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,463 @@
+use grid_map::{GridMap, Position};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{euclidean_distance_transform, path, Limits, Plan, Pose, Velocity};
+
+fn default_horizon() -> f64 {
+    2.0
+}
+
+fn default_spacing() -> f64 {
+    0.1
+}
+
+fn default_num_iterations() -> usize {
+    50
+}
+
+fn default_step_size() -> f64 {
+    0.2
+}
+
+fn default_min_obstacle_distance() -> f64 {
+    0.3
+}
+
+fn default_robot_radius() -> f64 {
+    0.1
+}
+
+fn default_obstacle_weight() -> f64 {
+    10.0
+}
+
+fn default_smoothness_weight() -> f64 {
+    1.0
+}
+
+fn default_path_weight() -> f64 {
+    0.2
+}
+
+/// Band of the poses from the robot and the time to the next pose
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimedElasticBand {
+    pub poses: Vec<Pose>,
+    /// (s) `time_intervals[i]` is the time from `poses[i]` to `poses[i + 1]`
+    pub time_intervals: Vec<f64>,
+    /// (m) Distance from each pose to the nearest obstacle
+    pub obstacle_distances: Vec<f64>,
+}
+
+impl TimedElasticBand {
+    /// (s) Time to the end of the band
+    pub fn duration(&self) -> f64 {
+        self.time_intervals.iter().sum()
+    }
+
+    pub fn min_obstacle_distance(&self) -> f64 {
+        self.obstacle_distances
+            .iter()
+            .copied()
+            .fold(f64::INFINITY, f64::min)
+    }
+}
+
+/// Local planner which deforms a segment of the global path like the Timed
+/// Elastic Band (TEB)
+///
+/// The segment of `horizon` ahead of the robot is resampled by `spacing`, and the
+/// waypoints are moved by the gradient descent of the costs of the clearance from
+/// the obstacles, the smoothness and the deviation from the global path. Then the
+/// fastest velocity profile within the velocity and the acceleration limits gives
+/// the time between the poses, and the command is the velocity to the next pose.
+///
+/// Unlike the sampling based planners, the trajectory is optimized continuously, so
+/// it keeps the clearance in the narrow passages. The cost of the plan is the
+/// duration of the band, or infinite if the band is closer to an obstacle than
+/// `robot_radius`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TebPlanner {
+    pub limits: Limits,
+    /// (s)
+    pub controller_dt: f64,
+    /// (m) Length of the optimized segment of the global path
+    #[serde(default = "default_horizon")]
+    pub horizon: f64,
+    /// (m) Distance between the poses of the band
+    #[serde(default = "default_spacing")]
+    pub spacing: f64,
+    #[serde(default = "default_num_iterations")]
+    pub num_iterations: usize,
+    #[serde(default = "default_step_size")]
+    pub step_size: f64,
+    /// (m) Poses closer to the obstacles are pushed away
+    #[serde(default = "default_min_obstacle_distance")]
+    pub min_obstacle_distance: f64,
+    /// (m)
+    #[serde(default = "default_robot_radius")]
+    pub robot_radius: f64,
+    #[serde(default = "default_obstacle_weight")]
+    pub obstacle_weight: f64,
+    #[serde(default = "default_smoothness_weight")]
+    pub smoothness_weight: f64,
+    #[serde(default = "default_path_weight")]
+    pub path_weight: f64,
+}
+
+impl TebPlanner {
+    pub fn new(limits: Limits, controller_dt: f64) -> Self {
+        Self {
+            limits,
+            controller_dt,
+            horizon: default_horizon(),
+            spacing: default_spacing(),
+            num_iterations: default_num_iterations(),
+            step_size: default_step_size(),
+            min_obstacle_distance: default_min_obstacle_distance(),
+            robot_radius: default_robot_radius(),
+            obstacle_weight: default_obstacle_weight(),
+            smoothness_weight: default_smoothness_weight(),
+            path_weight: default_path_weight(),
+        }
+    }
+
+    /// Positions of the global path within the horizon, resampled by the spacing
+    ///
+    /// The first position is the robot. Returns true as well if it reaches the end
+    /// of the global path.
+    fn reference(&self, position: [f64; 2], path: &[Vec<f64>]) -> (Vec<[f64; 2]>, bool) {
+        let spacing = self.spacing.max(1e-3);
+        let mut points = vec![position];
+        let mut waypoints = vec![vec![position[0], position[1]]];
+        waypoints.extend(path::prune_passed(path, position));
+        let dense = path::densify_path(&waypoints, spacing * 0.5);
+        let mut length = 0.0;
+        let mut last = position;
+        for p in dense.iter().skip(1) {
+            let d = (p[0] - last[0]).hypot(p[1] - last[1]);
+            if d < spacing {
+                continue;
+            }
+            length += d;
+            if length > self.horizon {
+                return (points, false);
+            }
+            last = [p[0], p[1]];
+            points.push(last);
+        }
+        let end = path.last().map_or(position, |p| [p[0], p[1]]);
+        if (end[0] - last[0]).hypot(end[1] - last[1]) > 1e-9 {
+            points.push(end);
+        }
+        (points, true)
+    }
+
+    /// Optimize the band along the global path `[x, y, (yaw)]` without the time profile
+    fn optimize(
+        &self,
+        pose: &Pose,
+        path: &[Vec<f64>],
+        map: &GridMap<u8>,
+    ) -> (Vec<[f64; 2]>, GridMap<f64>, bool) {
+        let position = [pose.translation.x, pose.translation.y];
+        let (reference, reaches_end) = self.reference(position, path);
+        // distance map around the band
+        let margin = self.min_obstacle_distance + self.spacing;
+        let (min, max) = reference.iter().fold(
+            ([f64::INFINITY; 2], [f64::NEG_INFINITY; 2]),
+            |(min, max), p| {
+                (
+                    [min[0].min(p[0]), min[1].min(p[1])],
+                    [max[0].max(p[0]), max[1].max(p[1])],
+                )
+            },
+        );
+        let local = map
+            .crop(
+                Position::new(min[0] - margin * 2.0, min[1] - margin * 2.0),
+                Position::new(max[0] + margin * 2.0, max[1] + margin * 2.0),
+            )
+            .unwrap_or_else(|| map.clone());
+        let cap = margin * 2.0;
+        let distances = euclidean_distance_transform(&local).map_values(|d| d.min(cap));
+
+        let mut band = reference.clone();
+        let n = band.len();
+        let max_move = self.spacing * 0.5;
+        for _ in 0..self.num_iterations {
+            for i in 1..n.saturating_sub(1) {
+                let p = band[i];
+                let mut grad = [0.0; 2];
+                for k in 0..2 {
+                    grad[k] += self.smoothness_weight
+                        * (2.0 * p[k] - band[i - 1][k] - band[i + 1][k])
+                        + self.path_weight * (p[k] - reference[i][k]);
+                }
+                let position = Position::new(p[0], p[1]);
+                if let (Some(d), Some(g)) = (
+                    distances.value_at_interpolated(&position),
+                    distances.gradient_at(&position),
+                ) {
+                    if d < self.min_obstacle_distance {
+                        let force = 2.0 * (self.min_obstacle_distance - d);
+                        grad[0] -= self.obstacle_weight * force * g[0];
+                        grad[1] -= self.obstacle_weight * force * g[1];
+                    }
+                }
+                let mut delta = [-self.step_size * grad[0], -self.step_size * grad[1]];
+                let norm = delta[0].hypot(delta[1]);
+                if norm > max_move {
+                    delta = [delta[0] * max_move / norm, delta[1] * max_move / norm];
+                }
+                band[i] = [p[0] + delta[0], p[1] + delta[1]];
+            }
+        }
+        (band, distances, reaches_end)
+    }
+
+    /// Optimize the band and give the fastest time between the poses
+    pub fn timed_elastic_band(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        path: &[Vec<f64>],
+        map: &GridMap<u8>,
+    ) -> TimedElasticBand {
+        let (band, distances, reaches_end) = self.optimize(pose, path, map);
+        let n = band.len();
+        let mut yaws = Vec::with_capacity(n);
+        yaws.push(pose.rotation.angle());
+        for i in 1..n {
+            let (a, b) = if i + 1 < n {
+                (band[i], band[i + 1])
+            } else {
+                (band[i - 1], band[i])
+            };
+            let yaw = match path.last() {
+                Some(goal) if i + 1 == n && reaches_end && goal.len() > 2 => goal[2],
+                _ => (b[1] - a[1]).atan2(b[0] - a[0]),
+            };
+            yaws.push(yaw);
+        }
+        let poses = band
+            .iter()
+            .zip(&yaws)
+            .map(|(p, yaw)| Pose::new(na::Vector2::new(p[0], p[1]), *yaw))
+            .collect::<Vec<_>>();
+        let obstacle_distances = band
+            .iter()
+            .map(|p| {
+                distances
+                    .value_at_interpolated(&Position::new(p[0], p[1]))
+                    .unwrap_or(f64::INFINITY)
+            })
+            .collect::<Vec<_>>();
+
+        // fastest velocity profile with the acceleration limits
+        let limits = &self.limits;
+        let max_v = limits.max_velocity.x.max(0.0);
+        let max_w = limits
+            .max_velocity
+            .theta
+            .min(-limits.min_velocity.theta)
+            .max(1e-6);
+        let accel = limits.max_accel.x.max(1e-6);
+        let decel = (-limits.min_accel.x).max(1e-6);
+        let segments = poses
+            .iter()
+            .zip(poses.iter().skip(1))
+            .map(|(a, b)| {
+                let length = (b.translation.vector - a.translation.vector).norm();
+                let rotation = (a.rotation.inverse() * b.rotation).angle().abs();
+                (length, rotation)
+            })
+            .collect::<Vec<_>>();
+        let caps = segments
+            .iter()
+            .map(|(length, rotation)| {
+                if *rotation > 1e-9 {
+                    max_v.min(max_w * length / rotation)
+                } else {
+                    max_v
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut speeds = vec![max_v; n];
+        speeds[0] = velocity.x.clamp(0.0, max_v);
+        for i in 0..segments.len() {
+            // the pose between the segments is limited by the both segments
+            let reachable = (speeds[i].powi(2) + 2.0 * accel * segments[i].0).sqrt();
+            let cap = caps.get(i + 1).map_or(caps[i], |next| caps[i].min(*next));
+            speeds[i + 1] = reachable.min(cap);
+        }
+        if reaches_end {
+            speeds[n - 1] = 0.0;
+        }
+        for i in (0..segments.len()).rev() {
+            let stoppable = (speeds[i + 1].powi(2) + 2.0 * decel * segments[i].0).sqrt();
+            if i > 0 {
+                speeds[i] = speeds[i].min(stoppable);
+            }
+        }
+        let time_intervals = segments
+            .iter()
+            .enumerate()
+            .map(|(i, (length, rotation))| {
+                let mean = (speeds[i] + speeds[i + 1]) * 0.5;
+                let by_translation = if mean > 1e-9 { length / mean } else { 0.0 };
+                by_translation.max(rotation / max_w)
+            })
+            .collect();
+        TimedElasticBand {
+            poses,
+            time_intervals,
+            obstacle_distances,
+        }
+    }
+
+    /// Command to follow the global path `[x, y, (yaw)]` from the pose
+    ///
+    /// The path of the plan is the poses of the band.
+    pub fn plan(
+        &self,
+        pose: &Pose,
+        velocity: &Velocity,
+        path: &[Vec<f64>],
+        map: &GridMap<u8>,
+    ) -> Plan {
+        if path.is_empty() {
+            return Plan {
+                cost: f64::INFINITY,
+                ..Default::default()
+            };
+        }
+        let band = self.timed_elastic_band(pose, velocity, path, map);
+        if band.poses.len() < 2 {
+            return Plan {
+                cost: 0.0,
+                path: band.poses,
+                ..Default::default()
+            };
+        }
+        let limits = &self.limits;
+        let dt = self.controller_dt;
+        let first = &band.poses[0];
+        let local = first.inverse_transform_point(&band.poses[1].translation.vector.into());
+        let interval = band.time_intervals[0].max(1e-6);
+        let heading = local.y.atan2(local.x);
+        let rotation = (first.rotation.inverse() * band.poses[1].rotation).angle();
+        let target = if local.x <= 0.0 || heading.abs() > std::f64::consts::FRAC_PI_2 {
+            // turn to the band first
+            Velocity {
+                x: 0.0,
+                theta: heading.signum() * limits.max_velocity.theta.abs(),
+            }
+        } else {
+            Velocity {
+                x: local.coords.norm() / interval,
+                theta: rotation / interval,
+            }
+        };
+        let next = Velocity {
+            x: target
+                .x
+                .clamp(
+                    velocity.x + limits.min_accel.x * dt,
+                    velocity.x + limits.max_accel.x * dt,
+                )
+                .clamp(limits.min_velocity.x, limits.max_velocity.x),
+            theta: target
+                .theta
+                .clamp(
+                    velocity.theta + limits.min_accel.theta * dt,
+                    velocity.theta + limits.max_accel.theta * dt,
+                )
+                .clamp(limits.min_velocity.theta, limits.max_velocity.theta),
+        };
+        let cost = if band.min_obstacle_distance() < self.robot_radius {
+            f64::INFINITY
+        } else {
+            band.duration()
+        };
+        Plan {
+            velocity: next,
+            cost,
+            path: band.poses,
+            sampling_issue: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dwa_planner::velocity_to_pose, Acceleration};
+    use grid_map::Grid;
+
+    fn limits() -> Limits {
+        Limits {
+            max_velocity: Velocity { x: 0.4, theta: 1.5 },
+            max_accel: Acceleration { x: 1.0, theta: 3.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -1.5,
+            },
+            min_accel: Acceleration {
+                x: -1.0,
+                theta: -3.0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_timed_elastic_band() {
+        let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(4.05, 2.05), 0.05);
+        let planner = TebPlanner::new(limits(), 0.1);
+        let path = vec![vec![0.5, 1.0, 0.0], vec![3.5, 1.0, 0.0]];
+        let start = Pose::new(na::Vector2::new(0.5, 1.0), 0.0);
+
+        // free and straight at the max velocity
+        let band =
+            planner.timed_elastic_band(&start, &Velocity { x: 0.4, theta: 0.0 }, &path, &map);
+        assert!((band.duration() - planner.horizon / 0.4).abs() < 0.05);
+        assert!(band
+            .poses
+            .iter()
+            .all(|p| (p.translation.y - 1.0).abs() < 1e-9));
+
+        // an obstacle just beside the path
+        for x in 30..34 {
+            for y in 14..19 {
+                map.set_obstacle(&Grid::new(x, y)).unwrap();
+            }
+        }
+        let band = planner.timed_elastic_band(&start, &Velocity::default(), &path, &map);
+        let near = band
+            .poses
+            .iter()
+            .filter(|p| (1.5..1.7).contains(&p.translation.x))
+            .map(|p| p.translation.y)
+            .fold(f64::INFINITY, f64::min);
+        assert!(near > 1.1, "{near}");
+        assert!(band.min_obstacle_distance() > planner.robot_radius);
+
+        let mut pose = start;
+        let mut velocity = Velocity::default();
+        for _ in 0..300 {
+            let plan = planner.plan(&pose, &velocity, &path, &map);
+            assert!(plan.cost.is_finite());
+            velocity = plan.velocity;
+            pose *= velocity_to_pose(&velocity, planner.controller_dt);
+            let grid = map.to_grid(pose.translation.x, pose.translation.y).unwrap();
+            assert!(!map.cell(&grid).unwrap().is_obstacle());
+            if (pose.translation.vector - na::Vector2::new(3.5, 1.0)).norm() < 0.1 {
+                break;
+            }
+        }
+        assert!((pose.translation.vector - na::Vector2::new(3.5, 1.0)).norm() < 0.1);
+        assert!(planner.plan(&pose, &velocity, &[], &map).cost.is_infinite());
+    }
+}
@@ -0,0 +1,59 @@
+//! Fixtures of the navigator for the tests of the planners and the bridges
+//!
+//! ```
+//! use openrr_nav_core::{testkit, Pose, Velocity};
+//!
+//! let mut navigator = testkit::navigator(testkit::free_map());
+//! navigator.set_goal(Pose::new(nalgebra::Vector2::new(2.5, 0.5), 0.0));
+//! let pose = Pose::new(nalgebra::Vector2::new(0.5, 0.5), 0.0);
+//! let command = navigator.tick(&pose, &Velocity::default());
+//! let pose = testkit::move_pose(&pose, &command.velocity(), testkit::DT);
+//! ```
+
+use grid_map::{Cell, GridMap, Position};
+
+use crate::{dwa_planner::velocity_to_pose, AStarPlanner, DwaPlanner, Navigator, Pose, Velocity};
+
+/// `controller_dt` of [`turtlebot_dwa`] (s)
+pub const DT: f64 = 0.1;
+
+/// Map of 3 m x 2 m at 0.05 m without obstacles
+pub fn free_map() -> GridMap<u8> {
+    let mut map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(3.05, 2.05), 0.05);
+    map.fill(Cell::Value(0));
+    map
+}
+
+/// [`DwaPlanner`] of `config/turtlebot_dwa_config.yaml`
+pub fn turtlebot_dwa() -> DwaPlanner {
+    DwaPlanner::new_from_config_text(include_str!("../config/turtlebot_dwa_config.yaml")).unwrap()
+}
+
+/// [`Navigator`] of [`AStarPlanner`] and [`turtlebot_dwa`] on the map
+pub fn navigator(map: GridMap<u8>) -> Navigator {
+    Navigator::new(
+        Box::new(AStarPlanner::default()),
+        Box::new(turtlebot_dwa()),
+        map,
+    )
+}
+
+/// Pose after moving at the velocity for `dt` like a differential drive base
+pub fn move_pose(pose: &Pose, velocity: &Velocity, dt: f64) -> Pose {
+    pose * velocity_to_pose(velocity, dt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixtures() {
+        assert_eq!(turtlebot_dwa().controller_dt(), DT);
+        let map = free_map();
+        assert_eq!((map.width(), map.height()), (60, 40));
+        assert!(map.cells().iter().all(|c| c.value() == Some(&0)));
+        let pose = move_pose(&Pose::identity(), &Velocity { x: 1.0, theta: 0.0 }, DT);
+        assert!((pose.translation.x - DT).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,139 @@
+/// Utility for debug
+pub fn show_ascii_map(map: &grid_map::GridMap<u8>, scale: f32) {
+    use grid_map::Cell;
+    for i in 0..map.height() {
+        for j in 0..map.width() {
+            let letter = match map.cells()[i * map.width() + j] {
+                Cell::Value(v) => {
+                    let v = (v as f32 * scale) as u8;
+                    if v <= 9 {
+                        format!("{v}")
+                    } else {
+                        "9".to_owned()
+                    }
+                }
+                Cell::Obstacle => "x".to_owned(),
+                Cell::Uninitialized => "u".to_owned(),
+                Cell::Unknown => "?".to_owned(),
+            };
+            print!("{letter:}");
+        }
+        println!();
+    }
+}
+
+pub fn nearest_path_point(path: &[Vec<f64>], target_point: [f64; 2]) -> Option<(usize, Vec<f64>)> {
+    if path.is_empty() {
+        None
+    } else {
+        let mut nearest = (0, f64::MAX);
+        for (i, p) in path.iter().enumerate() {
+            let dist = ((target_point[0] - p[0]).powi(2) + (target_point[1] - p[1]).powi(2)).sqrt();
+            if dist < nearest.1 {
+                nearest.0 = i;
+                nearest.1 = dist;
+            }
+        }
+        Some((nearest.0, path[nearest.0].clone()))
+    }
+}
+
+pub(crate) fn distance_to_segment(p: &[f64], a: &[f64], b: &[f64]) -> f64 {
+    let (dx, dy) = (b[0] - a[0], b[1] - a[1]);
+    let length_squared = dx * dx + dy * dy;
+    let t = if length_squared == 0.0 {
+        0.0
+    } else {
+        (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / length_squared).clamp(0.0, 1.0)
+    };
+    ((p[0] - a[0] - t * dx).powi(2) + (p[1] - a[1] - t * dy).powi(2)).sqrt()
+}
+
+/// Remove redundant waypoints of the path
+///
+/// Waypoints closer than `tolerance` to the line between the neighboring kept
+/// waypoints (collinear points and duplicates) are removed. The first and the
+/// last points are always kept. Only x and y (the first two elements) are used.
+pub fn simplify_path(path: &[Vec<f64>], tolerance: f64) -> Vec<Vec<f64>> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+    let mut pruned = vec![path[0].clone()];
+    for i in 1..path.len() - 1 {
+        let last = pruned.last().unwrap();
+        if distance_to_segment(&path[i], last, &path[i + 1]) > tolerance {
+            pruned.push(path[i].clone());
+        }
+    }
+    let last = path.last().unwrap();
+    if pruned.len() == 1 || pruned.last() != Some(last) {
+        pruned.push(last.clone());
+    }
+    pruned
+}
+
+/// Convert the path in the world frame into the grids of the map for the path distance map
+///
+/// The path should be densified with the spacing smaller than the resolution
+/// to get connected grids. Points out of the map and consecutive duplicated
+/// grids are removed.
+pub fn path_to_grids(map: &grid_map::GridMap<u8>, path: &[Vec<f64>]) -> Vec<grid_map::Grid> {
+    let mut grids = path
+        .iter()
+        .filter_map(|p| map.world_to_grid(&grid_map::Position::new(p[0], p[1])))
+        .collect::<Vec<_>>();
+    grids.dedup();
+    grids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::densify_path;
+
+    #[test]
+    fn test_simplify_path() {
+        let path = vec![
+            vec![0.0, 0.0],
+            vec![0.5, 0.0],
+            vec![0.5, 0.0],
+            vec![1.0, 0.001],
+            vec![1.0, 1.0],
+            vec![1.0, 2.0],
+        ];
+        assert_eq!(
+            simplify_path(&path, 0.01),
+            vec![vec![0.0, 0.0], vec![1.0, 0.001], vec![1.0, 2.0]]
+        );
+        assert_eq!(simplify_path(&path[..1], 0.01), vec![vec![0.0, 0.0]]);
+    }
+
+    #[test]
+    fn test_densify_path() {
+        let path = vec![
+            vec![0.0, 0.0, 0.5],
+            vec![1.0, 0.0, 1.0],
+            vec![1.0, 0.25, 2.0],
+        ];
+        let densified = densify_path(&path, 0.3);
+        // 4 + 1 + last
+        assert_eq!(densified.len(), 6);
+        assert_eq!(densified[1], vec![0.25, 0.0, 0.5]);
+        assert_eq!(densified.last(), path.last());
+        for (a, b) in densified.iter().zip(densified.iter().skip(1)) {
+            assert!(((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt() <= 0.3);
+        }
+
+        let map = grid_map::GridMap::<u8>::new(
+            grid_map::Position::new(-0.05, -0.05),
+            grid_map::Position::new(1.05, 0.35),
+            0.1,
+        );
+        let grids = path_to_grids(&map, &densify_path(&path, 0.05));
+        assert_eq!(grids.first(), Some(&grid_map::Grid::new(0, 0)));
+        assert_eq!(grids.last(), map.to_grid(1.0, 0.25).as_ref());
+        for (a, b) in grids.iter().zip(grids.iter().skip(1)) {
+            assert!(a.x.abs_diff(b.x) <= 1 && a.y.abs_diff(b.y) <= 1);
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Acceleration, Limits, Velocity};
+
+/// Filter of the velocity commands before sending them to the base
+///
+/// The target velocity (e.g. [`Plan::velocity`](crate::Plan::velocity)) is
+/// low-pass filtered with the time constant, and then the change from the last
+/// output is limited by the acceleration and the jerk limits, so the base receives
+/// smooth commands even if the output of the planner jumps between the cycles.
+///
+/// ```yaml
+/// max_acceleration: [1.0, 2.0]
+/// min_acceleration: [-1.0, -2.0]
+/// max_jerk: [5.0, 10.0]
+/// time_constant: 0.1
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VelocitySmoother {
+    /// (m/s^2, rad/s^2)
+    pub max_acceleration: Acceleration,
+    /// (m/s^2, rad/s^2), negative values
+    pub min_acceleration: Acceleration,
+    /// (m/s^3, rad/s^3) No jerk limit if `None`
+    #[serde(default)]
+    pub max_jerk: Option<Acceleration>,
+    /// (s) Time constant of the low-pass filter. Zero disables the filter.
+    #[serde(default)]
+    pub time_constant: f64,
+    #[serde(skip)]
+    velocity: Velocity,
+    #[serde(skip)]
+    acceleration: Acceleration,
+}
+
+impl VelocitySmoother {
+    /// Smoother with the acceleration limits of the planner
+    pub fn new(limits: &Limits) -> Self {
+        Self {
+            max_acceleration: limits.max_accel,
+            min_acceleration: limits.min_accel,
+            max_jerk: None,
+            time_constant: 0.0,
+            velocity: Velocity::default(),
+            acceleration: Acceleration::default(),
+        }
+    }
+
+    pub fn with_max_jerk(mut self, max_jerk: Acceleration) -> Self {
+        self.max_jerk = Some(max_jerk);
+        self
+    }
+
+    pub fn with_time_constant(mut self, time_constant: f64) -> Self {
+        self.time_constant = time_constant;
+        self
+    }
+
+    /// The last output
+    pub fn velocity(&self) -> Velocity {
+        self.velocity
+    }
+
+    /// Restart from the velocity, e.g. the measured velocity after the base was
+    /// stopped by something else
+    pub fn reset(&mut self, velocity: Velocity) {
+        self.velocity = velocity;
+        self.acceleration = Acceleration::default();
+    }
+
+    /// Filter the target velocity for the cycle of `dt` (s)
+    pub fn smooth(&mut self, target: &Velocity, dt: f64) -> Velocity {
+        if dt <= 0.0 {
+            return self.velocity;
+        }
+        let alpha = dt / (self.time_constant.max(0.0) + dt);
+        let max_jerk = self.max_jerk.map(|j| [j.x.abs(), j.theta.abs()]);
+        let axis = |i: usize, current: f64, target: f64| {
+            let (min_accel, max_accel, last_accel) = match i {
+                0 => (
+                    self.min_acceleration.x,
+                    self.max_acceleration.x,
+                    self.acceleration.x,
+                ),
+                _ => (
+                    self.min_acceleration.theta,
+                    self.max_acceleration.theta,
+                    self.acceleration.theta,
+                ),
+            };
+            let (mut low, mut high) = (min_accel.min(max_accel), max_accel.max(min_accel));
+            if let Some(jerk) = max_jerk {
+                // keep the acceleration range reachable from the last acceleration
+                low = low.max(last_accel - jerk[i] * dt).min(high);
+                high = high.min(last_accel + jerk[i] * dt).max(low);
+            }
+            let filtered = current + (target - current) * alpha;
+            let accel = ((filtered - current) / dt).clamp(low, high);
+            (current + accel * dt, accel)
+        };
+        let (x, accel_x) = axis(0, self.velocity.x, target.x);
+        let (theta, accel_theta) = axis(1, self.velocity.theta, target.theta);
+        self.velocity = Velocity { x, theta };
+        self.acceleration = Acceleration {
+            x: accel_x,
+            theta: accel_theta,
+        };
+        self.velocity
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_velocity_smoother() {
+        let limits = Limits {
+            max_velocity: Velocity { x: 0.5, theta: 1.0 },
+            max_accel: Acceleration { x: 1.0, theta: 2.0 },
+            min_velocity: Velocity {
+                x: 0.0,
+                theta: -1.0,
+            },
+            min_accel: Acceleration {
+                x: -0.5,
+                theta: -2.0,
+            },
+        };
+        let dt = 0.1;
+        let target = Velocity { x: 0.5, theta: 1.0 };
+        let mut smoother = VelocitySmoother::new(&limits);
+        let v = smoother.smooth(&target, dt);
+        assert!((v.x - 0.1).abs() < 1e-9);
+        assert!((v.theta - 0.2).abs() < 1e-9);
+        for _ in 0..10 {
+            smoother.smooth(&target, dt);
+        }
+        assert!((smoother.velocity().x - target.x).abs() < 1e-9);
+        assert!((smoother.velocity().theta - target.theta).abs() < 1e-9);
+        // decelerated by min_acceleration
+        let v = smoother.smooth(&Velocity::default(), dt);
+        assert!((v.x - 0.45).abs() < 1e-9);
+        assert!((v.theta - 0.8).abs() < 1e-9);
+
+        let mut smoother: VelocitySmoother = serde_yaml::from_str(
+            "{max_acceleration: [1.0, 2.0], min_acceleration: [-1.0, -2.0], max_jerk: [5.0, 10.0], time_constant: 0.2}",
+        )
+        .unwrap();
+        let mut last = Velocity::default();
+        let mut last_accel = 0.0;
+        for _ in 0..100 {
+            let v = smoother.smooth(&target, dt);
+            let accel = (v.x - last.x) / dt;
+            assert!(accel <= 1.0 + 1e-9);
+            assert!((accel - last_accel).abs() <= 5.0 * dt + 1e-9);
+            last = v;
+            last_accel = accel;
+        }
+        assert!((last.x - target.x).abs() < 1e-3);
+        assert!((last.theta - target.theta).abs() < 1e-3);
+
+        smoother.reset(Velocity::default());
+        assert_eq!(smoother.velocity(), Velocity::default());
+        assert_eq!(smoother.smooth(&target, 0.0), Velocity::default());
+    }
+}
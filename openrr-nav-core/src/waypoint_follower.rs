@@ -0,0 +1,385 @@
+use std::{fmt, time::SystemTime};
+
+use crate::{
+    Command, GoalChecker, MissionState, Navigator, NavigatorState, Pose, SimpleGoalChecker,
+    Velocity, Waypoint,
+};
+
+/// Progress of the mission reported to the callback of [`WaypointFollower`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaypointEvent {
+    /// The navigation to the waypoint is started
+    Started {
+        index: usize,
+    },
+    Reached {
+        index: usize,
+    },
+    /// Skipped by [`WaypointFollower::skip`], or failed without `stop_on_failure`
+    Skipped {
+        index: usize,
+    },
+    Failed {
+        index: usize,
+        error: String,
+    },
+    /// All waypoints are visited
+    Completed,
+    Aborted,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MissionStatus {
+    /// No mission is started
+    #[default]
+    Idle,
+    Running,
+    Completed,
+    /// Aborted by [`WaypointFollower::abort`] or by a failure with `stop_on_failure`
+    Aborted,
+}
+
+type ProgressCallback = Box<dyn FnMut(&WaypointEvent, &MissionState) + Send>;
+
+/// Visit the waypoints of a mission in order with the [`Navigator`]
+///
+/// The waypoints are sent to the navigator one at a time. After reaching a
+/// waypoint, the robot stays there for [`Waypoint::pause`] before heading to the
+/// next one. Save [`WaypointFollower::mission`] with
+/// [`MissionStore`](crate::MissionStore) to resume the mission after restart.
+pub struct WaypointFollower {
+    navigator: Navigator,
+    mission: MissionState,
+    status: MissionStatus,
+    /// xy tolerance of the waypoints with [`Waypoint::yaw_tolerance`]
+    xy_tolerance: f64,
+    stop_on_failure: bool,
+    /// Index of the waypoint sent to the navigator
+    active: Option<usize>,
+    /// Goal checker of the navigator replaced for the current waypoint
+    original_goal_checker: Option<Box<dyn GoalChecker>>,
+    pause_until: Option<SystemTime>,
+    callback: Option<ProgressCallback>,
+}
+
+impl fmt::Debug for WaypointFollower {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaypointFollower")
+            .field("navigator", &self.navigator)
+            .field("mission", &self.mission)
+            .field("status", &self.status)
+            .field("xy_tolerance", &self.xy_tolerance)
+            .field("stop_on_failure", &self.stop_on_failure)
+            .field("active", &self.active)
+            .field("pause_until", &self.pause_until)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WaypointFollower {
+    pub fn new(navigator: Navigator) -> Self {
+        Self {
+            navigator,
+            mission: MissionState::from_waypoints(vec![]),
+            status: MissionStatus::Idle,
+            xy_tolerance: SimpleGoalChecker::default().xy_tolerance,
+            stop_on_failure: true,
+            active: None,
+            original_goal_checker: None,
+            pause_until: None,
+            callback: None,
+        }
+    }
+
+    /// Set the xy tolerance used with [`Waypoint::yaw_tolerance`]
+    pub fn with_xy_tolerance(mut self, xy_tolerance: f64) -> Self {
+        self.xy_tolerance = xy_tolerance;
+        self
+    }
+
+    /// Abort the mission if the navigation to a waypoint fails (default), or skip
+    /// the waypoint and continue to the next one
+    pub fn with_stop_on_failure(mut self, stop_on_failure: bool) -> Self {
+        self.stop_on_failure = stop_on_failure;
+        self
+    }
+
+    /// Set the callback called for every [`WaypointEvent`]
+    pub fn set_progress_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&WaypointEvent, &MissionState) + Send + 'static,
+    {
+        self.callback = Some(Box::new(callback));
+    }
+
+    pub fn navigator(&self) -> &Navigator {
+        &self.navigator
+    }
+
+    pub fn navigator_mut(&mut self) -> &mut Navigator {
+        &mut self.navigator
+    }
+
+    pub fn mission(&self) -> &MissionState {
+        &self.mission
+    }
+
+    pub fn status(&self) -> MissionStatus {
+        self.status
+    }
+
+    /// Start the mission, canceling the current one
+    ///
+    /// The mission may be the one resumed by [`MissionStore::resume_last_mission`](crate::MissionStore::resume_last_mission).
+    pub fn start(&mut self, mission: MissionState) {
+        self.finish_waypoint();
+        self.navigator.cancel();
+        self.mission = mission;
+        self.status = MissionStatus::Running;
+        self.complete_if_finished();
+    }
+
+    /// Pause the mission. The robot stops until [`WaypointFollower::resume`].
+    pub fn pause(&mut self) {
+        self.mission.paused = true;
+    }
+
+    /// Resume the paused mission. The path to the current waypoint is planned again.
+    pub fn resume(&mut self) {
+        if self.mission.paused {
+            self.mission.paused = false;
+            self.active = None;
+        }
+    }
+
+    /// Skip the current waypoint and head to the next one
+    pub fn skip(&mut self) {
+        if self.status != MissionStatus::Running {
+            return;
+        }
+        let index = self.mission.current_index;
+        self.emit(WaypointEvent::Skipped { index });
+        self.advance();
+    }
+
+    /// Insert the waypoint at the index of the whole list
+    ///
+    /// If it is inserted at the current index, the robot heads to the new waypoint
+    /// immediately. The current waypoint is not changed by inserting before it.
+    pub fn insert(&mut self, index: usize, waypoint: Waypoint) {
+        let index = index.min(self.mission.waypoints.len());
+        self.mission.waypoints.insert(index, waypoint);
+        if index < self.mission.current_index {
+            self.mission.current_index += 1;
+            self.active = self.active.map(|i| i + 1);
+        } else if index == self.mission.current_index {
+            self.finish_waypoint();
+        }
+        if self.status == MissionStatus::Completed {
+            self.status = MissionStatus::Running;
+        }
+    }
+
+    /// Stop the robot and abort the mission
+    pub fn abort(&mut self) {
+        if self.status != MissionStatus::Running {
+            return;
+        }
+        self.finish_waypoint();
+        self.navigator.cancel();
+        self.status = MissionStatus::Aborted;
+        self.emit(WaypointEvent::Aborted);
+    }
+
+    /// [`WaypointFollower::tick_at`] with the current time
+    pub fn tick(&mut self, pose: &Pose, velocity: &Velocity) -> Command {
+        self.tick_at(pose, velocity, SystemTime::now())
+    }
+
+    /// Run one cycle of the mission and return the command to the base
+    pub fn tick_at(&mut self, pose: &Pose, velocity: &Velocity, now: SystemTime) -> Command {
+        if self.status != MissionStatus::Running || self.mission.paused {
+            return Command::Stop;
+        }
+        if let Some(until) = self.pause_until {
+            if now < until {
+                return Command::Stop;
+            }
+            self.advance();
+            if self.status != MissionStatus::Running {
+                return Command::Stop;
+            }
+        }
+        let index = self.mission.current_index;
+        if self.active.is_none() {
+            self.start_waypoint(index);
+        }
+        let command = self.navigator.tick(pose, velocity);
+        match self.navigator.state() {
+            NavigatorState::GoalReached => {
+                self.emit(WaypointEvent::Reached { index });
+                let pause = self.mission.waypoints[index].pause;
+                if pause.is_zero() {
+                    self.advance();
+                } else {
+                    self.pause_until = Some(now + pause);
+                }
+                Command::Stop
+            }
+            NavigatorState::Failed => {
+                let error = self.navigator.last_error().unwrap_or_default().to_owned();
+                self.emit(WaypointEvent::Failed { index, error });
+                if self.stop_on_failure {
+                    self.abort();
+                } else {
+                    self.emit(WaypointEvent::Skipped { index });
+                    self.advance();
+                }
+                Command::Stop
+            }
+            _ => command,
+        }
+    }
+
+    fn start_waypoint(&mut self, index: usize) {
+        let waypoint = self.mission.waypoints[index];
+        if let Some(yaw_tolerance) = waypoint.yaw_tolerance {
+            let checker = Box::new(SimpleGoalChecker::new(self.xy_tolerance, yaw_tolerance));
+            let original = self.navigator.replace_goal_checker(checker);
+            self.original_goal_checker.get_or_insert(original);
+        }
+        self.navigator.set_goal(Pose::from(&waypoint));
+        self.active = Some(index);
+        self.emit(WaypointEvent::Started { index });
+    }
+
+    /// Restore the goal checker and clear the state of the current waypoint
+    fn finish_waypoint(&mut self) {
+        if let Some(checker) = self.original_goal_checker.take() {
+            self.navigator.replace_goal_checker(checker);
+        }
+        self.active = None;
+        self.pause_until = None;
+    }
+
+    fn advance(&mut self) {
+        self.finish_waypoint();
+        self.mission.advance();
+        self.complete_if_finished();
+    }
+
+    fn complete_if_finished(&mut self) {
+        if self.status == MissionStatus::Running && self.mission.is_finished() {
+            self.navigator.cancel();
+            self.status = MissionStatus::Completed;
+            self.emit(WaypointEvent::Completed);
+        }
+    }
+
+    fn emit(&mut self, event: WaypointEvent) {
+        if let Some(callback) = &mut self.callback {
+            callback(&event, &self.mission);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{free_map, move_pose, navigator, DT};
+    use grid_map::Grid;
+    use nalgebra as na;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    fn waypoint(x: f64, y: f64, yaw: f64) -> Waypoint {
+        Waypoint::from(&Pose::new(na::Vector2::new(x, y), yaw))
+    }
+
+    #[test]
+    fn test_waypoint_follower() {
+        let mut map = free_map();
+        map.set_obstacle(&Grid::new(40, 30)).unwrap();
+        let navigator = navigator(map)
+            .with_goal_checker(Box::new(SimpleGoalChecker::new(0.1, std::f64::consts::PI)));
+        let mut follower = WaypointFollower::new(navigator).with_stop_on_failure(false);
+        let events = Arc::new(Mutex::new(vec![]));
+        let events_clone = events.clone();
+        follower.set_progress_callback(move |event, _| {
+            events_clone.lock().unwrap().push(event.clone());
+        });
+
+        follower.start(MissionState::from_waypoints(vec![
+            waypoint(1.0, 0.5, 0.0).with_pause(Duration::from_secs(1)),
+            // in the obstacle
+            waypoint(2.025, 1.525, 0.0),
+            waypoint(2.0, 0.5, 0.0),
+            waypoint(2.0, 1.0, 0.0),
+        ]));
+        follower.insert(3, waypoint(1.5, 0.5, 1.0).with_yaw_tolerance(0.3));
+        assert_eq!(follower.status(), MissionStatus::Running);
+
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut now = SystemTime::UNIX_EPOCH;
+        let mut reached_first = false;
+        let mut skipped = false;
+        for _ in 0..3000 {
+            let velocity = follower
+                .tick_at(&pose, &Velocity::default(), now)
+                .velocity();
+            if follower.status() != MissionStatus::Running {
+                break;
+            }
+            let index = follower.mission().current_index;
+            if index == 0 && events.lock().unwrap().len() > 1 {
+                // staying at the first waypoint
+                reached_first = true;
+                assert_eq!(velocity, Velocity::default());
+            }
+            if index == 4 && !skipped {
+                // turned to the yaw of the waypoint with the tolerance of it
+                let yaw = pose.rotation.angle();
+                assert!((yaw - 1.0).abs() < 0.3, "{yaw}");
+                follower.skip();
+                skipped = true;
+            }
+            pose = move_pose(&pose, &velocity, DT);
+            now += Duration::from_secs_f64(DT);
+        }
+        assert!(reached_first);
+        assert_eq!(follower.status(), MissionStatus::Completed);
+        assert!(follower.mission().is_finished());
+        let events = events.lock().unwrap();
+        assert!(matches!(
+            events[..3],
+            [
+                WaypointEvent::Started { index: 0 },
+                WaypointEvent::Reached { index: 0 },
+                WaypointEvent::Started { index: 1 },
+            ]
+        ));
+        assert!(events.contains(&WaypointEvent::Skipped { index: 1 }));
+        assert!(events.contains(&WaypointEvent::Reached { index: 2 }));
+        assert!(events.contains(&WaypointEvent::Reached { index: 3 }));
+        assert!(events.contains(&WaypointEvent::Skipped { index: 4 }));
+        assert!(!events.contains(&WaypointEvent::Reached { index: 4 }));
+        assert_eq!(events.last(), Some(&WaypointEvent::Completed));
+        drop(events);
+
+        // aborted
+        follower.start(MissionState::new(&[Pose::new(
+            na::Vector2::new(2.0, 1.0),
+            0.0,
+        )]));
+        follower.tick_at(&pose, &Velocity::default(), now);
+        follower.abort();
+        assert_eq!(follower.status(), MissionStatus::Aborted);
+        assert_eq!(follower.navigator().state(), NavigatorState::Idle);
+        assert_eq!(
+            follower.tick_at(&pose, &Velocity::default(), now),
+            Command::Stop
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use grid_map::{Cell, Grid, GridMap, Position};
+use serde::{Deserialize, Serialize};
+
+use crate::{polygon_contains, Pose};
+
+/// Polygonal region in the world coordinates with a restriction
+///
+/// ```yaml
+/// - type: keep_out
+///   polygon: [{x: 0.0, y: 0.0}, {x: 1.0, y: 0.0}, {x: 1.0, y: 1.0}]
+/// - type: speed_limit
+///   max_velocity: 0.2
+///   polygon: [{x: 2.0, y: 0.0}, {x: 3.0, y: 0.0}, {x: 3.0, y: 1.0}]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Zone {
+    /// The robot must not enter the region
+    KeepOut { polygon: Vec<Position> },
+    /// The linear velocity is limited to `max_velocity` (m/s) inside of the region
+    SpeedLimit {
+        polygon: Vec<Position>,
+        max_velocity: f64,
+    },
+}
+
+impl Zone {
+    pub fn polygon(&self) -> &[Position] {
+        match self {
+            Zone::KeepOut { polygon } | Zone::SpeedLimit { polygon, .. } => polygon,
+        }
+    }
+
+    pub fn contains(&self, position: &Position) -> bool {
+        polygon_contains(self.polygon(), position)
+    }
+}
+
+/// Keep-out and speed-restriction zones
+///
+/// The keep-out zones are rasterized into an obstacle layer, which is added to the
+/// layers of the planner (e.g. with a large weight, or merged into the map before
+/// computing the distance maps). The speed limit zones are consulted by
+/// [`DwaPlanner`](crate::DwaPlanner) to cap the linear velocity.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ZoneLayer {
+    zones: Vec<Zone>,
+}
+
+impl ZoneLayer {
+    pub fn new(zones: Vec<Zone>) -> Self {
+        Self { zones }
+    }
+
+    pub fn zones(&self) -> &[Zone] {
+        &self.zones
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn add_keep_out(&mut self, polygon: Vec<Position>) {
+        self.zones.push(Zone::KeepOut { polygon });
+    }
+
+    pub fn add_speed_limit(&mut self, polygon: Vec<Position>, max_velocity: f64) {
+        self.zones.push(Zone::SpeedLimit {
+            polygon,
+            max_velocity,
+        });
+    }
+
+    /// Whether the position is inside of any keep-out zone
+    pub fn is_kept_out(&self, position: &Position) -> bool {
+        self.zones
+            .iter()
+            .any(|z| matches!(z, Zone::KeepOut { .. }) && z.contains(position))
+    }
+
+    /// The strictest speed limit at the position, `None` if it is not limited
+    pub fn speed_limit_at(&self, position: &Position) -> Option<f64> {
+        self.zones
+            .iter()
+            .filter_map(|z| match z {
+                Zone::SpeedLimit { max_velocity, .. } if z.contains(position) => {
+                    Some(max_velocity.abs())
+                }
+                _ => None,
+            })
+            .min_by(f64::total_cmp)
+    }
+
+    /// [`ZoneLayer::speed_limit_at`] at the pose
+    pub fn speed_limit_at_pose(&self, pose: &Pose) -> Option<f64> {
+        self.speed_limit_at(&Position::new(pose.translation.x, pose.translation.y))
+    }
+
+    /// Layer of the same geometry as the map, where the cells whose centers are in a
+    /// keep-out zone are obstacles and the others are `Cell::Value(0)`
+    pub fn keep_out_layer<T: Clone>(&self, map: &GridMap<T>) -> GridMap<u8> {
+        let mut layer = map.map_values(|_| 0);
+        for y in 0..layer.height() {
+            for x in 0..layer.width() {
+                let grid = Grid::new(x, y);
+                let center = layer.cell_center(&grid);
+                let cell = layer.cell_mut(&grid).unwrap();
+                *cell = if self.is_kept_out(&center) {
+                    Cell::Obstacle
+                } else {
+                    Cell::Value(0)
+                };
+            }
+        }
+        layer
+    }
+
+    /// Layer of the same geometry as the map with the speed limits (m/s). Cells which
+    /// are not limited are `Cell::Uninitialized`.
+    pub fn speed_limit_layer<T: Clone>(&self, map: &GridMap<T>) -> GridMap<f64> {
+        let mut layer = map.map_values(|_| 0.0);
+        for y in 0..layer.height() {
+            for x in 0..layer.width() {
+                let grid = Grid::new(x, y);
+                let center = layer.cell_center(&grid);
+                let cell = layer.cell_mut(&grid).unwrap();
+                *cell = match self.speed_limit_at(&center) {
+                    Some(limit) => Cell::Value(limit),
+                    None => Cell::Uninitialized,
+                };
+            }
+        }
+        layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Position> {
+        vec![
+            Position::new(x, y),
+            Position::new(x + size, y),
+            Position::new(x + size, y + size),
+            Position::new(x, y + size),
+        ]
+    }
+
+    #[test]
+    fn test_zone_layer() {
+        let mut zones = ZoneLayer::default();
+        zones.add_keep_out(square(0.0, 0.0, 0.5));
+        zones.add_speed_limit(square(0.5, 0.0, 1.0), 0.3);
+        zones.add_speed_limit(square(1.0, 0.0, 1.0), 0.1);
+        assert!(zones.is_kept_out(&Position::new(0.2, 0.2)));
+        assert!(!zones.is_kept_out(&Position::new(0.7, 0.2)));
+        assert_eq!(zones.speed_limit_at(&Position::new(0.7, 0.2)), Some(0.3));
+        assert_eq!(zones.speed_limit_at(&Position::new(1.2, 0.2)), Some(0.1));
+        assert_eq!(zones.speed_limit_at(&Position::new(2.5, 0.2)), None);
+
+        let map = GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(2.05, 1.05), 0.1);
+        let keep_out = zones.keep_out_layer(&map);
+        assert_eq!(keep_out.cell(&Grid::new(4, 4)), Some(&Cell::Obstacle));
+        assert_eq!(keep_out.cell(&Grid::new(5, 4)), Some(&Cell::Value(0)));
+        let speed = zones.speed_limit_layer(&map);
+        assert_eq!(speed.value(&Grid::new(7, 2)), Some(0.3));
+        assert_eq!(speed.value(&Grid::new(12, 2)), Some(0.1));
+        assert_eq!(speed.cell(&Grid::new(7, 12)), None);
+        assert_eq!(speed.cell(&Grid::new(2, 2)), Some(&Cell::Uninitialized));
+
+        let yaml = serde_yaml::to_string(&zones).unwrap();
+        assert_eq!(serde_yaml::from_str::<ZoneLayer>(&yaml).unwrap(), zones);
+    }
+}
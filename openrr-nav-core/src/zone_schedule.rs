@@ -0,0 +1,231 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Error, LocalPlanner, Zone, ZoneLayer};
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// Daily time window `"HH:MM-HH:MM"`, which includes the start and excludes the end
+///
+/// The window wraps around midnight if the end is earlier than the start, e.g.
+/// `"22:00-06:00"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct TimeWindow {
+    /// (s) from midnight
+    start: u32,
+    /// (s) from midnight
+    end: u32,
+}
+
+impl TimeWindow {
+    /// Create the window from the hours and the minutes of the start and the end
+    pub fn new(start: (u32, u32), end: (u32, u32)) -> Result<Self, Error> {
+        let seconds = |(hour, minute): (u32, u32)| {
+            if hour > 24 || minute >= 60 || (hour == 24 && minute != 0) {
+                return Err(Error::Other(format!(
+                    "invalid time of day {hour:02}:{minute:02}"
+                )));
+            }
+            Ok((hour * 60 + minute) * 60)
+        };
+        Ok(Self {
+            start: seconds(start)?,
+            end: seconds(end)?,
+        })
+    }
+
+    /// Whether the time (s) from midnight is in the window
+    pub fn contains(&self, seconds_of_day: u32) -> bool {
+        if self.start <= self.end {
+            (self.start..self.end).contains(&seconds_of_day)
+        } else {
+            seconds_of_day >= self.start || seconds_of_day < self.end
+        }
+    }
+}
+
+impl TryFrom<String> for TimeWindow {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || Error::Other(format!("invalid time window {value:?} (HH:MM-HH:MM)"));
+        let parse = |time: &str| {
+            let (hour, minute) = time.trim().split_once(':').ok_or_else(invalid)?;
+            Ok::<_, Error>((
+                hour.parse().map_err(|_| invalid())?,
+                minute.parse().map_err(|_| invalid())?,
+            ))
+        };
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        Self::new(parse(start)?, parse(end)?)
+    }
+}
+
+impl From<TimeWindow> for String {
+    fn from(window: TimeWindow) -> Self {
+        window.to_string()
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hm = |s: u32| (s / 3600, s / 60 % 60);
+        let ((h0, m0), (h1, m1)) = (hm(self.start), hm(self.end));
+        write!(f, "{h0:02}:{m0:02}-{h1:02}:{m1:02}")
+    }
+}
+
+/// Zone which is active only in the time windows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScheduledZone {
+    pub zone: Zone,
+    /// Always active if empty
+    #[serde(default)]
+    pub windows: Vec<TimeWindow>,
+}
+
+impl ScheduledZone {
+    pub fn is_active(&self, seconds_of_day: u32) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|w| w.contains(seconds_of_day))
+    }
+}
+
+/// Keep-out and speed-limit zones activated by the time of day
+///
+/// ```yaml
+/// utc_offset_hours: 9
+/// zones:
+///   - windows: ["09:00-18:00"] # slower during business hours
+///     zone:
+///       type: speed_limit
+///       max_velocity: 0.3
+///       polygon: [{x: 0.0, y: 0.0}, {x: 5.0, y: 0.0}, {x: 5.0, y: 5.0}]
+/// ```
+///
+/// The schedule is evaluated every cycle with [`ZoneSchedule::update`], which
+/// replaces the zones of the planner when the set of the active zones changes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ZoneSchedule {
+    /// Offset of the local time of the windows from UTC
+    #[serde(default)]
+    pub utc_offset_hours: f64,
+    pub zones: Vec<ScheduledZone>,
+    #[serde(skip)]
+    active: Option<Vec<bool>>,
+}
+
+impl ZoneSchedule {
+    pub fn new(zones: Vec<ScheduledZone>, utc_offset_hours: f64) -> Self {
+        Self {
+            utc_offset_hours,
+            zones,
+            active: None,
+        }
+    }
+
+    /// Local time of the day (s)
+    pub fn seconds_of_day(&self, time: SystemTime) -> u32 {
+        let seconds = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        } + self.utc_offset_hours * 3600.0;
+        seconds.rem_euclid(SECONDS_PER_DAY as f64) as u32 % SECONDS_PER_DAY
+    }
+
+    /// Zones active at the time
+    pub fn active_zones(&self, time: SystemTime) -> ZoneLayer {
+        let seconds = self.seconds_of_day(time);
+        ZoneLayer::new(
+            self.zones
+                .iter()
+                .filter(|z| z.is_active(seconds))
+                .map(|z| z.zone.clone())
+                .collect(),
+        )
+    }
+
+    /// Set the zones to the planner in the next update even if they are not changed,
+    /// e.g. after the planner is replaced
+    pub fn invalidate(&mut self) {
+        self.active = None;
+    }
+
+    /// Set the active zones to the planner if they are changed since the last update
+    ///
+    /// Returns true if the zones of the planner are replaced.
+    pub fn update(&mut self, planner: &mut dyn LocalPlanner, time: SystemTime) -> bool {
+        let seconds = self.seconds_of_day(time);
+        let active = self
+            .zones
+            .iter()
+            .map(|z| z.is_active(seconds))
+            .collect::<Vec<_>>();
+        if self.active.as_ref() == Some(&active) {
+            return false;
+        }
+        planner.set_zones(self.active_zones(time));
+        self.active = Some(active);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DwaPlanner;
+    use grid_map::Position;
+    use std::time::Duration;
+
+    fn at(hour: u64, minute: u64) -> SystemTime {
+        // 2024-01-01T00:00:00Z
+        UNIX_EPOCH + Duration::from_secs(1_704_067_200 + (hour * 60 + minute) * 60)
+    }
+
+    #[test]
+    fn test_zone_schedule() {
+        let window = TimeWindow::try_from("22:00-06:30".to_owned()).unwrap();
+        assert_eq!(window.to_string(), "22:00-06:30");
+        assert!(window.contains(23 * 3600) && window.contains(6 * 3600));
+        assert!(!window.contains(6 * 3600 + 1800) && !window.contains(12 * 3600));
+        assert!(TimeWindow::try_from("25:00-06:00".to_owned()).is_err());
+        assert!(TimeWindow::try_from("0800-1800".to_owned()).is_err());
+
+        let yaml = r#"
+utc_offset_hours: 9
+zones:
+  - windows: ["09:00-18:00"]
+    zone:
+      type: speed_limit
+      max_velocity: 0.3
+      polygon: [{x: 0.0, y: 0.0}, {x: 5.0, y: 0.0}, {x: 5.0, y: 5.0}, {x: 0.0, y: 5.0}]
+  - zone:
+      type: keep_out
+      polygon: [{x: 6.0, y: 0.0}, {x: 7.0, y: 0.0}, {x: 7.0, y: 1.0}]
+"#;
+        let mut schedule: ZoneSchedule = serde_yaml::from_str(yaml).unwrap();
+        let inside = Position::new(1.0, 1.0);
+        // 10:00 in UTC+9
+        let zones = schedule.active_zones(at(1, 0));
+        assert_eq!(zones.zones().len(), 2);
+        assert_eq!(zones.speed_limit_at(&inside), Some(0.3));
+        // 20:00 in UTC+9
+        assert_eq!(
+            schedule.active_zones(at(11, 0)).speed_limit_at(&inside),
+            None
+        );
+
+        let mut planner = DwaPlanner::new(Default::default(), Default::default(), 0.1, 1.0, 5);
+        assert!(schedule.update(&mut planner, at(1, 0)));
+        assert_eq!(planner.zones().speed_limit_at(&inside), Some(0.3));
+        assert!(!schedule.update(&mut planner, at(2, 0)));
+        assert!(schedule.update(&mut planner, at(9, 0)));
+        assert_eq!(planner.zones().speed_limit_at(&inside), None);
+        assert!(planner.zones().is_kept_out(&Position::new(6.8, 0.2)));
+    }
+}
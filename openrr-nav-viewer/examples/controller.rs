@@ -4,7 +4,7 @@
 // # start viewer
 // cargo run --release -p openrr-nav-viewer
 // # start controller example
-// cargo run --release -p openrr-nav-viewer --example controller -- -f openrr-nav/config/dwa_parameter_config.yaml
+// cargo run --release -p openrr-nav-viewer --example controller -- -f openrr-nav-core/config/dwa_parameter_config.yaml
 // ```
 
 mod shared;
@@ -12,12 +12,13 @@ mod shared;
 use anyhow::Result;
 use clap::Parser;
 use grid_map::*;
-use openrr_nav::{utils::nearest_path_point, *};
+use openrr_nav_core::{utils::nearest_path_point, *};
 use openrr_nav_viewer::*;
-use rand::distributions::{Distribution, Uniform};
 use shared::*;
 
 const ENDPOINT: &str = "http://[::1]:50101";
+/// (s)
+const CONTROL_PERIOD: f64 = 0.05;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -27,21 +28,58 @@ async fn main() -> Result<()> {
         text: std::fs::read_to_string(&args.planner_config_path)?,
     })
     .await?;
+    let mut planners = global_planners(args.seed);
     loop {
-        controller(&mut api).await?
+        controller(&mut api, &mut planners).await?
+    }
+}
+
+/// Name and revision, and map of the scenario or the map loaded in the viewer, or the
+/// sample map
+async fn scenario_map(
+    api: &mut openrr_nav_viewer::pb::api_client::ApiClient<tonic::transport::Channel>,
+    time: Option<f64>,
+) -> Result<((String, u64), GridMap<u8>)> {
+    let pb::ScenarioMap {
+        name,
+        map,
+        revision,
+    } = api
+        .get_scenario_map(pb::ScenarioMapRequest { time })
+        .await?
+        .into_inner();
+    Ok((
+        (name, revision),
+        map.map_or_else(new_sample_map, Into::into),
+    ))
+}
+
+/// Wait while the loop is paused in the viewer and return the speed multiplier
+async fn wait_cycle(
+    api: &mut openrr_nav_viewer::pb::api_client::ApiClient<tonic::transport::Channel>,
+) -> Result<f64> {
+    loop {
+        let pb::CycleControl { start, speed } = api.start_cycle(()).await?.into_inner();
+        if start {
+            return Ok(speed);
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
     }
 }
 
 async fn controller(
     api: &mut openrr_nav_viewer::pb::api_client::ApiClient<tonic::transport::Channel>,
+    planners: &mut GlobalPlannerRegistry,
 ) -> Result<()> {
     if !api.get_is_run(()).await?.into_inner() {
         tokio::time::sleep(std::time::Duration::from_millis(10)).await;
         return Ok(());
     }
-    let mut map = new_sample_map();
-    let x_range = Uniform::new(map.min_point().x, map.max_point().x);
-    let y_range = Uniform::new(map.min_point().y, map.max_point().y);
+    let (scenario_id, mut map) = scenario_map(api, None).await?;
+    let planner_name = api.get_global_planner(()).await?.into_inner();
+    if let Err(e) = planners.set_active(&planner_name) {
+        eprintln!("{e}");
+    }
     let start = Pose::from(api.get_start_position(()).await?.into_inner());
     let start = [
         start.translation.x,
@@ -54,29 +92,14 @@ async fn controller(
         goal.translation.y,
         goal.rotation.angle(),
     ];
-    let is_free = |p: &[f64]| {
-        !matches!(
-            map.cell(&map.to_grid(p[0], p[1]).unwrap()).unwrap(),
-            Cell::Obstacle
-        )
+    let result = match plan_global_path(planners, &map, start, goal) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("failed to plan the global path: {e}");
+            api.set_is_run(false).await?;
+            return Ok(());
+        }
     };
-    const EXTEND_LENGTH: f64 = 0.05;
-    let mut result = rrt::dual_rrt_connect(
-        &[start[0], start[1]],
-        &[goal[0], goal[1]],
-        is_free,
-        || {
-            let mut rng = rand::thread_rng();
-            vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
-        },
-        EXTEND_LENGTH,
-        4000,
-    )
-    .unwrap();
-    rrt::smooth_path(&mut result, is_free, EXTEND_LENGTH, 1000);
-    let result = linear_interpolate_path(result, EXTEND_LENGTH);
-    let result =
-        add_target_position_to_path(result, &Pose::new(Vector2::new(goal[0], goal[1]), goal[2]));
     api.set_global_path(pb::RobotPath::from(robot_path_from_vec_vec(result.clone())))
         .await?;
     let path_grid = result
@@ -101,19 +124,19 @@ async fn controller(
     api.set_layered_grid_map(pb::SetLayeredGridMapRequest {
         maps: vec![
             pb::NamedGridMap {
-                name: PATH_DISTANCE_MAP_NAME.to_owned(),
+                name: LayerId::PATH.to_string(),
                 map: Some((&path_distance_map).into()),
             },
             pb::NamedGridMap {
-                name: GOAL_DISTANCE_MAP_NAME.to_owned(),
+                name: LayerId::GOAL.to_string(),
                 map: Some((&goal_distance_map).into()),
             },
             pb::NamedGridMap {
-                name: OBSTACLE_DISTANCE_MAP_NAME.to_owned(),
+                name: LayerId::OBSTACLE.to_string(),
                 map: Some((&obstacle_distance_map).into()),
             },
             pb::NamedGridMap {
-                name: LOCAL_GOAL_DISTANCE_MAP_NAME.to_owned(),
+                name: LayerId::LOCAL_GOAL.to_string(),
                 map: Some((&local_goal_distance_map).into()),
             },
         ],
@@ -123,15 +146,15 @@ async fn controller(
     api.set_angle_table(pb::SetAngleTableRequest {
         table: vec![
             pb::NamedAngle {
-                name: ROTATION_COST_NAME.to_owned(),
+                name: LayerId::ROTATION.to_string(),
                 angle: start[2],
             },
             pb::NamedAngle {
-                name: PATH_DIRECTION_COST_NAME.to_owned(),
+                name: LayerId::PATH_DIRECTION.to_string(),
                 angle: start[2],
             },
             pb::NamedAngle {
-                name: GOAL_DIRECTION_COST_NAME.to_owned(),
+                name: LayerId::GOAL_DIRECTION.to_string(),
                 angle: goal[2],
             },
         ],
@@ -143,18 +166,39 @@ async fn controller(
 
     let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
     let mut plan_map = map.clone();
+    let goal_checker = SimpleGoalChecker::new(0.1, 0.4);
 
     for i in 0..300 {
-        // let dynamic_map = new_dynamic_sample_map(i);
-        let dynamic_map = new_sample_map();
-        let path_distance_map = openrr_nav::path_distance_map(&dynamic_map, &path_grid).unwrap();
+        let speed = wait_cycle(api).await?;
+        let (id, dynamic_map) = scenario_map(api, Some(i as f64 * CONTROL_PERIOD)).await?;
+        if id != scenario_id {
+            // another scenario is loaded or the map is edited
+            return Ok(());
+        }
+        let new_goal = Pose::from(api.get_goal_position(()).await?.into_inner());
+        let new_goal = [
+            new_goal.translation.x,
+            new_goal.translation.y,
+            new_goal.rotation.angle(),
+        ];
+        if new_goal != goal {
+            // the goal (or its heading) is changed
+            return Ok(());
+        }
+        if api.get_global_planner(()).await?.into_inner() != planner_name {
+            // another global planner is selected
+            return Ok(());
+        }
+        let path_distance_map =
+            openrr_nav_core::path_distance_map(&dynamic_map, &path_grid).unwrap();
 
         let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
-        let goal_distance_map = openrr_nav::goal_distance_map(&dynamic_map, &goal_grid).unwrap();
+        let goal_distance_map =
+            openrr_nav_core::goal_distance_map(&dynamic_map, &goal_grid).unwrap();
 
-        let obstacle_distance_map = openrr_nav::obstacle_distance_map(&dynamic_map).unwrap();
+        let obstacle_distance_map = openrr_nav_core::obstacle_distance_map(&dynamic_map).unwrap();
 
-        let local_goal_distance_map = openrr_nav::local_goal_distance_map(
+        let local_goal_distance_map = openrr_nav_core::local_goal_distance_map(
             &map,
             &result,
             [current_pose.translation.x, current_pose.translation.y],
@@ -164,19 +208,19 @@ async fn controller(
         api.set_layered_grid_map(pb::SetLayeredGridMapRequest {
             maps: vec![
                 pb::NamedGridMap {
-                    name: PATH_DISTANCE_MAP_NAME.to_owned(),
+                    name: LayerId::PATH.to_string(),
                     map: Some((&path_distance_map).into()),
                 },
                 pb::NamedGridMap {
-                    name: GOAL_DISTANCE_MAP_NAME.to_owned(),
+                    name: LayerId::GOAL.to_string(),
                     map: Some((&goal_distance_map).into()),
                 },
                 pb::NamedGridMap {
-                    name: OBSTACLE_DISTANCE_MAP_NAME.to_owned(),
+                    name: LayerId::OBSTACLE.to_string(),
                     map: Some((&obstacle_distance_map).into()),
                 },
                 pb::NamedGridMap {
-                    name: LOCAL_GOAL_DISTANCE_MAP_NAME.to_owned(),
+                    name: LayerId::LOCAL_GOAL.to_string(),
                     map: Some((&local_goal_distance_map).into()),
                 },
             ],
@@ -190,14 +234,14 @@ async fn controller(
             );
             let len = result.len();
             let mut table = vec![pb::NamedAngle {
-                name: ROTATION_COST_NAME.to_owned(),
+                name: LayerId::ROTATION.to_string(),
                 angle: current_pose.rotation.angle(),
             }];
             const FORWARD_OFFSET: usize = 20;
             if let Some((idx, _)) = nearest_path_point {
                 let look_ahead_idx = (idx + FORWARD_OFFSET).min(len - 1);
                 table.push(pb::NamedAngle {
-                    name: PATH_DIRECTION_COST_NAME.to_owned(),
+                    name: LayerId::PATH_DIRECTION.to_string(),
                     angle: result[look_ahead_idx][2],
                 });
             }
@@ -234,14 +278,18 @@ async fn controller(
         current_velocity = plan.velocity.unwrap().into();
         current_pose = plan
             .path
-            .get(0)
+            .first()
             .cloned()
             .map(Into::into)
             .unwrap_or_default();
+        // There is no pose estimator in this example, so the robot jumps to the initial pose
+        if let Some(initial_pose) = api.take_initial_pose(()).await?.into_inner().pose {
+            current_pose = PoseWithCovariance::try_from(initial_pose)?.pose;
+        }
 
         api.set_current_pose(pb::Isometry2::from(current_pose))
             .await?;
-        std::thread::sleep(std::time::Duration::from_millis(50));
+        std::thread::sleep(std::time::Duration::from_secs_f64(CONTROL_PERIOD / speed));
 
         if let Some(grid) = plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
         {
@@ -250,13 +298,7 @@ async fn controller(
             println!("OUT OF MAP!");
             return Ok(());
         }
-        const GOAL_THRESHOLD_DISTANCE: f64 = 0.1;
-        const GOAL_THRESHOLD_ANGLE_DIFFERENCE: f64 = 0.4;
-        if (goal_pose.translation.vector - current_pose.translation.vector).norm()
-            < GOAL_THRESHOLD_DISTANCE
-            && (goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
-                < GOAL_THRESHOLD_ANGLE_DIFFERENCE
-        {
+        if goal_checker.is_reached(&goal_pose.into(), &current_pose, &current_velocity) {
             println!("GOAL! count = {i}");
             break;
         }
@@ -8,18 +8,36 @@ mod shared;
 
 use clap::Parser;
 use grid_map::*;
-use openrr_nav::{utils::nearest_path_point, *};
+use openrr_nav_core::{utils::nearest_path_point, *};
 use openrr_nav_viewer::*;
-use rand::distributions::{Distribution, Uniform};
 use shared::*;
 
+/// (s)
+const CONTROL_PERIOD: f64 = 0.05;
+
+/// Name and revision, and map of the scenario or the map loaded in the viewer, or the
+/// sample map
+fn scenario_map(nav: &NavigationViz, time: Option<f64>) -> ((String, u64), GridMap<u8>) {
+    match nav.current_map(time) {
+        Some(LoadedMap {
+            name,
+            map,
+            revision,
+        }) => ((name, revision), map),
+        None => ((String::new(), 0), new_sample_map()),
+    }
+}
+
 fn main() {
-    let nav: NavigationViz = Args::parse().try_into().unwrap();
+    let args = Args::parse();
+    let seed = args.seed;
+    let nav: NavigationViz = args.try_into().unwrap();
+    *nav.global_planners.lock().unwrap() = global_planners(seed);
 
     let cloned_nav = nav.clone();
 
     let planner = DwaPlanner::new_from_config(format!(
-        "{}/../openrr-nav/config/dwa_parameter_config.yaml",
+        "{}/../openrr-nav-core/config/dwa_parameter_config.yaml",
         env!("CARGO_MANIFEST_DIR")
     ))
     .unwrap();
@@ -29,14 +47,18 @@ fn main() {
         *locked_planner = planner;
     }
 
-    std::thread::spawn(move || loop {
+    std::thread::spawn(move || 'run: loop {
         if !*cloned_nav.is_run.lock().unwrap() {
             std::thread::sleep(std::time::Duration::from_millis(10));
             continue;
         }
-        let mut map = new_sample_map();
-        let x_range = Uniform::new(map.min_point().x, map.max_point().x);
-        let y_range = Uniform::new(map.min_point().y, map.max_point().y);
+        let (scenario_id, mut map) = scenario_map(&cloned_nav, None);
+        let planner_name = cloned_nav
+            .global_planners
+            .lock()
+            .unwrap()
+            .active()
+            .map(str::to_owned);
         let start;
         let goal;
         {
@@ -53,31 +75,20 @@ fn main() {
                 locked_goal.rotation.angle(),
             ];
         }
-        let is_free = |p: &[f64]| {
-            !matches!(
-                map.cell(&map.to_grid(p[0], p[1]).unwrap()).unwrap(),
-                Cell::Obstacle
-            )
-        };
-        const EXTEND_LENGTH: f64 = 0.05;
-        let mut result = rrt::dual_rrt_connect(
-            &[start[0], start[1]],
-            &[goal[0], goal[1]],
-            is_free,
-            || {
-                let mut rng = rand::thread_rng();
-                vec![x_range.sample(&mut rng), y_range.sample(&mut rng)]
-            },
-            EXTEND_LENGTH,
-            4000,
-        )
-        .unwrap();
-        rrt::smooth_path(&mut result, is_free, EXTEND_LENGTH, 1000);
-        let result = linear_interpolate_path(result, EXTEND_LENGTH);
-        let result = add_target_position_to_path(
-            result,
-            &Pose::new(Vector2::new(goal[0], goal[1]), goal[2]),
+        let planned = plan_global_path(
+            &cloned_nav.global_planners.lock().unwrap(),
+            &map,
+            start,
+            goal,
         );
+        let result = match planned {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("failed to plan the global path: {e}");
+                *cloned_nav.is_run.lock().unwrap() = false;
+                continue;
+            }
+        };
         {
             let mut locked_robot_path = cloned_nav.robot_path.lock().unwrap();
             locked_robot_path.set_global_path(robot_path_from_vec_vec(result.clone()));
@@ -103,42 +114,64 @@ fn main() {
 
         {
             let mut locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
-            locked_layered_grid_map.add_layer(PATH_DISTANCE_MAP_NAME.to_owned(), path_distance_map);
-            locked_layered_grid_map.add_layer(GOAL_DISTANCE_MAP_NAME.to_owned(), goal_distance_map);
-            locked_layered_grid_map
-                .add_layer(OBSTACLE_DISTANCE_MAP_NAME.to_owned(), obstacle_distance_map);
-            locked_layered_grid_map.add_layer(
-                LOCAL_GOAL_DISTANCE_MAP_NAME.to_owned(),
-                local_goal_distance_map,
-            );
+            locked_layered_grid_map.add_layer(LayerId::PATH, path_distance_map);
+            locked_layered_grid_map.add_layer(LayerId::GOAL, goal_distance_map);
+            locked_layered_grid_map.add_layer(LayerId::OBSTACLE, obstacle_distance_map);
+            locked_layered_grid_map.add_layer(LayerId::LOCAL_GOAL, local_goal_distance_map);
         }
 
         {
             let mut locked_angle_table = cloned_nav.angle_table.lock().unwrap();
-            locked_angle_table.insert(ROTATION_COST_NAME.to_owned(), start[2]);
-            locked_angle_table.insert(PATH_DIRECTION_COST_NAME.to_owned(), start[2]);
-            locked_angle_table.insert(GOAL_DIRECTION_COST_NAME.to_owned(), goal[2]);
+            locked_angle_table.insert(LayerId::ROTATION, start[2]);
+            locked_angle_table.insert(LayerId::PATH_DIRECTION, start[2]);
+            locked_angle_table.insert(LayerId::GOAL_DIRECTION, goal[2]);
         }
 
         let mut current_pose = Pose::new(Vector2::new(start[0], start[1]), start[2]);
+        let mut odometry = DeadReckoning::from_pose(current_pose);
         let goal_pose = Pose::new(Vector2::new(goal[0], goal[1]), goal[2]);
 
         let mut current_velocity = Velocity { x: 0.0, theta: 0.0 };
         let mut plan_map = map.clone();
+        let goal_checker = SimpleGoalChecker::new(0.1, 0.4);
 
         for i in 0..300 {
-            // let dynamic_map = new_dynamic_sample_map(i);
-            let dynamic_map = new_sample_map();
+            while !cloned_nav.start_cycle() {
+                // paused in the viewer
+                std::thread::sleep(std::time::Duration::from_millis(10));
+            }
+            let (id, dynamic_map) = scenario_map(&cloned_nav, Some(i as f64 * CONTROL_PERIOD));
+            if id != scenario_id {
+                // another scenario is loaded or the map is edited
+                continue 'run;
+            }
+            {
+                let locked_goal = cloned_nav.goal_position.lock().unwrap();
+                let new_goal = [
+                    locked_goal.translation.x,
+                    locked_goal.translation.y,
+                    locked_goal.rotation.angle(),
+                ];
+                if new_goal != goal {
+                    // the goal (or its heading) is changed
+                    continue 'run;
+                }
+            }
+            if cloned_nav.global_planners.lock().unwrap().active() != planner_name.as_deref() {
+                // another global planner is selected
+                continue 'run;
+            }
             let path_distance_map =
-                openrr_nav::path_distance_map(&dynamic_map, &path_grid).unwrap();
+                openrr_nav_core::path_distance_map(&dynamic_map, &path_grid).unwrap();
 
             let goal_grid = map.to_grid(goal[0], goal[1]).unwrap();
             let goal_distance_map =
-                openrr_nav::goal_distance_map(&dynamic_map, &goal_grid).unwrap();
+                openrr_nav_core::goal_distance_map(&dynamic_map, &goal_grid).unwrap();
 
-            let obstacle_distance_map = openrr_nav::obstacle_distance_map(&dynamic_map).unwrap();
+            let obstacle_distance_map =
+                openrr_nav_core::obstacle_distance_map(&dynamic_map).unwrap();
 
-            let local_goal_distance_map = openrr_nav::local_goal_distance_map(
+            let local_goal_distance_map = openrr_nav_core::local_goal_distance_map(
                 &map,
                 &result,
                 [current_pose.translation.x, current_pose.translation.y],
@@ -147,16 +180,10 @@ fn main() {
 
             {
                 let mut locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
-                locked_layered_grid_map
-                    .add_layer(PATH_DISTANCE_MAP_NAME.to_owned(), path_distance_map);
-                locked_layered_grid_map
-                    .add_layer(GOAL_DISTANCE_MAP_NAME.to_owned(), goal_distance_map);
-                locked_layered_grid_map
-                    .add_layer(OBSTACLE_DISTANCE_MAP_NAME.to_owned(), obstacle_distance_map);
-                locked_layered_grid_map.add_layer(
-                    LOCAL_GOAL_DISTANCE_MAP_NAME.to_owned(),
-                    local_goal_distance_map,
-                );
+                locked_layered_grid_map.add_layer(LayerId::PATH, path_distance_map);
+                locked_layered_grid_map.add_layer(LayerId::GOAL, goal_distance_map);
+                locked_layered_grid_map.add_layer(LayerId::OBSTACLE, obstacle_distance_map);
+                locked_layered_grid_map.add_layer(LayerId::LOCAL_GOAL, local_goal_distance_map);
             }
 
             {
@@ -166,15 +193,11 @@ fn main() {
                 );
                 let len = result.len();
                 let mut locked_angle_table = cloned_nav.angle_table.lock().unwrap();
-                locked_angle_table
-                    .insert(ROTATION_COST_NAME.to_owned(), current_pose.rotation.angle());
+                locked_angle_table.insert(LayerId::ROTATION, current_pose.rotation.angle());
                 const FORWARD_OFFSET: usize = 20;
                 if let Some((idx, _)) = nearest_path_point {
                     let look_ahead_idx = (idx + FORWARD_OFFSET).min(len - 1);
-                    locked_angle_table.insert(
-                        PATH_DIRECTION_COST_NAME.to_owned(),
-                        result[look_ahead_idx][2],
-                    );
+                    locked_angle_table.insert(LayerId::PATH_DIRECTION, result[look_ahead_idx][2]);
                 }
             }
 
@@ -182,35 +205,52 @@ fn main() {
                 let locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
                 let locked_angle_table = cloned_nav.angle_table.lock().unwrap();
                 let locked_planner = cloned_nav.planner.lock().unwrap();
+                let candidates =
+                    locked_planner.generate_candidates(&current_pose, &current_velocity);
                 (
-                    locked_planner.plan_local_path(
-                        &current_pose,
-                        &current_velocity,
+                    DwaPlanner::score_candidates(
+                        &candidates,
                         &locked_layered_grid_map,
                         &locked_angle_table,
+                        locked_planner.map_name_weight(),
                     ),
-                    locked_planner.predicted_plan_candidates(&current_pose, &current_velocity),
+                    candidates,
                 )
             };
             {
-                let mut locked_robot_path = cloned_nav.robot_path.lock().unwrap();
-                locked_robot_path.set_local_path(RobotPath(plan.path.clone()));
-                for (i, candidate) in candidates.iter().enumerate() {
-                    locked_robot_path.add_user_defined_path(
-                        &format!("candidate_{}", i),
-                        RobotPath(candidate.path.clone()),
-                    );
-                }
+                let locked_layered_grid_map = cloned_nav.layered_grid_map.lock().unwrap();
+                let locked_angle_table = cloned_nav.angle_table.lock().unwrap();
+                let locked_planner = cloned_nav.planner.lock().unwrap();
+                *cloned_nav.candidates.lock().unwrap() = candidates
+                    .into_iter()
+                    .map(|mut c| {
+                        c.cost = DwaPlanner::score_plan(
+                            &c,
+                            &locked_layered_grid_map,
+                            &locked_angle_table,
+                            locked_planner.map_name_weight(),
+                        );
+                        c
+                    })
+                    .collect();
             }
+            cloned_nav
+                .robot_path
+                .lock()
+                .unwrap()
+                .set_local_path(RobotPath(plan.path.clone()));
+            cloned_nav.record_cycle();
 
             current_velocity = plan.velocity;
-            current_pose = plan.path[0];
+            let dt = cloned_nav.planner.lock().unwrap().controller_dt();
+            current_pose = odometry.integrate(&current_velocity, dt).pose;
 
             {
                 let mut locked_robot_pose = cloned_nav.robot_pose.lock().unwrap();
                 *locked_robot_pose = current_pose;
             }
-            std::thread::sleep(std::time::Duration::from_millis(50));
+            let speed = cloned_nav.loop_control.lock().unwrap().speed;
+            std::thread::sleep(std::time::Duration::from_secs_f64(CONTROL_PERIOD / speed));
 
             if let Some(grid) =
                 plan_map.to_grid(current_pose.translation.x, current_pose.translation.y)
@@ -220,13 +260,7 @@ fn main() {
                 println!("OUT OF MAP!");
                 return;
             }
-            const GOAL_THRESHOLD_DISTANCE: f64 = 0.1;
-            const GOAL_THRESHOLD_ANGLE_DIFFERENCE: f64 = 0.4;
-            if (goal_pose.translation.vector - current_pose.translation.vector).norm()
-                < GOAL_THRESHOLD_DISTANCE
-                && (goal_pose.rotation.angle() - current_pose.rotation.angle()).abs()
-                    < GOAL_THRESHOLD_ANGLE_DIFFERENCE
-            {
+            if goal_checker.is_reached(&goal_pose.into(), &current_pose, &current_velocity) {
                 println!("GOAL! count = {i}");
                 break;
             }
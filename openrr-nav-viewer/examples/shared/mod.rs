@@ -1,7 +1,7 @@
 use clap::Parser;
 use grid_map::*;
 use nalgebra as na;
-use openrr_nav::*;
+use openrr_nav_core::*;
 use openrr_nav_viewer::NavigationViz;
 
 #[derive(Debug, Parser)]
@@ -11,18 +11,21 @@ pub struct Args {
         long = "config-file",
         default_value = concat!(
             env!("CARGO_MANIFEST_DIR"),
-            "/../openrr-nav/config/dwa_parameter_config.yaml"
+            "/../openrr-nav-core/config/dwa_parameter_config.yaml"
         ),
         env = "PLANNER_CONFIG_PATH",
         help = "planner config file path"
     )]
     pub planner_config_path: String,
+    /// Seed of the random sampling of the global planner
+    #[clap(long, default_value_t = 0)]
+    pub seed: u64,
 }
 
 impl TryFrom<Args> for NavigationViz {
-    type Error = openrr_nav::Error;
+    type Error = openrr_nav_core::Error;
 
-    fn try_from(value: Args) -> openrr_nav::Result<Self> {
+    fn try_from(value: Args) -> openrr_nav_core::Result<Self> {
         NavigationViz::new(&value.planner_config_path)
     }
 }
@@ -51,61 +54,45 @@ pub(crate) fn robot_path_from_vec_vec(path: Vec<Vec<f64>>) -> RobotPath {
     RobotPath(robot_path_inner)
 }
 
-pub(crate) fn linear_interpolate_path(path: Vec<Vec<f64>>, extend_length: f64) -> Vec<Vec<f64>> {
-    if path.len() < 2 {
-        return path;
-    }
-    let mut interpolated_path = vec![];
-    for (p0, p1) in path.iter().zip(path.iter().skip(1)) {
-        let diff_x = p1[0] - p0[0];
-        let diff_y = p1[1] - p0[1];
-        let diff = (diff_x.powi(2) + diff_y.powi(2)).sqrt();
-        let direction = diff_y.atan2(diff_x);
-        let interpolate_num = (diff / extend_length) as usize;
-        if interpolate_num > 0 {
-            let unit_diff_x = diff_x / interpolate_num as f64;
-            let unit_diff_y = diff_y / interpolate_num as f64;
-            for j in 1..interpolate_num {
-                interpolated_path.push(vec![
-                    p0[0] + unit_diff_x * j as f64,
-                    p0[1] + unit_diff_y * j as f64,
-                    direction,
-                ]);
-            }
-        } else {
-            interpolated_path.push({
-                let mut p = p0.to_owned();
-                p.push(direction);
-                p
-            });
-        }
-    }
-    let last_point_angle = interpolated_path.last().unwrap()[2];
-    interpolated_path.push({
-        let mut end_path = path.last().unwrap().clone();
-        end_path.push(last_point_angle);
-        end_path
-    });
-    interpolated_path
+/// Global planners of the examples, of which `rrt` (active) samples with the seed
+pub(crate) fn global_planners(seed: u64) -> GlobalPlannerRegistry {
+    let mut planners = GlobalPlannerRegistry::default();
+    planners.register(
+        "rrt",
+        RrtPlanner {
+            seed,
+            ..Default::default()
+        },
+    );
+    planners.set_active("rrt").unwrap();
+    planners
 }
 
-pub(crate) fn add_target_position_to_path(
-    path: Vec<Vec<f64>>,
-    target_pose: &Pose,
-) -> Vec<Vec<f64>> {
-    let mut p = path.clone();
-    let target_pose_vec = vec![
-        target_pose.translation.x,
-        target_pose.translation.y,
-        target_pose.rotation.angle(),
-    ];
-    match p.last_mut() {
-        Some(v) => {
-            *v = target_pose_vec;
-        }
-        None => {
-            p.push(target_pose_vec);
-        }
-    }
-    p
+/// Plan the global path `[x, y, theta]` with the active planner, keeping away from
+/// the obstacles by the inflation layer of the default [`NavigatorConfig`]
+pub(crate) fn plan_global_path(
+    planners: &GlobalPlannerRegistry,
+    map: &GridMap<u8>,
+    start: [f64; 3],
+    goal: [f64; 3],
+) -> openrr_nav_core::Result<Vec<Vec<f64>>> {
+    const EXTEND_LENGTH: f64 = 0.05;
+    let config = NavigatorConfig::default();
+    let inflation = inflate_obstacles(
+        map,
+        config.inscribed_radius,
+        config.inflation_radius,
+        config.cost_scaling,
+    )?;
+    let poses = planners.plan_with_cost(
+        map,
+        Some(&inflation),
+        &Pose::new(na::Vector2::new(start[0], start[1]), start[2]),
+        &Pose::new(na::Vector2::new(goal[0], goal[1]), goal[2]),
+    )?;
+    let path = poses
+        .iter()
+        .map(|p| vec![p.translation.x, p.translation.y, p.rotation.angle()])
+        .collect::<Vec<_>>();
+    Ok(path::densify_path(&path, EXTEND_LENGTH))
 }
@@ -1,27 +1,27 @@
 use bevy::{
     prelude::*,
+    render::view::screenshot::ScreenshotManager,
+    window::PrimaryWindow,
     winit::{UpdateMode, WinitSettings},
 };
 use bevy_egui::{
     egui::{
         self,
-        plot::{Line, Plot, PlotPoints, Polygon},
+        plot::{Legend, Line, Plot, PlotPoint, PlotPoints, Points, Polygon},
         Color32,
     },
     EguiContexts, EguiPlugin,
 };
+use grid_map::LayerId;
+use image::RgbaImage;
 use nalgebra::Vector2;
-use openrr_nav::Pose;
+use openrr_nav_core::{
+    DwaParameters, Footprint, Plan, Pose, PoseStdDev, PoseWithCovariance, RobotPath,
+};
 
-use crate::*;
+use std::sync::{Arc, Mutex};
 
-pub const PATH_DISTANCE_MAP_NAME: &str = "path";
-pub const GOAL_DISTANCE_MAP_NAME: &str = "goal";
-pub const OBSTACLE_DISTANCE_MAP_NAME: &str = "obstacle";
-pub const LOCAL_GOAL_DISTANCE_MAP_NAME: &str = "local_goal";
-pub const ROTATION_COST_NAME: &str = "rotation";
-pub const PATH_DIRECTION_COST_NAME: &str = "path_direction";
-pub const GOAL_DIRECTION_COST_NAME: &str = "goal_direction";
+use crate::*;
 
 pub const DEFAULT_PATH_DISTANCE_WEIGHT: f64 = 0.8;
 pub const DEFAULT_GOAL_DISTANCE_WEIGHT: f64 = 0.1;
@@ -35,6 +35,8 @@ pub const DEFAULT_GOAL_DIRECTION_COST_WEIGHT: f64 = 0.01;
 pub struct UiCheckboxes {
     pub set_start: bool,
     pub set_goal: bool,
+    pub set_initial_pose: bool,
+    pub edit_map: bool,
     pub restart: bool,
     pub counter: usize,
 }
@@ -44,6 +46,8 @@ impl Default for UiCheckboxes {
         Self {
             set_start: false,
             set_goal: false,
+            set_initial_pose: false,
+            edit_map: false,
             restart: true,
             counter: 0,
         }
@@ -77,6 +81,159 @@ impl DisplayedArrows {
     }
 }
 
+/// Start or goal marker on the map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    Start,
+    Goal,
+}
+
+/// State of dragging the start/goal markers
+#[derive(Debug, Default, Resource)]
+pub struct MarkerDrag {
+    hovered: Option<Marker>,
+    dragging: Option<Marker>,
+}
+
+impl MarkerDrag {
+    pub fn dragging(&self) -> Option<Marker> {
+        self.dragging
+    }
+}
+
+/// Scenarios selectable in the viewer
+#[derive(Debug, Resource)]
+pub struct ScenarioGallery {
+    pub scenarios: Vec<Scenario>,
+    pub selected: usize,
+}
+
+impl Default for ScenarioGallery {
+    fn default() -> Self {
+        Self {
+            scenarios: builtin_scenarios(),
+            selected: 0,
+        }
+    }
+}
+
+/// Brush of the "Edit map" mode
+#[derive(Debug, Resource)]
+pub struct MapBrush {
+    /// (m)
+    pub radius: f64,
+    painting: bool,
+    /// Error of the last edit, shown in the panel
+    error: Option<String>,
+}
+
+impl Default for MapBrush {
+    fn default() -> Self {
+        Self {
+            radius: 0.1,
+            painting: false,
+            error: None,
+        }
+    }
+}
+
+/// Parameters of the planner edited in the panel, applied by "Apply"
+#[derive(Debug, Default, Resource)]
+pub struct PlannerEditor {
+    /// Parameters of the planner when the edit is started
+    base: DwaParameters,
+    draft: DwaParameters,
+    error: Option<String>,
+}
+
+/// What the dialog of the File menu does with the path
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FileAction {
+    #[default]
+    OpenMap,
+    LoadScenario,
+    SaveScenario,
+    OpenSession,
+    RecordSession,
+    ExportPng,
+    RecordGif,
+    RecordMp4,
+}
+
+impl FileAction {
+    fn title(self) -> &'static str {
+        match self {
+            Self::OpenMap => "Open map",
+            Self::LoadScenario => "Load scenario",
+            Self::SaveScenario => "Save scenario",
+            Self::OpenSession => "Open session",
+            Self::RecordSession => "Record session",
+            Self::ExportPng => "Screenshot (PNG)",
+            Self::RecordGif => "Record GIF",
+            Self::RecordMp4 => "Record MP4",
+        }
+    }
+
+    fn writes(self) -> bool {
+        !matches!(self, Self::OpenMap | Self::LoadScenario | Self::OpenSession)
+    }
+
+    fn hint(self) -> &'static str {
+        match self {
+            Self::OpenMap => "ROS map yaml (.yaml, .yml) or GridMap saved by save_to_file",
+            Self::LoadScenario | Self::SaveScenario => {
+                "yaml of the map, the start, the goal, the weights and the planner parameters"
+            }
+            Self::OpenSession | Self::RecordSession => "log of the planning cycles",
+            Self::ExportPng => "PNG of the map plot",
+            Self::RecordGif => "animated GIF of the map plot, recorded until \"Stop\"",
+            Self::RecordMp4 => "MP4 of the map plot encoded by ffmpeg, recorded until \"Stop\"",
+        }
+    }
+}
+
+/// Dialog of the path opened from the File menu
+#[derive(Debug, Default, Resource)]
+pub struct FileDialog {
+    pub open: bool,
+    pub action: FileAction,
+    pub path: String,
+    pub error: Option<String>,
+    /// Export chosen in the dialog, taken by the export system
+    pub pending_export: Option<(FileAction, String)>,
+}
+
+/// Frames of the animation captured in the export
+const ANIMATION_FPS: u32 = 10;
+/// 1 minute
+const MAX_ANIMATION_FRAMES: usize = 600;
+
+/// Animation of the map plot being recorded
+#[derive(Debug)]
+struct AnimationRecording {
+    action: FileAction,
+    path: String,
+    /// Captured in the screenshot callbacks
+    frames: Arc<Mutex<Vec<RgbaImage>>>,
+    /// Pixels of the plot in the window: x, y, width and height
+    rect: [u32; 4],
+    /// (s) of egui
+    last_capture: f64,
+}
+
+/// Screenshots and animations of the map plot exported from the Export menu
+#[derive(Debug, Default, Resource)]
+pub struct Exporter {
+    animation: Option<AnimationRecording>,
+    /// Result of the last export, set by the other threads
+    status: Arc<Mutex<Option<String>>>,
+}
+
+/// Distance in pixels to grab a marker
+const MARKER_GRAB_RADIUS: f32 = 12.0;
+/// Distance in pixels to drag for the heading of the goal
+const MIN_HEADING_DRAG: f32 = 5.0;
+
 #[derive(Debug, Default)]
 pub struct BevyAppNav {
     app: App,
@@ -97,9 +254,15 @@ impl BevyAppNav {
             ..Default::default()
         });
 
-        let map_type = MapType::default();
+        let layer_display_settings = LayerDisplaySettings::default();
         let ui_checkboxes = UiCheckboxes::default();
         let displayed_arrows = DisplayedArrows::default();
+        let marker_drag = MarkerDrag::default();
+        let scenario_gallery = ScenarioGallery::default();
+        let file_dialog = FileDialog::default();
+        let map_brush = MapBrush::default();
+        let planner_editor = PlannerEditor::default();
+        let exporter = Exporter::default();
 
         // Refs:
         // - https://github.com/bevyengine/bevy/blob/HEAD/examples/window/low_power.rs
@@ -116,15 +279,23 @@ impl BevyAppNav {
 
         self.app
             .insert_resource(nav)
-            .insert_resource(map_type)
+            .insert_resource(layer_display_settings)
             .insert_resource(ui_checkboxes)
             .insert_resource(displayed_arrows)
+            .insert_resource(marker_drag)
+            .insert_resource(scenario_gallery)
+            .insert_resource(file_dialog)
+            .insert_resource(map_brush)
+            .insert_resource(planner_editor)
+            .insert_resource(exporter)
             .insert_resource(winit_settings)
             .add_plugins(user_plugin)
             .add_plugins(EguiPlugin)
             .add_systems(Update, ui_system)
             .add_systems(Update, update_system)
-            .add_systems(Update, bottom_monitor_system);
+            .add_systems(Update, bottom_monitor_system)
+            .add_systems(Update, velocity_space_system)
+            .add_systems(Update, export_system);
     }
 
     pub fn run(&mut self) {
@@ -135,47 +306,66 @@ impl BevyAppNav {
 fn update_system(
     mut contexts: EguiContexts<'_, '_>,
     res_nav: Res<'_, NavigationViz>,
-    map_type: Res<'_, MapType>,
+    layer_display_settings: Res<'_, LayerDisplaySettings>,
     mut ui_checkboxes: ResMut<'_, UiCheckboxes>,
     mut displayed_arrows: ResMut<'_, DisplayedArrows>,
+    mut marker_drag: ResMut<'_, MarkerDrag>,
+    mut map_brush: ResMut<'_, MapBrush>,
 ) {
     let ctx = contexts.ctx_mut();
 
     egui::CentralPanel::default().show(ctx, |ui| {
-        Plot::new("Map").data_aspect(1.).show(ui, |plot_ui| {
-            // Plot map
-            let map = res_nav.layered_grid_map.lock().unwrap();
-            match map_type.as_ref() {
-                MapType::PathDistanceMap => {
-                    if let Some(dist_map) = map.layer(PATH_DISTANCE_MAP_NAME) {
-                        for p in grid_map_to_polygon(dist_map) {
-                            plot_ui.polygon(p);
-                        }
-                    }
-                }
-                MapType::GoalDistanceMap => {
-                    if let Some(dist_map) = map.layer(GOAL_DISTANCE_MAP_NAME) {
-                        for p in grid_map_to_polygon(dist_map) {
-                            plot_ui.polygon(p);
-                        }
-                    }
-                }
-                MapType::ObstacleDistanceMap => {
-                    if let Some(dist_map) = map.layer(OBSTACLE_DISTANCE_MAP_NAME) {
-                        for p in grid_map_to_polygon(dist_map) {
-                            plot_ui.polygon(p);
-                        }
+        // Don't pan the plot while grabbing a marker or painting the map
+        let allow_drag = marker_drag.hovered.is_none()
+            && marker_drag.dragging.is_none()
+            && !ui_checkboxes.set_initial_pose
+            && !ui_checkboxes.edit_map;
+        let plot = Plot::new("Map")
+            .data_aspect(1.)
+            .allow_drag(allow_drag)
+            .allow_boxed_zoom(!ui_checkboxes.edit_map);
+        let response = plot.show(ui, |plot_ui| {
+            // Edit map: left-drag paints obstacles and right-drag clears them
+            if ui_checkboxes.edit_map {
+                let (primary, secondary) =
+                    ctx.input(|i| (i.pointer.primary_down(), i.pointer.secondary_down()));
+                if let Some(p) = plot_ui.pointer_coordinate() {
+                    if (primary || secondary) && plot_ui.plot_hovered() {
+                        let center = grid_map::Position::new(p.x, p.y);
+                        map_brush.error = res_nav
+                            .edit_map(&center, map_brush.radius, primary)
+                            .err()
+                            .map(|e| format!("failed to edit the map: {e}"));
+                        map_brush.painting = true;
                     }
+                    plot_ui.line(
+                        Line::new(circle_points([p.x, p.y], map_brush.radius, 36))
+                            .color(Color32::WHITE),
+                    );
                 }
-                MapType::LocalGoalDistanceMap => {
-                    if let Some(dist_map) = map.layer(LOCAL_GOAL_DISTANCE_MAP_NAME) {
-                        for p in grid_map_to_polygon(dist_map) {
-                            plot_ui.polygon(p);
-                        }
-                    }
+                if map_brush.painting && !primary && !secondary {
+                    // Replan on release
+                    map_brush.painting = false;
+                    res_nav.finish_map_edit();
                 }
             }
 
+            // Plot map
+            let map = res_nav.layered_grid_map.lock().unwrap();
+            let layers = layer_display_settings
+                .visible_layers()
+                .filter_map(|(map_type, opacity)| {
+                    map.layer(map_type.layer_id()).map(|m| (m, opacity))
+                })
+                .collect::<Vec<_>>();
+            for p in blended_grid_maps_to_polygon(
+                &layers,
+                layer_display_settings.blend_mode,
+                layer_display_settings.colormap,
+            ) {
+                plot_ui.polygon(p);
+            }
+
             // Plot path
             let path = res_nav.robot_path.lock().unwrap();
             plot_ui.line(robot_path_to_line(path.global_path(), Color32::BLUE, 10.));
@@ -184,9 +374,41 @@ fn update_system(
                 plot_ui.line(robot_path_to_line(p, Color32::LIGHT_YELLOW, 3.));
             }
 
-            // Plot robot pose
+            // Plot candidates colored by the cost, the selected one on top
+            if layer_display_settings.show_candidates {
+                let candidates = res_nav.candidates.lock().unwrap();
+                let range = cost_range(&candidates);
+                for candidate in candidates.iter() {
+                    plot_ui.line(robot_path_to_line(
+                        &RobotPath(candidate.path.clone()),
+                        candidate_color(candidate.cost, range),
+                        2.,
+                    ));
+                }
+                if let Some(selected) = selected_candidate(&candidates) {
+                    plot_ui.line(
+                        robot_path_to_line(&RobotPath(selected.path.clone()), Color32::WHITE, 5.)
+                            .name(format!("selected (cost {:.2})", selected.cost)),
+                    );
+                }
+            }
+
+            // Plot robot footprint and heading
+            let footprint = res_nav.footprint.lock().unwrap();
+            if layer_display_settings.sweep_footprint {
+                let local_path = &path.local_path().0;
+                // About 10 footprints along the plan
+                for p in local_path.iter().step_by(local_path.len() / 10 + 1) {
+                    plot_ui.line(footprint_to_line(&footprint, p, Color32::LIGHT_RED));
+                }
+            }
             let pose = res_nav.robot_pose.lock().unwrap();
-            plot_ui.polygon(robot_pose_to_polygon(&pose, Color32::DARK_RED, 1.));
+            plot_ui.polygon(footprint_to_polygon(&footprint, &pose, Color32::DARK_RED));
+            plot_ui.line(heading_to_line(
+                &pose,
+                Color32::WHITE,
+                1.5 * footprint_front(&footprint),
+            ));
 
             let pointer_coordinate = plot_ui.pointer_coordinate();
 
@@ -222,69 +444,280 @@ fn update_system(
                 }
             }
 
+            // Set goal: press at the position and drag toward the heading
             if let Some(p) = pointer_coordinate {
                 if ui_checkboxes.set_goal
                     && ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Primary))
-                    && ui_checkboxes.counter == 1
+                    && !ctx.is_pointer_over_area()
+                    && ui_checkboxes.counter == 0
                 {
-                    ui_checkboxes.set_goal = false;
+                    ui_checkboxes.counter = 1;
+                    displayed_arrows.set_start([p.x, p.y]);
+                }
+                if ui_checkboxes.set_goal && ui_checkboxes.counter == 1 {
+                    displayed_arrows.set_end([p.x, p.y]);
+                }
+            }
+            if ui_checkboxes.set_goal
+                && ui_checkboxes.counter == 1
+                && !ctx.input(|i| i.pointer.primary_down())
+            {
+                if let Some([from, to]) = displayed_arrows.0 {
                     let mut goal_position = res_nav.goal_position.lock().unwrap();
-                    let angle = (p.y - goal_position.translation.y)
-                        .atan2(p.x - goal_position.translation.x);
-                    *goal_position = Pose::new(
-                        Vector2::new(goal_position.translation.x, goal_position.translation.y),
-                        angle,
-                    );
+                    let drag = plot_ui
+                        .screen_from_plot(PlotPoint::new(from[0], from[1]))
+                        .distance(plot_ui.screen_from_plot(PlotPoint::new(to[0], to[1])));
+                    // Keep the heading if it is clicked without dragging
+                    let angle = if drag < MIN_HEADING_DRAG {
+                        goal_position.rotation.angle()
+                    } else {
+                        (to[1] - from[1]).atan2(to[0] - from[0])
+                    };
+                    *goal_position = Pose::new(Vector2::new(from[0], from[1]), angle);
                     println!("goal: {:?}", goal_position);
-                    let mut is_run = res_nav.is_run.lock().unwrap();
-                    *is_run = true;
-                    ui_checkboxes.counter = 0;
-                    displayed_arrows.0 = None;
+                    *res_nav.is_run.lock().unwrap() = true;
                 }
-                if ui_checkboxes.set_goal
+                ui_checkboxes.set_goal = false;
+                ui_checkboxes.counter = 0;
+                displayed_arrows.0 = None;
+            }
+
+            // Set initial pose: press at the position and drag toward the heading
+            if let Some(p) = pointer_coordinate {
+                if ui_checkboxes.set_initial_pose
                     && ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Primary))
                     && !ctx.is_pointer_over_area()
                     && ui_checkboxes.counter == 0
                 {
-                    let mut goal_position = res_nav.goal_position.lock().unwrap();
-                    *goal_position = Pose::new(Vector2::new(p.x, p.y), 0.0);
                     ui_checkboxes.counter = 1;
                     displayed_arrows.set_start([p.x, p.y]);
                 }
-                if ui_checkboxes.set_goal && ui_checkboxes.counter == 1 {
+                if ui_checkboxes.set_initial_pose && ui_checkboxes.counter == 1 {
                     displayed_arrows.set_end([p.x, p.y]);
                 }
             }
+            if ui_checkboxes.set_initial_pose
+                && ui_checkboxes.counter == 1
+                && !ctx.input(|i| i.pointer.primary_down())
+            {
+                if let Some([from, to]) = displayed_arrows.0 {
+                    let angle = (to[1] - from[1]).atan2(to[0] - from[0]);
+                    let pose = Pose::new(Vector2::new(from[0], from[1]), angle);
+                    let std_dev = res_nav.initial_pose_std_dev.lock().unwrap();
+                    let initial_pose = PoseWithCovariance::from_std_dev(pose, &std_dev);
+                    *res_nav.initial_pose.lock().unwrap() = Some(initial_pose);
+                }
+                ui_checkboxes.set_initial_pose = false;
+                ui_checkboxes.counter = 0;
+                displayed_arrows.0 = None;
+            }
+            if let Some(initial_pose) = *res_nav.initial_pose.lock().unwrap() {
+                plot_ui.line(
+                    Line::new(PlotPoints::new(initial_pose.position_ellipse(2.0, 36)))
+                        .color(Color32::LIGHT_BLUE)
+                        .name("initial pose (2σ)"),
+                );
+                plot_ui.polygon(robot_pose_to_polygon(
+                    &initial_pose.pose,
+                    Color32::LIGHT_BLUE,
+                    1.,
+                ));
+            }
+
+            if let Some([from, to]) = displayed_arrows.0 {
+                plot_ui.line(Line::new(arrow_points(from, to)).width(2.));
+            }
 
-            match &displayed_arrows.0 {
-                Some(p) => {
-                    plot_ui.line(Line::new(PlotPoints::new(vec![p[0], p[1]])));
+            // Plot start/goal markers and drag them
+            let mut start_position = res_nav.start_position.lock().unwrap();
+            let mut goal_position = res_nav.goal_position.lock().unwrap();
+            plot_ui.points(marker_to_points(&start_position, Color32::GREEN, "start"));
+            plot_ui.points(marker_to_points(&goal_position, Color32::GOLD, "goal"));
+            plot_ui.line(heading_to_line(&goal_position, Color32::GOLD, 0.3));
+            let pointer = ctx.input(|i| i.pointer.hover_pos());
+            let setting_mode = ui_checkboxes.set_start
+                || ui_checkboxes.set_goal
+                || ui_checkboxes.set_initial_pose
+                || ui_checkboxes.edit_map;
+            marker_drag.hovered = match pointer {
+                Some(pointer) if !setting_mode && marker_drag.dragging.is_none() => {
+                    let distance = |pose: &Pose| {
+                        plot_ui
+                            .screen_from_plot(PlotPoint::new(
+                                pose.translation.x,
+                                pose.translation.y,
+                            ))
+                            .distance(pointer)
+                    };
+                    [
+                        (Marker::Start, distance(&start_position)),
+                        (Marker::Goal, distance(&goal_position)),
+                    ]
+                    .into_iter()
+                    .filter(|(_, d)| *d < MARKER_GRAB_RADIUS)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(marker, _)| marker)
+                }
+                _ => None,
+            };
+            if marker_drag.hovered.is_some()
+                && plot_ui.plot_hovered()
+                && ctx.input(|i| i.pointer.button_pressed(egui::PointerButton::Primary))
+            {
+                marker_drag.dragging = marker_drag.hovered;
+            }
+            if let (Some(marker), Some(pointer)) = (marker_drag.dragging, pointer) {
+                let p = plot_ui.plot_from_screen(pointer);
+                let pose = match marker {
+                    Marker::Start => &mut *start_position,
+                    Marker::Goal => &mut *goal_position,
+                };
+                *pose = Pose::new(Vector2::new(p.x, p.y), pose.rotation.angle());
+                if !ctx.input(|i| i.pointer.primary_down()) {
+                    // Replan on release
+                    marker_drag.dragging = None;
+                    *res_nav.is_run.lock().unwrap() = true;
                 }
-                None => {}
             }
         });
+        // Cropped from the window by the export
+        ctx.data_mut(|d| d.insert_temp(map_plot_rect_id(), response.response.rect));
     });
 }
 
 fn ui_system(
     mut contexts: EguiContexts<'_, '_>,
     res_nav: Res<'_, NavigationViz>,
-    mut map_type: ResMut<'_, MapType>,
+    mut layer_display_settings: ResMut<'_, LayerDisplaySettings>,
     mut ui_checkboxes: ResMut<'_, UiCheckboxes>,
+    mut scenario_gallery: ResMut<'_, ScenarioGallery>,
+    mut file_dialog: ResMut<'_, FileDialog>,
+    mut map_brush: ResMut<'_, MapBrush>,
 ) {
     let ctx = contexts.ctx_mut();
 
+    egui::TopBottomPanel::top("menu").show(ctx, |ui| {
+        egui::menu::bar(ui, |ui| {
+            ui.menu_button("File", |m_ui| {
+                let recording = res_nav.session_recorder.lock().unwrap().is_some();
+                for action in [
+                    FileAction::OpenMap,
+                    FileAction::LoadScenario,
+                    FileAction::SaveScenario,
+                    FileAction::OpenSession,
+                    FileAction::RecordSession,
+                ] {
+                    if action == FileAction::RecordSession && recording {
+                        if m_ui.button("Stop recording").clicked() {
+                            res_nav.stop_recording();
+                            m_ui.close_menu();
+                        }
+                    } else if m_ui.button(format!("{}...", action.title())).clicked() {
+                        file_dialog.open = true;
+                        file_dialog.action = action;
+                        file_dialog.error = None;
+                        m_ui.close_menu();
+                    }
+                }
+            });
+            ui.menu_button("Export", |m_ui| {
+                for action in [
+                    FileAction::ExportPng,
+                    FileAction::RecordGif,
+                    FileAction::RecordMp4,
+                ] {
+                    if m_ui.button(format!("{}...", action.title())).clicked() {
+                        file_dialog.open = true;
+                        file_dialog.action = action;
+                        file_dialog.error = None;
+                        m_ui.close_menu();
+                    }
+                }
+            });
+        });
+    });
+    file_window(ctx, &res_nav, &mut file_dialog);
+    session_window(ctx, &res_nav);
+
     egui::SidePanel::left("left_side_panel")
         .default_width(200.)
         .min_width(200.)
         .show(ctx, |ui| {
-            ui.radio_value(map_type.as_mut(), MapType::PathDistanceMap, "Path");
-            ui.radio_value(map_type.as_mut(), MapType::GoalDistanceMap, "Goal");
-            ui.radio_value(map_type.as_mut(), MapType::ObstacleDistanceMap, "Obstacle");
-            ui.radio_value(
-                map_type.as_mut(),
-                MapType::LocalGoalDistanceMap,
-                "Local Goal",
+            scenario_selector(ui, &res_nav, &mut scenario_gallery);
+            ui.separator();
+            ui.label("");
+
+            for (map_type, style) in layer_display_settings.layers.iter_mut() {
+                ui.horizontal(|h_ui| {
+                    h_ui.add_sized(
+                        [100.0, 30.0],
+                        egui::Checkbox::new(&mut style.visible, map_type.label()),
+                    );
+                    h_ui.spacing_mut().slider_width = 150.;
+                    h_ui.add_enabled(
+                        style.visible,
+                        egui::Slider::new(&mut style.opacity, 0.0..=1.0).text("opacity"),
+                    );
+                });
+            }
+            ui.horizontal(|h_ui| {
+                h_ui.label("blend");
+                h_ui.radio_value(
+                    &mut layer_display_settings.blend_mode,
+                    BlendMode::Max,
+                    "Max",
+                );
+                h_ui.radio_value(
+                    &mut layer_display_settings.blend_mode,
+                    BlendMode::Additive,
+                    "Additive",
+                );
+                h_ui.radio_value(
+                    &mut layer_display_settings.blend_mode,
+                    BlendMode::Difference,
+                    "Difference",
+                );
+            });
+            egui::ComboBox::from_label("colormap")
+                .selected_text(layer_display_settings.colormap.label())
+                .show_ui(ui, |c_ui| {
+                    for colormap in Colormap::ALL {
+                        c_ui.selectable_value(
+                            &mut layer_display_settings.colormap,
+                            colormap,
+                            colormap.label(),
+                        );
+                    }
+                });
+            {
+                let map = res_nav.layered_grid_map.lock().unwrap();
+                let ranges = layer_display_settings
+                    .visible_layers()
+                    .filter_map(|(map_type, _)| {
+                        Some((map_type, value_range(map.layer(map_type.layer_id())?)?))
+                    })
+                    .collect::<Vec<_>>();
+                let colormap = layer_display_settings.colormap;
+                match &ranges[..] {
+                    [(_, a), (_, b), ..]
+                        if layer_display_settings.blend_mode == BlendMode::Difference =>
+                    {
+                        colormap_legend(ui, "difference", colormap, difference_range(*a, *b));
+                    }
+                    _ => {
+                        for (map_type, range) in ranges {
+                            colormap_legend(ui, map_type.label(), colormap, range);
+                        }
+                    }
+                }
+            }
+            ui.checkbox(
+                &mut layer_display_settings.show_candidates,
+                "candidate trajectories (blue: low cost, red: high cost)",
+            );
+            ui.checkbox(
+                &mut layer_display_settings.sweep_footprint,
+                "footprint along the local plan",
             );
             ui.label("");
             ui.separator();
@@ -298,6 +731,8 @@ fn ui_system(
                     {
                         ui_checkboxes.set_start = !ui_checkboxes.set_start;
                         ui_checkboxes.set_goal = false;
+                        ui_checkboxes.set_initial_pose = false;
+                        ui_checkboxes.edit_map = false;
                         ui_checkboxes.counter = 0;
                     }
                     if c_ui[1]
@@ -306,27 +741,72 @@ fn ui_system(
                     {
                         ui_checkboxes.set_goal = !ui_checkboxes.set_goal;
                         ui_checkboxes.set_start = false;
+                        ui_checkboxes.set_initial_pose = false;
+                        ui_checkboxes.edit_map = false;
                         ui_checkboxes.counter = 0;
                     }
                 });
             });
+            if ui
+                .add_sized([200., 30.], egui::Button::new("Set Initial Pose"))
+                .clicked()
+            {
+                ui_checkboxes.set_initial_pose = !ui_checkboxes.set_initial_pose;
+                ui_checkboxes.set_start = false;
+                ui_checkboxes.set_goal = false;
+                ui_checkboxes.edit_map = false;
+                ui_checkboxes.counter = 0;
+            }
+            ui.horizontal(|h_ui| {
+                if h_ui
+                    .add_sized([100., 30.], egui::Button::new("Edit Map"))
+                    .clicked()
+                {
+                    ui_checkboxes.edit_map = !ui_checkboxes.edit_map;
+                    ui_checkboxes.set_start = false;
+                    ui_checkboxes.set_goal = false;
+                    ui_checkboxes.set_initial_pose = false;
+                    ui_checkboxes.counter = 0;
+                }
+                h_ui.add(egui::Slider::new(&mut map_brush.radius, 0.05..=1.0).text("brush [m]"));
+            });
+            if let Some(error) = &map_brush.error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
             ui.colored_label(
                 egui::Color32::RED,
                 if ui_checkboxes.set_start {
                     "Set start  "
                 } else if ui_checkboxes.set_goal {
                     "Set goal   "
+                } else if ui_checkboxes.set_initial_pose {
+                    "Set initial pose"
+                } else if ui_checkboxes.edit_map {
+                    "Edit map (left: paint, right: erase)"
                 } else {
                     "Choose mode"
                 },
             );
             ui.label("");
 
+            {
+                let mut replan = false;
+                let mut start_position = res_nav.start_position.lock().unwrap();
+                replan |= pose_editor(ui, "start", &mut start_position);
+                let mut goal_position = res_nav.goal_position.lock().unwrap();
+                replan |= pose_editor(ui, "goal", &mut goal_position);
+                if replan {
+                    *res_nav.is_run.lock().unwrap() = true;
+                }
+            }
+            std_dev_editor(ui, &mut res_nav.initial_pose_std_dev.lock().unwrap());
+            ui.label("");
+
             {
                 let mut planner = res_nav.planner.lock().unwrap();
                 let weight = planner.map_name_weight_mut();
                 let mut path_weight = weight
-                    .get(PATH_DISTANCE_MAP_NAME)
+                    .get(&LayerId::PATH)
                     .copied()
                     .unwrap_or(DEFAULT_PATH_DISTANCE_WEIGHT)
                     as f32;
@@ -336,7 +816,7 @@ fn ui_system(
                     h_ui.add(egui::Slider::new(&mut path_weight, 0.0..=1.0));
                 });
                 let mut goal_weight = weight
-                    .get(GOAL_DISTANCE_MAP_NAME)
+                    .get(&LayerId::GOAL)
                     .copied()
                     .unwrap_or(DEFAULT_GOAL_DISTANCE_WEIGHT)
                     as f32;
@@ -346,7 +826,7 @@ fn ui_system(
                     h_ui.add(egui::Slider::new(&mut goal_weight, 0.0..=1.0));
                 });
                 let mut obstacle_weight = weight
-                    .get(OBSTACLE_DISTANCE_MAP_NAME)
+                    .get(&LayerId::OBSTACLE)
                     .copied()
                     .unwrap_or(DEFAULT_OBSTACLE_DISTANCE_WEIGHT)
                     as f32;
@@ -356,7 +836,7 @@ fn ui_system(
                     h_ui.add(egui::Slider::new(&mut obstacle_weight, 0.0..=1.0));
                 });
                 let mut local_goal_weight = weight
-                    .get(LOCAL_GOAL_DISTANCE_MAP_NAME)
+                    .get(&LayerId::LOCAL_GOAL)
                     .copied()
                     .unwrap_or(DEFAULT_LOCAL_GOAL_DISTANCE_MAP_WEIGHT)
                     as f32;
@@ -366,7 +846,7 @@ fn ui_system(
                     h_ui.add(egui::Slider::new(&mut local_goal_weight, 0.0..=1.0));
                 });
                 let mut rotation_cost_weight = weight
-                    .get(ROTATION_COST_NAME)
+                    .get(&LayerId::ROTATION)
                     .copied()
                     .unwrap_or(DEFAULT_ROTATION_COST_WEIGHT)
                     as f32;
@@ -376,7 +856,7 @@ fn ui_system(
                     h_ui.add(egui::Slider::new(&mut rotation_cost_weight, 0.0..=1.0));
                 });
                 let mut path_direction_cost_weight = weight
-                    .get(PATH_DIRECTION_COST_NAME)
+                    .get(&LayerId::PATH_DIRECTION)
                     .copied()
                     .unwrap_or(DEFAULT_PATH_DIRECTION_COST_WEIGHT)
                     as f32;
@@ -389,7 +869,7 @@ fn ui_system(
                     ));
                 });
                 let mut goal_direction_cost_weight = weight
-                    .get(GOAL_DIRECTION_COST_NAME)
+                    .get(&LayerId::GOAL_DIRECTION)
                     .copied()
                     .unwrap_or(DEFAULT_GOAL_DIRECTION_COST_WEIGHT)
                     as f32;
@@ -427,31 +907,21 @@ fn ui_system(
                         *is_run = true;
                     }
                 });
+                loop_controller(ui, &mut res_nav.loop_control.lock().unwrap());
 
-                weight.insert(PATH_DISTANCE_MAP_NAME.to_owned(), path_weight as f64);
-                weight.insert(GOAL_DISTANCE_MAP_NAME.to_owned(), goal_weight as f64);
-                weight.insert(
-                    OBSTACLE_DISTANCE_MAP_NAME.to_owned(),
-                    obstacle_weight as f64,
-                );
-                weight.insert(
-                    LOCAL_GOAL_DISTANCE_MAP_NAME.to_owned(),
-                    local_goal_weight as f64,
-                );
-                weight.insert(ROTATION_COST_NAME.to_owned(), rotation_cost_weight as f64);
-                weight.insert(
-                    PATH_DIRECTION_COST_NAME.to_owned(),
-                    path_direction_cost_weight as f64,
-                );
-                weight.insert(
-                    GOAL_DIRECTION_COST_NAME.to_owned(),
-                    goal_direction_cost_weight as f64,
-                );
+                weight.insert(LayerId::PATH, path_weight as f64);
+                weight.insert(LayerId::GOAL, goal_weight as f64);
+                weight.insert(LayerId::OBSTACLE, obstacle_weight as f64);
+                weight.insert(LayerId::LOCAL_GOAL, local_goal_weight as f64);
+                weight.insert(LayerId::ROTATION, rotation_cost_weight as f64);
+                weight.insert(LayerId::PATH_DIRECTION, path_direction_cost_weight as f64);
+                weight.insert(LayerId::GOAL_DIRECTION, goal_direction_cost_weight as f64);
             }
             ui.label("");
             ui.separator();
             ui.label("");
 
+            global_planner_selector(ui, &res_nav);
             if ui
                 .add_sized([200., 30.], egui::Button::new("Reload planner config"))
                 .clicked()
@@ -461,12 +931,738 @@ fn ui_system(
         });
 }
 
+/// Switch the active planner of the registry, which restarts the run
+fn global_planner_selector(ui: &mut egui::Ui, nav: &NavigationViz) {
+    let mut planners = nav.global_planners.lock().unwrap();
+    let mut selected = planners.active().unwrap_or_default().to_owned();
+    let names = planners.names().map(str::to_owned).collect::<Vec<_>>();
+    egui::ComboBox::from_label("global planner")
+        .selected_text(&selected)
+        .show_ui(ui, |c_ui| {
+            for name in names {
+                c_ui.selectable_value(&mut selected, name.clone(), name);
+            }
+        });
+    if planners.active() != Some(selected.as_str()) && planners.set_active(&selected).is_ok() {
+        drop(planners);
+        *nav.is_run.lock().unwrap() = true;
+    }
+}
+
+fn scenario_selector(ui: &mut egui::Ui, nav: &NavigationViz, gallery: &mut ScenarioGallery) {
+    if gallery.scenarios.is_empty() {
+        return;
+    }
+    let ScenarioGallery {
+        scenarios,
+        selected,
+    } = gallery;
+    ui.horizontal(|h_ui| {
+        egui::ComboBox::from_label("scenario")
+            .selected_text(&scenarios[*selected].name)
+            .show_ui(h_ui, |c_ui| {
+                for (i, scenario) in scenarios.iter().enumerate() {
+                    c_ui.selectable_value(selected, i, &scenario.name);
+                }
+            });
+        if h_ui.button("Load").clicked() {
+            nav.load_scenario(&scenarios[*selected]);
+        }
+    });
+    ui.label(&scenarios[*selected].description);
+}
+
+fn file_window(ctx: &egui::Context, nav: &NavigationViz, dialog: &mut FileDialog) {
+    let mut open = dialog.open;
+    let mut run = false;
+    let action = dialog.action;
+    egui::Window::new(action.title())
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            ui.label(action.hint());
+            let response = ui.add(egui::TextEdit::singleline(&mut dialog.path).desired_width(400.));
+            run |= response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            run |= ui
+                .button(if action.writes() { "Save" } else { "Open" })
+                .clicked();
+            if let Some(error) = &dialog.error {
+                ui.colored_label(Color32::RED, error);
+            }
+        });
+    dialog.open = open;
+    if run {
+        let result = match action {
+            FileAction::OpenMap => nav.load_map_file(&dialog.path),
+            FileAction::LoadScenario => nav.load_scenario_file(&dialog.path),
+            FileAction::SaveScenario => nav.save_scenario_file(&dialog.path),
+            FileAction::OpenSession => nav.open_session(&dialog.path),
+            FileAction::RecordSession => nav.start_recording(&dialog.path),
+            FileAction::ExportPng | FileAction::RecordGif | FileAction::RecordMp4 => {
+                dialog.pending_export = Some((action, dialog.path.clone()));
+                Ok(())
+            }
+        };
+        match result {
+            Ok(()) => {
+                dialog.open = false;
+                dialog.error = None;
+            }
+            Err(e) => {
+                dialog.error = Some(format!(
+                    "failed to {} {}: {e}",
+                    if action.writes() { "save" } else { "load" },
+                    dialog.path
+                ))
+            }
+        }
+    }
+}
+
+fn map_plot_rect_id() -> egui::Id {
+    egui::Id::new("map_plot_rect")
+}
+
+/// Opaque pixels of the screenshot
+fn screenshot_pixels(image: Image) -> Result<RgbaImage, String> {
+    // the alpha channel may be the brightness with HDR
+    let rgb = image
+        .try_into_dynamic()
+        .map_err(|e| e.to_string())?
+        .to_rgb8();
+    Ok(image::DynamicImage::ImageRgb8(rgb).to_rgba8())
+}
+
+fn export_system(
+    mut contexts: EguiContexts<'_, '_>,
+    mut file_dialog: ResMut<'_, FileDialog>,
+    mut exporter: ResMut<'_, Exporter>,
+    mut screenshot_manager: ResMut<'_, ScreenshotManager>,
+    windows: Query<'_, '_, Entity, With<PrimaryWindow>>,
+) {
+    let ctx = contexts.ctx_mut();
+    let (Ok(window), Some(rect)) = (
+        windows.get_single(),
+        ctx.data(|d| d.get_temp::<egui::Rect>(map_plot_rect_id())),
+    ) else {
+        return;
+    };
+    let scale = ctx.pixels_per_point();
+    let rect = [
+        (rect.min.x * scale) as u32,
+        (rect.min.y * scale) as u32,
+        (rect.width() * scale) as u32,
+        (rect.height() * scale) as u32,
+    ];
+    let now = ctx.input(|i| i.time);
+
+    if let Some((action, path)) = file_dialog.pending_export.take() {
+        if action == FileAction::ExportPng {
+            let status = exporter.status.clone();
+            let requested = screenshot_manager.take_screenshot(window, move |image| {
+                let result = screenshot_pixels(image)
+                    .and_then(|pixels| crop(&pixels, rect).save(&path).map_err(|e| e.to_string()));
+                *status.lock().unwrap() = Some(match result {
+                    Ok(()) => format!("saved {path}"),
+                    Err(e) => format!("failed to save {path}: {e}"),
+                });
+            });
+            if requested.is_err() {
+                *exporter.status.lock().unwrap() = Some("screenshot is busy".to_owned());
+            }
+        } else {
+            exporter.animation = Some(AnimationRecording {
+                action,
+                path,
+                frames: Default::default(),
+                rect,
+                last_capture: f64::NEG_INFINITY,
+            });
+        }
+    }
+
+    if let Some(animation) = &mut exporter.animation {
+        ctx.request_repaint();
+        let num_frames = animation.frames.lock().unwrap().len();
+        if now - animation.last_capture >= 1.0 / ANIMATION_FPS as f64
+            && num_frames < MAX_ANIMATION_FRAMES
+        {
+            let frames = animation.frames.clone();
+            let rect = animation.rect;
+            let requested = screenshot_manager.take_screenshot(window, move |image| {
+                if let Ok(pixels) = screenshot_pixels(image) {
+                    let frame = crop(&pixels, rect);
+                    let mut frames = frames.lock().unwrap();
+                    // the frames of the other size (e.g. the window is resized) are dropped
+                    if frames
+                        .first()
+                        .is_none_or(|first| first.dimensions() == frame.dimensions())
+                    {
+                        frames.push(frame);
+                    }
+                }
+            });
+            if requested.is_ok() {
+                animation.last_capture = now;
+            }
+        }
+    }
+
+    let status = exporter.status.lock().unwrap().clone();
+    if exporter.animation.is_none() && status.is_none() {
+        return;
+    }
+    let mut stop = false;
+    let mut close = false;
+    egui::Window::new("Export")
+        .anchor(egui::Align2::RIGHT_BOTTOM, [-10., -10.])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            if let Some(animation) = &exporter.animation {
+                ui.label(format!(
+                    "recording {} frames to {}",
+                    animation.frames.lock().unwrap().len(),
+                    animation.path
+                ));
+                stop = ui.button("Stop").clicked();
+            } else if let Some(status) = &status {
+                ui.label(status);
+                close = ui.button("Close").clicked();
+            }
+        });
+    if close {
+        *exporter.status.lock().unwrap() = None;
+    }
+    if stop {
+        let animation = exporter.animation.take().unwrap();
+        let status = exporter.status.clone();
+        *status.lock().unwrap() = Some(format!("encoding {}", animation.path));
+        std::thread::spawn(move || {
+            let frames = animation.frames.lock().unwrap();
+            let result = if animation.action == FileAction::RecordMp4 {
+                save_mp4(&animation.path, &frames, ANIMATION_FPS)
+            } else {
+                save_gif(&animation.path, &frames, ANIMATION_FPS)
+            };
+            *status.lock().unwrap() = Some(match result {
+                Ok(()) => format!("saved {} frames to {}", frames.len(), animation.path),
+                Err(e) => format!("failed to save {}: {e}", animation.path),
+            });
+        });
+    }
+}
+
+/// Timeline of the session log replayed in the viewer
+fn session_window(ctx: &egui::Context, nav: &NavigationViz) {
+    let mut session_player = nav.session_player.lock().unwrap();
+    let mut session_error = nav.session_error.lock().unwrap();
+    let Some(player) = &mut *session_player else {
+        // e.g. the recording is stopped by the error
+        let mut open = session_error.is_some();
+        egui::Window::new("Session")
+            .open(&mut open)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                if let Some(error) = &*session_error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+            });
+        if !open {
+            *session_error = None;
+        }
+        return;
+    };
+    let mut changed = player.advance(ctx.input(|i| i.unstable_dt) as f64);
+    if player.playing {
+        ctx.request_repaint();
+    }
+    let last = player.frames().len() - 1;
+    let mut open = true;
+    egui::Window::new("Session playback")
+        .open(&mut open)
+        .collapsible(false)
+        .show(ctx, |ui| {
+            let frame = player.frame();
+            ui.label(format!(
+                "frame {} / {}, {:.2} [s]",
+                player.index, last, frame.time
+            ));
+            ui.label(match frame.command {
+                Some(v) => format!("command: {:.3} [m/s], {:.3} [rad/s]", v.x, v.theta),
+                None => "command: -".to_owned(),
+            });
+            ui.horizontal(|h_ui| {
+                if h_ui
+                    .add_enabled(player.index > 0, egui::Button::new("Prev"))
+                    .clicked()
+                {
+                    player.index -= 1;
+                    player.playing = false;
+                    changed = true;
+                }
+                if h_ui
+                    .button(if player.playing { "Pause" } else { "Play" })
+                    .clicked()
+                {
+                    player.playing = !player.playing;
+                    player.elapsed = 0.0;
+                    if player.playing && player.index == last {
+                        player.index = 0;
+                        changed = true;
+                    }
+                }
+                if h_ui
+                    .add_enabled(player.index < last, egui::Button::new("Next"))
+                    .clicked()
+                {
+                    player.index += 1;
+                    player.playing = false;
+                    changed = true;
+                }
+            });
+            let mut index = player.index;
+            ui.spacing_mut().slider_width = 300.;
+            if ui
+                .add(egui::Slider::new(&mut index, 0..=last).text("frame"))
+                .changed()
+            {
+                player.index = index;
+                player.playing = false;
+                changed = true;
+            }
+            if let Some(error) = &*session_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+        });
+    drop(session_player);
+    if !open {
+        *session_error = None;
+        drop(session_error);
+        nav.close_session();
+    } else if changed {
+        *session_error = nav
+            .show_session_frame()
+            .err()
+            .map(|e| format!("failed to show the session frame: {e}"));
+    }
+}
+
+/// Color bar of the colormap between the min and the max values of the layer
+fn colormap_legend(ui: &mut egui::Ui, label: &str, colormap: Colormap, (min, max): (u8, u8)) {
+    const STEPS: usize = 32;
+    ui.horizontal(|h_ui| {
+        h_ui.add_sized([100.0, 20.0], egui::Label::new(label));
+        h_ui.label(min.to_string());
+        let (rect, _) = h_ui.allocate_exact_size(egui::vec2(150., 12.), egui::Sense::hover());
+        let step = rect.width() / STEPS as f32;
+        for i in 0..STEPS {
+            h_ui.painter().rect_filled(
+                egui::Rect::from_min_size(
+                    egui::pos2(rect.left() + i as f32 * step, rect.top()),
+                    egui::vec2(step, rect.height()),
+                ),
+                0.0,
+                colormap.color(i as f32 / (STEPS - 1) as f32),
+            );
+        }
+        h_ui.label(max.to_string());
+    });
+}
+
+/// Line from `from` to `to` with the arrowhead
+fn arrow_points(from: [f64; 2], to: [f64; 2]) -> PlotPoints {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let head = 0.2 * dx.hypot(dy);
+    let angle = dy.atan2(dx);
+    let wing = |offset: f64| {
+        [
+            to[0] + head * (angle + offset).cos(),
+            to[1] + head * (angle + offset).sin(),
+        ]
+    };
+    let offset = std::f64::consts::PI * 5. / 6.;
+    PlotPoints::new(vec![from, to, wing(offset), to, wing(-offset)])
+}
+
+fn circle_points(center: [f64; 2], radius: f64, num: usize) -> PlotPoints {
+    (0..=num)
+        .map(|i| {
+            let theta = i as f64 / num as f64 * 2. * std::f64::consts::PI;
+            [
+                center[0] + radius * theta.cos(),
+                center[1] + radius * theta.sin(),
+            ]
+        })
+        .collect()
+}
+
+/// Run/Pause/Step buttons and the speed of the planning loop
+fn loop_controller(ui: &mut egui::Ui, loop_control: &mut LoopControl) {
+    ui.horizontal(|h_ui| {
+        h_ui.columns(3, |c_ui| {
+            if c_ui[0]
+                .add_enabled(
+                    loop_control.paused,
+                    egui::Button::new("Run").min_size([0., 30.].into()),
+                )
+                .clicked()
+            {
+                loop_control.paused = false;
+                loop_control.steps = 0;
+            }
+            if c_ui[1]
+                .add_enabled(
+                    !loop_control.paused,
+                    egui::Button::new("Pause").min_size([0., 30.].into()),
+                )
+                .clicked()
+            {
+                loop_control.paused = true;
+            }
+            if c_ui[2]
+                .add(egui::Button::new("Step").min_size([0., 30.].into()))
+                .clicked()
+            {
+                loop_control.paused = true;
+                loop_control.steps += 1;
+            }
+        });
+    });
+    ui.horizontal(|h_ui| {
+        h_ui.add_sized([100.0, 30.0], egui::Label::new("speed"));
+        h_ui.add(
+            egui::Slider::new(&mut loop_control.speed, 0.1..=10.0)
+                .logarithmic(true)
+                .suffix("x"),
+        );
+    });
+}
+
+/// Distance from the center to the front of the footprint
+fn footprint_front(footprint: &Footprint) -> f64 {
+    match footprint {
+        Footprint::Circle { radius } => *radius,
+        Footprint::Polygon { points } => points.iter().map(|p| p.x).fold(0.0, f64::max),
+    }
+}
+
+/// Arrow of the heading of the pose
+fn heading_to_line(pose: &Pose, color: Color32, length: f64) -> Line {
+    let from = [pose.translation.x, pose.translation.y];
+    let angle = pose.rotation.angle();
+    let to = [
+        from[0] + length * angle.cos(),
+        from[1] + length * angle.sin(),
+    ];
+    Line::new(arrow_points(from, to)).color(color).width(2.)
+}
+
+fn marker_to_points(pose: &Pose, color: Color32, name: &str) -> Points {
+    Points::new(vec![[pose.translation.x, pose.translation.y]])
+        .radius(6.)
+        .color(color)
+        .name(name)
+}
+
+/// Editable numeric fields of the pose. Returns true when the edit is finished.
+fn pose_editor(ui: &mut egui::Ui, label: &str, pose: &mut Pose) -> bool {
+    let mut x = pose.translation.x;
+    let mut y = pose.translation.y;
+    let mut yaw = pose.rotation.angle().to_degrees();
+    let mut changed = false;
+    let mut finished = false;
+    ui.horizontal(|h_ui| {
+        h_ui.add_sized([40.0, 20.0], egui::Label::new(label));
+        for (name, value, speed) in [
+            ("x", &mut x, 0.01),
+            ("y", &mut y, 0.01),
+            ("yaw", &mut yaw, 1.0),
+        ] {
+            h_ui.label(name);
+            let response = h_ui.add(egui::DragValue::new(value).speed(speed).max_decimals(2));
+            changed |= response.changed();
+            finished |= response.drag_released() || (response.changed() && !response.dragged());
+        }
+    });
+    if changed {
+        *pose = Pose::new(Vector2::new(x, y), yaw.to_radians());
+    }
+    finished
+}
+
+/// Editable parameters of the DWA with Apply/Revert
+fn planner_parameter_editor(ui: &mut egui::Ui, nav: &NavigationViz, editor: &mut PlannerEditor) {
+    let mut planner = nav.planner.lock().unwrap();
+    let current = planner.parameters();
+    if current != editor.base && editor.draft == editor.base {
+        // not edited, follow the changes e.g. by "Reload planner config"
+        editor.base = current.clone();
+        editor.draft = current.clone();
+    }
+    let draft = &mut editor.draft;
+    egui::Grid::new("planner_parameters")
+        .num_columns(3)
+        .show(ui, |g_ui| {
+            g_ui.label("");
+            g_ui.label("x");
+            g_ui.label("theta");
+            g_ui.end_row();
+            for (name, v) in [
+                ("max velocity", &mut draft.limits.max_velocity),
+                ("min velocity", &mut draft.limits.min_velocity),
+            ] {
+                g_ui.label(name);
+                g_ui.add(egui::DragValue::new(&mut v.x).speed(0.01).max_decimals(3));
+                g_ui.add(
+                    egui::DragValue::new(&mut v.theta)
+                        .speed(0.01)
+                        .max_decimals(3),
+                );
+                g_ui.end_row();
+            }
+            for (name, a) in [
+                ("max acceleration", &mut draft.limits.max_accel),
+                ("min acceleration", &mut draft.limits.min_accel),
+            ] {
+                g_ui.label(name);
+                g_ui.add(egui::DragValue::new(&mut a.x).speed(0.01).max_decimals(3));
+                g_ui.add(
+                    egui::DragValue::new(&mut a.theta)
+                        .speed(0.01)
+                        .max_decimals(3),
+                );
+                g_ui.end_row();
+            }
+            g_ui.label("controller dt [s]");
+            g_ui.add(
+                egui::DragValue::new(&mut draft.controller_dt)
+                    .speed(0.001)
+                    .max_decimals(3),
+            );
+            g_ui.end_row();
+            g_ui.label("horizon [s]");
+            g_ui.add(
+                egui::DragValue::new(&mut draft.simulation_duration)
+                    .speed(0.01)
+                    .max_decimals(3),
+            );
+            g_ui.end_row();
+            g_ui.label("velocity samples");
+            g_ui.add(egui::DragValue::new(&mut draft.num_vel_sample).speed(0.1));
+            g_ui.end_row();
+        });
+
+    let modified = editor.draft != current;
+    let validation = editor.draft.validate();
+    if let Err(e) = &validation {
+        ui.colored_label(Color32::RED, e.to_string());
+    }
+    ui.horizontal(|h_ui| {
+        if h_ui
+            .add_enabled(modified && validation.is_ok(), egui::Button::new("Apply"))
+            .clicked()
+        {
+            match planner.set_parameters(editor.draft.clone()) {
+                Ok(()) => {
+                    editor.base = editor.draft.clone();
+                    editor.error = None;
+                }
+                Err(e) => editor.error = Some(e.to_string()),
+            }
+        }
+        if h_ui
+            .add_enabled(modified, egui::Button::new("Revert"))
+            .clicked()
+        {
+            editor.base = current.clone();
+            editor.draft = current;
+            editor.error = None;
+        }
+        if modified {
+            h_ui.label("modified");
+        }
+    });
+    if let Some(e) = &editor.error {
+        ui.colored_label(Color32::RED, e);
+    }
+}
+
+/// Editable standard deviation of the initial pose
+fn std_dev_editor(ui: &mut egui::Ui, std_dev: &mut PoseStdDev) {
+    let mut yaw = std_dev.yaw.to_degrees();
+    ui.horizontal(|h_ui| {
+        h_ui.add_sized([40.0, 20.0], egui::Label::new("σ"));
+        for (name, value, speed) in [
+            ("x", &mut std_dev.x, 0.01),
+            ("y", &mut std_dev.y, 0.01),
+            ("yaw", &mut yaw, 1.0),
+        ] {
+            h_ui.label(name);
+            h_ui.add(
+                egui::DragValue::new(value)
+                    .speed(speed)
+                    .clamp_range(0.0..=f64::MAX)
+                    .max_decimals(2),
+            );
+        }
+    });
+    std_dev.yaw = yaw.to_radians();
+}
+
+/// Blue (low) to red (high) color of the normalized cost
+fn cost_to_color(normalized: f64) -> Color32 {
+    let v = normalized.clamp(0.0, 1.0);
+    Color32::from_rgb(
+        (255.0 * v) as u8,
+        (255.0 * (1.0 - (2.0 * v - 1.0).abs())) as u8,
+        (255.0 * (1.0 - v)) as u8,
+    )
+}
+
+/// Min and max of the finite costs of the candidates
+fn cost_range(candidates: &[Plan]) -> Option<(f64, f64)> {
+    candidates
+        .iter()
+        .map(|c| c.cost)
+        .filter(|cost| cost.is_finite())
+        .fold(None, |range, c| match range {
+            Some((min, max)) => Some((c.min(min), c.max(max))),
+            None => Some((c, c)),
+        })
+}
+
+/// Candidate with the lowest cost, which is selected by the planner
+fn selected_candidate(candidates: &[Plan]) -> Option<&Plan> {
+    candidates
+        .iter()
+        .filter(|c| c.cost.is_finite())
+        .min_by(|a, b| a.cost.total_cmp(&b.cost))
+}
+
+fn candidate_color(cost: f64, range: Option<(f64, f64)>) -> Color32 {
+    match range {
+        Some((min, max)) if cost.is_finite() && max > min => {
+            cost_to_color((cost - min) / (max - min))
+        }
+        _ => Color32::GRAY,
+    }
+}
+
+/// Heatmap of the sampled (x, theta) velocities colored by their total costs
+fn velocity_space_system(
+    mut contexts: EguiContexts<'_, '_>,
+    res_nav: Res<'_, NavigationViz>,
+    mut planner_editor: ResMut<'_, PlannerEditor>,
+) {
+    let ctx = contexts.ctx_mut();
+
+    egui::SidePanel::right("velocity_space")
+        .default_width(300.)
+        .min_width(200.)
+        .show(ctx, |ui| {
+            ui.label("velocity space (x: linear, y: angular)");
+            let candidates = res_nav.candidates.lock().unwrap();
+            let range = cost_range(&candidates);
+            if let Some((min, max)) = range {
+                ui.label(format!("cost: {min:.2} (blue) - {max:.2} (red)"));
+            }
+            Plot::new("velocity_space")
+                .allow_drag(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    for candidate in candidates.iter() {
+                        let velocity = candidate.velocity;
+                        plot_ui.points(
+                            Points::new(vec![[velocity.x, velocity.theta]])
+                                .radius(4.)
+                                .color(candidate_color(candidate.cost, range)),
+                        );
+                    }
+                    if let Some(selected) = selected_candidate(&candidates) {
+                        let velocity = selected.velocity;
+                        plot_ui.points(
+                            Points::new(vec![[velocity.x, velocity.theta]])
+                                .radius(7.)
+                                .filled(false)
+                                .color(Color32::WHITE)
+                                .name("selected"),
+                        );
+                    }
+                });
+            drop(candidates);
+            ui.separator();
+            egui::CollapsingHeader::new("planner parameters")
+                .default_open(true)
+                .show(ui, |ui| {
+                    planner_parameter_editor(ui, &res_nav, &mut planner_editor);
+                });
+        });
+}
+
+/// Scrolling plots of the velocity, the cost and the distance to the goal of the
+/// recent planning cycles
+fn time_series_plots(ui: &mut egui::Ui, telemetry: &Telemetry) {
+    let series = |f: &dyn Fn(&TelemetrySample) -> f64| {
+        PlotPoints::from_iter(telemetry.samples().map(|s| [s.time, f(s)]))
+    };
+    let plot = |id: &str| {
+        Plot::new(id)
+            .height(120.)
+            .auto_bounds_x()
+            .auto_bounds_y()
+            .allow_drag(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .allow_boxed_zoom(false)
+            .legend(Legend::default())
+    };
+    let layer_ids = telemetry
+        .samples()
+        .last()
+        .map(|s| s.layer_costs.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .unwrap_or_default();
+    ui.columns(3, |c_ui| {
+        c_ui[0].label("velocity");
+        plot("velocity_series").show(&mut c_ui[0], |plot_ui| {
+            plot_ui.line(Line::new(series(&|s| s.velocity.x)).name("v [m/s]"));
+            plot_ui.line(Line::new(series(&|s| s.velocity.theta)).name("ω [rad/s]"));
+        });
+        c_ui[1].label("cost of the selected plan");
+        plot("cost_series").show(&mut c_ui[1], |plot_ui| {
+            plot_ui.line(
+                Line::new(series(&|s| s.cost))
+                    .name("total")
+                    .color(Color32::WHITE),
+            );
+            for id in &layer_ids {
+                plot_ui.line(
+                    Line::new(series(&|s| {
+                        s.layer_costs
+                            .iter()
+                            .find(|(layer, _)| layer == id)
+                            .map_or(0.0, |(_, cost)| *cost)
+                    }))
+                    .name(id.name()),
+                );
+            }
+        });
+        c_ui[2].label("distance to goal [m]");
+        plot("distance_series").show(&mut c_ui[2], |plot_ui| {
+            plot_ui.line(Line::new(series(&|s| s.distance_to_goal)).name("distance"));
+        });
+    });
+}
+
 fn bottom_monitor_system(mut contexts: EguiContexts<'_, '_>, res_nav: Res<'_, NavigationViz>) {
     let ctx = contexts.ctx_mut();
 
     egui::TopBottomPanel::bottom("monitor")
-        .default_height(150.)
+        .default_height(300.)
         .show(ctx, |ui| {
+            time_series_plots(ui, &res_nav.telemetry.lock().unwrap());
+            ui.separator();
+
             let angle_table = res_nav.angle_table.lock().unwrap();
 
             ui.columns(angle_table.len(), |c_ui| {
@@ -478,7 +1674,7 @@ fn bottom_monitor_system(mut contexts: EguiContexts<'_, '_>, res_nav: Res<'_, Na
                 }
 
                 for (i, (name, angle)) in angle_table.iter().enumerate() {
-                    c_ui[i].label(name);
+                    c_ui[i].label(name.name());
                     Plot::new(format!("angle{}", i))
                         .data_aspect(1.)
                         .auto_bounds_x()
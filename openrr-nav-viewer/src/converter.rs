@@ -5,38 +5,166 @@ use bevy_egui::egui::{
 };
 use grid_map::*;
 use nalgebra as na;
-use openrr_nav::*;
+use openrr_nav_core::*;
+
+use crate::{BlendMode, Colormap};
 
 pub fn grid_map_to_polygon(grid_map: &GridMap<u8>) -> Vec<Polygon> {
-    let min_point = grid_map.min_point();
-    let resolution = grid_map.resolution();
-    let width = grid_map.width();
-    let cells = grid_map.cells();
+    blended_grid_maps_to_polygon(
+        &[(grid_map, 1.0)],
+        BlendMode::default(),
+        Colormap::default(),
+    )
+}
+
+fn cell_polygon_points(
+    min_point: &Position,
+    resolution: f64,
+    width: usize,
+    i: usize,
+) -> PlotPoints {
+    let order = [0, 1, 3, 2];
+    order
+        .iter()
+        .map(|j| {
+            let x = min_point.x + (i % width + j % 2) as f64 * resolution;
+            let y = min_point.y + (i / width + j / 2) as f64 * resolution;
+            [x, y]
+        })
+        .collect()
+}
+
+/// Color of a cell used when a layer is drawn
+pub fn cell_to_color(cell: &Cell<u8>) -> Color32 {
+    match cell {
+        Cell::Unknown => Color32::from_gray(120),
+        Cell::Value(v) => Hsva::new(*v as f32 / 360.0, 1.0, 1.0, 1.0).into(),
+        _ => Color32::from_gray(0),
+    }
+}
+
+/// Min and max of the values of the layer
+pub fn value_range(map: &GridMap<u8>) -> Option<(u8, u8)> {
+    map.cells()
+        .iter()
+        .filter_map(|cell| cell.value().copied())
+        .fold(None, |range, v| match range {
+            Some((min, max)) => Some((v.min(min), v.max(max))),
+            None => Some((v, v)),
+        })
+}
+
+/// Range of the absolute differences of the values in the two ranges
+pub fn difference_range(a: (u8, u8), b: (u8, u8)) -> (u8, u8) {
+    (0, a.1.max(b.1) - a.0.min(b.0))
+}
+
+/// Color of a cell, whose value is normalized in the range
+pub fn cell_to_colormap_color(cell: &Cell<u8>, colormap: Colormap, range: (u8, u8)) -> Color32 {
+    match cell {
+        Cell::Value(v) => {
+            let (min, max) = range;
+            let t = if max > min {
+                (v.saturating_sub(min)) as f32 / (max - min) as f32
+            } else {
+                0.0
+            };
+            colormap.color(t)
+        }
+        _ => cell_to_color(cell),
+    }
+}
+
+fn blend_colors(colors: &[(Color32, f32)], blend_mode: BlendMode) -> Color32 {
+    let mut rgb = [0.0f32; 3];
+    for (color, opacity) in colors {
+        let weighted = [
+            color.r() as f32 * opacity,
+            color.g() as f32 * opacity,
+            color.b() as f32 * opacity,
+        ];
+        for (acc, w) in rgb.iter_mut().zip(weighted) {
+            *acc = match blend_mode {
+                BlendMode::Max | BlendMode::Difference => acc.max(w),
+                BlendMode::Additive => *acc + w,
+            };
+        }
+    }
+    let alpha = colors.iter().map(|(_, o)| *o).fold(0.0f32, f32::max) * 255.0;
+    // the channels are already weighted by the opacity
+    let to_u8 = |v: f32| v.round().clamp(0.0, alpha.round()) as u8;
+    Color32::from_rgba_premultiplied(to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]), to_u8(alpha))
+}
+
+/// Convert multiple layers into polygons, blending the colors of overlapping cells.
+///
+/// The largest layer is used as the drawing grid and the other layers are sampled
+/// at the cell centers, so layers with different extents (like the local goal map)
+/// can be overlaid. Obstacle cells in any layer are always drawn opaque. The values
+/// are colored from the min to the max of each layer (see [`value_range`]).
+pub fn blended_grid_maps_to_polygon(
+    layers: &[(&GridMap<u8>, f32)],
+    blend_mode: BlendMode,
+    colormap: Colormap,
+) -> Vec<Polygon> {
+    let Some((base, _)) = layers.iter().max_by_key(|(map, _)| map.len()) else {
+        return vec![];
+    };
+    let min_point = base.min_point();
+    let resolution = base.resolution();
+    let width = base.width();
+    let ranges = layers
+        .iter()
+        .map(|(map, _)| value_range(map).unwrap_or_default())
+        .collect::<Vec<_>>();
 
     let mut polygons = Vec::<Polygon>::new();
+    let mut colors = Vec::with_capacity(layers.len());
+    let mut values = Vec::with_capacity(layers.len());
 
-    for (i, cell_i) in cells.iter().enumerate() {
-        let order = [0, 1, 3, 2];
-        let plot_points: PlotPoints = order
-            .iter()
-            .map(|j| {
-                let x = min_point.x + (i % width + j % 2) as f64 * resolution;
-                let y = min_point.y + (i / width + j / 2) as f64 * resolution;
-                [x, y]
-            })
-            .collect();
-        let polygon = match cell_i {
-            Cell::Unknown => Polygon::new(plot_points)
-                .color(Color32::from_gray(120))
-                .fill_alpha(1.0),
-            Cell::Value(v) => Polygon::new(plot_points)
-                .color(Hsva::new(*v as f32 / 360.0, 1.0, 1.0, 1.0))
-                .fill_alpha(1.0),
-            _ => Polygon::new(plot_points)
-                .color(Color32::from_gray(0))
-                .fill_alpha(1.0),
+    for i in 0..base.len() {
+        let center_x = min_point.x + ((i % width) as f64 + 0.5) * resolution;
+        let center_y = min_point.y + ((i / width) as f64 + 0.5) * resolution;
+        colors.clear();
+        values.clear();
+        let mut is_obstacle = false;
+        for ((map, opacity), range) in layers.iter().zip(&ranges) {
+            let Some(cell) = map
+                .to_grid(center_x, center_y)
+                .and_then(|grid| map.cell(&grid))
+            else {
+                continue;
+            };
+            match cell {
+                Cell::Obstacle => is_obstacle = true,
+                _ => {
+                    colors.push((cell_to_colormap_color(cell, colormap, *range), *opacity));
+                    values.push(cell.value().copied().map(|v| (v, *range)));
+                }
+            }
+        }
+        let color = if is_obstacle {
+            Color32::from_gray(0)
+        } else if colors.is_empty() {
+            continue;
+        } else if let (BlendMode::Difference, [Some((a, a_range)), Some((b, b_range)), ..]) =
+            (blend_mode, &values[..])
+        {
+            let opacity = colors[0].1.max(colors[1].1);
+            let color = cell_to_colormap_color(
+                &Cell::Value(a.abs_diff(*b)),
+                colormap,
+                difference_range(*a_range, *b_range),
+            );
+            blend_colors(&[(color, opacity)], blend_mode)
+        } else {
+            blend_colors(&colors, blend_mode)
         };
-        polygons.push(polygon);
+        polygons.push(
+            Polygon::new(cell_polygon_points(min_point, resolution, width, i))
+                .color(color)
+                .fill_alpha(1.0),
+        );
     }
 
     polygons
@@ -59,6 +187,40 @@ pub fn robot_pose_to_point(robot_pose: &Pose, color: Color32, point_radius: f32)
     Points::new(plot_point).color(color).radius(point_radius)
 }
 
+/// Vertices of the footprint at the pose
+pub fn footprint_outline(footprint: &Footprint, pose: &Pose) -> Vec<[f64; 2]> {
+    const CIRCLE_POINTS: usize = 36;
+    let points = match footprint {
+        Footprint::Circle { radius } => (0..CIRCLE_POINTS)
+            .map(|i| {
+                let theta = i as f64 / CIRCLE_POINTS as f64 * 2. * std::f64::consts::PI;
+                [radius * theta.cos(), radius * theta.sin()]
+            })
+            .collect(),
+        Footprint::Polygon { points } => points.iter().map(|p| [p.x, p.y]).collect::<Vec<_>>(),
+    };
+    points
+        .into_iter()
+        .map(|[x, y]| {
+            let p = pose.transform_point(&na::Point2::new(x, y));
+            [p.x, p.y]
+        })
+        .collect()
+}
+
+pub fn footprint_to_polygon(footprint: &Footprint, pose: &Pose, color: Color32) -> Polygon {
+    Polygon::new(PlotPoints::new(footprint_outline(footprint, pose)))
+        .color(color)
+        .fill_alpha(0.5)
+}
+
+/// Closed outline of the footprint at the pose
+pub fn footprint_to_line(footprint: &Footprint, pose: &Pose, color: Color32) -> Line {
+    let mut points = footprint_outline(footprint, pose);
+    points.extend(points.first().copied());
+    Line::new(PlotPoints::new(points)).color(color)
+}
+
 pub fn robot_pose_to_polygon(robot_pose: &Pose, color: Color32, scale: f64) -> Polygon {
     let robot_size = scale * 0.04;
     let vertices = vec![
@@ -0,0 +1,71 @@
+use image::{
+    codecs::gif::{GifEncoder, Repeat},
+    Delay, Frame, RgbaImage,
+};
+use openrr_nav_core::{Error, Result};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    process::{Command, Stdio},
+};
+
+/// Pixels in the rectangle `[x, y, width, height]`, which is clamped into the image
+pub fn crop(image: &RgbaImage, [x, y, width, height]: [u32; 4]) -> RgbaImage {
+    image::imageops::crop_imm(image, x, y, width, height).to_image()
+}
+
+/// Animated GIF of the frames, which loops forever
+pub fn save_gif<P: AsRef<Path>>(path: P, frames: &[RgbaImage], fps: u32) -> Result<()> {
+    let mut encoder = GifEncoder::new(BufWriter::new(File::create(path)?));
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .map_err(grid_map::Error::from)?;
+    let delay = Delay::from_numer_denom_ms(1000, fps);
+    for frame in frames {
+        encoder
+            .encode_frame(Frame::from_parts(frame.clone(), 0, 0, delay))
+            .map_err(grid_map::Error::from)?;
+    }
+    Ok(())
+}
+
+/// MP4 (H.264) of the frames of the same size, encoded by `ffmpeg` in `PATH`
+pub fn save_mp4<P: AsRef<Path>>(path: P, frames: &[RgbaImage], fps: u32) -> Result<()> {
+    let Some(first) = frames.first() else {
+        return Err(Error::Other("no frames to save".to_owned()));
+    };
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", first.width(), first.height())])
+        .args(["-r", &fps.to_string(), "-i", "-"])
+        // yuv420p needs the even width and height
+        .args([
+            "-vf",
+            "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            "-pix_fmt",
+            "yuv420p",
+        ])
+        .arg(path.as_ref())
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| Error::Other(format!("failed to run ffmpeg: {e}")))?;
+    let mut stdin = child.stdin.take().unwrap();
+    for frame in frames {
+        stdin.write_all(frame.as_raw())?;
+    }
+    drop(stdin);
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(Error::Other(format!("ffmpeg failed: {status}")));
+    }
+    Ok(())
+}
@@ -1,12 +1,22 @@
 mod bevy_app;
 mod converter;
+mod export;
 mod map_type;
 mod nav_viz;
+mod scenario;
+mod session;
+mod telemetry;
 
 pub use bevy_app::*;
 pub use converter::*;
+pub use export::*;
 pub use map_type::*;
 pub use nav_viz::*;
+pub use scenario::*;
+pub use session::*;
+pub use telemetry::*;
+
+use grid_map::LayerId;
 
 pub mod pb {
     #![allow(unreachable_pub)]
@@ -51,14 +61,16 @@ impl pb::api_server::Api for NavigationViz {
         request: tonic::Request<pb::PathAndCandidates>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
         let pb::PathAndCandidates { path, candidates } = request.into_inner();
-        let mut robot_path = self.robot_path.lock().unwrap();
-        robot_path.set_local_path(path.unwrap().into());
-        for (i, candidate) in candidates.into_iter().enumerate() {
-            robot_path.add_user_defined_path(
-                &format!("candidate_{i}"),
-                openrr_nav::RobotPath(candidate.path.into_iter().map(Into::into).collect()),
-            );
-        }
+        *self.candidates.lock().unwrap() = candidates
+            .into_iter()
+            .filter(|c| c.velocity.is_some())
+            .map(Into::into)
+            .collect();
+        self.robot_path
+            .lock()
+            .unwrap()
+            .set_local_path(path.unwrap().into());
+        self.record_cycle();
         Ok(tonic::Response::new(()))
     }
     async fn set_layered_grid_map(
@@ -68,7 +80,8 @@ impl pb::api_server::Api for NavigationViz {
         let pb::SetLayeredGridMapRequest { maps } = request.into_inner();
         let mut layered_grid_map = self.layered_grid_map.lock().unwrap();
         for named_map in maps {
-            layered_grid_map.add_layer(named_map.name, named_map.map.unwrap().into());
+            layered_grid_map
+                .add_layer(LayerId::new(&named_map.name), named_map.map.unwrap().into());
         }
         Ok(tonic::Response::new(()))
     }
@@ -79,7 +92,7 @@ impl pb::api_server::Api for NavigationViz {
         let pb::SetAngleTableRequest { table } = request.into_inner();
         let mut angle_table = self.angle_table.lock().unwrap();
         for angle in table {
-            angle_table.insert(angle.name, angle.angle);
+            angle_table.insert(LayerId::new(&angle.name), angle.angle);
         }
         Ok(tonic::Response::new(()))
     }
@@ -95,8 +108,8 @@ impl pb::api_server::Api for NavigationViz {
         request: tonic::Request<pb::Config>,
     ) -> Result<tonic::Response<()>, tonic::Status> {
         let text = request.into_inner().text;
-        match openrr_nav::DwaPlanner::new_from_config_text(&text) {
-            Ok(new) => *self.planner.lock().unwrap() = new,
+        match openrr_nav_core::LocalPlannerConfig::new_from_config_text(&text) {
+            Ok(config) => self.set_planner_config(config),
             Err(e) => {
                 return Err(tonic::Status::invalid_argument(format!(
                     "failed to parse config: {e}"
@@ -120,15 +133,36 @@ impl pb::api_server::Api for NavigationViz {
             current_pose,
             current_velocity,
         } = request.into_inner();
+        let pose: openrr_nav_core::Pose = current_pose.unwrap().into();
+        let velocity: openrr_nav_core::Velocity = current_velocity.unwrap().into();
+        let global_path = self
+            .robot_path
+            .lock()
+            .unwrap()
+            .global_path()
+            .0
+            .iter()
+            .map(|p| vec![p.translation.x, p.translation.y, p.rotation.angle()])
+            .collect::<Vec<_>>();
         let layered_grid_map = self.layered_grid_map.lock().unwrap();
         let angle_table = self.angle_table.lock().unwrap();
-        let planner = self.planner.lock().unwrap();
-        let plan = planner.plan_local_path(
-            &current_pose.unwrap().into(),
-            &current_velocity.unwrap().into(),
-            &layered_grid_map,
-            &angle_table,
-        );
+        let plan = match &*self.local_planner.lock().unwrap() {
+            Some(local_planner) => local_planner
+                .plan(
+                    &pose,
+                    &velocity,
+                    &layered_grid_map,
+                    &angle_table,
+                    &global_path,
+                )
+                .map_err(|e| tonic::Status::failed_precondition(e.to_string()))?,
+            None => self.planner.lock().unwrap().plan_local_path(
+                &pose,
+                &velocity,
+                &layered_grid_map,
+                &angle_table,
+            ),
+        };
         Ok(tonic::Response::new(plan.into()))
     }
     async fn predicted_plan_candidates(
@@ -139,25 +173,91 @@ impl pb::api_server::Api for NavigationViz {
             current_pose,
             current_velocity,
         } = request.into_inner();
+        let layered_grid_map = self.layered_grid_map.lock().unwrap();
+        let angle_table = self.angle_table.lock().unwrap();
         let planner = self.planner.lock().unwrap();
-        let candidates = planner.predicted_plan_candidates(
+        let mut candidates = planner.predicted_plan_candidates(
             &current_pose.unwrap().into(),
             &current_velocity.unwrap().into(),
         );
+        for candidate in &mut candidates {
+            candidate.cost = openrr_nav_core::DwaPlanner::score_plan(
+                candidate,
+                &layered_grid_map,
+                &angle_table,
+                planner.map_name_weight(),
+            );
+        }
         Ok(tonic::Response::new(pb::Candidates {
             candidates: candidates.into_iter().map(Into::into).collect(),
         }))
     }
+    async fn explain_cost(
+        &self,
+        request: tonic::Request<pb::Position>,
+    ) -> Result<tonic::Response<pb::CostReport>, tonic::Status> {
+        let position = request.into_inner().into();
+        let layered_grid_map = self.layered_grid_map.lock().unwrap();
+        let planner = self.planner.lock().unwrap();
+        let report = planner.explain_cost_at(&layered_grid_map, &position);
+        Ok(tonic::Response::new(report.into()))
+    }
+    async fn take_initial_pose(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<tonic::Response<pb::InitialPose>, tonic::Status> {
+        let pose = self.initial_pose.lock().unwrap().take();
+        Ok(tonic::Response::new(pb::InitialPose {
+            pose: pose.map(Into::into),
+        }))
+    }
+    async fn get_scenario_map(
+        &self,
+        request: tonic::Request<pb::ScenarioMapRequest>,
+    ) -> Result<tonic::Response<pb::ScenarioMap>, tonic::Status> {
+        let pb::ScenarioMapRequest { time } = request.into_inner();
+        Ok(tonic::Response::new(match self.current_map(time) {
+            Some(LoadedMap {
+                name,
+                map,
+                revision,
+            }) => pb::ScenarioMap {
+                name,
+                map: Some((&map).into()),
+                revision,
+            },
+            None => pb::ScenarioMap::default(),
+        }))
+    }
+    async fn start_cycle(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<tonic::Response<pb::CycleControl>, tonic::Status> {
+        let start = self.start_cycle();
+        Ok(tonic::Response::new(pb::CycleControl {
+            start,
+            speed: self.loop_control.lock().unwrap().speed,
+        }))
+    }
+    async fn get_global_planner(
+        &self,
+        _request: tonic::Request<()>,
+    ) -> Result<tonic::Response<String>, tonic::Status> {
+        let planners = self.global_planners.lock().unwrap();
+        Ok(tonic::Response::new(
+            planners.active().unwrap_or_default().to_owned(),
+        ))
+    }
 }
 
-impl From<openrr_nav::RobotPath> for pb::RobotPath {
-    fn from(val: openrr_nav::RobotPath) -> Self {
+impl From<openrr_nav_core::RobotPath> for pb::RobotPath {
+    fn from(val: openrr_nav_core::RobotPath) -> Self {
         Self {
             path: val.0.into_iter().map(Into::into).collect(),
         }
     }
 }
-impl From<pb::RobotPath> for openrr_nav::RobotPath {
+impl From<pb::RobotPath> for openrr_nav_core::RobotPath {
     fn from(val: pb::RobotPath) -> Self {
         Self(val.path.into_iter().map(Into::into).collect())
     }
@@ -174,7 +274,7 @@ impl From<pb::Position> for grid_map::Position {
     }
 }
 
-impl From<pb::Velocity> for openrr_nav::Velocity {
+impl From<pb::Velocity> for openrr_nav_core::Velocity {
     fn from(value: pb::Velocity) -> Self {
         Self {
             x: value.x,
@@ -182,8 +282,8 @@ impl From<pb::Velocity> for openrr_nav::Velocity {
         }
     }
 }
-impl From<openrr_nav::Velocity> for pb::Velocity {
-    fn from(value: openrr_nav::Velocity) -> Self {
+impl From<openrr_nav_core::Velocity> for pb::Velocity {
+    fn from(value: openrr_nav_core::Velocity) -> Self {
         Self {
             x: value.x,
             theta: value.theta,
@@ -191,8 +291,8 @@ impl From<openrr_nav::Velocity> for pb::Velocity {
     }
 }
 
-impl From<openrr_nav::Plan> for pb::Plan {
-    fn from(val: openrr_nav::Plan) -> Self {
+impl From<openrr_nav_core::Plan> for pb::Plan {
+    fn from(val: openrr_nav_core::Plan) -> Self {
         Self {
             velocity: Some(pb::Velocity {
                 x: val.velocity.x,
@@ -203,16 +303,17 @@ impl From<openrr_nav::Plan> for pb::Plan {
         }
     }
 }
-impl From<pb::Plan> for openrr_nav::Plan {
+impl From<pb::Plan> for openrr_nav_core::Plan {
     fn from(val: pb::Plan) -> Self {
         let velocity = val.velocity.unwrap();
         Self {
-            velocity: openrr_nav::Velocity {
+            velocity: openrr_nav_core::Velocity {
                 x: velocity.x,
                 theta: velocity.theta,
             },
             cost: val.cost,
             path: val.path.into_iter().map(Into::into).collect(),
+            sampling_issue: None,
         }
     }
 }
@@ -272,6 +373,66 @@ impl From<pb::Cell> for grid_map::Cell<u8> {
     }
 }
 
+impl From<openrr_nav_core::CostReport> for pb::CostReport {
+    fn from(val: openrr_nav_core::CostReport) -> Self {
+        Self {
+            position: Some(val.position.into()),
+            layers: val
+                .layers
+                .into_iter()
+                .map(|l| pb::LayerCost {
+                    name: l.name.to_string(),
+                    cell: l.cell.map(Into::into),
+                    cost: l.cost,
+                    weight: l.weight,
+                })
+                .collect(),
+        }
+    }
+}
+impl From<pb::CostReport> for openrr_nav_core::CostReport {
+    fn from(val: pb::CostReport) -> Self {
+        Self {
+            position: val.position.unwrap().into(),
+            layers: val
+                .layers
+                .into_iter()
+                .map(|l| openrr_nav_core::LayerCost {
+                    name: LayerId::new(&l.name),
+                    cell: l.cell.map(Into::into),
+                    cost: l.cost,
+                    weight: l.weight,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<openrr_nav_core::PoseWithCovariance> for pb::PoseWithCovariance {
+    fn from(val: openrr_nav_core::PoseWithCovariance) -> Self {
+        Self {
+            pose: Some(val.pose.into()),
+            covariance: val.covariance.transpose().as_slice().to_vec(),
+        }
+    }
+}
+impl TryFrom<pb::PoseWithCovariance> for openrr_nav_core::PoseWithCovariance {
+    type Error = tonic::Status;
+
+    fn try_from(val: pb::PoseWithCovariance) -> Result<Self, Self::Error> {
+        if val.covariance.len() != 9 {
+            return Err(tonic::Status::invalid_argument(format!(
+                "covariance must have 9 elements, but got {}",
+                val.covariance.len()
+            )));
+        }
+        Ok(Self::new(
+            val.pose.unwrap().into(),
+            nalgebra::Matrix3::from_row_slice(&val.covariance),
+        ))
+    }
+}
+
 impl From<nalgebra::Isometry2<f64>> for pb::Isometry2 {
     fn from(val: nalgebra::Isometry2<f64>) -> Self {
         Self {
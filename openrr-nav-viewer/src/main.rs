@@ -1,27 +1,91 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use openrr_nav_viewer::*;
 
+const ENDPOINT: &str = "http://[::1]:50101";
+
 #[derive(Debug, Parser)]
+#[clap(subcommand_negates_reqs = true)]
 struct Args {
     #[clap(
         short = 'f',
         long = "config-file",
         env = "PLANNER_CONFIG_PATH",
-        help = "planner config file path"
+        help = "planner config file path",
+        required = true
+    )]
+    planner_config_path: Option<String>,
+    #[clap(
+        short = 'm',
+        long = "map",
+        help = "map file to load (ROS map yaml or GridMap saved by save_to_file)"
+    )]
+    map_path: Option<String>,
+    #[clap(
+        long = "scenario-file",
+        help = "scenario saved by File → Save scenario",
+        conflicts_with = "map_path"
+    )]
+    scenario_path: Option<String>,
+    #[clap(
+        long = "footprint",
+        help = "footprint of the robot in yaml, e.g. '{type: circle, radius: 0.2}'"
     )]
-    planner_config_path: String,
+    footprint: Option<String>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Print which layer contributes how much to the cost of a position in the running viewer
+    ExplainCost {
+        #[clap(allow_hyphen_values = true)]
+        x: f64,
+        #[clap(allow_hyphen_values = true)]
+        y: f64,
+        #[clap(long, default_value = ENDPOINT)]
+        endpoint: String,
+    },
 }
 
 impl TryFrom<Args> for NavigationViz {
-    type Error = openrr_nav::Error;
+    type Error = openrr_nav_core::Error;
 
     fn try_from(value: Args) -> Result<Self, Self::Error> {
-        NavigationViz::new(&value.planner_config_path)
+        let nav = NavigationViz::new(&value.planner_config_path.unwrap_or_default())?;
+        if let Some(map_path) = value.map_path {
+            nav.load_map_file(map_path)?;
+        }
+        if let Some(scenario_path) = value.scenario_path {
+            nav.load_scenario_file(scenario_path)?;
+        }
+        if let Some(footprint) = value.footprint {
+            *nav.footprint.lock().unwrap() = serde_yaml::from_str(&footprint)
+                .map_err(|e| openrr_nav_core::Error::Other(format!("invalid footprint: {e}")))?;
+        }
+        Ok(nav)
     }
 }
 
+fn explain_cost(x: f64, y: f64, endpoint: String) -> Result<(), Box<dyn std::error::Error>> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?
+        .block_on(async {
+            let mut api = pb::api_client::ApiClient::connect(endpoint).await?;
+            let report = api.explain_cost(pb::Position { x, y }).await?.into_inner();
+            println!("{}", openrr_nav_core::CostReport::from(report));
+            Ok(())
+        })
+}
+
 fn main() {
-    let nav: NavigationViz = Args::parse().try_into().unwrap();
+    let args = Args::parse();
+    if let Some(Command::ExplainCost { x, y, endpoint }) = args.command {
+        explain_cost(x, y, endpoint).unwrap();
+        return;
+    }
+    let nav: NavigationViz = args.try_into().unwrap();
 
     let cloned_nav = nav.clone();
     let h = std::thread::spawn(|| {
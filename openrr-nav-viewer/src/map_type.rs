@@ -1,6 +1,9 @@
 use bevy::prelude::*;
+use bevy_egui::egui::{epaint::Hsva, Color32};
 
-#[derive(Debug, Clone, Default, Resource, PartialEq, PartialOrd)]
+use grid_map::LayerId;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd)]
 pub enum MapType {
     #[default]
     PathDistanceMap,
@@ -8,3 +11,165 @@ pub enum MapType {
     ObstacleDistanceMap,
     LocalGoalDistanceMap,
 }
+
+impl MapType {
+    pub const ALL: [MapType; 4] = [
+        MapType::PathDistanceMap,
+        MapType::GoalDistanceMap,
+        MapType::ObstacleDistanceMap,
+        MapType::LocalGoalDistanceMap,
+    ];
+
+    /// Id of the layer in the `LayeredGridMap`
+    pub fn layer_id(&self) -> LayerId {
+        match self {
+            MapType::PathDistanceMap => LayerId::PATH,
+            MapType::GoalDistanceMap => LayerId::GOAL,
+            MapType::ObstacleDistanceMap => LayerId::OBSTACLE,
+            MapType::LocalGoalDistanceMap => LayerId::LOCAL_GOAL,
+        }
+    }
+
+    /// Label shown in the side panel
+    pub fn label(&self) -> &'static str {
+        match self {
+            MapType::PathDistanceMap => "Path",
+            MapType::GoalDistanceMap => "Goal",
+            MapType::ObstacleDistanceMap => "Obstacle",
+            MapType::LocalGoalDistanceMap => "Local Goal",
+        }
+    }
+}
+
+/// How the colors of the visible layers are combined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Take the brightest channel among the layers
+    #[default]
+    Max,
+    /// Sum the channels of the layers (saturating)
+    Additive,
+    /// Color the absolute difference of the values of the first two visible layers,
+    /// to compare them. The cells without the values in both layers are blended
+    /// like [`BlendMode::Max`].
+    Difference,
+}
+
+/// Colors of the values of the layers, from the min (0.0) to the max (1.0) of each layer
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Colormap {
+    /// Red (min) to blue (max) in the hue
+    #[default]
+    Hue,
+    Viridis,
+    Turbo,
+    Grayscale,
+}
+
+impl Colormap {
+    pub const ALL: [Colormap; 4] = [
+        Colormap::Hue,
+        Colormap::Viridis,
+        Colormap::Turbo,
+        Colormap::Grayscale,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Colormap::Hue => "Hue",
+            Colormap::Viridis => "Viridis",
+            Colormap::Turbo => "Turbo",
+            Colormap::Grayscale => "Grayscale",
+        }
+    }
+
+    /// Color of the normalized value (clamped to 0.0 ..= 1.0)
+    pub fn color(&self, t: f32) -> Color32 {
+        let t = t.clamp(0.0, 1.0);
+        // Polynomial approximations of the colormaps
+        let polynomial = |coefficients: [[f32; 3]; 7]| {
+            let mut rgb = [0.0f32; 3];
+            for c in coefficients.iter().rev() {
+                for (v, c) in rgb.iter_mut().zip(c) {
+                    *v = *v * t + c;
+                }
+            }
+            let to_u8 = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+            Color32::from_rgb(to_u8(rgb[0]), to_u8(rgb[1]), to_u8(rgb[2]))
+        };
+        match self {
+            Colormap::Hue => Hsva::new(t * 255.0 / 360.0, 1.0, 1.0, 1.0).into(),
+            Colormap::Viridis => polynomial([
+                [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+                [0.105_093_04, 1.404_613_5, 1.384_590_2],
+                [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+                [-4.634_230_6, -5.799_101, -19.332_441],
+                [6.228_27, 14.179_933, 56.690_55],
+                [4.776_385, -13.745_145, -65.353_035],
+                [-5.435_456, 4.645_852_6, 26.312_435],
+            ]),
+            Colormap::Turbo => polynomial([
+                [0.135_721_38, 0.091_402_61, 0.106_673_3],
+                [4.615_392_6, 2.194_188_4, 12.641_946],
+                [-42.660_324, 4.842_966_6, -60.582_05],
+                [132.131_09, -14.185_033, 110.362_77],
+                [-152.942_4, 4.277_298_5, -89.903_11],
+                [59.286_38, 2.829_566, 27.348_25],
+                [0.0, 0.0, 0.0],
+            ]),
+            Colormap::Grayscale => Color32::from_gray((t * 255.0).round() as u8),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayerStyle {
+    pub visible: bool,
+    /// 0.0 (transparent) ..= 1.0 (opaque)
+    pub opacity: f32,
+}
+
+/// Visibility and opacity of each layer, and the overlays on them
+#[derive(Debug, Clone, Resource, PartialEq)]
+pub struct LayerDisplaySettings {
+    pub layers: Vec<(MapType, LayerStyle)>,
+    pub blend_mode: BlendMode,
+    pub colormap: Colormap,
+    /// Draw the sampled trajectories of the local planner colored by the cost
+    pub show_candidates: bool,
+    /// Draw the footprint at the poses of the local plan
+    pub sweep_footprint: bool,
+}
+
+impl Default for LayerDisplaySettings {
+    fn default() -> Self {
+        Self {
+            layers: MapType::ALL
+                .iter()
+                .map(|&map_type| {
+                    (
+                        map_type,
+                        LayerStyle {
+                            visible: map_type == MapType::default(),
+                            opacity: 1.0,
+                        },
+                    )
+                })
+                .collect(),
+            blend_mode: BlendMode::default(),
+            colormap: Colormap::default(),
+            show_candidates: true,
+            sweep_footprint: false,
+        }
+    }
+}
+
+impl LayerDisplaySettings {
+    /// Visible layers with their opacity
+    pub fn visible_layers(&self) -> impl Iterator<Item = (MapType, f32)> + '_ {
+        self.layers
+            .iter()
+            .filter(|(_, style)| style.visible && style.opacity > 0.0)
+            .map(|(map_type, style)| (*map_type, style.opacity))
+    }
+}
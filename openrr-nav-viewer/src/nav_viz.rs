@@ -1,44 +1,498 @@
+use crate::{
+    Scenario, ScenarioSnapshot, SessionFrame, SessionPlan, SessionPlayer, SessionRecorder,
+    Telemetry, TelemetrySample,
+};
 use bevy::prelude::*;
 use grid_map::*;
-use openrr_nav::*;
+use openrr_nav_core::*;
 use std::{
     collections::HashMap,
+    path::Path,
     sync::{Arc, Mutex},
 };
 
+/// Map loaded from a file or edited in the viewer
+#[derive(Debug, Clone)]
+pub struct LoadedMap {
+    /// Path of the file or the name of the scenario
+    pub name: String,
+    pub map: GridMap<u8>,
+    /// Incremented when an edit is finished, so the controller replans
+    pub revision: u64,
+}
+
+/// Run, pause and single-step of the planning loop set in the viewer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoopControl {
+    pub paused: bool,
+    /// Cycles to be run while paused
+    pub steps: usize,
+    /// Multiplier of the speed of the loop
+    pub speed: f64,
+}
+
+impl Default for LoopControl {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            steps: 0,
+            speed: 1.0,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Resource)]
 pub struct NavigationViz {
     pub layered_grid_map: Arc<Mutex<LayeredGridMap<u8>>>,
-    pub angle_table: Arc<Mutex<HashMap<String, f64>>>,
+    pub angle_table: Arc<Mutex<HashMap<LayerId, f64>>>,
     pub robot_path: Arc<Mutex<NavigationRobotPath>>,
     pub robot_pose: Arc<Mutex<Pose>>,
     pub is_run: Arc<Mutex<bool>>,
+    pub loop_control: Arc<Mutex<LoopControl>>,
     pub start_position: Arc<Mutex<Pose>>,
     pub goal_position: Arc<Mutex<Pose>>,
+    /// DWA used for the candidates, the weights and the cost breakdown
     pub planner: Arc<Mutex<DwaPlanner>>,
+    /// Global planners selectable in the viewer
+    pub global_planners: Arc<Mutex<GlobalPlannerRegistry>>,
+    /// Planner used by `plan_local_path` instead of `planner` if the config selects
+    /// another [`LocalPlanner`]
+    pub local_planner: Arc<Mutex<Option<Box<dyn LocalPlanner>>>>,
+    /// Sampled trajectories of the latest planning cycle with their total costs
+    pub candidates: Arc<Mutex<Vec<Plan>>>,
+    /// Selected plans of the recent planning cycles
+    pub telemetry: Arc<Mutex<Telemetry>>,
+    /// Session log written in each planning cycle while recording
+    pub session_recorder: Arc<Mutex<Option<SessionRecorder>>>,
+    /// Session log replayed instead of the planning loop
+    pub session_player: Arc<Mutex<Option<SessionPlayer>>>,
+    /// Error of the last recording or playback of the session, shown in the viewer
+    pub session_error: Arc<Mutex<Option<String>>>,
+    /// Initial pose set in the viewer, cleared when it is taken by the pose estimator
+    pub initial_pose: Arc<Mutex<Option<PoseWithCovariance>>>,
+    /// Uncertainty of the initial pose set in the viewer
+    pub initial_pose_std_dev: Arc<Mutex<PoseStdDev>>,
+    /// Demo scenario loaded in the viewer, whose map is used by the controller
+    pub scenario: Arc<Mutex<Option<Scenario>>>,
+    /// Footprint of the robot drawn in the viewer
+    pub footprint: Arc<Mutex<Footprint>>,
+    /// Map loaded from a file or edited in the viewer, which is used by the
+    /// controller instead of the scenario
+    pub loaded_map: Arc<Mutex<Option<LoadedMap>>>,
     planner_config_path: String,
 }
 
 impl NavigationViz {
-    pub fn new(planner_config_path: &str) -> openrr_nav::Result<Self> {
-        let planner = DwaPlanner::new_from_config(planner_config_path)?;
-        Ok(Self {
+    pub fn new(planner_config_path: &str) -> openrr_nav_core::Result<Self> {
+        let config = LocalPlannerConfig::new_from_config(planner_config_path)?;
+        let nav = Self {
             layered_grid_map: Default::default(),
             angle_table: Default::default(),
             robot_path: Default::default(),
             robot_pose: Default::default(),
             is_run: Arc::new(Mutex::new(true)),
+            loop_control: Default::default(),
             start_position: Arc::new(Mutex::new(Pose::new(Vector2::new(-1.6, -1.8), 0.0))),
             goal_position: Arc::new(Mutex::new(Pose::new(Vector2::new(5.0, 1.0), 0.0))),
-            planner: Arc::new(Mutex::new(planner)),
+            planner: Default::default(),
+            global_planners: Default::default(),
+            local_planner: Default::default(),
+            candidates: Default::default(),
+            telemetry: Default::default(),
+            session_recorder: Default::default(),
+            session_player: Default::default(),
+            session_error: Default::default(),
+            initial_pose: Default::default(),
+            initial_pose_std_dev: Default::default(),
+            scenario: Default::default(),
+            footprint: Arc::new(Mutex::new(Footprint::Circle { radius: 0.1 })),
+            loaded_map: Default::default(),
             planner_config_path: planner_config_path.to_string(),
+        };
+        nav.set_planner_config(config);
+        Ok(nav)
+    }
+
+    pub fn reload_planner(&self) -> openrr_nav_core::Result<()> {
+        let config = LocalPlannerConfig::new_from_config(&self.planner_config_path)?;
+        self.set_planner_config(config);
+        Ok(())
+    }
+
+    /// Replace `planner` if the config is the DWA, otherwise `local_planner`
+    pub fn set_planner_config(&self, config: LocalPlannerConfig) {
+        let mut local_planner = self.local_planner.lock().unwrap();
+        match config {
+            LocalPlannerConfig::Dwa(planner) => {
+                *self.planner.lock().unwrap() = planner;
+                *local_planner = None;
+            }
+            config => *local_planner = Some(config.to_planner()),
+        }
+    }
+
+    /// Set the map, the start, the goal and the weights of the scenario and restart
+    /// the run
+    pub fn load_scenario(&self, scenario: &Scenario) {
+        let start = scenario.start_pose();
+        *self.start_position.lock().unwrap() = start;
+        *self.goal_position.lock().unwrap() = scenario.goal_pose();
+        *self.robot_pose.lock().unwrap() = start;
+        self.planner
+            .lock()
+            .unwrap()
+            .map_name_weight_mut()
+            .extend(scenario.weights());
+        {
+            let mut layered_grid_map = self.layered_grid_map.lock().unwrap();
+            *layered_grid_map = Default::default();
+            layered_grid_map.add_layer(LayerId::OBSTACLE, scenario.map());
+        }
+        *self.robot_path.lock().unwrap() = Default::default();
+        self.candidates.lock().unwrap().clear();
+        self.telemetry.lock().unwrap().clear();
+        *self.scenario.lock().unwrap() = Some(scenario.clone());
+        *self.loaded_map.lock().unwrap() = None;
+        *self.is_run.lock().unwrap() = true;
+    }
+
+    /// Replace the scenario with the map, rebuild the obstacle distance layer and
+    /// restart the run
+    pub fn load_map(&self, name: &str, map: GridMap<u8>) -> openrr_nav_core::Result<()> {
+        let obstacle_distance_map = obstacle_distance_map(&map)?;
+        {
+            let mut layered_grid_map = self.layered_grid_map.lock().unwrap();
+            *layered_grid_map = Default::default();
+            layered_grid_map.add_layer(LayerId::OBSTACLE, obstacle_distance_map);
+        }
+        *self.robot_pose.lock().unwrap() = *self.start_position.lock().unwrap();
+        *self.robot_path.lock().unwrap() = Default::default();
+        self.candidates.lock().unwrap().clear();
+        self.telemetry.lock().unwrap().clear();
+        *self.scenario.lock().unwrap() = None;
+        *self.loaded_map.lock().unwrap() = Some(LoadedMap {
+            name: name.to_owned(),
+            map,
+            revision: 0,
+        });
+        *self.is_run.lock().unwrap() = true;
+        Ok(())
+    }
+
+    /// Load the map from the ROS map_server yaml or the file saved by
+    /// [`GridMap::save_to_file`]
+    pub fn load_map_file<P: AsRef<Path>>(&self, path: P) -> openrr_nav_core::Result<()> {
+        let path = path.as_ref();
+        let map = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => GridMap::from_ros_map_yaml(path)?,
+            _ => GridMap::load_from_file(path)?,
+        };
+        self.load_map(&path.display().to_string(), map)
+    }
+
+    /// Map, start, goal, weights and planner parameters of the current run, or
+    /// `None` if neither a scenario nor a map is loaded
+    pub fn scenario_snapshot(&self) -> Option<ScenarioSnapshot> {
+        let LoadedMap { name, map, .. } = self.current_map(None)?;
+        let to_array = |pose: &Pose| {
+            [
+                pose.translation.x,
+                pose.translation.y,
+                pose.rotation.angle(),
+            ]
+        };
+        let planner = self.planner.lock().unwrap();
+        Some(ScenarioSnapshot {
+            name,
+            start: to_array(&self.start_position.lock().unwrap()),
+            goal: to_array(&self.goal_position.lock().unwrap()),
+            weights: planner
+                .map_name_weight()
+                .iter()
+                .map(|(id, weight)| (id.name().to_owned(), *weight))
+                .collect(),
+            planner: planner.parameters(),
+            map,
         })
     }
 
-    pub fn reload_planner(&self) -> openrr_nav::Result<()> {
-        let planner = DwaPlanner::new_from_config(&self.planner_config_path)?;
-        let mut locked_planner = self.planner.lock().unwrap();
-        *locked_planner = planner;
+    pub fn save_scenario_file<P: AsRef<Path>>(&self, path: P) -> openrr_nav_core::Result<()> {
+        let snapshot = self
+            .scenario_snapshot()
+            .ok_or_else(|| openrr_nav_core::Error::Other("no map to save".to_owned()))?;
+        Ok(snapshot.save_to_file(path)?)
+    }
+
+    /// Load the file saved by [`NavigationViz::save_scenario_file`] and restart the
+    /// run
+    pub fn load_scenario_file<P: AsRef<Path>>(&self, path: P) -> openrr_nav_core::Result<()> {
+        let snapshot = ScenarioSnapshot::load_from_file(path)?;
+        {
+            let mut planner = self.planner.lock().unwrap();
+            planner.set_parameters(snapshot.planner.clone())?;
+            *planner.map_name_weight_mut() = snapshot.weights();
+        }
+        *self.start_position.lock().unwrap() = snapshot.start_pose();
+        *self.goal_position.lock().unwrap() = snapshot.goal_pose();
+        self.load_map(&snapshot.name, snapshot.map)
+    }
+
+    /// Paint the cells within the radius (m) as obstacles (or clear them if
+    /// `obstacle` is false) and rebuild the obstacle distance layer
+    ///
+    /// The edited map replaces the scenario. The map in the obstacle layer is used if
+    /// neither a scenario nor a map is loaded. Call [`NavigationViz::finish_map_edit`]
+    /// to replan.
+    pub fn edit_map(
+        &self,
+        center: &Position,
+        radius: f64,
+        obstacle: bool,
+    ) -> openrr_nav_core::Result<()> {
+        let mut loaded_map = self.loaded_map.lock().unwrap();
+        if loaded_map.is_none() {
+            let scenario = self.scenario.lock().unwrap().take();
+            *loaded_map = match scenario {
+                Some(scenario) => Some(LoadedMap {
+                    map: scenario.map(),
+                    name: scenario.name,
+                    revision: 0,
+                }),
+                None => self
+                    .layered_grid_map
+                    .lock()
+                    .unwrap()
+                    .layer(LayerId::OBSTACLE)
+                    .map(|layer| {
+                        let mut map = layer.clone();
+                        for cell in map.cells_mut() {
+                            if !cell.is_obstacle() {
+                                *cell = Cell::Value(0);
+                            }
+                        }
+                        LoadedMap {
+                            name: "edited map".to_owned(),
+                            map,
+                            revision: 0,
+                        }
+                    }),
+            };
+        }
+        let Some(LoadedMap { map, .. }) = &mut *loaded_map else {
+            return Err(openrr_nav_core::Error::Other("no map to edit".to_owned()));
+        };
+        let grids = map
+            .cells_in_radius(center, radius)
+            .map(|(grid, _)| grid)
+            .collect::<Vec<_>>();
+        for grid in &grids {
+            if obstacle {
+                map.set_obstacle(grid);
+            } else {
+                map.set_value(grid, 0);
+            }
+        }
+        self.layered_grid_map
+            .lock()
+            .unwrap()
+            .add_layer(LayerId::OBSTACLE, obstacle_distance_map(map)?);
+        Ok(())
+    }
+
+    /// Finish the edit of the map and replan
+    pub fn finish_map_edit(&self) {
+        if let Some(loaded_map) = &mut *self.loaded_map.lock().unwrap() {
+            loaded_map.revision += 1;
+        }
+        *self.is_run.lock().unwrap() = true;
+    }
+
+    /// Whether the planning loop can run the next cycle, which consumes a step if
+    /// it is paused
+    pub fn start_cycle(&self) -> bool {
+        let mut loop_control = self.loop_control.lock().unwrap();
+        if !loop_control.paused {
+            true
+        } else if loop_control.steps > 0 {
+            loop_control.steps -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Record the planning cycle to the session log while recording, and the lowest
+    /// cost candidate to `telemetry`
+    ///
+    /// Nothing is recorded to `telemetry` if there are no candidates, e.g. the local
+    /// planner is not the DWA.
+    pub fn record_cycle(&self) {
+        if let Err(e) = self.record_session_frame() {
+            *self.session_error.lock().unwrap() =
+                Some(format!("failed to record the session, stop recording: {e}"));
+            *self.session_recorder.lock().unwrap() = None;
+        }
+        let layered_grid_map = self.layered_grid_map.lock().unwrap();
+        let angle_table = self.angle_table.lock().unwrap();
+        let planner = self.planner.lock().unwrap();
+        let candidates = self.candidates.lock().unwrap();
+        let Some(selected) = candidates
+            .iter()
+            .filter(|c| c.cost.is_finite())
+            .min_by(|a, b| a.cost.total_cmp(&b.cost))
+        else {
+            return;
+        };
+        let mut layer_costs = planner
+            .map_name_weight()
+            .iter()
+            .map(|(id, weight)| {
+                let cost = DwaPlanner::score_plan(
+                    selected,
+                    &layered_grid_map,
+                    &angle_table,
+                    &HashMap::from([(*id, *weight)]),
+                );
+                (*id, cost)
+            })
+            .collect::<Vec<_>>();
+        layer_costs.sort_by(|a, b| a.0.name().cmp(b.0.name()));
+        let goal = self.goal_position.lock().unwrap().translation.vector;
+        let pose = self.robot_pose.lock().unwrap().translation.vector;
+        let mut telemetry = self.telemetry.lock().unwrap();
+        let time = telemetry.next_time(planner.controller_dt());
+        telemetry.push(TelemetrySample {
+            time,
+            velocity: selected.velocity,
+            cost: selected.cost,
+            layer_costs,
+            distance_to_goal: (goal - pose).norm(),
+        });
+    }
+
+    pub fn start_recording<P: AsRef<Path>>(&self, path: P) -> openrr_nav_core::Result<()> {
+        *self.session_recorder.lock().unwrap() = Some(SessionRecorder::create(path)?);
+        *self.session_error.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// Stop recording and return the number of the recorded frames
+    pub fn stop_recording(&self) -> Option<usize> {
+        self.session_recorder
+            .lock()
+            .unwrap()
+            .take()
+            .map(|recorder| recorder.num_frames())
+    }
+
+    fn record_session_frame(&self) -> openrr_nav_core::Result<()> {
+        if self.session_recorder.lock().unwrap().is_none() {
+            return Ok(());
+        }
+        let layered_grid_map = self.layered_grid_map.lock().unwrap().clone();
+        let mut angles = self
+            .angle_table
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, angle)| (*id, *angle))
+            .collect::<Vec<_>>();
+        angles.sort_by(|a, b| a.0.name().cmp(b.0.name()));
+        let dt = self.planner.lock().unwrap().controller_dt();
+        let candidates = self
+            .candidates
+            .lock()
+            .unwrap()
+            .iter()
+            .map(SessionPlan::from)
+            .collect::<Vec<_>>();
+        let robot_path = self.robot_path.lock().unwrap().clone();
+        let pose = *self.robot_pose.lock().unwrap();
+        let goal = *self.goal_position.lock().unwrap();
+
+        let mut recorder = self.session_recorder.lock().unwrap();
+        let Some(recorder) = &mut *recorder else {
+            return Ok(());
+        };
+        let frame = SessionFrame {
+            time: recorder.next_time(dt),
+            pose,
+            goal,
+            global_path: recorder.global_path_update(&robot_path.global_path().0),
+            local_path: robot_path.local_path().0.clone(),
+            command: candidates
+                .iter()
+                .filter(|c| c.cost.is_finite())
+                .min_by(|a, b| a.cost.total_cmp(&b.cost))
+                .map(|c| c.velocity),
+            candidates,
+            layers: recorder.layer_updates(&layered_grid_map),
+            angles,
+        };
+        recorder.write(&frame)
+    }
+
+    /// Open the session log and pause the planning loop to replay it
+    pub fn open_session<P: AsRef<Path>>(&self, path: P) -> openrr_nav_core::Result<()> {
+        let mut player = SessionPlayer::open(path)?;
+        let mut loop_control = self.loop_control.lock().unwrap();
+        let mut session_player = self.session_player.lock().unwrap();
+        // keep the state before the first session
+        player.resume = match &*session_player {
+            Some(opened) => opened.resume,
+            None => !loop_control.paused,
+        };
+        loop_control.paused = true;
+        loop_control.steps = 0;
+        *session_player = Some(player);
+        *self.session_error.lock().unwrap() = None;
+        drop(session_player);
+        drop(loop_control);
+        self.show_session_frame()
+    }
+
+    /// Close the session log and resume the planning loop if it was running
+    pub fn close_session(&self) {
+        if let Some(player) = self.session_player.lock().unwrap().take() {
+            if player.resume {
+                self.loop_control.lock().unwrap().paused = false;
+            }
+        }
+    }
+
+    /// Show the current frame of the session log in the viewer
+    pub fn show_session_frame(&self) -> openrr_nav_core::Result<()> {
+        let session_player = self.session_player.lock().unwrap();
+        let Some(player) = &*session_player else {
+            return Ok(());
+        };
+        let (layers, global_path) = player.state()?;
+        let frame = player.frame();
+        *self.layered_grid_map.lock().unwrap() = layers;
+        *self.angle_table.lock().unwrap() = frame.angles.iter().copied().collect();
+        *self.robot_path.lock().unwrap() =
+            NavigationRobotPath::new(RobotPath(frame.local_path.clone()), RobotPath(global_path));
+        *self.candidates.lock().unwrap() =
+            frame.candidates.iter().cloned().map(Into::into).collect();
+        *self.robot_pose.lock().unwrap() = frame.pose;
+        *self.goal_position.lock().unwrap() = frame.goal;
         Ok(())
     }
+
+    /// Map used by the controller: the loaded map, or the map of the scenario at the
+    /// time (s) (the static one if it is not set)
+    pub fn current_map(&self, time: Option<f64>) -> Option<LoadedMap> {
+        if let Some(loaded_map) = &*self.loaded_map.lock().unwrap() {
+            return Some(loaded_map.clone());
+        }
+        self.scenario
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|scenario| LoadedMap {
+                name: scenario.name.clone(),
+                map: time.map_or_else(|| scenario.map(), |t| scenario.map_at(t)),
+                revision: 0,
+            })
+    }
 }
@@ -0,0 +1,181 @@
+use grid_map::{GridMap, LayerId, Shape, World};
+use nalgebra::Vector2;
+use openrr_nav_core::{DwaParameters, Pose};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+
+const BUILTIN_SCENARIOS: [&str; 4] = [
+    include_str!("../scenarios/maze.yaml"),
+    include_str!("../scenarios/office.yaml"),
+    include_str!("../scenarios/warehouse.yaml"),
+    include_str!("../scenarios/dynamic_pedestrian.yaml"),
+];
+
+/// Circular obstacle walking back and forth between two points
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pedestrian {
+    pub from: [f64; 2],
+    pub to: [f64; 2],
+    pub radius: f64,
+    /// (m/s)
+    pub speed: f64,
+}
+
+impl Pedestrian {
+    /// Position at the time (s) from the start of the run
+    pub fn position_at(&self, time: f64) -> [f64; 2] {
+        let diff = [self.to[0] - self.from[0], self.to[1] - self.from[1]];
+        let length = diff[0].hypot(diff[1]);
+        if length == 0.0 {
+            return self.from;
+        }
+        let s = (self.speed * time.max(0.0)).rem_euclid(2.0 * length);
+        let d = if s <= length { s } else { 2.0 * length - s } / length;
+        [self.from[0] + diff[0] * d, self.from[1] + diff[1] * d]
+    }
+}
+
+/// Demo world with the start, the goal and the recommended weights of the planner
+///
+/// ```yaml
+/// name: example
+/// resolution: 0.05
+/// start: [0.5, 0.5, 0.0] # x, y, yaw
+/// goal: [3.5, 1.5, 1.57]
+/// weights:
+///   path: 0.8
+///   obstacle: 0.5
+/// world:
+///   min_point: [0.0, 0.0]
+///   max_point: [4.0, 2.0]
+///   obstacles:
+///     - type: circle
+///       center: [2.0, 1.0]
+///       radius: 0.3
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub resolution: f64,
+    pub start: [f64; 3],
+    pub goal: [f64; 3],
+    /// Weights by the layer name. The layers which are not listed keep their weights.
+    #[serde(default)]
+    pub weights: HashMap<String, f64>,
+    pub world: World,
+    /// Moving obstacles, which are not in the map for the global path
+    #[serde(default)]
+    pub pedestrians: Vec<Pedestrian>,
+}
+
+impl Scenario {
+    pub fn from_yaml_str(yaml: &str) -> grid_map::Result<Self> {
+        let scenario: Self = serde_yaml::from_str(yaml)?;
+        scenario.world.validate()?;
+        if scenario.resolution <= 0.0 {
+            return Err(grid_map::Error::Other(format!(
+                "resolution of the scenario {} must be positive",
+                scenario.name
+            )));
+        }
+        Ok(scenario)
+    }
+
+    pub fn start_pose(&self) -> Pose {
+        let [x, y, yaw] = self.start;
+        Pose::new(Vector2::new(x, y), yaw)
+    }
+
+    pub fn goal_pose(&self) -> Pose {
+        let [x, y, yaw] = self.goal;
+        Pose::new(Vector2::new(x, y), yaw)
+    }
+
+    pub fn weights(&self) -> HashMap<LayerId, f64> {
+        self.weights
+            .iter()
+            .map(|(name, weight)| (LayerId::new(name), *weight))
+            .collect()
+    }
+
+    /// Static map without the pedestrians
+    pub fn map(&self) -> GridMap<u8> {
+        self.world.rasterize(self.resolution)
+    }
+
+    /// Map with the pedestrians at the time (s) from the start of the run
+    pub fn map_at(&self, time: f64) -> GridMap<u8> {
+        let mut world = self.world.clone();
+        world
+            .obstacles
+            .extend(self.pedestrians.iter().map(|p| Shape::Circle {
+                center: p.position_at(time),
+                radius: p.radius,
+            }));
+        world.rasterize(self.resolution)
+    }
+}
+
+/// Map, start, goal, weights and planner parameters saved by "Save scenario" to
+/// reproduce the situation, e.g. in bug reports
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScenarioSnapshot {
+    pub name: String,
+    pub start: [f64; 3],
+    pub goal: [f64; 3],
+    /// Weights by the layer name
+    pub weights: BTreeMap<String, f64>,
+    pub planner: DwaParameters,
+    pub map: GridMap<u8>,
+}
+
+impl ScenarioSnapshot {
+    pub fn from_yaml_str(yaml: &str) -> grid_map::Result<Self> {
+        let snapshot: Self = serde_yaml::from_str(yaml)?;
+        snapshot.map.validate()?;
+        Ok(snapshot)
+    }
+
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> grid_map::Result<Self> {
+        Self::from_yaml_str(&fs::read_to_string(path)?)
+    }
+
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> grid_map::Result<()> {
+        fs::write(path, serde_yaml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn start_pose(&self) -> Pose {
+        let [x, y, yaw] = self.start;
+        Pose::new(Vector2::new(x, y), yaw)
+    }
+
+    pub fn goal_pose(&self) -> Pose {
+        let [x, y, yaw] = self.goal;
+        Pose::new(Vector2::new(x, y), yaw)
+    }
+
+    pub fn weights(&self) -> HashMap<LayerId, f64> {
+        self.weights
+            .iter()
+            .map(|(name, weight)| (LayerId::new(name), *weight))
+            .collect()
+    }
+}
+
+/// Maze, office, warehouse and dynamic_pedestrian scenarios
+pub fn builtin_scenarios() -> Vec<Scenario> {
+    BUILTIN_SCENARIOS
+        .iter()
+        .map(|yaml| Scenario::from_yaml_str(yaml).expect("invalid builtin scenario"))
+        .collect()
+}
@@ -0,0 +1,273 @@
+use grid_map::{Cell, GridMap, LayerId, LayeredGridMap};
+use openrr_nav_core::{Error, Plan, Pose, Result, Velocity};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 8] = b"NAVSES01";
+
+/// Change of a layer from the previous frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LayerUpdate {
+    /// The first frame of the layer, or the size is changed
+    Full(LayerId, GridMap<u8>),
+    /// Indices and values of the changed cells
+    Diff(LayerId, Vec<(u32, Cell<u8>)>),
+    Removed(LayerId),
+}
+
+/// Candidate trajectory in the session log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionPlan {
+    pub velocity: Velocity,
+    pub cost: f64,
+    pub path: Vec<Pose>,
+}
+
+impl From<&Plan> for SessionPlan {
+    fn from(plan: &Plan) -> Self {
+        Self {
+            velocity: plan.velocity,
+            cost: plan.cost,
+            path: plan.path.clone(),
+        }
+    }
+}
+
+impl From<SessionPlan> for Plan {
+    fn from(plan: SessionPlan) -> Self {
+        Self {
+            velocity: plan.velocity,
+            cost: plan.cost,
+            path: plan.path,
+            sampling_issue: None,
+        }
+    }
+}
+
+/// Planning cycle recorded in the session log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFrame {
+    /// (s) from the start of the recording
+    pub time: f64,
+    pub pose: Pose,
+    pub goal: Pose,
+    /// Set if the global path is changed from the previous frame
+    pub global_path: Option<Vec<Pose>>,
+    pub local_path: Vec<Pose>,
+    pub candidates: Vec<SessionPlan>,
+    /// Command of the lowest cost candidate
+    pub command: Option<Velocity>,
+    pub layers: Vec<LayerUpdate>,
+    pub angles: Vec<(LayerId, f64)>,
+}
+
+/// Writer of the planning cycles of the viewer to the session log
+///
+/// The log is the header followed by the frames in bincode. Only the changes of the
+/// layers and the global path are written after the first frame.
+#[derive(Debug)]
+pub struct SessionRecorder {
+    writer: BufWriter<File>,
+    num_frames: usize,
+    last_time: Option<f64>,
+    global_path: Vec<Pose>,
+    layers: HashMap<LayerId, GridMap<u8>>,
+}
+
+impl SessionRecorder {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            writer,
+            num_frames: 0,
+            last_time: None,
+            global_path: vec![],
+            layers: HashMap::new(),
+        })
+    }
+
+    pub fn num_frames(&self) -> usize {
+        self.num_frames
+    }
+
+    /// Time (s) of the cycle after the last one
+    pub fn next_time(&self, dt: f64) -> f64 {
+        self.last_time.map_or(0.0, |t| t + dt)
+    }
+
+    /// The global path if it is changed from the last call
+    pub fn global_path_update(&mut self, global_path: &[Pose]) -> Option<Vec<Pose>> {
+        if self.num_frames > 0 && self.global_path == global_path {
+            return None;
+        }
+        self.global_path = global_path.to_vec();
+        Some(self.global_path.clone())
+    }
+
+    /// Changes of the layers from the last call
+    pub fn layer_updates(&mut self, maps: &LayeredGridMap<u8>) -> Vec<LayerUpdate> {
+        let mut ids = maps.layer_ids().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.name());
+        let mut updates = vec![];
+        self.layers.retain(|id, _| {
+            let exists = maps.contains(*id);
+            if !exists {
+                updates.push(LayerUpdate::Removed(*id));
+            }
+            exists
+        });
+        for id in ids {
+            let map = maps.layer(id).unwrap();
+            match self.layers.get(&id) {
+                Some(last)
+                    if last.width() == map.width()
+                        && last.height() == map.height()
+                        && last.min_point() == map.min_point() =>
+                {
+                    let cells = last
+                        .cells()
+                        .iter()
+                        .zip(map.cells())
+                        .enumerate()
+                        .filter(|(_, (last, cell))| last != cell)
+                        .map(|(i, (_, cell))| (i as u32, *cell))
+                        .collect::<Vec<_>>();
+                    if !cells.is_empty() {
+                        updates.push(LayerUpdate::Diff(id, cells));
+                    }
+                }
+                _ => updates.push(LayerUpdate::Full(id, map.clone())),
+            }
+            self.layers.insert(id, map.clone());
+        }
+        updates
+    }
+
+    pub fn write(&mut self, frame: &SessionFrame) -> Result<()> {
+        bincode::serialize_into(&mut self.writer, frame)?;
+        self.writer.flush()?;
+        self.num_frames += 1;
+        self.last_time = Some(frame.time);
+        Ok(())
+    }
+}
+
+/// Frames of the session log replayed in the viewer
+#[derive(Debug)]
+pub struct SessionPlayer {
+    frames: Vec<SessionFrame>,
+    /// Frame shown in the viewer
+    pub index: usize,
+    pub playing: bool,
+    /// (s) since the frame is shown while playing
+    pub(crate) elapsed: f64,
+    /// Whether the planning loop is resumed when the player is closed
+    pub(crate) resume: bool,
+}
+
+impl SessionPlayer {
+    /// Read the log written by [`SessionRecorder`]. The broken last frame (e.g. the
+    /// viewer was killed) is ignored.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Other("not a session log".to_owned()));
+        }
+        let mut frames = vec![];
+        loop {
+            match bincode::deserialize_from(&mut reader) {
+                Ok(frame) => frames.push(frame),
+                Err(e) => match &*e {
+                    bincode::ErrorKind::Io(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                    _ => return Err(e.into()),
+                },
+            }
+        }
+        if frames.is_empty() {
+            return Err(Error::Other("no frames in the session log".to_owned()));
+        }
+        let mut player = Self {
+            index: frames.len() - 1,
+            frames,
+            playing: false,
+            elapsed: 0.0,
+            resume: false,
+        };
+        // check the diffs of all frames
+        player.state()?;
+        player.index = 0;
+        Ok(player)
+    }
+
+    pub fn frames(&self) -> &[SessionFrame] {
+        &self.frames
+    }
+
+    pub fn frame(&self) -> &SessionFrame {
+        &self.frames[self.index]
+    }
+
+    /// Layers and the global path at the frame, which are reconstructed from the
+    /// first frame
+    pub fn state(&self) -> Result<(LayeredGridMap<u8>, Vec<Pose>)> {
+        let mut layers = HashMap::new();
+        let mut global_path = vec![];
+        for frame in &self.frames[..=self.index] {
+            if let Some(path) = &frame.global_path {
+                global_path = path.clone();
+            }
+            for update in &frame.layers {
+                match update {
+                    LayerUpdate::Full(id, map) => {
+                        layers.insert(*id, map.clone());
+                    }
+                    LayerUpdate::Removed(id) => {
+                        layers.remove(id);
+                    }
+                    LayerUpdate::Diff(id, cells) => {
+                        let map = layers.get_mut(id).ok_or_else(|| {
+                            Error::Other(format!("diff of {id} before the layer"))
+                        })?;
+                        for (i, cell) in cells {
+                            *map.cells_mut().get_mut(*i as usize).ok_or_else(|| {
+                                Error::Other(format!("out of range cell {i} of {id}"))
+                            })? = *cell;
+                        }
+                    }
+                }
+            }
+        }
+        Ok((LayeredGridMap::new(layers), global_path))
+    }
+
+    /// Advance the frame by the time (s) while playing. Returns true if the frame is
+    /// changed.
+    pub fn advance(&mut self, dt: f64) -> bool {
+        if !self.playing {
+            return false;
+        }
+        self.elapsed += dt;
+        let mut changed = false;
+        while let Some(next) = self.frames.get(self.index + 1) {
+            let period = next.time - self.frames[self.index].time;
+            if self.elapsed < period {
+                break;
+            }
+            self.elapsed -= period;
+            self.index += 1;
+            changed = true;
+        }
+        if self.index + 1 == self.frames.len() {
+            self.playing = false;
+        }
+        changed
+    }
+}
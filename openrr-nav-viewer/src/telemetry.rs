@@ -0,0 +1,48 @@
+use grid_map::LayerId;
+use openrr_nav_core::Velocity;
+use std::collections::VecDeque;
+
+/// Values of a planning cycle plotted in the viewer
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetrySample {
+    /// (s) from the first recorded cycle
+    pub time: f64,
+    /// Command of the selected plan
+    pub velocity: Velocity,
+    /// Total cost of the selected plan
+    pub cost: f64,
+    /// Weighted cost of each layer of the selected plan, sorted by the name
+    pub layer_costs: Vec<(LayerId, f64)>,
+    /// (m)
+    pub distance_to_goal: f64,
+}
+
+/// Recent planning cycles, the oldest ones are dropped
+#[derive(Debug, Clone, Default)]
+pub struct Telemetry {
+    samples: VecDeque<TelemetrySample>,
+}
+
+impl Telemetry {
+    pub const MAX_SAMPLES: usize = 600;
+
+    pub fn push(&mut self, sample: TelemetrySample) {
+        if self.samples.len() == Self::MAX_SAMPLES {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &TelemetrySample> {
+        self.samples.iter()
+    }
+
+    /// Time (s) of the cycle after the last one
+    pub fn next_time(&self, dt: f64) -> f64 {
+        self.samples.back().map_or(0.0, |s| s.time + dt)
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
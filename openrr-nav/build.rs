@@ -0,0 +1,71 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_navigation_service();
+}
+
+/// The messages are defined in `src/grpc.rs`, so protoc is not required
+#[cfg(feature = "grpc")]
+fn compile_navigation_service() {
+    use tonic_build::manual::{Method, Service};
+
+    let method = |name: &str, route_name: &str, input_type: &str, output_type: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(input_type)
+            .output_type(output_type)
+            .codec_path("tonic::codec::ProstCodec")
+    };
+    let service = Service::builder()
+        .name("Navigation")
+        .package("openrr_nav")
+        .method(
+            method(
+                "set_goal",
+                "SetGoal",
+                "super::SetGoalRequest",
+                "super::Empty",
+            )
+            .build(),
+        )
+        .method(method("cancel_goal", "CancelGoal", "super::Empty", "super::Empty").build())
+        .method(
+            method(
+                "get_status",
+                "GetStatus",
+                "super::Empty",
+                "super::NavigationStatus",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "stream_local_plan",
+                "StreamLocalPlan",
+                "super::Empty",
+                "super::LocalPlan",
+            )
+            .server_streaming()
+            .build(),
+        )
+        .method(
+            method(
+                "get_parameters",
+                "GetParameters",
+                "super::Empty",
+                "super::Parameters",
+            )
+            .build(),
+        )
+        .method(
+            method(
+                "set_parameters",
+                "SetParameters",
+                "super::Parameters",
+                "super::Empty",
+            )
+            .build(),
+        )
+        .build();
+    tonic_build::manual::Builder::new().compile(&[service]);
+}
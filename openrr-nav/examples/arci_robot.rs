@@ -0,0 +1,57 @@
+// Navigate a robot through the arci traits.
+//
+// Replace the dummy clients in `main` with the clients of the robot, e.g. the
+// `MoveBase` publishing the velocity commands and the `Localization` subscribing the
+// pose of arci-ros.
+//
+// How to run:
+//
+// ```sh
+// cargo run --release -p openrr-nav --example arci_robot -- [MAP_FILE]
+// ```
+
+mod shared;
+
+use anyhow::Result;
+use arci::{DummyLocalization, DummyMoveBase, Localization, MoveBase};
+use grid_map::*;
+use openrr_nav::*;
+use shared::*;
+use std::time::Duration;
+
+fn run(move_base: impl MoveBase, localization: impl Localization, map: GridMap<u8>) -> Result<()> {
+    let planner = DwaPlanner::new_from_config(format!(
+        "{}/../openrr-nav-core/config/dwa_parameter_config.yaml",
+        env!("CARGO_MANIFEST_DIR")
+    ))?;
+    let config = NavigationConfig {
+        goal_threshold: 0.1,
+        period: Duration::from_secs_f64(planner.controller_dt()),
+        max_cycles: 50,
+        realtime: true,
+    };
+    let goal = Pose::new(nalgebra::Vector2::new(1.5, 0.5), 0.0);
+    if navigate(&move_base, &localization, &planner, &map, &goal, &config)? {
+        println!("arrived at the goal");
+    } else {
+        println!("the goal is not reached in {} cycles", config.max_cycles);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let map = match std::env::args().nth(1) {
+        Some(path) => GridMap::load_from_file(path)?,
+        None => {
+            let mut map =
+                GridMap::new(Position::new(-1.05, -1.05), Position::new(2.05, 2.05), 0.05);
+            for y in 20..40 {
+                map.set_obstacle(&Grid::new(40, y)).unwrap();
+            }
+            map
+        }
+    };
+    // The dummy localization does not follow the commands, so the loop ends after
+    // `max_cycles`.
+    run(DummyMoveBase::new(), DummyLocalization::new(), map)
+}
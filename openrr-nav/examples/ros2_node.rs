@@ -0,0 +1,290 @@
+// ROS 2 node of the navigator like nav2 for the simple robots.
+//
+// Subscribes /map, /odom, /scan and /goal_pose, runs the `Navigator` through the
+// `NavigationBridge` in every control period, and publishes /cmd_vel and /plan.
+// The odometry is used as the pose in the map frame.
+//
+// Building r2r needs a sourced ROS 2 installation with nav_msgs and sensor_msgs.
+//
+// How to run:
+//
+// ```sh
+// source /opt/ros/humble/setup.bash
+// cargo run --release -p openrr-nav --features r2r --example ros2_node -- [DWA_CONFIG_FILE]
+// ```
+
+use anyhow::Result;
+use futures::{
+    executor::{LocalPool, LocalSpawner},
+    task::LocalSpawnExt,
+    Stream, StreamExt,
+};
+use grid_map::{GridMap, Position};
+use openrr_nav::{
+    ros::{geometry_msgs, nav_msgs, sensor_msgs, std_msgs},
+    *,
+};
+use r2r::QosProfile;
+use std::{cell::RefCell, rc::Rc, time::Duration};
+
+const FRAME_ID: &str = "map";
+/// (m)
+const MAX_SCAN_RANGE: f64 = 3.0;
+
+type SharedBridge = Rc<RefCell<NavigationBridge>>;
+
+// The messages of `openrr_nav::ros` have the fields of ROS1, so the headers are
+// converted here and the other fields are copied.
+
+fn header(header: &r2r::std_msgs::msg::Header) -> std_msgs::Header {
+    std_msgs::Header {
+        seq: 0,
+        stamp: std_msgs::Time {
+            secs: header.stamp.sec.max(0) as u32,
+            nsecs: header.stamp.nanosec,
+        },
+        frame_id: header.frame_id.clone(),
+    }
+}
+
+fn ros2_header(header: &std_msgs::Header) -> r2r::std_msgs::msg::Header {
+    r2r::std_msgs::msg::Header {
+        stamp: r2r::builtin_interfaces::msg::Time {
+            sec: header.stamp.secs as i32,
+            nanosec: header.stamp.nsecs,
+        },
+        frame_id: header.frame_id.clone(),
+    }
+}
+
+fn pose(pose: &r2r::geometry_msgs::msg::Pose) -> geometry_msgs::Pose {
+    geometry_msgs::Pose {
+        position: geometry_msgs::Point {
+            x: pose.position.x,
+            y: pose.position.y,
+            z: pose.position.z,
+        },
+        orientation: geometry_msgs::Quaternion {
+            x: pose.orientation.x,
+            y: pose.orientation.y,
+            z: pose.orientation.z,
+            w: pose.orientation.w,
+        },
+    }
+}
+
+fn ros2_pose(pose: &geometry_msgs::Pose) -> r2r::geometry_msgs::msg::Pose {
+    r2r::geometry_msgs::msg::Pose {
+        position: r2r::geometry_msgs::msg::Point {
+            x: pose.position.x,
+            y: pose.position.y,
+            z: pose.position.z,
+        },
+        orientation: r2r::geometry_msgs::msg::Quaternion {
+            x: pose.orientation.x,
+            y: pose.orientation.y,
+            z: pose.orientation.z,
+            w: pose.orientation.w,
+        },
+    }
+}
+
+fn vector3(v: &r2r::geometry_msgs::msg::Vector3) -> geometry_msgs::Vector3 {
+    geometry_msgs::Vector3 {
+        x: v.x,
+        y: v.y,
+        z: v.z,
+    }
+}
+
+fn ros2_vector3(v: &geometry_msgs::Vector3) -> r2r::geometry_msgs::msg::Vector3 {
+    r2r::geometry_msgs::msg::Vector3 {
+        x: v.x,
+        y: v.y,
+        z: v.z,
+    }
+}
+
+fn occupancy_grid(map: &r2r::nav_msgs::msg::OccupancyGrid) -> nav_msgs::OccupancyGrid {
+    nav_msgs::OccupancyGrid {
+        header: header(&map.header),
+        info: nav_msgs::MapMetaData {
+            map_load_time: std_msgs::Time {
+                secs: map.info.map_load_time.sec.max(0) as u32,
+                nsecs: map.info.map_load_time.nanosec,
+            },
+            resolution: map.info.resolution,
+            width: map.info.width,
+            height: map.info.height,
+            origin: pose(&map.info.origin),
+        },
+        data: map.data.clone(),
+    }
+}
+
+fn odometry(odometry: &r2r::nav_msgs::msg::Odometry) -> nav_msgs::Odometry {
+    nav_msgs::Odometry {
+        header: header(&odometry.header),
+        child_frame_id: odometry.child_frame_id.clone(),
+        pose: geometry_msgs::PoseWithCovariance {
+            pose: pose(&odometry.pose.pose),
+            covariance: odometry.pose.covariance.clone(),
+        },
+        twist: geometry_msgs::TwistWithCovariance {
+            twist: geometry_msgs::Twist {
+                linear: vector3(&odometry.twist.twist.linear),
+                angular: vector3(&odometry.twist.twist.angular),
+            },
+            covariance: odometry.twist.covariance.clone(),
+        },
+    }
+}
+
+fn laser_scan(scan: &r2r::sensor_msgs::msg::LaserScan) -> sensor_msgs::LaserScan {
+    sensor_msgs::LaserScan {
+        header: header(&scan.header),
+        angle_min: scan.angle_min,
+        angle_max: scan.angle_max,
+        angle_increment: scan.angle_increment,
+        time_increment: scan.time_increment,
+        scan_time: scan.scan_time,
+        range_min: scan.range_min,
+        range_max: scan.range_max,
+        ranges: scan.ranges.clone(),
+        intensities: scan.intensities.clone(),
+    }
+}
+
+fn pose_stamped(goal: &r2r::geometry_msgs::msg::PoseStamped) -> geometry_msgs::PoseStamped {
+    geometry_msgs::PoseStamped {
+        header: header(&goal.header),
+        pose: pose(&goal.pose),
+    }
+}
+
+fn ros2_twist(twist: &geometry_msgs::Twist) -> r2r::geometry_msgs::msg::Twist {
+    r2r::geometry_msgs::msg::Twist {
+        linear: ros2_vector3(&twist.linear),
+        angular: ros2_vector3(&twist.angular),
+    }
+}
+
+fn ros2_path(path: &nav_msgs::Path) -> r2r::nav_msgs::msg::Path {
+    r2r::nav_msgs::msg::Path {
+        header: ros2_header(&path.header),
+        poses: path
+            .poses
+            .iter()
+            .map(|p| r2r::geometry_msgs::msg::PoseStamped {
+                header: ros2_header(&p.header),
+                pose: ros2_pose(&p.pose),
+            })
+            .collect(),
+    }
+}
+
+/// Pass the messages of the subscription to the bridge
+fn forward<T: 'static>(
+    spawner: &LocalSpawner,
+    mut subscription: impl Stream<Item = T> + Unpin + 'static,
+    bridge: &SharedBridge,
+    callback: impl Fn(&mut NavigationBridge, &T) + 'static,
+) -> Result<()> {
+    let bridge = bridge.clone();
+    spawner.spawn_local(async move {
+        while let Some(msg) = subscription.next().await {
+            callback(&mut bridge.borrow_mut(), &msg);
+        }
+    })?;
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let config_path = std::env::args().nth(1).unwrap_or_else(|| {
+        format!(
+            "{}/../openrr-nav-core/config/turtlebot_dwa_config.yaml",
+            env!("CARGO_MANIFEST_DIR")
+        )
+    });
+    let planner = DwaPlanner::new_from_config(config_path)?;
+    let period = Duration::from_secs_f64(planner.controller_dt());
+    // replaced by the first message of /map
+    let map = GridMap::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.05);
+    let navigator = Navigator::new(Box::new(AStarPlanner::default()), Box::new(planner), map);
+    let bridge = Rc::new(RefCell::new(
+        NavigationBridge::new(navigator, FRAME_ID)
+            .with_scan_integrator(ScanIntegrator::new(MAX_SCAN_RANGE)),
+    ));
+
+    let ctx = r2r::Context::create()?;
+    let mut node = r2r::Node::create(ctx, "openrr_nav", "")?;
+    let mut pool = LocalPool::new();
+    let spawner = pool.spawner();
+
+    // the map server publishes the map only once
+    let map_qos = QosProfile::default().transient_local();
+    forward(
+        &spawner,
+        node.subscribe::<r2r::nav_msgs::msg::OccupancyGrid>(MAP_TOPIC, map_qos)?,
+        &bridge,
+        |bridge, map| {
+            if let Err(e) = bridge.on_map(&occupancy_grid(map)) {
+                eprintln!("failed to set the map: {e}");
+            }
+        },
+    )?;
+    forward(
+        &spawner,
+        node.subscribe::<r2r::nav_msgs::msg::Odometry>(ODOM_TOPIC, QosProfile::default())?,
+        &bridge,
+        |bridge, msg| bridge.on_odom(&odometry(msg)),
+    )?;
+    forward(
+        &spawner,
+        node.subscribe::<r2r::sensor_msgs::msg::LaserScan>(SCAN_TOPIC, QosProfile::sensor_data())?,
+        &bridge,
+        |bridge, scan| {
+            if let Err(e) = bridge.on_scan(&laser_scan(scan)) {
+                eprintln!("failed to integrate the scan: {e}");
+            }
+        },
+    )?;
+    forward(
+        &spawner,
+        node.subscribe::<r2r::geometry_msgs::msg::PoseStamped>(GOAL_TOPIC, QosProfile::default())?,
+        &bridge,
+        |bridge, goal| bridge.on_goal(&pose_stamped(goal)),
+    )?;
+
+    let cmd_vel = node
+        .create_publisher::<r2r::geometry_msgs::msg::Twist>(CMD_VEL_TOPIC, QosProfile::default())?;
+    let plan =
+        node.create_publisher::<r2r::nav_msgs::msg::Path>(PLAN_TOPIC, QosProfile::default())?;
+    let mut timer = node.create_wall_timer(period)?;
+    spawner.spawn_local(async move {
+        let mut last_state = None;
+        while timer.tick().await.is_ok() {
+            // waiting for the first odometry
+            let Some(output) = bridge.borrow_mut().spin_once() else {
+                continue;
+            };
+            if let Err(e) = cmd_vel.publish(&ros2_twist(&output.cmd_vel)) {
+                eprintln!("failed to publish {CMD_VEL_TOPIC}: {e}");
+            }
+            if let Some(path) = &output.plan {
+                if let Err(e) = plan.publish(&ros2_path(path)) {
+                    eprintln!("failed to publish {PLAN_TOPIC}: {e}");
+                }
+            }
+            if last_state != Some(output.state) {
+                println!("{:?}", output.state);
+                last_state = Some(output.state);
+            }
+        }
+    })?;
+
+    loop {
+        node.spin_once(Duration::from_millis(10));
+        pool.run_until_stalled();
+    }
+}
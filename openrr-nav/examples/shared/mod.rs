@@ -0,0 +1,82 @@
+//! Navigation loop shared by the robot examples
+//!
+//! The robot is accessed only through the arci traits: [`MoveBase`] to send the
+//! velocity commands and [`Localization`] to get the pose in the map frame, so the
+//! same loop runs with the simulated base and with the real robot.
+
+use anyhow::{bail, Result};
+use arci::{BaseVelocity, Localization, MoveBase};
+use grid_map::*;
+use nalgebra::Vector2;
+use openrr_nav::*;
+use std::time::Duration;
+
+pub(crate) const FRAME_ID: &str = "map";
+
+#[derive(Debug, Clone)]
+pub(crate) struct NavigationConfig {
+    /// (m)
+    pub(crate) goal_threshold: f64,
+    /// Period of the control loop
+    pub(crate) period: Duration,
+    pub(crate) max_cycles: usize,
+    /// Sleep for the period in each cycle. The simulation runs as fast as possible.
+    pub(crate) realtime: bool,
+}
+
+/// Pose from arci, which uses another version of nalgebra
+pub(crate) fn current_pose(localization: &impl Localization) -> Result<Pose> {
+    let pose = localization.current_pose(FRAME_ID)?;
+    Ok(Pose::new(
+        Vector2::new(pose.translation.x, pose.translation.y),
+        pose.rotation.angle(),
+    ))
+}
+
+/// Navigate to the goal with [`Navigator`] until the goal is reached
+///
+/// Only the position is checked for the goal. Returns false if the goal is not
+/// reached in `max_cycles`.
+pub(crate) fn navigate(
+    move_base: &impl MoveBase,
+    localization: &impl Localization,
+    planner: &DwaPlanner,
+    map: &GridMap<u8>,
+    goal: &Pose,
+    config: &NavigationConfig,
+) -> Result<bool> {
+    let mut navigator = Navigator::new(
+        Box::new(AStarPlanner::default()),
+        Box::new(planner.clone()),
+        map.clone(),
+    )
+    .with_goal_checker(Box::new(SimpleGoalChecker::new(
+        config.goal_threshold,
+        std::f64::consts::PI,
+    )));
+    navigator.set_goal(*goal);
+
+    let mut velocity = Velocity { x: 0.0, theta: 0.0 };
+    for cycle in 0..config.max_cycles {
+        let pose = current_pose(localization)?;
+        velocity = navigator.tick(&pose, &velocity).velocity();
+        move_base.send_velocity(&BaseVelocity::new(velocity.x, 0.0, velocity.theta))?;
+        match navigator.state() {
+            NavigatorState::GoalReached => {
+                println!("reached the goal in {cycle} cycles");
+                return Ok(true);
+            }
+            NavigatorState::Failed => bail!(
+                "failed at {:?}: {}",
+                pose.translation,
+                navigator.last_error().unwrap_or_default()
+            ),
+            _ => {}
+        }
+        if config.realtime {
+            std::thread::sleep(config.period);
+        }
+    }
+    move_base.send_velocity(&BaseVelocity::default())?;
+    Ok(false)
+}
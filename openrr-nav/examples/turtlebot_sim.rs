@@ -0,0 +1,107 @@
+// Navigate a simulated TurtleBot-like differential drive base in a room.
+//
+// How to run:
+//
+// ```sh
+// cargo run --release -p openrr-nav --example turtlebot_sim
+// ```
+
+mod shared;
+
+use anyhow::Result;
+use arci::{BaseVelocity, Isometry2, Localization, MoveBase, Vector2};
+use grid_map::*;
+use openrr_nav::*;
+use shared::*;
+use std::{sync::Mutex, time::Duration};
+
+/// Unicycle model which holds each velocity command for one control period
+#[derive(Debug)]
+struct SimulatedBase {
+    state: Mutex<(Isometry2<f64>, BaseVelocity)>,
+    period: Duration,
+}
+
+impl SimulatedBase {
+    fn new(pose: Isometry2<f64>, period: Duration) -> Self {
+        Self {
+            state: Mutex::new((pose, BaseVelocity::default())),
+            period,
+        }
+    }
+}
+
+impl MoveBase for SimulatedBase {
+    fn send_velocity(&self, velocity: &BaseVelocity) -> Result<(), arci::Error> {
+        let mut state = self.state.lock().unwrap();
+        let dt = self.period.as_secs_f64();
+        let yaw = state.0.rotation.angle() + velocity.theta * dt * 0.5;
+        let translation =
+            state.0.translation.vector + Vector2::new(yaw.cos(), yaw.sin()) * velocity.x * dt;
+        let rotation = state.0.rotation.angle() + velocity.theta * dt;
+        *state = (Isometry2::new(translation, rotation), *velocity);
+        Ok(())
+    }
+
+    fn current_velocity(&self) -> Result<BaseVelocity, arci::Error> {
+        Ok(self.state.lock().unwrap().1)
+    }
+}
+
+impl Localization for SimulatedBase {
+    fn current_pose(&self, _frame_id: &str) -> Result<Isometry2<f64>, arci::Error> {
+        Ok(self.state.lock().unwrap().0)
+    }
+}
+
+/// 4 m x 3 m room with a table and a partition
+fn new_room_map() -> GridMap<u8> {
+    let rectangle = |min: [f64; 2], max: [f64; 2]| Shape::Polygon {
+        points: vec![min, [max[0], min[1]], max, [min[0], max[1]]],
+    };
+    let world = World {
+        min_point: [0.0, 0.0],
+        max_point: [4.0, 3.0],
+        obstacles: vec![
+            rectangle([1.2, 1.0], [1.8, 1.6]),
+            rectangle([2.6, 0.0], [2.7, 2.0]),
+        ],
+    };
+    let mut map = world.rasterize(0.05);
+    // walls
+    for x in 0..map.width() {
+        map.set_obstacle(&Grid::new(x, 0)).unwrap();
+        map.set_obstacle(&Grid::new(x, map.height() - 1)).unwrap();
+    }
+    for y in 0..map.height() {
+        map.set_obstacle(&Grid::new(0, y)).unwrap();
+        map.set_obstacle(&Grid::new(map.width() - 1, y)).unwrap();
+    }
+    map
+}
+
+fn main() -> Result<()> {
+    let planner = DwaPlanner::new_from_config(format!(
+        "{}/../openrr-nav-core/config/turtlebot_dwa_config.yaml",
+        env!("CARGO_MANIFEST_DIR")
+    ))?;
+    let config = NavigationConfig {
+        goal_threshold: 0.1,
+        period: Duration::from_secs_f64(planner.controller_dt()),
+        max_cycles: 1000,
+        realtime: false,
+    };
+    let map = new_room_map();
+    let base = SimulatedBase::new(Isometry2::new(Vector2::new(0.4, 0.4), 0.0), config.period);
+    let goal = Pose::new(nalgebra::Vector2::new(3.4, 0.5), -1.57);
+
+    let reached = navigate(&base, &base, &planner, &map, &goal, &config)?;
+    let pose = current_pose(&base)?;
+    println!(
+        "reached = {reached}, pose = ({:.2}, {:.2}, {:.2})",
+        pose.translation.x,
+        pose.translation.y,
+        pose.rotation.angle()
+    );
+    Ok(())
+}
@@ -0,0 +1,337 @@
+use arci::{BaseVelocity, Localization, MoveBase, Navigation, WaitFuture};
+use futures::channel::oneshot;
+use nalgebra as na;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{Navigator, NavigatorState, Pose, Velocity};
+
+/// [`arci::Navigation`] backend running the [`Navigator`]
+///
+/// [`Navigation::send_goal_pose`] starts the control loop in a thread, which gets
+/// the pose from the [`Localization`] in the frame of the goal and sends the
+/// velocity to the [`MoveBase`] in every `period`. The returned [`WaitFuture`]
+/// completes when the navigation is over:
+///
+/// - `Ok(())` if the goal is reached
+/// - [`arci::Error::Canceled`] if [`Navigation::cancel`] is called, a new goal is
+///   sent or the velocity is sent by [`MoveBase::send_velocity`] of this backend
+/// - [`arci::Error::TimeoutWithDiff`] if the goal is not reached in the timeout
+/// - [`arci::Error::Other`] if the navigation failed
+///
+/// The base is stopped in all cases except the new goal.
+#[derive(Debug)]
+pub struct ArciNavigator<M, L> {
+    inner: Arc<Inner<M, L>>,
+}
+
+#[derive(Debug)]
+struct Inner<M, L> {
+    navigator: Mutex<Navigator>,
+    move_base: M,
+    localization: L,
+    period: Duration,
+    /// Incremented for each goal and cancel to stop the running control loop
+    goal_id: AtomicU64,
+}
+
+impl<M, L> ArciNavigator<M, L>
+where
+    M: MoveBase + 'static,
+    L: Localization + 'static,
+{
+    pub fn new(navigator: Navigator, move_base: M, localization: L, period: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                navigator: Mutex::new(navigator),
+                move_base,
+                localization,
+                period,
+                goal_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Lock the navigator, e.g. to update the map during the navigation
+    pub fn navigator(&self) -> MutexGuard<'_, Navigator> {
+        self.inner.navigator.lock().unwrap()
+    }
+
+    pub fn period(&self) -> Duration {
+        self.inner.period
+    }
+}
+
+impl<M, L> Inner<M, L>
+where
+    M: MoveBase,
+    L: Localization,
+{
+    fn is_current(&self, goal_id: u64) -> bool {
+        self.goal_id.load(Ordering::SeqCst) == goal_id
+    }
+
+    fn stop(&self) -> Result<(), arci::Error> {
+        self.move_base.send_velocity(&BaseVelocity::default())
+    }
+
+    fn current_pose(&self, frame_id: &str) -> Result<Pose, arci::Error> {
+        // arci uses another version of nalgebra
+        let pose = self.localization.current_pose(frame_id)?;
+        Ok(Pose::new(
+            na::Vector2::new(pose.translation.x, pose.translation.y),
+            pose.rotation.angle(),
+        ))
+    }
+
+    fn run(
+        &self,
+        goal_id: u64,
+        goal: Pose,
+        frame_id: &str,
+        timeout: Duration,
+    ) -> Result<(), arci::Error> {
+        let canceled = || arci::Error::Canceled {
+            message: "the navigation is canceled".to_owned(),
+        };
+        let start = Instant::now();
+        let mut velocity = Velocity { x: 0.0, theta: 0.0 };
+        loop {
+            let pose = match self.current_pose(frame_id) {
+                Ok(pose) => pose,
+                Err(e) => {
+                    self.cancel_if_current(goal_id)?;
+                    return Err(e);
+                }
+            };
+            let state = {
+                let mut navigator = self.navigator.lock().unwrap();
+                if !self.is_current(goal_id) {
+                    return Err(canceled());
+                }
+                if start.elapsed() > timeout {
+                    navigator.cancel();
+                    drop(navigator);
+                    self.stop()?;
+                    return Err(arci::Error::TimeoutWithDiff {
+                        target: vec![
+                            goal.translation.x,
+                            goal.translation.y,
+                            goal.rotation.angle(),
+                        ],
+                        current: vec![
+                            pose.translation.x,
+                            pose.translation.y,
+                            pose.rotation.angle(),
+                        ],
+                        is_reached: vec![false; 3],
+                    });
+                }
+                velocity = navigator.tick(&pose, &velocity).velocity();
+                self.move_base.send_velocity(&BaseVelocity::new(
+                    velocity.x,
+                    0.0,
+                    velocity.theta,
+                ))?;
+                match navigator.state() {
+                    NavigatorState::Failed => Err(navigator
+                        .last_error()
+                        .unwrap_or("the navigation failed")
+                        .to_owned()),
+                    state => Ok(state),
+                }
+            };
+            match state {
+                Ok(NavigatorState::GoalReached) => {
+                    self.stop()?;
+                    return Ok(());
+                }
+                Err(message) => {
+                    self.stop()?;
+                    return Err(arci::Error::Other(anyhow::anyhow!(message)));
+                }
+                _ => {}
+            }
+            thread::sleep(self.period);
+        }
+    }
+
+    /// Cancel the navigation and stop the base if the goal is still running
+    fn cancel_if_current(&self, goal_id: u64) -> Result<(), arci::Error> {
+        let mut navigator = self.navigator.lock().unwrap();
+        if self.is_current(goal_id) {
+            navigator.cancel();
+            drop(navigator);
+            self.stop()?;
+        }
+        Ok(())
+    }
+
+    fn cancel(&self) -> Result<(), arci::Error> {
+        let mut navigator = self.navigator.lock().unwrap();
+        self.goal_id.fetch_add(1, Ordering::SeqCst);
+        navigator.cancel();
+        drop(navigator);
+        self.stop()
+    }
+}
+
+impl<M, L> Navigation for ArciNavigator<M, L>
+where
+    M: MoveBase + 'static,
+    L: Localization + 'static,
+{
+    fn send_goal_pose(
+        &self,
+        goal: arci::Isometry2<f64>,
+        frame_id: &str,
+        timeout: Duration,
+    ) -> Result<WaitFuture, arci::Error> {
+        let goal = Pose::new(
+            na::Vector2::new(goal.translation.x, goal.translation.y),
+            goal.rotation.angle(),
+        );
+        let goal_id = {
+            let mut navigator = self.inner.navigator.lock().unwrap();
+            navigator.set_goal(goal);
+            self.inner.goal_id.fetch_add(1, Ordering::SeqCst) + 1
+        };
+        let (sender, receiver) = oneshot::channel();
+        let inner = self.inner.clone();
+        let frame_id = frame_id.to_owned();
+        thread::spawn(move || {
+            let _ = sender.send(inner.run(goal_id, goal, &frame_id, timeout));
+        });
+        Ok(WaitFuture::new(async move {
+            receiver.await.unwrap_or_else(|_| {
+                Err(arci::Error::Canceled {
+                    message: "the control loop is stopped".to_owned(),
+                })
+            })
+        }))
+    }
+
+    fn cancel(&self) -> Result<(), arci::Error> {
+        self.inner.cancel()
+    }
+}
+
+/// The velocity from the other clients (e.g. the joystick) cancels the navigation.
+impl<M, L> MoveBase for ArciNavigator<M, L>
+where
+    M: MoveBase + 'static,
+    L: Localization + 'static,
+{
+    fn send_velocity(&self, velocity: &BaseVelocity) -> Result<(), arci::Error> {
+        {
+            let mut navigator = self.inner.navigator.lock().unwrap();
+            if navigator.goal().is_some() {
+                self.inner.goal_id.fetch_add(1, Ordering::SeqCst);
+                navigator.cancel();
+            }
+        }
+        self.inner.move_base.send_velocity(velocity)
+    }
+
+    fn current_velocity(&self) -> Result<BaseVelocity, arci::Error> {
+        self.inner.move_base.current_velocity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testkit, SimpleGoalChecker};
+
+    /// Base moving by the last velocity for `dt` in each call of `current_pose`
+    #[derive(Debug, Clone)]
+    struct SimBase {
+        state: Arc<Mutex<(arci::Isometry2<f64>, BaseVelocity)>>,
+        dt: f64,
+    }
+
+    impl MoveBase for SimBase {
+        fn send_velocity(&self, velocity: &BaseVelocity) -> Result<(), arci::Error> {
+            self.state.lock().unwrap().1 = *velocity;
+            Ok(())
+        }
+
+        fn current_velocity(&self) -> Result<BaseVelocity, arci::Error> {
+            Ok(self.state.lock().unwrap().1)
+        }
+    }
+
+    impl Localization for SimBase {
+        fn current_pose(&self, _frame_id: &str) -> Result<arci::Isometry2<f64>, arci::Error> {
+            let mut state = self.state.lock().unwrap();
+            let (pose, velocity) = &mut *state;
+            *pose *= arci::Isometry2::new(
+                arci::Vector2::new(velocity.x * self.dt, 0.0),
+                velocity.theta * self.dt,
+            );
+            Ok(*pose)
+        }
+    }
+
+    fn new_navigator() -> (ArciNavigator<SimBase, SimBase>, SimBase) {
+        let base = SimBase {
+            state: Arc::new(Mutex::new((
+                arci::Isometry2::new(arci::Vector2::new(0.5, 0.5), 0.0),
+                BaseVelocity::default(),
+            ))),
+            dt: testkit::DT,
+        };
+        let navigator = testkit::navigator(testkit::free_map())
+            .with_goal_checker(Box::new(SimpleGoalChecker::new(0.1, std::f64::consts::PI)));
+        (
+            ArciNavigator::new(navigator, base.clone(), base.clone(), Duration::ZERO),
+            base,
+        )
+    }
+
+    #[test]
+    fn test_arci_navigator() {
+        let goal = arci::Isometry2::new(arci::Vector2::new(2.0, 1.5), 0.0);
+        let (navigator, base) = new_navigator();
+        let wait = navigator
+            .send_goal_pose(goal, "map", Duration::from_secs(30))
+            .unwrap();
+        futures::executor::block_on(wait).unwrap();
+        let (pose, velocity) = *base.state.lock().unwrap();
+        assert!((pose.translation.vector - goal.translation.vector).norm() < 0.1);
+        assert_eq!((velocity.x, velocity.theta), (0.0, 0.0));
+        assert_eq!(navigator.navigator().state(), NavigatorState::GoalReached);
+
+        let wait = navigator
+            .send_goal_pose(
+                arci::Isometry2::new(arci::Vector2::new(0.5, 0.5), 0.0),
+                "map",
+                Duration::from_secs(30),
+            )
+            .unwrap();
+        navigator.cancel().unwrap();
+        assert!(matches!(
+            futures::executor::block_on(wait),
+            Err(arci::Error::Canceled { .. })
+        ));
+        assert_eq!(navigator.navigator().state(), NavigatorState::Idle);
+
+        let wait = navigator
+            .send_goal_pose(
+                arci::Isometry2::new(arci::Vector2::new(2.0, 0.5), 0.0),
+                "map",
+                Duration::ZERO,
+            )
+            .unwrap();
+        assert!(matches!(
+            futures::executor::block_on(wait),
+            Err(arci::Error::TimeoutWithDiff { .. })
+        ));
+    }
+}
@@ -0,0 +1,644 @@
+//! gRPC service of the [`Navigator`] and the client
+//!
+//! The service `openrr_nav.Navigation` has the RPCs `SetGoal`, `CancelGoal`,
+//! `GetStatus`, `StreamLocalPlan`, `GetParameters` and `SetParameters`. Run
+//! [`NavigationService::serve`] on the robot, call [`NavigationService::tick`] in
+//! the control loop, and drive the navigation remotely with [`NavigationClient`].
+
+use nalgebra as na;
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::broadcast;
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{transport::Channel, Request, Response, Status};
+
+use crate::{
+    Command, LocalPlannerConfig, Navigator, NavigatorConfig, NavigatorState, Plan, Pose, Velocity,
+};
+
+/// Messages of the service
+pub mod pb {
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Empty {}
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Pose2d {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(double, tag = "3")]
+        pub theta: f64,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Velocity2d {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub theta: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct SetGoalRequest {
+        #[prost(message, optional, tag = "1")]
+        pub goal: Option<Pose2d>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NavigationStatus {
+        /// Name of [`NavigatorState`](crate::NavigatorState), e.g. `FollowingPath`
+        #[prost(string, tag = "1")]
+        pub state: String,
+        #[prost(message, optional, tag = "2")]
+        pub goal: Option<Pose2d>,
+        /// Pose of the last tick
+        #[prost(message, optional, tag = "3")]
+        pub pose: Option<Pose2d>,
+        #[prost(message, optional, tag = "4")]
+        pub velocity: Option<Velocity2d>,
+        /// Empty if no error
+        #[prost(string, tag = "5")]
+        pub last_error: String,
+        #[prost(message, repeated, tag = "6")]
+        pub global_path: Vec<Pose2d>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LocalPlan {
+        #[prost(message, optional, tag = "1")]
+        pub velocity: Option<Velocity2d>,
+        #[prost(double, tag = "2")]
+        pub cost: f64,
+        #[prost(message, repeated, tag = "3")]
+        pub path: Vec<Pose2d>,
+        #[prost(string, tag = "4")]
+        pub state: String,
+    }
+
+    /// YAML of the configs. The empty fields are not changed by `SetParameters`.
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Parameters {
+        /// [`NavigatorConfig`](crate::NavigatorConfig)
+        #[prost(string, tag = "1")]
+        pub navigator_config: String,
+        /// [`LocalPlannerConfig`](crate::LocalPlannerConfig), empty if it has not been
+        /// set by `SetParameters`
+        #[prost(string, tag = "2")]
+        pub local_planner_config: String,
+    }
+
+    include!(concat!(env!("OUT_DIR"), "/openrr_nav.Navigation.rs"));
+}
+
+impl From<&Pose> for pb::Pose2d {
+    fn from(pose: &Pose) -> Self {
+        Self {
+            x: pose.translation.x,
+            y: pose.translation.y,
+            theta: pose.rotation.angle(),
+        }
+    }
+}
+
+impl From<&pb::Pose2d> for Pose {
+    fn from(pose: &pb::Pose2d) -> Self {
+        Pose::new(na::Vector2::new(pose.x, pose.y), pose.theta)
+    }
+}
+
+impl From<&Velocity> for pb::Velocity2d {
+    fn from(velocity: &Velocity) -> Self {
+        Self {
+            x: velocity.x,
+            theta: velocity.theta,
+        }
+    }
+}
+
+impl From<&pb::Velocity2d> for Velocity {
+    fn from(velocity: &pb::Velocity2d) -> Self {
+        Velocity {
+            x: velocity.x,
+            theta: velocity.theta,
+        }
+    }
+}
+
+fn path_to_pb(path: &[Vec<f64>]) -> Vec<pb::Pose2d> {
+    path.iter()
+        .map(|p| pb::Pose2d {
+            x: p[0],
+            y: p[1],
+            theta: p[2],
+        })
+        .collect()
+}
+
+/// Capacity of the local plans buffered for the slow subscribers
+const LOCAL_PLAN_CHANNEL_CAPACITY: usize = 16;
+
+#[derive(Debug)]
+struct SharedNavigator {
+    navigator: Navigator,
+    pose: Option<Pose>,
+    velocity: Velocity,
+    local_planner_config: Option<LocalPlannerConfig>,
+}
+
+/// gRPC service wrapping the [`Navigator`]
+///
+/// The navigator is ticked by the control loop of the robot with
+/// [`NavigationService::tick`], and the RPCs change the goal and the parameters
+/// between the ticks. The local plan of each tick is sent to the `StreamLocalPlan`
+/// subscribers.
+#[derive(Debug, Clone)]
+pub struct NavigationService {
+    shared: Arc<Mutex<SharedNavigator>>,
+    local_plans: broadcast::Sender<pb::LocalPlan>,
+}
+
+impl NavigationService {
+    pub fn new(navigator: Navigator) -> Self {
+        Self {
+            shared: Arc::new(Mutex::new(SharedNavigator {
+                navigator,
+                pose: None,
+                velocity: Velocity::default(),
+                local_planner_config: None,
+            })),
+            local_plans: broadcast::channel(LOCAL_PLAN_CHANNEL_CAPACITY).0,
+        }
+    }
+
+    /// Run a control cycle of the navigator, see [`Navigator::tick`]
+    pub fn tick(&self, pose: &Pose, velocity: &Velocity) -> Command {
+        let mut shared = self.shared.lock().unwrap();
+        shared.pose = Some(*pose);
+        shared.velocity = *velocity;
+        let command = shared.navigator.tick(pose, velocity);
+        if let (Command::Velocity(_), Some(plan)) = (command, shared.navigator.last_plan()) {
+            // no receiver is not an error
+            let _ = self
+                .local_plans
+                .send(local_plan_to_pb(plan, shared.navigator.state()));
+        }
+        command
+    }
+
+    /// Access the navigator, e.g. to update the map
+    pub fn with_navigator<R>(&self, f: impl FnOnce(&mut Navigator) -> R) -> R {
+        f(&mut self.shared.lock().unwrap().navigator)
+    }
+
+    pub fn into_server(self) -> pb::navigation_server::NavigationServer<Self> {
+        pb::navigation_server::NavigationServer::new(self)
+    }
+
+    /// Serve at the address until the error
+    pub async fn serve(self, addr: SocketAddr) -> Result<(), tonic::transport::Error> {
+        tonic::transport::Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await
+    }
+}
+
+fn local_plan_to_pb(plan: &Plan, state: NavigatorState) -> pb::LocalPlan {
+    pb::LocalPlan {
+        velocity: Some((&plan.velocity).into()),
+        cost: plan.cost,
+        path: plan.path.iter().map(pb::Pose2d::from).collect(),
+        state: format!("{state:?}"),
+    }
+}
+
+#[tonic::async_trait]
+impl pb::navigation_server::Navigation for NavigationService {
+    async fn set_goal(
+        &self,
+        request: Request<pb::SetGoalRequest>,
+    ) -> Result<Response<pb::Empty>, Status> {
+        let goal = request
+            .into_inner()
+            .goal
+            .ok_or_else(|| Status::invalid_argument("no goal"))?;
+        self.with_navigator(|navigator| navigator.set_goal(Pose::from(&goal)));
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn cancel_goal(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<pb::Empty>, Status> {
+        self.with_navigator(Navigator::cancel);
+        Ok(Response::new(pb::Empty {}))
+    }
+
+    async fn get_status(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<pb::NavigationStatus>, Status> {
+        let shared = self.shared.lock().unwrap();
+        let navigator = &shared.navigator;
+        Ok(Response::new(pb::NavigationStatus {
+            state: format!("{:?}", navigator.state()),
+            goal: navigator.target().map(pb::Pose2d::from),
+            pose: shared.pose.as_ref().map(pb::Pose2d::from),
+            velocity: Some((&shared.velocity).into()),
+            last_error: navigator.last_error().unwrap_or_default().to_owned(),
+            global_path: path_to_pb(navigator.global_path()),
+        }))
+    }
+
+    type StreamLocalPlanStream =
+        Pin<Box<dyn Stream<Item = Result<pb::LocalPlan, Status>> + Send + 'static>>;
+
+    async fn stream_local_plan(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<Self::StreamLocalPlanStream>, Status> {
+        // the lagged plans are skipped
+        let stream =
+            BroadcastStream::new(self.local_plans.subscribe()).filter_map(|plan| plan.ok().map(Ok));
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn get_parameters(
+        &self,
+        _request: Request<pb::Empty>,
+    ) -> Result<Response<pb::Parameters>, Status> {
+        let shared = self.shared.lock().unwrap();
+        let to_yaml = |e: serde_yaml::Error| Status::internal(e.to_string());
+        Ok(Response::new(pb::Parameters {
+            navigator_config: serde_yaml::to_string(shared.navigator.config()).map_err(to_yaml)?,
+            local_planner_config: match &shared.local_planner_config {
+                Some(config) => serde_yaml::to_string(config).map_err(to_yaml)?,
+                None => String::new(),
+            },
+        }))
+    }
+
+    async fn set_parameters(
+        &self,
+        request: Request<pb::Parameters>,
+    ) -> Result<Response<pb::Empty>, Status> {
+        let parameters = request.into_inner();
+        // parse all the parameters before changing any
+        let navigator_config = (!parameters.navigator_config.is_empty())
+            .then(|| serde_yaml::from_str::<NavigatorConfig>(&parameters.navigator_config))
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("invalid navigator_config: {e}")))?;
+        let local_planner_config = (!parameters.local_planner_config.is_empty())
+            .then(|| LocalPlannerConfig::new_from_config_text(&parameters.local_planner_config))
+            .transpose()
+            .map_err(|e| Status::invalid_argument(format!("invalid local_planner_config: {e}")))?;
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(config) = navigator_config {
+            shared.navigator.set_config(config);
+        }
+        if let Some(config) = local_planner_config {
+            shared.navigator.replace_local_planner(config.to_planner());
+            shared.local_planner_config = Some(config);
+        }
+        Ok(Response::new(pb::Empty {}))
+    }
+}
+
+/// Status of the remote navigator
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteStatus {
+    /// Name of [`NavigatorState`], e.g. `FollowingPath`
+    pub state: String,
+    pub goal: Option<Pose>,
+    pub pose: Option<Pose>,
+    pub velocity: Velocity,
+    pub last_error: Option<String>,
+    pub global_path: Vec<Pose>,
+}
+
+/// Client of [`NavigationService`] with the types of this crate
+#[derive(Debug, Clone)]
+pub struct NavigationClient {
+    client: pb::navigation_client::NavigationClient<Channel>,
+}
+
+impl NavigationClient {
+    /// Connect to the address, e.g. `http://127.0.0.1:50101`
+    pub async fn connect(addr: impl Into<String>) -> Result<Self, tonic::transport::Error> {
+        Ok(Self {
+            client: pb::navigation_client::NavigationClient::connect(addr.into()).await?,
+        })
+    }
+
+    /// The generated client for the other RPCs
+    pub fn inner_mut(&mut self) -> &mut pb::navigation_client::NavigationClient<Channel> {
+        &mut self.client
+    }
+
+    pub async fn set_goal(&mut self, goal: &Pose) -> Result<(), Status> {
+        self.client
+            .set_goal(pb::SetGoalRequest {
+                goal: Some(goal.into()),
+            })
+            .await?;
+        Ok(())
+    }
+
+    pub async fn cancel_goal(&mut self) -> Result<(), Status> {
+        self.client.cancel_goal(pb::Empty {}).await?;
+        Ok(())
+    }
+
+    pub async fn status(&mut self) -> Result<RemoteStatus, Status> {
+        let status = self.client.get_status(pb::Empty {}).await?.into_inner();
+        Ok(RemoteStatus {
+            state: status.state,
+            goal: status.goal.as_ref().map(Pose::from),
+            pose: status.pose.as_ref().map(Pose::from),
+            velocity: status
+                .velocity
+                .as_ref()
+                .map(Velocity::from)
+                .unwrap_or_default(),
+            last_error: (!status.last_error.is_empty()).then_some(status.last_error),
+            global_path: status.global_path.iter().map(Pose::from).collect(),
+        })
+    }
+
+    /// Local plans of the ticks after the call
+    #[allow(clippy::result_large_err)] // Status of tonic
+    pub async fn local_plans(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<Plan, Status>>, Status> {
+        let stream = self
+            .client
+            .stream_local_plan(pb::Empty {})
+            .await?
+            .into_inner();
+        Ok(stream.map(|plan| {
+            plan.map(|plan| Plan {
+                velocity: plan
+                    .velocity
+                    .as_ref()
+                    .map(Velocity::from)
+                    .unwrap_or_default(),
+                cost: plan.cost,
+                path: plan.path.iter().map(Pose::from).collect(),
+                sampling_issue: None,
+            })
+        }))
+    }
+
+    pub async fn parameters(&mut self) -> Result<pb::Parameters, Status> {
+        Ok(self.client.get_parameters(pb::Empty {}).await?.into_inner())
+    }
+
+    /// Replace the configs given as YAML. `None` is not changed.
+    pub async fn set_parameters(
+        &mut self,
+        navigator_config: Option<&NavigatorConfig>,
+        local_planner_config: Option<&LocalPlannerConfig>,
+    ) -> Result<(), Status> {
+        let to_yaml = |e: serde_yaml::Error| Status::invalid_argument(e.to_string());
+        let parameters = pb::Parameters {
+            navigator_config: navigator_config
+                .map(serde_yaml::to_string)
+                .transpose()
+                .map_err(to_yaml)?
+                .unwrap_or_default(),
+            local_planner_config: local_planner_config
+                .map(serde_yaml::to_string)
+                .transpose()
+                .map_err(to_yaml)?
+                .unwrap_or_default(),
+        };
+        self.client.set_parameters(parameters).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{free_map, move_pose, navigator, DT};
+    use tokio_stream::wrappers::TcpListenerStream;
+
+    /// Serve the navigator at a free port and connect to it
+    async fn serve(navigator: Navigator) -> (NavigationService, NavigationClient) {
+        let service = NavigationService::new(navigator);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = service.clone().into_server();
+        tokio::spawn(async move {
+            tonic::transport::Server::builder()
+                .add_service(server)
+                .serve_with_incoming(TcpListenerStream::new(listener))
+                .await
+                .unwrap();
+        });
+        let client = NavigationClient::connect(format!("http://{addr}"))
+            .await
+            .unwrap();
+        (service, client)
+    }
+
+    #[tokio::test]
+    async fn test_navigation_service() {
+        let (service, mut client) = serve(navigator(free_map())).await;
+        assert_eq!(client.status().await.unwrap().state, "Idle");
+        let goal = Pose::new(na::Vector2::new(2.0, 1.5), 0.0);
+        client.set_goal(&goal).await.unwrap();
+        let status = client.status().await.unwrap();
+        assert_eq!(status.state, "ComputingPath");
+        assert_eq!(status.goal, Some(goal));
+
+        let mut plans = client.local_plans().await.unwrap();
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let velocity = service.tick(&pose, &Velocity::default()).velocity();
+        let plan = plans.next().await.unwrap().unwrap();
+        assert_eq!(plan.velocity, velocity);
+        assert!(!plan.path.is_empty());
+        let status = client.status().await.unwrap();
+        assert_eq!(status.state, "FollowingPath");
+        assert_eq!(status.pose, Some(pose));
+        assert!(!status.global_path.is_empty());
+        pose = move_pose(&pose, &velocity, DT);
+        service.tick(&pose, &velocity);
+
+        let config = NavigatorConfig {
+            max_recoveries: 1,
+            ..Default::default()
+        };
+        let teb = LocalPlannerConfig::new_from_config_text(
+            "type: teb
+limits:
+  max_velocity: [0.4, 1.5]
+  max_acceleration: [1.0, 3.0]
+  min_velocity: [0.0, -1.5]
+  min_acceleration: [-1.0, -3.0]
+controller_dt: 0.1
+",
+        )
+        .unwrap();
+        client
+            .set_parameters(Some(&config), Some(&teb))
+            .await
+            .unwrap();
+        let parameters = client.parameters().await.unwrap();
+        assert!(parameters.navigator_config.contains("max_recoveries: 1"));
+        assert!(parameters.local_planner_config.contains("type: teb"));
+        assert!(service
+            .with_navigator(|n| format!("{:?}", n.local_planner()).starts_with("TebPlanner")));
+        let invalid = pb::Parameters {
+            navigator_config: "unknown: 1".to_owned(),
+            local_planner_config: String::new(),
+        };
+        let error = client
+            .inner_mut()
+            .set_parameters(invalid)
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+
+        client.cancel_goal().await.unwrap();
+        assert_eq!(client.status().await.unwrap().state, "Idle");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_goal() {
+        let (service, mut client) = serve(navigator(free_map())).await;
+        let pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        client
+            .set_goal(&Pose::new(na::Vector2::new(2.0, 1.5), 0.0))
+            .await
+            .unwrap();
+        assert!(matches!(
+            service.tick(&pose, &Velocity::default()),
+            Command::Velocity(_)
+        ));
+        client.cancel_goal().await.unwrap();
+        let status = client.status().await.unwrap();
+        assert_eq!(status.state, "Idle");
+        assert_eq!(status.goal, None);
+        assert!(status.global_path.is_empty());
+        assert_eq!(service.tick(&pose, &Velocity::default()), Command::Stop);
+        // nothing to cancel
+        client.cancel_goal().await.unwrap();
+        assert_eq!(client.status().await.unwrap().state, "Idle");
+    }
+
+    #[tokio::test]
+    async fn test_get_status() {
+        let mut map = free_map();
+        let goal = Pose::new(na::Vector2::new(2.0, 1.5), 0.0);
+        map.set_obstacle(&map.to_grid(goal.translation.x, goal.translation.y).unwrap())
+            .unwrap();
+        let navigator = navigator(map).with_config(NavigatorConfig {
+            max_recoveries: 0,
+            ..Default::default()
+        });
+        let (service, mut client) = serve(navigator).await;
+        assert_eq!(
+            client.status().await.unwrap(),
+            RemoteStatus {
+                state: "Idle".to_owned(),
+                goal: None,
+                pose: None,
+                velocity: Velocity::default(),
+                last_error: None,
+                global_path: vec![],
+            }
+        );
+
+        // the goal in the obstacle
+        client.set_goal(&goal).await.unwrap();
+        let pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let velocity = Velocity { x: 0.1, theta: 0.2 };
+        for _ in 0..3 {
+            assert_eq!(service.tick(&pose, &velocity), Command::Stop);
+        }
+        let status = client.status().await.unwrap();
+        assert_eq!(status.state, "Failed");
+        assert_eq!(status.pose, Some(pose));
+        assert_eq!(status.velocity, velocity);
+        assert!(status.last_error.is_some());
+        assert!(status.global_path.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_set_invalid_parameters() {
+        let (service, mut client) = serve(navigator(free_map())).await;
+        let before = client.parameters().await.unwrap();
+        let valid_navigator_config = serde_yaml::to_string(&NavigatorConfig {
+            max_recoveries: 5,
+            ..Default::default()
+        })
+        .unwrap();
+        for (navigator_config, local_planner_config) in [
+            ("look_ahead: [".to_owned(), String::new()),
+            ("look_ahead: -1".to_owned(), String::new()),
+            (String::new(), "type: unknown".to_owned()),
+            // nothing is changed if one of them is invalid
+            (valid_navigator_config, "type: teb".to_owned()),
+        ] {
+            let error = client
+                .inner_mut()
+                .set_parameters(pb::Parameters {
+                    navigator_config: navigator_config.clone(),
+                    local_planner_config: local_planner_config.clone(),
+                })
+                .await
+                .unwrap_err();
+            assert_eq!(
+                error.code(),
+                tonic::Code::InvalidArgument,
+                "{navigator_config:?}, {local_planner_config:?}"
+            );
+        }
+        assert_eq!(client.parameters().await.unwrap(), before);
+        assert_eq!(
+            service.with_navigator(|n| n.config().max_recoveries),
+            NavigatorConfig::default().max_recoveries
+        );
+
+        let error = client
+            .inner_mut()
+            .set_goal(pb::SetGoalRequest { goal: None })
+            .await
+            .unwrap_err();
+        assert_eq!(error.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_stream_local_plan() {
+        let (service, mut client) = serve(navigator(free_map())).await;
+        let mut first = client.local_plans().await.unwrap();
+        let mut second = client.clone().local_plans().await.unwrap();
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        // no plan without the goal
+        assert_eq!(service.tick(&pose, &Velocity::default()), Command::Stop);
+
+        client
+            .set_goal(&Pose::new(na::Vector2::new(2.0, 1.5), 0.0))
+            .await
+            .unwrap();
+        let mut velocity = Velocity::default();
+        let mut velocities = vec![];
+        for _ in 0..3 {
+            velocity = service.tick(&pose, &velocity).velocity();
+            velocities.push(velocity);
+            pose = move_pose(&pose, &velocity, DT);
+        }
+        for plans in [&mut first, &mut second] {
+            for velocity in &velocities {
+                let plan = plans.next().await.unwrap().unwrap();
+                assert_eq!(plan.velocity, *velocity);
+                assert!(!plan.path.is_empty());
+            }
+        }
+    }
+}
@@ -1,12 +1,32 @@
-// mod angle_table;
-mod cost_map;
-mod dwa_planner;
-mod error;
-mod robot_path;
-pub mod utils;
+//! Bridges of the navigator of [`openrr_nav_core`] to the other stacks (arci,
+//! gRPC, MQTT, ROS, WebSocket) and the run logs, each behind a feature.
 
-// pub use crate::angle_table::*;
-pub use crate::cost_map::*;
-pub use crate::dwa_planner::*;
-pub use crate::error::*;
-pub use crate::robot_path::*;
+#[cfg(feature = "arci")]
+mod arci_navigation;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+#[cfg(feature = "proto")]
+pub mod proto;
+#[cfg(feature = "recording")]
+mod recording;
+#[cfg(feature = "ros")]
+pub mod ros;
+#[cfg(feature = "ros2")]
+mod ros_bridge;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+
+pub use openrr_nav_core::*;
+
+#[cfg(feature = "arci")]
+pub use crate::arci_navigation::*;
+#[cfg(feature = "mqtt")]
+pub use crate::mqtt::*;
+#[cfg(feature = "recording")]
+pub use crate::recording::*;
+#[cfg(feature = "ros2")]
+pub use crate::ros_bridge::*;
+#[cfg(feature = "telemetry")]
+pub use crate::telemetry::*;
@@ -0,0 +1,208 @@
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Navigator, NavigatorState, Pose, Result, Velocity};
+
+/// Payload of the goal topic in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MqttGoal {
+    /// Assigned by the fleet manager to match the status with the goal
+    #[serde(default)]
+    pub id: Option<String>,
+    pub x: f64,
+    pub y: f64,
+    #[serde(default)]
+    pub theta: f64,
+}
+
+/// Payload of the status topic in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttStatus {
+    pub state: NavigatorState,
+    /// Id of the current (or the last) goal
+    pub goal_id: Option<String>,
+    pub last_error: Option<String>,
+}
+
+/// Message to be published by the MQTT client
+#[derive(Debug, Clone, PartialEq)]
+pub struct MqttPublish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    /// Publish with the retain flag, so the late subscribers get the last one
+    pub retain: bool,
+}
+
+/// Adapter between the MQTT topics of the fleet manager and the [`Navigator`]
+///
+/// The adapter doesn't depend on the MQTT client library. Subscribe
+/// [`MqttAdapter::subscriptions`] with rumqttc or paho, pass the messages to
+/// [`MqttAdapter::on_message`], and publish the messages returned by
+/// [`MqttAdapter::spin_once`] in the control loop. The topics under the prefix
+/// (e.g. `openrr_nav/robot1`) are:
+///
+/// | topic           | direction | payload                                     |
+/// |-----------------|-----------|---------------------------------------------|
+/// | `<prefix>/goal`   | subscribe | [`MqttGoal`]                              |
+/// | `<prefix>/cancel` | subscribe | ignored                                   |
+/// | `<prefix>/pose`   | publish   | `{"x":..,"y":..,"theta":..}` in every cycle |
+/// | `<prefix>/plan`   | publish   | `[[x, y, theta], ..]` when the global path is changed (retained) |
+/// | `<prefix>/status` | publish   | [`MqttStatus`] when it is changed (retained) |
+#[derive(Debug)]
+pub struct MqttAdapter {
+    navigator: Navigator,
+    prefix: String,
+    goal_id: Option<String>,
+    published_status: Option<MqttStatus>,
+    published_path: Option<Vec<Vec<f64>>>,
+}
+
+#[derive(Serialize)]
+struct PoseMessage {
+    x: f64,
+    y: f64,
+    theta: f64,
+}
+
+impl MqttAdapter {
+    pub fn new(navigator: Navigator, prefix: impl Into<String>) -> Self {
+        Self {
+            navigator,
+            prefix: prefix.into().trim_end_matches('/').to_owned(),
+            goal_id: None,
+            published_status: None,
+            published_path: None,
+        }
+    }
+
+    pub fn navigator(&self) -> &Navigator {
+        &self.navigator
+    }
+
+    pub fn navigator_mut(&mut self) -> &mut Navigator {
+        &mut self.navigator
+    }
+
+    pub fn topic(&self, name: &str) -> String {
+        format!("{}/{name}", self.prefix)
+    }
+
+    /// Topics to be subscribed
+    pub fn subscriptions(&self) -> Vec<String> {
+        vec![self.topic("goal"), self.topic("cancel")]
+    }
+
+    /// Handle the message of the subscribed topic
+    ///
+    /// Returns false if the topic is not of this adapter.
+    pub fn on_message(&mut self, topic: &str, payload: &[u8]) -> Result<bool> {
+        if topic == self.topic("goal") {
+            let goal: MqttGoal = serde_json::from_slice(payload)
+                .map_err(|e| Error::Other(format!("invalid goal: {e}")))?;
+            self.navigator
+                .set_goal(Pose::new(na::Vector2::new(goal.x, goal.y), goal.theta));
+            self.goal_id = goal.id;
+            Ok(true)
+        } else if topic == self.topic("cancel") {
+            self.navigator.cancel();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Run a control cycle and return the velocity command and the messages to be
+    /// published
+    pub fn spin_once(&mut self, pose: &Pose, velocity: &Velocity) -> (Velocity, Vec<MqttPublish>) {
+        let command = self.navigator.tick(pose, velocity).velocity();
+        let mut messages = vec![self.publish(
+            "pose",
+            &PoseMessage {
+                x: pose.translation.x,
+                y: pose.translation.y,
+                theta: pose.rotation.angle(),
+            },
+            false,
+        )];
+
+        let path = self.navigator.global_path();
+        if self.published_path.as_deref() != Some(path) {
+            self.published_path = Some(path.to_vec());
+            let path = path.iter().map(|p| [p[0], p[1], p[2]]).collect::<Vec<_>>();
+            messages.push(self.publish("plan", &path, true));
+        }
+
+        let status = MqttStatus {
+            state: self.navigator.state(),
+            goal_id: self.goal_id.clone(),
+            last_error: self.navigator.last_error().map(str::to_owned),
+        };
+        if self.published_status.as_ref() != Some(&status) {
+            messages.push(self.publish("status", &status, true));
+            self.published_status = Some(status);
+        }
+        (command, messages)
+    }
+
+    fn publish(&self, name: &str, payload: &impl Serialize, retain: bool) -> MqttPublish {
+        MqttPublish {
+            topic: self.topic(name),
+            // the payloads have no maps with the non-string keys
+            payload: serde_json::to_vec(payload).unwrap(),
+            retain,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{free_map, move_pose, navigator, DT};
+
+    #[test]
+    fn test_mqtt_adapter() {
+        let mut adapter = MqttAdapter::new(navigator(free_map()), "openrr_nav/robot1/");
+        assert_eq!(
+            adapter.subscriptions(),
+            ["openrr_nav/robot1/goal", "openrr_nav/robot1/cancel"]
+        );
+        assert!(!adapter.on_message("other/goal", b"{}").unwrap());
+        assert!(adapter
+            .on_message("openrr_nav/robot1/goal", b"{\"x\":2.0}")
+            .is_err());
+        assert!(adapter
+            .on_message(
+                "openrr_nav/robot1/goal",
+                br#"{"id":"job-1","x":2.0,"y":1.5}"#
+            )
+            .unwrap());
+
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity { x: 0.0, theta: 0.0 };
+        let mut statuses = vec![];
+        for _ in 0..1000 {
+            let (command, messages) = adapter.spin_once(&pose, &velocity);
+            assert_eq!(messages[0].topic, "openrr_nav/robot1/pose");
+            statuses.extend(
+                messages
+                    .iter()
+                    .filter(|m| m.topic.ends_with("/status"))
+                    .map(|m| serde_json::from_slice::<MqttStatus>(&m.payload).unwrap()),
+            );
+            if adapter.navigator().state().is_finished() {
+                break;
+            }
+            velocity = command;
+            pose = move_pose(&pose, &velocity, DT);
+        }
+        let last = statuses.last().unwrap();
+        assert_eq!(last.state, NavigatorState::GoalReached);
+        assert_eq!(last.goal_id.as_deref(), Some("job-1"));
+        // only the changes
+        assert!(statuses.len() < 5);
+
+        assert!(adapter.on_message("openrr_nav/robot1/cancel", b"").unwrap());
+        assert_eq!(adapter.navigator().state(), NavigatorState::Idle);
+    }
+}
@@ -0,0 +1,309 @@
+//! Protobuf encoding of the maps, paths and plans
+//!
+//! The messages are defined in `proto/openrr_nav.proto` (package `openrr_nav.v1`),
+//! so the logs and the network messages can be read from the other languages. The
+//! Rust types are written with prost by hand to build without protoc, and must be
+//! kept in sync with the schema.
+
+use grid_map::{Cell, GridMap, LayerId, LayeredGridMap, Position, Size};
+use nalgebra as na;
+use prost::Message;
+use std::collections::HashMap;
+
+use crate::{Error, Plan, Pose, Result, Velocity};
+
+/// Messages of `openrr_nav.v1`
+pub mod pb {
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Position {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Pose {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub y: f64,
+        #[prost(double, tag = "3")]
+        pub theta: f64,
+    }
+
+    #[derive(Clone, Copy, PartialEq, prost::Message)]
+    pub struct Velocity {
+        #[prost(double, tag = "1")]
+        pub x: f64,
+        #[prost(double, tag = "2")]
+        pub theta: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct GridMap {
+        #[prost(double, tag = "1")]
+        pub resolution: f64,
+        #[prost(message, optional, tag = "2")]
+        pub min_point: Option<Position>,
+        #[prost(uint32, tag = "3")]
+        pub width: u32,
+        #[prost(uint32, tag = "4")]
+        pub height: u32,
+        /// Not set for the identity
+        #[prost(message, optional, tag = "5")]
+        pub origin: Option<Pose>,
+        /// The value (0-255), [`CELL_UNKNOWN`], [`CELL_OBSTACLE`] or
+        /// [`CELL_UNINITIALIZED`]
+        #[prost(sint32, repeated, tag = "6")]
+        pub cells: Vec<i32>,
+    }
+
+    pub const CELL_UNKNOWN: i32 = -1;
+    pub const CELL_OBSTACLE: i32 = -2;
+    pub const CELL_UNINITIALIZED: i32 = -3;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct NamedGridMap {
+        #[prost(string, tag = "1")]
+        pub name: String,
+        #[prost(message, optional, tag = "2")]
+        pub map: Option<GridMap>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct LayeredGridMap {
+        #[prost(message, repeated, tag = "1")]
+        pub layers: Vec<NamedGridMap>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Plan {
+        #[prost(message, optional, tag = "1")]
+        pub velocity: Option<Velocity>,
+        #[prost(double, tag = "2")]
+        pub cost: f64,
+        #[prost(message, repeated, tag = "3")]
+        pub path: Vec<Pose>,
+    }
+}
+
+/// Types encoded as the messages of [`pb`]
+pub trait ProtoCodec: Sized {
+    type Message: Message + Default;
+
+    fn to_proto(&self) -> Self::Message;
+
+    fn from_proto(message: Self::Message) -> Result<Self>;
+
+    fn encode_proto(&self) -> Vec<u8> {
+        self.to_proto().encode_to_vec()
+    }
+
+    fn decode_proto(buf: &[u8]) -> Result<Self> {
+        let message = Self::Message::decode(buf)
+            .map_err(|e| Error::Other(format!("failed to decode protobuf: {e}")))?;
+        Self::from_proto(message)
+    }
+}
+
+fn missing(field: &str) -> Error {
+    Error::Other(format!("{field} is not set"))
+}
+
+impl ProtoCodec for Pose {
+    type Message = pb::Pose;
+
+    fn to_proto(&self) -> pb::Pose {
+        pb::Pose {
+            x: self.translation.x,
+            y: self.translation.y,
+            theta: self.rotation.angle(),
+        }
+    }
+
+    fn from_proto(pose: pb::Pose) -> Result<Self> {
+        Ok(Pose::new(na::Vector2::new(pose.x, pose.y), pose.theta))
+    }
+}
+
+impl ProtoCodec for GridMap<u8> {
+    type Message = pb::GridMap;
+
+    fn to_proto(&self) -> pb::GridMap {
+        pb::GridMap {
+            resolution: self.resolution(),
+            min_point: Some(pb::Position {
+                x: self.min_point().x,
+                y: self.min_point().y,
+            }),
+            width: self.width() as u32,
+            height: self.height() as u32,
+            origin: self.origin().map(|origin| origin.to_proto()),
+            cells: self
+                .cells()
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Value(v) => *v as i32,
+                    Cell::Unknown => pb::CELL_UNKNOWN,
+                    Cell::Obstacle => pb::CELL_OBSTACLE,
+                    Cell::Uninitialized => pb::CELL_UNINITIALIZED,
+                })
+                .collect(),
+        }
+    }
+
+    fn from_proto(map: pb::GridMap) -> Result<Self> {
+        let min_point = map.min_point.ok_or_else(|| missing("min_point"))?;
+        let (width, height, resolution) = (map.width as usize, map.height as usize, map.resolution);
+        if resolution <= 0.0 || map.cells.len() != width * height {
+            return Err(Error::Other(format!(
+                "invalid GridMap of {width}x{height} with {} cells and resolution {resolution}",
+                map.cells.len()
+            )));
+        }
+        let mut grid_map = GridMap::with_size(
+            Position::new(min_point.x, min_point.y),
+            Size::new(width, height),
+            resolution,
+        );
+        for (cell, value) in grid_map.cells_mut().iter_mut().zip(&map.cells) {
+            *cell = match *value {
+                v @ 0..=255 => Cell::Value(v as u8),
+                pb::CELL_UNKNOWN => Cell::Unknown,
+                pb::CELL_OBSTACLE => Cell::Obstacle,
+                pb::CELL_UNINITIALIZED => Cell::Uninitialized,
+                v => return Err(Error::Other(format!("invalid cell {v}"))),
+            };
+        }
+        match map.origin {
+            Some(origin) => Ok(grid_map.with_origin(Pose::from_proto(origin)?)),
+            None => Ok(grid_map),
+        }
+    }
+}
+
+impl ProtoCodec for LayeredGridMap<u8> {
+    type Message = pb::LayeredGridMap;
+
+    /// The layers are sorted by the name to make the encoding deterministic.
+    fn to_proto(&self) -> pb::LayeredGridMap {
+        let mut ids = self.layer_ids().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.name());
+        pb::LayeredGridMap {
+            layers: ids
+                .into_iter()
+                .map(|id| pb::NamedGridMap {
+                    name: id.name().to_owned(),
+                    map: self.layer(id).map(|map| map.to_proto()),
+                })
+                .collect(),
+        }
+    }
+
+    fn from_proto(layered: pb::LayeredGridMap) -> Result<Self> {
+        let mut maps = HashMap::new();
+        for layer in layered.layers {
+            let map = layer.map.ok_or_else(|| missing("map"))?;
+            maps.insert(LayerId::new(&layer.name), GridMap::from_proto(map)?);
+        }
+        Ok(LayeredGridMap::new(maps))
+    }
+}
+
+/// [`Plan::sampling_issue`] is not encoded.
+impl ProtoCodec for Plan {
+    type Message = pb::Plan;
+
+    fn to_proto(&self) -> pb::Plan {
+        pb::Plan {
+            velocity: Some(pb::Velocity {
+                x: self.velocity.x,
+                theta: self.velocity.theta,
+            }),
+            cost: self.cost,
+            path: self.path.iter().map(Pose::to_proto).collect(),
+        }
+    }
+
+    fn from_proto(plan: pb::Plan) -> Result<Self> {
+        let velocity = plan.velocity.ok_or_else(|| missing("velocity"))?;
+        Ok(Plan {
+            velocity: Velocity {
+                x: velocity.x,
+                theta: velocity.theta,
+            },
+            cost: plan.cost,
+            path: plan
+                .path
+                .into_iter()
+                .map(Pose::from_proto)
+                .collect::<Result<_>>()?,
+            sampling_issue: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proto_codec() {
+        let mut map = GridMap::<u8>::new(Position::new(-1.0, 0.5), Position::new(1.0, 1.5), 0.1)
+            .with_origin(Pose::new(na::Vector2::new(2.0, 3.0), 0.5));
+        for (i, cell) in map.cells_mut().iter_mut().enumerate() {
+            *cell = match i % 4 {
+                0 => Cell::Value((i % 256) as u8),
+                1 => Cell::Unknown,
+                2 => Cell::Obstacle,
+                _ => Cell::Uninitialized,
+            };
+        }
+        let decoded = GridMap::<u8>::decode_proto(&map.encode_proto()).unwrap();
+        assert_eq!(
+            (decoded.width(), decoded.height()),
+            (map.width(), map.height())
+        );
+        assert_eq!(decoded.min_point(), map.min_point());
+        assert_eq!(decoded.cells(), map.cells());
+        let origin = decoded.origin().unwrap();
+        assert!((origin.translation.vector - na::Vector2::new(2.0, 3.0)).norm() < 1e-9);
+        assert!((origin.rotation.angle() - 0.5).abs() < 1e-9);
+
+        let layered = LayeredGridMap::new(HashMap::from([
+            (LayerId::new("a"), map.clone()),
+            (
+                LayerId::new("b"),
+                GridMap::from_proto(map.to_proto()).unwrap(),
+            ),
+        ]));
+        let decoded = LayeredGridMap::<u8>::decode_proto(&layered.encode_proto()).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(
+            decoded.layer(LayerId::new("b")).unwrap().cells(),
+            map.cells()
+        );
+
+        let plan = Plan {
+            velocity: Velocity {
+                x: 0.3,
+                theta: -0.1,
+            },
+            cost: 1.5,
+            path: vec![Pose::new(na::Vector2::new(0.1, 0.2), 0.3)],
+            sampling_issue: None,
+        };
+        let decoded = Plan::decode_proto(&plan.encode_proto()).unwrap();
+        assert_eq!(decoded.velocity, plan.velocity);
+        assert_eq!(decoded.cost, plan.cost);
+        assert!(
+            (decoded.path[0].translation.vector - plan.path[0].translation.vector).norm() < 1e-9
+        );
+
+        let mut broken = map.to_proto();
+        broken.cells.pop();
+        assert!(GridMap::<u8>::from_proto(broken).is_err());
+        assert!(Plan::decode_proto(&[0xff]).is_err());
+    }
+}
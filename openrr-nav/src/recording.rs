@@ -0,0 +1,271 @@
+use grid_map::GridMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    Error, Navigator, NavigatorState, Pose, RangeReading, Result, ScanIntegrator, Velocity,
+};
+
+const MAGIC: &[u8; 8] = b"NAVLOG01";
+
+/// Message of the recorded run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecordedMessage {
+    Map(GridMap<u8>),
+    /// Pose in the map frame and the measured velocity
+    Odometry {
+        pose: Pose,
+        velocity: Velocity,
+    },
+    Scan(Vec<RangeReading>),
+    Goal(Pose),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// (sec)
+    pub stamp: f64,
+    pub message: RecordedMessage,
+}
+
+/// Writer of the recorded run in the binary log format
+///
+/// The log is the header followed by the frames, so the frames can be appended
+/// while the robot is running.
+#[derive(Debug)]
+pub struct LogWriter<W: Write> {
+    writer: W,
+}
+
+impl LogWriter<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> LogWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer.write_all(MAGIC)?;
+        Ok(Self { writer })
+    }
+
+    pub fn write(&mut self, stamp: f64, message: RecordedMessage) -> Result<()> {
+        bincode::serialize_into(&mut self.writer, &RecordedFrame { stamp, message })?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reader of the log written by [`LogWriter`]
+///
+/// Iterates the frames in the recorded order. The broken last frame (e.g. the
+/// recording process was killed) is ignored.
+#[derive(Debug)]
+pub struct LogReader<R: Read> {
+    reader: R,
+}
+
+impl LogReader<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> LogReader<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Error::Other("not a navigation log".to_owned()));
+        }
+        Ok(Self { reader })
+    }
+}
+
+impl<R: Read> Iterator for LogReader<R> {
+    type Item = Result<RecordedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match bincode::deserialize_from(&mut self.reader) {
+            Ok(frame) => Some(Ok(frame)),
+            Err(e) => match &*e {
+                bincode::ErrorKind::Io(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+                _ => Some(Err(e.into())),
+            },
+        }
+    }
+}
+
+/// Result of a control cycle in [`Playback`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlaybackStep {
+    pub stamp: f64,
+    pub pose: Pose,
+    /// Velocity of the robot in the recorded run
+    pub recorded_velocity: Velocity,
+    /// Velocity commanded by the navigator in the playback
+    pub command: Velocity,
+    pub state: NavigatorState,
+}
+
+/// Replay the recorded run through the [`Navigator`] offline
+///
+/// The maps, scans and goals are passed to the navigator in the recorded order,
+/// and the navigator ticks at each odometry with the recorded pose. Comparing the
+/// commands with the recorded velocities validates the planner changes against
+/// the real runs. The robot doesn't follow the commands, so the replay is
+/// meaningful only while the commands are close to the recorded ones.
+#[derive(Debug)]
+pub struct Playback {
+    navigator: Navigator,
+    scan_integrator: Option<ScanIntegrator>,
+    last_pose: Option<Pose>,
+}
+
+impl Playback {
+    pub fn new(navigator: Navigator) -> Self {
+        Self {
+            navigator,
+            scan_integrator: None,
+            last_pose: None,
+        }
+    }
+
+    /// Integrate the recorded scans into the map of the navigator
+    pub fn with_scan_integrator(mut self, scan_integrator: ScanIntegrator) -> Self {
+        self.scan_integrator = Some(scan_integrator);
+        self
+    }
+
+    pub fn navigator(&self) -> &Navigator {
+        &self.navigator
+    }
+
+    pub fn into_navigator(self) -> Navigator {
+        self.navigator
+    }
+
+    /// Process a frame. Returns the step for the odometry.
+    pub fn step(&mut self, frame: &RecordedFrame) -> Result<Option<PlaybackStep>> {
+        match &frame.message {
+            RecordedMessage::Map(map) => {
+                if map.cells().len() != map.width() * map.height() {
+                    return Err(Error::Other("broken map in the log".to_owned()));
+                }
+                self.navigator.set_map(map.clone())?;
+            }
+            RecordedMessage::Scan(readings) => {
+                // the scan before the first odometry is ignored
+                if let (Some(integrator), Some(pose)) = (&self.scan_integrator, &self.last_pose) {
+                    let mut map = self.navigator.map().clone();
+                    integrator.integrate(&mut map, pose, readings);
+                    self.navigator.set_map(map)?;
+                }
+            }
+            RecordedMessage::Goal(goal) => self.navigator.set_goal(*goal),
+            RecordedMessage::Odometry { pose, velocity } => {
+                self.last_pose = Some(*pose);
+                let command = self.navigator.tick(pose, velocity).velocity();
+                return Ok(Some(PlaybackStep {
+                    stamp: frame.stamp,
+                    pose: *pose,
+                    recorded_velocity: *velocity,
+                    command,
+                    state: self.navigator.state(),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Replay all frames and return the steps
+    pub fn play<I>(&mut self, frames: I) -> Result<Vec<PlaybackStep>>
+    where
+        I: IntoIterator<Item = Result<RecordedFrame>>,
+    {
+        let mut steps = vec![];
+        for frame in frames {
+            steps.extend(self.step(&frame?)?);
+        }
+        Ok(steps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        testkit::{self, DT},
+        AStarPlanner,
+    };
+    use grid_map::Position;
+    use nalgebra as na;
+
+    #[test]
+    fn test_record_and_playback() {
+        let map = testkit::free_map();
+        // the clone sums the costs in the same order as the recording, so the
+        // commands are exactly the same
+        let dwa = testkit::turtlebot_dwa();
+
+        // record a run driven by a navigator
+        let mut navigator = Navigator::new(
+            Box::new(AStarPlanner::default()),
+            Box::new(dwa.clone()),
+            map.clone(),
+        );
+        let goal = Pose::new(na::Vector2::new(2.0, 1.5), 0.0);
+        let mut writer = LogWriter::new(vec![]).unwrap();
+        writer
+            .write(0.0, RecordedMessage::Map(map.clone()))
+            .unwrap();
+        writer.write(0.0, RecordedMessage::Goal(goal)).unwrap();
+        navigator.set_goal(goal);
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let mut velocity = Velocity { x: 0.0, theta: 0.0 };
+        let mut recorded = vec![];
+        for i in 0..1000 {
+            let stamp = i as f64 * DT;
+            writer
+                .write(stamp, RecordedMessage::Odometry { pose, velocity })
+                .unwrap();
+            velocity = navigator.tick(&pose, &velocity).velocity();
+            recorded.push(velocity);
+            if navigator.state().is_finished() {
+                break;
+            }
+            pose = testkit::move_pose(&pose, &velocity, DT);
+        }
+        assert_eq!(navigator.state(), NavigatorState::GoalReached);
+        let mut log = writer.into_inner();
+        // broken last frame
+        log.extend_from_slice(&[1, 0]);
+
+        let reader = LogReader::new(log.as_slice()).unwrap();
+        let mut playback = Playback::new(Navigator::new(
+            Box::new(AStarPlanner::default()),
+            Box::new(dwa),
+            GridMap::new(Position::new(0.0, 0.0), Position::new(1.0, 1.0), 0.05),
+        ));
+        let steps = playback.play(reader).unwrap();
+        assert_eq!(steps.len(), recorded.len());
+        for (step, command) in steps.iter().zip(&recorded) {
+            assert_eq!(step.command, *command);
+        }
+        assert_eq!(playback.navigator().state(), NavigatorState::GoalReached);
+
+        assert!(LogReader::new(&b"otherlog"[..]).is_err());
+    }
+}
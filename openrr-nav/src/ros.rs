@@ -0,0 +1,446 @@
+//! ROS messages used by the navigation stack and the conversions
+//!
+//! The messages have the same fields as the ROS1 messages (and the ROS 2 messages of
+//! the same names), so the generated types of rosrust or r2r can be converted field by
+//! field without depending on them here. They are also serialized as the JSON of
+//! rosbridge.
+
+use grid_map::{Cell, GridMap, Position, Size};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, RangeReading, Result, Velocity};
+
+pub mod std_msgs {
+    use super::*;
+
+    /// `time` of ROS1
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Time {
+        pub secs: u32,
+        pub nsecs: u32,
+    }
+
+    /// `std_msgs/Header`
+    #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct Header {
+        pub seq: u32,
+        pub stamp: Time,
+        pub frame_id: String,
+    }
+
+    impl Header {
+        pub fn new(frame_id: impl Into<String>) -> Self {
+            Self {
+                frame_id: frame_id.into(),
+                ..Default::default()
+            }
+        }
+    }
+}
+
+pub mod geometry_msgs {
+    use super::*;
+
+    /// `geometry_msgs/Point`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Point {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+    }
+
+    /// `geometry_msgs/Vector3`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Vector3 {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+    }
+
+    /// `geometry_msgs/Quaternion`
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+    pub struct Quaternion {
+        pub x: f64,
+        pub y: f64,
+        pub z: f64,
+        pub w: f64,
+    }
+
+    impl Default for Quaternion {
+        fn default() -> Self {
+            Self {
+                x: 0.0,
+                y: 0.0,
+                z: 0.0,
+                w: 1.0,
+            }
+        }
+    }
+
+    impl Quaternion {
+        /// Rotation around the z axis
+        pub fn from_yaw(yaw: f64) -> Self {
+            let (sin, cos) = (yaw / 2.0).sin_cos();
+            Self {
+                x: 0.0,
+                y: 0.0,
+                z: sin,
+                w: cos,
+            }
+        }
+
+        /// Yaw of the rotation. The roll and the pitch are ignored.
+        pub fn yaw(&self) -> f64 {
+            let q = na::UnitQuaternion::from_quaternion(na::Quaternion::new(
+                self.w, self.x, self.y, self.z,
+            ));
+            q.euler_angles().2
+        }
+    }
+
+    /// `geometry_msgs/Pose`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Pose {
+        pub position: Point,
+        pub orientation: Quaternion,
+    }
+
+    impl From<&crate::Pose> for Pose {
+        fn from(pose: &crate::Pose) -> Self {
+            Self {
+                position: Point {
+                    x: pose.translation.x,
+                    y: pose.translation.y,
+                    z: 0.0,
+                },
+                orientation: Quaternion::from_yaw(pose.rotation.angle()),
+            }
+        }
+    }
+
+    impl From<&Pose> for crate::Pose {
+        fn from(pose: &Pose) -> Self {
+            crate::Pose::new(
+                na::Vector2::new(pose.position.x, pose.position.y),
+                pose.orientation.yaw(),
+            )
+        }
+    }
+
+    /// `geometry_msgs/PoseStamped`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct PoseStamped {
+        pub header: std_msgs::Header,
+        pub pose: Pose,
+    }
+
+    impl PoseStamped {
+        pub fn new(header: std_msgs::Header, pose: &crate::Pose) -> Self {
+            Self {
+                header,
+                pose: pose.into(),
+            }
+        }
+    }
+
+    impl From<&PoseStamped> for crate::Pose {
+        fn from(pose: &PoseStamped) -> Self {
+            (&pose.pose).into()
+        }
+    }
+
+    /// `geometry_msgs/Twist`
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Twist {
+        pub linear: Vector3,
+        pub angular: Vector3,
+    }
+
+    impl From<&Velocity> for Twist {
+        fn from(velocity: &Velocity) -> Self {
+            Self {
+                linear: Vector3 {
+                    x: velocity.x,
+                    ..Default::default()
+                },
+                angular: Vector3 {
+                    z: velocity.theta,
+                    ..Default::default()
+                },
+            }
+        }
+    }
+
+    impl From<&Twist> for Velocity {
+        fn from(twist: &Twist) -> Self {
+            Velocity {
+                x: twist.linear.x,
+                theta: twist.angular.z,
+            }
+        }
+    }
+
+    /// `geometry_msgs/PoseWithCovariance`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct PoseWithCovariance {
+        pub pose: Pose,
+        /// Row-major 6x6 covariance of (x, y, z, roll, pitch, yaw)
+        pub covariance: Vec<f64>,
+    }
+
+    /// `geometry_msgs/TwistWithCovariance`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct TwistWithCovariance {
+        pub twist: Twist,
+        /// Row-major 6x6 covariance of the linear and the angular velocities
+        pub covariance: Vec<f64>,
+    }
+}
+
+pub mod sensor_msgs {
+    use super::*;
+
+    /// `sensor_msgs/LaserScan`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct LaserScan {
+        pub header: std_msgs::Header,
+        /// (rad)
+        pub angle_min: f32,
+        pub angle_max: f32,
+        pub angle_increment: f32,
+        /// (s)
+        pub time_increment: f32,
+        pub scan_time: f32,
+        /// (m)
+        pub range_min: f32,
+        pub range_max: f32,
+        pub ranges: Vec<f32>,
+        pub intensities: Vec<f32>,
+    }
+
+    impl LaserScan {
+        /// Readings in the sensor frame. The ranges out of `[range_min, range_max]`
+        /// become NaN (no measurement) except the infinity (no obstacle).
+        pub fn readings(&self) -> Vec<RangeReading> {
+            self.ranges
+                .iter()
+                .enumerate()
+                .map(|(i, range)| {
+                    let bearing = self.angle_min as f64 + i as f64 * self.angle_increment as f64;
+                    let range = if *range == f32::INFINITY
+                        || (self.range_min..=self.range_max).contains(range)
+                    {
+                        *range as f64
+                    } else {
+                        f64::NAN
+                    };
+                    RangeReading::new(bearing, range)
+                })
+                .collect()
+        }
+    }
+}
+
+pub mod nav_msgs {
+    use super::*;
+
+    /// Value of the unknown cells of [`OccupancyGrid`]
+    pub const UNKNOWN: i8 = -1;
+    /// Value of the occupied cells of [`OccupancyGrid`]
+    pub const OCCUPIED: i8 = 100;
+
+    /// `nav_msgs/MapMetaData`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct MapMetaData {
+        pub map_load_time: std_msgs::Time,
+        /// (m/cell)
+        pub resolution: f32,
+        pub width: u32,
+        pub height: u32,
+        /// Pose of the cell (0, 0) in the frame of the header
+        pub origin: geometry_msgs::Pose,
+    }
+
+    /// `nav_msgs/OccupancyGrid`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct OccupancyGrid {
+        pub header: std_msgs::Header,
+        pub info: MapMetaData,
+        /// Row-major from the cell (0, 0), `-1` for the unknown cells
+        pub data: Vec<i8>,
+    }
+
+    impl OccupancyGrid {
+        /// Convert the map
+        ///
+        /// [`Cell::Obstacle`] becomes `100`, the values are clamped to `[0, 99]`, and
+        /// the others become `-1`. The origin is the pose of the min point of the map
+        /// in the world frame (see [`GridMap::origin`]).
+        pub fn from_grid_map(header: std_msgs::Header, map: &GridMap<u8>) -> Self {
+            let min_point = map.map_to_world(map.min_point());
+            let yaw = map.origin().map_or(0.0, |o| o.rotation.angle());
+            let origin = crate::Pose::new(na::Vector2::new(min_point.x, min_point.y), yaw);
+            Self {
+                header,
+                info: MapMetaData {
+                    map_load_time: Default::default(),
+                    resolution: map.resolution() as f32,
+                    width: map.width() as u32,
+                    height: map.height() as u32,
+                    origin: (&origin).into(),
+                },
+                data: map
+                    .cells()
+                    .iter()
+                    .map(|cell| match cell {
+                        Cell::Obstacle => OCCUPIED,
+                        Cell::Value(v) => (*v).min(99) as i8,
+                        Cell::Unknown | Cell::Uninitialized => UNKNOWN,
+                    })
+                    .collect(),
+            }
+        }
+
+        /// Convert into the map
+        ///
+        /// `100` (and larger) become [`Cell::Obstacle`], `[0, 99]` become the values,
+        /// and the negative values become [`Cell::Unknown`]. If the origin is rotated,
+        /// the map frame starts at the origin and the pose is kept as
+        /// [`GridMap::origin`].
+        pub fn to_grid_map(&self) -> Result<GridMap<u8>> {
+            let (w, h) = (self.info.width as usize, self.info.height as usize);
+            let resolution = self.info.resolution as f64;
+            if resolution <= 0.0 || self.data.len() != w * h {
+                return Err(Error::Other(format!(
+                    "invalid OccupancyGrid of {w}x{h} with {} cells and resolution {resolution}",
+                    self.data.len()
+                )));
+            }
+            let origin = crate::Pose::from(&self.info.origin);
+            let yaw = origin.rotation.angle();
+            let min_point = if yaw == 0.0 {
+                Position::new(origin.translation.x, origin.translation.y)
+            } else {
+                Position::new(0.0, 0.0)
+            };
+            let mut map = GridMap::with_size(min_point, Size::new(w, h), resolution);
+            for (cell, value) in map.cells_mut().iter_mut().zip(&self.data) {
+                *cell = match *value {
+                    v if v >= OCCUPIED => Cell::Obstacle,
+                    v if v < 0 => Cell::Unknown,
+                    v => Cell::Value(v as u8),
+                };
+            }
+            if yaw != 0.0 {
+                map.set_origin(Some(origin));
+            }
+            Ok(map)
+        }
+    }
+
+    /// `nav_msgs/Odometry`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Odometry {
+        pub header: std_msgs::Header,
+        pub child_frame_id: String,
+        /// Pose in the frame of the header
+        pub pose: geometry_msgs::PoseWithCovariance,
+        /// Velocity in the frame of `child_frame_id`
+        pub twist: geometry_msgs::TwistWithCovariance,
+    }
+
+    impl Odometry {
+        pub fn pose(&self) -> crate::Pose {
+            (&self.pose.pose).into()
+        }
+
+        pub fn velocity(&self) -> Velocity {
+            (&self.twist.twist).into()
+        }
+    }
+
+    /// `nav_msgs/Path`
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    pub struct Path {
+        pub header: std_msgs::Header,
+        pub poses: Vec<geometry_msgs::PoseStamped>,
+    }
+
+    impl Path {
+        /// Path of the poses, all of which have the same header
+        pub fn from_poses(header: std_msgs::Header, poses: &[crate::Pose]) -> Self {
+            Self {
+                poses: poses
+                    .iter()
+                    .map(|pose| geometry_msgs::PoseStamped::new(header.clone(), pose))
+                    .collect(),
+                header,
+            }
+        }
+
+        pub fn to_poses(&self) -> Vec<crate::Pose> {
+            self.poses.iter().map(crate::Pose::from).collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pose;
+    use grid_map::Grid;
+
+    #[test]
+    fn test_ros_conversions() {
+        let header = std_msgs::Header::new("map");
+        let mut map = GridMap::<u8>::new(Position::new(-1.0, 0.5), Position::new(1.0, 1.5), 0.1);
+        map.set_obstacle(&Grid::new(3, 2)).unwrap();
+        map.set_value(&Grid::new(4, 2), 50).unwrap();
+        map.set_value(&Grid::new(5, 2), 200).unwrap();
+        let grid = nav_msgs::OccupancyGrid::from_grid_map(header.clone(), &map);
+        assert_eq!((grid.info.width, grid.info.height), (20, 10));
+        assert_eq!(grid.data[2 * 20 + 3], nav_msgs::OCCUPIED);
+        assert_eq!(grid.data[2 * 20 + 4], 50);
+        assert_eq!(grid.data[2 * 20 + 5], 99);
+        assert_eq!(grid.data[0], nav_msgs::UNKNOWN);
+        assert_eq!(grid.info.origin.position.x, -1.0);
+        let restored = grid.to_grid_map().unwrap();
+        assert_eq!((restored.width(), restored.height()), (20, 10));
+        assert_eq!(restored.min_point(), map.min_point());
+        assert_eq!(restored.cell(&Grid::new(3, 2)), Some(&Cell::Obstacle));
+        assert_eq!(restored.cell(&Grid::new(4, 2)), Some(&Cell::Value(50)));
+        assert_eq!(restored.cell(&Grid::new(0, 0)), Some(&Cell::Unknown));
+
+        // rotated origin
+        let rotated = map
+            .clone()
+            .with_origin(Pose::new(na::Vector2::new(2.0, 1.0), 0.5));
+        let grid = nav_msgs::OccupancyGrid::from_grid_map(header.clone(), &rotated);
+        let restored = grid.to_grid_map().unwrap();
+        let p = rotated.map_to_world(&rotated.cell_center(&Grid::new(3, 2)));
+        assert_eq!(restored.cell_by_position(&p), Some(&Cell::Obstacle));
+        let mut invalid = grid;
+        invalid.data.pop();
+        assert!(invalid.to_grid_map().is_err());
+
+        let poses = vec![
+            Pose::new(na::Vector2::new(0.0, 1.0), 0.3),
+            Pose::new(na::Vector2::new(1.0, 2.0), -2.0),
+        ];
+        let path = nav_msgs::Path::from_poses(header, &poses);
+        assert_eq!(path.poses[1].header.frame_id, "map");
+        for (a, b) in path.to_poses().iter().zip(&poses) {
+            assert!((a.translation.vector - b.translation.vector).norm() < 1e-12);
+            assert!((a.rotation.angle() - b.rotation.angle()).abs() < 1e-12);
+        }
+
+        let velocity = Velocity {
+            x: 0.3,
+            theta: -0.5,
+        };
+        let twist = geometry_msgs::Twist::from(&velocity);
+        assert_eq!(twist.angular.z, -0.5);
+        assert_eq!(Velocity::from(&twist), velocity);
+    }
+}
@@ -0,0 +1,185 @@
+use nalgebra as na;
+
+use crate::{
+    ros::{geometry_msgs, nav_msgs, sensor_msgs, std_msgs},
+    Navigator, NavigatorState, Pose, Result, ScanIntegrator, Velocity,
+};
+
+pub const MAP_TOPIC: &str = "/map";
+pub const ODOM_TOPIC: &str = "/odom";
+pub const SCAN_TOPIC: &str = "/scan";
+pub const GOAL_TOPIC: &str = "/goal_pose";
+pub const CMD_VEL_TOPIC: &str = "/cmd_vel";
+pub const PLAN_TOPIC: &str = "/plan";
+
+/// Messages to be published after [`NavigationBridge::spin_once`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BridgeOutput {
+    /// Published to [`CMD_VEL_TOPIC`]
+    pub cmd_vel: geometry_msgs::Twist,
+    /// Published to [`PLAN_TOPIC`] when the global path is changed
+    pub plan: Option<nav_msgs::Path>,
+    pub state: NavigatorState,
+}
+
+/// ROS 2 node of the [`Navigator`] like nav2 for the simple robots
+///
+/// The bridge doesn't depend on the ROS client library. Subscribe [`MAP_TOPIC`],
+/// [`ODOM_TOPIC`], [`SCAN_TOPIC`] and [`GOAL_TOPIC`] with r2r or rclrs, pass the
+/// messages to the `on_*` methods (the generated messages have the same fields as
+/// [`crate::ros`] except the headers), and call [`NavigationBridge::spin_once`] in
+/// the timer of the control period to publish [`CMD_VEL_TOPIC`] and [`PLAN_TOPIC`].
+/// `examples/ros2_node.rs` (the `r2r` feature) is the node wired with r2r.
+///
+/// The scans are integrated into the map of the navigator by the
+/// [`ScanIntegrator`], if any. The odometry is used as the pose in the map frame,
+/// so localize the robot (e.g. by [`ParticleFilter`](crate::ParticleFilter)) and
+/// publish the pose in the map frame as the odometry if the odometry drifts.
+#[derive(Debug)]
+pub struct NavigationBridge {
+    navigator: Navigator,
+    frame_id: String,
+    scan_integrator: Option<ScanIntegrator>,
+    odometry: Option<(Pose, Velocity)>,
+    published_path: Vec<Vec<f64>>,
+}
+
+impl NavigationBridge {
+    pub fn new(navigator: Navigator, frame_id: impl Into<String>) -> Self {
+        Self {
+            navigator,
+            frame_id: frame_id.into(),
+            scan_integrator: None,
+            odometry: None,
+            published_path: vec![],
+        }
+    }
+
+    pub fn with_scan_integrator(mut self, scan_integrator: ScanIntegrator) -> Self {
+        self.scan_integrator = Some(scan_integrator);
+        self
+    }
+
+    pub fn navigator(&self) -> &Navigator {
+        &self.navigator
+    }
+
+    pub fn navigator_mut(&mut self) -> &mut Navigator {
+        &mut self.navigator
+    }
+
+    /// Callback of [`MAP_TOPIC`]
+    pub fn on_map(&mut self, map: &nav_msgs::OccupancyGrid) -> Result<()> {
+        self.navigator.set_map(map.to_grid_map()?)
+    }
+
+    /// Callback of [`ODOM_TOPIC`]
+    pub fn on_odom(&mut self, odometry: &nav_msgs::Odometry) {
+        self.odometry = Some((odometry.pose(), odometry.velocity()));
+    }
+
+    /// Callback of [`SCAN_TOPIC`]. The scan is ignored before the first odometry.
+    pub fn on_scan(&mut self, scan: &sensor_msgs::LaserScan) -> Result<()> {
+        let (Some(integrator), Some((pose, _))) = (&self.scan_integrator, &self.odometry) else {
+            return Ok(());
+        };
+        let mut map = self.navigator.map().clone();
+        integrator.integrate(&mut map, pose, &scan.readings());
+        self.navigator.set_map(map)
+    }
+
+    /// Callback of [`GOAL_TOPIC`]
+    pub fn on_goal(&mut self, goal: &geometry_msgs::PoseStamped) {
+        self.navigator.set_goal(Pose::from(goal));
+    }
+
+    /// Run a control cycle. Returns `None` before the first odometry.
+    pub fn spin_once(&mut self) -> Option<BridgeOutput> {
+        let (pose, velocity) = self.odometry?;
+        let command = self.navigator.tick(&pose, &velocity);
+        let path = self.navigator.global_path();
+        let plan = (path != self.published_path.as_slice()).then(|| {
+            self.published_path = path.to_vec();
+            let poses = path
+                .iter()
+                .map(|p| Pose::new(na::Vector2::new(p[0], p[1]), p[2]))
+                .collect::<Vec<_>>();
+            nav_msgs::Path::from_poses(std_msgs::Header::new(self.frame_id.clone()), &poses)
+        });
+        Some(BridgeOutput {
+            cmd_vel: (&command.velocity()).into(),
+            plan,
+            state: self.navigator.state(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{free_map, move_pose, navigator, DT};
+    use grid_map::Position;
+
+    #[test]
+    fn test_navigation_bridge() {
+        let mut bridge = NavigationBridge::new(navigator(free_map()), "map")
+            .with_scan_integrator(ScanIntegrator::new(3.0));
+        assert!(bridge.spin_once().is_none());
+
+        let free = free_map();
+        let header = std_msgs::Header::new("map");
+        bridge
+            .on_map(&nav_msgs::OccupancyGrid::from_grid_map(
+                header.clone(),
+                &free,
+            ))
+            .unwrap();
+        let mut pose = Pose::new(na::Vector2::new(0.5, 0.5), 0.0);
+        let odometry = |pose: &Pose, twist: geometry_msgs::Twist| nav_msgs::Odometry {
+            header: header.clone(),
+            pose: geometry_msgs::PoseWithCovariance {
+                pose: pose.into(),
+                ..Default::default()
+            },
+            twist: geometry_msgs::TwistWithCovariance {
+                twist,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        bridge.on_odom(&odometry(&pose, Default::default()));
+        bridge
+            .on_scan(&sensor_msgs::LaserScan {
+                angle_min: 0.0,
+                angle_increment: 0.1,
+                range_max: 3.0,
+                ranges: vec![1.0],
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(
+            bridge
+                .navigator()
+                .map()
+                .cell_by_position(&Position::new(1.5, 0.5)),
+            Some(&grid_map::Cell::Obstacle)
+        );
+        bridge.on_goal(&geometry_msgs::PoseStamped::new(
+            header.clone(),
+            &Pose::new(na::Vector2::new(2.0, 1.5), 0.0),
+        ));
+        let mut num_plans = 0;
+        for _ in 0..1000 {
+            let output = bridge.spin_once().unwrap();
+            num_plans += output.plan.is_some() as usize;
+            if output.state.is_finished() {
+                break;
+            }
+            let velocity = Velocity::from(&output.cmd_vel);
+            pose = move_pose(&pose, &velocity, DT);
+            bridge.on_odom(&odometry(&pose, output.cmd_vel));
+        }
+        assert_eq!(bridge.navigator().state(), NavigatorState::GoalReached);
+        assert!(num_plans >= 1);
+    }
+}
@@ -0,0 +1,707 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use grid_map::{Aggregation, Cell, GridMap};
+use nalgebra as na;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Error, LocalPlannerConfig, Navigator, NavigatorConfig, NavigatorState, Pose, Result, Velocity,
+};
+
+/// Pose in the JSON messages
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pose2d {
+    pub x: f64,
+    pub y: f64,
+    pub theta: f64,
+}
+
+impl From<&Pose> for Pose2d {
+    fn from(pose: &Pose) -> Self {
+        Self {
+            x: pose.translation.x,
+            y: pose.translation.y,
+            theta: pose.rotation.angle(),
+        }
+    }
+}
+
+impl From<&Pose2d> for Pose {
+    fn from(pose: &Pose2d) -> Self {
+        Pose::new(na::Vector2::new(pose.x, pose.y), pose.theta)
+    }
+}
+
+/// Message streamed to the dashboards by [`TelemetryServer`] in JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TelemetryMessage {
+    Pose(Pose2d),
+    Status {
+        state: NavigatorState,
+        goal: Option<Pose2d>,
+        last_error: Option<String>,
+    },
+    /// Sent when the global path is changed and to the new clients
+    GlobalPath {
+        path: Vec<Pose2d>,
+    },
+    LocalPlan {
+        velocity: Velocity,
+        cost: f64,
+        path: Vec<Pose2d>,
+    },
+    /// Snapshot of a layer like the cells of nav_msgs/OccupancyGrid: -1 is unknown,
+    /// 100 is an obstacle and the values are clamped to 99
+    ///
+    /// The layers larger than `MAX_LAYER_CELLS` are downsampled keeping the highest
+    /// cost of the cells, so `resolution` can be coarser than the layer.
+    Layer {
+        name: String,
+        width: usize,
+        height: usize,
+        resolution: f64,
+        /// Pose of the min point in the world frame
+        origin: Pose2d,
+        data: Vec<i8>,
+    },
+    /// Reply to the invalid command
+    Error {
+        message: String,
+    },
+}
+
+impl TelemetryMessage {
+    pub fn layer(name: impl Into<String>, map: &GridMap<u8>) -> Self {
+        let mut factor = 1;
+        while map.width().div_ceil(factor) * map.height().div_ceil(factor) > MAX_LAYER_CELLS {
+            factor += 1;
+        }
+        if factor > 1 {
+            return Self::layer(name, &map.downsample(factor, Aggregation::Max));
+        }
+        let min_point = map.map_to_world(map.min_point());
+        Self::Layer {
+            name: name.into(),
+            width: map.width(),
+            height: map.height(),
+            resolution: map.resolution(),
+            origin: Pose2d {
+                x: min_point.x,
+                y: min_point.y,
+                theta: map.origin().map_or(0.0, |o| o.rotation.angle()),
+            },
+            data: map
+                .cells()
+                .iter()
+                .map(|cell| match cell {
+                    Cell::Obstacle => 100,
+                    Cell::Value(v) => (*v).min(99) as i8,
+                    Cell::Unknown | Cell::Uninitialized => -1,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Command from the dashboards in JSON
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TelemetryCommand {
+    SetGoal(Pose2d),
+    Cancel,
+    /// The `None` configs are not changed
+    SetParameters {
+        #[serde(default)]
+        navigator: Option<Box<NavigatorConfig>>,
+        #[serde(default)]
+        local_planner: Option<Box<LocalPlannerConfig>>,
+    },
+}
+
+impl TelemetryCommand {
+    pub fn apply(self, navigator: &mut Navigator) {
+        match self {
+            TelemetryCommand::SetGoal(goal) => navigator.set_goal(Pose::from(&goal)),
+            TelemetryCommand::Cancel => navigator.cancel(),
+            TelemetryCommand::SetParameters {
+                navigator: navigator_config,
+                local_planner,
+            } => {
+                if let Some(config) = navigator_config {
+                    navigator.set_config(*config);
+                }
+                if let Some(config) = local_planner {
+                    navigator.replace_local_planner(config.to_planner());
+                }
+            }
+        }
+    }
+}
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+/// Larger frames from the clients close the connection
+const MAX_FRAME_LEN: usize = 1 << 20;
+/// Larger requests of the handshake close the connection
+const MAX_REQUEST_LEN: usize = 8192;
+/// The connections not completing the handshake in this time are closed
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+/// The clients with more bytes not sent yet are too slow and disconnected
+const MAX_BACKLOG_LEN: usize = 1 << 24;
+/// Larger layers are downsampled, so that the snapshots of all layers fit in the
+/// backlog
+const MAX_LAYER_CELLS: usize = 1 << 18;
+
+/// Connection during the opening handshake
+#[derive(Debug)]
+struct Pending {
+    stream: TcpStream,
+    addr: SocketAddr,
+    request: Vec<u8>,
+    accepted_at: Instant,
+}
+
+#[derive(Debug)]
+struct Client {
+    stream: TcpStream,
+    addr: SocketAddr,
+    buffer: Vec<u8>,
+    /// Bytes not accepted by the socket yet
+    backlog: Vec<u8>,
+    /// The global path has not been sent yet
+    is_new: bool,
+}
+
+/// WebSocket server streaming the telemetry of the [`Navigator`] to the browser
+/// dashboards, for the headless robots without the native viewer
+///
+/// The server is polled in the control loop by [`TelemetryServer::spin_once`],
+/// which sends the pose, the status and the local plan in every call, the global
+/// path when it is changed, and the snapshots of the layers in every
+/// `layer_interval`. The [`TelemetryCommand`]s from the clients are applied to the
+/// navigator. Only the unfragmented text frames are supported.
+///
+/// The sockets are non-blocking so that the slow clients don't stall the control
+/// loop: the handshakes are completed across the cycles, and the bytes the socket
+/// doesn't accept are kept and written in the next cycles. The clients falling too
+/// far behind are disconnected.
+#[derive(Debug)]
+pub struct TelemetryServer {
+    listener: TcpListener,
+    pending: Vec<Pending>,
+    clients: Vec<Client>,
+    layer_interval: Duration,
+    last_layers: Option<Instant>,
+    published_path: Vec<Vec<f64>>,
+}
+
+impl TelemetryServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            pending: vec![],
+            clients: vec![],
+            layer_interval: Duration::from_secs(1),
+            last_layers: None,
+            published_path: vec![],
+        })
+    }
+
+    /// Interval of the layer snapshots, which are much larger than the other messages
+    pub fn with_layer_interval(mut self, layer_interval: Duration) -> Self {
+        self.layer_interval = layer_interval;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn num_clients(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Accept the new connections and continue the handshakes. The failed
+    /// handshakes are ignored.
+    pub fn accept(&mut self) -> Result<()> {
+        let now = Instant::now();
+        loop {
+            match self.listener.accept() {
+                Ok((stream, addr)) => {
+                    if stream.set_nonblocking(true).is_ok() {
+                        self.pending.push(Pending {
+                            stream,
+                            addr,
+                            request: vec![],
+                            accepted_at: now,
+                        });
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        for mut pending in std::mem::take(&mut self.pending) {
+            match pending.handshake() {
+                Ok(Some((response, buffer))) => {
+                    let mut client = Client {
+                        stream: pending.stream,
+                        addr: pending.addr,
+                        buffer,
+                        backlog: response,
+                        is_new: true,
+                    };
+                    if client.flush().is_ok() {
+                        self.clients.push(client);
+                    }
+                }
+                Ok(None) if now.duration_since(pending.accepted_at) < HANDSHAKE_TIMEOUT => {
+                    self.pending.push(pending);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Receive the commands from all clients
+    ///
+    /// The invalid commands are replied with [`TelemetryMessage::Error`], and the
+    /// closed clients are removed.
+    pub fn receive_commands(&mut self) -> Vec<TelemetryCommand> {
+        let mut commands = vec![];
+        self.clients.retain_mut(|client| {
+            let Ok(frames) = client.receive() else {
+                return false;
+            };
+            for (opcode, payload) in frames {
+                let result = match opcode {
+                    OPCODE_TEXT => match serde_json::from_slice(&payload) {
+                        Ok(command) => {
+                            commands.push(command);
+                            Ok(())
+                        }
+                        Err(e) => client.send(&TelemetryMessage::Error {
+                            message: format!("invalid command: {e}"),
+                        }),
+                    },
+                    OPCODE_PING => client.send_frame(OPCODE_PONG, &payload),
+                    OPCODE_CLOSE => return false,
+                    _ => Ok(()),
+                };
+                if result.is_err() {
+                    return false;
+                }
+            }
+            true
+        });
+        commands
+    }
+
+    /// Send the message to all clients. The disconnected clients are removed.
+    pub fn broadcast(&mut self, message: &TelemetryMessage) {
+        let Ok(text) = serde_json::to_vec(message) else {
+            return;
+        };
+        self.clients
+            .retain_mut(|client| client.send_frame(OPCODE_TEXT, &text).is_ok());
+    }
+
+    /// Run a cycle of the server with the navigator
+    pub fn spin_once(
+        &mut self,
+        navigator: &mut Navigator,
+        pose: &Pose,
+        now: Instant,
+    ) -> Result<()> {
+        self.accept()?;
+        for command in self.receive_commands() {
+            command.apply(navigator);
+        }
+        if self.clients.is_empty() {
+            return Ok(());
+        }
+
+        self.broadcast(&TelemetryMessage::Pose(pose.into()));
+        self.broadcast(&TelemetryMessage::Status {
+            state: navigator.state(),
+            goal: navigator.target().map(Pose2d::from),
+            last_error: navigator.last_error().map(str::to_owned),
+        });
+        let path = navigator.global_path();
+        let path_changed = path != self.published_path.as_slice();
+        if path_changed || self.clients.iter().any(|c| c.is_new) {
+            self.published_path = path.to_vec();
+            self.broadcast(&TelemetryMessage::GlobalPath {
+                path: path
+                    .iter()
+                    .map(|p| Pose2d {
+                        x: p[0],
+                        y: p[1],
+                        theta: p[2],
+                    })
+                    .collect(),
+            });
+            for client in &mut self.clients {
+                client.is_new = false;
+            }
+        }
+        if let Some(plan) = navigator.last_plan() {
+            self.broadcast(&TelemetryMessage::LocalPlan {
+                velocity: plan.velocity,
+                cost: plan.cost,
+                path: plan.path.iter().map(Pose2d::from).collect(),
+            });
+        }
+        if self
+            .last_layers
+            .is_none_or(|last| now.duration_since(last) >= self.layer_interval)
+        {
+            self.last_layers = Some(now);
+            self.broadcast(&TelemetryMessage::layer("map", navigator.map()));
+            let layers = navigator.layers();
+            for id in layers.layer_ids() {
+                self.broadcast(&TelemetryMessage::layer(
+                    id.name(),
+                    layers.layer(id).unwrap(),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Pending {
+    /// Read the available bytes of the request of RFC 6455
+    ///
+    /// Returns the response and the bytes received after the request, which are the
+    /// first frames, or `None` if the request is not complete.
+    fn handshake(&mut self) -> Result<Option<(Vec<u8>, Vec<u8>)>> {
+        read_available(&mut self.stream, &mut self.request, self.addr)?;
+        let Some(end) = self.request.windows(4).position(|w| w == b"\r\n\r\n") else {
+            if self.request.len() > MAX_REQUEST_LEN {
+                return Err(Error::Other("invalid handshake".to_owned()));
+            }
+            return Ok(None);
+        };
+        let rest = self.request.split_off(end + 4);
+        let request = String::from_utf8_lossy(&self.request);
+        let key = request
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("sec-websocket-key"))
+            .map(|(_, value)| value.trim())
+            .ok_or_else(|| Error::Other("no Sec-WebSocket-Key".to_owned()))?;
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        );
+        Ok(Some((response.into_bytes(), rest)))
+    }
+}
+
+impl Client {
+    fn send(&mut self, message: &TelemetryMessage) -> std::io::Result<()> {
+        let text = serde_json::to_vec(message)?;
+        self.send_frame(OPCODE_TEXT, &text)
+    }
+
+    /// Queue the unmasked final frame and write as much of the backlog as the socket
+    /// accepts
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> std::io::Result<()> {
+        if self.backlog.len() + payload.len() > MAX_BACKLOG_LEN {
+            return Err(std::io::Error::other(format!("{} is too slow", self.addr)));
+        }
+        encode_frame(&mut self.backlog, opcode, payload);
+        self.flush()
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        let mut written = 0;
+        let result = loop {
+            if written == self.backlog.len() {
+                break Ok(());
+            }
+            match self.stream.write(&self.backlog[written..]) {
+                Ok(0) => break Err(ErrorKind::WriteZero.into()),
+                Ok(n) => written += n,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+        self.backlog.drain(..written);
+        result
+    }
+
+    /// Read the available bytes and return the complete frames
+    fn receive(&mut self) -> Result<Vec<(u8, Vec<u8>)>> {
+        self.flush()?;
+        read_available(&mut self.stream, &mut self.buffer, self.addr)?;
+
+        let mut frames = vec![];
+        while let Some((opcode, payload, len)) = parse_frame(&self.buffer)? {
+            frames.push((opcode, payload));
+            self.buffer.drain(..len);
+        }
+        Ok(frames)
+    }
+}
+
+/// Read the bytes until the non-blocking socket would block
+fn read_available(stream: &mut TcpStream, buffer: &mut Vec<u8>, addr: SocketAddr) -> Result<()> {
+    let mut buf = [0; 4096];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => return Err(Error::Other(format!("{addr} is disconnected"))),
+            Ok(n) => buffer.extend_from_slice(&buf[..n]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Parse a frame from the client, which is masked
+///
+/// Returns the opcode, the payload and the length of the frame, or `None` if the
+/// frame is not complete.
+fn parse_frame(buffer: &[u8]) -> Result<Option<(u8, Vec<u8>, usize)>> {
+    if buffer.len() < 2 {
+        return Ok(None);
+    }
+    let opcode = buffer[0] & 0x0f;
+    if buffer[1] & 0x80 == 0 {
+        return Err(Error::Other("unmasked frame from the client".to_owned()));
+    }
+    let (payload_len, mut offset) = match buffer[1] & 0x7f {
+        126 if buffer.len() >= 4 => (u16::from_be_bytes([buffer[2], buffer[3]]) as usize, 4),
+        127 if buffer.len() >= 10 => (
+            u64::from_be_bytes(buffer[2..10].try_into().unwrap()) as usize,
+            10,
+        ),
+        126 | 127 => return Ok(None),
+        len => (len as usize, 2),
+    };
+    if payload_len > MAX_FRAME_LEN {
+        return Err(Error::Other("too large frame".to_owned()));
+    }
+    if buffer.len() < offset + 4 + payload_len {
+        return Ok(None);
+    }
+    let mask = &buffer[offset..offset + 4];
+    offset += 4;
+    let payload = buffer[offset..offset + payload_len]
+        .iter()
+        .enumerate()
+        .map(|(i, b)| b ^ mask[i % 4])
+        .collect();
+    Ok(Some((opcode, payload, offset + payload_len)))
+}
+
+fn encode_frame(buffer: &mut Vec<u8>, opcode: u8, payload: &[u8]) {
+    buffer.push(0x80 | opcode);
+    match payload.len() {
+        len if len < 126 => buffer.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            buffer.push(126);
+            buffer.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            buffer.push(127);
+            buffer.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    buffer.extend_from_slice(payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit::{free_map, navigator};
+    use grid_map::{Grid, Position};
+
+    fn read_message(stream: &mut TcpStream) -> TelemetryMessage {
+        let mut header = [0; 2];
+        stream.read_exact(&mut header).unwrap();
+        let len = match header[1] {
+            126 => {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            127 => {
+                let mut len = [0; 8];
+                stream.read_exact(&mut len).unwrap();
+                u64::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    #[test]
+    fn test_telemetry_server() {
+        // example of RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+
+        let mut navigator = navigator(free_map());
+        let mut server = TelemetryServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        write!(
+            client,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let command = br#"{"type":"set_goal","x":0.8,"y":0.5,"theta":0.0}"#;
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | command.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(command.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        client.write_all(&frame).unwrap();
+
+        let pose = Pose::new(na::Vector2::new(0.2, 0.5), 0.0);
+        let start = Instant::now();
+        while navigator.goal().is_none() && start.elapsed() < Duration::from_secs(5) {
+            server
+                .spin_once(&mut navigator, &pose, Instant::now())
+                .unwrap();
+        }
+        assert_eq!(server.num_clients(), 1);
+        assert_eq!(
+            navigator.target().map(Pose2d::from),
+            Some(Pose2d {
+                x: 0.8,
+                y: 0.5,
+                theta: 0.0
+            })
+        );
+
+        let mut response = vec![];
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            client.read_exact(&mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        assert!(String::from_utf8_lossy(&response).contains("s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        assert_eq!(
+            read_message(&mut client),
+            TelemetryMessage::Pose((&pose).into())
+        );
+        assert!(matches!(
+            read_message(&mut client),
+            TelemetryMessage::Status { .. }
+        ));
+        assert!(matches!(
+            read_message(&mut client),
+            TelemetryMessage::GlobalPath { .. }
+        ));
+        assert!(matches!(
+            read_message(&mut client),
+            TelemetryMessage::Layer { name, .. } if name == "map"
+        ));
+    }
+
+    #[test]
+    fn test_telemetry_large_layer() {
+        let mut map =
+            GridMap::<u8>::new(Position::new(0.0, 0.0), Position::new(150.0, 150.0), 0.05);
+        map.fill(Cell::Value(0));
+        map.set_obstacle(&Grid::new(1234, 2345)).unwrap();
+        let TelemetryMessage::Layer {
+            width,
+            height,
+            resolution,
+            data,
+            ..
+        } = TelemetryMessage::layer("map", &map)
+        else {
+            unreachable!()
+        };
+        assert!(width * height <= MAX_LAYER_CELLS);
+        assert_eq!(data.len(), width * height);
+        assert_eq!(width, map.width().div_ceil(6));
+        assert!((resolution - 0.3).abs() < 1e-9);
+        assert_eq!(data.iter().filter(|v| **v == 100).count(), 1);
+
+        // the client of the large map is not disconnected
+        let mut navigator = navigator(free_map());
+        navigator.set_map(map).unwrap();
+        let mut server = TelemetryServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client
+            .write_all(
+                b"GET / HTTP/1.1\r\nUpgrade: websocket\r\n\
+                  Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+            )
+            .unwrap();
+        let pose = Pose::new(na::Vector2::new(0.2, 0.5), 0.0);
+        let start = Instant::now();
+        while server.num_clients() == 0 && start.elapsed() < Duration::from_secs(5) {
+            server
+                .spin_once(&mut navigator, &pose, Instant::now())
+                .unwrap();
+        }
+        assert_eq!(server.num_clients(), 1);
+        server
+            .spin_once(&mut navigator, &pose, Instant::now())
+            .unwrap();
+        assert_eq!(server.num_clients(), 1);
+    }
+
+    #[test]
+    fn test_telemetry_server_incomplete_handshake() {
+        let mut navigator = navigator(free_map());
+        let mut server = TelemetryServer::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n")
+            .unwrap();
+
+        let pose = Pose::new(na::Vector2::new(0.2, 0.5), 0.0);
+        let start = Instant::now();
+        for _ in 0..10 {
+            server
+                .spin_once(&mut navigator, &pose, Instant::now())
+                .unwrap();
+        }
+        // the handshake waits for the rest of the request without blocking the cycles
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert_eq!(server.num_clients(), 0);
+
+        client
+            .write_all(b"Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n")
+            .unwrap();
+        let start = Instant::now();
+        while server.num_clients() == 0 && start.elapsed() < Duration::from_secs(5) {
+            server
+                .spin_once(&mut navigator, &pose, Instant::now())
+                .unwrap();
+        }
+        assert_eq!(server.num_clients(), 1);
+    }
+}